@@ -1,5 +1,8 @@
 mod notify;
+mod send;
+
 use clap::{Parser, Subcommand};
+use std::{path::PathBuf, process::ExitCode};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -23,16 +26,102 @@ enum NotifyCommand {
         )]
         all: bool,
 
-        #[arg(short, long, help = "Dismiss a specific notification by index")]
+        #[arg(
+            short,
+            long,
+            help = "Dismiss a specific notification by the id list prints"
+        )]
         notification: Option<u32>,
+
+        #[arg(
+            long,
+            help = "Dismiss all notifications from an application",
+            conflicts_with_all = ["all", "notification"]
+        )]
+        app: Option<String>,
     },
 
-    #[command(about = "List active notifications")]
-    List,
+    #[command(
+        about = "List active notifications, or the history entries on screen by their place in the history"
+    )]
+    List {
+        #[arg(
+            long,
+            help = "Print notifications as a JSON array",
+            conflicts_with = "format"
+        )]
+        json: bool,
+
+        #[arg(
+            long,
+            help = "Print each notification using a template such as '{id} {summary}'",
+            default_value = notify::DEFAULT_FORMAT
+        )]
+        format: String,
+
+        #[arg(long, help = "Only list notifications from an application")]
+        app: Option<String>,
+
+        #[arg(
+            long,
+            help = "Hide the content of apps whose notifications the history stores without it"
+        )]
+        redact: bool,
+
+        #[arg(
+            long,
+            help = "Only list notifications of an urgency",
+            value_parser = ["low", "normal", "critical"]
+        )]
+        urgency: Option<String>,
+    },
+
+    #[command(about = "Invoke an action on a notification")]
+    Action {
+        #[arg(help = "Id of the notification")]
+        id: u32,
+
+        #[arg(help = "Key of the action to invoke")]
+        key: String,
+    },
+
+    #[command(about = "Hide a notification for a while and show it again afterwards")]
+    Snooze {
+        #[arg(help = "Id of the notification")]
+        id: u32,
+
+        #[arg(
+            long,
+            help = "Minutes to snooze the notification for",
+            default_value_t = 10
+        )]
+        minutes: u32,
+    },
+
+    #[command(about = "Keep a notification from expiring, or let it expire again")]
+    Pin {
+        #[arg(help = "Id of the notification")]
+        id: u32,
+    },
+
+    #[command(about = "Open the first link in the body of a notification")]
+    Open {
+        #[arg(help = "Id of the notification")]
+        id: u32,
+    },
 
     #[command(about = "List active notifications")]
     Waiting,
 
+    #[command(about = "Print the number of active notifications")]
+    Count,
+
+    #[command(about = "Print mute, inhibit and history state along with notification counts")]
+    Status {
+        #[arg(long, help = "Print the status as a JSON object")]
+        json: bool,
+    },
+
     #[command(about = "Mute notifications")]
     Mute {
         #[command(subcommand)]
@@ -42,7 +131,7 @@ enum NotifyCommand {
     #[command(about = "Manage notification history visibility")]
     History {
         #[command(subcommand)]
-        action: SwitchAction,
+        action: HistoryAction,
     },
 
     #[command(about = "Inhibit notifications")]
@@ -50,6 +139,87 @@ enum NotifyCommand {
         #[command(subcommand)]
         action: SwitchAction,
     },
+
+    #[command(about = "Move notifications to another output")]
+    Output {
+        #[command(subcommand)]
+        action: OutputAction,
+    },
+
+    #[command(about = "Move notifications to another corner or edge of the output")]
+    Anchor {
+        #[command(subcommand)]
+        action: AnchorAction,
+    },
+
+    #[command(about = "Show only the summary of notifications")]
+    Compact {
+        #[command(subcommand)]
+        action: SwitchAction,
+    },
+
+    #[command(about = "Send a notification")]
+    Send(send::Options),
+
+    #[command(about = "Print events of the daemon as JSON, one per line, until interrupted")]
+    Subscribe {
+        #[arg(
+            long,
+            help = "Only print these events, all of them when not given",
+            value_delimiter = ',',
+            value_parser = notify::EVENTS
+        )]
+        events: Vec<String>,
+    },
+
+    #[command(about = "Close all notifications and stop the daemon")]
+    Quit,
+
+    #[command(about = "Tools for debugging the daemon")]
+    Debug {
+        #[command(subcommand)]
+        action: DebugAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum DebugAction {
+    #[command(about = "Save what's shown on screen as a PNG")]
+    Screenshot {
+        #[arg(help = "File to write")]
+        path: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum OutputAction {
+    #[command(about = "Show notifications on an output until the daemon restarts")]
+    Set {
+        #[arg(help = "Name of the output such as DP-3, or all for every output")]
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum AnchorAction {
+    #[command(about = "Anchor notifications somewhere until the daemon restarts")]
+    Set {
+        #[arg(
+            help = "Where to anchor notifications",
+            value_parser = [
+                "top-right",
+                "top-center",
+                "top-left",
+                "bottom-right",
+                "bottom-center",
+                "bottom-left",
+                "center-right",
+                "center-left",
+                "center",
+            ]
+        )]
+        anchor: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -60,16 +230,73 @@ enum SwitchAction {
     State,
 }
 
+#[derive(Subcommand)]
+enum HistoryAction {
+    On,
+    Off,
+    Toggle,
+    State,
+    #[command(about = "Show only history entries whose summary or body contain a query")]
+    Search {
+        #[arg(help = "Text to look for")]
+        query: String,
+    },
+}
+
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> anyhow::Result<ExitCode> {
     let cli = Cli::parse();
 
     let event = match cli.command {
+        NotifyCommand::Send(options) => return send::send(options).await,
         NotifyCommand::Waiting => notify::Event::Waiting,
+        NotifyCommand::Count => notify::Event::Count,
+        NotifyCommand::Status { json } => notify::Event::Status { json },
+        NotifyCommand::Subscribe { events } => notify::Event::Subscribe(events),
         NotifyCommand::Focus => notify::Event::Focus,
-        NotifyCommand::List => notify::Event::List,
-        NotifyCommand::Dismiss { all, notification } => {
-            if all {
+        NotifyCommand::Quit => notify::Event::Quit,
+        NotifyCommand::Debug { action } => match action {
+            DebugAction::Screenshot { path } => {
+                notify::Event::Screenshot(std::path::absolute(path)?)
+            }
+        },
+        NotifyCommand::List {
+            json,
+            format,
+            app,
+            redact,
+            urgency,
+        } => notify::Event::List {
+            json,
+            format,
+            app,
+            redact,
+            urgency,
+        },
+        NotifyCommand::Action { id, key } => notify::Event::InvokeAction { id, key },
+        NotifyCommand::Snooze { id, minutes } => notify::Event::Snooze { id, minutes },
+        NotifyCommand::Pin { id } => notify::Event::Pin(id),
+        NotifyCommand::Output {
+            action: OutputAction::Set { name },
+        } => notify::Event::SetOutput(name),
+        NotifyCommand::Anchor {
+            action: AnchorAction::Set { anchor },
+        } => notify::Event::SetAnchor(anchor),
+        NotifyCommand::Compact { action } => match action {
+            SwitchAction::On => notify::Event::SetCompact(true),
+            SwitchAction::Off => notify::Event::SetCompact(false),
+            SwitchAction::Toggle => notify::Event::ToggleCompact,
+            SwitchAction::State => notify::Event::CompactState,
+        },
+        NotifyCommand::Open { id } => notify::Event::OpenLink(id),
+        NotifyCommand::Dismiss {
+            all,
+            notification,
+            app,
+        } => {
+            if let Some(app) = app {
+                notify::Event::DismissApp(app)
+            } else if all {
                 notify::Event::DismissAll
             } else {
                 let idx = notification.unwrap_or_default();
@@ -83,10 +310,11 @@ async fn main() -> anyhow::Result<()> {
             SwitchAction::State => notify::Event::MuteState,
         },
         NotifyCommand::History { action } => match action {
-            SwitchAction::On => notify::Event::ShowHistory,
-            SwitchAction::Off => notify::Event::HideHistory,
-            SwitchAction::Toggle => notify::Event::ToggleHistory,
-            SwitchAction::State => notify::Event::HistoryState,
+            HistoryAction::On => notify::Event::ShowHistory,
+            HistoryAction::Off => notify::Event::HideHistory,
+            HistoryAction::Toggle => notify::Event::ToggleHistory,
+            HistoryAction::State => notify::Event::HistoryState,
+            HistoryAction::Search { query } => notify::Event::SearchHistory(query),
         },
         NotifyCommand::Inhibit { action } => match action {
             SwitchAction::On => notify::Event::Inhibit,
@@ -96,5 +324,7 @@ async fn main() -> anyhow::Result<()> {
         },
     };
 
-    notify::emit(event).await.map_err(Into::into)
+    notify::emit(event).await?;
+
+    Ok(ExitCode::SUCCESS)
 }
@@ -1,16 +1,56 @@
-use serde::Deserialize;
-use std::io::{self, Write};
-use zbus::zvariant::Type;
+use futures_lite::StreamExt;
+use moxnotify_ipc::{EmitEvent, History, InhibitState, MoxnotifyClient, Notification, Status};
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+/// Template `list` prints each notification with unless told otherwise.
+pub const DEFAULT_FORMAT: &str = "{id}\t{app_name}\t{summary}";
+
+/// Names of the events `subscribe` prints, as given to `--events`.
+pub const EVENTS: [&str; 7] = [
+    "received", "closed", "action", "mute", "inhibit", "history", "count",
+];
 
 pub enum Event {
     Waiting,
+    Count,
+    Status {
+        json: bool,
+    },
     Focus,
-    List,
+    Quit,
+    Screenshot(PathBuf),
+    List {
+        json: bool,
+        format: String,
+        app: Option<String>,
+        redact: bool,
+        urgency: Option<String>,
+    },
     DismissAll,
     DismissOne(u32),
+    DismissApp(String),
+    InvokeAction {
+        id: u32,
+        key: String,
+    },
+    Snooze {
+        id: u32,
+        minutes: u32,
+    },
+    Pin(u32),
+    OpenLink(u32),
+    SetOutput(String),
+    SetAnchor(String),
+    SetCompact(bool),
+    ToggleCompact,
+    CompactState,
     Mute,
     Unmute,
     ShowHistory,
+    SearchHistory(String),
     HideHistory,
     HistoryState,
     Inhibit,
@@ -20,85 +60,181 @@ pub enum Event {
     ToggleInhibit,
     ToggleMute,
     MuteState,
+    /// Prints the events named, all of them if none are.
+    Subscribe(Vec<String>),
 }
 
-#[derive(Default, PartialEq, Clone, Copy, Type, Deserialize)]
-pub enum History {
-    #[default]
-    Hidden,
-    Shown,
-}
+/// What keeps notifications inhibited, `None` if it's only a manual inhibit or nothing at all.
+fn inhibit_reasons(state: InhibitState) -> Option<String> {
+    let reasons: Vec<&str> = [
+        ("manual", state.manual),
+        ("screencast", state.screencast),
+        ("schedule", state.schedule),
+    ]
+    .into_iter()
+    .filter_map(|(reason, active)| active.then_some(reason))
+    .collect();
 
-#[zbus::proxy(
-    interface = "org.freedesktop.Notifications",
-    default_service = "org.freedesktop.Notifications",
-    default_path = "/org/freedesktop/Notifications"
-)]
-trait Notifications {
-    #[allow(clippy::type_complexity)]
-    async fn get_server_information(
-        &self,
-    ) -> zbus::fdo::Result<(Box<str>, Box<str>, Box<str>, Box<str>)>;
+    match reasons.as_slice() {
+        [] | ["manual"] => None,
+        reasons => Some(reasons.join(", ")),
+    }
 }
 
-#[zbus::proxy(
-    interface = "pl.mox.Notify",
-    default_service = "pl.mox.Notify",
-    default_path = "/pl/mox/Notify"
-)]
-trait Notify {
-    async fn focus(&self) -> zbus::Result<()>;
-
-    async fn list(&self) -> zbus::Result<Vec<String>>;
-
-    async fn dismiss(&self, all: bool, id: u32) -> zbus::Result<()>;
-
-    async fn mute(&self) -> zbus::Result<()>;
-
-    async fn unmute(&self) -> zbus::Result<()>;
-
-    async fn muted(&self) -> zbus::Result<bool>;
-
-    async fn show_history(&self) -> zbus::Result<()>;
-
-    async fn hide_history(&self) -> zbus::Result<()>;
-
-    async fn history(&self) -> zbus::Result<History>;
-
-    async fn inhibit(&self) -> zbus::Result<()>;
-
-    async fn uninhibit(&self) -> zbus::Result<()>;
-
-    async fn inhibited(&self) -> zbus::Result<bool>;
-
-    async fn waiting(&self) -> zbus::Result<u32>;
+fn status_json(status: &Status) -> serde_json::Value {
+    serde_json::json!({
+        "muted": status.muted,
+        "inhibited": status.inhibited.inhibited(),
+        "inhibit": status.inhibited,
+        "history": status.history.as_str(),
+        "waiting": status.waiting,
+        "count": status.active_count,
+        "renderer": status.renderer,
+    })
 }
 
-pub async fn emit(event: Event) -> zbus::Result<()> {
-    let conn = zbus::Connection::session().await?;
+fn event_name(event: &EmitEvent) -> &'static str {
+    match event {
+        EmitEvent::NotificationReceived(_) => "received",
+        EmitEvent::NotificationClosed { .. } => "closed",
+        EmitEvent::ActionInvoked { .. } => "action",
+        EmitEvent::MuteStateChanged(_) => "mute",
+        EmitEvent::InhibitChanged(_) => "inhibit",
+        EmitEvent::HistoryStateChanged(_) => "history",
+        EmitEvent::NotificationCountChanged(_) => "count",
+    }
+}
 
-    let notifications = NotificationsProxy::new(&conn).await?;
-    let server_information = notifications.get_server_information().await?;
-    if *server_information.0 != *"moxnotify" && *server_information.1 != *"mox" {
-        panic!("Unkown notification server");
+fn event_json(event: &EmitEvent) -> serde_json::Value {
+    let name = event_name(event);
+    match event {
+        EmitEvent::NotificationReceived(notification) => {
+            serde_json::json!({ "event": name, "notification": notification })
+        }
+        EmitEvent::NotificationClosed { id, reason } => {
+            let reason = match reason {
+                1 => "expired",
+                2 => "dismissed",
+                3 => "closed",
+                _ => "undefined",
+            };
+            serde_json::json!({ "event": name, "id": id, "reason": reason })
+        }
+        EmitEvent::ActionInvoked { id, key } => {
+            serde_json::json!({ "event": name, "id": id, "key": key })
+        }
+        EmitEvent::MuteStateChanged(muted) => serde_json::json!({ "event": name, "muted": muted }),
+        EmitEvent::InhibitChanged(inhibited) => {
+            serde_json::json!({ "event": name, "inhibited": inhibited })
+        }
+        EmitEvent::HistoryStateChanged(history) => {
+            serde_json::json!({ "event": name, "history": history.as_str() })
+        }
+        EmitEvent::NotificationCountChanged(count) => {
+            serde_json::json!({ "event": name, "count": count })
+        }
     }
+}
 
-    let notify = NotifyProxy::new(&conn).await?;
+pub async fn emit(event: Event) -> anyhow::Result<()> {
+    let notify = MoxnotifyClient::connect().await?;
     let mut out = io::stdout().lock();
 
     match event {
         Event::Focus => notify.focus().await?,
+        Event::Quit => notify.quit().await?,
+        Event::Screenshot(path) => {
+            let path = path
+                .to_str()
+                .ok_or_else(|| anyhow::anyhow!("{} isn't valid UTF-8", path.display()))?;
+            notify.screenshot(path).await?
+        }
         Event::Waiting => {
             writeln!(out, "{}", notify.waiting().await?)?;
         }
-        Event::List => {
-            let list = notify.list().await?;
-            for item in list {
-                writeln!(out, "{item}")?;
+        Event::Count => {
+            writeln!(out, "{}", notify.count().await?)?;
+        }
+        Event::Status { json } => {
+            let status = notify.status().await?;
+            if json {
+                writeln!(
+                    out,
+                    "{}",
+                    serde_json::to_string_pretty(&status_json(&status))?
+                )?;
+            } else {
+                let inhibited = match inhibit_reasons(status.inhibited) {
+                    _ if !status.inhibited.inhibited() => "no".to_string(),
+                    Some(reasons) => format!("yes ({reasons})"),
+                    None => "yes".to_string(),
+                };
+                writeln!(out, "muted: {}", if status.muted { "yes" } else { "no" })?;
+                writeln!(out, "inhibited: {inhibited}")?;
+                writeln!(out, "history: {}", status.history.as_str())?;
+                writeln!(out, "waiting: {}", status.waiting)?;
+                writeln!(out, "notifications: {}", status.active_count)?;
+                writeln!(out, "renderer: {}", status.renderer)?;
+            }
+        }
+        Event::List {
+            json,
+            format,
+            app,
+            redact,
+            urgency,
+        } => {
+            let list = match redact {
+                true => notify.list_redacted().await?,
+                false => notify.list().await?,
+            };
+            let list: Vec<Notification> = list
+                .into_iter()
+                .filter(|notification| app.as_ref().is_none_or(|app| notification.app_name == *app))
+                .filter(|notification| {
+                    urgency
+                        .as_ref()
+                        .is_none_or(|urgency| notification.urgency == *urgency)
+                })
+                .collect();
+
+            if json {
+                writeln!(out, "{}", serde_json::to_string_pretty(&list)?)?;
+            } else {
+                // Kept off stdout so that scripts reading the list aren't thrown off by it
+                if notify.history().await? == History::Shown {
+                    writeln!(
+                        io::stderr(),
+                        "History entries are listed by their place in the history, \
+                         `dismiss -n` takes that id rather than the one they were sent with"
+                    )?;
+                }
+                for notification in list {
+                    writeln!(out, "{}", notification.format(&format))?;
+                }
             }
         }
-        Event::DismissAll => notify.dismiss(true, 0).await?,
-        Event::DismissOne(index) => notify.dismiss(false, index).await?,
+        Event::DismissAll => notify.dismiss_all().await?,
+        Event::DismissOne(index) => notify.dismiss(index).await?,
+        Event::DismissApp(app) => {
+            writeln!(out, "{}", notify.dismiss_app(&app).await?)?;
+        }
+        Event::InvokeAction { id, key } => notify.invoke_action(id, &key).await?,
+        Event::Snooze { id, minutes } => notify.snooze(id, minutes).await?,
+        Event::Pin(id) => notify.pin(id).await?,
+        Event::OpenLink(id) => {
+            if !notify.open_link(id).await? {
+                anyhow::bail!("Notification {id} has no link to open");
+            }
+        }
+        Event::SetOutput(name) => notify.set_output(&name).await?,
+        Event::SetAnchor(anchor) => notify.set_anchor(&anchor).await?,
+        Event::SetCompact(compact) => notify.set_compact(compact).await?,
+        Event::ToggleCompact => notify.set_compact(!notify.compact().await?).await?,
+        Event::CompactState => match notify.compact().await? {
+            true => writeln!(out, "compact")?,
+            false => writeln!(out, "full")?,
+        },
         Event::Unmute => notify.unmute().await?,
         Event::Mute => notify.mute().await?,
         Event::ToggleMute => {
@@ -113,6 +249,7 @@ pub async fn emit(event: Event) -> zbus::Result<()> {
             false => writeln!(out, "unmuted")?,
         },
         Event::ShowHistory => notify.show_history().await?,
+        Event::SearchHistory(query) => notify.search_history(&query).await?,
         Event::HideHistory => notify.hide_history().await?,
         Event::ToggleHistory => {
             if notify.history().await? == History::Shown {
@@ -128,17 +265,102 @@ pub async fn emit(event: Event) -> zbus::Result<()> {
         Event::Inhibit => notify.inhibit().await?,
         Event::Uninhibit => notify.uninhibit().await?,
         Event::ToggleInhibit => {
-            if notify.inhibited().await? {
+            if notify.inhibit_state().await?.manual {
                 notify.uninhibit().await?
             } else {
                 notify.inhibit().await?
             }
         }
-        Event::InhibitState => match notify.inhibited().await? {
-            true => writeln!(out, "inhibited")?,
-            false => writeln!(out, "uninhibited")?,
-        },
+        Event::InhibitState => {
+            let state = notify.inhibit_state().await?;
+            match inhibit_reasons(state) {
+                _ if !state.inhibited() => writeln!(out, "uninhibited")?,
+                Some(reasons) => writeln!(out, "inhibited ({reasons})")?,
+                None => writeln!(out, "inhibited")?,
+            }
+        }
+        Event::Subscribe(events) => {
+            let mut stream = notify.subscribe_events().await?;
+            while let Some(event) = stream.next().await {
+                if !events.is_empty() && !events.iter().any(|name| name == event_name(&event)) {
+                    continue;
+                }
+
+                writeln!(out, "{}", event_json(&event))?;
+            }
+        }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{event_json, inhibit_reasons, status_json};
+    use moxnotify_ipc::{EmitEvent, History, InhibitState, Status};
+
+    #[test]
+    fn test_status_json() {
+        let status = Status {
+            muted: false,
+            inhibited: InhibitState {
+                manual: false,
+                screencast: true,
+                schedule: false,
+            },
+            history: History::Shown,
+            waiting: 2,
+            active_count: 0,
+            renderer: "llvmpipe (LLVM 19.1.7, 256 bits) (gl)".into(),
+        };
+
+        assert_eq!(
+            status_json(&status),
+            serde_json::json!({
+                "muted": false,
+                "inhibited": true,
+                "inhibit": { "manual": false, "screencast": true, "schedule": false },
+                "history": "shown",
+                "waiting": 2,
+                "count": 0,
+                "renderer": "llvmpipe (LLVM 19.1.7, 256 bits) (gl)",
+            })
+        );
+    }
+
+    #[test]
+    fn test_inhibit_reasons() {
+        let manual = InhibitState {
+            manual: true,
+            ..Default::default()
+        };
+        assert_eq!(inhibit_reasons(InhibitState::default()), None);
+        assert_eq!(inhibit_reasons(manual), None);
+        assert_eq!(
+            inhibit_reasons(InhibitState {
+                schedule: true,
+                ..manual
+            }),
+            Some("manual, schedule".into())
+        );
+    }
+
+    #[test]
+    fn test_event_json() {
+        assert_eq!(
+            event_json(&EmitEvent::NotificationClosed { id: 4, reason: 2 }),
+            serde_json::json!({ "event": "closed", "id": 4, "reason": "dismissed" })
+        );
+        assert_eq!(
+            event_json(&EmitEvent::ActionInvoked {
+                id: 4,
+                key: "default".into(),
+            }),
+            serde_json::json!({ "event": "action", "id": 4, "key": "default" })
+        );
+        assert_eq!(
+            event_json(&EmitEvent::HistoryStateChanged(History::Shown)),
+            serde_json::json!({ "event": "history", "history": "shown" })
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use futures_lite::StreamExt;
+use moxnotify_ipc::NotificationsProxy;
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    process::ExitCode,
+};
+use zbus::zvariant::Value;
+
+#[derive(clap::Args)]
+pub struct Options {
+    #[arg(long, help = "Summary of the notification")]
+    summary: String,
+
+    #[arg(long, help = "Body of the notification", default_value = "")]
+    body: String,
+
+    #[arg(
+        long,
+        help = "Urgency of the notification",
+        value_parser = ["low", "normal", "critical"]
+    )]
+    urgency: Option<String>,
+
+    #[arg(
+        long,
+        help = "Milliseconds before the notification expires, 0 never expires and -1 leaves it to the server",
+        default_value_t = -1,
+        allow_negative_numbers = true
+    )]
+    timeout: i32,
+
+    #[arg(long, help = "Name of the sending application", default_value = "mox")]
+    app_name: String,
+
+    #[arg(long, help = "Icon name or path", default_value = "")]
+    icon: String,
+
+    #[arg(
+        long = "action",
+        help = "Action to add as key=label, can be repeated",
+        value_parser = parse_action
+    )]
+    actions: Vec<(String, String)>,
+
+    #[arg(
+        long = "hint",
+        help = "Hint to add as key=type:value where type is one of byte, boolean, int32, uint32, double or string",
+        value_parser = parse_hint
+    )]
+    hints: Vec<(String, Hint)>,
+
+    #[arg(long, help = "Id of a notification to replace", default_value_t = 0)]
+    replace_id: u32,
+
+    #[arg(
+        long,
+        help = "Wait until an action is invoked or the notification is closed, the exit code is 0 for an action and the close reason otherwise"
+    )]
+    wait_for_action: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Hint {
+    Byte(u8),
+    Boolean(bool),
+    Int32(i32),
+    Uint32(u32),
+    Double(f64),
+    String(String),
+}
+
+impl From<&Hint> for Value<'_> {
+    fn from(hint: &Hint) -> Self {
+        match hint {
+            Hint::Byte(value) => Value::from(*value),
+            Hint::Boolean(value) => Value::from(*value),
+            Hint::Int32(value) => Value::from(*value),
+            Hint::Uint32(value) => Value::from(*value),
+            Hint::Double(value) => Value::from(*value),
+            Hint::String(value) => Value::from(value.clone()),
+        }
+    }
+}
+
+fn parse_action(arg: &str) -> Result<(String, String), String> {
+    arg.split_once('=')
+        .map(|(key, label)| (key.into(), label.into()))
+        .ok_or_else(|| format!("expected key=label, got '{arg}'"))
+}
+
+fn parse_hint(arg: &str) -> Result<(String, Hint), String> {
+    let (key, rest) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected key=type:value, got '{arg}'"))?;
+    let (kind, value) = rest
+        .split_once(':')
+        .ok_or_else(|| format!("expected key=type:value, got '{arg}'"))?;
+
+    let hint = match kind {
+        "byte" => value.parse().map(Hint::Byte).map_err(|e| e.to_string())?,
+        "boolean" => value
+            .parse()
+            .map(Hint::Boolean)
+            .map_err(|e| e.to_string())?,
+        "int32" => value.parse().map(Hint::Int32).map_err(|e| e.to_string())?,
+        "uint32" => value.parse().map(Hint::Uint32).map_err(|e| e.to_string())?,
+        "double" => value.parse().map(Hint::Double).map_err(|e| e.to_string())?,
+        "string" => Hint::String(value.into()),
+        _ => return Err(format!("unknown hint type '{kind}'")),
+    };
+
+    Ok((key.into(), hint))
+}
+
+/// Sends the notification to whichever server owns org.freedesktop.Notifications and prints
+/// its id.
+pub async fn send(options: Options) -> anyhow::Result<ExitCode> {
+    let conn = zbus::Connection::session().await?;
+    let notifications = NotificationsProxy::new(&conn).await?;
+
+    // Subscribed before sending so a quick click can't slip past
+    let (mut invoked, mut closed) = if options.wait_for_action {
+        (
+            Some(notifications.receive_action_invoked().await?),
+            Some(notifications.receive_notification_closed().await?),
+        )
+    } else {
+        (None, None)
+    };
+
+    let actions: Vec<&str> = options
+        .actions
+        .iter()
+        .flat_map(|(key, label)| [key.as_str(), label.as_str()])
+        .collect();
+
+    let mut hints: HashMap<&str, Value> = options
+        .hints
+        .iter()
+        .map(|(key, hint)| (key.as_str(), hint.into()))
+        .collect();
+    if let Some(urgency) = options.urgency.as_deref() {
+        let level: u8 = match urgency {
+            "low" => 0,
+            "critical" => 2,
+            _ => 1,
+        };
+        hints.insert("urgency", level.into());
+    }
+
+    let id = notifications
+        .notify(
+            &options.app_name,
+            options.replace_id,
+            &options.icon,
+            &options.summary,
+            &options.body,
+            &actions,
+            hints,
+            options.timeout,
+        )
+        .await?;
+
+    let mut out = io::stdout().lock();
+    writeln!(out, "{id}")?;
+
+    let (Some(invoked), Some(closed)) = (invoked.as_mut(), closed.as_mut()) else {
+        return Ok(ExitCode::SUCCESS);
+    };
+
+    loop {
+        tokio::select! {
+            biased;
+            Some(signal) = invoked.next() => {
+                let args = signal.args()?;
+                if *args.id() == id {
+                    writeln!(out, "{}", args.action_key())?;
+                    return Ok(ExitCode::SUCCESS);
+                }
+            }
+            Some(signal) = closed.next() => {
+                let args = signal.args()?;
+                if *args.id() == id {
+                    let reason = *args.reason();
+                    let name = match reason {
+                        1 => "expired",
+                        2 => "dismissed",
+                        3 => "closed",
+                        _ => "undefined",
+                    };
+                    writeln!(out, "{name}")?;
+                    return Ok(ExitCode::from(reason.clamp(1, 4) as u8));
+                }
+            }
+            else => anyhow::bail!("Notification server stopped sending signals"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_action, parse_hint, Hint};
+
+    #[test]
+    fn test_parse_hint() {
+        assert_eq!(
+            parse_hint("urgency=byte:2"),
+            Ok(("urgency".into(), Hint::Byte(2)))
+        );
+        assert_eq!(
+            parse_hint("x-canonical-private-synchronous=string:volume:50"),
+            Ok((
+                "x-canonical-private-synchronous".into(),
+                Hint::String("volume:50".into())
+            ))
+        );
+        assert_eq!(
+            parse_hint("transient=boolean:true"),
+            Ok(("transient".into(), Hint::Boolean(true)))
+        );
+        assert!(parse_hint("value=int32:abc").is_err());
+        assert!(parse_hint("value=float:1").is_err());
+        assert!(parse_hint("value").is_err());
+
+        assert_eq!(
+            parse_action("default=Open"),
+            Ok(("default".into(), "Open".into()))
+        );
+        assert!(parse_action("default").is_err());
+    }
+}
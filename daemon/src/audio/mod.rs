@@ -1,7 +1,18 @@
 mod playback;
 pub mod tinyaudio;
 
-use std::{collections::BTreeMap, path::Path};
+pub use playback::Playback;
+
+use crate::{components::notification::NotificationId, Event};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Sound theme every other theme inherits from, searched when the configured one lacks a sound.
+const FALLBACK_THEME: &str = "freedesktop";
 
 #[derive(Default)]
 struct Cache(BTreeMap<Box<Path>, playback::Playback>);
@@ -23,42 +34,153 @@ impl Cache {
     }
 }
 
+/// Sound theme lookups by name and theme, including the ones that found nothing.
 #[derive(Default)]
+struct Lookups(HashMap<(Box<str>, Box<str>), Option<Arc<Path>>>);
+
+impl Lookups {
+    fn lookup(&mut self, name: &str, theme: &str) -> Option<Arc<Path>> {
+        self.0
+            .entry((name.into(), theme.into()))
+            .or_insert_with(|| {
+                freedesktop_sound::lookup(name)
+                    .with_theme(theme)
+                    .with_cache()
+                    .find()
+                    .or_else(|| {
+                        (theme != FALLBACK_THEME)
+                            .then(|| {
+                                freedesktop_sound::lookup(name)
+                                    .with_theme(FALLBACK_THEME)
+                                    .with_cache()
+                                    .find()
+                            })
+                            .flatten()
+                    })
+                    .map(Into::into)
+            })
+            .clone()
+    }
+}
+
+#[derive(Clone)]
+pub enum Sound {
+    File(Arc<Path>),
+    /// Name from the sound theme spec, such as `message-new-instant`.
+    Name(Arc<str>),
+}
+
 pub struct Audio {
-    cache: Cache,
+    cache: Arc<Mutex<Cache>>,
+    lookups: Arc<Mutex<Lookups>>,
+    theme: Arc<str>,
+    sender: calloop::channel::Sender<Event>,
     muted: bool,
-    playback: Option<playback::Playback<playback::Played>>,
+    /// Sound that's playing and the notification it was played for.
+    playback: Option<(NotificationId, playback::Playback<playback::Played>)>,
 }
 
 impl Audio {
-    pub fn new() -> Self {
-        Self::default()
+    pub fn new(
+        theme: Option<&str>,
+        sender: calloop::channel::Sender<Event>,
+    ) -> anyhow::Result<Self> {
+        if !tinyaudio::device_available() {
+            return Err(anyhow::anyhow!("No audio output device available"));
+        }
+
+        Ok(Self {
+            cache: Arc::default(),
+            lookups: Arc::default(),
+            theme: theme.unwrap_or(FALLBACK_THEME).into(),
+            sender,
+            muted: false,
+            playback: None,
+        })
     }
 
-    pub fn play<T>(&mut self, path: T) -> anyhow::Result<()>
-    where
-        T: AsRef<Path>,
-    {
+    /// Looks up and decodes `sound` of notification `id` off the main thread, it's started once
+    /// it comes back to the event loop as `Event::SoundLoaded`. Sound names missing from the theme
+    /// play `fallback`. A `looped` sound repeats for that long unless stopped before.
+    pub fn play(
+        &self,
+        id: NotificationId,
+        sound: Sound,
+        fallback: Option<Arc<Path>>,
+        looped: Option<Duration>,
+    ) {
         if self.muted {
-            return Ok(());
+            return;
         }
 
-        if let Some(playback) = self.playback.take() {
-            playback.stop();
+        let cache = Arc::clone(&self.cache);
+        let lookups = Arc::clone(&self.lookups);
+        let theme = Arc::clone(&self.theme);
+        let sender = self.sender.clone();
+
+        rayon::spawn(move || {
+            let path = match sound {
+                Sound::File(path) => Some(path),
+                Sound::Name(name) => lookups.lock().unwrap().lookup(&name, &theme).or_else(|| {
+                    log::debug!("Sound {name} not found in {theme} theme");
+                    fallback
+                }),
+            };
+            let Some(path) = path else {
+                return;
+            };
+
+            let cached = cache.lock().unwrap().get(&path);
+            let playback = match cached {
+                Some(playback) => playback,
+                None => match playback::Playback::new(&path) {
+                    Ok(playback) => {
+                        cache.lock().unwrap().insert(&path, playback.clone());
+                        playback
+                    }
+                    Err(e) => {
+                        log::warn!("Failed to decode {}: {e}", path.display());
+                        return;
+                    }
+                },
+            };
+
+            let playback = match looped {
+                Some(max) => playback.looped(max),
+                None => playback,
+            };
+            _ = sender.send(Event::SoundLoaded {
+                id,
+                playback: Box::new(playback),
+            });
+        });
+    }
+
+    /// Starts a sound decoded by [`Audio::play`], cutting off the one that's still playing.
+    pub fn start(&mut self, id: NotificationId, playback: Playback) {
+        if self.muted {
+            return;
         }
 
-        let playback = match self.cache.get(&path) {
-            Some(playback) => playback,
-            None => {
-                let playback = playback::Playback::new(&path).unwrap();
-                self.cache.insert(&path, playback.clone());
-                playback
-            }
-        };
+        if let Some((_, playback)) = self.playback.take() {
+            playback.stop();
+        }
 
-        self.playback = Some(playback.start());
+        self.playback = Some((id, playback.start()));
+    }
 
-        Ok(())
+    /// Stops the sound of notification `id`, if it's still playing.
+    pub fn stop(&mut self, id: NotificationId) {
+        if self
+            .playback
+            .as_ref()
+            .is_some_and(|(playing, _)| *playing == id)
+        {
+            if let Some((_, playback)) = self.playback.take() {
+                log::debug!("Stopping sound of notification, id: {id}");
+                playback.stop();
+            }
+        }
     }
 
     pub fn mute(&mut self) {
@@ -73,3 +195,25 @@ impl Audio {
         self.muted
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Lookups, FALLBACK_THEME};
+
+    #[test]
+    fn test_lookups_cache_misses() {
+        let mut lookups = Lookups::default();
+        let name = "moxnotify-test-missing-sound";
+
+        assert!(lookups.lookup(name, "moxnotify-test-theme").is_none());
+        assert!(lookups.lookup(name, FALLBACK_THEME).is_none());
+        assert_eq!(lookups.0.len(), 2);
+        assert!(lookups
+            .0
+            .get(&(name.into(), "moxnotify-test-theme".into()))
+            .is_some_and(Option::is_none));
+
+        assert!(lookups.lookup(name, FALLBACK_THEME).is_none());
+        assert_eq!(lookups.0.len(), 2);
+    }
+}
@@ -2,9 +2,12 @@ use crate::audio::tinyaudio::SoundDevice;
 
 use super::tinyaudio::OutputDeviceParameters;
 use std::{
-    fs,
+    fmt, fs,
     path::Path,
-    sync::atomic::{AtomicUsize, Ordering},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     thread,
     time::Duration,
 };
@@ -25,16 +28,29 @@ pub struct Played {
         crossbeam_channel::Sender<()>,
         crossbeam_channel::Receiver<()>,
     ),
+    /// Checked by the device callback, which outputs silence from then on instead of reading
+    /// the buffer it shares with the cache.
+    stop_requested: Arc<AtomicBool>,
 }
 
 #[derive(Clone)]
 pub struct Playback<State = Ready> {
     duration: Duration,
-    buffer: Vec<f32>,
+    buffer: Arc<[f32]>,
     params: OutputDeviceParameters,
+    /// How long the sample is repeated for, it's played once when None.
+    looped: Option<Duration>,
     state: State,
 }
 
+impl fmt::Debug for Playback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Playback")
+            .field("duration", &self.duration)
+            .finish_non_exhaustive()
+    }
+}
+
 impl Playback {
     pub fn new<T>(path: T) -> anyhow::Result<Playback<Ready>>
     where
@@ -128,27 +144,48 @@ impl Playback {
 
         Ok(Self {
             duration,
-            buffer: audio_buffer,
+            buffer: audio_buffer.into(),
             params,
+            looped: None,
             state: Ready,
         })
     }
 
+    /// Repeats the sample until it's stopped, or for `max` at most.
+    pub fn looped(self, max: Duration) -> Self {
+        Self {
+            looped: Some(max),
+            ..self
+        }
+    }
+
     pub fn start(self) -> Playback<Played> {
         let (tx, rx) = crossbeam_channel::unbounded();
+        let stop_requested = Arc::new(AtomicBool::new(false));
 
-        let buffer = self.buffer.clone();
+        let buffer = Arc::clone(&self.buffer);
         let params = self.params;
-        let duration = self.duration;
+        let looped = self.looped.is_some();
+        let duration = self.looped.unwrap_or(self.duration);
 
         thread::spawn({
             let rx = rx.clone();
+            let stop_requested = Arc::clone(&stop_requested);
             move || {
-                let index = AtomicUsize::new(0);
+                let mut index = 0;
                 let mut device = SoundDevice::new(params, move |data| {
+                    // The device may still ask for a buffer or two after the stop
+                    if stop_requested.load(Ordering::Acquire) || buffer.is_empty() {
+                        data.fill(0.0);
+                        return;
+                    }
+
                     data.iter_mut().for_each(|sample| {
-                        let current_index = index.fetch_add(1, Ordering::Relaxed);
-                        *sample = *buffer.get(current_index).unwrap_or(&0.0);
+                        *sample = match looped {
+                            true => buffer[index % buffer.len()],
+                            false => *buffer.get(index).unwrap_or(&0.0),
+                        };
+                        index += 1;
                     });
                 })
                 .unwrap();
@@ -163,8 +200,10 @@ impl Playback {
             duration: self.duration,
             buffer: self.buffer,
             params: self.params,
+            looped: self.looped,
             state: Played {
                 shutdown_channel: (tx, rx),
+                stop_requested,
             },
         }
     }
@@ -172,6 +211,7 @@ impl Playback {
 
 impl Playback<Played> {
     pub fn stop(self) {
+        self.state.stop_requested.store(true, Ordering::Release);
         self.state.shutdown_channel.0.send(()).unwrap();
     }
 }
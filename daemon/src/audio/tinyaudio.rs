@@ -46,6 +46,24 @@ pub fn check(err_code: c_int) -> anyhow::Result<()> {
     }
 }
 
+pub fn device_available() -> bool {
+    unsafe {
+        let name = CString::new("default").unwrap();
+        let mut playback_device = std::ptr::null_mut();
+        if snd_pcm_open(
+            &mut playback_device,
+            name.as_ptr() as *const _,
+            SND_PCM_STREAM_PLAYBACK,
+            SND_PCM_NONBLOCK as c_int,
+        ) < 0
+        {
+            return false;
+        }
+        snd_pcm_close(playback_device);
+        true
+    }
+}
+
 impl<C> SoundDevice<C>
 where
     C: FnMut(&mut [f32]) + Send + 'static,
@@ -1,23 +1,28 @@
 use super::{Button, ButtonType, Hint, State};
 use crate::{
-    components::{Bounds, Component},
-    config::{button::ButtonState, Config},
+    components::{Bounds, Component, Layer},
+    config::{button::ButtonState, Config, StyleKey},
     manager::UiState,
-    rendering::{text_renderer, texture_renderer},
-    utils::buffers,
+    rendering::{
+        text_renderer,
+        texture_renderer::{self, TextureArea, TextureBounds},
+    },
+    utils::{buffers, image_data::ImageData},
     Urgency,
 };
 use std::sync::{atomic::Ordering, Arc};
 
 pub struct ActionButton {
     pub id: u32,
-    pub app_name: Arc<str>,
+    pub style_key: StyleKey,
     pub ui_state: UiState,
     pub x: f32,
     pub y: f32,
     pub hint: Hint,
     pub config: Arc<Config>,
     pub text: text_renderer::Text,
+    /// Icon shown in place of the label when the notification sets the action-icons hint.
+    pub icon: Option<ImageData>,
     pub action: Arc<str>,
     pub state: State,
     pub width: f32,
@@ -35,8 +40,12 @@ impl Component for ActionButton {
         self.id
     }
 
-    fn get_app_name(&self) -> &str {
-        &self.app_name
+    fn get_style_key(&self) -> &StyleKey {
+        &self.style_key
+    }
+
+    fn set_style_key(&mut self, style_key: StyleKey) {
+        self.style_key = style_key;
     }
 
     fn get_ui_state(&self) -> &UiState {
@@ -44,118 +53,51 @@ impl Component for ActionButton {
     }
 
     fn get_instances(&self, urgency: &Urgency) -> Vec<buffers::Instance> {
-        let style = self.get_style();
-        let bounds = self.get_render_bounds();
-
-        vec![buffers::Instance {
-            rect_pos: [bounds.x, bounds.y],
-            rect_size: [
-                bounds.width - style.border.size.left - style.border.size.right,
-                bounds.height - style.border.size.top - style.border.size.bottom,
-            ],
-            rect_color: style.background.to_linear(urgency),
-            border_radius: style.border.radius.into(),
-            border_size: style.border.size.into(),
-            border_color: style.border.color.to_linear(urgency),
-            scale: self.ui_state.scale.load(Ordering::Relaxed),
-            depth: 0.8,
-        }]
+        vec![super::background(
+            self.get_style(),
+            self.get_render_bounds(),
+            urgency,
+            self.ui_state.scale.load(Ordering::Relaxed),
+        )]
     }
 
     fn get_text_areas(&self, urgency: &Urgency) -> Vec<glyphon::TextArea<'_>> {
-        let extents = self.get_render_bounds();
-        let style = self.get_style();
-        let text_extents = self.text.get_bounds();
-
-        let remaining_padding = extents.width - text_extents.width;
-        let (pl, _) = match (style.padding.left.is_auto(), style.padding.right.is_auto()) {
-            (true, true) => (remaining_padding / 2., remaining_padding / 2.),
-            (true, false) => (remaining_padding, style.padding.right.resolve(0.)),
-            _ => (
-                style.padding.left.resolve(0.),
-                style.padding.right.resolve(0.),
-            ),
-        };
-
-        let remaining_padding = extents.height - text_extents.height;
-        let (pt, _) = match (style.padding.top.is_auto(), style.padding.bottom.is_auto()) {
-            (true, true) => (remaining_padding / 2., remaining_padding / 2.),
-            (true, false) => (remaining_padding, style.padding.bottom.resolve(0.)),
-            _ => (
-                style.padding.top.resolve(0.),
-                style.padding.bottom.resolve(0.),
-            ),
-        };
+        if self.icon.is_some() {
+            return Vec::new();
+        }
 
-        vec![glyphon::TextArea {
-            buffer: &self.text.buffer,
-            left: extents.x + style.border.size.left + style.padding.left.resolve(pl),
-            top: extents.y + style.border.size.top + style.padding.top.resolve(pt),
-            scale: self.ui_state.scale.load(Ordering::Relaxed),
-            bounds: glyphon::TextBounds {
-                left: (extents.x + style.border.size.left + style.padding.left.resolve(pl)) as i32,
-                top: (extents.y + style.border.size.top + style.padding.top.resolve(pt)) as i32,
-                right: (extents.x
-                    + style.border.size.left
-                    + style.padding.left.resolve(pl)
-                    + text_extents.width) as i32,
-                bottom: (extents.y
-                    + style.border.size.top
-                    + style.padding.top.resolve(pt)
-                    + text_extents.height) as i32,
-            },
-            custom_glyphs: &[],
-            default_color: style.font.color.into_glyphon(urgency),
-        }]
+        vec![super::label(
+            self.get_style(),
+            self.get_render_bounds(),
+            &self.text,
+            urgency,
+            self.ui_state.scale.load(Ordering::Relaxed),
+        )]
     }
 
     fn get_style(&self) -> &Self::Style {
         let style = self.get_notification_style();
 
         match self.state() {
-            State::Unhovered => &style.buttons.action.default,
-            State::Hovered => &style.buttons.action.hover,
+            State::Unhovered => &style.buttons.action_for(&self.action).default,
+            State::Hovered => &style.buttons.action_for(&self.action).hover,
         }
     }
 
     fn get_bounds(&self) -> Bounds {
-        let style = self.get_style();
-        let text_extents = self.text.get_bounds();
-
-        let width = style.width.resolve(self.width)
-            + style.border.size.left
-            + style.border.size.right
-            + style.padding.left
-            + style.padding.right
-            + style.margin.left
-            + style.margin.right;
+        let (width, height) = match self.icon {
+            Some(_) => {
+                let size = self.config.general.app_icon_size as f32;
+                (size, size)
+            }
+            None => (self.width, self.text.get_bounds().height),
+        };
 
-        let height = style.height.resolve(text_extents.height)
-            + style.border.size.top
-            + style.border.size.bottom
-            + style.padding.top
-            + style.padding.bottom
-            + style.margin.top
-            + style.margin.bottom;
-
-        Bounds {
-            x: self.x,
-            y: self.y,
-            width,
-            height,
-        }
+        super::outer_bounds(self.get_style(), self.x, self.y, width, height)
     }
 
     fn get_render_bounds(&self) -> Bounds {
-        let bounds = self.get_bounds();
-        let style = self.get_style();
-
-        Bounds {
-            x: bounds.x + style.margin.left,
-            y: bounds.y + style.margin.top,
-            width: bounds.width - style.margin.left - style.margin.right,
-            height: bounds.height - style.margin.top - style.margin.bottom,
-        }
+        super::without_margin(self.get_style(), self.get_bounds())
     }
 
     fn set_position(&mut self, x: f32, y: f32) {
@@ -168,7 +110,45 @@ impl Component for ActionButton {
     }
 
     fn get_textures(&self) -> Vec<texture_renderer::TextureArea<'_>> {
-        Vec::new()
+        let Some(icon) = self.icon.as_ref() else {
+            return Vec::new();
+        };
+
+        let extents = self.get_render_bounds();
+        let style = self.get_style();
+        let size = self.config.general.app_icon_size as f32;
+
+        // Centered within whatever the padding leaves, like the label would be
+        let left = extents.x
+            + style.border.size.left
+            + style.padding.left.resolve(
+                (extents.width - style.border.size.left - style.border.size.right - size) / 2.,
+            );
+        let top = extents.y
+            + style.border.size.top
+            + style.padding.top.resolve(
+                (extents.height - style.border.size.top - style.border.size.bottom - size) / 2.,
+            );
+
+        vec![TextureArea {
+            left,
+            top,
+            width: size,
+            height: size,
+            scale: self.ui_state.scale.load(Ordering::Relaxed),
+            border_size: [0.; 4],
+            bounds: TextureBounds {
+                left: left as u32,
+                top: top as u32,
+                right: (left + size) as u32,
+                bottom: (top + size) as u32,
+            },
+            data: icon.data(),
+            data_size: icon.size(),
+            radius: [0.; 4],
+            depth: Layer::Icon.depth(),
+            opacity: 1.,
+        }]
     }
 }
 
@@ -177,6 +157,10 @@ impl Button for ActionButton {
         &self.hint
     }
 
+    fn hint_mut(&mut self) -> &mut Hint {
+        &mut self.hint
+    }
+
     fn click(&self) {
         if let Some(tx) = self.tx.as_ref() {
             _ = tx.send(crate::Event::InvokeAction {
@@ -215,7 +199,7 @@ impl Button for ActionButton {
 mod tests {
     use crate::{
         components::button::{Button, Hint, State},
-        config::Config,
+        config::{Config, StyleKey},
         manager::UiState,
         rendering::text_renderer::Text,
         Event,
@@ -232,7 +216,7 @@ mod tests {
         let hint = Hint::new(
             0,
             "",
-            "".into(),
+            StyleKey::default(),
             Arc::clone(&config),
             &mut FontSystem::new(),
             ui_state.clone(),
@@ -247,13 +231,14 @@ mod tests {
             y: 0.,
             hint,
             text: Text::new(&config.styles.default.font, &mut FontSystem::new(), ""),
+            icon: None,
             state: State::Hovered,
             config: Arc::clone(&config),
             ui_state: ui_state.clone(),
             tx: Some(tx),
             width: 100.,
             action: Arc::clone(&test_action),
-            app_name: "".into(),
+            style_key: StyleKey::default(),
         };
 
         button.click();
@@ -277,7 +262,7 @@ mod tests {
         let hint = Hint::new(
             0,
             "",
-            "".into(),
+            StyleKey::default(),
             Arc::clone(&config),
             &mut FontSystem::new(),
             ui_state.clone(),
@@ -289,13 +274,14 @@ mod tests {
             y: 0.,
             hint,
             text: Text::new(&config.styles.default.font, &mut FontSystem::new(), ""),
+            icon: None,
             state: State::Hovered,
             config: Arc::clone(&config),
             ui_state: ui_state.clone(),
             tx: Some(tx.clone()),
             width: 100.,
             action: Arc::clone(&test_action1),
-            app_name: "".into(),
+            style_key: StyleKey::default(),
         };
 
         let (tx, text_rx2) = calloop::channel::channel();
@@ -305,7 +291,7 @@ mod tests {
         let hint = Hint::new(
             0,
             "",
-            "".into(),
+            StyleKey::default(),
             Arc::clone(&config),
             &mut FontSystem::new(),
             ui_state.clone(),
@@ -316,13 +302,14 @@ mod tests {
             y: 0.,
             hint,
             text: Text::new(&config.styles.default.font, &mut FontSystem::new(), ""),
+            icon: None,
             state: State::Hovered,
             config: Arc::clone(&config),
             ui_state: ui_state.clone(),
             tx: Some(tx.clone()),
             width: 100.,
             action: Arc::clone(&test_action2),
-            app_name: "".into(),
+            style_key: StyleKey::default(),
         };
 
         button1.click();
@@ -1,7 +1,7 @@
 use super::{Button, Component, Hint, State};
 use crate::{
-    components::{text::body::Anchor, Bounds},
-    config::{button::ButtonState, Config},
+    components::{text::body::Anchor, Bounds, Layer},
+    config::{button::ButtonState, Config, StyleKey},
     manager::UiState,
     rendering::{text_renderer::Text, texture_renderer},
     utils::buffers,
@@ -19,7 +19,7 @@ pub struct AnchorButton {
     pub ui_state: UiState,
     pub tx: Option<calloop::channel::Sender<crate::Event>>,
     pub anchor: Arc<Anchor>,
-    pub app_name: Arc<str>,
+    pub style_key: StyleKey,
 }
 
 impl Component for AnchorButton {
@@ -33,8 +33,12 @@ impl Component for AnchorButton {
         self.id
     }
 
-    fn get_app_name(&self) -> &str {
-        &self.app_name
+    fn get_style_key(&self) -> &StyleKey {
+        &self.style_key
+    }
+
+    fn set_style_key(&mut self, style_key: StyleKey) {
+        self.style_key = style_key;
     }
 
     fn get_ui_state(&self) -> &UiState {
@@ -56,7 +60,7 @@ impl Component for AnchorButton {
             border_size: style.border.size.into(),
             border_color: style.border.color.to_linear(urgency),
             scale: 0.,
-            depth: 0.8,
+            depth: Layer::Element.depth(),
         }]
     }
 
@@ -111,6 +115,10 @@ impl Button for AnchorButton {
         &self.hint
     }
 
+    fn hint_mut(&mut self) -> &mut Hint {
+        &mut self.hint
+    }
+
     fn click(&self) {
         if let Some(tx) = self.tx.as_ref() {
             _ = tx.send(crate::Event::InvokeAnchor(Arc::clone(&self.anchor.href)));
@@ -140,4 +148,8 @@ impl Button for AnchorButton {
     fn set_hint(&mut self, hint: Hint) {
         self.hint = hint;
     }
+
+    fn hidden(&self) -> bool {
+        !self.anchor.visible()
+    }
 }
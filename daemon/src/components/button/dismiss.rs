@@ -1,7 +1,7 @@
 use super::{Button, ButtonType, Hint, State};
 use crate::{
-    components::{Bounds, Component},
-    config::{button::ButtonState, Config},
+    components::{Bounds, Component, Layer},
+    config::{button::ButtonState, Config, StyleKey},
     manager::UiState,
     rendering::text_renderer,
     rendering::texture_renderer,
@@ -20,7 +20,7 @@ pub struct DismissButton {
     pub state: State,
     pub ui_state: UiState,
     pub tx: Option<calloop::channel::Sender<crate::Event>>,
-    pub app_name: Arc<str>,
+    pub style_key: StyleKey,
 }
 
 impl Component for DismissButton {
@@ -34,8 +34,12 @@ impl Component for DismissButton {
         &self.config
     }
 
-    fn get_app_name(&self) -> &str {
-        &self.app_name
+    fn get_style_key(&self) -> &StyleKey {
+        &self.style_key
+    }
+
+    fn set_style_key(&mut self, style_key: StyleKey) {
+        self.style_key = style_key;
     }
 
     fn get_ui_state(&self) -> &UiState {
@@ -65,7 +69,7 @@ impl Component for DismissButton {
             border_size: style.border.size.into(),
             border_color: style.border.color.to_linear(urgency),
             scale: self.ui_state.scale.load(Ordering::Relaxed),
-            depth: 0.8,
+            depth: Layer::Element.depth(),
         }]
     }
 
@@ -175,11 +179,16 @@ impl Button for DismissButton {
         &self.hint
     }
 
+    fn hint_mut(&mut self) -> &mut Hint {
+        &mut self.hint
+    }
+
     fn click(&self) {
         if let Some(tx) = self.tx.as_ref() {
             _ = tx.send(crate::Event::Dismiss {
                 all: false,
                 id: self.id,
+                app: None,
             });
         }
     }
@@ -214,7 +223,7 @@ mod tests {
     use super::DismissButton;
     use crate::{
         components::button::{Button, Hint, State},
-        config::Config,
+        config::{Config, StyleKey},
         manager::UiState,
         rendering::text_renderer::Text,
     };
@@ -228,7 +237,7 @@ mod tests {
         let hint = Hint::new(
             0,
             "",
-            "".into(),
+            StyleKey::default(),
             Arc::clone(&config),
             &mut FontSystem::new(),
             ui_state.clone(),
@@ -238,7 +247,7 @@ mod tests {
         let test_id = 10;
         let button = DismissButton {
             id: test_id,
-            app_name: "".into(),
+            style_key: StyleKey::default(),
             x: 0.,
             y: 0.,
             hint,
@@ -251,7 +260,7 @@ mod tests {
 
         button.click();
 
-        if let crate::Event::Dismiss { all: false, id } = rx.try_recv().unwrap() {
+        if let crate::Event::Dismiss { all: false, id, .. } = rx.try_recv().unwrap() {
             assert_eq!(id, test_id, "Button click should send button ID");
         };
     }
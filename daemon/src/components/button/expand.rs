@@ -0,0 +1,203 @@
+use super::{Button, ButtonType, Hint, State};
+use crate::{
+    components::{Bounds, Component},
+    config::{button::ButtonState, Config, StyleKey},
+    manager::UiState,
+    rendering::text_renderer,
+    rendering::texture_renderer,
+    utils::buffers,
+    Urgency,
+};
+use std::sync::{atomic::Ordering, Arc};
+
+/// Label of the button while the body is cut off.
+pub const SHOW_MORE: &str = "Show more";
+
+/// Label of the button while the body is shown in full.
+pub const SHOW_LESS: &str = "Show less";
+
+/// Expands a body that's cut off at `max_lines` and collapses it again.
+pub struct ExpandButton {
+    pub id: u32,
+    pub x: f32,
+    pub y: f32,
+    pub hint: Hint,
+    pub config: Arc<Config>,
+    pub text: text_renderer::Text,
+    pub state: State,
+    pub ui_state: UiState,
+    pub tx: Option<calloop::channel::Sender<crate::Event>>,
+    pub style_key: StyleKey,
+}
+
+impl Component for ExpandButton {
+    type Style = ButtonState;
+
+    fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    fn get_style_key(&self) -> &StyleKey {
+        &self.style_key
+    }
+
+    fn set_style_key(&mut self, style_key: StyleKey) {
+        self.style_key = style_key;
+    }
+
+    fn get_ui_state(&self) -> &UiState {
+        &self.ui_state
+    }
+
+    fn get_style(&self) -> &Self::Style {
+        let style = self.get_notification_style();
+        match self.state() {
+            State::Unhovered => &style.buttons.action.default,
+            State::Hovered => &style.buttons.action.hover,
+        }
+    }
+
+    fn get_instances(&self, urgency: &Urgency) -> Vec<buffers::Instance> {
+        vec![super::background(
+            self.get_style(),
+            self.get_render_bounds(),
+            urgency,
+            self.ui_state.scale.load(Ordering::Relaxed),
+        )]
+    }
+
+    fn get_text_areas(&self, urgency: &Urgency) -> Vec<glyphon::TextArea<'_>> {
+        vec![super::label(
+            self.get_style(),
+            self.get_render_bounds(),
+            &self.text,
+            urgency,
+            self.ui_state.scale.load(Ordering::Relaxed),
+        )]
+    }
+
+    fn get_bounds(&self) -> Bounds {
+        let text_extents = self.text.get_bounds();
+        super::outer_bounds(
+            self.get_style(),
+            self.x,
+            self.y,
+            text_extents.width,
+            text_extents.height,
+        )
+    }
+
+    fn get_render_bounds(&self) -> Bounds {
+        super::without_margin(self.get_style(), self.get_bounds())
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+        self.text.set_buffer_position(x, y);
+
+        let bounds = self.get_render_bounds();
+        self.hint.set_position(bounds.x, bounds.y);
+    }
+
+    fn get_textures(&self) -> Vec<texture_renderer::TextureArea<'_>> {
+        Vec::new()
+    }
+}
+
+impl Button for ExpandButton {
+    fn hint(&self) -> &Hint {
+        &self.hint
+    }
+
+    fn hint_mut(&mut self) -> &mut Hint {
+        &mut self.hint
+    }
+
+    fn click(&self) {
+        if let Some(tx) = self.tx.as_ref() {
+            _ = tx.send(crate::Event::ToggleExpand(self.id));
+        }
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn button_type(&self) -> ButtonType {
+        ButtonType::Expand
+    }
+
+    fn state(&self) -> State {
+        self.state
+    }
+
+    fn hover(&mut self) {
+        self.state = State::Hovered;
+    }
+
+    fn unhover(&mut self) {
+        self.state = State::Unhovered
+    }
+
+    fn set_hint(&mut self, hint: Hint) {
+        self.hint = hint;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExpandButton, SHOW_MORE};
+    use crate::{
+        components::button::{Button, Hint, State},
+        config::{Config, StyleKey},
+        manager::UiState,
+        rendering::text_renderer::Text,
+    };
+    use glyphon::FontSystem;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_expand_button() {
+        let config = Arc::new(Config::default());
+        let ui_state = UiState::default();
+        let hint = Hint::new(
+            0,
+            "",
+            StyleKey::default(),
+            Arc::clone(&config),
+            &mut FontSystem::new(),
+            ui_state.clone(),
+        );
+
+        let (tx, rx) = calloop::channel::channel();
+        let test_id = 10;
+        let button = ExpandButton {
+            id: test_id,
+            style_key: StyleKey::default(),
+            x: 0.,
+            y: 0.,
+            hint,
+            text: Text::new(
+                &config.styles.default.font,
+                &mut FontSystem::new(),
+                SHOW_MORE,
+            ),
+            state: State::Unhovered,
+            config: Arc::clone(&config),
+            ui_state,
+            tx: Some(tx),
+        };
+
+        button.click();
+
+        assert!(matches!(
+            rx.try_recv().unwrap(),
+            crate::Event::ToggleExpand(id) if id == test_id
+        ));
+    }
+}
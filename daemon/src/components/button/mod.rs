@@ -1,28 +1,33 @@
 mod action;
 mod anchor;
 mod dismiss;
+mod expand;
 
-use super::text::body;
+use super::{
+    icons::{cached_icon, find_icon},
+    text::{body, input::INLINE_REPLY},
+};
 use crate::{
-    components::{Bounds, Component, Data},
+    components::{Bounds, Component, Layer},
     config::{
         self,
         button::ButtonState,
         keymaps::{self},
-        Config,
+        Config, StyleKey,
     },
     manager::UiState,
     rendering::{text_renderer, texture_renderer},
-    utils::buffers,
-    Urgency,
+    utils::{buffers, image_data::ImageData},
+    History, Urgency,
 };
 use action::ActionButton;
 use anchor::AnchorButton;
 use dismiss::DismissButton;
-use glyphon::{FontSystem, TextArea};
+use expand::{ExpandButton, SHOW_LESS, SHOW_MORE};
+use glyphon::{Attrs, FontSystem, Shaping, TextArea, Weight};
 use std::sync::{atomic::Ordering, Arc};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum State {
     Unhovered,
     Hovered,
@@ -31,6 +36,8 @@ pub enum State {
 pub trait Button: Component + Send + Sync {
     fn hint(&self) -> &Hint;
 
+    fn hint_mut(&mut self) -> &mut Hint;
+
     fn click(&self);
 
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any;
@@ -44,13 +51,19 @@ pub trait Button: Component + Send + Sync {
     fn unhover(&mut self);
 
     fn set_hint(&mut self, hint: Hint);
+
+    /// Whether the button is out of sight, such as an anchor past `max_lines`, and gets no hint.
+    fn hidden(&self) -> bool {
+        false
+    }
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Debug)]
 pub enum ButtonType {
     Dismiss,
     Action,
     Anchor,
+    Expand,
 }
 
 pub struct NotReady;
@@ -58,13 +71,18 @@ pub struct Ready;
 pub struct Finished;
 
 pub struct ButtonManager<State = NotReady> {
-    app_name: Arc<str>,
+    style_key: StyleKey,
     id: u32,
     buttons: Vec<Box<dyn Button<Style = ButtonState>>>,
     urgency: Urgency,
     pub ui_state: UiState,
     sender: Option<calloop::channel::Sender<crate::Event>>,
     config: Arc<Config>,
+    /// Whether the buttons belong to a notification loaded from history, whose sender may be
+    /// long gone. Actions can't be invoked on those, anchors still work.
+    history: History,
+    /// Index of the button that keyboard focus is on.
+    focused: Option<usize>,
     _state: std::marker::PhantomData<State>,
 }
 
@@ -72,10 +90,11 @@ impl ButtonManager<NotReady> {
     pub fn new(
         id: u32,
         urgency: Urgency,
-        app_name: Arc<str>,
+        style_key: StyleKey,
         ui_state: UiState,
         sender: Option<calloop::channel::Sender<crate::Event>>,
         config: Arc<Config>,
+        history: History,
     ) -> Self {
         Self {
             id,
@@ -84,7 +103,9 @@ impl ButtonManager<NotReady> {
             ui_state,
             sender,
             config,
-            app_name,
+            history,
+            style_key,
+            focused: None,
             _state: std::marker::PhantomData,
         }
     }
@@ -92,28 +113,40 @@ impl ButtonManager<NotReady> {
     pub fn add_actions(
         self,
         actions: &[(Arc<str>, Arc<str>)],
+        action_icons: bool,
         font_system: &mut FontSystem,
     ) -> Self {
-        let app_name = Arc::clone(&self.app_name);
-        self.internal_add_actions(app_name, actions, font_system)
+        let style_key = self.style_key.clone();
+        self.internal_add_actions(style_key, actions, action_icons, font_system)
     }
 
     pub fn add_anchors(self, anchors: &[Arc<body::Anchor>], font_system: &mut FontSystem) -> Self {
         self.internal_add_anchors(anchors, font_system)
     }
 
+    /// Finishes without any buttons, not even the dismiss one.
+    pub fn without_buttons(self) -> ButtonManager<Finished> {
+        self.into_state()
+    }
+
+    /// Adds the dismiss button, unless it's disabled in the config.
     pub fn add_dismiss(mut self, font_system: &mut FontSystem) -> ButtonManager<Ready> {
+        let options = &self.config.general.buttons.dismiss;
+        if !options.enabled {
+            return self.into_state();
+        }
+
         let font = &self.config.styles.default.buttons.dismiss.default.font;
-        let text = text_renderer::Text::new(font, font_system, "X");
+        let text = text_renderer::Text::new(font, font_system, &options.label);
 
         let button = DismissButton {
             id: self.id,
-            app_name: "".into(),
+            style_key: StyleKey::default(),
             ui_state: self.ui_state.clone(),
             hint: Hint::new(
                 0,
                 "",
-                "".into(),
+                StyleKey::default(),
                 Arc::clone(&self.config),
                 font_system,
                 self.ui_state.clone(),
@@ -128,16 +161,7 @@ impl ButtonManager<NotReady> {
 
         self.buttons.push(Box::new(button));
 
-        ButtonManager {
-            id: self.id,
-            app_name: self.app_name,
-            buttons: self.buttons,
-            urgency: self.urgency,
-            ui_state: self.ui_state,
-            sender: self.sender,
-            config: self.config,
-            _state: std::marker::PhantomData,
-        }
+        self.into_state()
     }
 }
 
@@ -145,57 +169,49 @@ impl ButtonManager<Ready> {
     pub fn add_actions(
         self,
         actions: &[(Arc<str>, Arc<str>)],
+        action_icons: bool,
         font_system: &mut FontSystem,
     ) -> Self {
-        let app_name = Arc::clone(&self.app_name);
-        self.internal_add_actions(app_name, actions, font_system)
+        let style_key = self.style_key.clone();
+        self.internal_add_actions(style_key, actions, action_icons, font_system)
     }
 
     pub fn add_anchors(self, anchors: &[Arc<body::Anchor>], font_system: &mut FontSystem) -> Self {
         self.internal_add_anchors(anchors, font_system)
     }
 
-    pub fn finish(mut self, font_system: &mut FontSystem) -> ButtonManager<Finished> {
-        let hint_chars: Vec<char> = self.config.general.hint_characters.chars().collect();
-        let n = hint_chars.len() as i32;
-
-        self.buttons.iter_mut().enumerate().for_each(|(i, button)| {
-            let mut m = i as i32;
-            let mut indices = Vec::new();
-
-            loop {
-                let rem = (m % n) as usize;
-                indices.push(rem);
-                m = (m / n) - 1;
-                if m < 0 {
-                    break;
-                }
-            }
+    /// Adds the button that expands a body cut off at `max_lines`.
+    pub fn add_expand(mut self, font_system: &mut FontSystem) -> Self {
+        let font = &self.config.styles.default.buttons.action.default.font;
+        let text = text_renderer::Text::new(font, font_system, SHOW_MORE);
 
-            indices.reverse();
-            let combination: String = indices.into_iter().map(|i| hint_chars[i]).collect();
-            let hint = Hint::new(
+        let button = ExpandButton {
+            id: self.id,
+            style_key: self.style_key.clone(),
+            ui_state: self.ui_state.clone(),
+            hint: Hint::new(
                 0,
-                &combination,
-                "".into(),
+                "",
+                StyleKey::default(),
                 Arc::clone(&self.config),
                 font_system,
                 self.ui_state.clone(),
-            );
+            ),
+            text,
+            x: 0.,
+            y: 0.,
+            config: Arc::clone(&self.config),
+            state: State::Unhovered,
+            tx: self.sender.clone(),
+        };
 
-            button.set_hint(hint);
-        });
+        self.buttons.push(Box::new(button));
+        self
+    }
 
-        ButtonManager {
-            id: self.id,
-            app_name: self.app_name,
-            buttons: self.buttons,
-            urgency: self.urgency,
-            ui_state: self.ui_state,
-            sender: self.sender,
-            config: self.config,
-            _state: std::marker::PhantomData,
-        }
+    pub fn finish(mut self, font_system: &mut FontSystem) -> ButtonManager<Finished> {
+        self.assign_hints(font_system);
+        self.into_state()
     }
 }
 
@@ -220,115 +236,345 @@ impl ButtonManager<Finished> {
             .is_some()
     }
 
+    /// Updates the hover state of every button, returns whether any of them is under the pointer.
     pub fn hover(&mut self, x: f64, y: f64) -> bool {
-        self.buttons
-            .iter_mut()
-            .filter_map(|button| {
-                let bounds = button.get_render_bounds();
-                if x >= bounds.x as f64
-                    && y >= bounds.y as f64
-                    && x <= (bounds.x + bounds.width) as f64
-                    && y <= (bounds.y + bounds.height) as f64
-                {
-                    button.hover();
-                    Some(true)
-                } else {
-                    button.unhover();
-                    None
-                }
-            })
-            .next()
-            .is_some()
+        self.buttons.iter_mut().fold(false, |hovered, button| {
+            let bounds = button.get_render_bounds();
+            if x >= bounds.x as f64
+                && y >= bounds.y as f64
+                && x <= (bounds.x + bounds.width) as f64
+                && y <= (bounds.y + bounds.height) as f64
+            {
+                button.hover();
+                true
+            } else {
+                button.unhover();
+                hovered
+            }
+        })
     }
 
-    pub fn hint<T>(&mut self, combination: T)
+    /// Moves keyboard focus to the next button, wrapping around to the first one.
+    pub fn focus_next(&mut self) {
+        if self.buttons.is_empty() {
+            return;
+        }
+
+        self.focused = Some(self.focused.map_or(0, |i| (i + 1) % self.buttons.len()));
+    }
+
+    /// Moves keyboard focus to the previous button, wrapping around to the last one.
+    pub fn focus_prev(&mut self) {
+        let len = self.buttons.len();
+        if len == 0 {
+            return;
+        }
+
+        self.focused = Some(self.focused.map_or(len - 1, |i| (i + len - 1) % len));
+    }
+
+    pub fn unfocus(&mut self) {
+        self.focused = None;
+    }
+
+    pub fn focused(&self) -> Option<ButtonType> {
+        self.focused
+            .and_then(|i| self.buttons.get(i))
+            .map(|button| button.button_type())
+    }
+
+    /// Indices of the button under the pointer and of the one keyboard focus is on.
+    pub fn highlighted(&self) -> (Option<usize>, Option<usize>) {
+        let hovered = self
+            .buttons
+            .iter()
+            .position(|button| button.state() == State::Hovered);
+
+        (hovered, self.focused)
+    }
+
+    /// Clicks the focused button, returns false when nothing has focus.
+    pub fn activate_focused(&self) -> bool {
+        match self.focused.and_then(|i| self.buttons.get(i)) {
+            Some(button) => {
+                button.click();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Ring drawn around the focused button in the color of its label.
+    fn focus_outline(&self) -> Option<buffers::Instance> {
+        const WIDTH: f32 = 2.;
+
+        let button = self.buttons.get(self.focused?)?;
+        let style = button.get_style();
+        let bounds = button.get_render_bounds();
+
+        Some(buffers::Instance {
+            rect_pos: [bounds.x, bounds.y],
+            rect_size: [bounds.width - WIDTH * 2., bounds.height - WIDTH * 2.],
+            rect_color: [0.; 4],
+            border_radius: style.border.radius.into(),
+            border_size: [WIDTH; 4],
+            border_color: style.font.color.to_linear(&self.urgency),
+            scale: self.ui_state.scale.load(Ordering::Relaxed),
+            depth: Layer::Focus.depth(),
+        })
+    }
+
+    /// Clicks the button whose hint is `combination`, returns whether there was one.
+    pub fn hint<T>(&mut self, combination: T) -> bool
     where
         T: AsRef<str>,
     {
-        if let Some(button) = self
+        if combination.as_ref().is_empty() {
+            return false;
+        }
+
+        match self
             .buttons
             .iter()
             .find(|button| &*button.hint().combination == combination.as_ref())
         {
-            button.click();
+            Some(button) => {
+                button.click();
+                true
+            }
+            None => false,
         }
     }
 
-    pub fn instances(&self) -> Vec<buffers::Instance> {
-        let mut buttons = self
-            .buttons
-            .iter()
-            .flat_map(|button| button.get_instances(&self.urgency))
-            .collect::<Vec<_>>();
+    /// Colors the typed part of every hint starting with `typed`, returns whether any does.
+    pub fn highlight_hints(&mut self, typed: &str, font_system: &mut FontSystem) -> bool {
+        let urgency = self.urgency;
+        self.buttons
+            .iter_mut()
+            .map(|button| button.hint_mut())
+            .filter(|hint| !hint.combination.is_empty())
+            .fold(false, |any, hint| {
+                let matched = match hint.combination.starts_with(typed) {
+                    true => typed.chars().count(),
+                    false => 0,
+                };
+                hint.set_matched(matched, &urgency, font_system);
+                any || hint.combination.starts_with(typed)
+            })
+    }
 
-        if self.ui_state.mode.load(Ordering::Relaxed) == keymaps::Mode::Hint
+    /// Hints shown while this notification is selected in hint mode, the ones that start with
+    /// what was typed so far.
+    fn shown_hints(&self) -> impl Iterator<Item = &Hint> {
+        let shown = self.ui_state.mode.load(Ordering::Relaxed) == keymaps::Mode::Hint
             && self.ui_state.selected_id.load(Ordering::Relaxed) == self.id
-            && self.ui_state.selected.load(Ordering::Relaxed)
-        {
-            let hints = self
-                .buttons
-                .iter()
-                .flat_map(|button| button.hint().get_instances(&self.urgency))
-                .collect::<Vec<_>>();
-            buttons.extend_from_slice(&hints);
-        }
+            && self.ui_state.selected.load(Ordering::Relaxed);
+        let typed = self.ui_state.typed_hint.lock().unwrap().clone();
 
-        buttons
+        self.buttons
+            .iter()
+            .map(|button| button.hint())
+            .filter(move |hint| {
+                shown && !hint.combination.is_empty() && hint.combination.starts_with(&*typed)
+            })
+    }
+
+    pub fn instances(&self) -> Vec<buffers::Instance> {
+        self.buttons
+            .iter()
+            .flat_map(|button| button.get_instances(&self.urgency))
+            .chain(self.focus_outline())
+            .chain(
+                self.shown_hints()
+                    .flat_map(|hint| hint.get_instances(&self.urgency)),
+            )
+            .collect()
     }
 
     pub fn text_areas(&self) -> Vec<TextArea<'_>> {
-        let mut text_areas = self
-            .buttons
+        self.buttons
             .iter()
             .flat_map(|button| button.get_text_areas(&self.urgency))
-            .collect::<Vec<_>>();
+            .chain(
+                self.shown_hints()
+                    .flat_map(|hint| hint.get_text_areas(&self.urgency)),
+            )
+            .collect()
+    }
 
-        if self.ui_state.mode.load(Ordering::Relaxed) == keymaps::Mode::Hint
-            && self.ui_state.selected_id.load(Ordering::Relaxed) == self.id
-            && self.ui_state.selected.load(Ordering::Relaxed)
-        {
-            let hints = self
-                .buttons
-                .iter()
-                .flat_map(|button| button.hint().get_text_areas(&self.urgency));
-            text_areas.extend(hints);
-        }
+    pub fn textures(&self) -> Vec<texture_renderer::TextureArea<'_>> {
+        self.buttons
+            .iter()
+            .flat_map(|button| button.get_textures())
+            .chain(self.shown_hints().flat_map(|hint| hint.get_textures()))
+            .collect()
+    }
+
+    /// Switches the label of the expand button between "show more" and "show less".
+    pub fn set_expanded(&mut self, expanded: bool, font_system: &mut FontSystem) {
+        let font = &self.config.styles.default.buttons.action.default.font;
+        let label = match expanded {
+            true => SHOW_LESS,
+            false => SHOW_MORE,
+        };
 
-        text_areas
+        self.buttons
+            .iter_mut()
+            .filter_map(|button| button.as_any_mut().downcast_mut::<ExpandButton>())
+            .for_each(|button| button.text = text_renderer::Text::new(font, font_system, label));
     }
 
-    pub fn data(&self) -> Vec<Data<'_>> {
-        let mut data = self
-            .buttons
+    /// Index among the anchors of the one under the pointer.
+    pub fn hovered_anchor(&self) -> Option<usize> {
+        self.buttons
             .iter()
-            .flat_map(|button| button.get_data(&self.urgency))
-            .collect::<Vec<_>>();
+            .filter(|button| button.button_type() == ButtonType::Anchor)
+            .position(|button| button.state() == State::Hovered)
+    }
 
-        if self.ui_state.mode.load(Ordering::Relaxed) == keymaps::Mode::Hint
-            && self.ui_state.selected_id.load(Ordering::Relaxed) == self.id
-            && self.ui_state.selected.load(Ordering::Relaxed)
-        {
-            let hints = self
-                .buttons
-                .iter()
-                .flat_map(|button| button.hint().get_data(&self.urgency));
-            data.extend(hints);
-        }
+    /// An empty manager for the same notification, to build its buttons again with.
+    pub fn renew(&self) -> ButtonManager<NotReady> {
+        ButtonManager::new(
+            self.id,
+            self.urgency,
+            self.style_key.clone(),
+            self.ui_state.clone(),
+            self.sender.clone(),
+            Arc::clone(&self.config),
+            self.history,
+        )
+    }
 
-        data
+    pub fn set_style_key(&mut self, style_key: StyleKey) {
+        self.buttons
+            .iter_mut()
+            .for_each(|button| button.set_style_key(style_key.clone()));
+        self.style_key = style_key;
+    }
+
+    /// Points the anchor buttons at where the body placed its anchors since.
+    pub fn set_anchors(&mut self, anchors: &[Arc<body::Anchor>]) {
+        self.buttons
+            .iter_mut()
+            .filter_map(|button| button.as_any_mut().downcast_mut::<AnchorButton>())
+            .zip(anchors)
+            .for_each(|(button, anchor)| button.anchor = Arc::clone(anchor));
     }
 
-    pub fn set_action_widths(&mut self, width: f32) {
+    /// Shows `icon` in place of the label of the buttons of action `action`, returns false when
+    /// there's none.
+    pub fn set_action_icon(&mut self, action: &str, icon: ImageData) -> bool {
+        let mut found = false;
         self.buttons
             .iter_mut()
             .filter_map(|button| button.as_any_mut().downcast_mut::<ActionButton>())
+            .filter(|button| &*button.action == action)
+            .for_each(|button| {
+                button.icon = Some(icon.clone());
+                found = true;
+            });
+
+        found
+    }
+
+    /// Shares `row_width` between the action buttons, icon-only ones keep their own size and
+    /// the labeled ones split whatever is left.
+    pub fn set_action_widths(&mut self, row_width: f32) {
+        let mut actions = self
+            .buttons
+            .iter_mut()
+            .filter_map(|button| button.as_any_mut().downcast_mut::<ActionButton>())
+            .collect::<Vec<_>>();
+
+        let (icons_width, labels) =
+            actions
+                .iter()
+                .fold((0., 0), |(width, labels), action| match action.icon {
+                    Some(_) => (width + action.get_bounds().width, labels),
+                    None => (width, labels + 1),
+                });
+        if labels == 0 {
+            return;
+        }
+
+        let slot_width = (row_width - icons_width) / labels as f32;
+        actions
+            .iter_mut()
+            .filter(|action| action.icon.is_none())
             .for_each(|action| {
-                action.width = width;
+                let style = action.get_style();
+                action.width = slot_width
+                    - style.margin.left
+                    - style.margin.right
+                    - style.padding.left
+                    - style.padding.right
+                    - style.border.size.left
+                    - style.border.size.right;
             });
     }
 }
 
 impl<S> ButtonManager<S> {
+    /// Gives every button that can be clicked a hint, in the order they were added.
+    pub fn assign_hints(&mut self, font_system: &mut FontSystem) {
+        let hint_chars: Vec<char> = self.config.general.hint_characters.chars().collect();
+        let n = hint_chars.len();
+
+        let history = self.history;
+        let hinted = |button: &Box<dyn Button<Style = ButtonState>>| {
+            !button.hidden()
+                && (history == History::Hidden || button.button_type() != ButtonType::Action)
+        };
+
+        // All combinations are equally long so that none is the start of another
+        let count = self.buttons.iter().filter(|button| hinted(button)).count();
+        let mut len = 1;
+        while n > 1 && n.pow(len) < count {
+            len += 1;
+        }
+
+        let mut i = 0;
+        for button in self.buttons.iter_mut() {
+            let combination: String = if hinted(button) {
+                let mut m = i;
+                i += 1;
+                let mut combination = Vec::new();
+                for _ in 0..len {
+                    combination.push(hint_chars[m % n]);
+                    m /= n;
+                }
+                combination.into_iter().rev().collect()
+            } else {
+                String::new()
+            };
+
+            let hint = Hint::new(
+                0,
+                &combination,
+                StyleKey::default(),
+                Arc::clone(&self.config),
+                font_system,
+                self.ui_state.clone(),
+            );
+            button.set_hint(hint);
+        }
+    }
+
+    fn into_state<T>(self) -> ButtonManager<T> {
+        ButtonManager {
+            id: self.id,
+            style_key: self.style_key,
+            buttons: self.buttons,
+            urgency: self.urgency,
+            ui_state: self.ui_state,
+            sender: self.sender,
+            config: self.config,
+            history: self.history,
+            focused: self.focused,
+            _state: std::marker::PhantomData,
+        }
+    }
+
     fn internal_add_anchors(
         mut self,
         anchors: &[Arc<body::Anchor>],
@@ -349,7 +595,7 @@ impl<S> ButtonManager<S> {
                 hint: Hint::new(
                     0,
                     "",
-                    "".into(),
+                    StyleKey::default(),
                     Arc::clone(&self.config),
                     font_system,
                     self.ui_state.clone(),
@@ -360,7 +606,7 @@ impl<S> ButtonManager<S> {
                 text,
                 ui_state: self.ui_state.clone(),
                 anchor: Arc::clone(anchor),
-                app_name: Arc::clone(&self.app_name),
+                style_key: self.style_key.clone(),
             }) as Box<dyn Button<Style = ButtonState>>
         }));
 
@@ -369,8 +615,9 @@ impl<S> ButtonManager<S> {
 
     fn internal_add_actions(
         mut self,
-        app_name: Arc<str>,
+        style_key: StyleKey,
         actions: &[(Arc<str>, Arc<str>)],
+        action_icons: bool,
         font_system: &mut FontSystem,
     ) -> Self {
         if actions.is_empty() {
@@ -379,9 +626,24 @@ impl<S> ButtonManager<S> {
 
         let mut buttons = actions
             .iter()
+            .filter(|action| &*action.0 != INLINE_REPLY)
             .cloned()
             .map(|action| {
-                let font = &self.config.styles.default.buttons.action.default.font;
+                // With the action-icons hint the keys are icon names, the label is shown until
+                // the icon is found and when it can't be
+                let icon = match action_icons {
+                    true => self.action_icon(&action.0),
+                    false => None,
+                };
+
+                let font = &self
+                    .config
+                    .styles
+                    .default
+                    .buttons
+                    .action_for(&action.0)
+                    .default
+                    .font;
                 let text = text_renderer::Text::new(font, font_system, &action.1);
 
                 Box::new(ActionButton {
@@ -390,20 +652,24 @@ impl<S> ButtonManager<S> {
                     hint: Hint::new(
                         0,
                         "",
-                        "".into(),
+                        StyleKey::default(),
                         Arc::clone(&self.config),
                         font_system,
                         self.ui_state.clone(),
                     ),
                     text,
+                    icon,
                     x: 0.,
                     y: 0.,
                     config: Arc::clone(&self.config),
                     action: action.0,
                     state: State::Unhovered,
                     width: 0.,
-                    app_name: Arc::clone(&app_name),
-                    tx: self.sender.clone(),
+                    style_key: style_key.clone(),
+                    tx: self
+                        .sender
+                        .clone()
+                        .filter(|_| self.history == History::Hidden),
                 }) as Box<dyn Button<Style = ButtonState>>
             })
             .collect();
@@ -413,6 +679,34 @@ impl<S> ButtonManager<S> {
         self
     }
 
+    /// Icon of action `name` if it was found before. Otherwise it's looked up off the event
+    /// loop and comes back as `Event::ActionIconLoaded`, without a sender it's looked up right
+    /// away.
+    fn action_icon(&self, name: &Arc<str>) -> Option<ImageData> {
+        let icon_size = self.config.general.app_icon_size as u16;
+        let theme = self.config.general.theme.clone();
+        if let Some(icon) = cached_icon(name, icon_size, theme.as_deref()) {
+            return Some(icon);
+        }
+
+        let Some(sender) = self.sender.clone() else {
+            return find_icon(name, icon_size, theme.as_deref());
+        };
+
+        let (id, action) = (self.id, Arc::clone(name));
+        rayon::spawn(move || {
+            if let Some(icon) = find_icon(&action, icon_size, theme.as_deref()) {
+                _ = sender.send(crate::Event::ActionIconLoaded {
+                    id,
+                    action,
+                    icon: Box::new(icon),
+                });
+            }
+        });
+
+        None
+    }
+
     pub fn buttons(&self) -> &[Box<dyn Button<Style = ButtonState>>] {
         &self.buttons
     }
@@ -425,10 +719,12 @@ impl<S> ButtonManager<S> {
 pub struct Hint {
     id: u32,
     combination: Box<str>,
-    app_name: Arc<str>,
+    style_key: StyleKey,
     text: text_renderer::Text,
     config: Arc<Config>,
     ui_state: UiState,
+    /// Leading characters of the combination drawn in the matched color.
+    matched: usize,
     x: f32,
     y: f32,
 }
@@ -437,7 +733,7 @@ impl Hint {
     pub fn new<T>(
         id: u32,
         combination: T,
-        app_name: Arc<str>,
+        style_key: StyleKey,
         config: Arc<Config>,
         font_system: &mut FontSystem,
         ui_state: UiState,
@@ -447,19 +743,55 @@ impl Hint {
     {
         Self {
             id,
-            app_name,
+            style_key,
             combination: combination.as_ref().into(),
             ui_state,
-            text: text_renderer::Text::new(
+            text: text_renderer::Text::with_layer(
                 &config.styles.default.font,
                 font_system,
                 combination.as_ref(),
+                Layer::HintText,
             ),
             config,
+            matched: 0,
             x: 0.,
             y: 0.,
         }
     }
+
+    fn set_matched(&mut self, matched: usize, urgency: &Urgency, font_system: &mut FontSystem) {
+        if self.matched == matched {
+            return;
+        }
+        self.matched = matched;
+
+        let font = &self.config.styles.default.font;
+        let attrs = Attrs::new()
+            .metadata(Layer::HintText.metadata())
+            .family(glyphon::Family::Name(&font.family))
+            .weight(Weight::BOLD);
+        let split = self
+            .combination
+            .char_indices()
+            .nth(matched)
+            .map_or(self.combination.len(), |(i, _)| i);
+        let (typed, rest) = self.combination.split_at(split);
+        let color = self
+            .config
+            .styles
+            .hover
+            .hint
+            .font_matched_color
+            .into_glyphon(urgency);
+
+        self.text.buffer.set_rich_text(
+            font_system,
+            [(typed, attrs.clone().color(color)), (rest, attrs.clone())],
+            &attrs,
+            Shaping::Advanced,
+            None,
+        );
+    }
 }
 
 impl Component for Hint {
@@ -473,8 +805,12 @@ impl Component for Hint {
         self.id
     }
 
-    fn get_app_name(&self) -> &str {
-        &self.app_name
+    fn get_style_key(&self) -> &StyleKey {
+        &self.style_key
+    }
+
+    fn set_style_key(&mut self, style_key: StyleKey) {
+        self.style_key = style_key;
     }
 
     fn get_ui_state(&self) -> &UiState {
@@ -537,7 +873,7 @@ impl Component for Hint {
             border_size: style.border.size.into(),
             border_color: style.border.color.to_linear(urgency),
             scale: self.ui_state.scale.load(Ordering::Relaxed),
-            depth: 0.7,
+            depth: Layer::Hint.depth(),
         }]
     }
 
@@ -591,12 +927,192 @@ impl Component for Hint {
     }
 }
 
+/// Background of a labelled button drawn within `bounds`.
+fn background(
+    style: &ButtonState,
+    bounds: Bounds,
+    urgency: &Urgency,
+    scale: f32,
+) -> buffers::Instance {
+    buffers::Instance {
+        rect_pos: [bounds.x, bounds.y],
+        rect_size: [
+            bounds.width - style.border.size.left - style.border.size.right,
+            bounds.height - style.border.size.top - style.border.size.bottom,
+        ],
+        rect_color: style.background.to_linear(urgency),
+        border_radius: style.border.radius.into(),
+        border_size: style.border.size.into(),
+        border_color: style.border.color.to_linear(urgency),
+        scale,
+        depth: Layer::Element.depth(),
+    }
+}
+
+/// Label of a button drawn within `extents`, centered along the sides with automatic padding.
+fn label<'a>(
+    style: &ButtonState,
+    extents: Bounds,
+    text: &'a text_renderer::Text,
+    urgency: &Urgency,
+    scale: f32,
+) -> TextArea<'a> {
+    let text_extents = text.get_bounds();
+
+    let remaining_padding = extents.width - text_extents.width;
+    let pl = match (style.padding.left.is_auto(), style.padding.right.is_auto()) {
+        (true, true) => remaining_padding / 2.,
+        (true, false) => remaining_padding,
+        _ => style.padding.left.resolve(0.),
+    };
+
+    let remaining_padding = extents.height - text_extents.height;
+    let pt = match (style.padding.top.is_auto(), style.padding.bottom.is_auto()) {
+        (true, true) => remaining_padding / 2.,
+        (true, false) => remaining_padding,
+        _ => style.padding.top.resolve(0.),
+    };
+
+    let left = extents.x + style.border.size.left + style.padding.left.resolve(pl);
+    let top = extents.y + style.border.size.top + style.padding.top.resolve(pt);
+    TextArea {
+        buffer: &text.buffer,
+        left,
+        top,
+        scale,
+        bounds: glyphon::TextBounds {
+            left: left as i32,
+            top: top as i32,
+            right: (left + text_extents.width) as i32,
+            bottom: (top + text_extents.height) as i32,
+        },
+        custom_glyphs: &[],
+        default_color: style.font.color.into_glyphon(urgency),
+    }
+}
+
+/// Bounds of a button at `x`, `y` whose content takes up `width` by `height` unless its style
+/// sets a size, margins included.
+fn outer_bounds(style: &ButtonState, x: f32, y: f32, width: f32, height: f32) -> Bounds {
+    Bounds {
+        x,
+        y,
+        width: style.width.resolve(width)
+            + style.border.size.left
+            + style.border.size.right
+            + style.padding.left
+            + style.padding.right
+            + style.margin.left
+            + style.margin.right,
+        height: style.height.resolve(height)
+            + style.border.size.top
+            + style.border.size.bottom
+            + style.padding.top
+            + style.padding.bottom
+            + style.margin.top
+            + style.margin.bottom,
+    }
+}
+
+/// `bounds` without the margins of the button.
+fn without_margin(style: &ButtonState, bounds: Bounds) -> Bounds {
+    Bounds {
+        x: bounds.x + style.margin.left,
+        y: bounds.y + style.margin.top,
+        width: bounds.width - style.margin.left - style.margin.right,
+        height: bounds.height - style.margin.top - style.margin.bottom,
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::ButtonManager;
-    use crate::{manager::UiState, Urgency};
+    use super::{ButtonManager, ButtonType, ImageData, State};
+    use crate::{
+        components::{text::body::Anchor, Bounds},
+        config::{keymaps::Mode, StyleKey},
+        manager::UiState,
+        History, Urgency,
+    };
     use glyphon::FontSystem;
-    use std::sync::Arc;
+    use std::sync::{atomic::Ordering, Arc};
+
+    #[test]
+    fn test_dismiss_options() {
+        let mut font_system = FontSystem::new();
+        let manager = |config: crate::config::Config, font_system: &mut FontSystem| {
+            ButtonManager::new(
+                1,
+                Urgency::Normal,
+                StyleKey::default(),
+                UiState::default(),
+                None,
+                Arc::new(config),
+                History::Hidden,
+            )
+            .add_dismiss(font_system)
+            .finish(font_system)
+        };
+
+        let mut config = crate::config::Config::default();
+        config.general.buttons.dismiss.label = "Close".into();
+        let button_manager = manager(config, &mut font_system);
+        let text_areas = button_manager.text_areas();
+        let label: String = text_areas[0]
+            .buffer
+            .layout_runs()
+            .map(|run| run.text)
+            .collect();
+        assert_eq!(label, "Close");
+
+        let mut config = crate::config::Config::default();
+        config.general.buttons.dismiss.enabled = false;
+        let button_manager = manager(config, &mut font_system);
+        assert!(button_manager.buttons().is_empty());
+    }
+
+    #[test]
+    fn test_focus_cycles_through_buttons() {
+        let config = Arc::new(crate::config::Config::default());
+        let mut font_system = FontSystem::new();
+        let actions = [
+            (Arc::from("open"), Arc::from("Open")),
+            (Arc::from("archive"), Arc::from("Archive")),
+        ];
+
+        let mut button_manager = ButtonManager::new(
+            1,
+            Urgency::Normal,
+            StyleKey::default(),
+            UiState::default(),
+            None,
+            config,
+            History::Hidden,
+        )
+        .add_dismiss(&mut font_system)
+        .add_actions(&actions, false, &mut font_system)
+        .finish(&mut font_system);
+
+        assert_eq!(button_manager.focused(), None);
+        assert!(!button_manager.activate_focused());
+        let unfocused = button_manager.instances().len();
+
+        button_manager.focus_next();
+        assert_eq!(button_manager.focused(), Some(ButtonType::Dismiss));
+        assert_eq!(button_manager.instances().len(), unfocused + 1);
+
+        button_manager.focus_next();
+        button_manager.focus_next();
+        assert_eq!(button_manager.focused(), Some(ButtonType::Action));
+        button_manager.focus_next();
+        assert_eq!(button_manager.focused(), Some(ButtonType::Dismiss));
+
+        button_manager.focus_prev();
+        assert_eq!(button_manager.focused(), Some(ButtonType::Action));
+        assert!(button_manager.activate_focused());
+
+        button_manager.unfocus();
+        assert_eq!(button_manager.instances().len(), unfocused);
+    }
 
     #[test]
     fn test_button_click_detection() {
@@ -607,10 +1123,11 @@ mod tests {
         let mut button_manager = ButtonManager::new(
             1,
             Urgency::Normal,
-            "".into(),
+            StyleKey::default(),
             ui_state,
             None,
             Arc::clone(&config),
+            History::Hidden,
         )
         .add_dismiss(&mut font_system)
         .finish(&mut font_system);
@@ -672,10 +1189,11 @@ mod tests {
         let mut button_manager = ButtonManager::new(
             1,
             Urgency::Normal,
-            "".into(),
+            StyleKey::default(),
             ui_state,
             None,
             Arc::clone(&config),
+            History::Hidden,
         )
         .add_dismiss(&mut font_system)
         .finish(&mut font_system);
@@ -727,4 +1245,279 @@ mod tests {
                 );
             });
     }
+
+    #[test]
+    fn test_hover_moves_between_buttons() {
+        let config = Arc::new(crate::config::Config::default());
+        let mut font_system = FontSystem::new();
+        let actions: [(Arc<str>, Arc<str>); 1] = [("open".into(), "Open".into())];
+
+        let mut button_manager = ButtonManager::new(
+            1,
+            Urgency::Normal,
+            StyleKey::default(),
+            UiState::default(),
+            None,
+            Arc::clone(&config),
+            History::Hidden,
+        )
+        .add_dismiss(&mut font_system)
+        .add_actions(&actions, false, &mut font_system)
+        .finish(&mut font_system);
+
+        button_manager
+            .buttons_mut()
+            .iter_mut()
+            .for_each(|button| match button.button_type() {
+                ButtonType::Dismiss => button.set_position(0., 0.),
+                _ => button.set_position(500., 500.),
+            });
+
+        let state = |button_manager: &ButtonManager<_>, button_type| {
+            button_manager
+                .buttons()
+                .iter()
+                .find(|button| button.button_type() == button_type)
+                .map(|button| button.state())
+        };
+
+        assert!(button_manager.hover(501., 501.));
+        assert_eq!(
+            state(&button_manager, ButtonType::Action),
+            Some(State::Hovered)
+        );
+
+        assert!(button_manager.hover(1., 1.));
+        assert_eq!(
+            state(&button_manager, ButtonType::Dismiss),
+            Some(State::Hovered)
+        );
+        assert_eq!(
+            state(&button_manager, ButtonType::Action),
+            Some(State::Unhovered)
+        );
+
+        assert!(!button_manager.hover(-10., -10.));
+        assert_eq!(
+            state(&button_manager, ButtonType::Dismiss),
+            Some(State::Unhovered)
+        );
+    }
+
+    #[test]
+    fn test_history_actions_have_no_hints() {
+        let config = Arc::new(crate::config::Config::default());
+        let mut font_system = FontSystem::new();
+        let actions: [(Arc<str>, Arc<str>); 1] = [("open".into(), "Open".into())];
+        let (tx, rx) = calloop::channel::channel();
+
+        let button_manager = ButtonManager::new(
+            1,
+            Urgency::Normal,
+            StyleKey::default(),
+            UiState::default(),
+            Some(tx),
+            Arc::clone(&config),
+            History::Shown,
+        )
+        .add_dismiss(&mut font_system)
+        .add_actions(&actions, false, &mut font_system)
+        .finish(&mut font_system);
+
+        let action = button_manager
+            .buttons()
+            .iter()
+            .find(|button| button.button_type() == ButtonType::Action)
+            .unwrap();
+        assert!(action.hint().combination.is_empty());
+
+        action.click();
+        assert!(rx.try_recv().is_err());
+
+        let dismiss = button_manager
+            .buttons()
+            .iter()
+            .find(|button| button.button_type() == ButtonType::Dismiss)
+            .unwrap();
+        assert!(!dismiss.hint().combination.is_empty());
+    }
+
+    #[test]
+    fn test_truncated_anchors_have_no_hints() {
+        let config = Arc::new(crate::config::Config::default());
+        let mut font_system = FontSystem::new();
+        let anchor = |line, width| {
+            Arc::new(Anchor {
+                href: "https://example.com".into(),
+                line,
+                start: 0,
+                end: 4,
+                bounds: Bounds {
+                    width,
+                    height: 10.,
+                    ..Default::default()
+                },
+            })
+        };
+        let hidden = [anchor(0, 20.), anchor(1, 0.)];
+
+        let mut button_manager = ButtonManager::new(
+            1,
+            Urgency::Normal,
+            StyleKey::default(),
+            UiState::default(),
+            None,
+            Arc::clone(&config),
+            History::Hidden,
+        )
+        .add_dismiss(&mut font_system)
+        .add_anchors(&hidden, &mut font_system)
+        .finish(&mut font_system);
+
+        let hints = |button_manager: &ButtonManager<_>| -> Vec<Box<str>> {
+            button_manager
+                .buttons()
+                .iter()
+                .filter(|button| button.button_type() == ButtonType::Anchor)
+                .map(|button| button.hint().combination.clone())
+                .collect()
+        };
+        let collapsed = hints(&button_manager);
+        assert!(!collapsed[0].is_empty());
+        assert!(collapsed[1].is_empty());
+
+        // Expanding brings the second line, and its anchor, into view
+        button_manager.set_anchors(&[anchor(0, 20.), anchor(1, 20.)]);
+        button_manager.assign_hints(&mut font_system);
+        let expanded = hints(&button_manager);
+        assert!(expanded.iter().all(|hint| !hint.is_empty()));
+        assert_ne!(expanded[0], expanded[1]);
+    }
+
+    #[test]
+    fn test_typed_hint_narrows_hints() {
+        let mut config = crate::config::Config::default();
+        config.general.hint_characters = "ab".into();
+        let mut font_system = FontSystem::new();
+        let actions: [(Arc<str>, Arc<str>); 2] = [
+            ("open".into(), "Open".into()),
+            ("archive".into(), "Archive".into()),
+        ];
+        let ui_state = UiState::default();
+        ui_state.mode.store(Mode::Hint, Ordering::Relaxed);
+        ui_state.selected.store(true, Ordering::Relaxed);
+        ui_state.selected_id.store(1, Ordering::Relaxed);
+
+        let mut button_manager = ButtonManager::new(
+            1,
+            Urgency::Normal,
+            StyleKey::default(),
+            ui_state.clone(),
+            None,
+            Arc::new(config),
+            History::Hidden,
+        )
+        .add_dismiss(&mut font_system)
+        .add_actions(&actions, false, &mut font_system)
+        .finish(&mut font_system);
+
+        let combinations: Vec<_> = button_manager
+            .buttons()
+            .iter()
+            .map(|button| &*button.hint().combination)
+            .collect();
+        assert_eq!(combinations, ["aa", "ab", "ba"]);
+        assert_eq!(button_manager.shown_hints().count(), 3);
+
+        assert!(button_manager.highlight_hints("a", &mut font_system));
+        *ui_state.typed_hint.lock().unwrap() = "a".into();
+        let shown: Vec<_> = button_manager
+            .shown_hints()
+            .map(|hint| (&*hint.combination, hint.matched))
+            .collect();
+        assert_eq!(shown, [("aa", 1), ("ab", 1)]);
+
+        assert!(!button_manager.highlight_hints("bb", &mut font_system));
+        assert!(!button_manager.hint("bb"));
+        assert!(button_manager.hint("ab"));
+    }
+
+    #[test]
+    fn test_action_icons_fall_back_to_label() {
+        let config = Arc::new(crate::config::Config::default());
+        let mut font_system = FontSystem::new();
+        let actions: [(Arc<str>, Arc<str>); 2] = [
+            ("moxnotify-test-missing-icon".into(), "Open".into()),
+            ("moxnotify-test-other-missing-icon".into(), "Close".into()),
+        ];
+
+        let mut button_manager = ButtonManager::new(
+            1,
+            Urgency::Normal,
+            StyleKey::default(),
+            UiState::default(),
+            None,
+            Arc::clone(&config),
+            History::Hidden,
+        )
+        .add_dismiss(&mut font_system)
+        .add_actions(&actions, true, &mut font_system)
+        .finish(&mut font_system);
+
+        button_manager.set_action_widths(200.);
+
+        let actions = button_manager
+            .buttons()
+            .iter()
+            .filter(|button| button.button_type() == ButtonType::Action)
+            .collect::<Vec<_>>();
+        assert_eq!(actions.len(), 2);
+        actions.iter().for_each(|action| {
+            assert!(action.get_textures().is_empty());
+            assert_eq!(action.get_text_areas(&Urgency::Normal).len(), 1);
+            assert_eq!(action.get_bounds().width, 100.);
+        });
+    }
+
+    #[test]
+    fn test_set_action_icon() {
+        let config = Arc::new(crate::config::Config::default());
+        let mut font_system = FontSystem::new();
+        let actions: [(Arc<str>, Arc<str>); 2] = [
+            ("moxnotify-test-missing-icon".into(), "Open".into()),
+            ("moxnotify-test-other-missing-icon".into(), "Close".into()),
+        ];
+
+        let mut button_manager = ButtonManager::new(
+            1,
+            Urgency::Normal,
+            StyleKey::default(),
+            UiState::default(),
+            None,
+            Arc::clone(&config),
+            History::Hidden,
+        )
+        .add_dismiss(&mut font_system)
+        .add_actions(&actions, true, &mut font_system)
+        .finish(&mut font_system);
+
+        let size = config.general.app_icon_size;
+        let icon = ImageData::try_from(image::DynamicImage::new_rgba8(size, size)).unwrap();
+        assert!(!button_manager.set_action_icon("moxnotify-test-unknown", icon.clone()));
+        assert!(button_manager.set_action_icon("moxnotify-test-missing-icon", icon));
+
+        // The one that got its icon takes its size, the other one the rest of the row
+        button_manager.set_action_widths(200.);
+        let actions = button_manager
+            .buttons()
+            .iter()
+            .filter(|button| button.button_type() == ButtonType::Action)
+            .collect::<Vec<_>>();
+        assert_eq!(actions[0].get_textures().len(), 1);
+        assert!(actions[0].get_text_areas(&Urgency::Normal).is_empty());
+        let icon_width = actions[0].get_bounds().width;
+        assert!(icon_width >= size as f32);
+        assert_eq!(actions[1].get_text_areas(&Urgency::Normal).len(), 1);
+        assert_eq!(actions[1].get_bounds().width, 200. - icon_width);
+    }
 }
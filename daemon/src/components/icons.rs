@@ -1,45 +1,93 @@
 use crate::{
-    components::{Bounds, Component},
-    config::{Config, StyleState},
+    components::{Bounds, Component, Layer},
+    config::{Anchor, Config, StyleKey, StyleState},
     manager::UiState,
     rendering::texture_renderer::{self, TextureArea, TextureBounds},
     utils::buffers,
-    utils::image_data::ImageData,
-    Image,
+    utils::image_data::{ImageData, ImageFrame},
+    Event, Image,
 };
+use image::DynamicImage;
 use resvg::usvg;
 use std::{
-    collections::BTreeMap,
+    collections::VecDeque,
     path::Path,
     sync::{atomic::Ordering, Arc, LazyLock, Mutex},
+    time::Duration,
 };
 
 use super::Data;
 
-static ICON_CACHE: LazyLock<Cache> = LazyLock::new(Cache::default);
-type IconMap = BTreeMap<Box<Path>, ImageData>;
+static ICON_CACHE: LazyLock<IconCache> = LazyLock::new(IconCache::default);
 
-#[derive(Default)]
-pub struct Cache(Mutex<IconMap>);
+/// Frames of the animated image files decoded with `general.animated_images`, still ones are
+/// kept without frames so that they aren't decoded again either.
+static FRAME_CACHE: LazyLock<IconCache<Arc<[ImageFrame]>>> =
+    LazyLock::new(|| IconCache::with_capacity(FRAME_CACHE_CAPACITY));
 
-impl Cache {
-    pub fn insert<P>(&self, icon_path: &P, data: ImageData)
-    where
-        P: AsRef<Path>,
-    {
-        let mut icon_map = self.0.lock().unwrap();
-        let entry = icon_path.as_ref();
+/// Maximum number of decoded icons kept around, least recently used ones are evicted first.
+const ICON_CACHE_CAPACITY: usize = 100;
+
+/// Maximum number of animated images whose frames are kept around.
+const FRAME_CACHE_CAPACITY: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IconKey {
+    /// Icon name for themed lookups or file path for icons loaded from disk.
+    name: Box<str>,
+    size: u16,
+    theme: Option<Box<str>>,
+}
 
-        icon_map.insert(entry.into(), data);
+impl IconKey {
+    pub fn new(name: &str, size: u16, theme: Option<&str>) -> Self {
+        Self {
+            name: name.into(),
+            size,
+            theme: theme.map(Into::into),
+        }
     }
+}
 
-    pub fn get<P>(&self, icon_path: P) -> Option<ImageData>
-    where
-        P: AsRef<Path>,
-    {
-        let theme_map = self.0.lock().unwrap();
+pub struct IconCache<T = ImageData> {
+    capacity: usize,
+    /// Ordered from least to most recently used.
+    entries: Mutex<VecDeque<(IconKey, T)>>,
+}
 
-        theme_map.get(icon_path.as_ref()).cloned()
+impl Default for IconCache {
+    fn default() -> Self {
+        Self::with_capacity(ICON_CACHE_CAPACITY)
+    }
+}
+
+impl<T: Clone> IconCache<T> {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    pub fn insert(&self, key: IconKey, data: T) {
+        let mut entries = self.entries.lock().unwrap();
+
+        entries.retain(|(entry, _)| *entry != key);
+        if entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back((key, data));
+    }
+
+    pub fn get(&self, key: &IconKey) -> Option<T> {
+        let mut entries = self.entries.lock().unwrap();
+
+        let index = entries.iter().position(|(entry, _)| entry == key)?;
+        let entry = entries.remove(index)?;
+        let data = entry.1.clone();
+        entries.push_back(entry);
+
+        Some(data)
     }
 }
 
@@ -52,7 +100,12 @@ pub struct Icons {
     y: f32,
     ui_state: UiState,
     config: Arc<Config>,
-    app_name: Arc<str>,
+    style_key: StyleKey,
+    /// Image file that's still to be decoded, a placeholder is shown until then.
+    pending: Option<Box<Path>>,
+    /// Frames of an animated image with `general.animated_images`, `icon` is the one shown.
+    frames: Arc<[ImageFrame]>,
+    frame: usize,
 }
 
 impl Icons {
@@ -62,8 +115,13 @@ impl Icons {
         app_icon: Option<&str>,
         config: Arc<Config>,
         ui_state: UiState,
-        app_name: Arc<str>,
+        style_key: StyleKey,
     ) -> Self {
+        let icon_size = config.general.icon_size as u16;
+        let theme = config.general.theme.as_deref();
+        let mut pending = None;
+        let mut frames = Arc::default();
+
         let icon = match image {
             Some(Image::Data(image_data)) => Some(
                 image_data
@@ -71,39 +129,167 @@ impl Icons {
                     .to_rgba()
                     .resize(config.general.icon_size),
             ),
-            Some(Image::File(file)) => get_icon(file, config.general.icon_size as u16),
-            Some(Image::Name(name)) => find_icon(
-                name,
-                config.general.icon_size as u16,
-                config.general.theme.as_ref(),
-            ),
+            Some(Image::File(file)) => {
+                let key = IconKey::new(&file.to_string_lossy(), icon_size, None);
+                let cached_frames = match config.general.animated_images {
+                    true => FRAME_CACHE.get(&key),
+                    false => Some(Arc::default()),
+                };
+                let cached = match cached_frames.as_deref() {
+                    Some([first, ..]) => Some(first.image.clone()),
+                    _ => ICON_CACHE.get(&key),
+                };
+                match (cached.is_some(), cached_frames) {
+                    (true, Some(cached_frames)) => frames = cached_frames,
+                    _ => pending = Some(file.clone()),
+                }
+
+                cached.or_else(|| {
+                    find_icon(LOADING_ICON, icon_size, theme).or_else(|| {
+                        let size = icon_size as u32;
+                        ImageData::try_from(DynamicImage::new_rgba8(size, size)).ok()
+                    })
+                })
+            }
+            Some(Image::Name(name)) => find_icon(name, icon_size, theme)
+                .or_else(|| find_icon(MISSING_ICON, icon_size, theme)),
             _ => None,
         };
 
-        let app_icon = app_icon.as_ref().and_then(|icon| {
-            find_icon(
-                icon,
-                config.general.icon_size as u16,
-                config.general.theme.as_deref().as_ref(),
-            )
+        let sizes = (config.general.icon_size, config.general.app_icon_size);
+        let (icon, app_icon) = arrange(icon, sizes, |size| {
+            app_icon.and_then(|icon| find_icon(icon, size as u16, theme))
         });
 
-        let (final_app_icon, final_icon) = match icon.is_some() {
-            true => (app_icon, icon),
-            false => (None, app_icon),
-        };
-
         Self {
             id,
-            icon: final_icon,
-            app_icon: final_app_icon,
+            icon,
+            app_icon,
             x: 0.,
             y: 0.,
             ui_state,
             config,
-            app_name,
+            style_key,
+            pending,
+            frames,
+            frame: 0,
         }
     }
+
+    /// Decodes the pending image file off the main thread, the result comes back to the event
+    /// loop as `Event::IconLoaded`. Without a sender the image is decoded right away.
+    pub fn load_pending(&mut self, sender: Option<&calloop::channel::Sender<Event>>) {
+        let Some(path) = self.pending.take() else {
+            return;
+        };
+
+        let icon_size = self.config.general.icon_size as u16;
+        let max_size = (icon_size as f32 * self.ui_state.scale.load(Ordering::Relaxed)).ceil();
+        let animated = self.config.general.animated_images;
+        let load = move || {
+            let frames = match animated {
+                true => get_frames(&path, icon_size, max_size as u32),
+                false => Arc::default(),
+            };
+            let icon = match frames.first() {
+                Some(frame) => Some(frame.image.clone()),
+                None => get_icon(&path, icon_size, max_size as u32),
+            };
+            (path, icon, frames)
+        };
+
+        match sender {
+            Some(sender) => {
+                let (id, sender) = (self.id, sender.clone());
+                rayon::spawn(move || {
+                    let (path, icon, frames) = load();
+                    _ = sender.send(Event::IconLoaded {
+                        id,
+                        path,
+                        icon: icon.map(Box::new),
+                        frames,
+                    });
+                });
+            }
+            None => {
+                let (_, icon, frames) = load();
+                self.set_icon(icon, frames);
+            }
+        }
+    }
+
+    /// Replaces the image, with `frames` to cycle through if it's animated.
+    pub fn set_icon(&mut self, icon: Option<ImageData>, frames: Arc<[ImageFrame]>) {
+        let icon_size = self.config.general.icon_size as u16;
+        self.frames = frames;
+        self.frame = 0;
+        self.icon = icon.or_else(|| {
+            find_icon(
+                MISSING_ICON,
+                icon_size,
+                self.config.general.theme.as_deref(),
+            )
+        });
+    }
+
+    /// How long the frame shown is shown for, None unless the image is animated.
+    pub fn frame_delay(&self) -> Option<Duration> {
+        self.frames.get(self.frame).map(|frame| frame.delay)
+    }
+
+    /// Moves on to the next frame of an animated image, starting over after the last one.
+    /// Returns how long that frame is shown for.
+    pub fn next_frame(&mut self) -> Option<Duration> {
+        if self.frames.is_empty() {
+            return None;
+        }
+
+        self.frame = (self.frame + 1) % self.frames.len();
+        let frame = &self.frames[self.frame];
+        self.icon = Some(frame.image.clone());
+
+        Some(frame.delay)
+    }
+
+    /// Where the app icon is drawn over the image, `None` unless both are shown.
+    fn app_icon_bounds(&self) -> Option<Bounds> {
+        self.app_icon.as_ref()?;
+        self.icon.as_ref()?;
+
+        let style = self.config.find_style(
+            &self.style_key,
+            self.ui_state.selected_id.load(Ordering::Relaxed) == self.id
+                && self.ui_state.selected.load(Ordering::Relaxed),
+        );
+        let image = self.get_render_bounds();
+        let size = self.config.general.app_icon_size as f32;
+
+        let x = match style.app_icon.anchor {
+            Anchor::TopLeft | Anchor::CenterLeft | Anchor::BottomLeft => image.x,
+            Anchor::TopCenter | Anchor::Center | Anchor::BottomCenter => {
+                image.x + (image.width - size) / 2.
+            }
+            Anchor::TopRight | Anchor::CenterRight | Anchor::BottomRight => {
+                image.x + image.width - size
+            }
+        };
+        let y = match style.app_icon.anchor {
+            Anchor::TopLeft | Anchor::TopCenter | Anchor::TopRight => image.y,
+            Anchor::CenterLeft | Anchor::Center | Anchor::CenterRight => {
+                image.y + (image.height - size) / 2.
+            }
+            Anchor::BottomLeft | Anchor::BottomCenter | Anchor::BottomRight => {
+                image.y + image.height - size
+            }
+        };
+
+        Some(Bounds {
+            x,
+            y,
+            width: size,
+            height: size,
+        })
+    }
 }
 
 impl Component for Icons {
@@ -117,8 +303,12 @@ impl Component for Icons {
         self.id
     }
 
-    fn get_app_name(&self) -> &str {
-        &self.app_name
+    fn get_style_key(&self) -> &StyleKey {
+        &self.style_key
+    }
+
+    fn set_style_key(&mut self, style_key: StyleKey) {
+        self.style_key = style_key;
     }
 
     fn get_ui_state(&self) -> &UiState {
@@ -131,7 +321,7 @@ impl Component for Icons {
 
     fn get_bounds(&self) -> Bounds {
         let style = self.config.find_style(
-            &self.app_name,
+            &self.style_key,
             self.ui_state.selected_id.load(Ordering::Relaxed) == self.id
                 && self.ui_state.selected.load(Ordering::Relaxed),
         );
@@ -165,7 +355,7 @@ impl Component for Icons {
 
     fn get_render_bounds(&self) -> Bounds {
         let style = self.config.find_style(
-            &self.app_name,
+            &self.style_key,
             self.ui_state.selected_id.load(Ordering::Relaxed) == self.id
                 && self.ui_state.selected.load(Ordering::Relaxed),
         );
@@ -206,14 +396,13 @@ impl Component for Icons {
         let mut texture_areas = Vec::new();
 
         let style = self.config.find_style(
-            &self.app_name,
+            &self.style_key,
             self.ui_state.selected_id.load(Ordering::Relaxed) == self.id
                 && self.ui_state.selected.load(Ordering::Relaxed),
         );
 
-        let mut bounds = self.get_render_bounds();
-
         if let Some(icon) = self.icon.as_ref() {
+            let bounds = self.get_render_bounds();
             texture_areas.push(TextureArea {
                 left: bounds.x,
                 top: bounds.y,
@@ -228,33 +417,32 @@ impl Component for Icons {
                     bottom: (bounds.y + bounds.height) as u32,
                 },
                 data: icon.data(),
+                data_size: icon.size(),
                 radius: style.icon.border.radius.into(),
-                depth: 0.9,
+                depth: Layer::Icon.depth(),
+                opacity: 1.,
             });
-
-            bounds.x += bounds.height - self.config.general.app_icon_size as f32;
-            bounds.y += bounds.height - self.config.general.app_icon_size as f32;
         }
 
-        if let Some(app_icon) = self.app_icon.as_ref() {
-            let app_icon_size = self.config.general.app_icon_size as f32;
-
+        if let (Some(app_icon), Some(bounds)) = (self.app_icon.as_ref(), self.app_icon_bounds()) {
             texture_areas.push(TextureArea {
                 left: bounds.x,
                 top: bounds.y,
-                width: app_icon_size,
-                height: app_icon_size,
+                width: bounds.width,
+                height: bounds.height,
                 scale: self.ui_state.scale.load(Ordering::Relaxed),
-                border_size: style.icon.border.size.into(),
+                border_size: style.app_icon.border.size.into(),
                 bounds: TextureBounds {
                     left: bounds.x as u32,
                     top: bounds.y as u32,
-                    right: (bounds.x + app_icon_size) as u32,
-                    bottom: (bounds.y + app_icon_size) as u32,
+                    right: (bounds.x + bounds.width) as u32,
+                    bottom: (bounds.y + bounds.height) as u32,
                 },
                 data: app_icon.data(),
+                data_size: app_icon.size(),
                 radius: style.app_icon.border.radius.into(),
-                depth: 0.8,
+                depth: Layer::AppIcon.depth(),
+                opacity: 1.,
             });
         }
 
@@ -266,28 +454,97 @@ impl Component for Icons {
     }
 }
 
-fn find_icon<T>(name: T, icon_size: u16, theme: Option<T>) -> Option<ImageData>
-where
-    T: AsRef<str>,
-{
-    let icon_path = freedesktop_icons::lookup(name.as_ref())
+/// Splits what's shown into the main visual and the emblem over it. The image is the main
+/// visual when there's one, with the app icon loaded at `app_icon_size` as its emblem,
+/// otherwise the app icon takes its place at full size.
+fn arrange(
+    image: Option<ImageData>,
+    (icon_size, app_icon_size): (u32, u32),
+    app_icon: impl FnOnce(u32) -> Option<ImageData>,
+) -> (Option<ImageData>, Option<ImageData>) {
+    match image {
+        Some(image) => (Some(image), app_icon(app_icon_size)),
+        None => (app_icon(icon_size), None),
+    }
+}
+
+/// Icon shown in place of images that can't be found or decoded.
+const MISSING_ICON: &str = "image-missing";
+
+/// Icon shown while an image file is being decoded.
+const LOADING_ICON: &str = "image-loading";
+
+/// Icon `name` if it was found before, without looking it up.
+pub fn cached_icon(name: &str, icon_size: u16, theme: Option<&str>) -> Option<ImageData> {
+    ICON_CACHE.get(&IconKey::new(name, icon_size, theme))
+}
+
+pub fn find_icon(name: &str, icon_size: u16, theme: Option<&str>) -> Option<ImageData> {
+    if let Some(icon) = cached_icon(name, icon_size, theme) {
+        return Some(icon);
+    }
+    let key = IconKey::new(name, icon_size, theme);
+
+    // Lookup falls back to hicolor on its own when the icon isn't part of the configured theme
+    let icon_path = freedesktop_icons::lookup(name)
         .with_size(icon_size)
-        .with_theme(theme.as_ref().map(AsRef::as_ref).unwrap_or("hicolor"))
+        .with_theme(theme.unwrap_or("hicolor"))
         .force_svg()
         .with_cache()
         .find()?;
 
-    get_icon(&icon_path, icon_size)
+    let image_data = load_icon(&icon_path, icon_size, icon_size as u32)?;
+    ICON_CACHE.insert(key, image_data.clone());
+    Some(image_data)
 }
 
-pub fn get_icon<T>(icon_path: T, icon_size: u16) -> Option<ImageData>
+/// Frames of an animated image file at `icon_size`, none if it isn't animated or can't be
+/// decoded.
+fn get_frames<T>(icon_path: T, icon_size: u16, max_size: u32) -> Arc<[ImageFrame]>
 where
     T: AsRef<Path>,
 {
-    if let Some(icon) = ICON_CACHE.get(icon_path.as_ref()) {
+    let key = IconKey::new(&icon_path.as_ref().to_string_lossy(), icon_size, None);
+    if let Some(frames) = FRAME_CACHE.get(&key) {
+        return frames;
+    }
+
+    let frames: Arc<[ImageFrame]> = match ImageData::open_frames(icon_path, max_size) {
+        Ok(frames) => frames
+            .into_iter()
+            .map(|frame| ImageFrame {
+                image: frame.image.to_rgba().resize(icon_size as u32),
+                ..frame
+            })
+            .collect(),
+        Err(e) => {
+            log::warn!("Failed to load animated image: {e}");
+            return Arc::default();
+        }
+    };
+    FRAME_CACHE.insert(key, Arc::clone(&frames));
+    frames
+}
+
+/// Loads an image file, raster images are first shrunk to fit within `max_size`.
+pub fn get_icon<T>(icon_path: T, icon_size: u16, max_size: u32) -> Option<ImageData>
+where
+    T: AsRef<Path>,
+{
+    let key = IconKey::new(&icon_path.as_ref().to_string_lossy(), icon_size, None);
+    if let Some(icon) = ICON_CACHE.get(&key) {
         return Some(icon);
     }
 
+    let image_data = load_icon(icon_path, icon_size, max_size)?;
+    ICON_CACHE.insert(key, image_data.clone());
+    Some(image_data)
+}
+
+fn load_icon<T>(icon_path: T, icon_size: u16, max_size: u32) -> Option<ImageData>
+where
+    T: AsRef<Path>,
+{
     let image = if icon_path
         .as_ref()
         .extension()
@@ -314,36 +571,67 @@ where
             &mut pixmap.as_mut(),
         );
 
-        image::load_from_memory(&pixmap.encode_png().ok()?)
+        ImageData::try_from(image::load_from_memory(&pixmap.encode_png().ok()?).ok()?).ok()?
     } else {
-        image::open(icon_path.as_ref())
+        ImageData::open(icon_path, max_size)
+            .inspect_err(|e| log::warn!("Failed to load image: {e}"))
+            .ok()?
     };
 
-    let image_data = ImageData::try_from(image.ok()?);
-    let image_data = image_data
-        .ok()
-        .map(|i| i.to_rgba().resize(icon_size as u32))?;
-    ICON_CACHE.insert(&icon_path, image_data.clone());
-    Some(image_data)
+    Some(image.to_rgba().resize(icon_size as u32))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use image::{DynamicImage, RgbaImage};
-    use std::path::{Path, PathBuf};
     use std::sync::Arc;
 
+    fn image(size: u32) -> ImageData {
+        ImageData::try_from(DynamicImage::ImageRgba8(RgbaImage::new(size, size))).unwrap()
+    }
+
     #[test]
     fn cache_insert_and_retrieve() {
-        let cache = Cache::default();
-        let path = PathBuf::from("test_icon.png");
+        let cache = IconCache::default();
+        let key = IconKey::new("test_icon.png", 32, None);
 
-        let img = RgbaImage::new(32, 32);
-        let data = ImageData::try_from(DynamicImage::ImageRgba8(img)).unwrap();
+        let data = image(32);
 
-        cache.insert(&path, data.clone());
-        assert_eq!(cache.get(&path).unwrap(), data);
+        cache.insert(key.clone(), data.clone());
+        assert_eq!(cache.get(&key).unwrap(), data);
+    }
+
+    #[test]
+    fn cache_key_includes_size_and_theme() {
+        let cache = IconCache::default();
+        cache.insert(IconKey::new("firefox", 32, Some("Papirus")), image(32));
+
+        assert!(cache
+            .get(&IconKey::new("firefox", 32, Some("Papirus")))
+            .is_some());
+        assert!(cache
+            .get(&IconKey::new("firefox", 64, Some("Papirus")))
+            .is_none());
+        assert!(cache.get(&IconKey::new("firefox", 32, None)).is_none());
+    }
+
+    #[test]
+    fn cache_evicts_least_recently_used() {
+        let cache = IconCache::with_capacity(2);
+        let first = IconKey::new("first", 16, None);
+        let second = IconKey::new("second", 16, None);
+        let third = IconKey::new("third", 16, None);
+
+        cache.insert(first.clone(), image(16));
+        cache.insert(second.clone(), image(16));
+        assert!(cache.get(&first).is_some());
+
+        cache.insert(third.clone(), image(16));
+        assert_eq!(cache.entries.lock().unwrap().len(), 2);
+        assert!(cache.get(&first).is_some());
+        assert!(cache.get(&second).is_none());
+        assert!(cache.get(&third).is_some());
     }
 
     #[test]
@@ -355,7 +643,7 @@ mod tests {
         let image_data = ImageData::try_from(DynamicImage::ImageRgba8(img)).unwrap();
 
         let image = Image::Data(image_data.clone());
-        let icons = Icons::new(1, Some(&image), None, config, ui_state, Arc::from("app"));
+        let icons = Icons::new(1, Some(&image), None, config, ui_state, StyleKey::default());
 
         assert!(icons.icon.is_some());
         assert_eq!(icons.icon.unwrap().width(), 64);
@@ -363,9 +651,79 @@ mod tests {
 
     #[test]
     fn cache_miss_returns_none() {
-        let cache = Cache::default();
-        let non_existent_path = Path::new("non_existent.png");
-        assert!(cache.get(non_existent_path).is_none());
+        let cache = IconCache::default();
+        let non_existent = IconKey::new("non_existent.png", 32, None);
+        assert!(cache.get(&non_existent).is_none());
+    }
+
+    /// Icons laid out at the origin, from an image of the given size and an app icon loaded at
+    /// whatever size it's asked for.
+    fn layout(image_size: Option<u32>, app_icon: bool, config: Config) -> Icons {
+        let sizes = (config.general.icon_size, config.general.app_icon_size);
+        let (icon, app_icon) = arrange(image_size.map(image), sizes, |size| {
+            app_icon.then(|| image(size))
+        });
+
+        let mut icons = Icons {
+            icon,
+            app_icon,
+            config: Arc::new(config),
+            ..Default::default()
+        };
+        icons.set_position(0., 0.);
+        icons
+    }
+
+    fn positions(icons: &Icons) -> Vec<(f32, f32, f32)> {
+        icons
+            .get_textures()
+            .iter()
+            .map(|texture| (texture.left, texture.top, texture.width))
+            .collect()
+    }
+
+    #[test]
+    fn layout_image_only() {
+        let icons = layout(Some(64), false, Config::default());
+
+        assert_eq!(icons.get_bounds().width, 64. + 5. + 10.);
+        assert_eq!(positions(&icons), [(5., 0., 64.)]);
+    }
+
+    #[test]
+    fn layout_app_icon_only() {
+        // The app icon takes the place of the image at full size
+        let icons = layout(None, true, Config::default());
+
+        assert!(icons.app_icon.is_none());
+        assert_eq!(icons.get_bounds().width, 64. + 5. + 10.);
+        assert_eq!(positions(&icons), [(5., 0., 64.)]);
+    }
+
+    #[test]
+    fn layout_image_and_app_icon() {
+        let icons = layout(Some(64), true, Config::default());
+
+        // The emblem doesn't move the text
+        assert_eq!(icons.app_icon.as_ref().unwrap().width(), 24);
+        assert_eq!(icons.get_bounds().width, 64. + 5. + 10.);
+        assert_eq!(
+            positions(&icons),
+            [(5., 0., 64.), (5. + 64. - 24., 64. - 24., 24.)]
+        );
+
+        let mut config = Config::default();
+        config.styles.default.app_icon.anchor = Anchor::TopLeft;
+        let icons = layout(Some(64), true, config);
+        assert_eq!(positions(&icons), [(5., 0., 64.), (5., 0., 24.)]);
+    }
+
+    #[test]
+    fn layout_without_icons() {
+        let icons = layout(None, false, Config::default());
+
+        assert_eq!(icons.get_bounds().width, 0.);
+        assert!(icons.get_textures().is_empty());
     }
 
     #[test]
@@ -375,4 +733,28 @@ mod tests {
         assert_eq!(icons.x, 100.);
         assert_eq!(icons.y, 200.);
     }
+
+    #[test]
+    fn next_frame_cycles_through_frames() {
+        let frames: Vec<ImageFrame> = [(8, 10), (16, 20)]
+            .into_iter()
+            .map(|(size, delay)| ImageFrame {
+                image: image(size),
+                delay: Duration::from_millis(delay),
+            })
+            .collect();
+
+        let mut icons = Icons {
+            config: Arc::new(Config::default()),
+            ..Default::default()
+        };
+        assert_eq!(icons.next_frame(), None);
+
+        icons.set_icon(Some(image(8)), frames.into());
+        assert_eq!(icons.frame_delay(), Some(Duration::from_millis(10)));
+        assert_eq!(icons.next_frame(), Some(Duration::from_millis(20)));
+        assert_eq!(icons.icon.as_ref().map(ImageData::width), Some(16));
+        assert_eq!(icons.next_frame(), Some(Duration::from_millis(10)));
+        assert_eq!(icons.icon.as_ref().map(ImageData::width), Some(8));
+    }
 }
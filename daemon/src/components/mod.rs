@@ -7,7 +7,7 @@ pub mod text;
 use std::sync::atomic::Ordering;
 
 use crate::{
-    config::{Config, StyleState},
+    config::{Config, StyleKey, StyleState},
     manager::UiState,
     rendering::texture_renderer,
     utils::buffers,
@@ -20,6 +20,36 @@ pub enum Data<'a> {
     Texture(texture_renderer::TextureArea<'a>),
 }
 
+/// Stacking order of what's drawn for a notification, later variants end up in front no matter
+/// which render pass draws them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Layer {
+    Background,
+    Progress,
+    /// Buttons and the boxes text sits in.
+    Element,
+    Icon,
+    /// Drawn over a corner of the notification icon.
+    AppIcon,
+    Text,
+    /// Focus rings and the text input cursor.
+    Focus,
+    Hint,
+    HintText,
+}
+
+impl Layer {
+    /// Depth the layer is drawn at, the depth test keeps nearer layers in front.
+    pub fn depth(self) -> f32 {
+        0.9 - self as u8 as f32 * 0.05
+    }
+
+    /// Glyph metadata the text renderer reads the depth back from.
+    pub fn metadata(self) -> usize {
+        self.depth().to_bits() as usize
+    }
+}
+
 #[derive(Default, Debug)]
 pub struct Bounds {
     pub x: f32,
@@ -33,7 +63,9 @@ pub trait Component {
 
     fn get_config(&self) -> &Config;
 
-    fn get_app_name(&self) -> &str;
+    fn get_style_key(&self) -> &StyleKey;
+
+    fn set_style_key(&mut self, style_key: StyleKey);
 
     fn get_id(&self) -> u32;
 
@@ -41,7 +73,7 @@ pub trait Component {
 
     fn get_notification_style(&self) -> &StyleState {
         self.get_config().find_style(
-            self.get_app_name(),
+            self.get_style_key(),
             self.get_ui_state().selected.load(Ordering::Relaxed)
                 && self.get_ui_state().selected_id.load(Ordering::Relaxed) == self.get_id(),
         )
@@ -66,6 +98,7 @@ pub trait Component {
             .into_iter()
             .map(Data::Instance)
             .chain(self.get_text_areas(urgency).into_iter().map(Data::TextArea))
+            .chain(self.get_textures().into_iter().map(Data::Texture))
             .collect()
     }
 }
@@ -1,27 +1,49 @@
-use super::button::{ButtonManager, ButtonType, Finished};
+use super::button::{ButtonManager, ButtonType, Finished, Ready};
 use super::icons::Icons;
 use super::progress::Progress;
+use super::text::app_name::AppName;
 use super::text::body::Body;
+use super::text::input::{TextInput, INLINE_REPLY};
+use super::text::shape_cache::ShapeCache;
 use super::text::summary::Summary;
+use super::text::timestamp::Timestamp;
 use super::text::Text;
-use super::{Bounds, UiState};
+use super::{Bounds, Layer, UiState};
 use crate::manager::Reason;
+use crate::rendering::animation::{Animation, Frame, Kind};
 use crate::rendering::texture_renderer;
 use crate::{
     components::{Component, Data},
-    config::{Size, StyleState},
-    utils::buffers,
-    Config, Moxnotify, NotificationData, Urgency,
+    config::{
+        border::BorderRadius, keymaps, Anchor, CountdownPosition, RenderMode, Size, StyleKey,
+        StyleState,
+    },
+    utils::{buffers, image_data::ImageData},
+    Config, History, Moxnotify, NotificationData, Urgency,
 };
 use calloop::timer::{TimeoutAction, Timer};
 use calloop::{LoopHandle, RegistrationToken};
 use glyphon::FontSystem;
+use std::cell::RefCell;
+use std::rc::Rc;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 pub type NotificationId = u32;
 
+/// What the instances of a notification are drawn from, besides its layout and content.
+#[derive(Clone, Copy, PartialEq)]
+struct RenderKey {
+    urgency: Urgency,
+    hovered: bool,
+    selected: bool,
+    mode: keymaps::Mode,
+    scale: f32,
+    /// Buttons under the pointer and focused by keyboard.
+    buttons: (Option<usize>, Option<usize>),
+}
+
 pub struct Notification {
     pub y: f32,
     pub x: f32,
@@ -30,11 +52,34 @@ pub struct Notification {
     pub icons: Icons,
     progress: Option<Progress>,
     pub registration_token: Option<RegistrationToken>,
+    /// Timer moving an animated image on to its next frame, only running while it's in view.
+    pub frame_token: Option<RegistrationToken>,
+    timer_started: Option<(Instant, Duration)>,
+    remaining: Option<Duration>,
+    /// Pinned notifications don't expire, they stay until dismissed.
+    pinned: bool,
     pub buttons: ButtonManager<Finished>,
     pub data: NotificationData,
+    style_key: StyleKey,
+    /// When the notification was received, notifications without a timestamp count from when
+    /// they were added.
+    pub received_at: SystemTime,
     ui_state: UiState,
     pub summary: Summary,
+    timestamp: Option<Timestamp>,
+    app_name: Option<AppName>,
     pub body: Body,
+    pub reply: Option<TextInput>,
+    animation: Option<Animation>,
+    /// Sideways distance the notification is swiped by, while a finger is still on it.
+    swipe: f32,
+    /// Vertical distance the notification is dragged by, while the pointer holds it.
+    drag: f32,
+    /// Distance the `x` and `y` hints move the notification by, as clamped by the manager.
+    offset: (f32, f32),
+    /// Instances drawn last, reused while the key they were drawn with holds. Dropped when
+    /// the notification is laid out again, which every change of its content leads to.
+    instances: RefCell<Option<(RenderKey, Vec<buffers::Instance>)>>,
 }
 
 impl PartialEq for Notification {
@@ -50,8 +95,28 @@ impl Component for Notification {
         &self.config
     }
 
-    fn get_app_name(&self) -> &str {
-        &self.data.app_name
+    fn get_style_key(&self) -> &StyleKey {
+        &self.style_key
+    }
+
+    fn set_style_key(&mut self, style_key: StyleKey) {
+        self.summary.set_style_key(style_key.clone());
+        self.body.set_style_key(style_key.clone());
+        self.icons.set_style_key(style_key.clone());
+        self.buttons.set_style_key(style_key.clone());
+        if let Some(progress) = self.progress.as_mut() {
+            progress.set_style_key(style_key.clone());
+        }
+        if let Some(timestamp) = self.timestamp.as_mut() {
+            timestamp.set_style_key(style_key.clone());
+        }
+        if let Some(app_name) = self.app_name.as_mut() {
+            app_name.set_style_key(style_key.clone());
+        }
+        if let Some(reply) = self.reply.as_mut() {
+            reply.set_style_key(style_key.clone());
+        }
+        self.style_key = style_key;
     }
 
     fn get_id(&self) -> u32 {
@@ -94,30 +159,18 @@ impl Component for Notification {
         let style = self.get_style();
 
         Bounds {
-            x: extents.x + style.margin.left + self.x + self.data.hints.x as f32,
-            y: extents.y + style.margin.top,
+            x: extents.x + style.margin.left + self.x + self.offset.0,
+            y: extents.y + style.margin.top + self.offset.1,
             width: extents.width - style.margin.left - style.margin.right,
             height: extents.height - style.margin.top - style.margin.bottom,
         }
     }
 
     fn get_instances(&self, urgency: &Urgency) -> Vec<buffers::Instance> {
-        let extents = self.get_render_bounds();
-        let style = self.get_style();
+        let mut instances = vec![self.background_instance(urgency)];
+        instances.extend(self.countdown_instance(urgency));
 
-        vec![buffers::Instance {
-            rect_pos: [extents.x, extents.y],
-            rect_size: [
-                extents.width - style.border.size.left - style.border.size.right,
-                extents.height - style.border.size.top - style.border.size.bottom,
-            ],
-            rect_color: style.background.to_linear(urgency),
-            border_radius: style.border.radius.into(),
-            border_size: style.border.size.into(),
-            border_color: style.border.color.to_linear(urgency),
-            scale: self.ui_state.scale.load(Ordering::Relaxed),
-            depth: 0.9,
-        }]
+        instances
     }
 
     fn get_text_areas(&self, _: &Urgency) -> Vec<glyphon::TextArea<'_>> {
@@ -131,10 +184,16 @@ impl Component for Notification {
     fn set_position(&mut self, x: f32, y: f32) {
         self.x = x;
         self.y = y;
+        self.invalidate();
+
+        if self.style_key.osd {
+            self.set_osd_position();
+            return;
+        }
 
         let extents = self.get_render_bounds();
         let hovered = self.hovered();
-        let style = self.config.find_style(&self.data.app_name, hovered);
+        let style = self.config.find_style(&self.style_key, hovered);
 
         let x_offset = style.border.size.left + style.padding.left;
         let y_offset = style.border.size.top + style.padding.top;
@@ -179,10 +238,19 @@ impl Component for Notification {
             self.icons.set_position(icon_x, icon_y);
         }
 
+        // Position app name above the summary
+        let app_name_height = self.app_name_height();
+        if let Some(app_name) = self.app_name.as_mut() {
+            app_name.set_position(
+                extents.x + x_offset + self.icons.get_bounds().width,
+                extents.y + y_offset,
+            );
+        }
+
         // Position summary
         self.summary.set_position(
             extents.x + x_offset + self.icons.get_bounds().width,
-            extents.y + y_offset,
+            extents.y + y_offset + app_name_height,
         );
 
         // Position progress indicator if present
@@ -199,7 +267,7 @@ impl Component for Notification {
 
             let is_selected = self.ui_state.selected.load(Ordering::Relaxed)
                 && self.ui_state.selected_id.load(Ordering::Relaxed) == self.data.id;
-            let selected_style = self.config.find_style(&self.data.app_name, is_selected);
+            let selected_style = self.config.find_style(&self.style_key, is_selected);
 
             let progress_x =
                 extents.x + selected_style.border.size.left + selected_style.padding.left;
@@ -230,29 +298,26 @@ impl Component for Notification {
             })
             .unwrap_or(0.0);
 
+        // Position timestamp left of the dismiss button
+        let dismiss_width = self.dismiss_width();
+        if let Some(timestamp) = self.timestamp.as_mut() {
+            let timestamp_x = extents.x + extents.width
+                - style.border.size.right
+                - style.padding.right
+                - dismiss_width
+                - timestamp.get_bounds().width;
+
+            timestamp.set_position(timestamp_x, extents.y + y_offset);
+        }
+
         // Position action buttons
         if action_buttons_count > 0 {
-            let button_style = self
-                .buttons
-                .buttons()
-                .iter()
-                .find(|button| button.button_type() == ButtonType::Action)
-                .map(|button| button.get_style())
-                .unwrap_or_else(|| &style.buttons.action.default);
-
             let side_padding = style.border.size.left
                 + style.border.size.right
                 + style.padding.left
                 + style.padding.right;
 
-            let button_margin = button_style.margin.left + button_style.margin.right;
-            let available_width = extents.width - side_padding - button_margin;
-
-            let action_buttons_f32 = action_buttons_count as f32;
-            let total_spacing = (action_buttons_f32 - 1.0) * button_margin;
-            let button_width = (available_width - total_spacing) / action_buttons_f32;
-
-            self.buttons.set_action_widths(button_width);
+            self.buttons.set_action_widths(extents.width - side_padding);
 
             let progress_height = self
                 .progress
@@ -267,14 +332,13 @@ impl Component for Notification {
                 .buttons_mut()
                 .iter_mut()
                 .filter(|b| b.button_type() == ButtonType::Action)
-                .enumerate()
-                .for_each(|(i, button)| {
-                    let x_position = base_x + (button_width + button_margin) * i as f32;
+                .fold(base_x, |x_position, button| {
                     let y_position =
                         (extents.y + extents.height - bottom_padding - button.get_bounds().height)
                             .max(dismiss_bottom_y);
 
                     button.set_position(x_position, y_position);
+                    x_position + button.get_bounds().width
                 });
         }
 
@@ -294,26 +358,102 @@ impl Component for Notification {
         let bounds = self.get_render_bounds();
         self.body.set_position(
             bounds.x + x_offset + self.icons.get_bounds().width,
-            bounds.y + y_offset + self.summary.get_bounds().height,
+            bounds.y + y_offset + app_name_height + self.summary.get_bounds().height,
         );
+
+        // Position expand button below the body
+        let body = self.body.get_bounds();
+        let body_bottom = body.y + body.height;
+        self.buttons
+            .buttons_mut()
+            .iter_mut()
+            .filter(|b| b.button_type() == ButtonType::Expand)
+            .for_each(|button| button.set_position(body.x, body_bottom));
+
+        // Position reply input below the body
+        if let Some(reply) = self.reply.as_mut() {
+            reply.set_position(
+                bounds.x + x_offset + self.icons.get_bounds().width,
+                body_bottom + self.expand_button_height(),
+            );
+        }
     }
 
     fn get_data(&self, urgency: &Urgency) -> Vec<Data<'_>> {
-        let mut data = self
-            .get_instances(urgency)
+        let mut instances = self.cached_instances(urgency).into_iter();
+        // The countdown shrinks every frame, so it's never cached
+        let mut data = instances
+            .next()
             .into_iter()
+            .chain(self.countdown_instance(urgency))
+            .chain(instances)
             .map(Data::Instance)
-            .chain(self.get_text_areas(urgency).into_iter().map(Data::TextArea))
             .collect::<Vec<_>>();
 
+        // Text areas and textures only point at buffers and images, they're cheap to redo
         if let Some(progress) = self.progress.as_ref() {
-            data.extend(progress.get_data(urgency));
+            data.extend(
+                progress
+                    .get_text_areas(urgency)
+                    .into_iter()
+                    .map(Data::TextArea),
+            );
+            data.extend(progress.get_textures().into_iter().map(Data::Texture));
         }
 
         data.extend(self.icons.get_data(urgency));
-        data.extend(self.buttons.data());
-        data.extend(self.summary.get_data(urgency));
-        data.extend(self.body.get_data(urgency));
+        if !self.style_key.osd {
+            data.extend(self.buttons.text_areas().into_iter().map(Data::TextArea));
+            data.extend(self.buttons.textures().into_iter().map(Data::Texture));
+            data.extend(
+                self.summary
+                    .get_text_areas(urgency)
+                    .into_iter()
+                    .map(Data::TextArea),
+            );
+            if !self.style_key.compact {
+                data.extend(
+                    self.body
+                        .get_text_areas(urgency)
+                        .into_iter()
+                        .map(Data::TextArea),
+                );
+                data.extend(self.body.get_textures().into_iter().map(Data::Texture));
+            }
+        }
+
+        if let Some(timestamp) = self.timestamp.as_ref() {
+            data.extend(
+                timestamp
+                    .get_text_areas(urgency)
+                    .into_iter()
+                    .map(Data::TextArea),
+            );
+        }
+
+        if let Some(app_name) = self.app_name.as_ref() {
+            data.extend(
+                app_name
+                    .get_text_areas(urgency)
+                    .into_iter()
+                    .map(Data::TextArea),
+            );
+        }
+
+        if let Some(reply) = self.reply.as_ref() {
+            data.extend(
+                reply
+                    .get_text_areas(urgency)
+                    .into_iter()
+                    .map(Data::TextArea),
+            );
+        }
+
+        // Layout keeps using the resting position, the animation only shifts and fades what's drawn
+        let frame = self.drawn_frame();
+        if frame != Frame::REST {
+            data.iter_mut().for_each(|data| frame.apply(data));
+        }
 
         data
     }
@@ -326,11 +466,22 @@ impl Notification {
         data: NotificationData,
         ui_state: UiState,
         sender: Option<calloop::channel::Sender<crate::Event>>,
+        history: History,
+        shape_cache: Option<Rc<ShapeCache>>,
     ) -> Self {
+        let selected = ui_state.selected.load(Ordering::Relaxed)
+            && ui_state.selected_id.load(Ordering::Relaxed) == data.id;
+        let mut style_key = StyleKey::new(&data, history);
+        // Only notifications carrying a value can be shown as an OSD, which is never compact
+        style_key.osd = data.hints.value.is_some()
+            && config.find_style(&style_key, false).render_mode == RenderMode::Osd;
+        style_key.compact = !style_key.osd
+            && ui_state.compact.load(Ordering::Relaxed)
+            && !(selected && config.general.compact.expand_selected);
         let mut body = Body::new(
             data.id,
             Arc::clone(&config),
-            Arc::clone(&data.app_name),
+            style_key.clone(),
             ui_state.clone(),
             font_system,
         );
@@ -338,7 +489,7 @@ impl Notification {
         let mut summary = Summary::new(
             data.id,
             Arc::clone(&config),
-            Arc::clone(&data.app_name),
+            style_key.clone(),
             ui_state.clone(),
             font_system,
         );
@@ -357,49 +508,142 @@ impl Notification {
                     None,
                     Arc::clone(&config),
                     ui_state.clone(),
-                    Arc::clone(&data.app_name),
+                    style_key.clone(),
                 ),
                 progress: None,
                 registration_token: None,
+                frame_token: None,
+                timer_started: None,
+                remaining: None,
+                pinned: false,
                 buttons: ButtonManager::new(
                     data.id,
                     data.hints.urgency,
-                    Arc::clone(&data.app_name),
+                    style_key.clone(),
                     ui_state.clone(),
                     sender,
                     Arc::clone(&config),
+                    history,
                 )
                 .add_dismiss(font_system)
                 .finish(font_system),
                 ui_state: ui_state.clone(),
                 summary,
                 body,
+                reply: None,
+                timestamp: None,
+                app_name: None,
+                animation: None,
+                swipe: 0.,
+                drag: 0.,
+                offset: (0., 0.),
+                instances: RefCell::new(None),
                 data,
+                style_key,
+                received_at: SystemTime::now(),
             };
         }
 
-        let icons = Icons::new(
+        let mut icons = Icons::new(
             data.id,
             data.hints.image.as_ref(),
             data.app_icon.as_deref(),
             Arc::clone(&config),
             ui_state.clone(),
-            Arc::clone(&data.app_name),
+            style_key.clone(),
         );
+        icons.load_pending(sender.as_ref());
+
+        if let Some(value) = data.hints.value.filter(|_| style_key.osd) {
+            return Self {
+                y: 0.,
+                x: 0.,
+                hovered: false,
+                config: Arc::clone(&config),
+                icons,
+                progress: Some(Progress::new(
+                    data.id,
+                    value,
+                    ui_state.clone(),
+                    Arc::clone(&config),
+                    style_key.clone(),
+                )),
+                registration_token: None,
+                frame_token: None,
+                timer_started: None,
+                remaining: None,
+                pinned: false,
+                buttons: ButtonManager::new(
+                    data.id,
+                    data.hints.urgency,
+                    style_key.clone(),
+                    ui_state.clone(),
+                    sender,
+                    Arc::clone(&config),
+                    history,
+                )
+                .without_buttons(),
+                ui_state,
+                summary,
+                body,
+                reply: None,
+                timestamp: None,
+                app_name: None,
+                animation: None,
+                swipe: 0.,
+                drag: 0.,
+                offset: (0., 0.),
+                instances: RefCell::new(None),
+                data,
+                style_key,
+                received_at: SystemTime::now(),
+            };
+        }
+
+        body.set_shape_cache(shape_cache);
+        // Laid out while compact as well, so that the notification can be shown in full in place
+        body.set_text(font_system, &data.body);
+        body.load_images(font_system, sender.as_ref());
+        // Entries of the history stored without their content only have a placeholder
+        summary.set_italic(data.redacted);
+        summary.set_text(font_system, &data.summary);
 
         let buttons = ButtonManager::new(
             data.id,
             data.hints.urgency,
-            Arc::clone(&data.app_name),
+            style_key.clone(),
             ui_state.clone(),
             sender,
             Arc::clone(&config),
+            history,
         )
-        .add_dismiss(font_system)
-        .add_actions(&data.actions, font_system);
+        .add_dismiss(font_system);
 
-        body.set_text(font_system, &data.body);
-        summary.set_text(font_system, &data.summary);
+        let received_at = match data.timestamp {
+            0 => SystemTime::now(),
+            timestamp => UNIX_EPOCH + Duration::from_secs(timestamp),
+        };
+        let timestamp = (data.timestamp != 0).then(|| {
+            Timestamp::new(
+                data.id,
+                Arc::clone(&config),
+                style_key.clone(),
+                ui_state.clone(),
+                received_at,
+                font_system,
+            )
+        });
+
+        let app_name = config.general.show_app_name.then(|| {
+            AppName::new(
+                data.id,
+                Arc::clone(&config),
+                style_key.clone(),
+                ui_state.clone(),
+                data.display_name.as_deref().unwrap_or(&data.app_name),
+                font_system,
+            )
+        });
 
         let dismiss_button = buttons
             .buttons()
@@ -408,20 +652,14 @@ impl Notification {
             .map(|button| button.get_render_bounds().width)
             .unwrap_or(0.0);
 
-        let style = config.find_style(&data.app_name, false);
+        let style = config.find_style(&style_key, false);
         body.set_size(
             font_system,
             Some(style.width - icons.get_bounds().width - dismiss_button),
             None,
         );
 
-        summary.set_size(
-            font_system,
-            Some(style.width - icons.get_bounds().width - dismiss_button),
-            None,
-        );
-
-        Self {
+        let mut notification = Self {
             summary,
             progress: data.hints.value.map(|value| {
                 Progress::new(
@@ -429,24 +667,303 @@ impl Notification {
                     value,
                     ui_state.clone(),
                     Arc::clone(&config),
-                    Arc::clone(&data.app_name),
+                    style_key.clone(),
                 )
             }),
             y: 0.,
             x: 0.,
             icons,
-            buttons: buttons
-                .add_anchors(&body.anchors, font_system)
-                .finish(font_system),
+            buttons: Self::finish_buttons(buttons, &data, &style_key, &body, font_system),
             data,
+            style_key,
+            received_at,
             config,
             hovered: false,
             registration_token: None,
+            frame_token: None,
+            timer_started: None,
+            remaining: None,
+            pinned: false,
             ui_state: ui_state.clone(),
             body,
+            reply: None,
+            timestamp,
+            app_name,
+            animation: None,
+            swipe: 0.,
+            drag: 0.,
+            offset: (0., 0.),
+            instances: RefCell::new(None),
+        };
+        notification.fit_summary(font_system);
+
+        notification
+    }
+
+    /// Puts the icon and the progress bar next to each other, centered vertically.
+    fn set_osd_position(&mut self) {
+        let extents = self.get_render_bounds();
+        let style = self.config.find_style(&self.style_key, self.hovered);
+
+        let x = extents.x + style.border.size.left + style.padding.left;
+        let y = extents.y + style.border.size.top + style.padding.top;
+        let width = extents.width
+            - style.border.size.left
+            - style.border.size.right
+            - style.padding.left
+            - style.padding.right;
+        let height = extents.height
+            - style.border.size.top
+            - style.border.size.bottom
+            - style.padding.top
+            - style.padding.bottom;
+
+        let icon = self.icons.get_bounds();
+        self.icons.set_position(x, y + (height - icon.height) / 2.);
+
+        if let Some(progress) = self.progress.as_mut() {
+            progress.set_width(
+                width - icon.width - style.progress.margin.left - style.progress.margin.right,
+            );
+            let progress_height = progress.get_bounds().height;
+            progress.set_position(x + icon.width, y + (height - progress_height) / 2.);
+        }
+    }
+
+    pub fn osd(&self) -> bool {
+        self.style_key.osd
+    }
+
+    /// Whether only the summary is shown, the body and action buttons are left out.
+    pub fn compact(&self) -> bool {
+        self.style_key.compact
+    }
+
+    /// Switches to the compact layout or back in place, rather than building the notification
+    /// again, so that it stays hovered and dragged. Returns false when it's in it already.
+    pub fn set_compact(&mut self, compact: bool, font_system: &mut FontSystem) -> bool {
+        if self.style_key.osd || self.style_key.compact == compact {
+            return false;
+        }
+
+        let mut style_key = self.style_key.clone();
+        style_key.compact = compact;
+        self.set_style_key(style_key);
+
+        let buttons = self.buttons.renew().add_dismiss(font_system);
+        let dismiss_button = buttons
+            .buttons()
+            .iter()
+            .find(|button| button.button_type() == ButtonType::Dismiss)
+            .map(|button| button.get_render_bounds().width)
+            .unwrap_or(0.0);
+
+        let style = self.config.find_style(&self.style_key, false);
+        self.body.set_size(
+            font_system,
+            Some(style.width - self.icons.get_bounds().width - dismiss_button),
+            None,
+        );
+        self.buttons = Self::finish_buttons(
+            buttons,
+            &self.data,
+            &self.style_key,
+            &self.body,
+            font_system,
+        );
+        if self.body.expanded() {
+            self.buttons.set_expanded(true, font_system);
+        }
+        self.fit_summary(font_system);
+        self.invalidate();
+
+        true
+    }
+
+    /// Adds the action buttons, the expand button and the links of `body` after the dismiss
+    /// button, compact notifications leave them all out.
+    fn finish_buttons(
+        buttons: ButtonManager<Ready>,
+        data: &NotificationData,
+        style_key: &StyleKey,
+        body: &Body,
+        font_system: &mut FontSystem,
+    ) -> ButtonManager<Finished> {
+        if style_key.compact {
+            return buttons.finish(font_system);
+        }
+
+        let buttons = buttons.add_actions(&data.actions, data.hints.action_icons, font_system);
+        match body.overflowing() {
+            true => buttons.add_expand(font_system),
+            false => buttons,
+        }
+        .add_anchors(&body.anchors, font_system)
+        .finish(font_system)
+    }
+
+    fn dismiss_width(&self) -> f32 {
+        self.buttons
+            .buttons()
+            .iter()
+            .find(|button| button.button_type() == ButtonType::Dismiss)
+            .map(|button| button.get_render_bounds().width)
+            .unwrap_or_default()
+    }
+
+    /// Wraps the summary in whatever is left of the top row next to the icons, the timestamp
+    /// and the dismiss button.
+    pub fn fit_summary(&mut self, font_system: &mut FontSystem) {
+        let timestamp = self
+            .timestamp
+            .as_ref()
+            .map(|timestamp| timestamp.get_bounds().width)
+            .unwrap_or_default();
+
+        let width = self.get_style().width
+            - self.icons.get_bounds().width
+            - self.dismiss_width()
+            - timestamp;
+        self.summary.set_size(font_system, Some(width), None);
+        if let Some(app_name) = self.app_name.as_mut() {
+            app_name.set_size(font_system, Some(width), None);
         }
     }
 
+    fn app_name_height(&self) -> f32 {
+        self.app_name
+            .as_ref()
+            .map(|app_name| app_name.get_bounds().height)
+            .unwrap_or_default()
+    }
+
+    /// Updates the relative timestamp, returns whether its text changed.
+    pub fn refresh_timestamp(&mut self, font_system: &mut FontSystem, now: SystemTime) -> bool {
+        let changed = self
+            .timestamp
+            .as_mut()
+            .is_some_and(|timestamp| timestamp.refresh(font_system, now));
+        if changed {
+            self.fit_summary(font_system);
+        }
+
+        changed
+    }
+
+    pub fn accepts_reply(&self) -> bool {
+        self.data
+            .actions
+            .iter()
+            .any(|(key, _)| &**key == INLINE_REPLY)
+    }
+
+    /// Opens the reply input below the body, returns false when the sender didn't ask for one.
+    pub fn start_reply(&mut self, font_system: &mut FontSystem) -> bool {
+        if !self.accepts_reply() {
+            return false;
+        }
+
+        let mut reply = TextInput::new(
+            self.id(),
+            Arc::clone(&self.config),
+            self.style_key.clone(),
+            self.ui_state.clone(),
+            font_system,
+        );
+        reply.set_size(font_system, self.body.buffer.size().0, None);
+        self.reply = Some(reply);
+
+        true
+    }
+
+    /// Expands a body that's cut off at `max_lines` or collapses it back, returns false when
+    /// the body fits within its lines anyway.
+    pub fn toggle_expand(&mut self, font_system: &mut FontSystem) -> bool {
+        if !self.body.overflowing() {
+            return false;
+        }
+
+        let expanded = !self.body.expanded();
+        self.body.set_expanded(font_system, expanded);
+        self.buttons.set_expanded(expanded, font_system);
+        self.buttons.set_anchors(&self.body.anchors);
+        self.buttons.assign_hints(font_system);
+
+        true
+    }
+
+    /// Shows the decoded `<img>` source `src` in the body, returns false when the body isn't
+    /// waiting on it.
+    pub fn set_body_image(
+        &mut self,
+        font_system: &mut FontSystem,
+        src: &str,
+        image: Option<ImageData>,
+    ) -> bool {
+        if !self.body.set_image(font_system, src, image) {
+            return false;
+        }
+        self.buttons.set_anchors(&self.body.anchors);
+        self.buttons.assign_hints(font_system);
+
+        true
+    }
+
+    /// Shows `icon` in place of the label of action `action`, returns false when there's no
+    /// such action.
+    pub fn set_action_icon(&mut self, action: &str, icon: ImageData) -> bool {
+        if !self.buttons.set_action_icon(action, icon) {
+            return false;
+        }
+        self.invalidate();
+
+        true
+    }
+
+    /// Colors the anchor under the pointer as hovered, returns whether another one is now.
+    pub fn hover_anchor(&mut self, font_system: &mut FontSystem) -> bool {
+        let hovered = self.buttons.hovered_anchor();
+        if !self.body.set_hovered_anchor(font_system, hovered) {
+            return false;
+        }
+        self.buttons.set_anchors(&self.body.anchors);
+        self.invalidate();
+
+        true
+    }
+
+    /// Colors no anchor as hovered once the pointer left, returns whether one was.
+    pub fn unhover_anchor(&mut self, font_system: &mut FontSystem) -> bool {
+        if !self.body.set_hovered_anchor(font_system, None) {
+            return false;
+        }
+        self.buttons.set_anchors(&self.body.anchors);
+        self.invalidate();
+
+        true
+    }
+
+    /// Colors anchors linking to `href` as visited, returns false when there's none.
+    pub fn visit_link(&mut self, font_system: &mut FontSystem, href: &str) -> bool {
+        if !self.body.visit(font_system, href) {
+            return false;
+        }
+        self.buttons.set_anchors(&self.body.anchors);
+        self.invalidate();
+
+        true
+    }
+
+    fn expand_button_height(&self) -> f32 {
+        self.buttons
+            .buttons()
+            .iter()
+            .find(|button| button.button_type() == ButtonType::Expand)
+            .map(|button| button.get_bounds().height)
+            .unwrap_or_default()
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(id = self.id()))]
     pub fn start_timer(&mut self, loop_handle: &LoopHandle<'static, Moxnotify>) {
         if let Some(timeout) = self.timeout() {
             log::debug!(
@@ -455,10 +972,21 @@ impl Notification {
                 timeout
             );
 
-            let timer = Timer::from_duration(Duration::from_millis(timeout));
+            if let Some(token) = self.registration_token.take() {
+                loop_handle.remove(token);
+            }
+
+            let duration = self
+                .remaining
+                .take()
+                .unwrap_or(Duration::from_millis(timeout));
+            self.timer_started = Some((Instant::now(), duration));
+
+            let timer = Timer::from_duration(duration);
             let id = self.id();
             self.registration_token = loop_handle
                 .insert_source(timer, move |_, _, moxnotify| {
+                    let _span = tracing::info_span!("expire", id).entered();
                     moxnotify.dismiss_by_id(id, Some(Reason::Expired));
                     TimeoutAction::Drop
                 })
@@ -466,8 +994,10 @@ impl Notification {
         }
     }
 
-    pub fn stop_timer(&self, loop_handle: &LoopHandle<'static, Moxnotify>) {
-        if let Some(token) = self.registration_token {
+    #[tracing::instrument(level = "debug", skip_all, fields(id = self.id()))]
+    pub fn stop_timer(&mut self, loop_handle: &LoopHandle<'static, Moxnotify>) {
+        self.timer_started = None;
+        if let Some(token) = self.registration_token.take() {
             log::debug!(
                 "Expiration timer paused for notification, id: {}",
                 self.id()
@@ -477,34 +1007,301 @@ impl Notification {
         }
     }
 
-    pub fn timeout(&self) -> Option<u64> {
-        let notification_style_entry = self
-            .config
-            .styles
-            .notification
-            .iter()
-            .find(|entry| entry.app == self.data.app_name);
+    /// Stops the expiration timer while remembering how much of it was left, so that
+    /// the next `start_timer` call continues where it left off.
+    pub fn pause_timer(&mut self, loop_handle: &LoopHandle<'static, Moxnotify>) {
+        if let Some((started, duration)) = self.timer_started {
+            self.remaining = Some(duration.saturating_sub(started.elapsed()));
+            self.stop_timer(loop_handle);
+        }
+    }
 
-        let ignore_timeout = notification_style_entry
-            .and_then(|entry| entry.ignore_timeout)
-            .unwrap_or(self.config.general.ignore_timeout);
+    /// Starts cycling through the frames of an animated image unless it already is. The
+    /// timer keeps going until the manager finds the notification out of view.
+    pub fn animate_icon(&mut self, loop_handle: &LoopHandle<'static, Moxnotify>) {
+        if self.frame_token.is_some() {
+            return;
+        }
+        let Some(delay) = self.icons.frame_delay() else {
+            return;
+        };
 
-        let default_timeout = notification_style_entry
-            .and_then(|entry| entry.default_timeout.as_ref())
-            .unwrap_or(&self.config.general.default_timeout);
+        let id = self.id();
+        self.frame_token = loop_handle
+            .insert_source(
+                Timer::from_duration(delay),
+                move |_, _, moxnotify| match moxnotify.notifications.next_frame(id) {
+                    Some(delay) => {
+                        if let Err(e) = moxnotify.render_surfaces() {
+                            log::error!("Render error: {e}");
+                        }
+                        TimeoutAction::ToDuration(delay)
+                    }
+                    None => TimeoutAction::Drop,
+                },
+            )
+            .ok();
+    }
 
-        if ignore_timeout {
-            (default_timeout.get(&self.data.hints.urgency) > 0)
-                .then(|| (default_timeout.get(&self.data.hints.urgency) as u64) * 1000)
-        } else {
-            match self.data.timeout {
-                0 => None,
-                -1 => (default_timeout.get(&self.data.hints.urgency) > 0)
-                    .then(|| (default_timeout.get(&self.data.hints.urgency) as u64) * 1000),
-                t if t > 0 => Some(t as u64),
-                _ => None,
+    pub fn stop_icon_animation(&mut self, loop_handle: &LoopHandle<'static, Moxnotify>) {
+        if let Some(token) = self.frame_token.take() {
+            loop_handle.remove(token);
+        }
+    }
+
+    /// Shows the next frame of an animated image, returns how long it's shown for.
+    pub fn next_frame(&mut self) -> Option<Duration> {
+        let delay = self.icons.next_frame()?;
+        self.invalidate();
+        Some(delay)
+    }
+
+    pub fn pinned(&self) -> bool {
+        self.pinned
+    }
+
+    /// Pins the notification or unpins it. Pinning drops the expiration timer, it's up to the
+    /// caller to start it again after unpinning.
+    pub fn set_pinned(&mut self, pinned: bool, loop_handle: &LoopHandle<'static, Moxnotify>) {
+        self.pinned = pinned;
+        if pinned {
+            self.stop_timer(loop_handle);
+            self.remaining = None;
+        }
+        self.invalidate();
+    }
+
+    /// Builds the notification again with `config`, keeping whether it's hovered and what's
+    /// left of its expiration timer.
+    pub fn reconfigure(
+        &mut self,
+        config: Arc<Config>,
+        font_system: &mut FontSystem,
+        sender: Option<calloop::channel::Sender<crate::Event>>,
+        history: History,
+        loop_handle: &LoopHandle<'static, Moxnotify>,
+    ) {
+        let running = self.timer_started.is_some();
+        self.pause_timer(loop_handle);
+        self.stop_icon_animation(loop_handle);
+
+        let mut notification = Self::new(
+            config,
+            font_system,
+            self.data.clone(),
+            self.ui_state.clone(),
+            sender,
+            history,
+            self.body.shape_cache(),
+        );
+        notification.x = self.x;
+        notification.y = self.y;
+        notification.offset = self.offset;
+        notification.hovered = self.hovered;
+        notification.received_at = self.received_at;
+        notification.remaining = self.remaining.take();
+        notification.pinned = self.pinned;
+        *self = notification;
+
+        if running {
+            self.start_timer(loop_handle);
+        }
+    }
+
+    /// Time left until the notification expires, it stands still while the timer is paused.
+    pub fn remaining_timeout(&self) -> Option<Duration> {
+        match self.timer_started {
+            Some((started, duration)) => Some(duration.saturating_sub(started.elapsed())),
+            None => self.remaining,
+        }
+    }
+
+    /// Whether the countdown bar shrinks, so it has to be redrawn every frame.
+    pub fn counting_down(&self) -> bool {
+        self.timer_started.is_some() && self.get_style().countdown.height.resolve(0.) > 0.
+    }
+
+    fn background_instance(&self, urgency: &Urgency) -> buffers::Instance {
+        let extents = self.get_render_bounds();
+        let style = self.get_style();
+
+        buffers::Instance {
+            rect_pos: [extents.x, extents.y],
+            rect_size: [
+                extents.width - style.border.size.left - style.border.size.right,
+                extents.height - style.border.size.top - style.border.size.bottom,
+            ],
+            rect_color: style.background.to_linear(urgency),
+            border_radius: style.border.radius.into(),
+            border_size: style.border.size.into(),
+            border_color: style.border.color.to_linear(urgency),
+            scale: self.ui_state.scale.load(Ordering::Relaxed),
+            depth: Layer::Background.depth(),
+        }
+    }
+
+    /// Drops the cached instances, they're built anew the next time the notification is drawn.
+    pub fn invalidate(&self) {
+        *self.instances.borrow_mut() = None;
+    }
+
+    fn render_key(&self, urgency: &Urgency) -> RenderKey {
+        RenderKey {
+            urgency: *urgency,
+            hovered: self.hovered,
+            selected: self.ui_state.selected.load(Ordering::Relaxed)
+                && self.ui_state.selected_id.load(Ordering::Relaxed) == self.id(),
+            mode: self.ui_state.mode.load(Ordering::Relaxed),
+            scale: self.ui_state.scale.load(Ordering::Relaxed),
+            buttons: self.buttons.highlighted(),
+        }
+    }
+
+    /// Instances of the notification and everything in it but the countdown, in the order
+    /// they're drawn in. The background comes first.
+    fn cached_instances(&self, urgency: &Urgency) -> Vec<buffers::Instance> {
+        let key = self.render_key(urgency);
+        let mut cache = self.instances.borrow_mut();
+        match cache.as_ref() {
+            Some((cached, instances)) if *cached == key => return instances.clone(),
+            _ => {}
+        }
+
+        let mut instances = vec![self.background_instance(urgency)];
+        instances.extend(self.pin_instance(urgency));
+        if let Some(progress) = self.progress.as_ref() {
+            instances.extend(progress.get_instances(urgency));
+        }
+
+        if !self.style_key.osd {
+            instances.extend(self.buttons.instances());
+            instances.extend(self.summary.get_instances(urgency));
+            if !self.style_key.compact {
+                instances.extend(self.body.get_instances(urgency));
             }
         }
+
+        if let Some(timestamp) = self.timestamp.as_ref() {
+            instances.extend(timestamp.get_instances(urgency));
+        }
+
+        if let Some(app_name) = self.app_name.as_ref() {
+            instances.extend(app_name.get_instances(urgency));
+        }
+
+        if let Some(reply) = self.reply.as_ref() {
+            instances.extend(reply.get_instances(urgency));
+        }
+
+        *cache = Some((key, instances.clone()));
+        instances
+    }
+
+    /// Bar along the top or bottom edge whose width follows the time left until expiration.
+    fn countdown_instance(&self, urgency: &Urgency) -> Option<buffers::Instance> {
+        let style = self.get_style();
+        let height = style.countdown.height.resolve(0.);
+        if height <= 0. {
+            return None;
+        }
+
+        let timeout = Duration::from_millis(self.timeout()?);
+        let ratio = (self.remaining_timeout()?.as_secs_f32() / timeout.as_secs_f32()).min(1.);
+
+        let extents = self.get_render_bounds();
+        let x = extents.x + style.border.size.left;
+        let width = extents.width - style.border.size.left - style.border.size.right;
+        let (y, border_radius) = match style.countdown.position {
+            CountdownPosition::Top => (
+                extents.y + style.border.size.top,
+                BorderRadius {
+                    top_left: style.border.radius.top_left,
+                    top_right: style.border.radius.top_right,
+                    ..Default::default()
+                },
+            ),
+            CountdownPosition::Bottom => (
+                extents.y + extents.height - style.border.size.bottom - height,
+                BorderRadius {
+                    bottom_left: style.border.radius.bottom_left,
+                    bottom_right: style.border.radius.bottom_right,
+                    ..Default::default()
+                },
+            ),
+        };
+
+        Some(buffers::Instance {
+            rect_pos: [x, y],
+            rect_size: [width * ratio, height],
+            rect_color: style.countdown.background.to_linear(urgency),
+            border_radius: border_radius.into(),
+            border_size: [0.; 4],
+            border_color: style.countdown.background.to_linear(urgency),
+            scale: self.ui_state.scale.load(Ordering::Relaxed),
+            depth: Layer::Progress.depth(),
+        })
+    }
+
+    /// Dot at the edge of pinned notifications, placed by the `anchor` of its style.
+    fn pin_instance(&self, urgency: &Urgency) -> Option<buffers::Instance> {
+        if !self.pinned || self.style_key.osd {
+            return None;
+        }
+
+        let style = self.get_style();
+        let indicator = &style.pin_indicator;
+        let width = indicator.width.resolve(0.);
+        let height = indicator.height.resolve(0.);
+        if width <= 0. || height <= 0. {
+            return None;
+        }
+
+        let extents = self.get_render_bounds();
+        let left = extents.x + style.border.size.left + indicator.margin.left;
+        let right =
+            extents.x + extents.width - style.border.size.right - indicator.margin.right - width;
+        let top = extents.y + style.border.size.top + indicator.margin.top;
+        let bottom = extents.y + extents.height
+            - style.border.size.bottom
+            - indicator.margin.bottom
+            - height;
+
+        let x = match indicator.anchor {
+            Anchor::TopLeft | Anchor::CenterLeft | Anchor::BottomLeft => left,
+            Anchor::TopCenter | Anchor::Center | Anchor::BottomCenter => (left + right) / 2.,
+            Anchor::TopRight | Anchor::CenterRight | Anchor::BottomRight => right,
+        };
+        let y = match indicator.anchor {
+            Anchor::TopLeft | Anchor::TopCenter | Anchor::TopRight => top,
+            Anchor::CenterLeft | Anchor::Center | Anchor::CenterRight => (top + bottom) / 2.,
+            Anchor::BottomLeft | Anchor::BottomCenter | Anchor::BottomRight => bottom,
+        };
+
+        Some(buffers::Instance {
+            rect_pos: [x, y],
+            rect_size: [width, height],
+            rect_color: indicator.background.to_linear(urgency),
+            border_radius: indicator.border.radius.into(),
+            border_size: indicator.border.size.into(),
+            border_color: indicator.border.color.to_linear(urgency),
+            scale: self.ui_state.scale.load(Ordering::Relaxed),
+            depth: Layer::Element.depth(),
+        })
+    }
+
+    /// Milliseconds until the notification expires, None if it stays. Entries of the history
+    /// stay whatever timeout they were sent with, pinned notifications stay until unpinned.
+    pub fn timeout(&self) -> Option<u64> {
+        if self.style_key.history || self.pinned {
+            return None;
+        }
+
+        self.config.timeout(
+            &self.data.app_name,
+            self.data.hints.urgency,
+            self.data.timeout,
+            self.hovered,
+        )
     }
 
     pub fn height(&self) -> f32 {
@@ -539,21 +1336,39 @@ impl Notification {
             0.0
         };
 
+        // Percentages count as auto until there's an output to resolve them against
         let min_height = match style.min_height {
-            Size::Auto => 0.0,
+            Size::Auto | Size::Percent(_) => 0.0,
             Size::Value(value) => value,
         };
 
         let max_height = match style.max_height {
-            Size::Auto => f32::INFINITY,
+            Size::Auto | Size::Percent(_) => f32::INFINITY,
             Size::Value(value) => value,
         };
 
         match style.height {
             Size::Value(height) => height.clamp(min_height, max_height),
-            Size::Auto => {
-                let text_height =
-                    self.body.get_bounds().height + self.summary.get_bounds().height + progress;
+            Size::Auto | Size::Percent(_) if self.style_key.osd => {
+                (self.icons.get_bounds().height.max(progress) + style.padding.bottom)
+                    .clamp(min_height, max_height)
+            }
+            Size::Auto | Size::Percent(_) => {
+                let reply_height = self
+                    .reply
+                    .as_ref()
+                    .map(|reply| reply.get_bounds().height)
+                    .unwrap_or_default();
+                let body_height = match self.style_key.compact {
+                    true => 0.,
+                    false => self.body.get_bounds().height,
+                };
+                let text_height = body_height
+                    + self.expand_button_height()
+                    + self.summary.get_bounds().height
+                    + self.app_name_height()
+                    + reply_height
+                    + progress;
                 let icon_height = self.icons.get_bounds().height + progress;
                 let base_height = (text_height.max(icon_height).max(dismiss_button)
                     + action_button.height)
@@ -571,6 +1386,41 @@ impl Notification {
         }
     }
 
+    /// Offset the position hints ask for, `y` being 0 when it isn't given.
+    pub fn requested_offset(&self) -> (f32, f32) {
+        (
+            self.data.hints.x as f32,
+            self.data.hints.y.unwrap_or_default() as f32,
+        )
+    }
+
+    pub fn offset(&self) -> (f32, f32) {
+        self.offset
+    }
+
+    /// Takes effect with the next `set_position`.
+    pub fn set_offset(&mut self, x: f32, y: f32) {
+        self.offset = (x, y);
+    }
+
+    /// Shows `value` in the progress bar, adding one if there's none yet. Takes effect with the
+    /// next `set_position`.
+    pub fn set_value(&mut self, value: i32) {
+        self.data.hints.value = Some(value);
+        match self.progress.as_mut() {
+            Some(progress) => progress.set_value(value),
+            None => {
+                self.progress = Some(Progress::new(
+                    self.data.id,
+                    value,
+                    self.ui_state.clone(),
+                    Arc::clone(&self.config),
+                    self.style_key.clone(),
+                ))
+            }
+        }
+    }
+
     pub fn urgency(&self) -> &Urgency {
         &self.data.hints.urgency
     }
@@ -590,4 +1440,134 @@ impl Notification {
     pub fn id(&self) -> NotificationId {
         self.data.id
     }
+
+    pub fn frame(&self) -> Frame {
+        let mut frame = self
+            .animation
+            .map_or(Frame::REST, |animation| animation.frame(Instant::now()));
+        frame.shift += self.swipe;
+        frame.offset += self.drag;
+        frame
+    }
+
+    /// Opacity of the notification as set by its style, not counting animations.
+    pub fn opacity(&self) -> f32 {
+        self.config
+            .find_style(&self.style_key, self.hovered)
+            .opacity
+            .unwrap_or(self.config.general.opacity)
+    }
+
+    /// [`Self::frame`] with [`Self::opacity`] applied, what the notification is drawn with.
+    pub fn drawn_frame(&self) -> Frame {
+        let mut frame = self.frame();
+        frame.opacity *= self.opacity();
+        frame
+    }
+
+    pub fn animating(&self) -> bool {
+        self.animation.is_some()
+    }
+
+    pub fn exiting(&self) -> bool {
+        self.animation
+            .is_some_and(|animation| animation.kind == Kind::Exit)
+    }
+
+    fn animate(&mut self, kind: Kind, from: Frame, to: Frame) {
+        let config = &self.config.general.animation;
+        if config.duration_ms == 0 {
+            return;
+        }
+
+        self.animation = Some(Animation::new(config, kind, from, to));
+    }
+
+    fn hidden_frame(&self) -> Frame {
+        Frame {
+            offset: -self.get_bounds().height / 2.,
+            shift: 0.,
+            opacity: 0.,
+        }
+    }
+
+    pub fn animate_in(&mut self) {
+        self.animate(Kind::Enter, self.hidden_frame(), Frame::REST);
+    }
+
+    /// Fades the notification out, a swiped one slides off to the side it was swiped to.
+    pub fn animate_out(&mut self) {
+        let from = self.frame();
+        let to = match from.shift {
+            shift if shift != 0. => Frame {
+                offset: from.offset,
+                shift: shift.signum() * self.get_bounds().width,
+                opacity: 0.,
+            },
+            _ => self.hidden_frame(),
+        };
+
+        self.swipe = 0.;
+        self.drag = 0.;
+        self.animate(Kind::Exit, from, to);
+    }
+
+    /// Shifts the notification `distance` pixels sideways, following a finger swiping it.
+    pub fn swipe(&mut self, distance: f32) {
+        if self.exiting() {
+            return;
+        }
+
+        self.animation = None;
+        self.swipe = distance;
+    }
+
+    /// Moves the notification `distance` pixels up or down, following the pointer dragging it.
+    pub fn drag(&mut self, distance: f32) {
+        if self.exiting() {
+            return;
+        }
+
+        self.animation = None;
+        self.drag = distance;
+    }
+
+    /// Eases a notification that wasn't swiped far enough, or was let go of after a drag,
+    /// back into place.
+    pub fn snap_back(&mut self) {
+        let from = self.frame();
+        self.swipe = 0.;
+        self.drag = 0.;
+        self.animate(Kind::Move, from, Frame::REST);
+    }
+
+    /// Slides the notification from `from_y` to where it was just laid out.
+    pub fn animate_move(&mut self, from_y: f32) {
+        if from_y == self.y || self.exiting() {
+            return;
+        }
+
+        let current = self.frame();
+        let from = Frame {
+            offset: current.offset - self.drag + from_y - self.y,
+            shift: current.shift - self.swipe,
+            opacity: current.opacity,
+        };
+        self.animate(Kind::Move, from, Frame::REST);
+    }
+
+    /// Drops finished enter and move animations, returns true once an exit animation is over
+    /// and the notification can be removed.
+    pub fn settle(&mut self, now: Instant) -> bool {
+        match self.animation {
+            Some(animation) if animation.finished(now) => {
+                if animation.kind == Kind::Exit {
+                    return true;
+                }
+                self.animation = None;
+                false
+            }
+            _ => false,
+        }
+    }
 }
@@ -1,6 +1,6 @@
 use crate::{
-    components::{Bounds, Component},
-    config::{self, border::BorderRadius, Config, Insets, Size},
+    components::{Bounds, Component, Layer},
+    config::{self, border::BorderRadius, Config, Insets, Size, StyleKey},
     manager::UiState,
     rendering::texture_renderer,
     utils::buffers,
@@ -10,7 +10,7 @@ use std::sync::{atomic::Ordering, Arc};
 
 pub struct Progress {
     id: u32,
-    app_name: Arc<str>,
+    style_key: StyleKey,
     ui_state: UiState,
     config: Arc<Config>,
     value: i32,
@@ -26,8 +26,12 @@ impl Component for Progress {
         &self.config
     }
 
-    fn get_app_name(&self) -> &str {
-        &self.app_name
+    fn get_style_key(&self) -> &StyleKey {
+        &self.style_key
+    }
+
+    fn set_style_key(&mut self, style_key: StyleKey) {
+        self.style_key = style_key;
     }
 
     fn get_id(&self) -> u32 {
@@ -44,7 +48,7 @@ impl Component for Progress {
 
     fn get_bounds(&self) -> Bounds {
         let style = self.config.find_style(
-            &self.app_name,
+            &self.style_key,
             self.ui_state.selected_id.load(Ordering::Relaxed) == self.id
                 && self.ui_state.selected.load(Ordering::Relaxed),
         );
@@ -91,7 +95,7 @@ impl Component for Progress {
         let bounds = self.get_bounds();
 
         let style = self.config.find_style(
-            &self.app_name,
+            &self.style_key,
             self.ui_state.selected_id.load(Ordering::Relaxed) == self.id
                 && self.ui_state.selected.load(Ordering::Relaxed),
         );
@@ -165,7 +169,7 @@ impl Component for Progress {
                 border_size: border_size.into(),
                 border_color: style.border.color.to_linear(urgency),
                 scale: self.ui_state.scale.load(Ordering::Relaxed),
-                depth: 0.8,
+                depth: Layer::Progress.depth(),
             });
         }
 
@@ -200,7 +204,7 @@ impl Component for Progress {
                     border_size: border_size.into(),
                     border_color: style.border.color.to_linear(urgency),
                     scale: self.ui_state.scale.load(Ordering::Relaxed),
-                    depth: 0.8,
+                    depth: Layer::Progress.depth(),
                 });
             }
         }
@@ -219,11 +223,11 @@ impl Progress {
         value: i32,
         ui_state: UiState,
         config: Arc<Config>,
-        app_name: Arc<str>,
+        style_key: StyleKey,
     ) -> Self {
         Self {
             id,
-            app_name,
+            style_key,
             config,
             ui_state,
             value,
@@ -236,6 +240,10 @@ impl Progress {
     pub fn set_width(&mut self, width: f32) {
         self.width = width;
     }
+
+    pub fn set_value(&mut self, value: i32) {
+        self.value = value;
+    }
 }
 
 #[cfg(test)]
@@ -250,8 +258,11 @@ mod tests {
     fn create_test_progress(value: i32) -> Progress {
         let config = Arc::new(Config::default());
 
-        let app_name = Arc::from("test_app");
-        let mut progress = Progress::new(1, value, UiState::default(), config, app_name);
+        let style_key = StyleKey {
+            app_name: "test_app".into(),
+            ..Default::default()
+        };
+        let mut progress = Progress::new(1, value, UiState::default(), config, style_key);
         progress.set_width(300.0);
         progress.set_position(0.0, 0.0);
 
@@ -267,7 +278,7 @@ mod tests {
         assert_eq!(progress.x, 0.0);
         assert_eq!(progress.y, 0.0);
         assert_eq!(progress.width, 300.0);
-        assert_eq!(&*progress.app_name, "test_app");
+        assert_eq!(&*progress.style_key.app_name, "test_app");
     }
 
     #[test]
@@ -423,8 +434,7 @@ mod tests {
             ..Default::default()
         };
 
-        let app_name = Arc::from("test_app");
-        let progress = Progress::new(1, 50, ui_state, config, app_name);
+        let progress = Progress::new(1, 50, ui_state, config, StyleKey::default());
 
         assert!(progress.get_ui_state().selected.load(Ordering::Relaxed));
         assert_eq!(
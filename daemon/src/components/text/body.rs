@@ -1,21 +1,32 @@
 use super::{
-    markup::{Parser, Tag},
+    align_rtl, layout_width,
+    markup::{self, Parser, Tag},
+    shape_cache::{ShapeCache, ShapeKey},
     Text,
 };
 use crate::{
-    components::{notification::NotificationId, Bounds, Component, Data},
-    config::{self, Config},
+    components::{notification::NotificationId, Bounds, Component, Data, Layer},
+    config::{self, Config, StyleKey},
     manager::UiState,
-    utils::buffers,
-    Urgency,
+    rendering::texture_renderer::{TextureArea, TextureBounds},
+    utils::{buffers, image_data::ImageData},
+    Event, Urgency,
 };
-use glyphon::{Attrs, Buffer, Color, Family, FontSystem, Shaping, Stretch, Style, Weight};
-use std::sync::{atomic::Ordering, Arc};
+use glyphon::{Attrs, Buffer, BufferLine, Family, FontSystem, Shaping, Stretch, Style, Weight};
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{atomic::Ordering, Arc},
+};
+
+/// Appended to the last visible line of a body that's cut off.
+const ELLIPSIS: &str = "…";
 
 #[derive(Debug)]
 pub struct Anchor {
     pub href: Arc<str>,
     pub line: usize,
+    /// Byte range of the anchor's text within its line.
     pub start: usize,
     pub end: usize,
     pub bounds: Bounds,
@@ -25,15 +36,65 @@ impl Anchor {
     pub fn get_bounds(&self) -> Bounds {
         Bounds { ..self.bounds }
     }
+
+    /// Anchors on lines cut off by `max_lines` are left without bounds.
+    pub fn visible(&self) -> bool {
+        self.bounds.width > 0.
+    }
+}
+
+/// Image of an `<img>` tag, drawn below the line the tag is on.
+struct InlineImage {
+    src: Arc<str>,
+    line: usize,
+    state: ImageState,
+}
+
+#[derive(Clone)]
+enum ImageState {
+    /// Still being decoded, nothing takes its place until then.
+    Pending,
+    Loaded(ImageData),
+    /// Couldn't be decoded or isn't a local file, its alt text is shown instead.
+    Failed,
+}
+
+/// Local file an `<img>` source points at, remote images aren't fetched.
+fn image_path(src: &str) -> Option<PathBuf> {
+    match url::Url::parse(src) {
+        Ok(url) if url.scheme() == "file" => url.to_file_path().ok(),
+        Ok(_) => None,
+        Err(_) => Path::new(src).is_absolute().then(|| src.into()),
+    }
+}
+
+fn load_image(path: &Path, max_height: u32) -> Option<ImageData> {
+    ImageData::open_max_height(path, max_height)
+        .inspect_err(|e| log::warn!("Failed to load image {}: {e}", path.display()))
+        .ok()
 }
 
 pub struct Body {
     id: NotificationId,
-    app_name: Arc<str>,
+    style_key: StyleKey,
     ui_state: UiState,
     pub anchors: Vec<Arc<Anchor>>,
     config: Arc<Config>,
     pub buffer: Buffer,
+    /// Lines of the whole text, the buffer only holds the visible part of them while collapsed.
+    /// Shared with the shape cache.
+    lines: Rc<[BufferLine]>,
+    /// Markup the text was last set from, it's laid out again when an image fails to load.
+    text: String,
+    images: Vec<InlineImage>,
+    expanded: bool,
+    overflowing: bool,
+    /// Index of the anchor under the pointer.
+    hovered_anchor: Option<usize>,
+    /// Links opened from this or any other notification.
+    visited: Vec<Arc<str>>,
+    /// Lines of texts shaped by other notifications, shared by the ones shown together.
+    shape_cache: Option<Rc<ShapeCache>>,
     x: f32,
     y: f32,
 }
@@ -130,6 +191,7 @@ fn parse_color(value: &str) -> Option<glyphon::Color> {
 impl Text for Body {
     fn set_size(&mut self, font_system: &mut FontSystem, width: Option<f32>, height: Option<f32>) {
         self.buffer.set_size(font_system, width, height);
+        self.ellipsize(font_system);
     }
 
     fn set_text<T>(&mut self, font_system: &mut FontSystem, text: T)
@@ -139,34 +201,91 @@ impl Text for Body {
         let family = Arc::clone(&self.get_style().family);
 
         let attrs = Attrs::new()
-            .metadata(0.7_f32.to_bits() as usize)
+            .metadata(Layer::Text.metadata())
             .family(glyphon::Family::Name(&family));
 
+        self.text = text.as_ref().to_string();
+        let previous = std::mem::take(&mut self.images);
+
+        let limits = self.config.general.limits;
+        if !self.config.general.markup {
+            self.anchors.clear();
+            let text = markup::truncate(text.as_ref(), limits.max_length);
+            let key = ShapeKey::new(
+                &[(&*text, attrs.clone())],
+                self.buffer.metrics(),
+                self.buffer.size().0,
+            );
+            self.shape(font_system, key, |buffer, font_system| {
+                buffer.set_text(font_system, &text, &attrs, Shaping::Advanced)
+            });
+            self.ellipsize(font_system);
+            return;
+        }
+
         let mut anchors = Vec::new();
 
-        let mut parser = Parser::new(text.as_ref().to_string());
+        let mut parser = Parser::new(text.as_ref().to_string()).with_limits(limits);
         let body = parser.parse();
+
+        // Images that were already loaded or given up on keep their state
+        let images = body
+            .iter()
+            .filter_map(|tag| match tag {
+                Tag::Image { src, position, .. } => Some((src, position.line)),
+                _ => None,
+            })
+            .map(|(src, line)| {
+                let state = match previous.iter().find(|image| *image.src == **src) {
+                    Some(image) => image.state.clone(),
+                    None if image_path(src).is_some() => ImageState::Pending,
+                    None => {
+                        log::debug!("Showing alt text of image {src}, it isn't a local file");
+                        ImageState::Failed
+                    }
+                };
+
+                InlineImage {
+                    src: src.as_str().into(),
+                    line,
+                    state,
+                }
+            })
+            .collect::<Vec<_>>();
+
         let spans = body
             .iter()
             .map(|tag| match tag {
                 Tag::Bold(text) => (text.as_str(), attrs.clone().weight(Weight::BOLD)),
                 Tag::Italic(text) => (text.as_str(), attrs.clone().style(Style::Italic)),
                 Tag::Underline(text) => (text.as_str(), attrs.clone()),
-                Tag::Image { alt, src: _ } => (alt.as_str(), attrs.clone()),
+                Tag::Image { alt, src, .. } => {
+                    let failed = images.iter().any(|image| {
+                        *image.src == **src && matches!(image.state, ImageState::Failed)
+                    });
+                    match failed {
+                        true => (alt.as_str(), attrs.clone()),
+                        false => ("", attrs.clone()),
+                    }
+                }
                 Tag::Anchor {
                     href,
                     text,
                     position,
                 } => {
+                    // The column is in characters, it's turned into bytes once the line exists
                     let anchor = Anchor {
                         href: href.as_str().into(),
                         line: position.line,
                         start: position.column,
-                        end: position.column + text.len() - 1,
+                        end: text.len(),
                         bounds: Bounds::default(),
                     };
+                    let color = self
+                        .link_color(anchors.len(), href)
+                        .into_glyphon(&self.style_key.urgency);
                     anchors.push(anchor);
-                    (text.as_str(), attrs.clone().color(Color::rgb(0, 0, 255)))
+                    (text.as_str(), attrs.clone().color(color))
                 }
                 Tag::Span { text, attributes } => {
                     let mut attrs = attrs.clone();
@@ -318,26 +437,27 @@ impl Text for Body {
             })
             .collect::<Vec<_>>();
 
-        self.buffer
-            .set_rich_text(font_system, spans, &attrs, Shaping::Advanced, None);
+        let key = ShapeKey::new(&spans, self.buffer.metrics(), self.buffer.size().0);
+        self.shape(font_system, key, |buffer, font_system| {
+            buffer.set_rich_text(font_system, spans, &attrs, Shaping::Advanced, None)
+        });
 
         anchors.iter_mut().for_each(|anchor| {
-            if let Some(line) = self.buffer.layout_runs().nth(anchor.line) {
-                let first = line.glyphs.get(anchor.start);
-                let last = line.glyphs.get(anchor.end);
-
-                if let (Some(first), Some(last)) = (first, last) {
-                    anchor.bounds = Bounds {
-                        x: first.x,
-                        y: line.line_top,
-                        width: last.x + last.w,
-                        height: line.line_height,
-                    };
-                }
+            let Some(line) = self.lines.get(anchor.line) else {
+                return;
             };
+            let text = line.text();
+            let start = text
+                .char_indices()
+                .nth(anchor.start)
+                .map_or(text.len(), |(i, _)| i);
+            anchor.start = start;
+            anchor.end = (start + anchor.end).min(text.len());
         });
 
         self.anchors = anchors.into_iter().map(Arc::new).collect();
+        self.images = images;
+        self.ellipsize(font_system);
     }
 }
 
@@ -348,8 +468,12 @@ impl Component for Body {
         &self.config
     }
 
-    fn get_app_name(&self) -> &str {
-        &self.app_name
+    fn get_style_key(&self) -> &StyleKey {
+        &self.style_key
+    }
+
+    fn set_style_key(&mut self, style_key: StyleKey) {
+        self.style_key = style_key;
     }
 
     fn get_id(&self) -> u32 {
@@ -368,7 +492,7 @@ impl Component for Body {
         let style = self.get_style();
         let bounds = self.get_render_bounds();
 
-        vec![buffers::Instance {
+        let mut instances = vec![buffers::Instance {
             rect_pos: [bounds.x, bounds.y],
             rect_size: [bounds.width, bounds.height],
             rect_color: style.background.to_linear(urgency),
@@ -376,8 +500,13 @@ impl Component for Body {
             border_size: style.border.size.into(),
             border_color: style.border.color.to_linear(urgency),
             scale: self.ui_state.scale.load(Ordering::Relaxed),
-            depth: 0.8,
-        }]
+            depth: Layer::Element.depth(),
+        }];
+        if style.link.underline {
+            instances.extend(self.underlines(urgency));
+        }
+
+        instances
     }
 
     fn get_text_areas(&self, urgency: &crate::Urgency) -> Vec<glyphon::TextArea<'_>> {
@@ -398,35 +527,93 @@ impl Component for Body {
 
         let left = render_bounds.x + style.border.size.left + style.padding.left;
         let top = render_bounds.y + style.border.size.top + style.padding.top;
+        let bottom = top + content_height;
+
+        // The text is cut below every line with images under it, and what follows the cut is
+        // drawn that much lower
+        let mut areas = Vec::new();
+        let mut start = 0.;
+        let mut offset = 0.;
+        for (end, height) in self
+            .image_rows()
+            .into_iter()
+            .map(|(row_bottom, image)| (row_bottom, image.height() as f32))
+            .chain([(f32::INFINITY, 0.)])
+        {
+            if end > start {
+                areas.push(glyphon::TextArea {
+                    buffer: &self.buffer,
+                    left,
+                    top: top + offset,
+                    scale: self.ui_state.scale.load(Ordering::Relaxed),
+                    bounds: glyphon::TextBounds {
+                        left: left as i32,
+                        top: (top + offset + start) as i32,
+                        right: (left + content_width) as i32,
+                        bottom: (top + offset + end).min(bottom) as i32,
+                    },
+                    default_color: style.color.into_glyphon(urgency),
+                    custom_glyphs: &[],
+                });
+            }
+            start = end;
+            offset += height;
+        }
 
-        vec![glyphon::TextArea {
-            buffer: &self.buffer,
-            left,
-            top,
-            scale: self.ui_state.scale.load(Ordering::Relaxed),
-            bounds: glyphon::TextBounds {
-                left: left as i32,
-                top: top as i32,
-                right: (left + content_width) as i32,
-                bottom: (top + content_height) as i32,
-            },
-            default_color: style.color.into_glyphon(urgency),
-            custom_glyphs: &[],
-        }]
+        areas
     }
 
-    fn get_textures(&self) -> Vec<crate::rendering::texture_renderer::TextureArea<'_>> {
-        Vec::new()
+    fn get_textures(&self) -> Vec<TextureArea<'_>> {
+        let style = self.get_style();
+        let render_bounds = self.get_render_bounds();
+
+        let left = render_bounds.x + style.border.size.left + style.padding.left;
+        let top = render_bounds.y + style.border.size.top + style.padding.top;
+        let right = render_bounds.x + render_bounds.width - style.border.size.right;
+
+        let mut offset = 0.;
+        self.image_rows()
+            .into_iter()
+            .map(|(bottom, image)| {
+                let (width, height) = (image.width() as f32, image.height() as f32);
+                let y = top + bottom + offset;
+                offset += height;
+
+                TextureArea {
+                    left,
+                    top: y,
+                    width,
+                    height,
+                    scale: self.ui_state.scale.load(Ordering::Relaxed),
+                    border_size: [0.; 4],
+                    bounds: TextureBounds {
+                        left: left as u32,
+                        top: y as u32,
+                        right: (left + width).min(right) as u32,
+                        bottom: (y + height) as u32,
+                    },
+                    data: image.data(),
+                    data_size: image.size(),
+                    radius: [0.; 4],
+                    depth: Layer::Icon.depth(),
+                    opacity: 1.,
+                }
+            })
+            .collect()
     }
 
     fn get_bounds(&self) -> Bounds {
         let style = self.get_style();
-        let (width, total_lines) = self
-            .buffer
-            .layout_runs()
-            .fold((0.0, 0.0), |(width, total_lines), run| {
-                (run.line_w.max(width), total_lines + 1.0)
-            });
+        let images = self.image_rows();
+        let width = images
+            .iter()
+            .map(|(_, image)| image.width() as f32)
+            .fold(layout_width(&self.buffer), f32::max);
+        let images_height = images
+            .iter()
+            .map(|(_, image)| image.height() as f32)
+            .sum::<f32>();
+        let total_lines = self.buffer.layout_runs().count() as f32;
 
         Bounds {
             x: self.x,
@@ -439,6 +626,7 @@ impl Component for Body {
                 + style.border.size.left
                 + style.border.size.right,
             height: total_lines * self.buffer.metrics().line_height
+                + images_height
                 + style.margin.top
                 + style.margin.bottom
                 + style.padding.top
@@ -469,6 +657,7 @@ impl Component for Body {
             .into_iter()
             .map(Data::Instance)
             .chain(self.get_text_areas(urgency).into_iter().map(Data::TextArea))
+            .chain(self.get_textures().into_iter().map(Data::Texture))
             .collect()
     }
 }
@@ -477,7 +666,7 @@ impl Body {
     pub fn new(
         id: NotificationId,
         config: Arc<Config>,
-        app_name: Arc<str>,
+        style_key: StyleKey,
         ui_state: UiState,
         font_system: &mut FontSystem,
     ) -> Self {
@@ -496,8 +685,362 @@ impl Body {
             y: 0.,
             config,
             ui_state,
-            app_name,
+            style_key,
             anchors: Vec::new(),
+            lines: Rc::new([]),
+            text: String::new(),
+            images: Vec::new(),
+            expanded: false,
+            overflowing: false,
+            hovered_anchor: None,
+            visited: Vec::new(),
+            shape_cache: None,
+        }
+    }
+
+    pub fn shape_cache(&self) -> Option<Rc<ShapeCache>> {
+        self.shape_cache.clone()
+    }
+
+    /// Takes the lines of texts shaped before from `shape_cache`, and adds the ones it shapes.
+    pub fn set_shape_cache(&mut self, shape_cache: Option<Rc<ShapeCache>>) {
+        self.shape_cache = shape_cache;
+    }
+
+    /// Shapes the lines of the text with `shape`, unless a text with the same spans was shaped
+    /// before. They're only copied into the buffer by `ellipsize`, which has to follow.
+    fn shape<F>(&mut self, font_system: &mut FontSystem, key: ShapeKey, shape: F)
+    where
+        F: FnOnce(&mut Buffer, &mut FontSystem),
+    {
+        self.lines = match self.shape_cache.as_ref().and_then(|cache| cache.get(&key)) {
+            Some(lines) => lines,
+            None => {
+                shape(&mut self.buffer, font_system);
+                let lines: Rc<[BufferLine]> = std::mem::take(&mut self.buffer.lines).into();
+                if let Some(cache) = self.shape_cache.as_ref() {
+                    cache.insert(key, Rc::clone(&lines));
+                }
+                lines
+            }
+        };
+    }
+
+    /// Whether the text takes up more than `max_lines` lines, so there's something to expand.
+    pub fn overflowing(&self) -> bool {
+        self.overflowing
+    }
+
+    /// The whole text without markup, as it reads on screen.
+    pub fn text(&self) -> String {
+        self.lines
+            .iter()
+            .map(BufferLine::text)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn expanded(&self) -> bool {
+        self.expanded
+    }
+
+    pub fn set_expanded(&mut self, font_system: &mut FontSystem, expanded: bool) {
+        self.expanded = expanded;
+        self.ellipsize(font_system);
+    }
+
+    /// Decodes the images still waiting to be loaded off the main thread, each one comes back to
+    /// the event loop as `Event::ImageLoaded`. Without a sender they're decoded right away.
+    pub fn load_images(
+        &mut self,
+        font_system: &mut FontSystem,
+        sender: Option<&calloop::channel::Sender<Event>>,
+    ) {
+        let max_height = self.get_style().image_max_height;
+        let mut pending = self
+            .images
+            .iter()
+            .filter(|image| matches!(image.state, ImageState::Pending))
+            .filter_map(|image| Some((Arc::clone(&image.src), image_path(&image.src)?)))
+            .collect::<Vec<_>>();
+        pending.sort_unstable();
+        pending.dedup();
+
+        pending.into_iter().for_each(|(src, path)| match sender {
+            Some(sender) => {
+                let (id, sender) = (self.id, sender.clone());
+                rayon::spawn(move || {
+                    let image = load_image(&path, max_height);
+                    _ = sender.send(Event::ImageLoaded {
+                        id,
+                        src,
+                        image: image.map(Box::new),
+                    });
+                });
+            }
+            None => {
+                self.set_image(font_system, &src, load_image(&path, max_height));
+            }
+        });
+    }
+
+    /// Puts the decoded image of source `src` below its line, or its alt text in place of the
+    /// tag when it couldn't be decoded. Returns false when no image is waiting on `src`.
+    pub fn set_image(
+        &mut self,
+        font_system: &mut FontSystem,
+        src: &str,
+        image: Option<ImageData>,
+    ) -> bool {
+        let mut found = false;
+        self.images
+            .iter_mut()
+            .filter(|inline| *inline.src == *src && matches!(inline.state, ImageState::Pending))
+            .for_each(|inline| {
+                found = true;
+                inline.state = match image.as_ref() {
+                    Some(image) => ImageState::Loaded(image.clone()),
+                    None => ImageState::Failed,
+                };
+            });
+
+        if !found {
+            return false;
+        }
+
+        match image {
+            // The text stays the same, only what comes after the image moves down
+            Some(_) => self.locate_anchors(),
+            None => {
+                let text = std::mem::take(&mut self.text);
+                self.set_text(font_system, text);
+            }
+        }
+
+        true
+    }
+
+    /// Color of anchor `index` linking to `href`, depending on whether it's hovered or was
+    /// opened before.
+    fn link_color(&self, index: usize, href: &str) -> config::color::Color {
+        let link = &self.get_style().link;
+        if self.hovered_anchor == Some(index) {
+            link.hover_color
+        } else if self.visited.iter().any(|visited| **visited == *href) {
+            link.visited_color
+        } else {
+            link.color
+        }
+    }
+
+    /// Colors anchor `index` as hovered, None for no anchor. The text is only set again when
+    /// that changes, which gives the anchors new places, returns whether it did.
+    pub fn set_hovered_anchor(
+        &mut self,
+        font_system: &mut FontSystem,
+        index: Option<usize>,
+    ) -> bool {
+        if self.hovered_anchor == index {
+            return false;
+        }
+
+        self.hovered_anchor = index;
+        let text = std::mem::take(&mut self.text);
+        self.set_text(font_system, text);
+
+        true
+    }
+
+    /// Colors anchors linking to `href` as visited, returns false when there's none or they
+    /// already are.
+    pub fn visit(&mut self, font_system: &mut FontSystem, href: &str) -> bool {
+        if self.visited.iter().any(|visited| **visited == *href)
+            || !self.anchors.iter().any(|anchor| *anchor.href == *href)
+        {
+            return false;
+        }
+
+        self.visited.push(href.into());
+        let text = std::mem::take(&mut self.text);
+        self.set_text(font_system, text);
+
+        true
+    }
+
+    /// Lines under the anchors, one for every row an anchor wraps onto.
+    fn underlines(&self, urgency: &Urgency) -> Vec<buffers::Instance> {
+        let style = self.get_style();
+        let render_bounds = self.get_render_bounds();
+        let left = render_bounds.x + style.border.size.left + style.padding.left;
+        let top = render_bounds.y + style.border.size.top + style.padding.top;
+        let thickness = (self.buffer.metrics().font_size / 14.).max(1.);
+        let scale = self.ui_state.scale.load(Ordering::Relaxed);
+
+        self.anchors
+            .iter()
+            .enumerate()
+            .flat_map(|(index, anchor)| {
+                let color = self.link_color(index, &anchor.href).to_linear(urgency);
+                let offset = self.image_offset(anchor.line);
+                self.buffer
+                    .layout_runs()
+                    .filter(move |run| run.line_i == anchor.line)
+                    .filter_map(move |run| {
+                        let (start, end) = run
+                            .glyphs
+                            .iter()
+                            .filter(|glyph| glyph.start >= anchor.start && glyph.end <= anchor.end)
+                            .map(|glyph| (glyph.x, glyph.x + glyph.w))
+                            .reduce(|(start, end), (l, r)| (start.min(l), end.max(r)))?;
+
+                        Some(buffers::Instance {
+                            rect_pos: [left + start, top + offset + run.line_y + thickness],
+                            rect_size: [end - start, thickness],
+                            rect_color: color,
+                            border_radius: [0.; 4],
+                            border_size: [0.; 4],
+                            border_color: color,
+                            scale,
+                            depth: Layer::Text.depth(),
+                        })
+                    })
+            })
+            .collect()
+    }
+
+    /// Loaded images whose line is visible, along with where the last row of that line ends
+    /// within the text. Several images on one line are stacked in the order they appear.
+    fn image_rows(&self) -> Vec<(f32, &ImageData)> {
+        self.images
+            .iter()
+            .filter_map(|inline| match &inline.state {
+                ImageState::Loaded(image) => Some((inline.line, image)),
+                _ => None,
+            })
+            .filter_map(|(line, image)| {
+                self.buffer
+                    .layout_runs()
+                    .filter(|run| run.line_i == line)
+                    .last()
+                    .map(|run| (run.line_top + run.line_height, image))
+            })
+            .collect()
+    }
+
+    /// How far the images above line `line` push it down.
+    fn image_offset(&self, line: usize) -> f32 {
+        self.images
+            .iter()
+            .filter(|inline| inline.line < line)
+            .filter_map(|inline| match &inline.state {
+                ImageState::Loaded(image) => Some(image.height() as f32),
+                _ => None,
+            })
+            .sum()
+    }
+
+    /// Places anchors where their glyphs ended up, lines mixing directions can reorder an
+    /// anchor away from where its text starts.
+    fn locate_anchors(&mut self) {
+        let buffer = &self.buffer;
+        self.anchors = self
+            .anchors
+            .iter()
+            .map(|anchor| {
+                let offset = self.image_offset(anchor.line);
+                let bounds = buffer
+                    .layout_runs()
+                    .filter(|run| run.line_i == anchor.line)
+                    .flat_map(|run| {
+                        run.glyphs
+                            .iter()
+                            .filter(|glyph| glyph.start >= anchor.start && glyph.end <= anchor.end)
+                            .map(move |glyph| {
+                                (
+                                    glyph.x,
+                                    glyph.x + glyph.w,
+                                    run.line_top,
+                                    run.line_top + run.line_height,
+                                )
+                            })
+                    })
+                    .reduce(|(left, right, top, bottom), (l, r, t, b)| {
+                        (left.min(l), right.max(r), top.min(t), bottom.max(b))
+                    })
+                    .map(|(left, right, top, bottom)| Bounds {
+                        x: left,
+                        y: top + offset,
+                        width: right - left,
+                        height: bottom - top,
+                    })
+                    .unwrap_or_default();
+
+                Arc::new(Anchor {
+                    href: Arc::clone(&anchor.href),
+                    line: anchor.line,
+                    start: anchor.start,
+                    end: anchor.end,
+                    bounds,
+                })
+            })
+            .collect();
+    }
+
+    /// Lays the whole text out again and, unless expanded, cuts it down to `max_lines` lines with
+    /// the last one ending in an ellipsis.
+    fn ellipsize(&mut self, font_system: &mut FontSystem) {
+        self.buffer.lines = self.lines.to_vec();
+        self.buffer
+            .lines
+            .iter_mut()
+            .for_each(BufferLine::reset_layout);
+        self.buffer.shape_until_scroll(font_system, false);
+        align_rtl(&mut self.buffer, font_system);
+        self.locate_anchors();
+
+        let max_lines = match self.get_style().max_lines {
+            Some(max_lines) if max_lines > 0 => max_lines as usize,
+            _ => {
+                self.overflowing = false;
+                return;
+            }
+        };
+
+        self.overflowing = self.buffer.layout_runs().count() > max_lines;
+        if !self.overflowing || self.expanded {
+            return;
+        }
+
+        let Some((line_i, mut cuts)) = self.buffer.layout_runs().nth(max_lines - 1).map(|run| {
+            let cuts: Vec<usize> = run
+                .glyphs
+                .iter()
+                .map(|glyph| glyph.start)
+                .chain(run.glyphs.iter().map(|glyph| glyph.end).max())
+                .collect();
+            (run.line_i, cuts)
+        }) else {
+            return;
+        };
+        cuts.sort_unstable();
+        cuts.dedup();
+
+        // Glyphs are dropped from the end of the last visible line until the ellipsis fits on it
+        let lines = self.buffer.lines.clone();
+        for cut in cuts.into_iter().rev() {
+            let mut line = lines[line_i].clone();
+            let cut = line.text()[..cut].trim_end().len();
+            let mut ellipsis = line.split_off(cut);
+            ellipsis.set_text(ELLIPSIS, line.ending(), ellipsis.attrs_list().clone());
+            line.append(ellipsis);
+
+            self.buffer.lines = lines[..line_i].iter().cloned().chain([line]).collect();
+            self.buffer.shape_until_scroll(font_system, false);
+            if self.buffer.layout_runs().count() <= max_lines {
+                // Cutting the line moves what's left of it when it's aligned to the right
+                self.locate_anchors();
+                return;
+            }
         }
     }
 }
@@ -522,7 +1065,7 @@ mod tests {
         let mut body = Body::new(
             0,
             Arc::new(Config::default()),
-            "".into(),
+            StyleKey::default(),
             UiState::default(),
             &mut font_system,
         );
@@ -541,13 +1084,127 @@ mod tests {
         assert_eq!(lines.len(), 5);
     }
 
+    #[test]
+    fn test_max_lines() {
+        let mut font_system = FontSystem::new();
+        let mut config = Config::default();
+        config.styles.default.body.max_lines = Some(2);
+        config.styles.hover.body.max_lines = Some(2);
+
+        let mut body = Body::new(
+            0,
+            Arc::new(config),
+            StyleKey::default(),
+            UiState::default(),
+            &mut font_system,
+        );
+        body.set_text(&mut font_system, "Lorem ipsum dolor sit amet ".repeat(10));
+        body.set_size(&mut font_system, Some(150.), None);
+
+        let runs = |body: &Body| body.buffer.layout_runs().count();
+        let collapsed = body.get_bounds().height;
+
+        assert!(body.overflowing());
+        assert_eq!(runs(&body), 2);
+        assert!(body
+            .buffer
+            .layout_runs()
+            .last()
+            .is_some_and(|run| run.text.ends_with(ELLIPSIS)));
+
+        body.set_expanded(&mut font_system, true);
+        assert!(runs(&body) > 2);
+        assert!(body.get_bounds().height > collapsed);
+
+        body.set_expanded(&mut font_system, false);
+        assert_eq!(runs(&body), 2);
+        assert_eq!(body.get_bounds().height, collapsed);
+    }
+
+    #[test]
+    fn test_inline_images() {
+        let path = std::env::temp_dir().join("moxnotify-inline-image.png");
+        image::RgbaImage::new(40, 20).save(&path).unwrap();
+
+        let mut font_system = FontSystem::new();
+        let mut body = Body::new(
+            0,
+            Arc::new(Config::default()),
+            StyleKey::default(),
+            UiState::default(),
+            &mut font_system,
+        );
+        body.set_text(
+            &mut font_system,
+            format!(
+                "Local <img src=\"file://{}\" alt=\"local\"/>\nMissing <img src=\"/nonexistent/moxnotify.png\" alt=\"missing\"/>\nRemote <img src=\"https://example.com/image.png\" alt=\"remote\"/>",
+                path.display()
+            ),
+        );
+        body.load_images(&mut font_system, None);
+        std::fs::remove_file(&path).unwrap();
+
+        // Only images that loaded leave their alt text out
+        assert_eq!(body.text(), "Local \nMissing missing\nRemote remote");
+
+        let textures = body.get_textures();
+        assert_eq!(textures.len(), 1);
+        assert_eq!(textures[0].data_size, (40, 20));
+
+        let line_height = body.buffer.metrics().line_height;
+        let style = body.get_style();
+        let chrome = style.margin.top
+            + style.margin.bottom
+            + style.padding.top
+            + style.padding.bottom
+            + style.border.size.top
+            + style.border.size.bottom;
+        assert!((body.get_bounds().height - (3. * line_height + 20. + chrome)).abs() < 0.01);
+
+        // Lines after the image are drawn below it
+        let areas = body.get_text_areas(&Urgency::Normal);
+        assert_eq!(areas.len(), 2);
+        assert_eq!(areas[1].top - areas[0].top, 20.);
+        assert_eq!(textures[0].top, areas[0].top + line_height);
+    }
+
+    #[test]
+    fn test_image_max_height() {
+        let path = std::env::temp_dir().join("moxnotify-image-max-height.png");
+        image::RgbaImage::new(400, 20).save(&path).unwrap();
+
+        let mut font_system = FontSystem::new();
+        let mut config = Config::default();
+        config.styles.default.body.image_max_height = 10;
+        config.styles.hover.body.image_max_height = 10;
+
+        let mut body = Body::new(
+            0,
+            Arc::new(config),
+            StyleKey::default(),
+            UiState::default(),
+            &mut font_system,
+        );
+        body.set_text(
+            &mut font_system,
+            format!("<img src=\"file://{}\" alt=\"wide\"/>", path.display()),
+        );
+        body.load_images(&mut font_system, None);
+        std::fs::remove_file(&path).unwrap();
+
+        // Only the height is bounded, the image keeps its aspect ratio
+        let textures = body.get_textures();
+        assert_eq!(textures.len(), 1);
+        assert_eq!(textures[0].data_size, (200, 10));
+    }
+
     #[test]
     fn test_plain_url_detection() {
         let mut font_system = FontSystem::new();
         let mut body = Body::new(
             0,
             Arc::new(Config::default()),
-            "".into(),
+            StyleKey::default(),
             UiState::default(),
             &mut font_system,
         );
@@ -568,7 +1225,7 @@ mod tests {
         let mut body = Body::new(
             0,
             Arc::new(Config::default()),
-            "".into(),
+            StyleKey::default(),
             UiState::default(),
             &mut font_system,
         );
@@ -583,6 +1240,118 @@ mod tests {
         assert_eq!(body.anchors[1].href.as_ref(), "http://test.org");
     }
 
+    #[test]
+    fn test_link_states() {
+        let mut font_system = FontSystem::new();
+        let mut body = Body::new(
+            0,
+            Arc::new(Config::default()),
+            StyleKey::default(),
+            UiState::default(),
+            &mut font_system,
+        );
+
+        body.set_text(
+            &mut font_system,
+            "<a href=\"https://example.com\">first</a> and <a href=\"http://test.org\">second</a>",
+        );
+        body.set_size(&mut font_system, Some(300.), None);
+
+        let link = body.get_style().link.clone();
+        let colors = |body: &Body| {
+            body.underlines(&Urgency::Normal)
+                .iter()
+                .map(|underline| underline.rect_color)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(colors(&body), [link.color.to_linear(&Urgency::Normal); 2]);
+
+        assert!(body.set_hovered_anchor(&mut font_system, Some(1)));
+        assert!(!body.set_hovered_anchor(&mut font_system, Some(1)));
+        assert_eq!(
+            colors(&body),
+            [
+                link.color.to_linear(&Urgency::Normal),
+                link.hover_color.to_linear(&Urgency::Normal)
+            ]
+        );
+
+        assert!(body.visit(&mut font_system, "https://example.com"));
+        assert!(!body.visit(&mut font_system, "https://example.com"));
+        assert!(!body.visit(&mut font_system, "https://elsewhere.org"));
+        assert_eq!(
+            colors(&body)[0],
+            link.visited_color.to_linear(&Urgency::Normal)
+        );
+        assert_eq!(body.anchors.len(), 2);
+    }
+
+    /// Horizontal extent of the glyphs on the first line that aren't ASCII.
+    fn non_ascii_extent(body: &Body) -> (f32, f32) {
+        let run = body.buffer.layout_runs().next().unwrap();
+        run.glyphs
+            .iter()
+            .filter(|glyph| !run.text[glyph.start..glyph.end].is_ascii())
+            .fold((f32::MAX, f32::MIN), |(left, right), glyph| {
+                (left.min(glyph.x), right.max(glyph.x + glyph.w))
+            })
+    }
+
+    #[test]
+    fn test_anchor_in_ltr_paragraph() {
+        let mut font_system = FontSystem::new();
+        let mut body = Body::new(
+            0,
+            Arc::new(Config::default()),
+            StyleKey::default(),
+            UiState::default(),
+            &mut font_system,
+        );
+
+        body.set_text(
+            &mut font_system,
+            "<a href=\"https://example.com\">English</a> عربي",
+        );
+        body.set_size(&mut font_system, Some(300.), None);
+
+        assert!(!body.buffer.layout_runs().next().unwrap().rtl);
+        let anchor = body.anchors[0].get_bounds();
+        let (arabic_left, _) = non_ascii_extent(&body);
+        assert_eq!(anchor.x, 0.);
+        assert!(anchor.width > 0.);
+        assert!(anchor.x + anchor.width <= arabic_left);
+    }
+
+    #[test]
+    fn test_anchor_in_rtl_paragraph() {
+        let mut font_system = FontSystem::new();
+        let mut body = Body::new(
+            0,
+            Arc::new(Config::default()),
+            StyleKey::default(),
+            UiState::default(),
+            &mut font_system,
+        );
+
+        body.set_text(
+            &mut font_system,
+            "عربي <a href=\"https://example.com\">English</a>",
+        );
+        body.set_size(&mut font_system, Some(300.), None);
+
+        let run = body.buffer.layout_runs().next().unwrap();
+        assert!(run.rtl);
+        assert!(run.line_w < 300.);
+
+        // The paragraph hugs the right edge, with the English anchor read last on its left
+        let anchor = body.anchors[0].get_bounds();
+        let (arabic_left, arabic_right) = non_ascii_extent(&body);
+        assert!((arabic_right - 300.).abs() < 1.);
+        assert!(anchor.width > 0.);
+        assert!(anchor.x + anchor.width <= arabic_left);
+        assert_eq!(layout_width(&body.buffer), 300.);
+    }
+
     #[test]
     fn test_span_parser_basics() {
         let mut parser = Parser::new(String::from("<span color=\"red\">Red text</span>"));
@@ -811,4 +1580,40 @@ mod tests {
         assert!(has_instance);
         assert!(has_text_area);
     }
+
+    #[test]
+    fn test_shape_cache() {
+        let mut font_system = FontSystem::new();
+        let config = Arc::new(Config::default());
+        let shape_cache = Rc::new(ShapeCache::default());
+        let text = "Hello <b>world</b>\n<a href=\"https://example.com\">link</a>";
+
+        // The first one shapes the text, the second takes it from the cache
+        let bodies: Vec<Body> = [Some(&shape_cache), Some(&shape_cache), None]
+            .into_iter()
+            .map(|shape_cache| {
+                let mut body = Body::new(
+                    0,
+                    Arc::clone(&config),
+                    StyleKey::default(),
+                    UiState::default(),
+                    &mut font_system,
+                );
+                body.set_shape_cache(shape_cache.cloned());
+                body.set_text(&mut font_system, text);
+                body.set_size(&mut font_system, Some(200.), None);
+                body
+            })
+            .collect();
+
+        let widths = |body: &Body| {
+            body.buffer
+                .layout_runs()
+                .map(|run| run.line_w)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(widths(&bodies[1]), widths(&bodies[2]));
+        assert_eq!(bodies[1].text(), bodies[2].text());
+        assert_eq!(bodies[1].anchors.len(), 1);
+    }
 }
@@ -0,0 +1,329 @@
+use super::Text;
+use crate::{
+    components::{notification::NotificationId, Bounds, Component, Data, Layer},
+    config::{self, Config, StyleKey},
+    manager::UiState,
+    rendering::texture_renderer,
+    utils::buffers,
+    Urgency,
+};
+use glyphon::{Attrs, Buffer, FontSystem};
+use std::sync::{atomic::Ordering, Arc};
+
+/// Action key sent by clients that accept a typed reply instead of a button press.
+pub const INLINE_REPLY: &str = "inline-reply";
+
+/// Single text entry used for inline replies, edited from the keyboard handler while the
+/// daemon is in insert mode.
+pub struct TextInput {
+    id: NotificationId,
+    style_key: StyleKey,
+    ui_state: UiState,
+    config: Arc<Config>,
+    pub buffer: Buffer,
+    text: String,
+    /// Byte offset of the cursor into `text`, always on a char boundary.
+    cursor: usize,
+    x: f32,
+    y: f32,
+}
+
+impl Text for TextInput {
+    fn set_size(&mut self, font_system: &mut FontSystem, width: Option<f32>, height: Option<f32>) {
+        self.buffer.set_size(font_system, width, height);
+    }
+
+    fn set_text<T>(&mut self, font_system: &mut FontSystem, text: T)
+    where
+        T: AsRef<str>,
+    {
+        self.text = text.as_ref().to_string();
+        self.cursor = self.text.len();
+        self.shape(font_system);
+    }
+}
+
+impl TextInput {
+    pub fn new(
+        id: NotificationId,
+        config: Arc<Config>,
+        style_key: StyleKey,
+        ui_state: UiState,
+        font_system: &mut FontSystem,
+    ) -> Self {
+        let dpi = 96.0;
+        let font_size = config.styles.default.font.size * dpi / 72.0;
+        let mut buffer = Buffer::new(
+            font_system,
+            glyphon::Metrics::new(font_size, font_size * 1.2),
+        );
+        buffer.shape_until_scroll(font_system, true);
+
+        Self {
+            id,
+            buffer,
+            text: String::new(),
+            cursor: 0,
+            x: 0.,
+            y: 0.,
+            config,
+            ui_state,
+            style_key,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn insert(&mut self, font_system: &mut FontSystem, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+        self.shape(font_system);
+    }
+
+    pub fn backspace(&mut self, font_system: &mut FontSystem) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+            self.text.remove(self.cursor);
+            self.shape(font_system);
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        if let Some(c) = self.text[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
+        }
+    }
+
+    fn shape(&mut self, font_system: &mut FontSystem) {
+        let style = self.get_style();
+        let family = Arc::clone(&style.family);
+
+        let attrs = Attrs::new()
+            .metadata(Layer::Text.metadata())
+            .family(glyphon::Family::Name(&family));
+
+        self.buffer
+            .set_text(font_system, &self.text, &attrs, glyphon::Shaping::Advanced);
+    }
+
+    /// Position of the cursor relative to the top left corner of the text.
+    fn cursor_position(&self) -> (f32, f32) {
+        let mut position = (0., 0.);
+
+        for run in self.buffer.layout_runs() {
+            for glyph in run.glyphs {
+                if glyph.start >= self.cursor {
+                    return (glyph.x, run.line_top);
+                }
+                position = (glyph.x + glyph.w, run.line_top);
+            }
+        }
+
+        position
+    }
+}
+
+impl Component for TextInput {
+    type Style = config::text::Body;
+
+    fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    fn get_style_key(&self) -> &StyleKey {
+        &self.style_key
+    }
+
+    fn set_style_key(&mut self, style_key: StyleKey) {
+        self.style_key = style_key;
+    }
+
+    fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    fn get_ui_state(&self) -> &UiState {
+        &self.ui_state
+    }
+
+    fn get_style(&self) -> &Self::Style {
+        &self.get_notification_style().body
+    }
+
+    fn get_instances(&self, urgency: &Urgency) -> Vec<buffers::Instance> {
+        let style = self.get_style();
+        let bounds = self.get_render_bounds();
+        let scale = self.ui_state.scale.load(Ordering::Relaxed);
+
+        let (cursor_x, cursor_y) = self.cursor_position();
+
+        vec![
+            buffers::Instance {
+                rect_pos: [bounds.x, bounds.y],
+                rect_size: [
+                    bounds.width - style.border.size.left - style.border.size.right,
+                    bounds.height - style.border.size.top - style.border.size.bottom,
+                ],
+                rect_color: style.background.to_linear(urgency),
+                border_radius: style.border.radius.into(),
+                border_size: style.border.size.into(),
+                border_color: style.border.color.to_linear(urgency),
+                scale,
+                depth: Layer::Element.depth(),
+            },
+            buffers::Instance {
+                rect_pos: [
+                    bounds.x + style.border.size.left + style.padding.left + cursor_x,
+                    bounds.y + style.border.size.top + style.padding.top + cursor_y,
+                ],
+                rect_size: [1., self.buffer.metrics().line_height],
+                rect_color: style.color.to_linear(urgency),
+                border_radius: [0.; 4],
+                border_size: [0.; 4],
+                border_color: [0.; 4],
+                scale,
+                depth: Layer::Focus.depth(),
+            },
+        ]
+    }
+
+    fn get_text_areas(&self, urgency: &Urgency) -> Vec<glyphon::TextArea<'_>> {
+        let style = self.get_style();
+        let bounds = self.get_render_bounds();
+
+        let left = bounds.x + style.border.size.left + style.padding.left;
+        let top = bounds.y + style.border.size.top + style.padding.top;
+
+        let content_width = bounds.width
+            - style.border.size.left
+            - style.border.size.right
+            - style.padding.left
+            - style.padding.right;
+
+        let content_height = bounds.height
+            - style.border.size.top
+            - style.border.size.bottom
+            - style.padding.top
+            - style.padding.bottom;
+
+        vec![glyphon::TextArea {
+            buffer: &self.buffer,
+            left,
+            top,
+            scale: self.ui_state.scale.load(Ordering::Relaxed),
+            bounds: glyphon::TextBounds {
+                left: left as i32,
+                top: top as i32,
+                right: (left + content_width) as i32,
+                bottom: (top + content_height) as i32,
+            },
+            default_color: style.color.into_glyphon(urgency),
+            custom_glyphs: &[],
+        }]
+    }
+
+    fn get_textures(&self) -> Vec<texture_renderer::TextureArea<'_>> {
+        Vec::new()
+    }
+
+    fn get_bounds(&self) -> Bounds {
+        let style = self.get_style();
+        let width = self.buffer.size().0.unwrap_or_default();
+        let total_lines = self.buffer.layout_runs().count().max(1) as f32;
+
+        Bounds {
+            x: self.x,
+            y: self.y,
+            width: width
+                + style.margin.left
+                + style.margin.right
+                + style.padding.left
+                + style.padding.right
+                + style.border.size.left
+                + style.border.size.right,
+            height: total_lines * self.buffer.metrics().line_height
+                + style.margin.top
+                + style.margin.bottom
+                + style.padding.top
+                + style.padding.bottom
+                + style.border.size.top
+                + style.border.size.bottom,
+        }
+    }
+
+    fn get_render_bounds(&self) -> Bounds {
+        let style = self.get_style();
+        let bounds = self.get_bounds();
+        Bounds {
+            x: bounds.x + style.margin.left,
+            y: bounds.y + style.margin.top,
+            width: bounds.width - style.margin.left - style.margin.right,
+            height: bounds.height - style.margin.top - style.margin.bottom,
+        }
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+    }
+
+    fn get_data(&self, urgency: &Urgency) -> Vec<Data<'_>> {
+        self.get_instances(urgency)
+            .into_iter()
+            .map(Data::Instance)
+            .chain(self.get_text_areas(urgency).into_iter().map(Data::TextArea))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TextInput;
+    use crate::{
+        components::text::Text,
+        config::{Config, StyleKey},
+        manager::UiState,
+    };
+    use glyphon::FontSystem;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_editing() {
+        let mut font_system = FontSystem::new();
+
+        let mut input = TextInput::new(
+            0,
+            Arc::new(Config::default()),
+            StyleKey::default(),
+            UiState::default(),
+            &mut font_system,
+        );
+
+        "hllo"
+            .chars()
+            .for_each(|c| input.insert(&mut font_system, c));
+        (0..3).for_each(|_| input.move_left());
+        input.insert(&mut font_system, 'e');
+        assert_eq!(input.text(), "hello");
+
+        input.move_right();
+        input.backspace(&mut font_system);
+        assert_eq!(input.text(), "helo");
+
+        input.set_text(&mut font_system, "zażółć");
+        input.backspace(&mut font_system);
+        input.move_left();
+        input.move_right();
+        input.move_right();
+        input.insert(&mut font_system, '!');
+        assert_eq!(input.text(), "zażół!");
+    }
+}
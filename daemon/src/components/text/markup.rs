@@ -1,4 +1,12 @@
-use std::collections::HashMap;
+use crate::config::Limits;
+use std::{borrow::Cow, collections::HashMap};
+
+/// Bytes a markdown link is looked for in, links are short and scanning the rest of the
+/// text for every `[` would make runs of them quadratic.
+const MAX_MARKDOWN_LINK_LENGTH: usize = 2048;
+
+/// Tags that have to be closed, the content of the others is left as is.
+const ELEMENTS: [&str; 5] = ["b", "i", "u", "a", "span"];
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Position {
@@ -15,6 +23,7 @@ pub enum Tag {
     Image {
         alt: String,
         src: String,
+        position: Position,
     },
     Anchor {
         href: String,
@@ -28,12 +37,50 @@ pub enum Tag {
     },
 }
 
+/// `text` cut to `max_length` characters, with an ellipsis in place of the rest.
+pub fn truncate(text: &str, max_length: usize) -> Cow<'_, str> {
+    match text.char_indices().nth(max_length) {
+        Some((end, _)) => Cow::Owned(format!("{}…", &text[..end])),
+        None => Cow::Borrowed(text),
+    }
+}
+
+/// Whether `text` starts with an opening `<name>` tag, attributes or not.
+fn opens(text: &str, name: &str) -> bool {
+    text.strip_prefix('<')
+        .and_then(|rest| rest.strip_prefix(name))
+        .is_some_and(|rest| rest.starts_with(|c: char| c == '>' || c == '/' || c.is_whitespace()))
+}
+
+/// Byte offset of the `close` balancing the `open` right before `from`.
+fn matching_bracket(text: &str, from: usize, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text[from..].char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            if depth == 0 {
+                return Some(from + i);
+            }
+            depth -= 1;
+        }
+    }
+
+    None
+}
+
 pub struct Parser {
     pos: usize,
     line: usize,
     column: usize,
     text_column: usize,
     input: String,
+    limits: Limits,
+    /// Elements known not to be closed anywhere after the current position.
+    unclosed: Vec<&'static str>,
+    /// Bytes of the input looked at so far, counting every time one is looked at again. Grows
+    /// with the time the parse takes, without depending on how fast the machine is.
+    scanned: usize,
 }
 
 impl Parser {
@@ -44,69 +91,106 @@ impl Parser {
             column: 0,
             text_column: 0,
             input,
+            limits: Limits::default(),
+            unclosed: Vec::new(),
+            scanned: 0,
         }
     }
 
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
     pub fn parse(&mut self) -> Vec<Tag> {
+        self.scanned += self.input.len();
+        if let Some((end, _)) = self.input.char_indices().nth(self.limits.max_length) {
+            log::debug!(
+                "Truncated markup of {} bytes to {} characters",
+                self.input.len(),
+                self.limits.max_length
+            );
+            self.input.truncate(end);
+            self.input.push('…');
+        }
+
         let mut result = Vec::new();
         while self.pos < self.input.len() {
-            if self.input[self.pos..].starts_with('<') {
-                if self.input[self.pos..].starts_with("<b>") {
-                    self.consume_str("<b>", false);
-                    let content = self.parse_until("</b>");
-                    self.consume_str("</b>", false);
-                    result.push(Tag::Bold(content));
-                } else if self.input[self.pos..].starts_with("<i>") {
-                    self.consume_str("<i>", false);
-                    let content = self.parse_until("</i>");
-                    self.consume_str("</i>", false);
-                    result.push(Tag::Italic(content));
-                } else if self.input[self.pos..].starts_with("<u>") {
-                    self.consume_str("<u>", false);
-                    let content = self.parse_until("</u>");
-                    self.consume_str("</u>", false);
-                    result.push(Tag::Underline(content));
-                } else if self.input[self.pos..].starts_with("<img") {
-                    let (tag_end, attributes) = self.parse_tag_and_attributes(false);
-                    self.pos = tag_end + 1;
-                    self.update_position_for_tag_end(false);
-                    result.push(Tag::Image {
-                        alt: attributes.get("alt").cloned().unwrap_or_default(),
-                        src: attributes.get("src").cloned().unwrap_or_default(),
-                    });
-                } else if self.input[self.pos..].starts_with("<a") {
-                    let (tag_end, attributes) = self.parse_tag_and_attributes(false);
-                    self.pos = tag_end + 1;
-                    self.update_position_for_tag_end(false);
-
-                    let content_pos = Position {
-                        line: self.line,
-                        column: self.text_column,
-                        offset: self.pos,
-                    };
-
-                    let text = self.parse_until("</a>");
-                    self.consume_str("</a>", false);
-
-                    result.push(Tag::Anchor {
-                        href: attributes.get("href").cloned().unwrap_or_default(),
-                        text,
-                        position: content_pos,
-                    });
-                } else if self.input[self.pos..].starts_with("<span") {
-                    let (tag_end, attributes) = self.parse_tag_and_attributes(false);
-                    self.pos = tag_end + 1;
-                    self.update_position_for_tag_end(false);
-                    let text = self.parse_until("</span>");
-                    self.consume_str("</span>", false);
-                    result.push(Tag::Span { text, attributes });
-                } else {
-                    result.push(Tag::Text(self.consume_char(true).to_string()));
+            if result.len() >= self.limits.max_spans {
+                log::debug!(
+                    "Markup has over {} spans, the rest is kept as text",
+                    result.len()
+                );
+                self.scanned += self.input.len() - self.pos;
+                result.push(Tag::Text(self.input[self.pos..].to_string()));
+                break;
+            }
+
+            let remaining = &self.input[self.pos..];
+            if remaining.starts_with('<') {
+                let element = ELEMENTS.into_iter().find(|name| opens(remaining, name));
+                let close = match element {
+                    Some(name) => self.find_closing(name),
+                    None => None,
+                };
+
+                match (element, close) {
+                    (Some(name @ ("b" | "i" | "u")), Some(close)) => {
+                        self.parse_tag_and_attributes(false);
+                        let content = self.parse_content(close);
+                        self.consume_str(&format!("</{name}>"), false);
+                        result.push(match name {
+                            "b" => Tag::Bold(content),
+                            "i" => Tag::Italic(content),
+                            _ => Tag::Underline(content),
+                        });
+                    }
+                    (Some("a"), Some(close)) => {
+                        let attributes = self.parse_tag_and_attributes(false);
+
+                        let content_pos = Position {
+                            line: self.line,
+                            column: self.text_column,
+                            offset: self.pos,
+                        };
+
+                        let text = self.parse_content(close);
+                        self.consume_str("</a>", false);
+
+                        result.push(Tag::Anchor {
+                            href: attributes.get("href").cloned().unwrap_or_default(),
+                            text,
+                            position: content_pos,
+                        });
+                    }
+                    (Some(_), Some(close)) => {
+                        let attributes = self.parse_tag_and_attributes(false);
+                        let text = self.parse_content(close);
+                        self.consume_str("</span>", false);
+                        result.push(Tag::Span { text, attributes });
+                    }
+                    (None, _) if opens(&self.input[self.pos..], "img") => {
+                        let attributes = self.parse_tag_and_attributes(false);
+                        result.push(Tag::Image {
+                            alt: attributes.get("alt").cloned().unwrap_or_default(),
+                            src: attributes.get("src").cloned().unwrap_or_default(),
+                            position: Position {
+                                line: self.line,
+                                column: self.text_column,
+                                offset: self.pos,
+                            },
+                        });
+                    }
+                    // Unknown, unclosed and too deeply nested tags are shown as they are
+                    _ => result.push(Tag::Text(self.consume_char(true).to_string())),
                 }
             } else {
                 let mut text = String::new();
 
-                while self.pos < self.input.len() && !self.input[self.pos..].starts_with('<') {
+                while self.pos < self.input.len()
+                    && !self.input[self.pos..].starts_with('<')
+                    && result.len() < self.limits.max_spans
+                {
                     let current_line = self.line;
                     let current_column = self.text_column;
                     let current_pos = self.pos;
@@ -155,63 +239,127 @@ impl Parser {
                 }
             }
         }
+
+        log::debug!(
+            "Parsed {} bytes of markup, looking at {} bytes",
+            self.input.len(),
+            self.scanned
+        );
         result
     }
 
-    fn detect_markdown_link_at_current_position(&mut self) -> Option<(String, String)> {
-        let remaining = &self.input[self.pos..];
-
-        if !remaining.starts_with('[') {
+    /// Byte offset of the tag closing the `name` element opened at the current position. None
+    /// if it isn't closed, or elements in it are nested deeper than the limit.
+    fn find_closing(&mut self, name: &'static str) -> Option<usize> {
+        if self.unclosed.contains(&name) {
             return None;
         }
 
-        let mut bracket_depth = 0;
-        let mut text_end = 0;
-
-        for (i, c) in remaining.chars().enumerate().skip(1) {
-            if c == '[' {
-                bracket_depth += 1;
-            } else if c == ']' {
-                if bracket_depth == 0 {
-                    text_end = i;
-                    break;
+        // Without a `>` there's no closing tag either
+        let Some(tag_end) = self.input[self.pos..].find('>') else {
+            self.scanned += self.input.len() - self.pos;
+            self.unclosed.push(name);
+            return None;
+        };
+        let start = self.pos + tag_end + 1;
+        let close = format!("</{name}>");
+        let mut same = 0;
+        let mut depth = 0usize;
+        let mut offset = start;
+        while let Some(i) = self.input[offset..].find('<') {
+            let at = offset + i;
+            let rest = &self.input[at..];
+            if rest.starts_with(&close) {
+                if same == 0 {
+                    self.scanned += at - self.pos;
+                    return Some(at);
                 }
-                bracket_depth -= 1;
+                same -= 1;
             }
-        }
 
-        if text_end == 0 {
-            return None;
-        }
+            if ELEMENTS.iter().any(|element| opens(rest, element)) {
+                depth += 1;
+                if depth >= self.limits.max_depth {
+                    log::debug!(
+                        "Markup is nested deeper than {} elements",
+                        self.limits.max_depth
+                    );
+                    self.scanned += at - self.pos;
+                    return None;
+                }
+                same += usize::from(opens(rest, name));
+            } else if rest.starts_with("</") {
+                depth = depth.saturating_sub(1);
+            }
 
-        if remaining.chars().nth(text_end + 1) != Some('(') {
-            return None;
+            offset = at + 1;
         }
 
-        let link_text = remaining[1..text_end].to_string();
+        // Whatever follows doesn't close it either
+        self.scanned += self.input.len() - self.pos;
+        self.unclosed.push(name);
+        None
+    }
 
-        let mut paren_depth = 0;
-        let mut url_end = 0;
+    /// Text up to `end`, the markup of elements nested in it left out.
+    fn parse_content(&mut self, end: usize) -> String {
+        let mut content = String::new();
 
-        for (i, c) in remaining.chars().enumerate().skip(text_end + 2) {
-            if c == '(' {
-                paren_depth += 1;
-            } else if c == ')' {
-                if paren_depth == 0 {
-                    url_end = i;
-                    break;
+        while self.pos < end {
+            let remaining = &self.input[self.pos..end];
+            let nested = remaining.starts_with("</")
+                || ELEMENTS
+                    .iter()
+                    .chain(&["img"])
+                    .any(|element| opens(remaining, element));
+
+            let tag_end = nested.then(|| remaining.find('>')).flatten();
+            if nested {
+                self.scanned += tag_end.map_or(remaining.len(), |tag_end| tag_end + 1);
+            }
+            match tag_end {
+                Some(tag_end) => {
+                    let tag_end = self.pos + tag_end + 1;
+                    while self.pos < tag_end {
+                        self.consume_char(false);
+                    }
                 }
-                paren_depth -= 1;
+                _ => content.push(self.consume_char(true)),
             }
         }
 
-        if url_end == 0 {
+        content
+    }
+
+    fn detect_markdown_link_at_current_position(&mut self) -> Option<(String, String)> {
+        let mut remaining = &self.input[self.pos..];
+
+        if !remaining.starts_with('[') {
             return None;
         }
 
+        if remaining.len() > MAX_MARKDOWN_LINK_LENGTH {
+            let end = (0..=MAX_MARKDOWN_LINK_LENGTH)
+                .rev()
+                .find(|&end| remaining.is_char_boundary(end))
+                .unwrap_or_default();
+            remaining = &remaining[..end];
+        }
+        // At most the whole of it is looked at for each of the brackets
+        self.scanned += 2 * remaining.len();
+
+        let text_end = matching_bracket(remaining, 1, '[', ']')?;
+
+        if !remaining[text_end + 1..].starts_with('(') {
+            return None;
+        }
+
+        let link_text = remaining[1..text_end].to_string();
+        let url_end = matching_bracket(remaining, text_end + 2, '(', ')')?;
         let url = remaining[(text_end + 2)..url_end].to_string();
 
-        for _ in 0..(url_end + 1) {
+        let length = remaining[..=url_end].chars().count();
+        for _ in 0..length {
             self.consume_char(true);
         }
 
@@ -228,11 +376,7 @@ impl Parser {
                 let mut end_pos = 0;
                 let mut depth = 0;
 
-                for (i, c) in remaining.chars().enumerate() {
-                    if i < prefix.len() {
-                        continue;
-                    }
-
+                for (i, c) in remaining.char_indices().skip(prefix.len()) {
                     match c {
                         ' ' | '\t' | '\n' | '\r' | '<' | '>' => {
                             break;
@@ -267,15 +411,16 @@ impl Parser {
                         _ => {}
                     }
 
-                    end_pos = i + 1;
+                    end_pos = i + c.len_utf8();
                 }
 
+                self.scanned += end_pos;
                 if end_pos > 0 {
                     let url = remaining[..end_pos].to_string();
 
                     let url = url.trim_end_matches(|c| ".,:;!?".contains(c)).to_string();
 
-                    (0..url.len()).for_each(|_| {
+                    (0..url.chars().count()).for_each(|_| {
                         self.consume_char(true);
                     });
 
@@ -287,10 +432,8 @@ impl Parser {
         None
     }
 
-    fn parse_tag_and_attributes(
-        &mut self,
-        count_in_text: bool,
-    ) -> (usize, HashMap<String, String>) {
+    /// Consumes the tag at the current position, `>` included.
+    fn parse_tag_and_attributes(&mut self, count_in_text: bool) -> HashMap<String, String> {
         let mut attributes = HashMap::new();
         let mut in_attr_name = false;
         let mut in_attr_value = false;
@@ -300,20 +443,21 @@ impl Parser {
 
         while self.pos < self.input.len()
             && !self.input[self.pos..].starts_with(' ')
-            && self.input.chars().nth(self.pos) != Some('>')
+            && !self.input[self.pos..].starts_with('>')
         {
             self.consume_char(count_in_text);
         }
 
-        while self.pos < self.input.len() && self.input.chars().nth(self.pos) != Some('>') {
+        while self.pos < self.input.len() && !self.input[self.pos..].starts_with('>') {
             let current_char = self.consume_char(count_in_text);
 
             if in_attr_value {
                 if current_char == quote_char {
                     in_attr_value = false;
-                    attributes.insert(attr_name.clone(), attr_value.clone());
-                    attr_name.clear();
-                    attr_value.clear();
+                    attributes.insert(
+                        std::mem::take(&mut attr_name),
+                        std::mem::take(&mut attr_value),
+                    );
                 } else {
                     attr_value.push(current_char);
                 }
@@ -337,29 +481,15 @@ impl Parser {
             }
         }
 
-        if self.pos < self.input.len() && self.input.chars().nth(self.pos) == Some('>') {
+        if self.pos < self.input.len() {
             self.consume_char(count_in_text);
         }
 
-        (self.pos - 1, attributes)
-    }
-
-    fn parse_until(&mut self, end_marker: &str) -> String {
-        let mut content = String::new();
-
-        while self.pos < self.input.len() {
-            let remaining = &self.input[self.pos..];
-            if remaining.starts_with(end_marker) {
-                return content;
-            }
-
-            content.push(self.consume_char(true));
-        }
-
-        content
+        attributes
     }
 
     fn consume_str(&mut self, s: &str, count_in_text: bool) {
+        self.scanned += s.len();
         for c in s.chars() {
             if c == '\n' {
                 self.line += 1;
@@ -381,6 +511,7 @@ impl Parser {
         let mut iter = self.input[self.pos..].chars();
         let current_char = iter.next().unwrap();
         self.pos += current_char.len_utf8();
+        self.scanned += current_char.len_utf8();
 
         if current_char == '\n' {
             self.line += 1;
@@ -397,25 +528,6 @@ impl Parser {
 
         current_char
     }
-
-    fn update_position_for_tag_end(&mut self, count_in_text: bool) {
-        let slice = &self.input[..self.pos];
-        let last_newline_pos = slice.rfind('\n');
-
-        if let Some(pos) = last_newline_pos {
-            self.line = slice[..pos].chars().filter(|&c| c == '\n').count() + 1;
-            self.column = slice[pos + 1..].chars().count();
-
-            if count_in_text {
-                self.text_column = self.column;
-            }
-        } else {
-            self.column = slice.chars().count();
-            if count_in_text {
-                self.text_column = self.column;
-            }
-        }
-    }
 }
 
 #[cfg(test)]
@@ -451,9 +563,10 @@ mod tests {
         let mut parser = Parser::new("<img src=\"/tmp/image.png\" alt=\"Image\" />".to_string());
         let result = parser.parse();
         assert_eq!(result.len(), 1);
-        if let Tag::Image { alt, src } = &result[0] {
+        if let Tag::Image { alt, src, position } = &result[0] {
             assert_eq!(alt, "Image");
             assert_eq!(src, "/tmp/image.png");
+            assert_eq!(position.line, 0);
         } else {
             panic!("Expected Underline tag");
         }
@@ -656,9 +769,10 @@ mod tests {
             panic!("Expected Text tag with newline at position 7");
         }
 
-        if let Tag::Image { alt, src } = &result[8] {
+        if let Tag::Image { alt, src, position } = &result[8] {
             assert_eq!(alt, "image", "Expected image alt text");
             assert_eq!(src, "", "Expected empty src attribute");
+            assert_eq!(position.line, 4, "Expected image on line 4");
         } else {
             panic!("Expected Image tag at position 8");
         }
@@ -873,4 +987,133 @@ mod tests {
             panic!("Expected Plain URL Anchor tag");
         }
     }
+
+    fn text(result: &[Tag]) -> String {
+        result
+            .iter()
+            .map(|tag| match tag {
+                Tag::Text(text) => text.as_str(),
+                _ => panic!("Expected only Text tags, got {tag:?}"),
+            })
+            .collect()
+    }
+
+    /// Parses `input`, failing if the bytes looked at grow faster than its length, which is
+    /// what would stall the event loop on large bodies.
+    fn parse_bounded(input: String, limits: Limits) -> Vec<Tag> {
+        let len = input.len();
+        let mut parser = Parser::new(input).with_limits(limits);
+        let result = parser.parse();
+        assert!(
+            parser.scanned <= (2 * MAX_MARKDOWN_LINK_LENGTH + 64) * len.max(1),
+            "looked at {} bytes parsing {len}",
+            parser.scanned
+        );
+        result
+    }
+
+    #[test]
+    fn test_nested_tags() {
+        let mut parser = Parser::new("<b>bold <i>and italic</i></b>".to_string());
+        let result = parser.parse();
+        assert_eq!(result, vec![Tag::Bold("bold and italic".to_string())]);
+    }
+
+    #[test]
+    fn test_unclosed_tag() {
+        let html = "<b>never closed <i>at all";
+        let mut parser = Parser::new(html.to_string());
+        assert_eq!(text(&parser.parse()), html);
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let limits = Limits {
+            max_depth: 2,
+            ..Default::default()
+        };
+        let result = parse_bounded("<b><i><u>x</u></i></b>".to_string(), limits);
+
+        assert_eq!(result[0], Tag::Text("<".to_string()));
+        assert!(result.contains(&Tag::Italic("x".to_string())));
+    }
+
+    #[test]
+    fn test_max_length() {
+        let limits = Limits {
+            max_length: 5,
+            ..Default::default()
+        };
+        let result = parse_bounded("Hello <b>world</b>".to_string(), limits);
+        assert_eq!(text(&result), "Hello…");
+    }
+
+    #[test]
+    fn test_max_spans() {
+        let limits = Limits {
+            max_spans: 10,
+            ..Default::default()
+        };
+        let result = parse_bounded("<b>x</b>".repeat(1000), limits);
+
+        assert_eq!(result.len(), 11);
+        assert_eq!(result[10], Tag::Text("<b>x</b>".repeat(990)));
+    }
+
+    #[test]
+    fn test_deep_nesting() {
+        let limits = Limits {
+            max_length: usize::MAX,
+            ..Default::default()
+        };
+        let html = format!(
+            "{}x{}",
+            "<b><i>".repeat(100_000),
+            "</i></b>".repeat(100_000)
+        );
+        let result = parse_bounded(html, limits);
+        assert!(result.len() <= limits.max_spans + 1);
+
+        let html = "<b><i>".repeat(100_000);
+        let result = parse_bounded(html.clone(), limits);
+        assert_eq!(text(&result), html);
+    }
+
+    #[test]
+    fn test_unterminated_entities() {
+        let html = "&amp".repeat(500_000);
+        let result = parse_bounded(html.clone(), Limits::default());
+        assert_eq!(
+            text(&result).chars().count(),
+            Limits::default().max_length + 1
+        );
+
+        for html in [
+            "[".repeat(500_000),
+            "[a](".repeat(500_000),
+            "<".repeat(500_000),
+            "<b ".repeat(500_000),
+        ] {
+            parse_bounded(html, Limits::default());
+        }
+    }
+
+    #[test]
+    fn test_huge_attribute() {
+        let limits = Limits {
+            max_length: usize::MAX,
+            ..Default::default()
+        };
+        let value = "a".repeat(2_000_000);
+        let result = parse_bounded(format!("<span foo=\"{value}\">text</span>"), limits);
+
+        let Tag::Span { text, attributes } = &result[0] else {
+            panic!("Expected Span tag");
+        };
+        assert_eq!(text, "text");
+        assert_eq!(attributes["foo"], value);
+
+        // Unclosed, so the tag is kept as text
+        parse_bounded(format!("<a href=\"{value}"), limits);
+    }
 }
@@ -1,9 +1,13 @@
+pub mod app_name;
 pub mod body;
+pub mod input;
 pub mod markup;
+pub mod shape_cache;
 pub mod summary;
+pub mod timestamp;
 
 use super::Component;
-use glyphon::FontSystem;
+use glyphon::{cosmic_text::Align, Buffer, FontSystem};
 
 pub trait Text: Component {
     fn set_size(&mut self, font_system: &mut FontSystem, width: Option<f32>, height: Option<f32>);
@@ -12,3 +16,38 @@ pub trait Text: Component {
     where
         T: AsRef<str>;
 }
+
+/// Aligns paragraphs written right to left to the right edge, the glyphs themselves are
+/// already reordered while shaping. Without a width there's no edge to align to.
+pub fn align_rtl(buffer: &mut Buffer, font_system: &mut FontSystem) {
+    if buffer.size().0.is_none() {
+        return;
+    }
+
+    let rtl: Vec<usize> = buffer
+        .layout_runs()
+        .filter(|run| run.rtl)
+        .map(|run| run.line_i)
+        .collect();
+    if rtl.is_empty() {
+        return;
+    }
+
+    rtl.into_iter().for_each(|line_i| {
+        buffer.lines[line_i].set_align(Some(Align::Right));
+    });
+    buffer.shape_until_scroll(font_system, false);
+}
+
+/// Width taken up by the laid out text, right aligned paragraphs reach the edge of the buffer.
+pub fn layout_width(buffer: &Buffer) -> f32 {
+    let width = buffer
+        .layout_runs()
+        .map(|run| run.line_w)
+        .fold(0., f32::max);
+
+    match buffer.size().0 {
+        Some(buffer_width) if buffer.layout_runs().any(|run| run.rtl) => buffer_width.max(width),
+        _ => width,
+    }
+}
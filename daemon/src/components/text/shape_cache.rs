@@ -0,0 +1,162 @@
+use glyphon::{Attrs, BufferLine, Metrics};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    hash::{DefaultHasher, Hash, Hasher},
+    rc::Rc,
+};
+
+/// Maximum number of shaped texts kept around, enough for a page of history several times over.
+const SHAPE_CACHE_CAPACITY: usize = 1000;
+
+/// What the shaped lines of a text depend on. Fonts and colors are part of the attributes of
+/// each span.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShapeKey {
+    spans: u64,
+    font_size: u32,
+    line_height: u32,
+    width: Option<u32>,
+}
+
+impl ShapeKey {
+    pub fn new(spans: &[(&str, Attrs)], metrics: Metrics, width: Option<f32>) -> Self {
+        let mut hasher = DefaultHasher::new();
+        spans.hash(&mut hasher);
+
+        Self {
+            spans: hasher.finish(),
+            font_size: metrics.font_size.to_bits(),
+            line_height: metrics.line_height.to_bits(),
+            width: width.map(f32::to_bits),
+        }
+    }
+}
+
+/// Lines of texts shaped before, so that notifications replacing one with the same text and
+/// history entries shown again aren't shaped from scratch. Lines are shared with the bodies
+/// they were taken by rather than copied.
+pub struct ShapeCache {
+    capacity: usize,
+    entries: RefCell<Entries>,
+}
+
+#[derive(Default)]
+struct Entries {
+    lines: HashMap<ShapeKey, (u64, Rc<[BufferLine]>)>,
+    /// Keys by when they were last used, least recently used first.
+    used: BTreeMap<u64, ShapeKey>,
+    tick: u64,
+}
+
+impl Entries {
+    fn touch(&mut self, key: ShapeKey) -> u64 {
+        self.tick += 1;
+        self.used.insert(self.tick, key);
+        self.tick
+    }
+}
+
+impl Default for ShapeCache {
+    fn default() -> Self {
+        Self::with_capacity(SHAPE_CACHE_CAPACITY)
+    }
+}
+
+impl ShapeCache {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: RefCell::new(Entries::default()),
+        }
+    }
+
+    pub fn insert(&self, key: ShapeKey, lines: Rc<[BufferLine]>) {
+        let mut entries = self.entries.borrow_mut();
+
+        match entries.lines.remove(&key) {
+            Some((used, _)) => {
+                entries.used.remove(&used);
+            }
+            None if entries.lines.len() >= self.capacity => {
+                if let Some((_, evicted)) = entries.used.pop_first() {
+                    entries.lines.remove(&evicted);
+                }
+            }
+            None => {}
+        }
+
+        let used = entries.touch(key);
+        entries.lines.insert(key, (used, lines));
+    }
+
+    pub fn get(&self, key: &ShapeKey) -> Option<Rc<[BufferLine]>> {
+        let mut entries = self.entries.borrow_mut();
+
+        let previous = entries.lines.get(key)?.0;
+        entries.used.remove(&previous);
+        let used = entries.touch(*key);
+        let entry = entries.lines.get_mut(key)?;
+        entry.0 = used;
+
+        Some(Rc::clone(&entry.1))
+    }
+
+    /// Forgets every text, for when what they were shaped with changed.
+    pub fn clear(&self) {
+        *self.entries.borrow_mut() = Entries::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ShapeCache, ShapeKey};
+    use glyphon::{Attrs, Buffer, BufferLine, Family, FontSystem, Metrics, Shaping};
+    use std::rc::Rc;
+
+    fn key(text: &str, family: &str, font_size: f32) -> ShapeKey {
+        let attrs = Attrs::new().family(Family::Name(family));
+        ShapeKey::new(
+            &[(text, attrs)],
+            Metrics::new(font_size, font_size * 1.2),
+            None,
+        )
+    }
+
+    fn lines(font_system: &mut FontSystem, text: &str) -> Rc<[BufferLine]> {
+        let mut buffer = Buffer::new(font_system, Metrics::new(12., 14.));
+        buffer.set_text(font_system, text, &Attrs::new(), Shaping::Advanced);
+        buffer.lines.into()
+    }
+
+    #[test]
+    fn key_includes_family_and_size() {
+        assert_eq!(key("Hello", "Sans", 12.), key("Hello", "Sans", 12.));
+        assert_ne!(key("Hello", "Sans", 12.), key("Hello!", "Sans", 12.));
+        assert_ne!(key("Hello", "Sans", 12.), key("Hello", "Serif", 12.));
+        assert_ne!(key("Hello", "Sans", 12.), key("Hello", "Sans", 14.));
+    }
+
+    #[test]
+    fn cache_evicts_least_recently_used() {
+        let mut font_system = FontSystem::new();
+        let cache = ShapeCache::with_capacity(2);
+        let (first, second, third) = (
+            key("first", "Sans", 12.),
+            key("second", "Sans", 12.),
+            key("third", "Sans", 12.),
+        );
+
+        cache.insert(first, lines(&mut font_system, "first"));
+        cache.insert(second, lines(&mut font_system, "second"));
+        assert!(cache.get(&first).is_some());
+
+        cache.insert(third, lines(&mut font_system, "third"));
+        assert_eq!(cache.get(&first).unwrap()[0].text(), "first");
+        assert!(cache.get(&second).is_none());
+        assert!(cache.get(&third).is_some());
+
+        cache.clear();
+        assert!(cache.get(&first).is_none());
+    }
+}
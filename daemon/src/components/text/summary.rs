@@ -1,28 +1,31 @@
-use super::Text;
+use super::{align_rtl, layout_width, Text};
 use crate::{
-    components::{notification::NotificationId, Bounds, Component, Data},
-    config::{self, Config},
+    components::{notification::NotificationId, Bounds, Component, Data, Layer},
+    config::{self, Config, StyleKey},
     manager::UiState,
     rendering::texture_renderer,
     utils::buffers,
     Urgency,
 };
-use glyphon::{Attrs, Buffer, FontSystem, Weight};
+use glyphon::{Attrs, Buffer, FontSystem, Style, Weight};
 use std::sync::{atomic::Ordering, Arc};
 
 pub struct Summary {
     id: NotificationId,
-    app_name: Arc<str>,
+    style_key: StyleKey,
     ui_state: UiState,
     config: Arc<Config>,
     pub buffer: Buffer,
     x: f32,
     y: f32,
+    /// Set for placeholders standing in for a summary that wasn't kept.
+    italic: bool,
 }
 
 impl Text for Summary {
     fn set_size(&mut self, font_system: &mut FontSystem, width: Option<f32>, height: Option<f32>) {
         self.buffer.set_size(font_system, width, height);
+        align_rtl(&mut self.buffer, font_system);
     }
 
     fn set_text<T>(&mut self, font_system: &mut FontSystem, text: T)
@@ -33,9 +36,14 @@ impl Text for Summary {
         let family = Arc::clone(&style.family);
 
         let attrs = Attrs::new()
-            .metadata(0.7_f32.to_bits() as usize)
+            .metadata(Layer::Text.metadata())
             .family(glyphon::Family::Name(&family))
-            .weight(Weight::BOLD);
+            .weight(Weight::BOLD)
+            .style(if self.italic {
+                Style::Italic
+            } else {
+                Style::Normal
+            });
 
         self.buffer.set_text(
             font_system,
@@ -43,6 +51,7 @@ impl Text for Summary {
             &attrs,
             glyphon::Shaping::Advanced,
         );
+        align_rtl(&mut self.buffer, font_system);
     }
 }
 
@@ -53,8 +62,12 @@ impl Component for Summary {
         &self.config
     }
 
-    fn get_app_name(&self) -> &str {
-        &self.app_name
+    fn get_style_key(&self) -> &StyleKey {
+        &self.style_key
+    }
+
+    fn set_style_key(&mut self, style_key: StyleKey) {
+        self.style_key = style_key;
     }
 
     fn get_id(&self) -> u32 {
@@ -81,7 +94,7 @@ impl Component for Summary {
             border_size: style.border.size.into(),
             border_color: style.border.color.to_linear(urgency),
             scale: self.ui_state.scale.load(Ordering::Relaxed),
-            depth: 0.8,
+            depth: Layer::Element.depth(),
         }]
     }
 
@@ -130,12 +143,8 @@ impl Component for Summary {
 
     fn get_bounds(&self) -> Bounds {
         let style = self.get_style();
-        let (width, total_lines) = self
-            .buffer
-            .layout_runs()
-            .fold((0.0, 0.0), |(width, total_lines), run| {
-                (run.line_w.max(width), total_lines + 1.0)
-            });
+        let width = layout_width(&self.buffer);
+        let total_lines = self.buffer.layout_runs().count() as f32;
 
         if width == 0. || total_lines == 0. {
             return Bounds {
@@ -195,7 +204,7 @@ impl Summary {
     pub fn new(
         id: NotificationId,
         config: Arc<Config>,
-        app_name: Arc<str>,
+        style_key: StyleKey,
         ui_state: UiState,
         font_system: &mut FontSystem,
     ) -> Self {
@@ -215,16 +224,22 @@ impl Summary {
             y: 0.,
             config,
             ui_state,
-            app_name,
+            style_key,
+            italic: false,
         }
     }
+
+    /// Applies to the text set afterwards.
+    pub fn set_italic(&mut self, italic: bool) {
+        self.italic = italic;
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
         components::text::{summary::Summary, Text},
-        config::Config,
+        config::{Config, StyleKey},
         manager::UiState,
     };
     use glyphon::FontSystem;
@@ -237,7 +252,7 @@ mod tests {
         let mut summary = Summary::new(
             0,
             Arc::new(Config::default()),
-            "".into(),
+            StyleKey::default(),
             UiState::default(),
             &mut font_system,
         );
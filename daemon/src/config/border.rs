@@ -1,7 +1,7 @@
 use super::{
     color::Color,
     partial::{PartialBorder, PartialBorderRadius},
-    Insets, Size,
+    Insets, ResolvePercent, Size,
 };
 
 #[derive(Clone)]
@@ -25,6 +25,12 @@ impl Border {
     }
 }
 
+impl ResolvePercent for Border {
+    fn resolve_percent(&mut self, parent: f32) -> bool {
+        self.size.resolve_percent(parent)
+    }
+}
+
 impl Default for Border {
     fn default() -> Self {
         Self {
@@ -1,9 +1,38 @@
-use super::{partial::PartialStyle, Border, BorderRadius, Color, Font, Insets, Size};
+use super::{
+    partial::PartialStyle, Border, BorderRadius, Color, Font, Insets, ResolvePercent, Size,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Which buttons notifications get, as opposed to how they look.
+#[derive(Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct ButtonOptions {
+    pub dismiss: DismissOptions,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct DismissOptions {
+    pub enabled: bool,
+    pub label: Box<str>,
+}
+
+impl Default for DismissOptions {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            label: "X".into(),
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct Buttons {
     pub dismiss: Button,
     pub action: Button,
+    /// Styles of `action:<key>` selectors by action key, taking over from `action`.
+    pub actions: HashMap<Box<str>, Button>,
 }
 
 impl Default for Buttons {
@@ -11,16 +40,47 @@ impl Default for Buttons {
         Self {
             dismiss: Button::default(),
             action: Button::default_action(),
+            actions: HashMap::new(),
         }
     }
 }
 
+impl ResolvePercent for Buttons {
+    fn resolve_percent(&mut self, parent: f32) -> bool {
+        self.actions.values_mut().fold(false, |changed, button| {
+            button.resolve_percent(parent) | changed
+        }) | self.dismiss.resolve_percent(parent)
+            | self.action.resolve_percent(parent)
+    }
+}
+
+impl Buttons {
+    /// Style of the button of the action with `key`.
+    pub fn action_for(&self, key: &str) -> &Button {
+        self.actions.get(key).unwrap_or(&self.action)
+    }
+
+    /// Style of the button of the action with `key` to be changed, starting out as the one
+    /// every action button has.
+    pub fn action_for_mut(&mut self, key: &str) -> &mut Button {
+        self.actions
+            .entry(key.into())
+            .or_insert_with(|| self.action.clone())
+    }
+}
+
 #[derive(Clone)]
 pub struct Button {
     pub default: ButtonState,
     pub hover: ButtonState,
 }
 
+impl ResolvePercent for Button {
+    fn resolve_percent(&mut self, parent: f32) -> bool {
+        self.default.resolve_percent(parent) | self.hover.resolve_percent(parent)
+    }
+}
+
 impl Button {
     pub fn apply_hover(&mut self, partial: &PartialStyle) {
         if let Some(background) = partial.background.as_ref() {
@@ -177,6 +237,16 @@ impl ButtonState {
     }
 }
 
+impl ResolvePercent for ButtonState {
+    fn resolve_percent(&mut self, parent: f32) -> bool {
+        self.width.resolve_percent(parent)
+            | self.height.resolve_percent(parent)
+            | self.padding.resolve_percent(parent)
+            | self.margin.resolve_percent(parent)
+            | self.border.resolve_percent(parent)
+    }
+}
+
 impl Default for ButtonState {
     fn default() -> Self {
         Self {
@@ -5,12 +5,71 @@ use std::sync::atomic::{AtomicU8, Ordering};
 use std::{fmt, ops::Deref, str::FromStr};
 use xkbcommon::xkb::Keysym;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Keymaps(Vec<KeyCombination>);
 
+/// What the keys typed so far amount to, see [`Keymaps::find`].
+#[derive(Debug, PartialEq)]
+pub enum Match<'a> {
+    /// No binding starts with the keys.
+    None,
+    /// The keys start one or more bindings.
+    Pending,
+    /// The keys are a binding, and also the start of a longer one. It fires only if nothing
+    /// else is typed in time.
+    Ambiguous(&'a KeyCombination, u32),
+    Complete(&'a KeyCombination, u32),
+}
+
 impl Keymaps {
-    pub fn matches(&self, sequence: &[KeyWithModifiers]) -> bool {
-        self.iter().any(|kc| kc.keys.starts_with(sequence))
+    /// Matches `sequence` against the bindings of `mode`, along with the count typed before it.
+    /// Counts are leading digits not starting with 0, only typed in normal mode.
+    pub fn find(&self, sequence: &[KeyWithModifiers], mode: Mode) -> Match<'_> {
+        let digits = match mode {
+            Mode::Normal => sequence
+                .iter()
+                .enumerate()
+                .take_while(|(i, key)| match key.key {
+                    Key::Character(c) => {
+                        key.modifiers == Modifiers::default()
+                            && c.is_ascii_digit()
+                            && (*i > 0 || c != '0')
+                    }
+                    Key::SpecialKey(_) => false,
+                })
+                .count(),
+            _ => 0,
+        };
+        let (count, keys) = sequence.split_at(digits);
+        if keys.is_empty() {
+            return match count.is_empty() {
+                true => Match::None,
+                false => Match::Pending,
+            };
+        }
+
+        let count = match count.is_empty() {
+            true => 1,
+            false => count.iter().fold(0u32, |count, key| match key.key {
+                Key::Character(c) => count
+                    .saturating_mul(10)
+                    .saturating_add(c.to_digit(10).unwrap_or(0)),
+                Key::SpecialKey(_) => count,
+            }),
+        };
+
+        let mut candidates = self
+            .iter()
+            .filter(|kc| kc.mode == mode && kc.keys.starts_with(keys));
+        let exact = candidates.clone().find(|kc| kc.keys.len() == keys.len());
+        let longer = candidates.any(|kc| kc.keys.len() > keys.len());
+
+        match (exact, longer) {
+            (Some(kc), true) => Match::Ambiguous(kc, count),
+            (Some(kc), false) => Match::Complete(kc, count),
+            (None, true) => Match::Pending,
+            (None, false) => Match::None,
+        }
     }
 }
 
@@ -79,6 +138,14 @@ impl Default for Keymaps {
                 action: KeyAction::DismissNotification,
                 mode: Mode::Normal,
             },
+            KeyCombination {
+                keys: Keys(vec![KeyWithModifiers {
+                    key: Key::Character('D'),
+                    modifiers: Modifiers::default(),
+                }]),
+                action: KeyAction::DismissApp,
+                mode: Mode::Normal,
+            },
             KeyCombination {
                 keys: Keys(vec![KeyWithModifiers {
                     key: Key::Character('G'),
@@ -149,6 +216,118 @@ impl Default for Keymaps {
                 action: KeyAction::ToggleInhibit,
                 mode: Mode::Normal,
             },
+            KeyCombination {
+                keys: Keys(vec![KeyWithModifiers {
+                    key: Key::Character('e'),
+                    modifiers: Modifiers::default(),
+                }]),
+                action: KeyAction::ToggleGroup,
+                mode: Mode::Normal,
+            },
+            KeyCombination {
+                keys: Keys(vec![KeyWithModifiers {
+                    key: Key::Character('o'),
+                    modifiers: Modifiers::default(),
+                }]),
+                action: KeyAction::ToggleExpand,
+                mode: Mode::Normal,
+            },
+            KeyCombination {
+                keys: Keys(vec![KeyWithModifiers {
+                    key: Key::Character('r'),
+                    modifiers: Modifiers::default(),
+                }]),
+                action: KeyAction::Reply,
+                mode: Mode::Normal,
+            },
+            KeyCombination {
+                keys: Keys(vec![KeyWithModifiers {
+                    key: Key::SpecialKey(SpecialKeyCode::Tab),
+                    modifiers: Modifiers::default(),
+                }]),
+                action: KeyAction::FocusNextElement,
+                mode: Mode::Normal,
+            },
+            KeyCombination {
+                keys: Keys(vec![KeyWithModifiers {
+                    key: Key::SpecialKey(SpecialKeyCode::BackTab),
+                    modifiers: Modifiers::default(),
+                }]),
+                action: KeyAction::FocusPrevElement,
+                mode: Mode::Normal,
+            },
+            KeyCombination {
+                keys: Keys(vec![KeyWithModifiers {
+                    key: Key::SpecialKey(SpecialKeyCode::Enter),
+                    modifiers: Modifiers::default(),
+                }]),
+                action: KeyAction::ActivateElement,
+                mode: Mode::Normal,
+            },
+            KeyCombination {
+                keys: Keys(vec![KeyWithModifiers {
+                    key: Key::Character('y'),
+                    modifiers: Modifiers::default(),
+                }]),
+                action: KeyAction::CopyBody,
+                mode: Mode::Normal,
+            },
+            KeyCombination {
+                keys: Keys(vec![KeyWithModifiers {
+                    key: Key::Character('Y'),
+                    modifiers: Modifiers::default(),
+                }]),
+                action: KeyAction::CopySummary,
+                mode: Mode::Normal,
+            },
+            KeyCombination {
+                keys: Keys(vec![KeyWithModifiers {
+                    key: Key::Character('s'),
+                    modifiers: Modifiers::default(),
+                }]),
+                action: KeyAction::Snooze,
+                mode: Mode::Normal,
+            },
+            KeyCombination {
+                keys: Keys(vec![KeyWithModifiers {
+                    key: Key::Character('p'),
+                    modifiers: Modifiers::default(),
+                }]),
+                action: KeyAction::Pin,
+                mode: Mode::Normal,
+            },
+            KeyCombination {
+                keys: Keys(vec![KeyWithModifiers {
+                    key: Key::Character(']'),
+                    modifiers: Modifiers::default(),
+                }]),
+                action: KeyAction::HistoryPageNext,
+                mode: Mode::Normal,
+            },
+            KeyCombination {
+                keys: Keys(vec![KeyWithModifiers {
+                    key: Key::Character('['),
+                    modifiers: Modifiers::default(),
+                }]),
+                action: KeyAction::HistoryPagePrev,
+                mode: Mode::Normal,
+            },
+            KeyCombination {
+                keys: Keys(vec![KeyWithModifiers {
+                    key: Key::Character('O'),
+                    modifiers: Modifiers::default(),
+                }]),
+                action: KeyAction::OpenFirstLink,
+                mode: Mode::Normal,
+            },
+            KeyCombination {
+                keys: Keys(vec![KeyWithModifiers {
+                    key: Key::Character('c'),
+                    modifiers: Modifiers::default(),
+                }]),
+                action: KeyAction::ToggleCompact,
+                mode: Mode::Normal,
+            },
         ])
     }
 }
@@ -169,6 +348,8 @@ pub enum Mode {
     Normal = 0,
     #[serde(rename = "h")]
     Hint = 1,
+    #[serde(rename = "i")]
+    Insert = 2,
 }
 
 pub struct AtomicMode {
@@ -186,6 +367,7 @@ impl AtomicMode {
         match self.inner.load(ordering) {
             0 => Mode::Normal,
             1 => Mode::Hint,
+            2 => Mode::Insert,
             _ => unreachable!("Invalid Mode value"),
         }
     }
@@ -199,6 +381,7 @@ impl AtomicMode {
         match old {
             0 => Mode::Normal,
             1 => Mode::Hint,
+            2 => Mode::Insert,
             _ => unreachable!("Invalid Mode value"),
         }
     }
@@ -217,11 +400,13 @@ impl AtomicMode {
             Ok(old) => Ok(match old {
                 0 => Mode::Normal,
                 1 => Mode::Hint,
+                2 => Mode::Insert,
                 _ => unreachable!(),
             }),
             Err(old) => Err(match old {
                 0 => Mode::Normal,
                 1 => Mode::Hint,
+                2 => Mode::Insert,
                 _ => unreachable!(),
             }),
         }
@@ -241,6 +426,7 @@ impl FromStr for Mode {
         match s.to_lowercase().as_str() {
             "normal" => Ok(Mode::Normal),
             "hint" => Ok(Mode::Hint),
+            "insert" => Ok(Mode::Insert),
             _ => Err(format!("Invalid mode: {s}")),
         }
     }
@@ -253,7 +439,7 @@ pub struct Modifiers {
     pub meta: bool,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct Keys(pub Vec<KeyWithModifiers>);
 
 impl fmt::Display for Keys {
@@ -284,7 +470,7 @@ impl DerefMut for Keys {
     }
 }
 
-#[derive(Deserialize, PartialEq, Debug)]
+#[derive(Deserialize, PartialEq, Debug, Clone)]
 pub struct KeyCombination {
     pub mode: Mode,
     pub keys: Keys,
@@ -353,7 +539,7 @@ impl<'de> Deserialize<'de> for Keys {
     }
 }
 
-#[derive(PartialEq, Eq, Hash, Debug, Default)]
+#[derive(PartialEq, Eq, Hash, Debug, Default, Clone, Copy)]
 pub struct KeyWithModifiers {
     pub key: Key,
     pub modifiers: Modifiers,
@@ -388,6 +574,7 @@ impl fmt::Display for KeyWithModifiers {
                         SpecialKeyCode::Enter => "CR",
                         SpecialKeyCode::Backspace => "BS",
                         SpecialKeyCode::Tab => "Tab",
+                        SpecialKeyCode::BackTab => "S-Tab",
                         SpecialKeyCode::Space => "Space",
                         SpecialKeyCode::Escape => "Esc",
                         SpecialKeyCode::Up => "Up",
@@ -491,6 +678,7 @@ impl std::str::FromStr for KeyWithModifiers {
                 "CR" => Key::SpecialKey(SpecialKeyCode::Enter),
                 "BS" => Key::SpecialKey(SpecialKeyCode::Backspace),
                 "Tab" => Key::SpecialKey(SpecialKeyCode::Tab),
+                "S-Tab" => Key::SpecialKey(SpecialKeyCode::BackTab),
                 "Esc" => Key::SpecialKey(SpecialKeyCode::Escape),
                 "Up" => Key::SpecialKey(SpecialKeyCode::Up),
                 "Down" => Key::SpecialKey(SpecialKeyCode::Down),
@@ -567,6 +755,7 @@ impl Key {
             Keysym::Return => Some(Key::SpecialKey(SpecialKeyCode::Enter)),
             Keysym::BackSpace => Some(Key::SpecialKey(SpecialKeyCode::Backspace)),
             Keysym::Tab => Some(Key::SpecialKey(SpecialKeyCode::Tab)),
+            Keysym::ISO_Left_Tab => Some(Key::SpecialKey(SpecialKeyCode::BackTab)),
             Keysym::Escape => Some(Key::SpecialKey(SpecialKeyCode::Escape)),
             Keysym::space => Some(Key::SpecialKey(SpecialKeyCode::Space)),
             Keysym::uparrow => Some(Key::SpecialKey(SpecialKeyCode::Up)),
@@ -596,6 +785,8 @@ pub enum SpecialKeyCode {
     Enter,
     Backspace,
     Tab,
+    /// Tab pressed with shift.
+    BackTab,
     Space,
     Escape,
     Up,
@@ -622,12 +813,14 @@ pub enum SpecialKeyCode {
     F12,
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Debug, PartialEq, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum KeyAction {
     NextNotification,
     PreviousNotification,
     DismissNotification,
+    /// Dismisses every notification sent by the app the selected one came from.
+    DismissApp,
     FirstNotification,
     LastNotification,
     Unfocus,
@@ -643,4 +836,139 @@ pub enum KeyAction {
     ShowHistory,
     HideHistory,
     ToggleHistory,
+    HistoryPageNext,
+    HistoryPagePrev,
+    ToggleGroup,
+    ToggleExpand,
+    /// Pins the selected notification so that it doesn't expire, or unpins it.
+    Pin,
+    Reply,
+    FocusNextElement,
+    FocusPrevElement,
+    ActivateElement,
+    CopyBody,
+    CopySummary,
+    Snooze,
+    /// Opens the first link in the body of the selected notification, or of the newest one.
+    OpenFirstLink,
+    /// Shows only the summary of notifications, or everything again.
+    ToggleCompact,
+}
+
+impl KeyAction {
+    /// Whether holding down a key bound to the action keeps running it. Only moving around
+    /// does, anything that changes notifications waits for another press.
+    pub fn repeats(&self) -> bool {
+        matches!(
+            self,
+            Self::NextNotification
+                | Self::PreviousNotification
+                | Self::HistoryPageNext
+                | Self::HistoryPagePrev
+                | Self::FocusNextElement
+                | Self::FocusPrevElement
+        )
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum MouseAction {
+    Dismiss,
+    DefaultAction,
+    ContextMenu,
+    Noop,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct MouseBindings {
+    pub left_click: MouseAction,
+    pub middle_click: MouseAction,
+    pub right_click: MouseAction,
+}
+
+impl Default for MouseBindings {
+    fn default() -> Self {
+        Self {
+            left_click: MouseAction::DefaultAction,
+            middle_click: MouseAction::Dismiss,
+            right_click: MouseAction::ContextMenu,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KeyAction, KeyWithModifiers, Keymaps, Match, Mode};
+    use std::str::FromStr;
+
+    fn keys(s: &str) -> Vec<KeyWithModifiers> {
+        s.chars()
+            .map(|c| KeyWithModifiers::from_str(&c.to_string()).unwrap())
+            .collect()
+    }
+
+    fn action(found: Match) -> Option<(KeyAction, u32)> {
+        match found {
+            Match::Complete(kc, count) => Some((kc.action.clone(), count)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_sequences() {
+        let keymaps = Keymaps::default();
+
+        assert_eq!(keymaps.find(&keys("g"), Mode::Normal), Match::Pending);
+        assert_eq!(
+            action(keymaps.find(&keys("gg"), Mode::Normal)),
+            Some((KeyAction::FirstNotification, 1))
+        );
+        assert_eq!(
+            action(keymaps.find(&keys("G"), Mode::Normal)),
+            Some((KeyAction::LastNotification, 1))
+        );
+        assert_eq!(keymaps.find(&keys("gq"), Mode::Normal), Match::None);
+        assert_eq!(keymaps.find(&keys("j"), Mode::Hint), Match::None);
+    }
+
+    #[test]
+    fn test_counts() {
+        let keymaps = Keymaps::default();
+
+        assert_eq!(
+            action(keymaps.find(&keys("3j"), Mode::Normal)),
+            Some((KeyAction::NextNotification, 3))
+        );
+        assert_eq!(
+            action(keymaps.find(&keys("12k"), Mode::Normal)),
+            Some((KeyAction::PreviousNotification, 12))
+        );
+        assert_eq!(keymaps.find(&keys("3"), Mode::Normal), Match::Pending);
+        assert_eq!(keymaps.find(&keys("3g"), Mode::Normal), Match::Pending);
+        assert_eq!(keymaps.find(&keys("0j"), Mode::Normal), Match::None);
+        assert_eq!(keymaps.find(&keys("3j"), Mode::Hint), Match::None);
+    }
+
+    #[test]
+    fn test_ambiguous_prefix() {
+        let mut keymaps = Keymaps::default();
+        let mut first = keymaps
+            .iter()
+            .find(|kc| kc.keys.0 == keys("gg"))
+            .unwrap()
+            .clone();
+        first.keys.0 = keys("g");
+        keymaps.0.push(first);
+
+        assert!(matches!(
+            keymaps.find(&keys("g"), Mode::Normal),
+            Match::Ambiguous(_, 1)
+        ));
+        assert!(matches!(
+            keymaps.find(&keys("gg"), Mode::Normal),
+            Match::Complete(_, 1)
+        ));
+    }
 }
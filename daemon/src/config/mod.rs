@@ -3,28 +3,102 @@ pub mod button;
 pub mod color;
 pub mod keymaps;
 pub mod partial;
+pub mod rules;
 pub mod text;
+mod theme;
 
 use border::{Border, BorderRadius};
-use button::{Button, ButtonState, Buttons};
+use button::{Button, ButtonOptions, ButtonState, Buttons};
+use chrono::{Datelike, NaiveDateTime, NaiveTime, Weekday};
 use color::Color;
-use keymaps::Keymaps;
+use keymaps::{Keymaps, MouseBindings};
 use mlua::{Lua, LuaSerdeExt};
 use partial::{PartialFont, PartialInsets, PartialStyle};
+use rules::Rule;
 use serde::{Deserialize, Deserializer};
 use std::{
+    collections::HashMap,
     fmt, fs,
     ops::{Add, Sub},
     path::{Path, PathBuf},
     sync::Arc,
 };
-use text::{Body, Summary};
+use text::{AppName, Body, Summary, Timestamp};
+
+use crate::{dbus::xdg::NotificationData, History, Urgency};
+
+/// Sound played for an urgency, given as its path or as a table that can make it loop.
+#[derive(Clone, Debug)]
+pub struct UrgencySound {
+    pub path: Arc<Path>,
+    /// Whether the sound repeats until the notification is dismissed.
+    pub looped: bool,
+    /// Seconds a looped sound stops after even if the notification is still there.
+    pub max_duration: u64,
+}
+
+impl UrgencySound {
+    fn new(path: &Path) -> Self {
+        Self {
+            path: path.into(),
+            looped: false,
+            max_duration: default_max_duration(),
+        }
+    }
+}
+
+fn default_max_duration() -> u64 {
+    60
+}
+
+impl<'de> Deserialize<'de> for UrgencySound {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Path(PathBuf),
+            Table {
+                path: PathBuf,
+                #[serde(default, rename = "loop")]
+                looped: bool,
+                #[serde(default = "default_max_duration")]
+                max_duration: u64,
+            },
+        }
+
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Path(path) => UrgencySound::new(&path),
+            Repr::Table {
+                path,
+                looped,
+                max_duration,
+            } => UrgencySound {
+                path: path.into(),
+                looped,
+                max_duration,
+            },
+        })
+    }
+}
 
 #[derive(Default, Clone)]
 pub struct SoundFile {
-    pub urgency_low: Option<Arc<Path>>,
-    pub urgency_normal: Option<Arc<Path>>,
-    pub urgency_critical: Option<Arc<Path>>,
+    pub urgency_low: Option<UrgencySound>,
+    pub urgency_normal: Option<UrgencySound>,
+    pub urgency_critical: Option<UrgencySound>,
+}
+
+impl SoundFile {
+    pub fn get(&self, urgency: &Urgency) -> Option<&UrgencySound> {
+        match urgency {
+            Urgency::Low => self.urgency_low.as_ref(),
+            Urgency::Normal => self.urgency_normal.as_ref(),
+            Urgency::Critical => self.urgency_critical.as_ref(),
+        }
+    }
 }
 
 impl<'de> Deserialize<'de> for SoundFile {
@@ -46,9 +120,9 @@ impl<'de> Deserialize<'de> for SoundFile {
                 E: serde::de::Error,
             {
                 Ok(SoundFile {
-                    urgency_low: Some(Path::new(v).into()),
-                    urgency_normal: Some(Path::new(v).into()),
-                    urgency_critical: Some(Path::new(v).into()),
+                    urgency_low: Some(UrgencySound::new(Path::new(v))),
+                    urgency_normal: Some(UrgencySound::new(Path::new(v))),
+                    urgency_critical: Some(UrgencySound::new(Path::new(v))),
                 })
             }
 
@@ -57,9 +131,9 @@ impl<'de> Deserialize<'de> for SoundFile {
                 E: serde::de::Error,
             {
                 Ok(SoundFile {
-                    urgency_low: Some(Path::new(&v).into()),
-                    urgency_normal: Some(Path::new(&v).into()),
-                    urgency_critical: Some(Path::new(&v).into()),
+                    urgency_low: Some(UrgencySound::new(Path::new(&v))),
+                    urgency_normal: Some(UrgencySound::new(Path::new(&v))),
+                    urgency_critical: Some(UrgencySound::new(Path::new(&v))),
                 })
             }
 
@@ -97,11 +171,31 @@ impl<'de> Deserialize<'de> for SoundFile {
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 #[serde(default)]
 pub struct History {
     pub size: i64,
     pub path: PathBuf,
+    /// Entries shown at once while browsing the history, the rest is reached page by page.
+    pub page_size: usize,
+    /// Apps, as globs, whose notifications aren't stored at all.
+    pub exclude_apps: Vec<Box<str>>,
+    /// Apps, as globs, whose notifications are stored without their summary and body.
+    pub exclude_body_apps: Vec<Box<str>>,
+}
+
+impl History {
+    pub fn excludes(&self, app_name: &str) -> bool {
+        self.exclude_apps
+            .iter()
+            .any(|pattern| rules::glob_match(pattern, app_name))
+    }
+
+    pub fn redacts(&self, app_name: &str) -> bool {
+        self.exclude_body_apps
+            .iter()
+            .any(|pattern| rules::glob_match(pattern, app_name))
+    }
 }
 
 impl Default for History {
@@ -118,60 +212,470 @@ impl Default for History {
             std::fs::create_dir_all(dir).ok();
         }
 
-        Self { size: 100, path }
+        Self {
+            size: 100,
+            path,
+            page_size: 50,
+            exclude_apps: Vec::new(),
+            exclude_body_apps: Vec::new(),
+        }
     }
 }
 
-#[derive(Deserialize)]
+/// Bounds on the markup of notification bodies, so no body takes long to lay out.
+#[derive(Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct Limits {
+    /// Characters of the body kept, the rest is cut off with an ellipsis.
+    pub max_length: usize,
+    /// Elements nested deeper than this are shown as plain text.
+    pub max_depth: usize,
+    /// Spans of text and elements parsed, anything past them is kept as plain text.
+    pub max_spans: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_length: 10_000,
+            max_depth: 16,
+            max_spans: 512,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
 #[serde(default)]
 pub struct General {
     pub history: History,
     pub theme: Option<Box<str>>,
+    /// Theme in the `themes` directory next to the config whose colors and styles are used.
+    pub style_theme: Option<Box<str>>,
     pub default_sound_file: SoundFile,
     pub ignore_sound_file: bool,
+    /// Sound theme `sound-name` hints are looked up in, `freedesktop` when unset.
+    pub sound_theme: Option<Box<str>>,
+    /// Pixels scrolled per mouse wheel notch, touchpads scroll by the distance travelled.
     pub scroll_sensitivity: f64,
     pub hint_characters: Box<str>,
-    pub max_visible: usize,
+    pub max_visible: MaxVisible,
     pub icon_size: u32,
     pub app_icon_size: u32,
     pub margin: Insets,
     pub anchor: Anchor,
+    pub urgency_placement: UrgencyPlacement,
+    /// Anchor and margin of the surface while only OSD notifications are on screen, centered
+    /// unless set.
+    pub osd: Placement,
     pub layer: Layer,
+    pub urgency_layer: UrgencyLayer,
+    pub exclusive_zone: ExclusiveZone,
     pub queue: Queue,
+    /// Notifications that may wait below the view with `queue = "fifo"` before
+    /// `queue_overflow` drops some, unbounded when unset.
+    pub queue_limit: Option<usize>,
+    pub queue_overflow: QueueOverflow,
+    pub sort: Sort,
+    pub renderer: Renderer,
+    pub power_preference: PowerPreference,
+    /// Name of the output to show notifications on, `"all"` shows them on every output.
     pub output: Option<Box<str>>,
     pub default_timeout: Timeout,
     pub ignore_timeout: bool,
+    pub dnd_schedule: Vec<DndWindow>,
+    pub app_limits: HashMap<Box<str>, AppLimit>,
+    /// Urgencies or app names whose notifications are still shown while inhibited.
+    #[serde(deserialize_with = "deserialize_inhibit_pass")]
+    pub inhibit_pass_urgency: Vec<InhibitPass>,
+    /// Inhibit notifications while a screencast started through the desktop portal is active.
+    pub inhibit_on_screencast: bool,
+    /// Keep the inhibit, along with the notifications it holds back, across restarts.
+    pub persist_inhibit: bool,
+    /// What's shown of new notifications while the session is locked.
+    pub lockscreen: Lockscreen,
+    /// Urgencies or app names whose notifications are still shown while the session is locked.
+    #[serde(deserialize_with = "deserialize_inhibit_pass")]
+    pub lockscreen_pass_urgency: Vec<InhibitPass>,
+    /// Minutes the `snooze` key action puts a notification away for.
+    pub snooze_minutes: u32,
+    pub group_by_app: bool,
+    /// Show the name of the app above the summary, as its desktop file has it when there's one.
+    pub show_app_name: bool,
+    /// Follow the playback position of media players in the progress bar of their
+    /// notifications, matched by the `desktop-entry` hint.
+    pub mpris_enrichment: bool,
+    /// Play animated GIF and PNG images, otherwise only their first frame is shown.
+    pub animated_images: bool,
+    /// Opacity of notifications whose style doesn't set one, multiplied into every color.
+    #[serde(deserialize_with = "deserialize_opacity")]
+    pub opacity: f32,
+    pub markup: bool,
+    pub limits: Limits,
+    pub mouse_bindings: MouseBindings,
+    pub animation: Animation,
+    pub buttons: ButtonOptions,
+    pub compact: Compact,
 }
 
 impl Default for General {
     fn default() -> Self {
         Self {
             theme: None,
+            style_theme: None,
             margin: Insets::default(),
             history: History::default(),
             default_sound_file: SoundFile::default(),
             ignore_sound_file: false,
+            sound_theme: None,
             hint_characters: "sadfjklewcmpgh".into(),
-            scroll_sensitivity: 20.,
-            max_visible: 5,
+            scroll_sensitivity: 40.,
+            max_visible: MaxVisible::Count(5),
             icon_size: 64,
             app_icon_size: 24,
             anchor: Anchor::default(),
+            urgency_placement: UrgencyPlacement::default(),
+            osd: Placement::default(),
             layer: Layer::default(),
+            urgency_layer: UrgencyLayer::default(),
+            exclusive_zone: ExclusiveZone::default(),
             queue: Queue::default(),
+            queue_limit: None,
+            queue_overflow: QueueOverflow::default(),
+            sort: Sort::default(),
+            renderer: Renderer::default(),
+            power_preference: PowerPreference::default(),
             output: None,
-            default_timeout: Timeout::default(),
+            default_timeout: Timeout::global(),
             ignore_timeout: false,
+            dnd_schedule: Vec::new(),
+            app_limits: HashMap::new(),
+            inhibit_pass_urgency: Vec::new(),
+            inhibit_on_screencast: true,
+            persist_inhibit: false,
+            lockscreen: Lockscreen::default(),
+            lockscreen_pass_urgency: Vec::new(),
+            snooze_minutes: 10,
+            group_by_app: false,
+            show_app_name: false,
+            mpris_enrichment: false,
+            animated_images: false,
+            opacity: 1.,
+            markup: true,
+            limits: Limits::default(),
+            mouse_bindings: MouseBindings::default(),
+            animation: Animation::default(),
+            buttons: ButtonOptions::default(),
+            compact: Compact::default(),
+        }
+    }
+}
+
+impl General {
+    /// Anchor and margin of the surface for the notifications on screen, see
+    /// [`SurfacePlacement`].
+    pub fn placement(&self, placement: impl Into<SurfacePlacement>) -> (Anchor, Insets) {
+        let (placement, anchor) = match placement.into() {
+            SurfacePlacement::Urgency(urgency) => (
+                match urgency {
+                    Urgency::Low => self.urgency_placement.urgency_low,
+                    Urgency::Normal => self.urgency_placement.urgency_normal,
+                    Urgency::Critical => self.urgency_placement.urgency_critical,
+                }
+                .unwrap_or_default(),
+                self.anchor,
+            ),
+            SurfacePlacement::Osd => (self.osd, Anchor::Center),
+        };
+
+        (
+            placement.anchor.unwrap_or(anchor),
+            placement.margin.unwrap_or(self.margin),
+        )
+    }
+
+    /// Layer of the surface while notifications of `urgencies` are on screen, the highest one
+    /// any of them goes on.
+    pub fn layer(&self, urgencies: impl IntoIterator<Item = Urgency>) -> Layer {
+        urgencies
+            .into_iter()
+            .map(|urgency| {
+                match urgency {
+                    Urgency::Low => self.urgency_layer.low,
+                    Urgency::Normal => self.urgency_layer.normal,
+                    Urgency::Critical => self.urgency_layer.critical,
+                }
+                .unwrap_or(self.layer)
+            })
+            .max()
+            .unwrap_or(self.layer)
+    }
+}
+
+/// Overrides of `anchor` and `margin` that apply while every notification on screen is at least
+/// as urgent, so mixed urgencies are placed by the least urgent one.
+#[derive(Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct UrgencyPlacement {
+    pub urgency_low: Option<Placement>,
+    pub urgency_normal: Option<Placement>,
+    pub urgency_critical: Option<Placement>,
+}
+
+/// Overrides of `layer` for notifications of each urgency, so that critical ones can be raised
+/// above fullscreen windows while the rest stay below them.
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(default)]
+pub struct UrgencyLayer {
+    pub low: Option<Layer>,
+    pub normal: Option<Layer>,
+    pub critical: Option<Layer>,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(default)]
+pub struct Placement {
+    pub anchor: Option<Anchor>,
+    pub margin: Option<Insets>,
+}
+
+/// What the surface is placed by, the lowest urgency on screen, or `general.osd` while only
+/// OSD notifications are.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SurfacePlacement {
+    Urgency(Urgency),
+    Osd,
+}
+
+impl From<Urgency> for SurfacePlacement {
+    fn from(urgency: Urgency) -> Self {
+        Self::Urgency(urgency)
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct DndWindow {
+    #[serde(default = "DndWindow::all_days")]
+    pub days: Vec<Weekday>,
+    #[serde(deserialize_with = "deserialize_time")]
+    pub start: NaiveTime,
+    #[serde(deserialize_with = "deserialize_time")]
+    pub end: NaiveTime,
+}
+
+impl DndWindow {
+    fn all_days() -> Vec<Weekday> {
+        vec![
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+            Weekday::Sat,
+            Weekday::Sun,
+        ]
+    }
+
+    /// Windows where `end` is earlier than `start` cross midnight and belong
+    /// to the day on which they start.
+    pub fn contains(&self, time: NaiveDateTime) -> bool {
+        let day = time.weekday();
+        let time = time.time();
+
+        if self.start <= self.end {
+            self.days.contains(&day) && time >= self.start && time < self.end
+        } else {
+            (self.days.contains(&day) && time >= self.start)
+                || (self.days.contains(&day.pred()) && time < self.end)
+        }
+    }
+}
+
+fn deserialize_time<'de, D>(deserializer: D) -> Result<NaiveTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    NaiveTime::parse_from_str(&s, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(&s, "%H:%M:%S"))
+        .map_err(|_| {
+            serde::de::Error::invalid_value(serde::de::Unexpected::Str(&s), &"time in HH:MM format")
+        })
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitPolicy {
+    #[default]
+    Replace,
+    Queue,
+}
+
+#[derive(Clone, Copy)]
+pub struct AppLimit {
+    pub max: usize,
+    pub policy: LimitPolicy,
+}
+
+impl<'de> Deserialize<'de> for AppLimit {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct AppLimitVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for AppLimitVisitor {
+            type Value = AppLimit;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a number or a map")
+            }
+
+            fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                self.visit_u64(u64::try_from(v).map_err(E::custom)?)
+            }
+
+            fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(AppLimit {
+                    max: limit(v)?,
+                    policy: LimitPolicy::default(),
+                })
+            }
+
+            fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
+            where
+                M: serde::de::MapAccess<'de>,
+            {
+                let mut max = None;
+                let mut policy = None;
+
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "max" => max = Some(map.next_value()?),
+                        "policy" => policy = Some(map.next_value()?),
+                        _ => return Err(serde::de::Error::unknown_field(&key, &["max", "policy"])),
+                    }
+                }
+
+                Ok(AppLimit {
+                    max: limit(max.ok_or_else(|| serde::de::Error::missing_field("max"))?)?,
+                    policy: policy.unwrap_or_default(),
+                })
+            }
+        }
+
+        /// A limit of zero would drop every notification from the app.
+        fn limit<E>(v: u64) -> Result<usize, E>
+        where
+            E: serde::de::Error,
+        {
+            match v {
+                0 => Err(E::invalid_value(
+                    serde::de::Unexpected::Unsigned(v),
+                    &"a limit of at least 1",
+                )),
+                v => usize::try_from(v).map_err(E::custom),
+            }
+        }
+
+        deserializer.deserialize_any(AppLimitVisitor)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum InhibitPass {
+    Urgency(crate::Urgency),
+    App(Box<str>),
+}
+
+impl InhibitPass {
+    pub fn matches(&self, app_name: &str, urgency: crate::Urgency) -> bool {
+        match self {
+            InhibitPass::Urgency(u) => *u == urgency,
+            InhibitPass::App(app) => **app == *app_name,
+        }
+    }
+}
+
+impl From<&str> for InhibitPass {
+    fn from(value: &str) -> Self {
+        match value {
+            "low" => InhibitPass::Urgency(crate::Urgency::Low),
+            "normal" => InhibitPass::Urgency(crate::Urgency::Normal),
+            "critical" => InhibitPass::Urgency(crate::Urgency::Critical),
+            app => InhibitPass::App(app.into()),
+        }
+    }
+}
+
+/// Clamps an opacity into 0..1, values outside of it are likely typos worth pointing out.
+fn clamp_opacity(opacity: f32) -> f32 {
+    if (0.0..=1.0).contains(&opacity) {
+        return opacity;
+    }
+
+    log::warn!("Opacity {opacity} is outside of 0..1, clamping it");
+    if opacity.is_nan() {
+        1.
+    } else {
+        opacity.clamp(0., 1.)
+    }
+}
+
+fn deserialize_opacity<'de, D>(deserializer: D) -> Result<f32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    f32::deserialize(deserializer).map(clamp_opacity)
+}
+
+fn deserialize_inhibit_pass<'de, D>(deserializer: D) -> Result<Vec<InhibitPass>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct InhibitPassVisitor;
+
+    impl<'de> serde::de::Visitor<'de> for InhibitPassVisitor {
+        type Value = Vec<InhibitPass>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a string or a list of strings")
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            Ok(vec![value.into()])
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: serde::de::SeqAccess<'de>,
+        {
+            let mut passes = Vec::new();
+            while let Some(s) = seq.next_element::<String>()? {
+                passes.push(s.as_str().into());
+            }
+            Ok(passes)
         }
     }
+
+    deserializer.deserialize_any(InhibitPassVisitor)
 }
 
-#[derive(Deserialize, Default)]
+#[derive(Deserialize, Default, Clone)]
 #[serde(default)]
 pub struct Config {
     pub general: General,
     pub styles: Styles,
     pub keymaps: Keymaps,
+    pub rules: Vec<Rule>,
 }
 
 #[derive(Deserialize)]
@@ -189,6 +693,7 @@ pub struct Style {
     pub default_sound_file: Option<SoundFile>,
     #[serde(default)]
     pub ignore_sound_file: Option<bool>,
+    /// Theme the `"@name"` colors of the style are taken from instead of `general.style_theme`.
     #[serde(default)]
     pub theme: Option<Arc<str>>,
 }
@@ -237,6 +742,12 @@ pub enum State {
     Hover,
     ContainerHover,
     NamedContainerHover(Arc<str>),
+    /// Entries of the notification history, only taken by the `notification` selector.
+    History,
+    /// Notifications in the compact layout, only taken by the `notification` selector.
+    Compact,
+    /// Notifications shown as an OSD, only taken by the `notification` selector.
+    Osd,
 }
 
 impl<'de> Deserialize<'de> for State {
@@ -249,13 +760,24 @@ impl<'de> Deserialize<'de> for State {
             "default" => Ok(State::Default),
             "hover" => Ok(State::Hover),
             "container_hover" => Ok(State::ContainerHover),
+            "history" => Ok(State::History),
+            "compact" => Ok(State::Compact),
+            "osd" => Ok(State::Osd),
             _ => {
                 if let Some(container) = s.strip_prefix("container_hover:") {
                     Ok(State::NamedContainerHover(container.into()))
                 } else {
                     Err(serde::de::Error::unknown_variant(
                         &s,
-                        &["default", "hover", "container_hover", "container_hover:..."],
+                        &[
+                            "default",
+                            "hover",
+                            "container_hover",
+                            "container_hover:...",
+                            "history",
+                            "compact",
+                            "osd",
+                        ],
                     ))
                 }
             }
@@ -269,13 +791,26 @@ pub enum Selector {
     NextCounter,
     AllNotifications,
     Notification(Box<str>),
-    ActionButton,
+    /// Notifications with the given category hint.
+    Category(Box<str>),
+    Urgency(Urgency),
+    AllActionButtons,
+    /// Buttons of the action with the given key.
+    ActionButton(Box<str>),
     DismissButton,
     Progress,
     Icon,
+    /// App icon shown over the image as an emblem.
+    AppIcon,
     Hints,
     Summary,
     Body,
+    Timestamp,
+    /// Bar showing how long until the notification expires.
+    Countdown,
+    AppName,
+    /// Dot marking pinned notifications.
+    PinIndicator,
 }
 
 impl<'de> Deserialize<'de> for Selector {
@@ -289,16 +824,35 @@ impl<'de> Deserialize<'de> for Selector {
             "prev_counter" => Ok(Selector::PrevCounter),
             "next_counter" => Ok(Selector::NextCounter),
             "notification" => Ok(Selector::AllNotifications),
-            "action" => Ok(Selector::ActionButton),
+            "action" => Ok(Selector::AllActionButtons),
             "dismiss" => Ok(Selector::DismissButton),
             "progress" => Ok(Selector::Progress),
             "icon" => Ok(Selector::Icon),
+            "app_icon" => Ok(Selector::AppIcon),
             "hints" => Ok(Selector::Hints),
             "summary" => Ok(Selector::Summary),
             "body" => Ok(Selector::Body),
+            "timestamp" => Ok(Selector::Timestamp),
+            "countdown" => Ok(Selector::Countdown),
+            "app_name" => Ok(Selector::AppName),
+            "pin_indicator" => Ok(Selector::PinIndicator),
             _ => {
                 if let Some(notification) = s.strip_prefix("notification:") {
                     Ok(Selector::Notification(notification.into()))
+                } else if let Some(action) = s.strip_prefix("action:") {
+                    Ok(Selector::ActionButton(action.into()))
+                } else if let Some(category) = s.strip_prefix("category:") {
+                    Ok(Selector::Category(category.into()))
+                } else if let Some(urgency) = s.strip_prefix("urgency:") {
+                    match urgency {
+                        "low" => Ok(Selector::Urgency(Urgency::Low)),
+                        "normal" => Ok(Selector::Urgency(Urgency::Normal)),
+                        "critical" => Ok(Selector::Urgency(Urgency::Critical)),
+                        _ => Err(serde::de::Error::unknown_variant(
+                            urgency,
+                            &["low", "normal", "critical"],
+                        )),
+                    }
                 } else {
                     Err(serde::de::Error::unknown_variant(
                         &s,
@@ -308,7 +862,10 @@ impl<'de> Deserialize<'de> for Selector {
                             "next_counter",
                             "notification",
                             "notification:...",
+                            "category:...",
+                            "urgency:...",
                             "action",
+                            "action:...",
                             "dismiss",
                         ],
                     ))
@@ -395,7 +952,7 @@ impl Default for Font {
     }
 }
 
-#[derive(Deserialize, PartialEq, Default)]
+#[derive(Deserialize, PartialEq, Default, Clone, Copy)]
 #[serde(rename_all = "snake_case")]
 #[allow(clippy::upper_case_acronyms)]
 pub enum Queue {
@@ -404,11 +961,92 @@ pub enum Queue {
     FIFO,
 }
 
+/// Which notification goes when more are waiting to be shown than `general.queue_limit`.
+#[derive(Deserialize, PartialEq, Default, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueOverflow {
+    #[default]
+    DropOldest,
+    DropNewest,
+    /// The newest replaces the last one waiting from the same app, the oldest goes when
+    /// there's none.
+    Coalesce,
+}
+
+/// Order notifications are shown in, from the top of the list.
+#[derive(Deserialize, PartialEq, Default, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Sort {
+    NewestFirst,
+    #[default]
+    OldestFirst,
+    /// Critical notifications on top, the rest below them oldest first.
+    Urgency,
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    #[default]
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps linear progress in `0..=1` onto the easing curve.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0., 1.);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1. - (1. - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4. * t * t * t
+                } else {
+                    1. - (-2. * t + 2.).powi(3) / 2.
+                }
+            }
+        }
+    }
+}
+
+/// Enter, exit and move transitions of notifications, a duration of 0 disables them.
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(default)]
+pub struct Animation {
+    pub duration_ms: u64,
+    pub easing: Easing,
+}
+
+/// Layout showing only the summary of notifications, switched on and off at runtime.
+#[derive(Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct Compact {
+    /// Whether notifications start out compact.
+    pub enabled: bool,
+    /// Show the selected notification in full while the others stay compact.
+    pub expand_selected: bool,
+}
+
+impl Default for Compact {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            expand_selected: true,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Icon {
     pub border: Border,
     pub margin: Insets,
     pub padding: Insets,
+    /// Corner or edge of the image the app icon is shown at, when there's both.
+    pub anchor: Anchor,
 }
 
 impl Icon {
@@ -422,6 +1060,9 @@ impl Icon {
         if let Some(padding) = partial.padding.as_ref() {
             self.padding.apply(padding);
         }
+        if let Some(anchor) = partial.anchor {
+            self.anchor = anchor;
+        }
     }
 }
 
@@ -439,17 +1080,59 @@ impl Default for Icon {
                 ..Default::default()
             },
             padding: Insets::default(),
+            anchor: Anchor::BottomRight,
         }
     }
 }
 
-#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
 pub enum Size {
     #[default]
-    #[serde(rename = "auto")]
     Auto,
-    #[serde(untagged)]
     Value(f32),
+    /// Share of the width of the parent, the output for notifications and the notification
+    /// for what's inside of it. It's turned into a `Value` once the output is known.
+    Percent(f32),
+}
+
+impl<'de> Deserialize<'de> for Size {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct SizeVisitor;
+
+        impl serde::de::Visitor<'_> for SizeVisitor {
+            type Value = Size;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a number, 'auto', or a percentage like '50%'")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Size::parse(v).ok_or_else(|| {
+                    serde::de::Error::invalid_value(serde::de::Unexpected::Str(v), &self)
+                })
+            }
+
+            fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+                Ok(Size::Value(v as f32))
+            }
+
+            fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+                Ok(Size::Value(v as f32))
+            }
+
+            fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+                Ok(Size::Value(v as f32))
+            }
+        }
+
+        deserializer.deserialize_any(SizeVisitor)
+    }
 }
 
 impl Add for Size {
@@ -509,12 +1192,151 @@ impl Size {
         matches!(self, Size::Auto)
     }
 
+    /// Percentages that weren't resolved against an output yet count as 0.
     pub fn resolve(&self, auto: f32) -> f32 {
         match self {
             Size::Auto => auto,
             Size::Value(v) => *v,
+            Size::Percent(_) => 0.,
         }
     }
+
+    /// Parses `"auto"` and percentages, numbers are taken as they are by the deserializer.
+    fn parse(v: &str) -> Option<Self> {
+        if v == "auto" {
+            return Some(Size::Auto);
+        }
+
+        v.strip_suffix('%')?.trim().parse().ok().map(Size::Percent)
+    }
+}
+
+/// Turns the percentages of a style into pixels of the width of its parent.
+trait ResolvePercent {
+    /// Returns whether there was anything given in percent.
+    fn resolve_percent(&mut self, parent: f32) -> bool;
+}
+
+impl ResolvePercent for Size {
+    fn resolve_percent(&mut self, parent: f32) -> bool {
+        match *self {
+            Size::Percent(percent) => {
+                *self = Size::Value(parent * percent / 100.);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl ResolvePercent for Insets {
+    fn resolve_percent(&mut self, parent: f32) -> bool {
+        [
+            &mut self.left,
+            &mut self.right,
+            &mut self.top,
+            &mut self.bottom,
+        ]
+        .into_iter()
+        .fold(false, |found, size| size.resolve_percent(parent) | found)
+    }
+}
+
+impl ResolvePercent for Icon {
+    fn resolve_percent(&mut self, parent: f32) -> bool {
+        self.border.resolve_percent(parent)
+            | self.margin.resolve_percent(parent)
+            | self.padding.resolve_percent(parent)
+    }
+}
+
+impl ResolvePercent for Progress {
+    fn resolve_percent(&mut self, parent: f32) -> bool {
+        self.margin.resolve_percent(parent)
+            | self.height.resolve_percent(parent)
+            | self.width.resolve_percent(parent)
+            | self.border.resolve_percent(parent)
+    }
+}
+
+impl ResolvePercent for Countdown {
+    fn resolve_percent(&mut self, parent: f32) -> bool {
+        self.height.resolve_percent(parent)
+    }
+}
+
+impl ResolvePercent for PinIndicator {
+    fn resolve_percent(&mut self, parent: f32) -> bool {
+        self.width.resolve_percent(parent)
+            | self.height.resolve_percent(parent)
+            | self.border.resolve_percent(parent)
+            | self.margin.resolve_percent(parent)
+    }
+}
+
+impl ResolvePercent for Hint {
+    fn resolve_percent(&mut self, parent: f32) -> bool {
+        self.width.resolve_percent(parent)
+            | self.height.resolve_percent(parent)
+            | self.border.resolve_percent(parent)
+            | self.padding.resolve_percent(parent)
+            | self.margin.resolve_percent(parent)
+    }
+}
+
+impl ResolvePercent for StyleState {
+    /// The notification itself is sized against the output, what's inside of it against the
+    /// width the notification ends up with.
+    fn resolve_percent(&mut self, output_width: f32) -> bool {
+        let outer = self.width.resolve_percent(output_width)
+            | self.min_height.resolve_percent(output_width)
+            | self.max_height.resolve_percent(output_width)
+            | self.height.resolve_percent(output_width)
+            | self.border.resolve_percent(output_width)
+            | self.margin.resolve_percent(output_width)
+            | self.padding.resolve_percent(output_width);
+
+        let width = self.width.resolve(0.);
+        outer
+            | self.hint.resolve_percent(width)
+            | self.icon.resolve_percent(width)
+            | self.app_icon.resolve_percent(width)
+            | self.progress.resolve_percent(width)
+            | self.countdown.resolve_percent(width)
+            | self.buttons.resolve_percent(width)
+            | self.summary.resolve_percent(width)
+            | self.body.resolve_percent(width)
+            | self.timestamp.resolve_percent(width)
+            | self.app_name.resolve_percent(width)
+            | self.pin_indicator.resolve_percent(width)
+    }
+}
+
+impl ResolvePercent for NotificationCounter {
+    fn resolve_percent(&mut self, parent: f32) -> bool {
+        self.border.resolve_percent(parent)
+            | self.margin.resolve_percent(parent)
+            | self.padding.resolve_percent(parent)
+            | self.hover.border.resolve_percent(parent)
+    }
+}
+
+impl ResolvePercent for Styles {
+    fn resolve_percent(&mut self, output_width: f32) -> bool {
+        // Counters are as wide as the notifications they count
+        let mut width = self.default.width;
+        width.resolve_percent(output_width);
+        let counters = self.next.resolve_percent(width.resolve(0.))
+            | self.prev.resolve_percent(width.resolve(0.));
+
+        self.notification
+            .iter_mut()
+            .flat_map(|entry| [&mut entry.default, &mut entry.hover])
+            .chain([&mut self.default, &mut self.hover])
+            .fold(counters, |found, style| {
+                style.resolve_percent(output_width) | found
+            })
+    }
 }
 
 #[derive(Clone)]
@@ -577,12 +1399,115 @@ impl Default for Progress {
     }
 }
 
+/// Edge of the notification the countdown bar runs along.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum CountdownPosition {
+    Top,
+    #[default]
+    Bottom,
+}
+
+#[derive(Clone)]
+pub struct Countdown {
+    /// The bar isn't drawn at a height of 0.
+    pub height: Size,
+    pub background: Color,
+    pub position: CountdownPosition,
+}
+
+impl Countdown {
+    pub fn apply(&mut self, partial: &PartialStyle) {
+        if let Some(background) = partial.background.as_ref() {
+            self.background.apply(background);
+        }
+        if let Some(height) = partial.height.as_ref() {
+            self.height = *height;
+        }
+        if let Some(position) = partial.position {
+            self.position = position;
+        }
+    }
+}
+
+impl Default for Countdown {
+    fn default() -> Self {
+        Self {
+            height: Size::Value(0.),
+            background: Color {
+                urgency_low: [166, 227, 161, 255],
+                urgency_normal: [203, 166, 247, 255],
+                urgency_critical: [243, 139, 168, 255],
+            },
+            position: CountdownPosition::default(),
+        }
+    }
+}
+
+/// Dot drawn on pinned notifications, which stay until they're dismissed.
+#[derive(Clone)]
+pub struct PinIndicator {
+    pub width: Size,
+    pub height: Size,
+    pub background: Color,
+    pub border: Border,
+    pub margin: Insets,
+    /// Corner or edge of the notification the dot sits at.
+    pub anchor: Anchor,
+}
+
+impl PinIndicator {
+    pub fn apply(&mut self, partial: &PartialStyle) {
+        if let Some(background) = partial.background.as_ref() {
+            self.background.apply(background);
+        }
+        if let Some(width) = partial.width {
+            self.width = width;
+        }
+        if let Some(height) = partial.height {
+            self.height = height;
+        }
+        if let Some(border) = partial.border.as_ref() {
+            self.border.apply(border);
+        }
+        if let Some(margin) = partial.margin.as_ref() {
+            self.margin.apply(margin);
+        }
+        if let Some(anchor) = partial.anchor {
+            self.anchor = anchor;
+        }
+    }
+}
+
+impl Default for PinIndicator {
+    fn default() -> Self {
+        Self {
+            width: Size::Value(6.),
+            height: Size::Value(6.),
+            background: Color {
+                urgency_low: [166, 227, 161, 255],
+                urgency_normal: [203, 166, 247, 255],
+                urgency_critical: [243, 139, 168, 255],
+            },
+            border: Border {
+                size: Insets::size(Size::Value(0.)),
+                radius: BorderRadius::circle(),
+                color: Color::default(),
+            },
+            margin: Insets::size(Size::Value(3.)),
+            anchor: Anchor::TopLeft,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Hint {
     pub background: Color,
     pub width: Size,
     pub height: Size,
     pub font: Font,
+    /// Color of the part of the combination that was already typed.
+    pub font_matched_color: Color,
     pub border: Border,
     pub padding: Insets,
     pub margin: Insets,
@@ -596,6 +1521,9 @@ impl Hint {
         if let Some(font) = partial.font.as_ref() {
             self.font.apply(font);
         }
+        if let Some(color) = partial.font_matched_color.as_ref() {
+            self.font_matched_color.apply(color);
+        }
         if let Some(border) = partial.border.as_ref() {
             self.border.apply(border);
         }
@@ -616,6 +1544,7 @@ impl Default for Hint {
             width: Size::Value(15.),
             height: Size::Value(20.),
             font: Font::default(),
+            font_matched_color: Color::rgba([249, 226, 175, 255]),
             border: Border::default(),
             padding: Insets {
                 left: Size::Auto,
@@ -643,9 +1572,27 @@ pub struct StyleState {
     pub icon: Icon,
     pub app_icon: Icon,
     pub progress: Progress,
+    pub countdown: Countdown,
     pub buttons: Buttons,
     pub summary: Summary,
     pub body: Body,
+    pub timestamp: Timestamp,
+    pub app_name: AppName,
+    pub pin_indicator: PinIndicator,
+    pub render_mode: RenderMode,
+    /// Overrides `general.opacity`, so that hovered notifications can be made opaque.
+    pub opacity: Option<f32>,
+}
+
+/// How a notification is laid out.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum RenderMode {
+    #[default]
+    Normal,
+    /// Just the icon and the progress bar, for notifications carrying a value such as volume
+    /// changes. Applies only to notifications with a value hint.
+    Osd,
 }
 
 impl StyleState {
@@ -684,6 +1631,12 @@ impl StyleState {
         if let Some(partial_padding) = partial.padding.as_ref() {
             self.padding.apply(partial_padding);
         }
+        if let Some(render_mode) = partial.render_mode {
+            self.render_mode = render_mode;
+        }
+        if let Some(opacity) = partial.opacity {
+            self.opacity = Some(opacity);
+        }
     }
 }
 
@@ -692,6 +1645,9 @@ impl Default for StyleState {
         Self {
             body: Body::default(),
             summary: Summary::default(),
+            timestamp: Timestamp::default(),
+            app_name: AppName::default(),
+            pin_indicator: PinIndicator::default(),
             hint: Hint::default(),
             background: Color {
                 urgency_low: [26, 27, 38, 255],
@@ -709,11 +1665,15 @@ impl Default for StyleState {
             icon: Icon::default(),
             app_icon: Icon::default(),
             progress: Progress::default(),
+            countdown: Countdown::default(),
             buttons: Buttons::default(),
+            render_mode: RenderMode::Normal,
+            opacity: None,
         }
     }
 }
 
+#[derive(Clone)]
 pub struct Styles {
     pub next: NotificationCounter,
     pub prev: NotificationCounter,
@@ -734,6 +1694,12 @@ impl<'de> Deserialize<'de> for Styles {
             fn priority(style: &Style) -> u8 {
                 match (&style.selector[0], &style.state) {
                     (Selector::All, _) => 1,
+                    (Selector::AllNotifications, State::History) => 13,
+                    (_, State::History) => 0,
+                    (Selector::AllNotifications, State::Compact) => 13,
+                    (_, State::Compact) => 0,
+                    (Selector::AllNotifications, State::Osd) => 13,
+                    (_, State::Osd) => 0,
                     (Selector::AllNotifications, State::Default) => 2,
                     (Selector::AllNotifications, State::Hover) => 3,
                     (Selector::AllNotifications, State::ContainerHover) => 4,
@@ -742,21 +1708,36 @@ impl<'de> Deserialize<'de> for Styles {
                     (Selector::Notification(_), State::Hover) => 7,
                     (Selector::Notification(_), State::ContainerHover) => 8,
                     (Selector::Notification(_), State::NamedContainerHover(_)) => 9,
-                    (Selector::ActionButton, State::Default) => 10,
-                    (Selector::ActionButton, State::Hover) => 11,
-                    (Selector::ActionButton, State::ContainerHover) => 12,
-                    (Selector::ActionButton, State::NamedContainerHover(_)) => 13,
-                    (Selector::DismissButton, State::Default) => 14,
-                    (Selector::DismissButton, State::Hover) => 15,
-                    (Selector::DismissButton, State::ContainerHover) => 16,
-                    (Selector::DismissButton, State::NamedContainerHover(_)) => 17,
-                    (Selector::Icon, _) => 18,
-                    (Selector::Progress, _) => 19,
-                    (Selector::PrevCounter, _) => 20,
-                    (Selector::NextCounter, _) => 21,
-                    (Selector::Hints, _) => 22,
-                    (Selector::Summary, _) => 23,
-                    (Selector::Body, _) => 24,
+                    (Selector::Urgency(_), State::Default) => 10,
+                    (Selector::Urgency(_), _) => 11,
+                    (Selector::Category(_), State::Default) => 12,
+                    (Selector::Category(_), _) => 13,
+                    (Selector::AllActionButtons, State::Default) => 14,
+                    (Selector::AllActionButtons, State::Hover) => 15,
+                    (Selector::AllActionButtons, State::ContainerHover) => 16,
+                    (Selector::AllActionButtons, State::NamedContainerHover(_)) => 17,
+                    // Start out from the style of every action button, so they come after it
+                    (Selector::ActionButton(_), State::Default) => 18,
+                    (Selector::ActionButton(_), State::Hover) => 19,
+                    (Selector::ActionButton(_), State::ContainerHover) => 20,
+                    (Selector::ActionButton(_), State::NamedContainerHover(_)) => 21,
+                    (Selector::DismissButton, State::Default) => 22,
+                    (Selector::DismissButton, State::Hover) => 23,
+                    (Selector::DismissButton, State::ContainerHover) => 24,
+                    (Selector::DismissButton, State::NamedContainerHover(_)) => 25,
+                    (Selector::Icon | Selector::AppIcon, _) => 26,
+                    (Selector::Progress, _) => 27,
+                    (Selector::PrevCounter, State::Hover) => 29,
+                    (Selector::PrevCounter, _) => 28,
+                    (Selector::NextCounter, State::Hover) => 31,
+                    (Selector::NextCounter, _) => 30,
+                    (Selector::Hints, _) => 32,
+                    (Selector::Summary, _) => 33,
+                    (Selector::Body, _) => 34,
+                    (Selector::Timestamp, _) => 35,
+                    (Selector::Countdown, _) => 36,
+                    (Selector::AppName, _) => 37,
+                    (Selector::PinIndicator, _) => 38,
                 }
             }
 
@@ -766,7 +1747,7 @@ impl<'de> Deserialize<'de> for Styles {
             }
         }
 
-        let mut styles = Styles::default();
+        let mut styles = Styles::base();
 
         let temp_styles = {
             let styles_vec = TempStyles::deserialize(deserializer)?
@@ -817,12 +1798,44 @@ impl<'de> Deserialize<'de> for Styles {
 
                     styles.default.body.apply(&style.style);
                     styles.hover.body.apply(&style.style);
+
+                    styles.default.timestamp.apply(&style.style);
+                    styles.hover.timestamp.apply(&style.style);
+
+                    styles.default.app_name.apply(&style.style);
+                    styles.hover.app_name.apply(&style.style);
+                }
+                (Selector::AllNotifications, State::History) => {
+                    let entry = styles.entry(StyleMatch::History);
+                    entry.default.apply(&style.style);
+                    entry.hover.apply(&style.style);
+                }
+                (_, State::History) => {
+                    log::warn!("State \"history\" only applies to the notification selector")
+                }
+                (Selector::AllNotifications, State::Compact) => {
+                    let entry = styles.entry(StyleMatch::Compact);
+                    entry.default.apply(&style.style);
+                    entry.hover.apply(&style.style);
+                }
+                (_, State::Compact) => {
+                    log::warn!("State \"compact\" only applies to the notification selector")
+                }
+                (Selector::AllNotifications, State::Osd) => {
+                    let entry = styles.entry(StyleMatch::Osd);
+                    entry.default.apply(&style.style);
+                    entry.hover.apply(&style.style);
+                }
+                (_, State::Osd) => {
+                    log::warn!("State \"osd\" only applies to the notification selector")
                 }
                 (Selector::Hints, _) => {
                     styles.default.hint.apply(&style.style);
                     styles.hover.hint.apply(&style.style);
                 }
+                (Selector::NextCounter, State::Hover) => styles.next.apply_hover(&style.style),
                 (Selector::NextCounter, _) => styles.next.apply(&style.style),
+                (Selector::PrevCounter, State::Hover) => styles.prev.apply_hover(&style.style),
                 (Selector::PrevCounter, _) => styles.prev.apply(&style.style),
                 (Selector::Summary, State::ContainerHover) => {
                     styles.default.summary.apply(&style.style);
@@ -876,6 +1889,107 @@ impl<'de> Deserialize<'de> for Styles {
                     styles.default.body.apply(&style.style);
                     styles.hover.body.apply(&style.style);
                 }
+                (Selector::Timestamp, State::ContainerHover) => {
+                    styles.default.timestamp.apply(&style.style);
+                    styles.hover.timestamp.apply(&style.style);
+                }
+                (Selector::Timestamp, State::NamedContainerHover(app_name)) => {
+                    if let Some(notification) = styles
+                        .notification
+                        .iter_mut()
+                        .find(|notification| *notification.app == **app_name)
+                    {
+                        notification.hover.timestamp.apply(&style.style);
+                    } else {
+                        let mut notification = NotificationStyleEntry {
+                            default: styles.default.clone(),
+                            hover: styles.hover.clone(),
+                            app: (&**app_name).into(),
+                            ..Default::default()
+                        };
+                        notification.hover.timestamp.apply(&style.style);
+                        styles.notification.push(notification);
+                    }
+                }
+                (Selector::Timestamp, _) => {
+                    styles.default.timestamp.apply(&style.style);
+                    styles.hover.timestamp.apply(&style.style);
+                }
+                (Selector::AppName, State::ContainerHover) => {
+                    styles.hover.app_name.apply(&style.style);
+                }
+                (Selector::AppName, State::NamedContainerHover(app_name)) => {
+                    if let Some(notification) = styles
+                        .notification
+                        .iter_mut()
+                        .find(|notification| *notification.app == **app_name)
+                    {
+                        notification.hover.app_name.apply(&style.style);
+                    } else {
+                        let mut notification = NotificationStyleEntry {
+                            default: styles.default.clone(),
+                            hover: styles.hover.clone(),
+                            app: (&**app_name).into(),
+                            ..Default::default()
+                        };
+                        notification.hover.app_name.apply(&style.style);
+                        styles.notification.push(notification);
+                    }
+                }
+                (Selector::AppName, _) => {
+                    styles.default.app_name.apply(&style.style);
+                    styles.hover.app_name.apply(&style.style);
+                }
+                (Selector::PinIndicator, State::ContainerHover) => {
+                    styles.hover.pin_indicator.apply(&style.style);
+                }
+                (Selector::PinIndicator, State::NamedContainerHover(app_name)) => {
+                    if let Some(notification) = styles
+                        .notification
+                        .iter_mut()
+                        .find(|notification| *notification.app == **app_name)
+                    {
+                        notification.hover.pin_indicator.apply(&style.style);
+                    } else {
+                        let mut notification = NotificationStyleEntry {
+                            default: styles.default.clone(),
+                            hover: styles.hover.clone(),
+                            app: (&**app_name).into(),
+                            ..Default::default()
+                        };
+                        notification.hover.pin_indicator.apply(&style.style);
+                        styles.notification.push(notification);
+                    }
+                }
+                (Selector::PinIndicator, _) => {
+                    styles.default.pin_indicator.apply(&style.style);
+                    styles.hover.pin_indicator.apply(&style.style);
+                }
+                (Selector::Countdown, State::ContainerHover) => {
+                    styles.hover.countdown.apply(&style.style);
+                }
+                (Selector::Countdown, State::NamedContainerHover(app_name)) => {
+                    if let Some(notification) = styles
+                        .notification
+                        .iter_mut()
+                        .find(|notification| *notification.app == **app_name)
+                    {
+                        notification.hover.countdown.apply(&style.style);
+                    } else {
+                        let mut notification = NotificationStyleEntry {
+                            default: styles.default.clone(),
+                            hover: styles.hover.clone(),
+                            app: (&**app_name).into(),
+                            ..Default::default()
+                        };
+                        notification.hover.countdown.apply(&style.style);
+                        styles.notification.push(notification);
+                    }
+                }
+                (Selector::Countdown, _) => {
+                    styles.default.countdown.apply(&style.style);
+                    styles.hover.countdown.apply(&style.style);
+                }
                 (Selector::Progress, State::ContainerHover) => {
                     styles.hover.progress.apply(&style.style);
                 }
@@ -927,6 +2041,28 @@ impl<'de> Deserialize<'de> for Styles {
                     styles.default.icon.apply(&style.style);
                     styles.hover.icon.apply(&style.style);
                 }
+                (Selector::AppIcon, State::NamedContainerHover(app_name)) => {
+                    if let Some(notification) = styles
+                        .notification
+                        .iter_mut()
+                        .find(|notification| *notification.app == **app_name)
+                    {
+                        notification.hover.app_icon.apply(&style.style);
+                    } else {
+                        let mut notification = NotificationStyleEntry {
+                            default: styles.default.clone(),
+                            hover: styles.hover.clone(),
+                            app: (&**app_name).into(),
+                            ..Default::default()
+                        };
+                        notification.hover.app_icon.apply(&style.style);
+                        styles.notification.push(notification);
+                    }
+                }
+                (Selector::AppIcon, _) => {
+                    styles.default.app_icon.apply(&style.style);
+                    styles.hover.app_icon.apply(&style.style);
+                }
 
                 (Selector::AllNotifications, State::Default) => {
                     styles.default.apply(&style.style);
@@ -971,18 +2107,34 @@ impl<'de> Deserialize<'de> for Styles {
                         styles.notification.push(notification);
                     }
                 }
-                (Selector::ActionButton, State::Default) => {
+                (Selector::Category(category), State::Default) => styles
+                    .entry(StyleMatch::Category(category))
+                    .default
+                    .apply(&style.style),
+                (Selector::Category(category), _) => styles
+                    .entry(StyleMatch::Category(category))
+                    .hover
+                    .apply(&style.style),
+                (Selector::Urgency(urgency), State::Default) => styles
+                    .entry(StyleMatch::Urgency(*urgency))
+                    .default
+                    .apply(&style.style),
+                (Selector::Urgency(urgency), _) => styles
+                    .entry(StyleMatch::Urgency(*urgency))
+                    .hover
+                    .apply(&style.style),
+                (Selector::AllActionButtons, State::Default) => {
                     styles.default.buttons.action.apply(&style.style);
                     styles.hover.buttons.action.apply(&style.style);
                 }
-                (Selector::ActionButton, State::Hover) => {
+                (Selector::AllActionButtons, State::Hover) => {
                     styles.default.buttons.action.apply_hover(&style.style);
                     styles.hover.buttons.action.apply_hover(&style.style);
                 }
-                (Selector::ActionButton, State::ContainerHover) => {
+                (Selector::AllActionButtons, State::ContainerHover) => {
                     styles.hover.buttons.action.apply(&style.style);
                 }
-                (Selector::ActionButton, State::NamedContainerHover(app_name)) => {
+                (Selector::AllActionButtons, State::NamedContainerHover(app_name)) => {
                     if let Some(notification) = styles
                         .notification
                         .iter_mut()
@@ -1000,6 +2152,55 @@ impl<'de> Deserialize<'de> for Styles {
                         styles.notification.push(notification);
                     }
                 }
+                (Selector::ActionButton(key), State::Default) => {
+                    styles
+                        .default
+                        .buttons
+                        .action_for_mut(key)
+                        .apply(&style.style);
+                    styles.hover.buttons.action_for_mut(key).apply(&style.style);
+                }
+                (Selector::ActionButton(key), State::Hover) => {
+                    styles
+                        .default
+                        .buttons
+                        .action_for_mut(key)
+                        .apply_hover(&style.style);
+                    styles
+                        .hover
+                        .buttons
+                        .action_for_mut(key)
+                        .apply_hover(&style.style);
+                }
+                (Selector::ActionButton(key), State::ContainerHover) => {
+                    styles.hover.buttons.action_for_mut(key).apply(&style.style);
+                }
+                (Selector::ActionButton(key), State::NamedContainerHover(app_name)) => {
+                    if let Some(notification) = styles
+                        .notification
+                        .iter_mut()
+                        .find(|notification| *notification.app == **app_name)
+                    {
+                        notification
+                            .hover
+                            .buttons
+                            .action_for_mut(key)
+                            .apply(&style.style);
+                    } else {
+                        let mut notification = NotificationStyleEntry {
+                            default: styles.default.clone(),
+                            hover: styles.hover.clone(),
+                            app: (&**app_name).into(),
+                            ..Default::default()
+                        };
+                        notification
+                            .hover
+                            .buttons
+                            .action_for_mut(key)
+                            .apply(&style.style);
+                        styles.notification.push(notification);
+                    }
+                }
                 (Selector::DismissButton, State::Default) => {
                     styles.default.buttons.dismiss.apply(&style.style);
                     styles.hover.buttons.dismiss.apply(&style.style);
@@ -1032,12 +2233,109 @@ impl<'de> Deserialize<'de> for Styles {
             }
         });
 
+        // Timeouts apply whatever the state but hover, every `notification:` entry of an app adds
+        // to them
+        temp_styles.0.iter().for_each(|style| {
+            let Selector::Notification(app_name) = style.selector.first().unwrap() else {
+                return;
+            };
+            let Some(entry) = styles.notification.iter_mut().find(|entry| {
+                entry.category.is_none() && entry.urgency.is_none() && *entry.app == **app_name
+            }) else {
+                return;
+            };
+
+            if let Some(timeout) = style.default_timeout.as_ref() {
+                match style.state {
+                    State::Hover => &mut entry.hover_timeout,
+                    _ => &mut entry.default_timeout,
+                }
+                .get_or_insert_with(Timeout::default)
+                .merge(timeout);
+            }
+            if let Some(ignore_timeout) = style.ignore_timeout {
+                entry.ignore_timeout = Some(ignore_timeout);
+            }
+        });
+
+        // Compact notifications get the default compact style even without a `compact` entry
+        styles.entry(StyleMatch::Compact);
+
         Ok(styles)
     }
 }
 
+/// Padding of compact notifications unless their style sets one.
+const COMPACT_PADDING: f32 = 5.;
+
+/// Notification style entries that don't go by the app name.
+#[derive(Clone, Copy)]
+enum StyleMatch<'a> {
+    Category(&'a str),
+    Urgency(Urgency),
+    History,
+    Compact,
+    Osd,
+}
+
+impl Styles {
+    /// Entry for notifications matching `criteria`, created from the global styles if there's
+    /// none yet.
+    fn entry(&mut self, criteria: StyleMatch) -> &mut NotificationStyleEntry {
+        let index = self
+            .notification
+            .iter()
+            .position(|entry| match criteria {
+                StyleMatch::Category(category) => entry.category.as_deref() == Some(category),
+                StyleMatch::Urgency(urgency) => entry.urgency == Some(urgency),
+                StyleMatch::History => entry.history,
+                StyleMatch::Compact => entry.compact,
+                StyleMatch::Osd => entry.osd,
+            })
+            .unwrap_or_else(|| {
+                let (category, urgency) = match criteria {
+                    StyleMatch::Category(category) => (Some(category.into()), None),
+                    StyleMatch::Urgency(urgency) => (None, Some(urgency)),
+                    StyleMatch::History | StyleMatch::Compact | StyleMatch::Osd => (None, None),
+                };
+                let mut entry = NotificationStyleEntry {
+                    default: self.default.clone(),
+                    hover: self.hover.clone(),
+                    category,
+                    urgency,
+                    history: matches!(criteria, StyleMatch::History),
+                    compact: matches!(criteria, StyleMatch::Compact),
+                    osd: matches!(criteria, StyleMatch::Osd),
+                    ..Default::default()
+                };
+                if entry.compact {
+                    entry.default.padding = Insets::size(Size::Value(COMPACT_PADDING));
+                    entry.hover.padding = Insets::size(Size::Value(COMPACT_PADDING));
+                }
+                // Notifications taking the entry are shown as an OSD already
+                if entry.osd {
+                    entry.default.render_mode = RenderMode::Osd;
+                    entry.hover.render_mode = RenderMode::Osd;
+                }
+                self.notification.push(entry);
+                self.notification.len() - 1
+            });
+
+        &mut self.notification[index]
+    }
+}
+
 impl Default for Styles {
     fn default() -> Self {
+        let mut styles = Self::base();
+        styles.entry(StyleMatch::Compact);
+        styles
+    }
+}
+
+impl Styles {
+    /// Styles before any of the config or the default compact entry are applied.
+    fn base() -> Self {
         Self {
             next: NotificationCounter::default(),
             prev: NotificationCounter::default(),
@@ -1074,7 +2372,24 @@ impl Default for Styles {
     }
 }
 
-#[derive(Deserialize, Default)]
+/// Handling of notifications arriving while the session is locked. The lock state is read from
+/// the `LockedHint` of the logind session.
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Lockscreen {
+    /// Shown as if the session wasn't locked.
+    Show,
+    /// Held back until the session is unlocked.
+    #[default]
+    Hide,
+    /// Held back, with a single notification telling how many there are in their place.
+    SummaryCount,
+}
+
+/// Layers of the layer shell, from the lowest to the highest.
+#[derive(
+    Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum,
+)]
 #[serde(rename_all = "snake_case")]
 pub enum Layer {
     Background,
@@ -1084,7 +2399,53 @@ pub enum Layer {
     Overlay,
 }
 
-#[derive(Deserialize, Default)]
+/// Graphics API notifications are drawn with.
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Renderer {
+    /// Vulkan, then OpenGL, then whichever of them runs on the CPU.
+    #[default]
+    Auto,
+    Vulkan,
+    Gl,
+    /// Drawn on the CPU by lavapipe or llvmpipe, for when no GPU driver works.
+    Software,
+}
+
+/// Which GPU is picked on systems with more than one.
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerPreference {
+    #[default]
+    LowPower,
+    HighPerformance,
+}
+
+/// How many notifications are shown at once before the rest has to be scrolled to.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum MaxVisible {
+    /// As many as fit on the output, going by their heights.
+    #[serde(rename = "auto")]
+    Auto,
+    #[serde(untagged)]
+    Count(usize),
+}
+
+/// Space the notifications reserve along the edge they're anchored to, tiled windows are pushed
+/// aside instead of being covered.
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+pub enum ExclusiveZone {
+    #[default]
+    #[serde(rename = "none")]
+    None,
+    /// As much as the notifications on screen take up.
+    #[serde(rename = "auto")]
+    Auto,
+    #[serde(untagged)]
+    Value(u32),
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Debug)]
 #[serde(rename_all = "snake_case")]
 pub enum Anchor {
     #[default]
@@ -1099,30 +2460,75 @@ pub enum Anchor {
     Center,
 }
 
-#[derive(Clone, Copy)]
+impl std::str::FromStr for Anchor {
+    type Err = String;
+
+    /// Takes the names of the config, with dashes in place of underscores as well.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.replace('-', "_").as_str() {
+            "top_right" => Ok(Self::TopRight),
+            "top_center" => Ok(Self::TopCenter),
+            "top_left" => Ok(Self::TopLeft),
+            "bottom_right" => Ok(Self::BottomRight),
+            "bottom_center" => Ok(Self::BottomCenter),
+            "bottom_left" => Ok(Self::BottomLeft),
+            "center_right" => Ok(Self::CenterRight),
+            "center_left" => Ok(Self::CenterLeft),
+            "center" => Ok(Self::Center),
+            _ => Err(format!("Invalid anchor: {s}")),
+        }
+    }
+}
+
+/// Seconds notifications stay on screen for, a number sets it for every urgency and a map for
+/// the urgencies it names.
+#[derive(Clone, Copy, Default)]
 pub struct Timeout {
-    urgency_low: i32,
-    urgency_normal: i32,
-    urgency_critical: i32,
+    all: Option<i32>,
+    urgency_low: Option<i32>,
+    urgency_normal: Option<i32>,
+    urgency_critical: Option<i32>,
 }
 
-impl Default for Timeout {
-    fn default() -> Self {
+impl Timeout {
+    fn global() -> Self {
         Self {
-            urgency_low: 5,
-            urgency_normal: 10,
-            urgency_critical: 0,
+            all: None,
+            urgency_low: Some(5),
+            urgency_normal: Some(10),
+            urgency_critical: Some(0),
         }
     }
-}
 
-impl Timeout {
-    pub fn get(&self, urgency: &crate::Urgency) -> i32 {
-        match urgency {
+    fn all(value: i32) -> Self {
+        Self {
+            all: Some(value),
+            ..Default::default()
+        }
+    }
+
+    /// Timeout set for `urgency`, either by name or by a number.
+    pub fn find(&self, urgency: &crate::Urgency) -> Option<i32> {
+        let timeout = match urgency {
             crate::Urgency::Low => self.urgency_low,
             crate::Urgency::Normal => self.urgency_normal,
             crate::Urgency::Critical => self.urgency_critical,
-        }
+        };
+
+        timeout.or(self.all)
+    }
+
+    /// Timeout for `urgency`, urgencies a map leaves out never expire.
+    pub fn get(&self, urgency: &crate::Urgency) -> i32 {
+        self.find(urgency).unwrap_or_default()
+    }
+
+    /// Sets what `other` sets over this one.
+    fn merge(&mut self, other: &Self) {
+        self.all = other.all.or(self.all);
+        self.urgency_low = other.urgency_low.or(self.urgency_low);
+        self.urgency_normal = other.urgency_normal.or(self.urgency_normal);
+        self.urgency_critical = other.urgency_critical.or(self.urgency_critical);
     }
 }
 
@@ -1141,71 +2547,40 @@ impl<'de> Deserialize<'de> for Timeout {
             }
 
             fn visit_f32<E>(self, v: f32) -> Result<Self::Value, E> {
-                let value = v as i32;
-                Ok(Timeout {
-                    urgency_low: value,
-                    urgency_normal: value,
-                    urgency_critical: value,
-                })
+                Ok(Timeout::all(v as i32))
             }
 
             fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
-                let value = v as i32;
-                Ok(Timeout {
-                    urgency_low: value,
-                    urgency_normal: value,
-                    urgency_critical: value,
-                })
+                Ok(Timeout::all(v as i32))
             }
 
             fn visit_i32<E>(self, value: i32) -> Result<Self::Value, E> {
-                Ok(Timeout {
-                    urgency_low: value,
-                    urgency_normal: value,
-                    urgency_critical: value,
-                })
+                Ok(Timeout::all(value))
             }
 
             fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
-                let value = v as i32;
-                Ok(Timeout {
-                    urgency_low: value,
-                    urgency_normal: value,
-                    urgency_critical: value,
-                })
+                Ok(Timeout::all(v as i32))
             }
 
             fn visit_u32<E>(self, v: u32) -> Result<Self::Value, E> {
-                let value = v as i32;
-                Ok(Timeout {
-                    urgency_low: value,
-                    urgency_normal: value,
-                    urgency_critical: value,
-                })
+                Ok(Timeout::all(v as i32))
             }
 
             fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
-                let value = v as i32;
-                Ok(Timeout {
-                    urgency_low: value,
-                    urgency_normal: value,
-                    urgency_critical: value,
-                })
+                Ok(Timeout::all(v as i32))
             }
 
             fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
             where
                 M: serde::de::MapAccess<'de>,
             {
-                let mut urgency_low = None;
-                let mut urgency_normal = None;
-                let mut urgency_critical = None;
+                let mut timeout = Timeout::default();
 
                 while let Some(key) = map.next_key::<String>()? {
                     match key.as_str() {
-                        "urgency_low" => urgency_low = Some(map.next_value()?),
-                        "urgency_normal" => urgency_normal = Some(map.next_value()?),
-                        "urgency_critical" => urgency_critical = Some(map.next_value()?),
+                        "urgency_low" => timeout.urgency_low = Some(map.next_value()?),
+                        "urgency_normal" => timeout.urgency_normal = Some(map.next_value()?),
+                        "urgency_critical" => timeout.urgency_critical = Some(map.next_value()?),
                         _ => {
                             return Err(serde::de::Error::unknown_field(
                                 &key,
@@ -1215,11 +2590,7 @@ impl<'de> Deserialize<'de> for Timeout {
                     }
                 }
 
-                Ok(Timeout {
-                    urgency_low: urgency_low.unwrap_or_default(),
-                    urgency_normal: urgency_normal.unwrap_or_default(),
-                    urgency_critical: urgency_critical.unwrap_or_default(),
-                })
+                Ok(timeout)
             }
         }
 
@@ -1227,24 +2598,96 @@ impl<'de> Deserialize<'de> for Timeout {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct NotificationStyleEntry {
     pub app: Arc<str>,
+    /// Set for `category:` entries, which match on the category alone.
+    pub category: Option<Arc<str>>,
+    /// Set for `urgency:` entries, which match on the urgency alone.
+    pub urgency: Option<Urgency>,
+    /// Set for the `state = "history"` entry, which matches history entries alone.
+    pub history: bool,
+    /// Set for the `state = "compact"` entry, which matches compact notifications alone.
+    pub compact: bool,
+    /// Set for the `state = "osd"` entry, which matches OSD notifications alone.
+    pub osd: bool,
     pub default: StyleState,
     pub hover: StyleState,
     pub default_timeout: Option<Timeout>,
+    /// Timeout of `state = "hover"` entries, only taken while the notification is hovered.
+    pub hover_timeout: Option<Timeout>,
     pub ignore_timeout: Option<bool>,
     pub default_sound_file: Option<SoundFile>,
     pub ignore_sound_file: Option<bool>,
 }
 
+impl NotificationStyleEntry {
+    /// How strongly the entry applies to the notification, `None` if it doesn't.
+    fn precedence(&self, key: &StyleKey) -> Option<u8> {
+        if self.osd {
+            return key.osd.then_some(6);
+        }
+        if self.compact {
+            return key.compact.then_some(5);
+        }
+        if self.history {
+            return key.history.then_some(4);
+        }
+
+        match (self.category.as_deref(), self.urgency) {
+            (Some(category), _) => (key.category.as_deref() == Some(category)).then_some(3),
+            (None, Some(urgency)) => (key.urgency == urgency).then_some(2),
+            (None, None) => (self.app == key.app_name).then_some(1),
+        }
+    }
+}
+
+/// What notification style entries are matched against.
+#[derive(Clone, Default, Debug)]
+pub struct StyleKey {
+    pub app_name: Arc<str>,
+    pub category: Option<Arc<str>>,
+    pub urgency: Urgency,
+    /// Whether the notification is an entry of the history rather than a live one.
+    pub history: bool,
+    /// Whether the notification only shows its summary.
+    pub compact: bool,
+    /// Whether the notification only shows its icon and progress bar.
+    pub osd: bool,
+}
+
+impl StyleKey {
+    pub fn new(data: &NotificationData, history: History) -> Self {
+        Self {
+            app_name: Arc::clone(&data.app_name),
+            category: data.hints.category.as_deref().map(Into::into),
+            urgency: data.hints.urgency,
+            history: history == History::Shown,
+            compact: false,
+            osd: false,
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct NotificationCounter {
     pub format: Box<str>,
+    /// Shown by the counter below the view for notifications still waiting to be shown with
+    /// `queue = "fifo"`, apart from the ones scrolled past.
+    pub pending_format: Box<str>,
     pub border: Border,
     pub background: Color,
     pub margin: Insets,
     pub padding: Insets,
     pub font: Font,
+    /// Look of the counter while the pointer is over it.
+    pub hover: CounterHover,
+}
+
+#[derive(Clone)]
+pub struct CounterHover {
+    pub border: Border,
+    pub background: Color,
 }
 
 impl NotificationCounter {
@@ -1262,17 +2705,31 @@ impl NotificationCounter {
             self.padding.apply(padding);
         }
     }
+
+    pub fn apply_hover(&mut self, partial: &PartialStyle) {
+        if let Some(background) = partial.background.as_ref() {
+            self.hover.background.apply(background);
+        }
+        if let Some(border) = partial.border.as_ref() {
+            self.hover.border.apply(border);
+        }
+    }
 }
 
 impl Default for NotificationCounter {
     fn default() -> Self {
         Self {
             format: "({} more)".into(),
+            pending_format: "({} pending)".into(),
             border: Border::default(),
             background: Color::rgba([26, 27, 38, 255]),
             margin: Insets::default(),
             padding: Insets::default(),
             font: Font::default(),
+            hover: CounterHover {
+                border: Border::default(),
+                background: Color::rgba([47, 53, 73, 255]),
+            },
         }
     }
 }
@@ -1282,19 +2739,15 @@ impl Config {
     where
         T: AsRef<Path>,
     {
-        let lua_code = if let Some(path) = path {
-            if !path.as_ref().exists() {
-                return Ok(Self::default());
-            }
-            fs::read_to_string(path).unwrap_or_default()
-        } else {
-            let path = Self::path()?;
-            if !path.exists() {
-                return Ok(Self::default());
-            }
-
-            fs::read_to_string(path).unwrap_or_default()
+        let path: Box<Path> = match path {
+            Some(path) => path.as_ref().into(),
+            None => Self::path()?,
         };
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let lua_code = fs::read_to_string(&path).unwrap_or_default();
 
         let lua = Lua::new();
 
@@ -1303,19 +2756,24 @@ impl Config {
             .eval()
             .map_err(|e| anyhow::anyhow!("Lua evaluation error: {}", e))?;
 
+        if let mlua::Value::Table(config) = &lua_result {
+            let dir = path.parent().unwrap_or(Path::new("."));
+            theme::apply(&lua, config, &dir.join("themes"))?;
+        }
+
         lua.from_value(lua_result)
             .map_err(|e| anyhow::anyhow!("Config deserialization error: {}", e))
     }
 
-    pub fn find_style<T>(&self, app_name: T, hovered: bool) -> &StyleState
-    where
-        T: AsRef<str>,
-    {
+    /// Style of the notification `key` describes. When several entries match, `category:`
+    /// entries win over `urgency:` ones, which win over `notification:<app>` ones.
+    pub fn find_style(&self, key: &StyleKey, hovered: bool) -> &StyleState {
         self.styles
             .notification
             .iter()
-            .find(|n| &*n.app == app_name.as_ref())
-            .map(|c| if hovered { &c.hover } else { &c.default })
+            .filter_map(|entry| Some((entry.precedence(key)?, entry)))
+            .max_by_key(|(precedence, _)| *precedence)
+            .map(|(_, c)| if hovered { &c.hover } else { &c.default })
             .unwrap_or_else(|| {
                 if hovered {
                     &self.styles.hover
@@ -1325,6 +2783,87 @@ impl Config {
             })
     }
 
+    /// Milliseconds until a notification of `app_name` expires, `expire_timeout` being the one
+    /// it asked for. A timeout the `notification:` entry of the app sets for the urgency wins
+    /// over one it sets for every urgency, which wins over the global one of the urgency. While
+    /// `hovered`, the ones of its `state = "hover"` entry come first.
+    pub fn timeout(
+        &self,
+        app_name: &str,
+        urgency: Urgency,
+        expire_timeout: i32,
+        hovered: bool,
+    ) -> Option<u64> {
+        let entry = self.styles.notification.iter().find(|entry| {
+            entry.category.is_none() && entry.urgency.is_none() && *entry.app == *app_name
+        });
+
+        let ignore_timeout = entry
+            .and_then(|entry| entry.ignore_timeout)
+            .unwrap_or(self.general.ignore_timeout);
+
+        let hover_timeout = entry
+            .filter(|_| hovered)
+            .and_then(|entry| entry.hover_timeout.as_ref())
+            .and_then(|timeout| timeout.find(&urgency));
+        let default_timeout = hover_timeout
+            .or_else(|| {
+                entry
+                    .and_then(|entry| entry.default_timeout.as_ref())
+                    .and_then(|timeout| timeout.find(&urgency))
+            })
+            .unwrap_or_else(|| self.general.default_timeout.get(&urgency));
+        let default_timeout = (default_timeout > 0).then(|| default_timeout as u64 * 1000);
+
+        if ignore_timeout {
+            return default_timeout;
+        }
+
+        // 0 never expires, any negative timeout leaves it to the server
+        match expire_timeout {
+            0 => None,
+            t if t < 0 => default_timeout,
+            t => Some(t as u64),
+        }
+    }
+
+    /// Runs the rules matching `data` over it in order, so later ones override earlier ones.
+    pub fn apply_rules(&self, data: &mut NotificationData) {
+        for (i, rule) in self.rules.iter().enumerate() {
+            if rule.matches(data) {
+                log::debug!(
+                    "Rule {i} matched notification from {}: '{}'",
+                    data.app_name,
+                    data.summary
+                );
+                rule.apply(data);
+            }
+        }
+    }
+
+    /// Copy of the config with sizes given in percent turned into pixels of an output
+    /// `output_width` wide, None if there are none.
+    pub fn resolved(&self, output_width: f32) -> Option<Self> {
+        let mut config = self.clone();
+        let placement = &mut config.general.urgency_placement;
+        let margins = [
+            &mut placement.urgency_low,
+            &mut placement.urgency_normal,
+            &mut placement.urgency_critical,
+        ]
+        .into_iter()
+        .filter_map(|placement| placement.as_mut()?.margin.as_mut())
+        .fold(false, |found, margin| {
+            margin.resolve_percent(output_width) | found
+        });
+
+        let found = margins
+            | config.general.margin.resolve_percent(output_width)
+            | config.styles.resolve_percent(output_width);
+
+        found.then_some(config)
+    }
+
     pub fn path() -> anyhow::Result<Box<Path>> {
         let home_dir = std::env::var("HOME").map(PathBuf::from)?;
         let config_dir = std::env::var("XDG_CONFIG_HOME")
@@ -1341,3 +2880,532 @@ impl Config {
         Ok(standard_path.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Anchor, Config, Layer, RenderMode, Size, StyleKey, SurfacePlacement, COMPACT_PADDING,
+    };
+    use crate::Urgency;
+    use mlua::{Lua, LuaSerdeExt};
+    use std::path::Path;
+
+    #[test]
+    fn test_category_overrides_app() {
+        let lua = Lua::new();
+        let value = lua
+            .load(
+                r#"
+                return {
+                  styles = {
+                    { selector = "notification:Slack", style = { width = 400 } },
+                    { selector = "category:email", style = { width = 500 } },
+                    { selector = "urgency:critical", style = { width = 600 } },
+                  },
+                }
+                "#,
+            )
+            .eval()
+            .unwrap();
+        let config: Config = lua.from_value(value).unwrap();
+
+        let key = |app_name: &str, category: Option<&str>, urgency| StyleKey {
+            app_name: app_name.into(),
+            category: category.map(Into::into),
+            urgency,
+            history: false,
+            compact: false,
+            osd: false,
+        };
+
+        let width = |key: StyleKey| config.find_style(&key, false).width.resolve(0.);
+        assert_eq!(width(key("Slack", Some("email"), Urgency::Normal)), 500.);
+        assert_eq!(width(key("Slack", Some("email"), Urgency::Critical)), 500.);
+        assert_eq!(width(key("Slack", None, Urgency::Critical)), 600.);
+        assert_eq!(width(key("Slack", Some("im"), Urgency::Normal)), 400.);
+        assert_eq!(width(key("Mail", Some("email"), Urgency::Low)), 500.);
+        assert_eq!(
+            width(key("Mail", None, Urgency::Normal)),
+            config.styles.default.width.resolve(0.)
+        );
+    }
+
+    #[test]
+    fn test_history_style() {
+        let lua = Lua::new();
+        let value = lua
+            .load(
+                r#"
+                return {
+                  styles = {
+                    { selector = "notification", style = { width = 300 } },
+                    { selector = "urgency:critical", style = { width = 600 } },
+                    { selector = "notification", state = "history", style = { width = 250 } },
+                  },
+                }
+                "#,
+            )
+            .eval()
+            .unwrap();
+        let config: Config = lua.from_value(value).unwrap();
+
+        let key = |urgency, history| StyleKey {
+            app_name: "Mail".into(),
+            category: None,
+            urgency,
+            history,
+            compact: false,
+            osd: false,
+        };
+
+        let width = |key: StyleKey| config.find_style(&key, false).width.resolve(0.);
+        assert_eq!(width(key(Urgency::Normal, false)), 300.);
+        assert_eq!(width(key(Urgency::Critical, false)), 600.);
+        assert_eq!(width(key(Urgency::Critical, true)), 250.);
+        assert_eq!(
+            config
+                .find_style(&key(Urgency::Low, true), true)
+                .width
+                .resolve(0.),
+            250.
+        );
+    }
+
+    #[test]
+    fn test_compact_style() {
+        let lua = Lua::new();
+        let value = lua
+            .load(
+                r#"
+                return {
+                  general = { compact = { enabled = true } },
+                  styles = {
+                    { selector = "notification", style = { width = 300 } },
+                    { selector = "notification", state = "history", style = { width = 250 } },
+                    { selector = "notification", state = "compact", style = { width = 200 } },
+                  },
+                }
+                "#,
+            )
+            .eval()
+            .unwrap();
+        let config: Config = lua.from_value(value).unwrap();
+        assert!(config.general.compact.enabled);
+        assert!(config.general.compact.expand_selected);
+
+        let key = |history, compact| StyleKey {
+            app_name: "Mail".into(),
+            category: None,
+            urgency: Urgency::Normal,
+            history,
+            compact,
+            osd: false,
+        };
+
+        let width = |key: StyleKey| config.find_style(&key, false).width.resolve(0.);
+        assert_eq!(width(key(false, false)), 300.);
+        assert_eq!(width(key(false, true)), 200.);
+        // Compact history entries take the compact style
+        assert_eq!(width(key(true, true)), 200.);
+        assert_eq!(
+            config.find_style(&key(false, true), false).padding.top,
+            Size::Value(COMPACT_PADDING)
+        );
+
+        let config = Config::default();
+        assert_eq!(
+            config.find_style(&key(false, false), false).padding.top,
+            Size::Value(10.)
+        );
+        assert_eq!(
+            config.find_style(&key(false, true), false).padding.top,
+            Size::Value(COMPACT_PADDING)
+        );
+    }
+
+    #[test]
+    fn test_action_button_style() {
+        let lua = Lua::new();
+        let value = lua
+            .load(
+                r#"
+                return {
+                  styles = {
+                    { selector = "action:accept", style = { width = 80 } },
+                    { selector = "action", style = { width = 50, height = 20 } },
+                    { selector = "action:decline", state = "hover", style = { width = 90 } },
+                  },
+                }
+                "#,
+            )
+            .eval()
+            .unwrap();
+        let config: Config = lua.from_value(value).unwrap();
+
+        let buttons = &config.styles.default.buttons;
+        assert_eq!(buttons.action_for("accept").default.width.resolve(0.), 80.);
+        assert_eq!(buttons.action_for("accept").default.height.resolve(0.), 20.);
+        assert_eq!(buttons.action_for("decline").default.width.resolve(0.), 50.);
+        assert_eq!(buttons.action_for("decline").hover.width.resolve(0.), 90.);
+        assert_eq!(buttons.action_for("default").default.width.resolve(0.), 50.);
+    }
+
+    #[test]
+    fn test_opacity() {
+        let lua = Lua::new();
+        let value = lua
+            .load(
+                r#"
+                return {
+                  general = { opacity = 0.9 },
+                  styles = {
+                    { selector = "notification", state = "hover", style = { opacity = 1 } },
+                    { selector = "urgency:critical", style = { opacity = 2 } },
+                  },
+                }
+                "#,
+            )
+            .eval()
+            .unwrap();
+        let config: Config = lua.from_value(value).unwrap();
+        assert_eq!(config.general.opacity, 0.9);
+
+        let key = |urgency| StyleKey {
+            app_name: "Mail".into(),
+            category: None,
+            urgency,
+            history: false,
+            compact: false,
+            osd: false,
+        };
+        assert_eq!(
+            config.find_style(&key(Urgency::Normal), false).opacity,
+            None
+        );
+        assert_eq!(
+            config.find_style(&key(Urgency::Normal), true).opacity,
+            Some(1.)
+        );
+        assert_eq!(
+            config.find_style(&key(Urgency::Critical), false).opacity,
+            Some(1.)
+        );
+    }
+
+    #[test]
+    fn test_urgency_placement() {
+        let lua = Lua::new();
+        let value = lua
+            .load(
+                r#"
+                return {
+                  general = {
+                    anchor = "top_left",
+                    margin = { top = 10 },
+                    urgency_placement = {
+                      urgency_critical = { anchor = "center" },
+                    },
+                  },
+                }
+                "#,
+            )
+            .eval()
+            .unwrap();
+        let config: Config = lua.from_value(value).unwrap();
+
+        let (anchor, margin) = config.general.placement(Urgency::Critical);
+        assert_eq!(anchor, Anchor::Center);
+        assert_eq!(margin.top, Size::Value(10.));
+
+        let (anchor, _) = config.general.placement(Urgency::Normal);
+        assert_eq!(anchor, Anchor::TopLeft);
+    }
+
+    #[test]
+    fn test_osd() {
+        let lua = Lua::new();
+        let value = lua
+            .load(
+                r#"
+                return {
+                  general = { anchor = "top_left", margin = { top = 10 } },
+                  styles = {
+                    { selector = "notification:volume", style = { render_mode = "osd" } },
+                    { selector = "notification", state = "osd", style = { width = 200 } },
+                  },
+                }
+                "#,
+            )
+            .eval()
+            .unwrap();
+        let config: Config = lua.from_value(value).unwrap();
+
+        // Centered unless `general.osd` sets an anchor
+        let (anchor, margin) = config.general.placement(SurfacePlacement::Osd);
+        assert_eq!(anchor, Anchor::Center);
+        assert_eq!(margin.top, Size::Value(10.));
+
+        let key = |osd| StyleKey {
+            app_name: "volume".into(),
+            category: None,
+            urgency: Urgency::Normal,
+            history: false,
+            compact: false,
+            osd,
+        };
+        let style = config.find_style(&key(false), false);
+        assert_eq!(style.render_mode, RenderMode::Osd);
+        assert_eq!(style.width.resolve(0.), 300.);
+
+        let style = config.find_style(&key(true), false);
+        assert_eq!(style.render_mode, RenderMode::Osd);
+        assert_eq!(style.width.resolve(0.), 200.);
+    }
+
+    #[test]
+    fn test_urgency_layer() {
+        let lua = Lua::new();
+        let value = lua
+            .load(
+                r#"
+                return {
+                  general = {
+                    layer = "top",
+                    urgency_layer = { low = "bottom", critical = "overlay" },
+                  },
+                }
+                "#,
+            )
+            .eval()
+            .unwrap();
+        let config: Config = lua.from_value(value).unwrap();
+        let general = &config.general;
+
+        assert_eq!(general.layer([]), Layer::Top);
+        assert_eq!(general.layer([Urgency::Low]), Layer::Bottom);
+        assert_eq!(general.layer([Urgency::Low, Urgency::Normal]), Layer::Top);
+        assert_eq!(
+            general.layer([Urgency::Normal, Urgency::Critical]),
+            Layer::Overlay
+        );
+    }
+
+    #[test]
+    fn test_percent_sizes() {
+        let lua = Lua::new();
+        let value = lua
+            .load(
+                r#"
+                return {
+                  general = { margin = { left = "-5%", top = 10 } },
+                  styles = {
+                    {
+                      selector = "notification",
+                      style = { width = "40%", margin = { left = "30%" }, padding = "5%" },
+                    },
+                  },
+                }
+                "#,
+            )
+            .eval()
+            .unwrap();
+        let config: Config = lua.from_value(value).unwrap();
+        assert_eq!(config.styles.default.width, Size::Percent(40.));
+        assert_eq!(config.general.margin.left, Size::Percent(-5.));
+
+        let resolved = config.resolved(1000.).unwrap();
+        let style = &resolved.styles.default;
+        assert_eq!(style.width, Size::Value(400.));
+        assert_eq!(style.margin.left, Size::Value(300.));
+        assert_eq!(style.padding.top, Size::Value(50.));
+        assert_eq!(resolved.general.margin.left, Size::Value(-50.));
+        assert_eq!(resolved.general.margin.top, Size::Value(10.));
+
+        assert!(Config::default().resolved(1000.).is_none());
+    }
+
+    #[test]
+    fn test_percent_resolves_against_notification() {
+        let lua = Lua::new();
+        let value = lua
+            .load(
+                r#"
+                return {
+                  styles = {
+                    { selector = "notification", style = { width = "50%" } },
+                    { selector = "progress", style = { width = "50%", margin = { left = "25%" } } },
+                  },
+                }
+                "#,
+            )
+            .eval()
+            .unwrap();
+        let config: Config = lua.from_value(value).unwrap();
+
+        let progress = config.resolved(800.).unwrap().styles.default.progress;
+        assert_eq!(progress.width, Size::Value(200.));
+        assert_eq!(progress.margin.left, Size::Value(100.));
+    }
+
+    #[test]
+    fn test_parse_anchor() {
+        assert_eq!("bottom-right".parse(), Ok(Anchor::BottomRight));
+        assert_eq!("top_center".parse(), Ok(Anchor::TopCenter));
+        assert_eq!("center".parse(), Ok(Anchor::Center));
+        assert!("middle".parse::<Anchor>().is_err());
+    }
+
+    #[test]
+    fn test_timeout_precedence() {
+        let lua = Lua::new();
+        let value = lua
+            .load(
+                r#"
+                return {
+                  general = {
+                    default_timeout = { urgency_low = 3, urgency_normal = 7, urgency_critical = 0 },
+                  },
+                  styles = {
+                    {
+                      selector = "notification:Slack",
+                      style = {},
+                      default_timeout = { urgency_normal = 5, urgency_critical = 0 },
+                    },
+                    { selector = "notification:Slack", state = "hover", style = {}, default_timeout = 20 },
+                    { selector = "notification:Chat", style = {}, default_timeout = { urgency_low = 2 } },
+                    { selector = "notification:Mail", style = {}, default_timeout = 15, ignore_timeout = true },
+                    { selector = "category:email", style = {}, default_timeout = 1 },
+                  },
+                }
+                "#,
+            )
+            .eval()
+            .unwrap();
+        let config: Config = lua.from_value(value).unwrap();
+
+        // App and urgency, then app, then the global one of the urgency
+        assert_eq!(config.timeout("Slack", Urgency::Critical, -1, false), None);
+        assert_eq!(
+            config.timeout("Slack", Urgency::Normal, -1, false),
+            Some(5000)
+        );
+        assert_eq!(config.timeout("Slack", Urgency::Low, -1, false), Some(3000));
+        assert_eq!(config.timeout("Chat", Urgency::Low, -1, false), Some(2000));
+        assert_eq!(
+            config.timeout("Chat", Urgency::Normal, -1, false),
+            Some(7000)
+        );
+        assert_eq!(config.timeout("Chat", Urgency::Critical, -1, false), None);
+        assert_eq!(config.timeout("Other", Urgency::Low, -1, false), Some(3000));
+
+        // The hover entry only counts while the notification is hovered
+        assert_eq!(config.timeout("Slack", Urgency::Low, -1, true), Some(20000));
+        assert_eq!(
+            config.timeout("Slack", Urgency::Normal, -1, true),
+            Some(20000)
+        );
+        assert_eq!(config.timeout("Chat", Urgency::Low, -1, true), Some(2000));
+
+        // Timeouts asked for are kept unless they're ignored
+        assert_eq!(
+            config.timeout("Slack", Urgency::Normal, 3000, false),
+            Some(3000)
+        );
+        assert_eq!(config.timeout("Slack", Urgency::Low, 0, false), None);
+        assert_eq!(config.timeout("Other", Urgency::Low, -5, false), Some(3000));
+        assert_eq!(
+            config.timeout("Other", Urgency::Critical, 1000, false),
+            Some(1000)
+        );
+        assert_eq!(
+            config.timeout("Mail", Urgency::Normal, 3000, false),
+            Some(15000)
+        );
+        assert_eq!(
+            config.timeout("Mail", Urgency::Critical, 0, false),
+            Some(15000)
+        );
+
+        let value = lua
+            .load(
+                r#"
+                return {
+                  general = { ignore_timeout = true, default_timeout = 4 },
+                  styles = {
+                    { selector = "notification:Slack", style = {}, ignore_timeout = false },
+                  },
+                }
+                "#,
+            )
+            .eval()
+            .unwrap();
+        let config: Config = lua.from_value(value).unwrap();
+
+        assert_eq!(
+            config.timeout("Other", Urgency::Normal, 1000, false),
+            Some(4000)
+        );
+        assert_eq!(
+            config.timeout("Slack", Urgency::Normal, 1000, false),
+            Some(1000)
+        );
+        assert_eq!(
+            config.timeout("Slack", Urgency::Normal, -1, false),
+            Some(4000)
+        );
+    }
+
+    #[test]
+    fn test_app_limit() {
+        let lua = Lua::new();
+        let parse = |limits: &str| {
+            let value = lua
+                .load(format!(
+                    "return {{ general = {{ app_limits = {limits} }} }}"
+                ))
+                .eval()
+                .unwrap();
+            lua.from_value::<Config>(value)
+        };
+
+        let config = parse(r#"{ foo = 3, bar = { max = 1, policy = "queue" } }"#).unwrap();
+        assert_eq!(config.general.app_limits["foo"].max, 3);
+        assert_eq!(config.general.app_limits["bar"].max, 1);
+
+        assert!(parse(r#"{ foo = 0 }"#).is_err());
+        assert!(parse(r#"{ foo = { max = 0 } }"#).is_err());
+    }
+
+    #[test]
+    fn test_looped_sound() {
+        let lua = Lua::new();
+        let value = lua
+            .load(
+                r#"
+                return {
+                  general = {
+                    default_sound_file = {
+                      urgency_normal = "/tmp/normal.ogg",
+                      urgency_critical = { path = "/tmp/critical.ogg", loop = true },
+                    },
+                  },
+                }
+                "#,
+            )
+            .eval()
+            .unwrap();
+        let config: Config = lua.from_value(value).unwrap();
+        let sounds = &config.general.default_sound_file;
+
+        let normal = sounds.get(&Urgency::Normal).unwrap();
+        assert_eq!(&*normal.path, Path::new("/tmp/normal.ogg"));
+        assert!(!normal.looped);
+
+        let critical = sounds.get(&Urgency::Critical).unwrap();
+        assert_eq!(&*critical.path, Path::new("/tmp/critical.ogg"));
+        assert!(critical.looped);
+        assert_eq!(critical.max_duration, 60);
+
+        assert!(sounds.get(&Urgency::Low).is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use super::Size;
+use super::{Anchor, CountdownPosition, RenderMode, Size};
 use crate::config::color::{parse_hex, Color};
 use serde::{
     de::{self, MapAccess, Visitor},
@@ -16,9 +16,28 @@ pub struct PartialStyle {
     pub height: Option<Size>,
     pub max_height: Option<Size>,
     pub font: Option<PartialFont>,
+    /// Color of the typed part of hints.
+    pub font_matched_color: Option<PartialColor>,
     pub border: Option<PartialBorder>,
     pub margin: Option<PartialInsets>,
     pub padding: Option<PartialInsets>,
+    pub max_lines: Option<u32>,
+    pub image_max_height: Option<u32>,
+    pub render_mode: Option<RenderMode>,
+    pub position: Option<CountdownPosition>,
+    /// Where the app icon sits over the image.
+    pub anchor: Option<Anchor>,
+    #[serde(default, deserialize_with = "deserialize_opacity")]
+    pub opacity: Option<f32>,
+    /// Colors and underline of links in the body.
+    pub link: Option<PartialLink>,
+}
+
+fn deserialize_opacity<'de, D>(deserializer: D) -> Result<Option<f32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    super::deserialize_opacity(deserializer).map(Some)
 }
 
 #[derive(Debug, Clone, Default)]
@@ -101,6 +120,14 @@ pub struct PartialFont {
     pub color: Option<PartialColor>,
 }
 
+#[derive(Deserialize, Clone)]
+pub struct PartialLink {
+    pub color: Option<PartialColor>,
+    pub hover_color: Option<PartialColor>,
+    pub visited_color: Option<PartialColor>,
+    pub underline: Option<bool>,
+}
+
 #[derive(Default, Clone, Copy)]
 pub struct PartialInsets {
     pub left: Option<Size>,
@@ -131,27 +158,29 @@ impl<'de> Deserialize<'de> for PartialInsets {
             type Value = PartialInsets;
 
             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("a number, 'auto', or a map with inset values")
+                formatter.write_str("a number, 'auto', a percentage, or a map with inset values")
             }
 
             fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
             where
                 E: serde::de::Error,
             {
-                if v == "auto" {
-                    Ok(PartialInsets::size(Size::Auto))
-                } else {
-                    Err(serde::de::Error::invalid_value(
+                Size::parse(v).map(PartialInsets::size).ok_or_else(|| {
+                    serde::de::Error::invalid_value(
                         serde::de::Unexpected::Str(v),
-                        &"auto or number",
-                    ))
-                }
+                        &"auto, number or percentage",
+                    )
+                })
             }
 
             fn visit_f32<E>(self, v: f32) -> Result<Self::Value, E> {
                 Ok(PartialInsets::size(Size::Value(v)))
             }
 
+            fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+                Ok(PartialInsets::size(Size::Value(v as f32)))
+            }
+
             fn visit_i32<E>(self, v: i32) -> Result<Self::Value, E> {
                 Ok(PartialInsets::size(Size::Value(v as f32)))
             }
@@ -0,0 +1,149 @@
+use crate::{dbus::xdg::NotificationData, Urgency};
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+
+/// Rewrites the hints of notifications it matches, before they're stored or shown.
+#[derive(Deserialize, Clone)]
+pub struct Rule {
+    /// App name, `*` standing for any run of characters and `?` for any single one. Rules
+    /// without one match every app.
+    #[serde(default)]
+    pub app: Option<Box<str>>,
+    /// Pattern that has to match somewhere in the summary.
+    #[serde(default, deserialize_with = "deserialize_regex")]
+    pub summary: Option<Regex>,
+    #[serde(default)]
+    pub suppress_sound: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_urgency")]
+    pub urgency: Option<Urgency>,
+}
+
+impl Rule {
+    pub fn matches(&self, data: &NotificationData) -> bool {
+        self.app
+            .as_deref()
+            .is_none_or(|app| glob_match(app, &data.app_name))
+            && self
+                .summary
+                .as_ref()
+                .is_none_or(|summary| summary.is_match(&data.summary))
+    }
+
+    pub fn apply(&self, data: &mut NotificationData) {
+        if let Some(suppress_sound) = self.suppress_sound {
+            data.hints.suppress_sound = suppress_sound;
+        }
+        if let Some(urgency) = self.urgency {
+            data.hints.urgency = urgency;
+        }
+    }
+}
+
+fn deserialize_regex<'de, D>(deserializer: D) -> Result<Option<Regex>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+fn deserialize_urgency<'de, D>(deserializer: D) -> Result<Option<Urgency>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let urgency = String::deserialize(deserializer)?;
+    match urgency.as_str() {
+        "low" => Ok(Some(Urgency::Low)),
+        "normal" => Ok(Some(Urgency::Normal)),
+        "critical" => Ok(Some(Urgency::Critical)),
+        _ => Err(serde::de::Error::unknown_variant(
+            &urgency,
+            &["low", "normal", "critical"],
+        )),
+    }
+}
+
+/// Matches `text` against `pattern` as a whole, with `*` and `?` as wildcards.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and of the text it was tried at, to backtrack to
+    let mut star = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glob_match, Rule};
+    use crate::{dbus::xdg::NotificationData, Urgency};
+    use mlua::{Lua, LuaSerdeExt};
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("Spotify", "Spotify"));
+        assert!(!glob_match("Spotify", "spotify"));
+        assert!(glob_match("org.gnome.*", "org.gnome.Calendar"));
+        assert!(glob_match("*mail*", "Thunderbird mail client"));
+        assert!(glob_match("pager?", "pagerd"));
+        assert!(!glob_match("pager?", "pager"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("a*b", "acbc"));
+    }
+
+    #[test]
+    fn test_rule_rewrites_hints() {
+        let lua = Lua::new();
+        let value = lua
+            .load(
+                r#"
+                return {
+                  app = "Spot*",
+                  summary = "^Now playing",
+                  urgency = "low",
+                  suppress_sound = true,
+                }
+                "#,
+            )
+            .eval()
+            .unwrap();
+        let rule: Rule = lua.from_value(value).unwrap();
+
+        let mut data = NotificationData {
+            app_name: "Spotify".into(),
+            summary: "Now playing: Song".into(),
+            ..Default::default()
+        };
+        assert!(rule.matches(&data));
+        rule.apply(&mut data);
+        assert_eq!(data.hints.urgency, Urgency::Low);
+        assert!(data.hints.suppress_sound);
+
+        data.summary = "Paused".into();
+        assert!(!rule.matches(&data));
+    }
+}
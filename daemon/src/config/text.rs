@@ -1,4 +1,8 @@
-use super::{border::Border, partial::PartialStyle, Color, Insets};
+use super::{
+    border::Border,
+    partial::{PartialLink, PartialStyle},
+    Color, Insets, ResolvePercent, Size,
+};
 use std::sync::Arc;
 
 #[derive(Clone)]
@@ -12,6 +16,14 @@ pub struct Summary {
     pub background: Color,
 }
 
+impl ResolvePercent for Summary {
+    fn resolve_percent(&mut self, parent: f32) -> bool {
+        self.margin.resolve_percent(parent)
+            | self.padding.resolve_percent(parent)
+            | self.border.resolve_percent(parent)
+    }
+}
+
 impl Summary {
     pub fn apply(&mut self, partial: &PartialStyle) {
         if let Some(font) = partial.font.as_ref() {
@@ -66,10 +78,32 @@ pub struct Body {
     pub padding: Insets,
     pub border: Border,
     pub background: Color,
+    /// Lines shown before the body is cut off with an ellipsis, until it's expanded.
+    pub max_lines: Option<u32>,
+    /// Images of `<img>` tags are shrunk to this height, keeping their aspect ratio.
+    pub image_max_height: u32,
+    pub link: Link,
+}
+
+impl ResolvePercent for Body {
+    fn resolve_percent(&mut self, parent: f32) -> bool {
+        self.margin.resolve_percent(parent)
+            | self.padding.resolve_percent(parent)
+            | self.border.resolve_percent(parent)
+    }
 }
 
 impl Body {
     pub fn apply(&mut self, partial: &PartialStyle) {
+        if let Some(max_lines) = partial.max_lines {
+            self.max_lines = Some(max_lines);
+        }
+        if let Some(image_max_height) = partial.image_max_height {
+            self.image_max_height = image_max_height;
+        }
+        if let Some(link) = partial.link.as_ref() {
+            self.link.apply(link);
+        }
         if let Some(font) = partial.font.as_ref() {
             if let Some(size) = font.size {
                 self.size = size;
@@ -109,6 +143,180 @@ impl Default for Body {
                 ..Default::default()
             },
             background: Color::rgba([0, 0, 0, 0]),
+            max_lines: None,
+            image_max_height: 128,
+            link: Link::default(),
+        }
+    }
+}
+
+/// Links in the body, `<a>` tags of the markup.
+#[derive(Clone)]
+pub struct Link {
+    pub color: Color,
+    /// Color of the link under the pointer.
+    pub hover_color: Color,
+    /// Color of links that were opened before.
+    pub visited_color: Color,
+    pub underline: bool,
+}
+
+impl Link {
+    pub fn apply(&mut self, partial: &PartialLink) {
+        if let Some(color) = partial.color.as_ref() {
+            self.color.apply(color);
+        }
+        if let Some(hover_color) = partial.hover_color.as_ref() {
+            self.hover_color.apply(hover_color);
+        }
+        if let Some(visited_color) = partial.visited_color.as_ref() {
+            self.visited_color.apply(visited_color);
+        }
+        if let Some(underline) = partial.underline {
+            self.underline = underline;
+        }
+    }
+}
+
+impl Default for Link {
+    fn default() -> Self {
+        Self {
+            color: Color::rgba([122, 162, 247, 255]),
+            hover_color: Color::rgba([180, 190, 254, 255]),
+            visited_color: Color::rgba([187, 154, 247, 255]),
+            underline: true,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Timestamp {
+    pub size: f32,
+    pub family: Arc<str>,
+    pub color: Color,
+    pub margin: Insets,
+    pub padding: Insets,
+    pub border: Border,
+    pub background: Color,
+}
+
+impl ResolvePercent for Timestamp {
+    fn resolve_percent(&mut self, parent: f32) -> bool {
+        self.margin.resolve_percent(parent)
+            | self.padding.resolve_percent(parent)
+            | self.border.resolve_percent(parent)
+    }
+}
+
+impl Timestamp {
+    pub fn apply(&mut self, partial: &PartialStyle) {
+        if let Some(font) = partial.font.as_ref() {
+            if let Some(size) = font.size {
+                self.size = size;
+            }
+            if let Some(family) = font.family.as_ref().map(Arc::clone) {
+                self.family = family;
+            }
+            if let Some(color) = font.color.as_ref() {
+                self.color.apply(color);
+            }
+        }
+        if let Some(margin) = partial.margin.as_ref() {
+            self.margin.apply(margin);
+        }
+        if let Some(padding) = partial.padding.as_ref() {
+            self.padding.apply(padding);
+        }
+        if let Some(border) = partial.border.as_ref() {
+            self.border.apply(border);
+        }
+        if let Some(background) = partial.background.as_ref() {
+            self.background.apply(background);
+        }
+    }
+}
+
+impl Default for Timestamp {
+    fn default() -> Self {
+        Self {
+            size: 8.,
+            family: "DejaVu Sans".into(),
+            color: Color::rgba([169, 177, 214, 255]),
+            margin: Insets::default(),
+            padding: Insets {
+                right: Size::Value(5.),
+                ..Default::default()
+            },
+            border: Border {
+                size: Insets::default(),
+                ..Default::default()
+            },
+            background: Color::rgba([0, 0, 0, 0]),
+        }
+    }
+}
+
+/// Name of the app above the summary, shown with `general.show_app_name`.
+#[derive(Clone)]
+pub struct AppName {
+    pub size: f32,
+    pub family: Arc<str>,
+    pub color: Color,
+    pub margin: Insets,
+    pub padding: Insets,
+    pub border: Border,
+    pub background: Color,
+}
+
+impl ResolvePercent for AppName {
+    fn resolve_percent(&mut self, parent: f32) -> bool {
+        self.margin.resolve_percent(parent)
+            | self.padding.resolve_percent(parent)
+            | self.border.resolve_percent(parent)
+    }
+}
+
+impl AppName {
+    pub fn apply(&mut self, partial: &PartialStyle) {
+        if let Some(font) = partial.font.as_ref() {
+            if let Some(size) = font.size {
+                self.size = size;
+            }
+            if let Some(family) = font.family.as_ref().map(Arc::clone) {
+                self.family = family;
+            }
+            if let Some(color) = font.color.as_ref() {
+                self.color.apply(color);
+            }
+        }
+        if let Some(margin) = partial.margin.as_ref() {
+            self.margin.apply(margin);
+        }
+        if let Some(padding) = partial.padding.as_ref() {
+            self.padding.apply(padding);
+        }
+        if let Some(border) = partial.border.as_ref() {
+            self.border.apply(border);
+        }
+        if let Some(background) = partial.background.as_ref() {
+            self.background.apply(background);
+        }
+    }
+}
+
+impl Default for AppName {
+    fn default() -> Self {
+        Self {
+            size: 8.,
+            family: "DejaVu Sans".into(),
+            color: Color::rgba([169, 177, 214, 255]),
+            margin: Insets::default(),
+            padding: Insets::default(),
+            border: Border {
+                size: Insets::default(),
+                ..Default::default()
+            },
+            background: Color::rgba([0, 0, 0, 0]),
         }
     }
 }
@@ -0,0 +1,239 @@
+//! Themes are Lua files in the `themes` directory next to the config. They return a table of
+//! named `colors`, which styles reference as `"@name"`, and `styles` applied under the ones of
+//! the config.
+
+use mlua::{Lua, Table, Value};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+struct Themes<'a> {
+    lua: &'a Lua,
+    dir: PathBuf,
+    loaded: HashMap<Box<str>, Table>,
+}
+
+impl Themes<'_> {
+    /// Table the theme `name` returns, None when there's no such theme.
+    fn load(&mut self, name: &str) -> anyhow::Result<Option<Table>> {
+        if let Some(theme) = self.loaded.get(name) {
+            return Ok(Some(theme.clone()));
+        }
+
+        let path = self.dir.join(format!("{name}.lua"));
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let theme: Table = self
+            .lua
+            .load(fs::read_to_string(&path)?)
+            .eval()
+            .map_err(|e| anyhow::anyhow!("Failed to load theme {name}: {e}"))?;
+        self.loaded.insert(name.into(), theme.clone());
+
+        Ok(Some(theme))
+    }
+
+    /// Like [`Self::load`], but a missing theme is an error.
+    fn get(&mut self, name: &str) -> anyhow::Result<Table> {
+        self.load(name)?
+            .ok_or_else(|| anyhow::anyhow!("Theme {name} not found in {}", self.dir.display()))
+    }
+}
+
+/// Puts the styles of the theme named by `general.style_theme` under the ones of `config`, then
+/// resolves the color references of every style against its palette. Styles with a `theme` of
+/// their own use that one's palette instead.
+pub fn apply(lua: &Lua, config: &Table, dir: &Path) -> anyhow::Result<()> {
+    let mut themes = Themes {
+        lua,
+        dir: dir.into(),
+        loaded: HashMap::new(),
+    };
+
+    let name = config
+        .get::<Option<Table>>("general")?
+        .map(|general| general.get::<Option<String>>("style_theme"))
+        .transpose()?
+        .flatten();
+    let theme = name.map(|name| themes.get(&name)).transpose()?;
+
+    let styles = lua.create_table()?;
+    let theme_styles = match &theme {
+        Some(theme) => theme.get::<Option<Table>>("styles")?,
+        None => None,
+    };
+    for entries in theme_styles
+        .into_iter()
+        .chain(config.get::<Option<Table>>("styles")?)
+    {
+        for entry in entries.sequence_values::<Table>() {
+            styles.push(entry?)?;
+        }
+    }
+
+    for entry in styles.sequence_values::<Table>() {
+        let entry = entry?;
+        let palette = match entry.get::<Option<String>>("theme")? {
+            Some(name) => Some(themes.get(&name)?),
+            None => theme.clone(),
+        };
+
+        if let Some(style) = entry.get::<Option<Table>>("style")? {
+            resolve(&style, palette.as_ref())?;
+        }
+    }
+
+    config.set("styles", styles)?;
+
+    Ok(())
+}
+
+/// Replaces `"@name"` strings in `table`, nested tables included, with the color of that name
+/// from the `colors` of `theme`. Strings that don't name one of its colors are kept as they are.
+fn resolve(table: &Table, theme: Option<&Table>) -> anyhow::Result<()> {
+    let Some(colors) = theme
+        .map(|theme| theme.get::<Option<Table>>("colors"))
+        .transpose()?
+        .flatten()
+    else {
+        return Ok(());
+    };
+
+    resolve_colors(table, &colors)
+}
+
+fn resolve_colors(table: &Table, colors: &Table) -> anyhow::Result<()> {
+    let pairs = table
+        .pairs::<Value, Value>()
+        .collect::<mlua::Result<Vec<_>>>()?;
+
+    for (key, value) in pairs {
+        match value {
+            Value::Table(nested) => resolve_colors(&nested, colors)?,
+            Value::String(text) => {
+                let Some(name) = text.to_str()?.strip_prefix('@').map(str::to_owned) else {
+                    continue;
+                };
+
+                let color = colors.get::<Value>(name)?;
+                if !color.is_nil() {
+                    table.set(key, color)?;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        config::{Config, StyleKey},
+        Urgency,
+    };
+    use std::{fs, path::PathBuf};
+
+    fn write_config(dir: &str, config: &str, themes: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(dir);
+        fs::create_dir_all(dir.join("themes")).unwrap();
+        for (name, theme) in themes {
+            fs::write(dir.join("themes").join(format!("{name}.lua")), theme).unwrap();
+        }
+
+        let path = dir.join("config.lua");
+        fs::write(&path, config).unwrap();
+        path
+    }
+
+    const ALERT: &str = r##"
+        return {
+          colors = { calm = "#112233", alarm = "#ff0000" },
+          styles = {
+            {
+              selector = "*",
+              style = {
+                background = { urgency_low = "@calm", urgency_normal = "@calm", urgency_critical = "@alarm" },
+              },
+            },
+            { selector = "urgency:critical", style = { font = { color = "@calm" } } },
+          },
+        }
+    "##;
+
+    #[test]
+    fn test_theme_urgency_colors() {
+        let path = write_config(
+            "moxnotify-theme-urgency",
+            r##"
+            return {
+              general = { style_theme = "alert" },
+              styles = {
+                { selector = "urgency:critical", style = { font = { color = "@alarm" } } },
+                { selector = "notification:Slack", theme = "green", style = { background = "@alarm" } },
+              },
+            }
+            "##,
+            &[
+                ("alert", ALERT),
+                ("green", r##"return { colors = { alarm = "#00ff00" } }"##),
+            ],
+        );
+        let config = Config::load(Some(&path)).unwrap();
+
+        let background = config.styles.default.background;
+        assert_eq!(background.urgency_low, [0x11, 0x22, 0x33, 255]);
+        assert_eq!(background.urgency_critical, [255, 0, 0, 255]);
+
+        let key = |app_name: &str, urgency| StyleKey {
+            app_name: app_name.into(),
+            category: None,
+            urgency,
+            history: false,
+            compact: false,
+            osd: false,
+        };
+
+        // The config is applied over the theme
+        let critical = config.find_style(&key("Mail", Urgency::Critical), false);
+        assert_eq!(critical.font.color.urgency_critical, [255, 0, 0, 255]);
+
+        let slack = config.find_style(&key("Slack", Urgency::Normal), false);
+        assert_eq!(slack.background.urgency_normal, [0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn test_unknown_token() {
+        let path = write_config(
+            "moxnotify-theme-unknown",
+            r#"
+            return {
+              general = { style_theme = "alert" },
+              styles = { { selector = "*", style = { font = { family = "@missing" } } } },
+            }
+            "#,
+            &[("alert", ALERT)],
+        );
+        let config = Config::load(Some(&path)).unwrap();
+
+        // Only the colors of the theme are substituted
+        assert_eq!(&*config.styles.default.font.family, "@missing");
+    }
+
+    #[test]
+    fn test_missing_theme() {
+        let path = write_config(
+            "moxnotify-theme-missing",
+            r#"return { general = { theme = "Papirus", style_theme = "missing" } }"#,
+            &[],
+        );
+
+        let e = Config::load(Some(&path)).err().unwrap();
+        assert!(e.to_string().starts_with("Theme missing not found in"));
+    }
+}
@@ -0,0 +1,81 @@
+use anyhow::Context;
+use std::{
+    fs::File,
+    io::{self, PipeWriter, Read, Write},
+    os::fd::AsRawFd,
+    path::PathBuf,
+};
+
+/// Held by the forked daemon, the parent waits for it before exiting.
+pub struct Ready(PipeWriter);
+
+impl Ready {
+    /// Lets the parent exit successfully and detaches from the terminal it was started from,
+    /// nothing is printed there anymore.
+    pub fn notify(mut self) {
+        _ = self.0.write_all(&[0]);
+
+        match File::options().read(true).write(true).open("/dev/null") {
+            Ok(null) => [libc::STDIN_FILENO, libc::STDOUT_FILENO, libc::STDERR_FILENO]
+                .into_iter()
+                // SAFETY: Both descriptors are open
+                .for_each(|fd| unsafe {
+                    libc::dup2(null.as_raw_fd(), fd);
+                }),
+            Err(e) => log::warn!("Failed to open /dev/null: {e}"),
+        }
+    }
+}
+
+/// Forks into a new session. The parent sticks around until the child calls [`Ready::notify`]
+/// so that startup errors still reach the terminal, and fails if the child exits before.
+/// Has to be called before any thread is spawned.
+pub fn fork() -> anyhow::Result<Ready> {
+    let (mut reader, writer) = io::pipe()?;
+
+    // SAFETY: The process is single threaded still
+    match unsafe { libc::fork() } {
+        -1 => Err(io::Error::last_os_error()).context("Failed to fork"),
+        0 => {
+            drop(reader);
+            // SAFETY: The child isn't a process group leader, setsid can't fail because of that
+            if unsafe { libc::setsid() } == -1 {
+                return Err(io::Error::last_os_error()).context("Failed to start a session");
+            }
+
+            Ok(Ready(writer))
+        }
+        _ => {
+            drop(writer);
+            let mut ready = [0];
+            let code = match reader.read(&mut ready) {
+                Ok(1) => 0,
+                _ => 1,
+            };
+
+            std::process::exit(code);
+        }
+    }
+}
+
+/// File under `XDG_RUNTIME_DIR` holding the pid of the daemon, removed once it exits.
+pub struct Pidfile(PathBuf);
+
+impl Pidfile {
+    pub fn create() -> anyhow::Result<Self> {
+        let dir = std::env::var_os("XDG_RUNTIME_DIR").context("XDG_RUNTIME_DIR isn't set")?;
+        let path = PathBuf::from(dir).join("moxnotify.pid");
+        std::fs::write(&path, format!("{}\n", std::process::id()))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        Ok(Self(path))
+    }
+}
+
+impl Drop for Pidfile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.0) {
+            log::warn!("Failed to remove {}: {e}", self.0.display());
+        }
+    }
+}
@@ -1,3 +1,189 @@
 pub mod moxnotify;
+pub mod mpris;
 pub mod portal;
+pub mod portal_notification;
+pub mod screencast;
+pub mod session_lock;
 pub mod xdg;
+
+use crate::{Event, Urgency};
+use futures_lite::StreamExt;
+use std::{fmt, future::Future, time::Duration};
+use xdg::{IdGenerator, NotificationData, NotificationHints};
+use zbus::fdo::RequestNameFlags;
+
+/// Wait before serving again after a failed attempt, doubled after every further one.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+pub enum Error {
+    /// The well-known name is owned by another connection.
+    NameTaken(&'static str),
+    Dbus(zbus::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NameTaken(name) => {
+                write!(f, "{name} is already owned, is another daemon running?")
+            }
+            Error::Dbus(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<zbus::Error> for Error {
+    fn from(e: zbus::Error) -> Self {
+        Error::Dbus(e)
+    }
+}
+
+/// Requests `name` for `conn` without queueing up for it.
+pub async fn request_name(conn: &zbus::Connection, name: &'static str) -> Result<(), Error> {
+    match conn
+        .request_name_with_flags(name, RequestNameFlags::DoNotQueue.into())
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(zbus::Error::NameTaken) => Err(Error::NameTaken(name)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Resolves once `conn` is closed, or loses `name` if it owns it.
+pub async fn closed(conn: &zbus::Connection, name: &str) -> zbus::Result<()> {
+    let rule = zbus::MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .sender("org.freedesktop.DBus")?
+        .interface("org.freedesktop.DBus")?
+        .member("NameLost")?
+        .add_arg(name)?
+        .build();
+    let mut lost = zbus::MessageStream::for_match_rule(rule, conn, Some(1)).await?;
+
+    if let Some(Ok(_)) = lost.next().await {
+        log::warn!("Lost the name {name}");
+    }
+
+    Ok(())
+}
+
+/// Runs `task` until `conn` is closed, so tasks of a lost connection don't linger.
+pub fn spawn_until_closed<F>(conn: &zbus::Connection, task: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let conn = conn.clone();
+    tokio::spawn(async move {
+        // A unique name is only lost along with the connection
+        let name = conn
+            .unique_name()
+            .map(ToString::to_string)
+            .unwrap_or_default();
+        tokio::select! {
+            _ = closed(&conn, &name) => {}
+            _ = task => {}
+        }
+    });
+}
+
+/// Serves `name` again whenever the connection `serve` made is closed, retrying with
+/// exponential backoff until it's back. With `notice` the user is told by a critical
+/// notification while it's down. Only `Error::NameTaken` before the first connection gives up,
+/// as another daemon was running all along then.
+pub async fn supervise<F, Fut>(
+    name: &'static str,
+    notice: Option<(calloop::channel::Sender<Event>, IdGenerator)>,
+    mut serve: F,
+) -> Result<(), Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<zbus::Connection, Error>>,
+{
+    let mut delay = RETRY_DELAY;
+    let mut connected = false;
+    let mut shown = None;
+
+    loop {
+        let conn = match serve().await {
+            Ok(conn) => conn,
+            Err(e @ Error::NameTaken(_)) if !connected => return Err(e),
+            Err(e) => {
+                log::warn!("Failed to serve {name}: {e}, retrying in {delay:?}");
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_RETRY_DELAY);
+                continue;
+            }
+        };
+
+        if connected {
+            log::info!("Serving {name} again");
+        }
+        connected = true;
+        delay = RETRY_DELAY;
+        if let (Some((event_sender, _)), Some(id)) = (notice.as_ref(), shown.take()) {
+            _ = event_sender.send(Event::CloseNotification { id, reply: None });
+        }
+
+        if let Err(e) = closed(&conn, name).await {
+            log::warn!("Failed to watch the connection serving {name}: {e}");
+            continue;
+        }
+
+        // Closing a connection that only lost the name ends the tasks spawned for it as well
+        _ = conn.close().await;
+        log::error!("Lost the D-Bus connection serving {name}, retrying");
+        if let (Some((event_sender, ids)), None) = (notice.as_ref(), shown) {
+            let data = lost_connection(ids.next());
+            shown = Some(data.id);
+            _ = event_sender.send(Event::Notify(Box::new(data)));
+        }
+    }
+}
+
+/// Tells the user notifications can't come in until the connection is back.
+fn lost_connection(id: u32) -> NotificationData {
+    NotificationData {
+        id,
+        app_name: "moxnotify".into(),
+        summary: "Lost D-Bus connection, retrying".into(),
+        body: "New notifications can't arrive until it's back".into(),
+        timeout: 0,
+        hints: NotificationHints {
+            urgency: Urgency::Critical,
+            transient: true,
+            ..Default::default()
+        },
+        timestamp: xdg::timestamp(),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{supervise, Error};
+    use std::cell::Cell;
+
+    #[test]
+    fn test_name_taken_at_startup() {
+        let attempts = Cell::new(0);
+        let result = futures_lite::future::block_on(supervise(
+            "org.freedesktop.Notifications",
+            None,
+            || {
+                attempts.set(attempts.get() + 1);
+                async { Err(Error::NameTaken("org.freedesktop.Notifications")) }
+            },
+        ));
+
+        assert_eq!(attempts.get(), 1);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "org.freedesktop.Notifications is already owned, is another daemon running?"
+        );
+    }
+}
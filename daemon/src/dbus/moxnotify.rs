@@ -1,10 +1,108 @@
-use crate::{EmitEvent, Event, History};
-use tokio::sync::broadcast;
-use zbus::{fdo::RequestNameFlags, object_server::SignalEmitter};
+use crate::{
+    components::notification::Notification, config::Anchor, dbus::xdg::NotificationData, EmitEvent,
+    Event, History, InhibitState, Urgency,
+};
+use serde::Serialize;
+use std::{path::PathBuf, time::UNIX_EPOCH};
+use tokio::sync::{broadcast, oneshot};
+use zbus::{object_server::SignalEmitter, zvariant::Type};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Type)]
+pub struct ListedAction {
+    pub key: String,
+    pub label: String,
+}
+
+/// Notification as returned by `List`. Its fields are part of the D-Bus interface and stay put
+/// when `NotificationData` changes.
+#[derive(Clone, Debug, PartialEq, Serialize, Type)]
+pub struct ListedNotification {
+    pub id: u32,
+    pub app_name: String,
+    pub summary: String,
+    pub body: String,
+    /// One of `low`, `normal` or `critical`.
+    pub urgency: String,
+    /// Seconds since the Unix epoch at which the notification arrived.
+    pub timestamp: u64,
+    pub actions: Vec<ListedAction>,
+    pub has_icon: bool,
+}
+
+impl From<&Notification> for ListedNotification {
+    fn from(notification: &Notification) -> Self {
+        Self {
+            timestamp: notification
+                .received_at
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            ..Self::from(&notification.data)
+        }
+    }
+}
+
+impl From<&NotificationData> for ListedNotification {
+    fn from(data: &NotificationData) -> Self {
+        let urgency = match data.hints.urgency {
+            Urgency::Low => "low",
+            Urgency::Normal => "normal",
+            Urgency::Critical => "critical",
+        };
+
+        Self {
+            id: data.id,
+            app_name: data.app_name.to_string(),
+            summary: data.summary.to_string(),
+            body: data.body.to_string(),
+            urgency: urgency.into(),
+            timestamp: data.timestamp,
+            actions: data
+                .actions
+                .iter()
+                .map(|(key, label)| ListedAction {
+                    key: key.to_string(),
+                    label: label.to_string(),
+                })
+                .collect(),
+            has_icon: data.hints.image.is_some() || data.app_icon.is_some(),
+        }
+    }
+}
+
+/// Everything status bars show, gathered at once so the fields agree with each other.
+#[derive(Clone, Debug, PartialEq, Serialize, Type)]
+pub struct Status {
+    pub muted: bool,
+    pub inhibited: InhibitState,
+    pub history: History,
+    pub waiting: u32,
+    /// Same as the `NotificationCount` property.
+    pub active_count: u32,
+    /// Graphics adapter notifications are drawn with, and its backend.
+    pub renderer: String,
+}
 
 struct MoxnotifyInterface {
     event_sender: calloop::channel::Sender<Event>,
     emit_receiver: broadcast::Receiver<EmitEvent>,
+    count: u32,
+}
+
+impl MoxnotifyInterface {
+    async fn list_notifications(&mut self, redact: bool) -> Vec<ListedNotification> {
+        if let Err(e) = self.event_sender.send(Event::List { redact }) {
+            log::error!("{e}");
+        }
+
+        while let Ok(event) = self.emit_receiver.recv().await {
+            if let EmitEvent::List(list) = event {
+                return list;
+            }
+        }
+
+        Vec::new()
+    }
 }
 
 #[zbus::interface(name = "pl.mox.Notify")]
@@ -15,12 +113,167 @@ impl MoxnotifyInterface {
         }
     }
 
+    /// Closes all notifications and stops the daemon.
+    async fn quit(&self) {
+        if let Err(e) = self.event_sender.send(Event::Quit) {
+            log::error!("{e}");
+        }
+    }
+
+    /// Writes what's shown on screen to `path` as a PNG.
+    async fn screenshot(&mut self, path: String) -> zbus::fdo::Result<()> {
+        let path = PathBuf::from(path);
+        if path.is_relative() {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "{} isn't an absolute path",
+                path.display()
+            )));
+        }
+
+        self.emit_receiver = self.emit_receiver.resubscribe();
+        if let Err(e) = self.event_sender.send(Event::Screenshot(path)) {
+            log::error!("{e}");
+            return Err(zbus::fdo::Error::Failed(e.to_string()));
+        }
+
+        while let Ok(event) = self.emit_receiver.recv().await {
+            if let EmitEvent::Screenshot(result) = event {
+                return result.map_err(|e| zbus::fdo::Error::Failed(e.to_string()));
+            }
+        }
+
+        Err(zbus::fdo::Error::Failed("No reply from the daemon".into()))
+    }
+
+    /// Moves notifications to the output called `name`, or onto every output with `all`.
+    async fn set_output(&mut self, name: String) -> zbus::fdo::Result<()> {
+        self.emit_receiver = self.emit_receiver.resubscribe();
+        if let Err(e) = self.event_sender.send(Event::SetOutput(name.into())) {
+            log::error!("{e}");
+            return Err(zbus::fdo::Error::Failed(e.to_string()));
+        }
+
+        while let Ok(event) = self.emit_receiver.recv().await {
+            if let EmitEvent::OutputSet(result) = event {
+                return result.map_err(|e| zbus::fdo::Error::InvalidArgs(e.to_string()));
+            }
+        }
+
+        Err(zbus::fdo::Error::Failed("No reply from the daemon".into()))
+    }
+
+    /// Anchors notifications to `anchor`, named as in the config such as `bottom_right`.
+    async fn set_anchor(&self, anchor: &str) -> zbus::fdo::Result<()> {
+        let anchor = anchor
+            .parse::<Anchor>()
+            .map_err(zbus::fdo::Error::InvalidArgs)?;
+        if let Err(e) = self.event_sender.send(Event::SetAnchor(anchor)) {
+            log::error!("{e}");
+            return Err(zbus::fdo::Error::Failed(e.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Shows only the summary of notifications, or everything again.
+    async fn set_compact(&self, compact: bool) {
+        if let Err(e) = self.event_sender.send(Event::SetCompact(compact)) {
+            log::error!("{e}");
+        }
+    }
+
+    async fn compact(&mut self) -> zbus::fdo::Result<bool> {
+        self.emit_receiver = self.emit_receiver.resubscribe();
+        if let Err(e) = self.event_sender.send(Event::GetCompact) {
+            log::error!("{e}");
+            return Err(zbus::fdo::Error::Failed(e.to_string()));
+        }
+
+        while let Ok(event) = self.emit_receiver.recv().await {
+            if let EmitEvent::Compact(compact) = event {
+                return Ok(compact);
+            }
+        }
+
+        Err(zbus::fdo::Error::Failed("No reply from the daemon".into()))
+    }
+
     async fn dismiss(&self, all: bool, id: u32) {
-        if let Err(e) = self.event_sender.send(Event::Dismiss { all, id }) {
+        if let Err(e) = self
+            .event_sender
+            .send(Event::Dismiss { all, id, app: None })
+        {
             log::error!("{e}");
         }
     }
 
+    async fn snooze(&self, id: u32, minutes: u32) {
+        if let Err(e) = self.event_sender.send(Event::Snooze { id, minutes }) {
+            log::error!("{e}");
+        }
+    }
+
+    async fn pin(&self, id: u32) {
+        if let Err(e) = self.event_sender.send(Event::TogglePin(id)) {
+            log::error!("{e}");
+        }
+    }
+
+    /// Opens the first link in the body of notification `id`, returns false if it has none.
+    async fn open_link(&self, id: u32) -> zbus::fdo::Result<bool> {
+        let (reply, opened) = oneshot::channel();
+        if let Err(e) = self.event_sender.send(Event::OpenLink {
+            id: Some(id),
+            reply: Some(reply),
+        }) {
+            log::error!("{e}");
+            return Err(zbus::fdo::Error::Failed(e.to_string()));
+        }
+
+        opened
+            .await
+            .map_err(|_| zbus::fdo::Error::Failed("No reply from the daemon".into()))
+    }
+
+    async fn dismiss_app(&mut self, app: String) -> u32 {
+        if let Err(e) = self.event_sender.send(Event::Dismiss {
+            all: false,
+            id: 0,
+            app: Some(app.into()),
+        }) {
+            log::error!("{e}");
+            return 0;
+        }
+
+        while let Ok(event) = self.emit_receiver.recv().await {
+            if let EmitEvent::Dismissed(count) = event {
+                return count;
+            }
+        }
+
+        0
+    }
+
+    async fn invoke_action(&mut self, id: u32, key: String) -> zbus::fdo::Result<()> {
+        // Skip results left over from actions invoked by clicking
+        self.emit_receiver = self.emit_receiver.resubscribe();
+        if let Err(e) = self.event_sender.send(Event::InvokeAction {
+            id,
+            key: key.into(),
+        }) {
+            log::error!("{e}");
+            return Err(zbus::fdo::Error::Failed(e.to_string()));
+        }
+
+        while let Ok(event) = self.emit_receiver.recv().await {
+            if let EmitEvent::ActionResult(result) = event {
+                return result.map_err(|e| zbus::fdo::Error::InvalidArgs(e.to_string()));
+            }
+        }
+
+        Err(zbus::fdo::Error::Failed("No reply from the daemon".into()))
+    }
+
     async fn waiting(&mut self) -> u32 {
         if let Err(e) = self.event_sender.send(Event::Waiting) {
             log::error!("{e}");
@@ -35,18 +288,35 @@ impl MoxnotifyInterface {
         0
     }
 
-    async fn list(&mut self) -> Vec<String> {
-        if let Err(e) = self.event_sender.send(Event::List) {
+    /// Number of notifications currently displayed or collapsed into a group, excluding waiting
+    /// and history ones.
+    #[zbus(property)]
+    async fn notification_count(&self) -> u32 {
+        self.count
+    }
+
+    async fn list(&mut self) -> Vec<ListedNotification> {
+        self.list_notifications(false).await
+    }
+
+    /// Same as `List`, but without the summary and body of apps the history keeps them from.
+    async fn list_redacted(&mut self) -> Vec<ListedNotification> {
+        self.list_notifications(true).await
+    }
+
+    async fn status(&mut self) -> zbus::fdo::Result<Status> {
+        if let Err(e) = self.event_sender.send(Event::GetStatus) {
             log::error!("{e}");
+            return Err(zbus::fdo::Error::Failed(e.to_string()));
         }
 
         while let Ok(event) = self.emit_receiver.recv().await {
-            if let EmitEvent::List(list) = event {
-                return list;
+            if let EmitEvent::Status(status) = event {
+                return Ok(status);
             }
         }
 
-        Vec::new()
+        Err(zbus::fdo::Error::Failed("No reply from the daemon".into()))
     }
 
     async fn mute(&self) {
@@ -85,6 +355,13 @@ impl MoxnotifyInterface {
         }
     }
 
+    /// Shows the history limited to entries whose summary or body contain `query`.
+    async fn search_history(&self, query: &str) {
+        if let Err(e) = self.event_sender.send(Event::SearchHistory(query.into())) {
+            log::error!("{e}");
+        }
+    }
+
     async fn hide_history(&self) {
         if let Err(e) = self.event_sender.send(Event::HideHistory) {
             log::error!("{e}");
@@ -128,25 +405,45 @@ impl MoxnotifyInterface {
         }
 
         match self.emit_receiver.recv().await {
-            Ok(EmitEvent::Inhibited(inhibited)) => inhibited,
+            Ok(EmitEvent::Inhibited(state)) => state.inhibited(),
             _ => false,
         }
     }
 
+    async fn inhibit_state(&mut self) -> InhibitState {
+        if let Err(e) = self.event_sender.send(Event::GetInhibited) {
+            log::error!("{e}");
+            return InhibitState::default();
+        }
+
+        match self.emit_receiver.recv().await {
+            Ok(EmitEvent::Inhibited(state)) => state,
+            _ => InhibitState::default(),
+        }
+    }
+
     #[zbus(signal)]
     async fn inhibit_changed(
         signal_emitter: &SignalEmitter<'_>,
         inhibited: bool,
     ) -> zbus::Result<()>;
+
+    /// Sent for every notification the daemon receives, shown right away or not.
+    #[zbus(signal)]
+    async fn notification_received(
+        signal_emitter: &SignalEmitter<'_>,
+        notification: ListedNotification,
+    ) -> zbus::Result<()>;
 }
 
 pub async fn serve(
     event_sender: calloop::channel::Sender<Event>,
     mut emit_receiver: broadcast::Receiver<EmitEvent>,
-) -> zbus::Result<()> {
+) -> Result<zbus::Connection, super::Error> {
     let server = MoxnotifyInterface {
         event_sender,
         emit_receiver: emit_receiver.resubscribe(),
+        count: 0,
     };
 
     let conn = zbus::connection::Builder::session()?
@@ -154,15 +451,14 @@ pub async fn serve(
         .build()
         .await?;
 
-    conn.request_name_with_flags("pl.mox.Notify", RequestNameFlags::DoNotQueue.into())
-        .await?;
+    super::request_name(&conn, "pl.mox.Notify").await?;
 
     let iface = conn
         .object_server()
         .interface::<_, MoxnotifyInterface>("/pl/mox/Notify")
         .await?;
 
-    tokio::spawn(async move {
+    super::spawn_until_closed(&conn, async move {
         loop {
             match emit_receiver.recv().await {
                 Ok(EmitEvent::MuteStateChanged(muted)) => {
@@ -183,21 +479,45 @@ pub async fn serve(
                         log::error!("{e}");
                     }
                 }
-                Ok(EmitEvent::InhibitStateChanged(inhibited)) => {
+                Ok(EmitEvent::InhibitStateChanged(state)) => {
                     if let Err(e) = MoxnotifyInterfaceSignals::inhibit_changed(
                         iface.signal_emitter(),
-                        inhibited,
+                        state.inhibited(),
                     )
                     .await
                     {
                         log::error!("{e}");
                     }
                 }
+                Ok(EmitEvent::NotificationReceived(notification)) => {
+                    if let Err(e) = MoxnotifyInterfaceSignals::notification_received(
+                        iface.signal_emitter(),
+                        notification,
+                    )
+                    .await
+                    {
+                        log::error!("{e}");
+                    }
+                }
+                Ok(EmitEvent::CountChanged(count)) => {
+                    let mut server = iface.get_mut().await;
+                    if server.count == count {
+                        continue;
+                    }
+
+                    server.count = count;
+                    if let Err(e) = server
+                        .notification_count_changed(iface.signal_emitter())
+                        .await
+                    {
+                        log::error!("{e}");
+                    }
+                }
                 Err(e) => log::error!("{e}"),
                 _ => {}
             };
         }
     });
 
-    Ok(())
+    Ok(conn)
 }
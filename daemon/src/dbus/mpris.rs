@@ -0,0 +1,249 @@
+use crate::{components::notification::NotificationId, EmitEvent, Event};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    time::MissedTickBehavior,
+};
+use zbus::{
+    fdo::DBusProxy,
+    proxy::CacheProperties,
+    zvariant::{OwnedValue, Value},
+};
+
+const PLAYER_PREFIX: &str = "org.mpris.MediaPlayer2.";
+/// How often the playback position of players is read while their notifications are shown.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[zbus::proxy(
+    interface = "org.mpris.MediaPlayer2",
+    default_path = "/org/mpris/MediaPlayer2"
+)]
+trait MediaPlayer2 {
+    #[zbus(property)]
+    fn desktop_entry(&self) -> zbus::Result<String>;
+}
+
+#[zbus::proxy(
+    interface = "org.mpris.MediaPlayer2.Player",
+    default_path = "/org/mpris/MediaPlayer2"
+)]
+trait Player {
+    #[zbus(property)]
+    fn position(&self) -> zbus::Result<i64>;
+
+    #[zbus(property)]
+    fn metadata(&self) -> zbus::Result<HashMap<String, OwnedValue>>;
+}
+
+/// Notification sent by an app that may be a media player.
+struct Tracked {
+    desktop_entry: Arc<str>,
+    /// Player the notification is kept in sync with, None if no player belongs to the app.
+    player: Option<PlayerProxy<'static>>,
+    value: Option<i32>,
+}
+
+/// Whether the player owning `bus_name`, which calls itself `player_entry`, is the app with
+/// `desktop_entry`. Players without a desktop entry are recognized by their bus name.
+fn is_player_of(bus_name: &str, player_entry: Option<&str>, desktop_entry: &str) -> bool {
+    let desktop_entry = desktop_entry.trim_end_matches(".desktop");
+    if let Some(player_entry) = player_entry.filter(|entry| !entry.is_empty()) {
+        return player_entry
+            .trim_end_matches(".desktop")
+            .eq_ignore_ascii_case(desktop_entry);
+    }
+
+    // Players running more than once append `.instance<pid>` to their name
+    bus_name
+        .strip_prefix(PLAYER_PREFIX)
+        .and_then(|name| name.split('.').next())
+        .is_some_and(|name| name.eq_ignore_ascii_case(desktop_entry))
+}
+
+/// Length of the track in microseconds, players disagree on its type.
+fn track_length(metadata: &HashMap<String, OwnedValue>) -> Option<i64> {
+    match &**metadata.get("mpris:length")? {
+        Value::I64(length) => Some(*length),
+        Value::U64(length) => i64::try_from(*length).ok(),
+        Value::I32(length) => Some(i64::from(*length)),
+        Value::U32(length) => Some(i64::from(*length)),
+        _ => None,
+    }
+}
+
+/// Percentage of the track played at `position`, None for streams without a length.
+fn progress(position: i64, length: i64) -> Option<i32> {
+    (length > 0).then(|| (position.clamp(0, length) * 100 / length) as i32)
+}
+
+/// Finds the player the app with `desktop_entry` runs as, if any.
+async fn find_player(
+    conn: &zbus::Connection,
+    dbus: &DBusProxy<'_>,
+    desktop_entry: &str,
+) -> zbus::Result<Option<PlayerProxy<'static>>> {
+    for name in dbus.list_names().await? {
+        let name = name.to_string();
+        if !name.starts_with(PLAYER_PREFIX) {
+            continue;
+        }
+
+        let player_entry = MediaPlayer2Proxy::builder(conn)
+            .destination(name.clone())?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?
+            .desktop_entry()
+            .await
+            .ok();
+        if !is_player_of(&name, player_entry.as_deref(), desktop_entry) {
+            continue;
+        }
+
+        // Position isn't announced when it changes, so it's never cached
+        let player = PlayerProxy::builder(conn)
+            .destination(name)?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?;
+        return Ok(Some(player));
+    }
+
+    Ok(None)
+}
+
+async fn read_progress(player: &PlayerProxy<'_>) -> zbus::Result<Option<i32>> {
+    let length = track_length(&player.metadata().await?);
+    let position = player.position().await?;
+
+    Ok(length.and_then(|length| progress(position, length)))
+}
+
+/// Keeps the progress bar of notifications media players sent in sync with playback, as
+/// `Event::MediaProgress`. Notifications are matched to players by their `desktop-entry` hint
+/// as they're announced with `EmitEvent::MediaNotifications`, and players are only polled
+/// while one of them is shown.
+pub async fn watch(
+    event_sender: calloop::channel::Sender<Event>,
+    mut emit_receiver: broadcast::Receiver<EmitEvent>,
+) -> zbus::Result<()> {
+    let conn = zbus::Connection::session().await?;
+    let dbus = DBusProxy::new(&conn).await?;
+
+    let mut tracked: HashMap<NotificationId, Tracked> = HashMap::new();
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        let polling = tracked.values().any(|tracked| tracked.player.is_some());
+        let event = tokio::select! {
+            event = emit_receiver.recv() => event,
+            _ = interval.tick(), if polling => {
+                for (id, entry) in tracked.iter_mut() {
+                    let Some(player) = entry.player.as_ref() else {
+                        continue;
+                    };
+
+                    let value = match read_progress(player).await {
+                        Ok(value) => value,
+                        Err(e) => {
+                            log::debug!("Stopped following {}: {e}", entry.desktop_entry);
+                            entry.player = None;
+                            continue;
+                        }
+                    };
+
+                    if let Some(value) = value.filter(|value| entry.value != Some(*value)) {
+                        entry.value = Some(value);
+                        if let Err(e) = event_sender.send(Event::MediaProgress { id: *id, value }) {
+                            log::error!("Failed to send MediaProgress event: {e}");
+                        }
+                    }
+                }
+                continue;
+            }
+        };
+
+        match event {
+            Ok(EmitEvent::MediaNotifications(shown)) => {
+                tracked.retain(|id, entry| {
+                    shown.iter().any(|(shown, desktop_entry)| {
+                        shown == id && *desktop_entry == entry.desktop_entry
+                    })
+                });
+
+                for (id, desktop_entry) in shown {
+                    if tracked.contains_key(&id) {
+                        continue;
+                    }
+
+                    let player = match find_player(&conn, &dbus, &desktop_entry).await {
+                        Ok(player) => player,
+                        Err(e) => {
+                            log::warn!("Failed to look for a player of {desktop_entry}: {e}");
+                            None
+                        }
+                    };
+                    if player.is_some() {
+                        log::debug!("Following playback of {desktop_entry} for notification {id}");
+                    }
+
+                    tracked.insert(
+                        id,
+                        Tracked {
+                            desktop_entry,
+                            player,
+                            value: None,
+                        },
+                    );
+                }
+            }
+            Ok(EmitEvent::NotificationClosed { id, .. }) => _ = tracked.remove(&id),
+            Ok(_) => {}
+            Err(RecvError::Lagged(_)) => {}
+            Err(RecvError::Closed) => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_player_of, progress, track_length};
+    use std::collections::HashMap;
+    use zbus::zvariant::{OwnedValue, Value};
+
+    #[test]
+    fn test_is_player_of() {
+        let spotify = "org.mpris.MediaPlayer2.spotify";
+        assert!(is_player_of(spotify, Some("spotify"), "spotify.desktop"));
+        assert!(is_player_of(spotify, None, "Spotify"));
+        assert!(!is_player_of(spotify, Some("spotify"), "firefox"));
+
+        let firefox = "org.mpris.MediaPlayer2.firefox.instance_1_42";
+        assert!(is_player_of(firefox, Some(""), "firefox"));
+        assert!(!is_player_of(firefox, None, "fire"));
+    }
+
+    #[test]
+    fn test_progress() {
+        let metadata = |value: Value<'static>| {
+            HashMap::from([(
+                "mpris:length".to_string(),
+                OwnedValue::try_from(value).unwrap(),
+            )])
+        };
+
+        assert_eq!(
+            track_length(&metadata(Value::I64(200_000_000))),
+            Some(200_000_000)
+        );
+        assert_eq!(track_length(&metadata(Value::U64(1_000))), Some(1_000));
+        assert_eq!(track_length(&HashMap::new()), None);
+
+        assert_eq!(progress(50_000_000, 200_000_000), Some(25));
+        // Players report a position past the end while switching tracks
+        assert_eq!(progress(300, 200), Some(100));
+        assert_eq!(progress(-5, 200), Some(0));
+        assert_eq!(progress(100, 0), None);
+    }
+}
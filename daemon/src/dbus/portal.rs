@@ -86,11 +86,13 @@ where
     Ok(Fd::from(owned_fd))
 }
 
-pub async fn serve(mut receiver: broadcast::Receiver<EmitEvent>) -> zbus::Result<()> {
+pub async fn serve(
+    mut receiver: broadcast::Receiver<EmitEvent>,
+) -> Result<zbus::Connection, super::Error> {
     let conn = zbus::Connection::session().await?;
     let open_uri = OpenURIProxy::new(&conn).await?;
 
-    tokio::spawn(async move {
+    super::spawn_until_closed(&conn, async move {
         loop {
             if let Ok(EmitEvent::Open { uri, token }) = receiver.recv().await {
                 let mut options = HashMap::new();
@@ -119,5 +121,5 @@ pub async fn serve(mut receiver: broadcast::Receiver<EmitEvent>) -> zbus::Result
         }
     });
 
-    Ok(())
+    Ok(conn)
 }
@@ -0,0 +1,354 @@
+use super::xdg::{self, IdGenerator, NotificationData, NotificationHints};
+use crate::{
+    utils::{desktop_entry, image_data::ImageData},
+    EmitEvent, Event, Image, Urgency,
+};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::broadcast;
+use zbus::{
+    object_server::SignalEmitter,
+    zvariant::{OwnedValue, Value},
+};
+
+/// Action key the daemon invokes when the notification itself is clicked.
+const DEFAULT_ACTION: &str = "default";
+
+struct Action {
+    name: String,
+    target: Option<OwnedValue>,
+}
+
+/// Notification added through the portal, which identifies it by the sending app and an id
+/// picked by that app rather than by a number.
+struct PortalNotification {
+    app_id: String,
+    id: String,
+    /// Portal actions by the action key handed to the daemon.
+    actions: HashMap<String, Action>,
+}
+
+struct NotificationPortal {
+    event_sender: calloop::channel::Sender<Event>,
+    ids: IdGenerator,
+    notifications: HashMap<u32, PortalNotification>,
+}
+
+impl NotificationPortal {
+    fn find(&self, app_id: &str, id: &str) -> Option<u32> {
+        self.notifications
+            .iter()
+            .find(|(_, notification)| notification.app_id == app_id && notification.id == id)
+            .map(|(nid, _)| *nid)
+    }
+}
+
+#[zbus::interface(name = "org.freedesktop.impl.portal.Notification")]
+impl NotificationPortal {
+    async fn add_notification(
+        &mut self,
+        app_id: &str,
+        id: &str,
+        notification: HashMap<&str, Value<'_>>,
+    ) {
+        // Adding a notification with an id that's already in use replaces it
+        let nid = self.find(app_id, id).unwrap_or_else(|| self.ids.next());
+
+        let (mut data, actions) = parse_notification(nid, app_id, notification);
+        desktop_entry::enrich(&mut data).await;
+        self.notifications.insert(
+            nid,
+            PortalNotification {
+                app_id: app_id.into(),
+                id: id.into(),
+                actions,
+            },
+        );
+
+        if let Err(e) = self.event_sender.send(Event::Notify(Box::new(data))) {
+            log::error!("Error: {e}");
+        }
+    }
+
+    async fn remove_notification(&mut self, app_id: &str, id: &str) {
+        let Some(nid) = self.find(app_id, id) else {
+            return;
+        };
+
+        self.notifications.remove(&nid);
+        if let Err(e) = self.event_sender.send(Event::CloseNotification {
+            id: nid,
+            reply: None,
+        }) {
+            log::error!("Failed to send CloseNotification({nid}) event: {e}");
+        }
+    }
+
+    #[zbus(property)]
+    async fn version(&self) -> u32 {
+        2
+    }
+
+    #[zbus(property)]
+    async fn supported_options(&self) -> HashMap<String, OwnedValue> {
+        HashMap::new()
+    }
+
+    #[zbus(signal)]
+    async fn action_invoked(
+        signal_emitter: &SignalEmitter<'_>,
+        app_id: &str,
+        id: &str,
+        action: &str,
+        parameter: Vec<Value<'_>>,
+    ) -> zbus::Result<()>;
+}
+
+fn parse_notification(
+    nid: u32,
+    app_id: &str,
+    mut notification: HashMap<&str, Value<'_>>,
+) -> (NotificationData, HashMap<String, Action>) {
+    let mut string = |key| {
+        notification
+            .remove(key)
+            .and_then(|value| String::try_from(value).ok())
+    };
+
+    let summary = string("title").unwrap_or_default();
+    let body = string("markup-body")
+        .or_else(|| string("body"))
+        .unwrap_or_default();
+    let urgency = match string("priority").as_deref() {
+        Some("low") => Urgency::Low,
+        Some("urgent") => Urgency::Critical,
+        _ => Urgency::Normal,
+    };
+    let category = string("category");
+    let default_action = string("default-action");
+
+    let mut actions = HashMap::new();
+    if let Some(name) = default_action {
+        let target = notification
+            .remove("default-action-target")
+            .and_then(|target| OwnedValue::try_from(target).ok());
+        actions.insert(DEFAULT_ACTION.to_string(), Action { name, target });
+    }
+
+    let buttons: Vec<(String, String)> = notification
+        .remove("buttons")
+        .and_then(|buttons| Vec::<HashMap<String, OwnedValue>>::try_from(buttons).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|mut button| {
+            let label = String::try_from(button.remove("label")?).ok()?;
+            let name = String::try_from(button.remove("action")?).ok()?;
+            actions.insert(
+                name.clone(),
+                Action {
+                    name: name.clone(),
+                    target: button.remove("target"),
+                },
+            );
+            Some((name, label))
+        })
+        .collect();
+
+    let image = notification.remove("icon").and_then(parse_icon);
+
+    let data = NotificationData {
+        id: nid,
+        app_name: app_id.into(),
+        display_name: None,
+        app_icon: Some(app_id.into()),
+        summary: summary.into(),
+        body: body.into(),
+        timeout: -1,
+        actions: actions
+            .contains_key(DEFAULT_ACTION)
+            .then(|| (Arc::from(DEFAULT_ACTION), Arc::from("Open")))
+            .into_iter()
+            .chain(
+                buttons
+                    .into_iter()
+                    .map(|(name, label)| (Arc::from(name), Arc::from(label))),
+            )
+            .collect(),
+        hints: NotificationHints {
+            urgency,
+            image,
+            category: category.map(Into::into),
+            desktop_entry: Some(app_id.into()),
+            ..Default::default()
+        },
+        timestamp: xdg::timestamp(),
+        rowid: None,
+        sent_id: None,
+        redacted: false,
+    };
+
+    (data, actions)
+}
+
+/// Reads an icon serialized the way `GIcon` does it, as the kind of icon and its data.
+fn parse_icon(icon: Value<'_>) -> Option<Image> {
+    let Value::Structure(icon) = icon else {
+        return None;
+    };
+
+    let mut fields = icon.into_fields().into_iter();
+    let kind = String::try_from(fields.next()?).ok()?;
+    let data = match fields.next()? {
+        Value::Value(data) => *data,
+        data => data,
+    };
+
+    match kind.as_str() {
+        "themed" => Vec::<String>::try_from(data)
+            .ok()?
+            .into_iter()
+            .next()
+            .map(|name| Image::Name(name.into())),
+        "file" => url::Url::parse(&String::try_from(data).ok()?)
+            .ok()?
+            .to_file_path()
+            .ok()
+            .map(|path| Image::File(path.into())),
+        "bytes" => {
+            let bytes = Vec::<u8>::try_from(data).ok()?;
+            let image = image::load_from_memory(&bytes).ok()?;
+            ImageData::try_from(image).ok().map(Image::Data)
+        }
+        _ => None,
+    }
+}
+
+pub async fn serve(
+    event_sender: calloop::channel::Sender<Event>,
+    mut emit_receiver: broadcast::Receiver<EmitEvent>,
+    ids: IdGenerator,
+) -> Result<zbus::Connection, super::Error> {
+    let server = NotificationPortal {
+        event_sender,
+        ids,
+        notifications: HashMap::new(),
+    };
+
+    let conn = zbus::connection::Builder::session()?
+        .serve_at("/org/freedesktop/portal/desktop", server)?
+        .build()
+        .await?;
+
+    super::request_name(&conn, "org.freedesktop.impl.portal.desktop.moxnotify").await?;
+
+    let iface = conn
+        .object_server()
+        .interface::<_, NotificationPortal>("/org/freedesktop/portal/desktop")
+        .await?;
+
+    super::spawn_until_closed(&conn, async move {
+        loop {
+            match emit_receiver.recv().await {
+                Ok(EmitEvent::ActionInvoked { id, key, token }) => {
+                    let (app_id, portal_id, action, target) = {
+                        let portal = iface.get().await;
+                        let Some(notification) = portal.notifications.get(&id) else {
+                            continue;
+                        };
+                        let Some(action) = notification.actions.get(&*key) else {
+                            continue;
+                        };
+
+                        (
+                            notification.app_id.clone(),
+                            notification.id.clone(),
+                            action.name.clone(),
+                            action.target.as_ref().and_then(|t| t.try_clone().ok()),
+                        )
+                    };
+
+                    log::info!(
+                        "{action} portal action invoked for {app_id} notification {portal_id}"
+                    );
+
+                    // The target comes first if the action has one, followed by platform data
+                    let mut parameter: Vec<Value> = target.into_iter().map(Value::from).collect();
+                    let mut platform_data = HashMap::new();
+                    if !token.is_empty() {
+                        platform_data.insert("activation-token", Value::from(&*token));
+                    }
+                    parameter.push(Value::from(platform_data));
+
+                    _ = NotificationPortal::action_invoked(
+                        iface.signal_emitter(),
+                        &app_id,
+                        &portal_id,
+                        &action,
+                        parameter,
+                    )
+                    .await;
+                }
+                Ok(EmitEvent::NotificationClosed { id, .. }) => {
+                    iface.get_mut().await.notifications.remove(&id);
+                }
+                _ => {}
+            }
+        }
+    });
+
+    Ok(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_notification, DEFAULT_ACTION};
+    use crate::{Image, Urgency};
+    use std::collections::HashMap;
+    use zbus::zvariant::{Structure, Value};
+
+    #[test]
+    fn test_parse_notification() {
+        let button = HashMap::from([
+            ("label", Value::from("Reply")),
+            ("action", Value::from("app.reply")),
+            ("target", Value::from(42u32)),
+        ]);
+        let icon = Structure::from((
+            "themed",
+            Value::new(vec!["mail-unread", "mail-unread-symbolic"]),
+        ));
+
+        let notification = HashMap::from([
+            ("title", Value::from("New mail")),
+            ("body", Value::from("Hello")),
+            ("priority", Value::from("urgent")),
+            ("default-action", Value::from("app.open")),
+            ("buttons", Value::from(vec![button])),
+            ("icon", Value::from(icon)),
+        ]);
+
+        let (data, actions) = parse_notification(7, "org.example.Mail", notification);
+
+        assert_eq!(data.id, 7);
+        assert_eq!(&*data.summary, "New mail");
+        assert_eq!(&*data.body, "Hello");
+        assert_eq!(data.hints.urgency, Urgency::Critical);
+        assert!(matches!(data.hints.image, Some(Image::Name(name)) if &*name == "mail-unread"));
+        assert_eq!(
+            data.actions
+                .iter()
+                .map(|(key, label)| (&**key, &**label))
+                .collect::<Vec<_>>(),
+            vec![(DEFAULT_ACTION, "Open"), ("app.reply", "Reply")]
+        );
+
+        assert_eq!(actions[DEFAULT_ACTION].name, "app.open");
+        assert!(actions[DEFAULT_ACTION].target.is_none());
+        assert_eq!(
+            actions["app.reply"]
+                .target
+                .as_ref()
+                .and_then(|target| u32::try_from(target).ok()),
+            Some(42)
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use crate::Event;
+use futures_lite::StreamExt;
+use std::collections::{HashMap, HashSet};
+use zbus::{
+    fdo::MonitoringProxy,
+    message::Type as MessageType,
+    zvariant::{ObjectPath, OwnedObjectPath, Value},
+    MatchRule, MessageStream,
+};
+
+const SCREENCAST_INTERFACE: &str = "org.freedesktop.impl.portal.ScreenCast";
+const SESSION_INTERFACE: &str = "org.freedesktop.impl.portal.Session";
+
+/// Screencast sessions the portal frontend asked its backend to start and hasn't closed yet.
+#[derive(Default)]
+struct Sessions(HashSet<OwnedObjectPath>);
+
+impl Sessions {
+    /// Tracks a session by the path of its handle, returns whether any screencast is active
+    /// afterwards.
+    fn update(&mut self, member: &str, path: OwnedObjectPath) -> bool {
+        match member {
+            "Start" => _ = self.0.insert(path),
+            "Close" | "Closed" => _ = self.0.remove(&path),
+            _ => {}
+        }
+
+        !self.0.is_empty()
+    }
+}
+
+/// Watches for screencasts started through the desktop portal and reports whether one is active
+/// as `Event::Screencast`. The portal doesn't publish its sessions, so the session bus is
+/// monitored for the calls the frontend makes to its backend instead.
+pub async fn watch(event_sender: calloop::channel::Sender<Event>) -> zbus::Result<()> {
+    let conn = zbus::connection::Builder::session()?.build().await?;
+
+    let rules = [
+        MatchRule::builder()
+            .msg_type(MessageType::MethodCall)
+            .interface(SCREENCAST_INTERFACE)?
+            .member("Start")?
+            .build(),
+        MatchRule::builder()
+            .msg_type(MessageType::MethodCall)
+            .interface(SESSION_INTERFACE)?
+            .member("Close")?
+            .build(),
+        MatchRule::builder()
+            .msg_type(MessageType::Signal)
+            .interface(SESSION_INTERFACE)?
+            .member("Closed")?
+            .build(),
+    ];
+
+    MonitoringProxy::new(&conn)
+        .await?
+        .become_monitor(&rules, 0)
+        .await?;
+
+    let mut stream = MessageStream::from(&conn);
+    let mut sessions = Sessions::default();
+    let mut active = false;
+
+    while let Some(message) = stream.next().await {
+        let message = match message {
+            Ok(message) => message,
+            Err(e) => {
+                log::warn!("Failed to read monitored message: {e}");
+                continue;
+            }
+        };
+
+        let header = message.header();
+        let Some(member) = header.member() else {
+            continue;
+        };
+
+        // Start names the session it belongs to in its arguments, Close and Closed are sent to
+        // the session object itself
+        let path = match member.as_str() {
+            "Start" => {
+                type Start<'a> = (
+                    ObjectPath<'a>,
+                    ObjectPath<'a>,
+                    &'a str,
+                    &'a str,
+                    HashMap<&'a str, Value<'a>>,
+                );
+                match message.body().deserialize::<Start>() {
+                    Ok((_, session, ..)) => session.into(),
+                    Err(e) => {
+                        log::warn!("Failed to read screencast session: {e}");
+                        continue;
+                    }
+                }
+            }
+            _ => match header.path() {
+                Some(path) => path.clone().into(),
+                None => continue,
+            },
+        };
+
+        if sessions.update(member.as_str(), path) != active {
+            active = !active;
+            log::debug!("Screencast active: {active}");
+            if let Err(e) = event_sender.send(Event::Screencast(active)) {
+                log::error!("Failed to send Screencast({active}) event: {e}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sessions;
+    use zbus::zvariant::OwnedObjectPath;
+
+    fn path(session: &str) -> OwnedObjectPath {
+        OwnedObjectPath::try_from(format!(
+            "/org/freedesktop/portal/desktop/session/1_42/{session}"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_sessions() {
+        let mut sessions = Sessions::default();
+
+        assert!(sessions.update("Start", path("obs")));
+        assert!(sessions.update("Start", path("firefox")));
+        assert!(sessions.update("Close", path("obs")));
+        assert!(sessions.update("Closed", path("unknown")));
+        assert!(!sessions.update("Closed", path("firefox")));
+    }
+}
@@ -0,0 +1,55 @@
+use crate::Event;
+use futures_lite::StreamExt;
+use zbus::zvariant::OwnedObjectPath;
+
+#[zbus::proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+trait Manager {
+    fn get_session(&self, session_id: &str) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.login1.Session",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1/session/auto"
+)]
+trait Session {
+    #[zbus(property)]
+    fn id(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn locked_hint(&self) -> zbus::Result<bool>;
+}
+
+/// Watches the `LockedHint` of the logind session and reports it as `Event::Locked`, starting
+/// with its current value. ext-session-lock only tells the locker itself that the session is
+/// locked, so the hint lockers set is the one thing other clients can go by.
+pub async fn watch(event_sender: calloop::channel::Sender<Event>) -> zbus::Result<()> {
+    let conn = zbus::Connection::system().await?;
+
+    // The `auto` alias resolves calls only, changes are announced on the path of the session
+    let id = SessionProxy::new(&conn).await?.id().await?;
+    let path = ManagerProxy::new(&conn).await?.get_session(&id).await?;
+    let session = SessionProxy::builder(&conn).path(path)?.build().await?;
+
+    let mut changes = session.receive_locked_hint_changed().await;
+    while let Some(change) = changes.next().await {
+        let locked = match change.get().await {
+            Ok(locked) => locked,
+            Err(e) => {
+                log::warn!("Failed to read session lock state: {e}");
+                continue;
+            }
+        };
+
+        log::debug!("Session locked: {locked}");
+        if let Err(e) = event_sender.send(Event::Locked(locked)) {
+            log::error!("Failed to send Locked({locked}) event: {e}");
+        }
+    }
+
+    Ok(())
+}
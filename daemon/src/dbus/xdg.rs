@@ -1,10 +1,30 @@
-use crate::{utils::image_data::ImageData, EmitEvent, Event, Image, Urgency};
+use crate::{
+    config::Config,
+    utils::{desktop_entry, image_data::ImageData},
+    EmitEvent, Event, Image, Urgency,
+};
+use rusqlite::OptionalExtension;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, path::Path, sync::Arc};
-use tokio::sync::broadcast;
-use zbus::{fdo::RequestNameFlags, object_server::SignalEmitter, zvariant::Str};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    oneshot,
+};
+use zbus::{
+    object_server::{InterfaceRef, SignalEmitter},
+    zvariant::Str,
+};
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Summary history entries of apps in `history.exclude_body_apps` are stored with.
+pub const REDACTED: &str = "[redacted]";
+/// Version of the Desktop Notifications Specification the server implements.
+pub const SPEC_VERSION: &str = "1.3";
 
 #[derive(Debug, Default, Deserialize, Serialize, Clone)]
 pub struct NotificationHints {
@@ -16,6 +36,8 @@ pub struct NotificationHints {
     pub sound_file: Option<Arc<Path>>,
     pub sound_name: Option<Arc<str>>,
     pub suppress_sound: bool,
+    /// Tag shared by notifications that should replace each other regardless of their ids.
+    pub synchronous: Option<Box<str>>,
     pub transient: bool,
     pub x: i32,
     pub y: Option<i32>,
@@ -67,6 +89,9 @@ impl NotificationHints {
                             _ => false,
                         };
                     }
+                    "x-canonical-private-synchronous" | "x-dunst-stack-tag" => {
+                        nh.synchronous = Str::try_from(v).ok().map(|s| s.as_str().into())
+                    }
                     "transient" => {
                         nh.transient = match v {
                             zbus::zvariant::Value::Bool(b) => b,
@@ -91,10 +116,14 @@ impl NotificationHints {
                     }
                     "image-path" | "image_path" => {
                         if let Ok(s) = Str::try_from(v) {
-                            nh.image = if let Ok(path) = url::Url::parse(&s) {
-                                Some(Image::File(Path::new(path.as_str()).into()))
-                            } else {
-                                Some(Image::Name(s.as_str().into()))
+                            nh.image = match url::Url::parse(&s) {
+                                Ok(url) if url.scheme() == "file" => {
+                                    url.to_file_path().ok().map(|path| Image::File(path.into()))
+                                }
+                                _ if s.starts_with('/') => {
+                                    Some(Image::File(Path::new(s.as_str()).into()))
+                                }
+                                _ => Some(Image::Name(s.as_str().into())),
                             };
                         }
                     }
@@ -118,33 +147,595 @@ impl NotificationHints {
 pub struct NotificationData {
     pub id: u32,
     pub app_name: Arc<str>,
+    /// Name of the app as its desktop file has it, see [`desktop_entry::enrich`].
+    #[serde(default)]
+    pub display_name: Option<Box<str>>,
     pub app_icon: Option<Box<str>>,
     pub summary: Box<str>,
     pub body: Box<str>,
     pub timeout: i32,
     pub actions: Box<[(Arc<str>, Arc<str>)]>,
     pub hints: NotificationHints,
+    /// Seconds since the Unix epoch at which the notification was received, 0 if unknown.
+    #[serde(default)]
+    pub timestamp: u64,
+    /// Row of the history database the notification is stored in, None until it's persisted.
+    /// Replacements of the notification are written to the same row.
+    #[serde(skip)]
+    pub rowid: Option<i64>,
+    /// Id the notification was sent with this session, kept for history entries as they're
+    /// listed by their `rowid` instead. None for entries loaded from the database, ids from
+    /// earlier sessions clash with the ones apps use now.
+    #[serde(skip)]
+    pub sent_id: Option<u32>,
+    /// Whether the notification is a history entry stored without its summary and body.
+    #[serde(skip)]
+    pub redacted: bool,
 }
 
-struct NotificationsImpl {
-    next_id: u32,
-    event_sender: calloop::channel::Sender<Event>,
+/// Seconds since the Unix epoch, as stored in `NotificationData::timestamp`.
+pub fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
 }
 
-#[zbus::interface(name = "org.freedesktop.Notifications")]
-impl NotificationsImpl {
-    async fn get_capabilities(&self) -> &[&'static str] {
-        &[
-            // "action-icons",
+/// Creates the history table, adding columns that databases from older versions lack.
+/// `timestamp` is when the latest notification stored in a row was received, `created_at` when
+/// the first one was.
+pub fn create_history_table(db: &rusqlite::Connection) -> rusqlite::Result<()> {
+    db.execute(
+        "CREATE TABLE IF NOT EXISTS notifications (
+            rowid INTEGER PRIMARY KEY AUTOINCREMENT,
+            id INTEGER,
+            app_name TEXT,
+            app_icon TEXT,
+            summary TEXT,
+            body TEXT,
+            timeout INTEGER,
+            actions TEXT,
+            hints JSON,
+            timestamp INTEGER NOT NULL DEFAULT 0,
+            created_at INTEGER NOT NULL DEFAULT 0,
+            urgency INTEGER NOT NULL DEFAULT 1,
+            redacted INTEGER NOT NULL DEFAULT 0,
+            display_name TEXT
+        );",
+        (),
+    )?;
+
+    add_column(
+        db,
+        "notifications",
+        "timestamp",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    if add_column(
+        db,
+        "notifications",
+        "created_at",
+        "INTEGER NOT NULL DEFAULT 0",
+    )? {
+        db.execute("UPDATE notifications SET created_at = timestamp", ())?;
+    }
+    if add_column(db, "notifications", "urgency", "INTEGER NOT NULL DEFAULT 1")? {
+        db.execute(
+            "UPDATE notifications SET urgency = CASE json_extract(hints, '$.urgency')
+                WHEN 'Low' THEN 0
+                WHEN 'Critical' THEN 2
+                ELSE 1
+             END
+             WHERE json_valid(hints)",
+            (),
+        )?;
+    }
+    add_column(
+        db,
+        "notifications",
+        "redacted",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    add_column(db, "notifications", "display_name", "TEXT")?;
+
+    Ok(())
+}
+
+/// Adds `column` to `table` unless it's there already, returns whether it was added.
+fn add_column(
+    db: &rusqlite::Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> rusqlite::Result<bool> {
+    let exists: bool = db.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+        [table, column],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        db.execute(
+            &format!("ALTER TABLE {table} ADD COLUMN {column} {definition}"),
+            (),
+        )?;
+    }
+
+    Ok(!exists)
+}
+
+/// Urgency stored as its level in the `urgency` column of the history table.
+fn urgency_from_level(level: u8) -> Urgency {
+    match level {
+        0 => Urgency::Low,
+        2 => Urgency::Critical,
+        _ => Urgency::Normal,
+    }
+}
+
+impl NotificationData {
+    /// Stores the notification in the history database, transient ones are skipped. With a
+    /// `rowid` that row is overwritten, keeping its place in the history, otherwise the row it's
+    /// inserted as is set as `rowid`. `redact` stores it with [`REDACTED`] as its summary and
+    /// without a body.
+    pub fn persist(&mut self, db: &rusqlite::Connection, redact: bool) -> anyhow::Result<()> {
+        if self.hints.transient {
+            return Ok(());
+        }
+
+        let actions = serde_json::to_string(&self.actions)?;
+        let hints = serde_json::to_string(&self.hints)?;
+        let urgency = self.hints.urgency as u8;
+        let (summary, body) = self.stored_text(redact);
+
+        if let Some(rowid) = self.rowid {
+            let updated = db.execute(
+                "UPDATE notifications SET id = ?1, app_name = ?2, app_icon = ?3, timeout = ?4,
+                    summary = ?5, body = ?6, actions = ?7, hints = ?8, timestamp = ?9, urgency = ?10,
+                    redacted = ?11, display_name = ?12
+                 WHERE rowid = ?13",
+                rusqlite::params![
+                    self.id,
+                    self.app_name,
+                    self.app_icon,
+                    self.timeout,
+                    summary,
+                    body,
+                    actions,
+                    hints,
+                    self.timestamp,
+                    urgency,
+                    redact,
+                    self.display_name,
+                    rowid
+                ],
+            )?;
+            // The row is gone if the history was cleared in the meantime
+            if updated > 0 {
+                return Ok(());
+            }
+        }
+
+        db.execute(
+            "INSERT INTO notifications (id, app_name, app_icon, timeout, summary, body, actions, hints, timestamp, created_at, urgency, redacted, display_name)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9, ?10, ?11, ?12)",
+            rusqlite::params![
+                self.id,
+                self.app_name,
+                self.app_icon,
+                self.timeout,
+                summary,
+                body,
+                actions,
+                hints,
+                self.timestamp,
+                urgency,
+                redact,
+                self.display_name
+            ],
+        )?;
+        self.rowid = Some(db.last_insert_rowid());
+
+        Ok(())
+    }
+
+    /// Summary and body as they're stored, [`REDACTED`] and nothing when `redact`.
+    fn stored_text(&self, redact: bool) -> (&str, &str) {
+        match redact {
+            true => (REDACTED, ""),
+            false => (&self.summary, &self.body),
+        }
+    }
+
+    /// Stores the notification as held back by inhibit, replacing the one with the same id
+    /// without moving it in the queue. `redact` stores it the way [`Self::persist`] does.
+    pub fn hold(&self, db: &rusqlite::Connection, redact: bool) -> anyhow::Result<()> {
+        let (summary, body) = self.stored_text(redact);
+        db.execute(
+            "INSERT INTO waiting (id, app_name, app_icon, timeout, summary, body, actions, hints, timestamp,
+                display_name, redacted)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+             ON CONFLICT (id) DO UPDATE SET
+                app_name = excluded.app_name,
+                app_icon = excluded.app_icon,
+                timeout = excluded.timeout,
+                summary = excluded.summary,
+                body = excluded.body,
+                actions = excluded.actions,
+                hints = excluded.hints,
+                timestamp = excluded.timestamp,
+                display_name = excluded.display_name,
+                redacted = excluded.redacted",
+            rusqlite::params![
+                self.id,
+                self.app_name,
+                self.app_icon,
+                self.timeout,
+                summary,
+                body,
+                serde_json::to_string(&self.actions)?,
+                serde_json::to_string(&self.hints)?,
+                self.timestamp,
+                self.display_name,
+                redact
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Stores the notification as snoozed until `due`, in seconds since the Unix epoch.
+    /// `redact` stores it the way [`Self::persist`] does.
+    pub fn snooze(&self, db: &rusqlite::Connection, due: u64, redact: bool) -> anyhow::Result<()> {
+        let (summary, body) = self.stored_text(redact);
+        db.execute(
+            "INSERT OR REPLACE INTO snoozed (id, app_name, app_icon, timeout, summary, body, actions, hints, timestamp,
+                display_name, redacted, due)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            rusqlite::params![
+                self.id,
+                self.app_name,
+                self.app_icon,
+                self.timeout,
+                summary,
+                body,
+                serde_json::to_string(&self.actions)?,
+                serde_json::to_string(&self.hints)?,
+                self.timestamp,
+                self.display_name,
+                redact,
+                due
+            ],
+        )?;
+
+        Ok(())
+    }
+}
+
+/// `LIKE` pattern matching text that contains `query`, wildcards in it are taken literally.
+fn contains_pattern(query: &str) -> String {
+    let escaped = query
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+/// `limit` history entries starting `offset` entries from the newest. They're listed with
+/// their rowid as id, as ids of notifications from different sessions clash.
+/// With a `query` only entries whose summary or body contain it are counted.
+pub fn load_history(
+    db: &rusqlite::Connection,
+    query: Option<&str>,
+    limit: usize,
+    offset: usize,
+) -> anyhow::Result<Vec<NotificationData>> {
+    let mut stmt = db.prepare(
+        "SELECT rowid, app_name, app_icon, summary, body, timeout, actions, hints, timestamp,
+            display_name, redacted, urgency
+         FROM notifications
+         WHERE ?1 IS NULL OR summary LIKE ?1 ESCAPE '\\' OR body LIKE ?1 ESCAPE '\\'
+         ORDER BY rowid DESC LIMIT ?2 OFFSET ?3",
+    )?;
+    let rows = stmt.query_map(
+        rusqlite::params![query.map(contains_pattern), limit as i64, offset as i64],
+        |row| Ok((data_from_row(row)?, row.get::<_, u8>(11)?)),
+    )?;
+
+    rows.map(|row| -> anyhow::Result<NotificationData> {
+        let ((data, actions, hints), urgency) = row?;
+        let mut data = with_json(data, &actions, &hints)?;
+        data.rowid = Some(i64::from(data.id));
+        data.hints.urgency = urgency_from_level(urgency);
+        Ok(data)
+    })
+    // Transient notifications aren't stored anymore, but older databases may still contain some
+    .filter(|data| !data.as_ref().is_ok_and(|data| data.hints.transient))
+    .collect()
+}
+
+/// Number of history entries, only counting ones matching `query` when given.
+pub fn count_history(db: &rusqlite::Connection, query: Option<&str>) -> rusqlite::Result<usize> {
+    db.query_row(
+        "SELECT COUNT(*) FROM notifications
+         WHERE ?1 IS NULL OR summary LIKE ?1 ESCAPE '\\' OR body LIKE ?1 ESCAPE '\\'",
+        rusqlite::params![query.map(contains_pattern)],
+        |row| row.get(0),
+    )
+}
+
+/// Creates the table holding notifications held back while inhibited, so they outlive restarts.
+pub fn create_waiting_table(db: &rusqlite::Connection) -> rusqlite::Result<()> {
+    db.execute(
+        "CREATE TABLE IF NOT EXISTS waiting (
+            rowid INTEGER PRIMARY KEY AUTOINCREMENT,
+            id INTEGER UNIQUE,
+            app_name TEXT,
+            app_icon TEXT,
+            summary TEXT,
+            body TEXT,
+            timeout INTEGER,
+            actions TEXT,
+            hints JSON,
+            timestamp INTEGER NOT NULL DEFAULT 0,
+            display_name TEXT,
+            redacted INTEGER NOT NULL DEFAULT 0
+        );",
+        (),
+    )?;
+
+    add_column(db, "waiting", "display_name", "TEXT")?;
+    add_column(db, "waiting", "redacted", "INTEGER NOT NULL DEFAULT 0")?;
+
+    Ok(())
+}
+
+/// Creates the table of daemon state that outlives restarts, such as the inhibit.
+pub fn create_state_table(db: &rusqlite::Connection) -> rusqlite::Result<()> {
+    db.execute(
+        "CREATE TABLE IF NOT EXISTS state (
+            key TEXT PRIMARY KEY,
+            value INTEGER NOT NULL
+        );",
+        (),
+    )?;
+
+    Ok(())
+}
+
+/// Stores whether notifications are inhibited, held back ones are stored on their own.
+pub fn set_inhibited(db: &rusqlite::Connection, inhibited: bool) -> rusqlite::Result<()> {
+    db.execute(
+        "INSERT INTO state (key, value) VALUES ('inhibited', ?1)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        rusqlite::params![inhibited],
+    )?;
+    Ok(())
+}
+
+/// Whether notifications were inhibited before a restart.
+pub fn load_inhibited(db: &rusqlite::Connection) -> rusqlite::Result<bool> {
+    db.query_row(
+        "SELECT value FROM state WHERE key = 'inhibited'",
+        (),
+        |row| row.get(0),
+    )
+    .optional()
+    .map(|inhibited| inhibited.unwrap_or(false))
+}
+
+/// Reads a notification from a row starting with `id, app_name, app_icon, summary, body,
+/// timeout, actions, hints, timestamp, display_name, redacted`, the JSON columns are returned
+/// as they are.
+fn data_from_row(row: &rusqlite::Row) -> rusqlite::Result<(NotificationData, Box<str>, Box<str>)> {
+    Ok((
+        NotificationData {
+            id: row.get(0)?,
+            app_name: row.get(1)?,
+            app_icon: row.get::<_, Option<Box<str>>>(2)?,
+            summary: row.get::<_, Box<str>>(3)?,
+            body: row.get::<_, Box<str>>(4)?,
+            timeout: row.get(5)?,
+            timestamp: row.get(8)?,
+            display_name: row.get::<_, Option<Box<str>>>(9)?,
+            redacted: row.get(10)?,
+            ..Default::default()
+        },
+        row.get::<_, Box<str>>(6)?,
+        row.get::<_, Box<str>>(7)?,
+    ))
+}
+
+fn with_json(
+    data: NotificationData,
+    actions: &str,
+    hints: &str,
+) -> anyhow::Result<NotificationData> {
+    Ok(NotificationData {
+        actions: serde_json::from_str(actions)?,
+        hints: serde_json::from_str(hints)?,
+        ..data
+    })
+}
+
+/// Waiting notifications in the order they arrived.
+pub fn load_waiting(db: &rusqlite::Connection) -> anyhow::Result<Vec<NotificationData>> {
+    let mut stmt = db.prepare(
+        "SELECT id, app_name, app_icon, summary, body, timeout, actions, hints, timestamp,
+            display_name, redacted
+         FROM waiting ORDER BY rowid ASC",
+    )?;
+    let rows = stmt.query_map([], data_from_row)?;
+
+    rows.map(|row| -> anyhow::Result<NotificationData> {
+        let (data, actions, hints) = row?;
+        with_json(data, &actions, &hints)
+    })
+    .collect()
+}
+
+/// Deletes the history entry stored in `rowid`.
+pub fn forget(db: &rusqlite::Connection, rowid: i64) -> rusqlite::Result<()> {
+    db.execute(
+        "DELETE FROM notifications WHERE rowid = ?1",
+        rusqlite::params![rowid],
+    )?;
+    Ok(())
+}
+
+/// Forgets waiting notification `id`.
+pub fn unhold(db: &rusqlite::Connection, id: u32) -> rusqlite::Result<()> {
+    db.execute("DELETE FROM waiting WHERE id = ?1", rusqlite::params![id])?;
+    Ok(())
+}
+
+pub fn clear_waiting(db: &rusqlite::Connection) -> rusqlite::Result<()> {
+    db.execute("DELETE FROM waiting", ())?;
+    Ok(())
+}
+
+/// Creates the table of snoozed notifications along with when they're due again.
+pub fn create_snoozed_table(db: &rusqlite::Connection) -> rusqlite::Result<()> {
+    db.execute(
+        "CREATE TABLE IF NOT EXISTS snoozed (
+            rowid INTEGER PRIMARY KEY AUTOINCREMENT,
+            id INTEGER UNIQUE,
+            app_name TEXT,
+            app_icon TEXT,
+            summary TEXT,
+            body TEXT,
+            timeout INTEGER,
+            actions TEXT,
+            hints JSON,
+            timestamp INTEGER NOT NULL DEFAULT 0,
+            due INTEGER NOT NULL,
+            display_name TEXT,
+            redacted INTEGER NOT NULL DEFAULT 0
+        );",
+        (),
+    )?;
+
+    add_column(db, "snoozed", "display_name", "TEXT")?;
+    add_column(db, "snoozed", "redacted", "INTEGER NOT NULL DEFAULT 0")?;
+
+    Ok(())
+}
+
+/// Snoozed notifications paired with when they're due, soonest first.
+pub fn load_snoozed(db: &rusqlite::Connection) -> anyhow::Result<Vec<(NotificationData, u64)>> {
+    let mut stmt = db.prepare(
+        "SELECT id, app_name, app_icon, summary, body, timeout, actions, hints, timestamp,
+            display_name, redacted, due
+         FROM snoozed ORDER BY due ASC",
+    )?;
+    let rows = stmt.query_map([], |row| Ok((data_from_row(row)?, row.get::<_, u64>(11)?)))?;
+
+    rows.map(|row| -> anyhow::Result<(NotificationData, u64)> {
+        let ((data, actions, hints), due) = row?;
+        Ok((with_json(data, &actions, &hints)?, due))
+    })
+    .collect()
+}
+
+/// Forgets snoozed notification `id`, returns whether it was snoozed.
+pub fn unsnooze(db: &rusqlite::Connection, id: u32) -> rusqlite::Result<bool> {
+    Ok(db.execute("DELETE FROM snoozed WHERE id = ?1", rusqlite::params![id])? > 0)
+}
+
+/// Snapshot of the features the daemon can actually provide, taken at startup.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Capabilities {
+    body_markup: bool,
+    persistence: bool,
+    sound: bool,
+}
+
+impl Capabilities {
+    pub fn new(config: &Config, audio_available: bool) -> Self {
+        Self {
+            body_markup: config.general.markup,
+            persistence: config.general.history.size > 0,
+            sound: audio_available,
+        }
+    }
+
+    pub fn list(&self) -> Vec<&'static str> {
+        let mut capabilities = vec![
+            "action-icons",
             "actions",
             "body",
             "body-hyperlinks",
             "body-images",
-            "body-markup",
             "icon-multi",
-            "persistence",
-            "sound",
-        ]
+            "inline-reply",
+        ];
+
+        if self.body_markup {
+            capabilities.push("body-markup");
+        }
+
+        if self.persistence {
+            capabilities.push("persistence");
+        }
+
+        if self.sound {
+            capabilities.push("sound");
+        }
+
+        capabilities
+    }
+}
+
+/// Hands out notification ids, shared by everything that creates notifications so that their
+/// ids never collide. Ids start over from 1 after `u32::MAX`, skipping 0 and the ones still in
+/// use.
+#[derive(Clone, Default)]
+pub struct IdGenerator(Arc<Mutex<Ids>>);
+
+#[derive(Default)]
+struct Ids {
+    last: u32,
+    in_use: HashSet<u32>,
+}
+
+impl IdGenerator {
+    pub fn next(&self) -> u32 {
+        let mut ids = self.0.lock().unwrap();
+        let start = ids.last;
+        let mut id = start;
+        loop {
+            id = id.checked_add(1).unwrap_or(1);
+            // Every id being in use can't really happen, reusing one beats spinning forever
+            if !ids.in_use.contains(&id) || id == start {
+                break;
+            }
+        }
+
+        ids.last = id;
+        ids.in_use.insert(id);
+        id
+    }
+
+    /// Replaces the ids considered in use. Ids that were handed out but aren't shown yet can be
+    /// left out, they don't come up again before the ids wrap around.
+    pub fn set_in_use<I>(&self, in_use: I)
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        let mut ids = self.0.lock().unwrap();
+        ids.in_use = in_use.into_iter().collect();
+    }
+}
+
+struct NotificationsImpl {
+    ids: IdGenerator,
+    event_sender: calloop::channel::Sender<Event>,
+    capabilities: Capabilities,
+}
+
+#[zbus::interface(name = "org.freedesktop.Notifications")]
+impl NotificationsImpl {
+    async fn get_capabilities(&self) -> Vec<&'static str> {
+        self.capabilities.list()
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -160,11 +751,7 @@ impl NotificationsImpl {
         expire_timeout: i32,
     ) -> u32 {
         let id = match replaces_id == 0 {
-            true => {
-                let id = self.next_id;
-                self.next_id = self.next_id.checked_add(1).unwrap_or(1);
-                id
-            }
+            true => self.ids.next(),
             false => replaces_id,
         };
 
@@ -174,40 +761,58 @@ impl NotificationsImpl {
             Some(app_icon.into())
         };
 
-        if let Err(e) = self
-            .event_sender
-            .send(Event::Notify(Box::new(NotificationData {
-                id,
-                app_name: app_name.into(),
-                summary: summary.into(),
-                body: body.into(),
-                timeout: expire_timeout,
-                actions: actions
-                    .chunks_exact(2)
-                    .map(|action| (action[0].into(), action[1].into()))
-                    .collect(),
-                hints: NotificationHints::new(hints),
-                app_icon,
-            })))
-        {
+        let mut data = NotificationData {
+            id,
+            app_name: app_name.into(),
+            display_name: None,
+            summary: summary.into(),
+            body: body.into(),
+            timeout: expire_timeout,
+            actions: actions
+                .chunks_exact(2)
+                .map(|action| (action[0].into(), action[1].into()))
+                .collect(),
+            hints: NotificationHints::new(hints),
+            app_icon,
+            timestamp: timestamp(),
+            rowid: None,
+            sent_id: None,
+            redacted: false,
+        };
+        desktop_entry::enrich(&mut data).await;
+
+        if let Err(e) = self.event_sender.send(Event::Notify(Box::new(data))) {
             log::error!("Error: {e}");
         }
 
         id
     }
 
-    async fn close_notification(&self, id: u32) -> zbus::fdo::Result<()> {
-        if let Err(e) = self.event_sender.send(Event::CloseNotification(id)) {
+    async fn close_notification(&self, id: u32) -> Result<(), NotificationsError> {
+        let (reply, known) = oneshot::channel();
+        if let Err(e) = self.event_sender.send(Event::CloseNotification {
+            id,
+            reply: Some(reply),
+        }) {
             log::error!("Failed to send CloseNotification({id}) event: {e}");
+            return Err(NotificationsError::Failed(e.to_string()));
         }
 
-        Ok(())
+        match known.await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(NotificationsError::InvalidId(format!(
+                "No notification with id {id}"
+            ))),
+            Err(_) => Err(NotificationsError::Failed(
+                "No reply from the daemon".into(),
+            )),
+        }
     }
 
     async fn get_server_information(
         &self,
     ) -> zbus::fdo::Result<(&'static str, &'static str, &'static str, &'static str)> {
-        Ok(("moxnotify", "mox", VERSION, "1.2"))
+        Ok(("moxnotify", "mox", VERSION, SPEC_VERSION))
     }
 
     #[zbus(signal)]
@@ -224,6 +829,13 @@ impl NotificationsImpl {
         action_key: &str,
     ) -> zbus::Result<()>;
 
+    #[zbus(signal)]
+    async fn notification_replied(
+        signal_emitter: &SignalEmitter<'_>,
+        id: u32,
+        text: &str,
+    ) -> zbus::Result<()>;
+
     #[zbus(signal)]
     async fn activation_token(
         signal_emitter: &SignalEmitter<'_>,
@@ -232,13 +844,28 @@ impl NotificationsImpl {
     ) -> zbus::Result<()>;
 }
 
+/// Errors of the `org.freedesktop.Notifications` interface.
+#[derive(Debug, zbus::DBusError)]
+#[zbus(prefix = "org.freedesktop.Notifications")]
+pub enum NotificationsError {
+    #[zbus(error)]
+    ZBus(zbus::Error),
+    /// The notification to close doesn't exist or is already closed.
+    InvalidId(String),
+    Failed(String),
+}
+
 pub async fn serve(
     event_sender: calloop::channel::Sender<Event>,
     mut emit_receiver: broadcast::Receiver<EmitEvent>,
-) -> zbus::Result<()> {
+    capabilities: Capabilities,
+    ids: IdGenerator,
+) -> Result<zbus::Connection, super::Error> {
+    let exit_sender = event_sender.clone();
     let server = NotificationsImpl {
-        next_id: 1,
+        ids,
         event_sender,
+        capabilities,
     };
 
     let conn = zbus::connection::Builder::session()?
@@ -246,52 +873,721 @@ pub async fn serve(
         .build()
         .await?;
 
-    if let Err(e) = conn
-        .request_name_with_flags(
-            "org.freedesktop.Notifications",
-            RequestNameFlags::DoNotQueue.into(),
-        )
-        .await
-    {
-        log::error!("{e}, is another daemon running?");
-        std::process::exit(0);
-    }
+    super::request_name(&conn, "org.freedesktop.Notifications").await?;
 
     let iface = conn
         .object_server()
         .interface::<_, NotificationsImpl>("/org/freedesktop/Notifications")
         .await?;
+    super::spawn_until_closed(&conn, emit_signals(iface, emit_receiver, exit_sender));
 
-    tokio::spawn(async move {
-        loop {
-            match emit_receiver.recv().await {
-                Ok(EmitEvent::ActionInvoked {
+    Ok(conn)
+}
+
+/// Emits the signals of the interface for the events the daemon sends, in the order they're
+/// sent in. Clients rely on `ActionInvoked` coming before the `NotificationClosed` that follows
+/// an action.
+async fn emit_signals(
+    iface: InterfaceRef<NotificationsImpl>,
+    mut emit_receiver: broadcast::Receiver<EmitEvent>,
+    exit_sender: calloop::channel::Sender<Event>,
+) {
+    loop {
+        match emit_receiver.recv().await {
+            Ok(EmitEvent::ActionInvoked {
+                id,
+                key: action_key,
+                token,
+            }) => {
+                log::info!("{action_key} action invoked for notification with ID: {id}.");
+
+                _ = NotificationsImpl::activation_token(iface.signal_emitter(), id, &token).await;
+
+                _ = NotificationsImpl::action_invoked(iface.signal_emitter(), id, &action_key)
+                    .await;
+            }
+            Ok(EmitEvent::NotificationReplied { id, text }) => {
+                log::info!("Notification with ID: {id} was replied to.");
+
+                _ = NotificationsImpl::notification_replied(iface.signal_emitter(), id, &text)
+                    .await;
+            }
+            Ok(EmitEvent::NotificationClosed { id, reason }) => {
+                log::info!("Notification with ID: {id} was closed. Reason: {reason}");
+
+                _ = NotificationsImpl::notification_closed(
+                    iface.signal_emitter(),
                     id,
-                    key: action_key,
-                    token,
-                }) => {
-                    log::info!("{action_key} action invoked for notification with ID: {id}.");
+                    reason as u32,
+                )
+                .await;
+            }
+            Ok(EmitEvent::Shutdown) => {
+                // The closed signals before it were awaited, so they're on the bus by now
+                _ = exit_sender.send(Event::Exit);
+            }
+            Err(RecvError::Closed) => return,
+            _ => {}
+        };
+    }
+}
 
-                    _ = NotificationsImpl::activation_token(iface.signal_emitter(), id, &token)
-                        .await;
+#[cfg(test)]
+mod tests {
+    use super::{
+        clear_waiting, count_history, create_history_table, create_snoozed_table,
+        create_state_table, create_waiting_table, load_history, load_inhibited, load_snoozed,
+        load_waiting, set_inhibited, unsnooze, Capabilities, IdGenerator, NotificationData,
+        NotificationHints, NotificationsError, NotificationsImpl, REDACTED, VERSION,
+    };
+    use crate::{config::Config, manager::Reason, EmitEvent, Event, Image, Urgency};
+    use futures_lite::StreamExt;
+    use std::{collections::HashMap, path::Path, time::Duration};
+    use tokio::sync::broadcast;
+    use zbus::zvariant::Value;
 
-                    _ = NotificationsImpl::action_invoked(iface.signal_emitter(), id, &action_key)
-                        .await;
-                }
-                Ok(EmitEvent::NotificationClosed { id, reason }) => {
-                    log::info!("Notification with ID: {id} was closed. Reason: {reason}");
+    #[test]
+    fn test_synchronous_hints() {
+        let canonical = NotificationHints::new(HashMap::from([(
+            "x-canonical-private-synchronous",
+            Value::from("volume"),
+        )]));
+        let dunst = NotificationHints::new(HashMap::from([(
+            "x-dunst-stack-tag",
+            Value::from("volume"),
+        )]));
+
+        assert_eq!(canonical.synchronous.as_deref(), Some("volume"));
+        assert_eq!(dunst.synchronous.as_deref(), Some("volume"));
+    }
+
+    #[test]
+    fn test_image_path_uri() {
+        let hints = NotificationHints::new(HashMap::from([(
+            "image-path",
+            Value::from("file:///tmp/some%20dir/image.png"),
+        )]));
+        let name = NotificationHints::new(HashMap::from([("image-path", Value::from("firefox"))]));
+
+        assert!(matches!(
+            hints.image,
+            Some(Image::File(path)) if *path == *Path::new("/tmp/some dir/image.png")
+        ));
+        assert!(matches!(name.image, Some(Image::Name(name)) if &*name == "firefox"));
+    }
+
+    #[test]
+    fn test_transient_not_persisted() {
+        let db = rusqlite::Connection::open_in_memory().unwrap();
+        db.execute(
+            "CREATE TABLE notifications (
+            rowid INTEGER PRIMARY KEY AUTOINCREMENT,
+            id INTEGER,
+            app_name TEXT,
+            app_icon TEXT,
+            summary TEXT,
+            body TEXT,
+            timeout INTEGER,
+            actions TEXT,
+            hints JSON
+        );",
+            (),
+        )
+        .unwrap();
+        // Columns added since are filled in when the daemon starts
+        create_history_table(&db).unwrap();
+
+        let mut transient = NotificationData {
+            id: 1,
+            ..Default::default()
+        };
+        transient.hints.transient = true;
+        transient.persist(&db, false).unwrap();
+
+        let mut persistent = NotificationData {
+            id: 2,
+            ..Default::default()
+        };
+        persistent.persist(&db, false).unwrap();
+
+        let ids = db
+            .prepare("SELECT id FROM notifications")
+            .unwrap()
+            .query_map([], |row| row.get::<_, u32>(0))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(ids, vec![2]);
+    }
+
+    #[test]
+    fn test_history_round_trip() {
+        let db = rusqlite::Connection::open_in_memory().unwrap();
+        create_history_table(&db).unwrap();
+
+        let mut resident = NotificationData {
+            id: 1,
+            summary: "Download finished".into(),
+            actions: vec![("open".into(), "Open".into())].into(),
+            ..Default::default()
+        };
+        resident.hints.resident = true;
+        resident.persist(&db, false).unwrap();
 
-                    _ = NotificationsImpl::notification_closed(
-                        iface.signal_emitter(),
+        // Databases from before transient notifications were skipped may still hold some
+        let mut transient = NotificationData {
+            id: 2,
+            ..Default::default()
+        };
+        transient.hints.transient = true;
+        db.execute(
+            "INSERT INTO notifications (id, app_name, summary, body, timeout, actions, hints)
+             VALUES (2, '', '', '', 0, '[]', ?1)",
+            [serde_json::to_string(&transient.hints).unwrap()],
+        )
+        .unwrap();
+
+        let history = load_history(&db, None, 10, 0).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(&*history[0].summary, "Download finished");
+        assert_eq!(&*history[0].actions[0].0, "open");
+        assert!(history[0].hints.resident);
+        assert!(!history[0].hints.transient);
+    }
+
+    #[test]
+    fn test_history_pages() {
+        let db = rusqlite::Connection::open_in_memory().unwrap();
+        create_history_table(&db).unwrap();
+
+        for (summary, body) in [
+            ("Build finished", ""),
+            ("New mail", "100% done"),
+            ("Reminder", "build_2 failed"),
+            ("New mail", "hello"),
+        ] {
+            let mut data = NotificationData {
+                summary: summary.into(),
+                body: body.into(),
+                ..Default::default()
+            };
+            data.persist(&db, false).unwrap();
+        }
+
+        let ids = |query, limit, offset| {
+            load_history(&db, query, limit, offset)
+                .unwrap()
+                .iter()
+                .map(|data| data.id)
+                .collect::<Vec<_>>()
+        };
+
+        // Entries are listed by rowid, newest first
+        assert_eq!(ids(None, 3, 0), vec![4, 3, 2]);
+        assert_eq!(ids(None, 3, 3), vec![1]);
+        assert_eq!(count_history(&db, None).unwrap(), 4);
+
+        assert_eq!(ids(Some("build"), 10, 0), vec![3, 1]);
+        assert_eq!(ids(Some("build"), 1, 1), vec![1]);
+        assert_eq!(count_history(&db, Some("mail")).unwrap(), 2);
+
+        // Wildcards are matched literally
+        assert_eq!(ids(Some("%"), 10, 0), vec![2]);
+        assert_eq!(ids(Some("d_"), 10, 0), vec![3]);
+        assert_eq!(count_history(&db, Some("_")).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_waiting_replaced_in_place() {
+        let db = rusqlite::Connection::open_in_memory().unwrap();
+        create_waiting_table(&db).unwrap();
+
+        for (id, summary) in [(1, "first"), (2, "second"), (1, "replaced")] {
+            let mut data = NotificationData {
+                id,
+                summary: summary.into(),
+                ..Default::default()
+            };
+            data.hints.transient = true;
+            data.hold(&db, false).unwrap();
+        }
+
+        let waiting = load_waiting(&db).unwrap();
+        assert_eq!(
+            waiting
+                .iter()
+                .map(|data| (data.id, &*data.summary))
+                .collect::<Vec<_>>(),
+            vec![(1, "replaced"), (2, "second")]
+        );
+        assert!(waiting[0].hints.transient);
+
+        clear_waiting(&db).unwrap();
+        assert!(load_waiting(&db).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_inhibited_round_trip() {
+        let db = rusqlite::Connection::open_in_memory().unwrap();
+        create_state_table(&db).unwrap();
+        assert!(!load_inhibited(&db).unwrap());
+
+        // Kept with nothing held back
+        set_inhibited(&db, true).unwrap();
+        assert!(load_inhibited(&db).unwrap());
+
+        set_inhibited(&db, false).unwrap();
+        assert!(!load_inhibited(&db).unwrap());
+    }
+
+    #[test]
+    fn test_snoozed_round_trip() {
+        let db = rusqlite::Connection::open_in_memory().unwrap();
+        create_snoozed_table(&db).unwrap();
+
+        for (id, due) in [(1, 300), (2, 120)] {
+            let mut data = NotificationData {
+                id,
+                summary: "snoozed".into(),
+                actions: vec![("default".into(), "Open".into())].into(),
+                ..Default::default()
+            };
+            data.hints.resident = true;
+            data.snooze(&db, due, false).unwrap();
+        }
+
+        let snoozed = load_snoozed(&db).unwrap();
+        assert_eq!(
+            snoozed
+                .iter()
+                .map(|(data, due)| (data.id, *due))
+                .collect::<Vec<_>>(),
+            vec![(2, 120), (1, 300)]
+        );
+        assert!(snoozed[0].0.hints.resident);
+        assert_eq!(&*snoozed[0].0.actions[0].0, "default");
+
+        assert!(unsnooze(&db, 2).unwrap());
+        assert!(!unsnooze(&db, 2).unwrap());
+        assert_eq!(load_snoozed(&db).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_timestamp_migration() {
+        let db = rusqlite::Connection::open_in_memory().unwrap();
+        db.execute(
+            "CREATE TABLE notifications (
+            rowid INTEGER PRIMARY KEY AUTOINCREMENT,
+            id INTEGER,
+            app_name TEXT,
+            app_icon TEXT,
+            summary TEXT,
+            body TEXT,
+            timeout INTEGER,
+            actions TEXT,
+            hints JSON
+        );",
+            (),
+        )
+        .unwrap();
+        db.execute("INSERT INTO notifications (id) VALUES (1)", ())
+            .unwrap();
+
+        create_history_table(&db).unwrap();
+        create_history_table(&db).unwrap();
+
+        let mut data = NotificationData {
+            id: 2,
+            timestamp: 1700000000,
+            ..Default::default()
+        };
+        data.persist(&db, false).unwrap();
+
+        let timestamps = db
+            .prepare("SELECT timestamp FROM notifications ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get::<_, u64>(0))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(timestamps, vec![0, 1700000000]);
+    }
+
+    #[test]
+    fn test_urgency_migration() {
+        let db = rusqlite::Connection::open_in_memory().unwrap();
+        db.execute(
+            "CREATE TABLE notifications (
+            rowid INTEGER PRIMARY KEY AUTOINCREMENT,
+            id INTEGER,
+            app_name TEXT,
+            app_icon TEXT,
+            summary TEXT,
+            body TEXT,
+            timeout INTEGER,
+            actions TEXT,
+            hints JSON,
+            timestamp INTEGER NOT NULL DEFAULT 0
+        );",
+            (),
+        )
+        .unwrap();
+        let hints = NotificationHints {
+            urgency: Urgency::Critical,
+            ..Default::default()
+        };
+        db.execute(
+            "INSERT INTO notifications (id, app_name, summary, body, timeout, actions, hints, timestamp)
+             VALUES (1, 'pagerd', 'SEV1', '', 0, '[]', ?1, 1700000000)",
+            [serde_json::to_string(&hints).unwrap()],
+        )
+        .unwrap();
+
+        create_history_table(&db).unwrap();
+        create_history_table(&db).unwrap();
+
+        let (urgency, created_at) = db
+            .query_row("SELECT urgency, created_at FROM notifications", (), |row| {
+                Ok((row.get::<_, u8>(0)?, row.get::<_, u64>(1)?))
+            })
+            .unwrap();
+        assert_eq!((urgency, created_at), (2, 1700000000));
+        assert_eq!(
+            load_history(&db, None, 10, 0).unwrap()[0].hints.urgency,
+            Urgency::Critical
+        );
+    }
+
+    #[test]
+    fn test_replacement_updates_row() {
+        let db = rusqlite::Connection::open_in_memory().unwrap();
+        create_history_table(&db).unwrap();
+
+        let mut data = NotificationData {
+            id: 7,
+            app_name: "rsync".into(),
+            summary: "Copying".into(),
+            timeout: 5000,
+            timestamp: 100,
+            ..Default::default()
+        };
+        data.persist(&db, false).unwrap();
+        assert_eq!(data.rowid, Some(1));
+
+        let mut replacement = NotificationData {
+            summary: "Copied".into(),
+            timestamp: 200,
+            hints: NotificationHints {
+                urgency: Urgency::Low,
+                ..Default::default()
+            },
+            ..data.clone()
+        };
+        replacement.persist(&db, false).unwrap();
+
+        let history = load_history(&db, None, 10, 0).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].rowid, Some(1));
+        assert_eq!(history[0].sent_id, None);
+        assert_eq!(&*history[0].summary, "Copied");
+        assert_eq!(history[0].timeout, 5000);
+        assert_eq!(history[0].timestamp, 200);
+        assert_eq!(history[0].hints.urgency, Urgency::Low);
+
+        let created_at: u64 = db
+            .query_row("SELECT created_at FROM notifications", (), |row| row.get(0))
+            .unwrap();
+        assert_eq!(created_at, 100);
+
+        // A replacement of a row that's gone starts a new one
+        db.execute("DELETE FROM notifications", ()).unwrap();
+        replacement.persist(&db, false).unwrap();
+        assert_eq!(replacement.rowid, Some(2));
+    }
+
+    #[test]
+    fn test_redacted() {
+        let db = rusqlite::Connection::open_in_memory().unwrap();
+        create_history_table(&db).unwrap();
+
+        let mut data = NotificationData {
+            id: 3,
+            app_name: "Signal".into(),
+            summary: "Alice".into(),
+            body: "See you at 5".into(),
+            ..Default::default()
+        };
+        data.persist(&db, true).unwrap();
+
+        let history = load_history(&db, None, 10, 0).unwrap();
+        assert_eq!(&*history[0].summary, REDACTED);
+        assert!(history[0].body.is_empty());
+        assert!(history[0].redacted);
+        assert_eq!(count_history(&db, Some("Alice")).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_redacted_waiting_and_snoozed() {
+        let db = rusqlite::Connection::open_in_memory().unwrap();
+        create_waiting_table(&db).unwrap();
+        create_snoozed_table(&db).unwrap();
+
+        let data = NotificationData {
+            id: 3,
+            app_name: "Signal".into(),
+            summary: "Alice".into(),
+            body: "See you at 5".into(),
+            ..Default::default()
+        };
+        data.hold(&db, true).unwrap();
+        data.snooze(&db, 120, true).unwrap();
+
+        let waiting = load_waiting(&db).unwrap();
+        let (snoozed, _) = &load_snoozed(&db).unwrap()[0];
+        for data in [&waiting[0], snoozed] {
+            assert_eq!(&*data.summary, REDACTED);
+            assert!(data.body.is_empty());
+            assert!(data.redacted);
+        }
+    }
+
+    #[test]
+    fn test_display_name_stored() {
+        let db = rusqlite::Connection::open_in_memory().unwrap();
+        create_history_table(&db).unwrap();
+        create_waiting_table(&db).unwrap();
+        create_snoozed_table(&db).unwrap();
+
+        let mut data = NotificationData {
+            id: 1,
+            app_name: "org.gnome.Nautilus".into(),
+            display_name: Some("Files".into()),
+            ..Default::default()
+        };
+        data.persist(&db, false).unwrap();
+        data.hold(&db, false).unwrap();
+        data.snooze(&db, 120, false).unwrap();
+
+        let history = load_history(&db, None, 10, 0).unwrap();
+        let waiting = load_waiting(&db).unwrap();
+        let (snoozed, _) = &load_snoozed(&db).unwrap()[0];
+        for data in [&history[0], &waiting[0], snoozed] {
+            assert_eq!(data.display_name.as_deref(), Some("Files"));
+            assert!(!data.redacted);
+        }
+    }
+
+    #[test]
+    fn test_close_unknown_id() {
+        let (event_sender, channel) = calloop::channel::channel();
+
+        // Stands in for the daemon, which only knows notification 1
+        let daemon = std::thread::spawn(move || {
+            let mut event_loop = calloop::EventLoop::<usize>::try_new().unwrap();
+            event_loop
+                .handle()
+                .insert_source(channel, |event, _, replies| {
+                    if let calloop::channel::Event::Msg(Event::CloseNotification {
                         id,
-                        reason as u32,
-                    )
-                    .await;
+                        reply: Some(reply),
+                    }) = event
+                    {
+                        _ = reply.send(id == 1);
+                        *replies += 1;
+                    }
+                })
+                .unwrap();
+
+            let mut replies = 0;
+            for _ in 0..10 {
+                event_loop
+                    .dispatch(Duration::from_millis(100), &mut replies)
+                    .unwrap();
+                if replies == 2 {
+                    break;
                 }
-                _ => {}
-            };
+            }
+        });
+
+        let server = NotificationsImpl {
+            ids: IdGenerator::default(),
+            event_sender,
+            capabilities: Capabilities::new(&Config::default(), false),
+        };
+        futures_lite::future::block_on(async {
+            assert!(server.close_notification(1).await.is_ok());
+            assert!(matches!(
+                server.close_notification(7).await,
+                Err(NotificationsError::InvalidId(_))
+            ));
+        });
+
+        daemon.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_private_bus() {
+        const PATH: &str = "/org/freedesktop/Notifications";
+        const INTERFACE: &str = "org.freedesktop.Notifications";
+
+        let server = NotificationsImpl {
+            ids: IdGenerator::default(),
+            event_sender: calloop::channel::channel().0,
+            capabilities: Capabilities::new(&Config::default(), false),
+        };
+        let (server_stream, client_stream) = tokio::net::UnixStream::pair().unwrap();
+        let guid = zbus::Guid::generate();
+        let (server_conn, client) = tokio::try_join!(
+            zbus::connection::Builder::unix_stream(server_stream)
+                .server(guid)
+                .unwrap()
+                .p2p()
+                .serve_at(PATH, server)
+                .unwrap()
+                .build(),
+            zbus::connection::Builder::unix_stream(client_stream)
+                .p2p()
+                .build(),
+        )
+        .unwrap();
+
+        let iface = server_conn
+            .object_server()
+            .interface::<_, NotificationsImpl>(PATH)
+            .await
+            .unwrap();
+        let (emit_sender, emit_receiver) = broadcast::channel(8);
+        tokio::spawn(super::emit_signals(
+            iface,
+            emit_receiver,
+            calloop::channel::channel().0,
+        ));
+
+        let reply = client
+            .call_method(
+                None::<&str>,
+                PATH,
+                Some(INTERFACE),
+                "GetServerInformation",
+                &(),
+            )
+            .await
+            .unwrap();
+        let info: (String, String, String, String) = reply.body().deserialize().unwrap();
+        assert_eq!(
+            info,
+            (
+                "moxnotify".into(),
+                "mox".into(),
+                VERSION.into(),
+                "1.3".into()
+            )
+        );
+
+        let mut messages = zbus::MessageStream::from(&client);
+        _ = emit_sender.send(EmitEvent::ActionInvoked {
+            id: 3,
+            key: "default".into(),
+            token: "token".into(),
+        });
+        _ = emit_sender.send(EmitEvent::NotificationClosed {
+            id: 3,
+            reason: Reason::DismissedByUser,
+        });
+
+        async fn signals(messages: &mut zbus::MessageStream, count: usize, id: u32) -> Vec<String> {
+            let mut signals = Vec::new();
+            while signals.len() < count {
+                let message = tokio::time::timeout(Duration::from_secs(5), messages.next())
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .unwrap();
+                if message.message_type() != zbus::message::Type::Signal {
+                    continue;
+                }
+
+                let member = message.header().member().unwrap().to_string();
+                if member == "NotificationClosed" {
+                    let closed: (u32, u32) = message.body().deserialize().unwrap();
+                    assert_eq!(closed, (id, Reason::DismissedByUser as u32));
+                }
+                signals.push(member);
+            }
+            signals
         }
-    });
 
-    Ok(())
+        assert_eq!(
+            signals(&mut messages, 3, 3).await,
+            ["ActivationToken", "ActionInvoked", "NotificationClosed"]
+        );
+
+        // A transient notification that was replied to closes after the reply
+        _ = emit_sender.send(EmitEvent::NotificationReplied {
+            id: 4,
+            text: "hello".into(),
+        });
+        _ = emit_sender.send(EmitEvent::NotificationClosed {
+            id: 4,
+            reason: Reason::DismissedByUser,
+        });
+        assert_eq!(
+            signals(&mut messages, 2, 4).await,
+            ["NotificationReplied", "NotificationClosed"]
+        );
+    }
+
+    #[test]
+    fn test_capabilities_without_audio() {
+        let config = Config::default();
+
+        let with_audio = Capabilities::new(&config, true).list();
+        let without_audio = Capabilities::new(&config, false).list();
+
+        assert!(with_audio.contains(&"sound"));
+        assert!(!without_audio.contains(&"sound"));
+        assert_eq!(with_audio.len(), without_audio.len() + 1);
+    }
+
+    #[test]
+    fn test_capabilities_from_config() {
+        let mut config = Config::default();
+        config.general.markup = false;
+        config.general.history.size = 0;
+
+        let capabilities = Capabilities::new(&config, true).list();
+
+        assert!(!capabilities.contains(&"body-markup"));
+        assert!(!capabilities.contains(&"persistence"));
+        assert!(capabilities.contains(&"body-hyperlinks"));
+        assert!(capabilities.contains(&"action-icons"));
+    }
+
+    #[test]
+    fn test_ids_skip_active_after_wraparound() {
+        let ids = IdGenerator::default();
+        // Long lived notifications with ids the counter runs into after wrapping around
+        let mut active = vec![1, 2, 3, 100, 1000];
+        ids.set_in_use(active.iter().copied());
+        ids.0.lock().unwrap().last = u32::MAX - (1 << 15);
+
+        for i in 0..1u32 << 16 {
+            // Every third notification replaces one of the active ones and keeps its id
+            if i % 3 != 0 {
+                let id = ids.next();
+                assert_ne!(id, 0);
+                assert!(!active.contains(&id), "{id} handed out while in use");
+                active.push(id);
+            }
+
+            if active.len() > 16 {
+                active.remove(5);
+            }
+            ids.set_in_use(active.iter().copied());
+        }
+
+        assert_eq!(active[..5], [1, 2, 3, 100, 1000]);
+        let last = ids.0.lock().unwrap().last;
+        assert!(last < 1 << 16);
+    }
 }
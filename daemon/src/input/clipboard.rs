@@ -0,0 +1,90 @@
+use super::Seats;
+use crate::Moxnotify;
+use std::{fs::File, io::Write, sync::Arc};
+use wayland_client::{
+    delegate_noop, event_created_child,
+    protocol::{wl_data_device, wl_data_offer, wl_data_source},
+    Connection, Dispatch, Proxy, QueueHandle,
+};
+
+/// Mime types copied text is offered as, older clients still ask for the X11 name.
+const MIME_TYPES: [&str; 3] = ["text/plain;charset=utf-8", "text/plain", "UTF8_STRING"];
+
+impl Seats {
+    /// Sets `text` as the clipboard selection of `seat`, the compositor only accepts it with the
+    /// serial of a recent key press.
+    pub fn copy(&mut self, qh: &QueueHandle<Moxnotify>, seat: u32, text: Arc<str>) {
+        let Some(manager) = self.data_device_manager.as_ref() else {
+            log::warn!("Clipboard unavailable, compositor lacks wl_data_device_manager");
+            return;
+        };
+        let Some(seat) = self.seats.iter_mut().find(|s| s.id == seat) else {
+            return;
+        };
+        let Some(serial) = seat.keyboard.as_ref().map(|keyboard| keyboard.serial) else {
+            return;
+        };
+
+        let data_device = seat
+            .data_device
+            .get_or_insert_with(|| manager.get_data_device(&seat.wl_seat, qh, ()));
+
+        // The source owns the text until the compositor cancels it for another selection
+        let source = manager.create_data_source(qh, text);
+        MIME_TYPES
+            .into_iter()
+            .for_each(|mime_type| source.offer(mime_type.into()));
+        data_device.set_selection(Some(&source), serial);
+    }
+}
+
+impl Dispatch<wl_data_source::WlDataSource, Arc<str>> for Moxnotify {
+    fn event(
+        _: &mut Self,
+        source: &wl_data_source::WlDataSource,
+        event: <wl_data_source::WlDataSource as Proxy>::Event,
+        text: &Arc<str>,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_data_source::Event::Send { fd, .. } => {
+                // The receiving end may read slowly, so it's written off the event loop
+                let text = Arc::clone(text);
+                rayon::spawn(move || {
+                    if let Err(e) = File::from(fd).write_all(text.as_bytes()) {
+                        log::warn!("Failed to send clipboard contents: {e}");
+                    }
+                });
+            }
+            wl_data_source::Event::Cancelled => source.destroy(),
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<wl_data_device::WlDataDevice, ()> for Moxnotify {
+    fn event(
+        _: &mut Self,
+        _: &wl_data_device::WlDataDevice,
+        event: <wl_data_device::WlDataDevice as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        // Nothing is ever pasted, offers are let go of as soon as they're announced
+        match event {
+            wl_data_device::Event::Selection { id: Some(offer) }
+            | wl_data_device::Event::Enter {
+                id: Some(offer), ..
+            } => offer.destroy(),
+            _ => {}
+        }
+    }
+
+    event_created_child!(Moxnotify, wl_data_device::WlDataDevice, [
+        wl_data_device::EVT_DATA_OFFER_OPCODE => (wl_data_offer::WlDataOffer, ()),
+    ]);
+}
+
+delegate_noop!(Moxnotify: ignore wl_data_offer::WlDataOffer);
@@ -1,78 +1,162 @@
 use crate::{
-    config::keymaps::{self, Key, KeyAction, KeyWithModifiers, Keys, Modifiers},
+    config::keymaps::{
+        self, Key, KeyAction, KeyWithModifiers, Keys, Match, Modifiers, SpecialKeyCode,
+    },
     manager::Reason,
-    History, Moxnotify,
+    EmitEvent, History, Moxnotify,
 };
 use calloop::{
     timer::{TimeoutAction, Timer},
-    RegistrationToken,
+    LoopHandle, RegistrationToken,
+};
+use std::{
+    sync::{atomic::Ordering, Arc},
+    time::{Duration, Instant},
 };
-use std::{sync::atomic::Ordering, time::Duration};
 use wayland_client::{
     protocol::{wl_keyboard, wl_seat},
-    Connection, Dispatch, QueueHandle, WEnum,
+    Connection, Dispatch, Proxy, QueueHandle, WEnum,
 };
 use xkbcommon::xkb::{Context, Keymap, State};
 
+/// How long a binding that a longer one starts with waits for the rest of it.
+const SEQUENCE_TIMEOUT: Duration = Duration::from_secs(1);
+
 struct Xkb {
     context: Context,
     state: Option<State>,
 }
 
 pub struct Keyboard {
-    _wl_keyboard: wl_keyboard::WlKeyboard,
+    wl_keyboard: wl_keyboard::WlKeyboard,
     pub repeat: RepeatInfo,
     xkb: Xkb,
     pub key_combination: Keys,
+    /// Runs out when the keys typed so far wait for more that don't come.
+    sequence_timer: Option<RegistrationToken>,
     modifiers: Modifiers,
+    /// Serial of the last key press or keyboard enter, needed to set the clipboard selection.
+    pub serial: u32,
 }
 
 #[derive(Default)]
 pub struct RepeatInfo {
     pub key: Option<Key>,
+    /// Keycode of the key being held, only letting go of that one stops the repeat.
+    keycode: u32,
+    /// Repeats per second as the compositor asks for, 0 turning repeat off.
     rate: i32,
+    /// Milliseconds a key is held before it starts repeating.
     delay: i32,
+    schedule: Option<RepeatSchedule>,
     registration_token: Option<RegistrationToken>,
 }
 
+/// When the repeats of a held key are due, counted from the moment it was pressed so that
+/// timers firing late don't slow the repeat down.
+#[derive(Clone, Copy, Debug)]
+struct RepeatSchedule {
+    /// Moment the first repeat is due at.
+    start: Instant,
+    interval: Duration,
+    fired: u32,
+}
+
+impl RepeatSchedule {
+    /// None when the compositor turned repeat off.
+    fn new(rate: i32, delay: i32, pressed_at: Instant) -> Option<Self> {
+        let rate = u32::try_from(rate).ok().filter(|rate| *rate > 0)?;
+
+        Some(Self {
+            start: pressed_at + Duration::from_millis(delay.max(0) as u64),
+            interval: Duration::from_secs(1) / rate,
+            fired: 0,
+        })
+    }
+
+    /// Moment the next repeat is due at.
+    fn next_at(&self) -> Instant {
+        self.start + self.interval * self.fired
+    }
+
+    /// How many repeats came due by `now` since the last call.
+    fn due(&mut self, now: Instant) -> u32 {
+        let Some(elapsed) = now.checked_duration_since(self.start) else {
+            return 0;
+        };
+
+        let total = (elapsed.as_nanos() / self.interval.as_nanos()) as u32 + 1;
+        let due = total.saturating_sub(self.fired);
+        self.fired = self.fired.max(total);
+
+        due
+    }
+}
+
 impl Keyboard {
-    pub fn new(qh: &QueueHandle<Moxnotify>, wl_seat: &wl_seat::WlSeat) -> Self {
-        let wl_keyboard = wl_seat.get_keyboard(qh, ());
+    pub fn new(qh: &QueueHandle<Moxnotify>, wl_seat: &wl_seat::WlSeat, seat: u32) -> Self {
+        let wl_keyboard = wl_seat.get_keyboard(qh, seat);
 
         let xkb_context = Context::new(0);
 
         Self {
             key_combination: Keys(Vec::new()),
+            sequence_timer: None,
             xkb: Xkb {
                 context: xkb_context,
                 state: None,
             },
-            _wl_keyboard: wl_keyboard,
+            wl_keyboard,
             repeat: RepeatInfo::default(),
             modifiers: Modifiers::default(),
+            serial: 0,
+        }
+    }
+
+    /// Stops key repeat and lets go of the wl_keyboard once the seat loses it.
+    pub fn release(mut self, loop_handle: &LoopHandle<'static, Moxnotify>) {
+        self.stop_repeat(loop_handle);
+        if let Some(token) = self.sequence_timer.take() {
+            loop_handle.remove(token);
+        }
+        if self.wl_keyboard.version() >= 3 {
+            self.wl_keyboard.release();
+        }
+    }
+
+    /// Forgets the keys typed so far, including the one being repeated.
+    pub fn reset(&mut self) {
+        self.key_combination.clear();
+        self.repeat.key = None;
+    }
+
+    fn stop_repeat(&mut self, loop_handle: &LoopHandle<'static, Moxnotify>) {
+        self.repeat.key = None;
+        self.repeat.schedule = None;
+        if let Some(token) = self.repeat.registration_token.take() {
+            loop_handle.remove(token);
         }
     }
 }
 
-impl Dispatch<wl_keyboard::WlKeyboard, ()> for Moxnotify {
+impl Dispatch<wl_keyboard::WlKeyboard, u32> for Moxnotify {
     fn event(
         state: &mut Self,
         _: &wl_keyboard::WlKeyboard,
         event: <wl_keyboard::WlKeyboard as wayland_client::Proxy>::Event,
-        _: &(),
+        seat: &u32,
         _: &Connection,
         _: &QueueHandle<Self>,
     ) {
+        let seat = *seat;
+        let Some(keyboard) = state.seats.keyboard_mut(seat) else {
+            return;
+        };
+
         match event {
             wl_keyboard::Event::Keymap { format, fd, size } => {
                 let keymap_result = unsafe {
-                    Keymap::new_from_fd(
-                        &state.seat.keyboard.xkb.context,
-                        fd,
-                        size as usize,
-                        format.into(),
-                        0,
-                    )
+                    Keymap::new_from_fd(&keyboard.xkb.context, fd, size as usize, format.into(), 0)
                 }
                 .ok()
                 .flatten();
@@ -80,7 +164,7 @@ impl Dispatch<wl_keyboard::WlKeyboard, ()> for Moxnotify {
                 match keymap_result {
                     Some(keymap) => {
                         let xkb_state = State::new(&keymap);
-                        state.seat.keyboard.xkb.state = Some(xkb_state);
+                        keyboard.xkb.state = Some(xkb_state);
                     }
                     None => {
                         log::error!("Keymap data was unexpectedly empty.");
@@ -94,7 +178,7 @@ impl Dispatch<wl_keyboard::WlKeyboard, ()> for Moxnotify {
                 mods_locked,
                 group,
             } => {
-                if let Some(xkb_state) = state.seat.keyboard.xkb.state.as_mut() {
+                if let Some(xkb_state) = keyboard.xkb.state.as_mut() {
                     xkb_state.update_mask(mods_depressed, mods_latched, mods_locked, 0, 0, group);
 
                     let ctrl_active = xkb_state
@@ -104,7 +188,7 @@ impl Dispatch<wl_keyboard::WlKeyboard, ()> for Moxnotify {
                     let meta_active =
                         xkb_state.mod_name_is_active("Mod4", xkbcommon::xkb::STATE_MODS_EFFECTIVE);
 
-                    state.seat.keyboard.modifiers = Modifiers {
+                    keyboard.modifiers = Modifiers {
                         control: ctrl_active,
                         alt: alt_active,
                         meta: meta_active,
@@ -124,7 +208,7 @@ impl Dispatch<wl_keyboard::WlKeyboard, ()> for Moxnotify {
                 }
             }
             wl_keyboard::Event::Key {
-                serial: _,
+                serial,
                 time: _,
                 key,
                 state: WEnum::Value(value),
@@ -135,204 +219,529 @@ impl Dispatch<wl_keyboard::WlKeyboard, ()> for Moxnotify {
 
                 match value {
                     wl_keyboard::KeyState::Released => {
-                        state.seat.keyboard.repeat.key = None;
-                        if let Some(xkb_state) = state.seat.keyboard.xkb.state.as_ref() {
-                            if let Some(key) = Key::from_keycode(xkb_state, keycode.into()) {
-                                let key_with_modifiers = KeyWithModifiers {
-                                    key,
-                                    modifiers: state.seat.keyboard.modifiers,
-                                };
-
-                                if Keys(vec![key_with_modifiers])
-                                    != state.seat.keyboard.key_combination
-                                {
-                                    return;
-                                }
-                            }
-                        }
-
-                        if let Some(token) = state.seat.keyboard.repeat.registration_token.take() {
-                            state.loop_handle.remove(token);
+                        if keyboard.repeat.keycode == keycode {
+                            keyboard.stop_repeat(&state.loop_handle);
                         }
                     }
                     wl_keyboard::KeyState::Pressed => {
-                        if let Some(xkb_state) = state.seat.keyboard.xkb.state.as_ref() {
-                            let key = Key::from_keycode(xkb_state, keycode.into());
-                            state.seat.keyboard.repeat.key = key;
-                            if let Some(key) = key {
-                                let key_with_modifiers = KeyWithModifiers {
-                                    key,
-                                    modifiers: state.seat.keyboard.modifiers,
-                                };
-                                state.seat.keyboard.key_combination.push(key_with_modifiers);
-                            }
+                        keyboard.serial = serial;
+                        // A new key takes over the repeat of the one held before
+                        keyboard.stop_repeat(&state.loop_handle);
+
+                        let Some(xkb_state) = keyboard.xkb.state.as_ref() else {
+                            return;
+                        };
+                        let repeats = xkb_state.get_keymap().key_repeats(keycode.into());
+                        if let Some(key) = Key::from_keycode(xkb_state, keycode.into()) {
+                            let key_with_modifiers = KeyWithModifiers {
+                                key,
+                                modifiers: keyboard.modifiers,
+                            };
+                            keyboard.key_combination.push(key_with_modifiers);
 
-                            if xkb_state.get_keymap().key_repeats(keycode.into()) {
-                                if let Some(token) =
-                                    state.seat.keyboard.repeat.registration_token.take()
-                                {
-                                    state.loop_handle.remove(token);
-                                }
-
-                                let timer = Timer::from_duration(Duration::from_millis(
-                                    state.seat.keyboard.repeat.delay as u64,
-                                ));
-                                let rate = (1000 / state.seat.keyboard.repeat.rate) as u64;
-                                state.seat.keyboard.repeat.registration_token = state
-                                    .loop_handle
-                                    .insert_source(timer, move |_, _, moxnotify| {
-                                        if let Some(key) = moxnotify.seat.keyboard.repeat.key {
-                                            let key_with_modifiers = KeyWithModifiers {
-                                                key,
-                                                modifiers: moxnotify.seat.keyboard.modifiers,
-                                            };
-                                            moxnotify
-                                                .seat
-                                                .keyboard
-                                                .key_combination
-                                                .push(key_with_modifiers);
-                                        } else {
-                                            return TimeoutAction::Drop;
-                                        }
-
-                                        if moxnotify.handle_key().is_err() {
-                                            return TimeoutAction::Drop;
-                                        }
-                                        TimeoutAction::ToDuration(Duration::from_millis(rate))
-                                    })
-                                    .ok();
-                            } else if let Some(token) =
-                                state.seat.keyboard.repeat.registration_token
-                            {
-                                state.loop_handle.remove(token);
+                            if repeats && state.repeats(key_with_modifiers) {
+                                state.start_repeat(seat, keycode, key);
                             }
                         }
 
-                        _ = state.handle_key();
+                        _ = state.handle_key(seat);
                     }
                     _ => unreachable!(),
                 }
             }
             wl_keyboard::Event::RepeatInfo { rate, delay } => {
-                state.seat.keyboard.repeat.delay = delay;
-                state.seat.keyboard.repeat.rate = rate;
+                keyboard.repeat.delay = delay;
+                keyboard.repeat.rate = rate;
+            }
+            wl_keyboard::Event::Enter { serial, .. } => {
+                keyboard.serial = serial;
+                state.seats.active = Some(seat);
             }
+            wl_keyboard::Event::Leave { .. } => keyboard.stop_repeat(&state.loop_handle),
             _ => {}
         }
     }
 }
 
 impl Moxnotify {
-    fn handle_key(&mut self) -> anyhow::Result<()> {
-        if !self
-            .config
-            .keymaps
-            .matches(&self.seat.keyboard.key_combination)
-        {
-            let len = self.seat.keyboard.key_combination.len().saturating_sub(1);
-            self.seat.keyboard.key_combination.drain(..len);
+    /// Whether holding down `key` keeps doing what pressing it does: typing a reply, or
+    /// running a binding of an action that [`KeyAction::repeats`].
+    fn repeats(&self, key: KeyWithModifiers) -> bool {
+        let mode = self.notifications.ui_state.mode.load(Ordering::Relaxed);
+        if mode == keymaps::Mode::Insert {
+            return !matches!(
+                key.key,
+                Key::SpecialKey(SpecialKeyCode::Escape | SpecialKeyCode::Enter)
+            );
         }
 
-        log::debug!("key‑combo => {}", self.seat.keyboard.key_combination);
-
-        if let Some(key_combination) = self.config.keymaps.iter().find(|keymap| {
-            keymap.keys == self.seat.keyboard.key_combination
-                && keymap.mode == self.notifications.ui_state.mode.load(Ordering::Relaxed)
-        }) {
-            log::debug!("Action executed: {:?}", key_combination.action);
-            self.seat.keyboard.key_combination.clear();
-            match key_combination.action {
-                KeyAction::Noop => {}
-                KeyAction::NextNotification => self.notifications.next(),
-                KeyAction::PreviousNotification => self.notifications.prev(),
-                KeyAction::FirstNotification => {
-                    if let Some(notification) = self.notifications.notifications().first() {
-                        self.notifications.select(notification.id());
-                        self.notifications.prev();
-                        self.notifications.next();
-                    }
+        match self.config.keymaps.find(&Keys(vec![key]), mode) {
+            Match::Complete(key_combination, _) => key_combination.action.repeats(),
+            _ => false,
+        }
+    }
+
+    /// Repeats `key`, just pressed on `seat`, for as long as it's held, with the delay and rate
+    /// the compositor asked for.
+    fn start_repeat(&mut self, seat: u32, keycode: u32, key: Key) {
+        let Some(keyboard) = self.seats.keyboard_mut(seat) else {
+            return;
+        };
+        let Some(schedule) =
+            RepeatSchedule::new(keyboard.repeat.rate, keyboard.repeat.delay, Instant::now())
+        else {
+            return;
+        };
+
+        keyboard.repeat.key = Some(key);
+        keyboard.repeat.keycode = keycode;
+        keyboard.repeat.schedule = Some(schedule);
+        keyboard.repeat.registration_token = self
+            .loop_handle
+            .insert_source(
+                Timer::from_deadline(schedule.next_at()),
+                move |_, _, moxnotify| moxnotify.repeat_key(seat),
+            )
+            .ok();
+    }
+
+    /// Runs the key held on `seat` as many times as came due, until it's let go of or stops
+    /// doing something that repeats.
+    fn repeat_key(&mut self, seat: u32) -> TimeoutAction {
+        let Some(keyboard) = self.seats.keyboard_mut(seat) else {
+            return TimeoutAction::Drop;
+        };
+        let (Some(key), Some(mut schedule)) = (keyboard.repeat.key, keyboard.repeat.schedule)
+        else {
+            keyboard.repeat.schedule = None;
+            keyboard.repeat.registration_token = None;
+            return TimeoutAction::Drop;
+        };
+
+        let count = schedule.due(Instant::now());
+        keyboard.repeat.schedule = Some(schedule);
+        let key = KeyWithModifiers {
+            key,
+            modifiers: keyboard.modifiers,
+        };
+
+        let mode = self.notifications.ui_state.mode.load(Ordering::Relaxed);
+        let config = Arc::clone(&self.config);
+        let result = match mode {
+            _ if count == 0 => Ok(()),
+            keymaps::Mode::Insert if self.repeats(key) => (0..count).try_for_each(|_| {
+                if let Some(keyboard) = self.seats.keyboard_mut(seat) {
+                    keyboard.key_combination.push(key);
                 }
-                KeyAction::LastNotification => {
-                    if let Some(notification) = self.notifications.notifications().last() {
-                        self.notifications.select(notification.id());
-                        self.notifications.next();
-                        self.notifications.prev();
-                    }
+                self.handle_reply_key(seat)
+            }),
+            _ => match config.keymaps.find(&Keys(vec![key]), mode) {
+                Match::Complete(key_combination, _) if key_combination.action.repeats() => {
+                    self.run_keymap(seat, key_combination.action.clone(), count)
                 }
-                KeyAction::DismissNotification => {
-                    if let Some(id) = self.notifications.selected_id() {
-                        self.dismiss_by_id(id, Some(Reason::DismissedByUser));
-                        return Ok(());
+                _ => Err(anyhow::anyhow!("{key} doesn't repeat in this mode")),
+            },
+        };
+
+        if let Err(e) = result {
+            log::debug!("Stopped repeating: {e}");
+            if let Some(keyboard) = self.seats.keyboard_mut(seat) {
+                keyboard.repeat.key = None;
+                keyboard.repeat.schedule = None;
+                keyboard.repeat.registration_token = None;
+            }
+            return TimeoutAction::Drop;
+        }
+
+        TimeoutAction::ToInstant(schedule.next_at())
+    }
+
+    /// Runs the keymap matching what was typed on the keyboard of `seat`.
+    fn handle_key(&mut self, seat: u32) -> anyhow::Result<()> {
+        let escape = self
+            .seats
+            .keyboard_mut(seat)
+            .and_then(|keyboard| keyboard.key_combination.last())
+            .is_some_and(|key| matches!(key.key, Key::SpecialKey(SpecialKeyCode::Escape)));
+        // Escape lets go of a notification being dragged before it does anything else
+        if escape && self.cancel_drag() {
+            if let Some(keyboard) = self.seats.keyboard_mut(seat) {
+                keyboard.key_combination.clear();
+            }
+            return Ok(());
+        }
+
+        if self.notifications.ui_state.mode.load(Ordering::Relaxed) == keymaps::Mode::Insert {
+            return self.handle_reply_key(seat);
+        }
+
+        let Some(keyboard) = self.seats.keyboard_mut(seat) else {
+            return Ok(());
+        };
+        if let Some(token) = keyboard.sequence_timer.take() {
+            self.loop_handle.remove(token);
+        }
+
+        let mode = self.notifications.ui_state.mode.load(Ordering::Relaxed);
+        let config = Arc::clone(&self.config);
+        let mut found = config.keymaps.find(&keyboard.key_combination, mode);
+        // A key that doesn't continue the sequence may start a new one
+        if found == Match::None && keyboard.key_combination.len() > 1 {
+            let len = keyboard.key_combination.len() - 1;
+            keyboard.key_combination.drain(..len);
+            found = config.keymaps.find(&keyboard.key_combination, mode);
+        }
+
+        log::debug!("key‑combo => {}", keyboard.key_combination);
+
+        match found {
+            Match::Complete(key_combination, count) => {
+                keyboard.key_combination.clear();
+                self.run_keymap(seat, key_combination.action.clone(), count)
+            }
+            Match::Ambiguous(..) | Match::Pending => {
+                keyboard.sequence_timer = self
+                    .loop_handle
+                    .insert_source(
+                        Timer::from_duration(SEQUENCE_TIMEOUT),
+                        move |_, _, moxnotify| {
+                            moxnotify.sequence_timeout(seat);
+                            TimeoutAction::Drop
+                        },
+                    )
+                    .ok();
+                Ok(())
+            }
+            Match::None => {
+                let key = keyboard.key_combination.pop();
+                keyboard.key_combination.clear();
+                if let (
+                    keymaps::Mode::Hint,
+                    Some(KeyWithModifiers {
+                        key: Key::Character(c),
+                        ..
+                    }),
+                ) = (mode, key)
+                {
+                    self.notifications.type_hint(c);
+                    self.update_surface_size();
+                    _ = self.render_surfaces();
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Fires the binding typed on `seat` that a longer one starts with, once nothing else
+    /// followed it in time. Sequences left unfinished are dropped.
+    fn sequence_timeout(&mut self, seat: u32) {
+        let Some(keyboard) = self.seats.keyboard_mut(seat) else {
+            return;
+        };
+        keyboard.sequence_timer = None;
+
+        let mode = self.notifications.ui_state.mode.load(Ordering::Relaxed);
+        let config = Arc::clone(&self.config);
+        let found = config.keymaps.find(&keyboard.key_combination, mode);
+        keyboard.key_combination.clear();
+
+        if let Match::Ambiguous(key_combination, count) = found {
+            if let Err(e) = self.run_keymap(seat, key_combination.action.clone(), count) {
+                log::error!("Failed to run keymap: {e}");
+            }
+        }
+    }
+
+    /// Runs `action`, `count` times where repeating it makes sense.
+    fn run_keymap(&mut self, seat: u32, action: KeyAction, count: u32) -> anyhow::Result<()> {
+        log::debug!("Action executed: {action:?}");
+        match action {
+            KeyAction::Noop => {}
+            KeyAction::NextNotification => self.notifications.next_n(count),
+            KeyAction::PreviousNotification => self.notifications.prev_n(count),
+            KeyAction::FirstNotification => {
+                let first = self.notifications.displayed().next().map(|n| n.id());
+                if let Some(id) = first {
+                    self.notifications.select(id);
+                    self.notifications.prev();
+                    self.notifications.next();
+                }
+            }
+            KeyAction::LastNotification => {
+                let last = self.notifications.displayed().next_back().map(|n| n.id());
+                if let Some(id) = last {
+                    self.notifications.select(id);
+                    self.notifications.next();
+                    self.notifications.prev();
+                }
+            }
+            KeyAction::DismissNotification => {
+                if let Some(id) = self.notifications.selected_id() {
+                    self.dismiss_by_id(id, Some(Reason::DismissedByUser));
+                    return Ok(());
+                }
+            }
+            KeyAction::DismissApp => {
+                let app_name = self
+                    .notifications
+                    .selected_notification()
+                    .map(|notification| Arc::clone(&notification.data.app_name));
+                if let Some(app_name) = app_name {
+                    self.dismiss_app(&app_name);
+                    if self.notifications.selected_id().is_none() {
+                        if let Some(surface) = self
+                            .surfaces
+                            .iter_mut()
+                            .find(|surface| surface.focus_reason.is_some())
+                        {
+                            surface.unfocus();
+                        }
+                        if let Some(keyboard) = self.seats.keyboard_mut(seat) {
+                            keyboard.reset();
+                        }
                     }
+                    return Ok(());
                 }
-                KeyAction::Unfocus => {
-                    if let Some(surface) = self.surface.as_mut() {
-                        surface.unfocus();
-                        self.seat.keyboard.key_combination.clear();
-                        self.notifications.deselect();
-                        self.seat.keyboard.repeat.key = None;
+            }
+            KeyAction::Unfocus => {
+                if let Some(surface) = self
+                    .surfaces
+                    .iter_mut()
+                    .find(|surface| surface.focus_reason.is_some())
+                {
+                    surface.unfocus();
+                    if let Some(keyboard) = self.seats.keyboard_mut(seat) {
+                        keyboard.reset();
                     }
+                    self.notifications.deselect();
                 }
-                KeyAction::HintMode => self
-                    .notifications
+            }
+            KeyAction::HintMode => {
+                self.notifications.reset_hint();
+                self.notifications
                     .ui_state
                     .mode
-                    .store(keymaps::Mode::Hint, Ordering::Relaxed),
-                KeyAction::ShowHistory => self.handle_app_event(crate::Event::ShowHistory)?,
-                KeyAction::HideHistory => {
-                    self.handle_app_event(crate::Event::HideHistory)?;
-                    self.seat.keyboard.key_combination.clear();
-                    self.seat.keyboard.repeat.key = None;
+                    .store(keymaps::Mode::Hint, Ordering::Relaxed);
+            }
+            KeyAction::ShowHistory => self.handle_app_event(crate::Event::ShowHistory)?,
+            KeyAction::HideHistory => {
+                self.handle_app_event(crate::Event::HideHistory)?;
+                if let Some(keyboard) = self.seats.keyboard_mut(seat) {
+                    keyboard.reset();
                 }
-                KeyAction::ToggleHistory => {
-                    match self.history {
-                        History::Shown => {
-                            self.handle_app_event(crate::Event::HideHistory)?;
-                            self.seat.keyboard.key_combination.clear();
-                            self.seat.keyboard.repeat.key = None;
+            }
+            KeyAction::ToggleHistory => {
+                match self.history {
+                    History::Shown => {
+                        self.handle_app_event(crate::Event::HideHistory)?;
+                        if let Some(keyboard) = self.seats.keyboard_mut(seat) {
+                            keyboard.reset();
                         }
-                        History::Hidden => self.handle_app_event(crate::Event::ShowHistory)?,
-                    };
+                    }
+                    History::Hidden => self.handle_app_event(crate::Event::ShowHistory)?,
+                };
+            }
+            KeyAction::HistoryPageNext => _ = self.turn_history_page(true)?,
+            KeyAction::HistoryPagePrev => _ = self.turn_history_page(false)?,
+            KeyAction::Uninhibit => self.handle_app_event(crate::Event::Uninhibit)?,
+            KeyAction::Ihibit => self.handle_app_event(crate::Event::Inhibit)?,
+            KeyAction::ToggleInhibit => match self.notifications.inhibit_state().manual {
+                true => self.handle_app_event(crate::Event::Uninhibit)?,
+                false => self.handle_app_event(crate::Event::Inhibit)?,
+            },
+            KeyAction::ToggleCompact => self.handle_app_event(crate::Event::ToggleCompact)?,
+            KeyAction::Mute => {
+                if let Some(audio) = self.audio.as_mut() {
+                    audio.mute();
                 }
-                KeyAction::Uninhibit => self.notifications.uninhibit(),
-                KeyAction::Ihibit => self.notifications.inhibit(),
-                KeyAction::ToggleInhibit => match self.notifications.inhibited() {
-                    true => self.notifications.uninhibit(),
-                    false => self.notifications.inhibit(),
-                },
-                KeyAction::Mute => {
-                    self.audio.mute();
+            }
+            KeyAction::Unmute => {
+                if let Some(audio) = self.audio.as_mut() {
+                    audio.unmute();
                 }
-                KeyAction::Unmute => {
-                    self.audio.unmute();
+            }
+            KeyAction::ToggleMute => {
+                if let Some(audio) = self.audio.as_mut() {
+                    match audio.muted() {
+                        true => audio.unmute(),
+                        false => audio.mute(),
+                    }
                 }
-                KeyAction::ToggleMute => match self.audio.muted() {
-                    true => self.audio.unmute(),
-                    false => self.audio.mute(),
-                },
-                KeyAction::NormalMode => {
+            }
+            KeyAction::Reply => {
+                if self.history == History::Hidden && self.notifications.start_reply() {
                     self.notifications
                         .ui_state
                         .mode
-                        .store(keymaps::Mode::Normal, Ordering::Relaxed);
+                        .store(keymaps::Mode::Insert, Ordering::Relaxed);
+                }
+            }
+            KeyAction::ToggleGroup => {
+                self.notifications.toggle_group();
+                self.update_surface_size();
+            }
+            KeyAction::ToggleExpand => {
+                if let Some(id) = self.notifications.selected_id() {
+                    self.handle_app_event(crate::Event::ToggleExpand(id))?;
+                }
+            }
+            KeyAction::Pin => {
+                if let Some(id) = self.notifications.selected_id() {
+                    self.handle_app_event(crate::Event::TogglePin(id))?;
+                }
+            }
+            KeyAction::FocusNextElement => {
+                if let Some(notification) = self.notifications.selected_notification_mut() {
+                    notification.buttons.focus_next();
+                }
+            }
+            KeyAction::FocusPrevElement => {
+                if let Some(notification) = self.notifications.selected_notification_mut() {
+                    notification.buttons.focus_prev();
+                }
+            }
+            KeyAction::ActivateElement => {
+                if let Some(notification) = self.notifications.selected_notification_mut() {
+                    notification.buttons.activate_focused();
+                }
+            }
+            KeyAction::CopyBody => {
+                if let Some(notification) = self.notifications.selected_notification() {
+                    let body = notification.body.text();
+                    self.seats.copy(&self.qh, seat, body.into());
+                }
+            }
+            KeyAction::CopySummary => {
+                if let Some(notification) = self.notifications.selected_notification() {
+                    let summary = notification.data.summary.as_ref().into();
+                    self.seats.copy(&self.qh, seat, summary);
                 }
             }
-        } else {
-            let combination = self.seat.keyboard.key_combination.to_string();
-            if let Some(notification) = self.notifications.selected_notification_mut() {
-                notification.buttons.hint(&combination);
+            KeyAction::Snooze => {
+                if let Some(id) = self.notifications.selected_id() {
+                    self.snooze(id, self.config.general.snooze_minutes)?;
+                    return Ok(());
+                }
+            }
+            KeyAction::OpenFirstLink => {
+                self.handle_app_event(crate::Event::OpenLink {
+                    id: None,
+                    reply: None,
+                })?;
+                return Ok(());
+            }
+            KeyAction::NormalMode => {
+                self.notifications.reset_hint();
+                self.notifications
+                    .ui_state
+                    .mode
+                    .store(keymaps::Mode::Normal, Ordering::Relaxed);
             }
         }
 
         self.update_surface_size();
-        if let Some(surface) = self.surface.as_mut() {
-            _ = surface.render(
-                &self.wgpu_state.device,
-                &self.wgpu_state.queue,
-                &self.notifications,
-            );
+        _ = self.render_surfaces();
+
+        Ok(())
+    }
+
+    /// Feeds keys straight into the reply input, keymaps don't apply while typing.
+    fn handle_reply_key(&mut self, seat: u32) -> anyhow::Result<()> {
+        let Some(keyboard) = self.seats.keyboard_mut(seat) else {
+            return Ok(());
+        };
+        let Some(key) = keyboard.key_combination.pop() else {
+            return Ok(());
+        };
+        keyboard.key_combination.clear();
+
+        match key.key {
+            Key::SpecialKey(SpecialKeyCode::Escape) => {
+                self.notifications.take_reply();
+                self.notifications
+                    .ui_state
+                    .mode
+                    .store(keymaps::Mode::Normal, Ordering::Relaxed);
+            }
+            Key::SpecialKey(SpecialKeyCode::Enter) => {
+                self.notifications
+                    .ui_state
+                    .mode
+                    .store(keymaps::Mode::Normal, Ordering::Relaxed);
+
+                if let Some((id, reply)) = self.notifications.take_reply() {
+                    log::info!("Replying to notification, id: {id}");
+                    _ = self.emit_sender.send(EmitEvent::NotificationReplied {
+                        id,
+                        text: reply.text().into(),
+                    });
+
+                    if self.close_interacted(id) {
+                        return Ok(());
+                    }
+                }
+            }
+            Key::SpecialKey(SpecialKeyCode::Backspace) => self
+                .notifications
+                .edit_reply(|reply, font_system| reply.backspace(font_system)),
+            Key::SpecialKey(SpecialKeyCode::Left) => {
+                self.notifications.edit_reply(|reply, _| reply.move_left())
+            }
+            Key::SpecialKey(SpecialKeyCode::Right) => {
+                self.notifications.edit_reply(|reply, _| reply.move_right())
+            }
+            Key::SpecialKey(SpecialKeyCode::Space) => self
+                .notifications
+                .edit_reply(|reply, font_system| reply.insert(font_system, ' ')),
+            Key::Character(c)
+                if !c.is_control() && !key.modifiers.control && !key.modifiers.alt =>
+            {
+                self.notifications
+                    .edit_reply(|reply, font_system| reply.insert(font_system, c))
+            }
+            _ => {}
         }
 
+        self.update_surface_size();
+        _ = self.render_surfaces();
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::RepeatSchedule;
+    use crate::config::keymaps::KeyAction;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_repeat_schedule() {
+        let pressed_at = Instant::now();
+        let at = |ms| pressed_at + Duration::from_millis(ms);
+
+        assert!(RepeatSchedule::new(0, 600, pressed_at).is_none());
+
+        // 25 repeats per second after 600ms
+        let mut schedule = RepeatSchedule::new(25, 600, pressed_at).unwrap();
+        assert_eq!(schedule.next_at(), at(600));
+        assert_eq!(schedule.due(at(300)), 0);
+        assert_eq!(schedule.due(at(600)), 1);
+        assert_eq!(schedule.next_at(), at(640));
+        assert_eq!(schedule.due(at(610)), 0);
+        assert_eq!(schedule.due(at(645)), 1);
+
+        // A timer that fires late catches up instead of falling behind
+        assert_eq!(schedule.due(at(730)), 2);
+        assert_eq!(schedule.next_at(), at(760));
+        assert_eq!(schedule.due(at(730)), 0);
+    }
+
+    #[test]
+    fn test_repeating_actions() {
+        assert!(KeyAction::NextNotification.repeats());
+        assert!(KeyAction::HistoryPagePrev.repeats());
+        assert!(!KeyAction::DismissNotification.repeats());
+        assert!(!KeyAction::DismissApp.repeats());
+    }
+}
@@ -1,54 +1,198 @@
+mod clipboard;
 mod keyboard;
 mod pointer;
+mod touch;
 
 use crate::Moxnotify;
+use calloop::LoopHandle;
 use keyboard::Keyboard;
 use pointer::Pointer;
+use touch::Touch;
 use wayland_client::{
     delegate_noop,
     globals::GlobalList,
-    protocol::{wl_seat, wl_shm},
-    Connection, Dispatch, QueueHandle,
+    protocol::{wl_data_device, wl_data_device_manager, wl_registry, wl_seat, wl_shm},
+    Connection, Dispatch, Proxy, QueueHandle, WEnum,
 };
 use wayland_protocols::xdg::activation::v1::client::xdg_activation_v1;
 
-pub struct Seat {
+struct Seat {
+    /// Name of the wl_seat global, the seat's input objects carry it as their user data.
+    id: u32,
     name: Option<String>,
-    pub wl_seat: wl_seat::WlSeat,
-    pointer: Pointer,
-    pub keyboard: Keyboard,
-    pub xdg_activation: xdg_activation_v1::XdgActivationV1,
+    wl_seat: wl_seat::WlSeat,
+    pointer: Option<Pointer>,
+    keyboard: Option<Keyboard>,
+    touch: Option<Touch>,
+    /// Created the first time something is copied on this seat.
+    data_device: Option<wl_data_device::WlDataDevice>,
 }
 
 impl Seat {
-    pub fn new(qh: &QueueHandle<Moxnotify>, globals: &GlobalList) -> anyhow::Result<Self> {
-        let wl_seat = globals.bind::<wl_seat::WlSeat, _, _>(qh, 1..=4, ())?;
-        let keyboard = Keyboard::new(qh, &wl_seat);
-        let pointer = Pointer::new(qh, globals, &wl_seat)?;
+    fn release(self, loop_handle: &LoopHandle<'static, Moxnotify>) {
+        if let Some(keyboard) = self.keyboard {
+            keyboard.release(loop_handle);
+        }
+        if let Some(pointer) = self.pointer {
+            pointer.release(loop_handle);
+        }
+        if let Some(touch) = self.touch {
+            touch.release();
+        }
+        if let Some(data_device) = self.data_device {
+            if data_device.version() >= 2 {
+                data_device.release();
+            }
+        }
+        if self.wl_seat.version() >= 5 {
+            self.wl_seat.release();
+        }
+    }
+}
 
+pub struct Seats {
+    seats: Vec<Seat>,
+    /// Seat whose pointer, keyboard or touch was used on a surface last.
+    active: Option<u32>,
+    pub xdg_activation: xdg_activation_v1::XdgActivationV1,
+    data_device_manager: Option<wl_data_device_manager::WlDataDeviceManager>,
+}
+
+impl Seats {
+    pub fn new(qh: &QueueHandle<Moxnotify>, globals: &GlobalList) -> anyhow::Result<Self> {
         Ok(Self {
+            seats: Vec::new(),
+            active: None,
             xdg_activation: globals.bind(qh, 1..=1, ())?,
+            data_device_manager: globals.bind(qh, 1..=3, ()).ok(),
+        })
+    }
+
+    /// Binds the wl_seat global `id`, its keyboard, pointer and touch are created once the
+    /// compositor announces the seat's capabilities.
+    pub fn add(
+        &mut self,
+        registry: &wl_registry::WlRegistry,
+        qh: &QueueHandle<Moxnotify>,
+        id: u32,
+        version: u32,
+    ) {
+        let wl_seat = registry.bind::<wl_seat::WlSeat, _, _>(id, version.min(8), qh, id);
+        self.seats.push(Seat {
+            id,
             name: None,
             wl_seat,
-            pointer,
-            keyboard,
-        })
+            pointer: None,
+            keyboard: None,
+            touch: None,
+            data_device: None,
+        });
+    }
+
+    /// Drops the seat of global `id` along with its input objects, returns whether it was known.
+    pub fn remove(&mut self, id: u32, loop_handle: &LoopHandle<'static, Moxnotify>) -> bool {
+        let Some(index) = self.seats.iter().position(|seat| seat.id == id) else {
+            return false;
+        };
+
+        let seat = self.seats.swap_remove(index);
+        log::info!("Seat {} removed", seat.name.as_deref().unwrap_or("unknown"));
+        seat.release(loop_handle);
+
+        if self.active == Some(id) {
+            self.active = None;
+        }
+
+        true
+    }
+
+    /// The seat that was used last, activation tokens are requested on its behalf.
+    pub fn active(&self) -> Option<&wl_seat::WlSeat> {
+        self.seats
+            .iter()
+            .find(|seat| Some(seat.id) == self.active)
+            .or_else(|| self.seats.first())
+            .map(|seat| &seat.wl_seat)
+    }
+
+    pub fn keyboards_mut(&mut self) -> impl Iterator<Item = &mut Keyboard> {
+        self.seats
+            .iter_mut()
+            .filter_map(|seat| seat.keyboard.as_mut())
+    }
+
+    fn get_mut(&mut self, id: u32) -> Option<&mut Seat> {
+        self.seats.iter_mut().find(|seat| seat.id == id)
+    }
+
+    fn pointer_mut(&mut self, id: u32) -> Option<&mut Pointer> {
+        self.get_mut(id)?.pointer.as_mut()
+    }
+
+    fn pointers_mut(&mut self) -> impl Iterator<Item = &mut Pointer> {
+        self.seats
+            .iter_mut()
+            .filter_map(|seat| seat.pointer.as_mut())
+    }
+
+    fn keyboard_mut(&mut self, id: u32) -> Option<&mut Keyboard> {
+        self.get_mut(id)?.keyboard.as_mut()
+    }
+
+    fn touch_mut(&mut self, id: u32) -> Option<&mut Touch> {
+        self.get_mut(id)?.touch.as_mut()
     }
 }
 
-impl Dispatch<wl_seat::WlSeat, ()> for Moxnotify {
+impl Dispatch<wl_seat::WlSeat, u32> for Moxnotify {
     fn event(
         state: &mut Self,
-        _proxy: &wl_seat::WlSeat,
+        wl_seat: &wl_seat::WlSeat,
         event: <wl_seat::WlSeat as wayland_client::Proxy>::Event,
-        _data: &(),
-        _conn: &Connection,
-        _qh: &QueueHandle<Self>,
+        id: &u32,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
     ) {
-        if let wl_seat::Event::Name { name } = event {
-            state.seat.name = Some(name)
+        let Some(seat) = state.seats.get_mut(*id) else {
+            return;
+        };
+
+        match event {
+            wl_seat::Event::Name { name } => seat.name = Some(name),
+            wl_seat::Event::Capabilities {
+                capabilities: WEnum::Value(capabilities),
+            } => {
+                if !capabilities.contains(wl_seat::Capability::Keyboard) {
+                    if let Some(keyboard) = seat.keyboard.take() {
+                        keyboard.release(&state.loop_handle);
+                    }
+                } else if seat.keyboard.is_none() {
+                    seat.keyboard = Some(Keyboard::new(qh, wl_seat, *id));
+                }
+
+                if !capabilities.contains(wl_seat::Capability::Pointer) {
+                    if let Some(pointer) = seat.pointer.take() {
+                        pointer.release(&state.loop_handle);
+                    }
+                } else if seat.pointer.is_none() {
+                    match Pointer::new(conn, qh, &state.globals, wl_seat, *id) {
+                        Ok(pointer) => seat.pointer = Some(pointer),
+                        Err(e) => log::error!("Failed to create pointer: {e}"),
+                    }
+                }
+
+                if !capabilities.contains(wl_seat::Capability::Touch) {
+                    if let Some(touch) = seat.touch.take() {
+                        touch.release();
+                    }
+                } else if seat.touch.is_none() {
+                    seat.touch = Some(Touch::new(qh, wl_seat, *id));
+                }
+            }
+            _ => {}
         }
     }
 }
 
 delegate_noop!(Moxnotify: ignore wl_shm::WlShm);
+delegate_noop!(Moxnotify: wl_data_device_manager::WlDataDeviceManager);
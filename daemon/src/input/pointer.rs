@@ -1,16 +1,23 @@
-use std::sync::atomic::Ordering;
+use std::{sync::atomic::Ordering, time::Duration};
 
 use crate::{
-    config::keymaps::{self},
+    components::notification::NotificationId,
+    config::keymaps::{self, MouseAction},
+    manager::Reason,
     rendering::surface::FocusReason,
     Moxnotify,
 };
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    LoopHandle, RegistrationToken,
+};
 use wayland_client::{
     delegate_noop,
     globals::GlobalList,
-    protocol::{wl_pointer, wl_seat},
-    Connection, Dispatch, QueueHandle, WEnum,
+    protocol::{wl_compositor, wl_pointer, wl_seat, wl_shm, wl_surface},
+    Connection, Dispatch, Proxy, QueueHandle, WEnum,
 };
+use wayland_cursor::CursorTheme;
 use wayland_protocols::wp::cursor_shape::v1::client::{
     wp_cursor_shape_device_v1::{self, Shape},
     wp_cursor_shape_manager_v1,
@@ -23,12 +30,44 @@ enum PointerState {
     Hover,
 }
 
+/// Compositors without cursor-shape-v1 get the cursor drawn from the user's xcursor theme.
+enum Cursor {
+    Shape(wp_cursor_shape_device_v1::WpCursorShapeDeviceV1),
+    Theme {
+        theme: CursorTheme,
+        surface: wl_surface::WlSurface,
+    },
+}
+
+/// Notification the left button went down on, dragged up or down once the pointer moves.
+struct Drag {
+    /// None once the drag was cancelled, releasing the button does nothing then.
+    id: Option<NotificationId>,
+    start: (f64, f64),
+    /// Set once the pointer travels further than a click.
+    moved: bool,
+    /// Whether the pointer moved up or down first, dragging the notification along.
+    dragging: bool,
+}
+
 pub struct Pointer {
     state: PointerState,
     x: f64,
     y: f64,
-    scroll_accumulator: f64,
-    cursor_device: wp_cursor_shape_device_v1::WpCursorShapeDeviceV1,
+    /// Wheel notches of the current frame in 120ths, they take precedence over the
+    /// continuous axis value sent along with them.
+    discrete: i32,
+    /// Whether the current scroll comes from fingers on a touchpad.
+    finger: bool,
+    /// Touchpad scroll speed in pixels per millisecond, carried on after the fingers lift.
+    velocity: f64,
+    last_axis: u32,
+    kinetic: Option<RegistrationToken>,
+    drag: Option<Drag>,
+    wl_pointer: wl_pointer::WlPointer,
+    cursor: Option<Cursor>,
+    /// Last shape sent to the compositor, reset on enter since the serial changes.
+    shape: Option<Shape>,
     serial: u32,
 }
 
@@ -37,51 +76,325 @@ delegate_noop!(Moxnotify: wp_cursor_shape_device_v1::WpCursorShapeDeviceV1);
 
 impl Pointer {
     pub fn new(
+        conn: &Connection,
         qh: &QueueHandle<Moxnotify>,
         globals: &GlobalList,
         wl_seat: &wl_seat::WlSeat,
+        seat: u32,
     ) -> anyhow::Result<Self> {
-        let wl_pointer = wl_seat.get_pointer(qh, ());
-        let cursor_shape = globals
-            .bind::<wp_cursor_shape_manager_v1::WpCursorShapeManagerV1, _, _>(qh, 1..=1, ())?;
-        let cursor_device = cursor_shape.get_pointer(&wl_pointer, qh, ());
+        let wl_pointer = wl_seat.get_pointer(qh, seat);
+
+        let cursor = match globals.bind::<wp_cursor_shape_manager_v1::WpCursorShapeManagerV1, _, _>(
+            qh,
+            1..=1,
+            (),
+        ) {
+            Ok(cursor_shape) => Some(Cursor::Shape(cursor_shape.get_pointer(&wl_pointer, qh, ()))),
+            Err(_) => {
+                log::info!("Cursor shape protocol unavailable, falling back to cursor theme");
+                Self::load_theme(conn, qh, globals)
+            }
+        };
 
         Ok(Self {
             serial: 0,
-            cursor_device,
+            wl_pointer,
+            cursor,
+            shape: None,
             state: PointerState::Default,
             x: 0.,
             y: 0.,
-            scroll_accumulator: 0.,
+            discrete: 0,
+            finger: false,
+            velocity: 0.,
+            last_axis: 0,
+            kinetic: None,
+            drag: None,
         })
     }
 
+    /// Stops kinetic scrolling and lets go of the wl_pointer once the seat loses it.
+    pub fn release(mut self, loop_handle: &LoopHandle<'static, Moxnotify>) {
+        if let Some(token) = self.kinetic.take() {
+            loop_handle.remove(token);
+        }
+
+        match self.cursor {
+            Some(Cursor::Shape(device)) => device.destroy(),
+            Some(Cursor::Theme { surface, .. }) => surface.destroy(),
+            None => {}
+        }
+
+        if self.wl_pointer.version() >= 3 {
+            self.wl_pointer.release();
+        }
+    }
+
+    fn load_theme(
+        conn: &Connection,
+        qh: &QueueHandle<Moxnotify>,
+        globals: &GlobalList,
+    ) -> Option<Cursor> {
+        let shm = globals.bind::<wl_shm::WlShm, _, _>(qh, 1..=1, ()).ok()?;
+        let compositor = globals
+            .bind::<wl_compositor::WlCompositor, _, _>(qh, 1..=6, ())
+            .ok()?;
+
+        let size = std::env::var("XCURSOR_SIZE")
+            .ok()
+            .and_then(|size| size.parse().ok())
+            .unwrap_or(24);
+
+        match CursorTheme::load(conn, shm, size) {
+            Ok(theme) => Some(Cursor::Theme {
+                theme,
+                surface: compositor.create_surface(qh, ()),
+            }),
+            Err(e) => {
+                log::warn!("Failed to load cursor theme: {e}");
+                None
+            }
+        }
+    }
+
+    fn set_shape(&mut self, shape: Shape) {
+        if self.shape == Some(shape) {
+            return;
+        }
+
+        match self.cursor.as_mut() {
+            Some(Cursor::Shape(device)) => device.set_shape(self.serial, shape),
+            Some(Cursor::Theme { theme, surface }) => {
+                let names: &[&str] = match shape {
+                    Shape::Pointer => &["pointer", "hand2", "hand1"],
+                    _ => &["default", "left_ptr"],
+                };
+
+                let Some(name) = names.iter().find(|name| theme.get_cursor(name).is_some()) else {
+                    log::warn!("No cursor found for shape {shape:?}");
+                    return;
+                };
+                let Some(cursor) = theme.get_cursor(name) else {
+                    return;
+                };
+
+                let image = &cursor[0];
+                let (width, height) = image.dimensions();
+                let (x, y) = image.hotspot();
+
+                surface.attach(Some(&**image), 0, 0);
+                surface.damage_buffer(0, 0, width as i32, height as i32);
+                surface.commit();
+                self.wl_pointer
+                    .set_cursor(self.serial, Some(surface), x as i32, y as i32);
+            }
+            None => return,
+        }
+
+        self.shape = Some(shape);
+    }
+
     fn change_state(&mut self, pointer_state: PointerState) {
         match pointer_state {
-            PointerState::Default => {
-                self.cursor_device.set_shape(self.serial, Shape::Default);
-            }
+            PointerState::Default => self.set_shape(Shape::Default),
             PointerState::Pressed => {}
-            PointerState::Hover => {
-                self.cursor_device.set_shape(self.serial, Shape::Pointer);
-            }
+            PointerState::Hover => self.set_shape(Shape::Pointer),
         }
 
         self.state = pointer_state;
     }
 }
 
+/// Interval between steps of kinetic scrolling.
+const KINETIC_INTERVAL: Duration = Duration::from_millis(16);
+/// Share of the speed kept after every step of kinetic scrolling.
+const KINETIC_FRICTION: f64 = 0.95;
+/// Speed in pixels per millisecond below which kinetic scrolling stops.
+const KINETIC_MIN_VELOCITY: f64 = 0.05;
+
+/// Distance in pixels the pointer can travel with a button held and still count as clicking.
+const DRAG_SLOP: f64 = 10.;
+
 const LEFT_MOUSE_CLICK: u32 = 272;
+const RIGHT_MOUSE_CLICK: u32 = 273;
+const MIDDLE_MOUSE_CLICK: u32 = 274;
+
+impl Moxnotify {
+    /// Scrolls the notification list by `delta` pixels, returns false once it hits an edge.
+    fn scroll(&mut self, seat: u32, delta: f64) -> bool {
+        if !self.notifications.scroll(delta as f32) {
+            return false;
+        }
+
+        self.list_moved(seat);
+        true
+    }
+
+    /// Redraws after the list moved, whatever is under the pointer of `seat` changed along
+    /// with it.
+    fn list_moved(&mut self, seat: u32) {
+        if let Some(pointer) = self.seats.pointer_mut(seat) {
+            match self.notifications.hover(pointer.x, pointer.y) {
+                true => pointer.change_state(PointerState::Hover),
+                false => pointer.change_state(PointerState::Default),
+            }
+        }
+
+        self.update_surface_size();
+        _ = self.render_surfaces();
+    }
+
+    fn start_kinetic_scroll(&mut self, seat: u32) {
+        self.stop_kinetic_scroll(seat);
+        let Some(pointer) = self.seats.pointer_mut(seat) else {
+            return;
+        };
+        if pointer.velocity.abs() < KINETIC_MIN_VELOCITY {
+            return;
+        }
+
+        pointer.kinetic = self
+            .loop_handle
+            .insert_source(
+                Timer::from_duration(KINETIC_INTERVAL),
+                move |_, _, state| {
+                    let Some(pointer) = state.seats.pointer_mut(seat) else {
+                        return TimeoutAction::Drop;
+                    };
+                    pointer.velocity *= KINETIC_FRICTION;
+                    let velocity = pointer.velocity;
+
+                    if velocity.abs() < KINETIC_MIN_VELOCITY
+                        || !state.scroll(seat, velocity * KINETIC_INTERVAL.as_millis() as f64)
+                    {
+                        if let Some(pointer) = state.seats.pointer_mut(seat) {
+                            pointer.velocity = 0.;
+                            pointer.kinetic = None;
+                        }
+                        return TimeoutAction::Drop;
+                    }
+
+                    TimeoutAction::ToDuration(KINETIC_INTERVAL)
+                },
+            )
+            .ok();
+    }
+
+    fn stop_kinetic_scroll(&mut self, seat: u32) {
+        if let Some(token) = self
+            .seats
+            .pointer_mut(seat)
+            .and_then(|pointer| pointer.kinetic.take())
+        {
+            self.loop_handle.remove(token);
+        }
+    }
+
+    /// Moves the notification dragged by the pointer of `seat` along with it, the drag is
+    /// cancelled if the notification went away in the meantime.
+    fn drag_moved(&mut self, seat: u32, x: f64, y: f64) {
+        let Some(drag) = self
+            .seats
+            .pointer_mut(seat)
+            .and_then(|pointer| pointer.drag.as_mut())
+        else {
+            return;
+        };
+
+        let (dx, dy) = (x - drag.start.0, y - drag.start.1);
+        if !drag.moved && dx.hypot(dy) > DRAG_SLOP {
+            drag.moved = true;
+            drag.dragging = dy.abs() > dx.abs();
+        }
+
+        let (true, Some(id)) = (drag.dragging, drag.id) else {
+            return;
+        };
+        if self.notifications.drag(id, dy as f32) {
+            _ = self.render_surfaces();
+        } else if let Some(drag) = self
+            .seats
+            .pointer_mut(seat)
+            .and_then(|pointer| pointer.drag.as_mut())
+        {
+            log::debug!("Notification {id} went away while dragged");
+            drag.id = None;
+        }
+    }
+
+    /// Puts the notifications pointers are dragging back in place, returns whether there
+    /// were any.
+    pub fn cancel_drag(&mut self) -> bool {
+        let ids: Vec<NotificationId> = self
+            .seats
+            .pointers_mut()
+            .filter_map(|pointer| pointer.drag.as_mut())
+            .filter(|drag| drag.dragging)
+            .filter_map(|drag| drag.id.take())
+            .collect();
+        if ids.is_empty() {
+            return false;
+        }
+
+        ids.into_iter()
+            .for_each(|id| self.notifications.cancel_drag(id));
+        _ = self.render_surfaces();
+        true
+    }
 
-impl Dispatch<wl_pointer::WlPointer, ()> for Moxnotify {
+    fn handle_click(&mut self, seat: u32, button: u32, x: f64, y: f64) {
+        let bindings = &self.config.general.mouse_bindings;
+        let action = match button {
+            LEFT_MOUSE_CLICK => bindings.left_click,
+            MIDDLE_MOUSE_CLICK => bindings.middle_click,
+            RIGHT_MOUSE_CLICK => bindings.right_click,
+            _ => return,
+        };
+
+        if button == LEFT_MOUSE_CLICK && self.notifications.click_button(x, y) {
+            // Clicking a counter pages through the list
+            if self.notifications.needs_redraw() {
+                self.list_moved(seat);
+            }
+            return;
+        }
+
+        log::debug!("Mouse action executed: {action:?}");
+        match action {
+            MouseAction::Noop => {}
+            MouseAction::DefaultAction => _ = self.notifications.click(x, y),
+            MouseAction::Dismiss => {
+                if let Some(id) = self.notifications.get_by_coordinates(x, y).map(|n| n.id()) {
+                    self.dismiss_by_id(id, Some(Reason::DismissedByUser));
+                }
+            }
+            MouseAction::ContextMenu => {
+                if let Some(id) = self.notifications.get_by_coordinates(x, y).map(|n| n.id()) {
+                    self.notifications.select(id);
+                    self.notifications.reset_hint();
+                    self.notifications
+                        .ui_state
+                        .mode
+                        .store(keymaps::Mode::Hint, Ordering::Relaxed);
+
+                    self.update_surface_size();
+                    _ = self.render_surfaces();
+                }
+            }
+        }
+    }
+}
+
+impl Dispatch<wl_pointer::WlPointer, u32> for Moxnotify {
     fn event(
         state: &mut Self,
         _: &wl_pointer::WlPointer,
         event: <wl_pointer::WlPointer as wayland_client::Proxy>::Event,
-        _: &(),
+        seat: &u32,
         _: &Connection,
         _: &QueueHandle<Self>,
     ) {
+        let seat = *seat;
         match event {
             wl_pointer::Event::Motion {
                 surface_x,
@@ -93,39 +406,26 @@ impl Dispatch<wl_pointer::WlPointer, ()> for Moxnotify {
                     .get_by_coordinates(surface_x, surface_y)
                     .map(|n| n.id());
 
-                let pointer = &mut state.seat.pointer;
+                let Some(pointer) = state.seats.pointer_mut(seat) else {
+                    return;
+                };
                 pointer.x = surface_x;
                 pointer.y = surface_y;
 
                 if let PointerState::Pressed = pointer.state {
+                    state.drag_moved(seat, surface_x, surface_y);
                     return;
                 }
 
-                let pointer = &state.seat.pointer;
-                if state.notifications.hover(pointer.x, pointer.y) {
-                    if state.seat.pointer.state != PointerState::Hover {
-                        if let Some(surface) = state.surface.as_mut() {
-                            _ = surface.render(
-                                &state.wgpu_state.device,
-                                &state.wgpu_state.queue,
-                                &state.notifications,
-                            );
-                        }
-                    }
-
-                    state.seat.pointer.change_state(PointerState::Hover);
-                } else {
-                    if state.seat.pointer.state != PointerState::Default {
-                        if let Some(surface) = state.surface.as_mut() {
-                            _ = surface.render(
-                                &state.wgpu_state.device,
-                                &state.wgpu_state.queue,
-                                &state.notifications,
-                            );
-                        }
-                    }
+                let pointer_state = match state.notifications.hover(surface_x, surface_y) {
+                    true => PointerState::Hover,
+                    false => PointerState::Default,
+                };
+                let changed = pointer.state != pointer_state;
+                pointer.change_state(pointer_state);
 
-                    state.seat.pointer.change_state(PointerState::Default);
+                if changed || state.notifications.needs_redraw() {
+                    _ = state.render_surfaces();
                 }
 
                 match (hovered_id, state.notifications.selected_id()) {
@@ -133,31 +433,21 @@ impl Dispatch<wl_pointer::WlPointer, ()> for Moxnotify {
                         state.update_surface_size();
                         state.notifications.select(new_id);
 
-                        if let Some(surface) = state.surface.as_mut() {
-                            _ = surface.render(
-                                &state.wgpu_state.device,
-                                &state.wgpu_state.queue,
-                                &state.notifications,
-                            );
-                        }
+                        _ = state.render_surfaces();
                     }
                     (Some(new_id), None) => {
                         state.update_surface_size();
                         state.notifications.select(new_id);
 
-                        if let Some(surface) = state.surface.as_mut() {
-                            _ = surface.render(
-                                &state.wgpu_state.device,
-                                &state.wgpu_state.queue,
-                                &state.notifications,
-                            );
-                        }
+                        _ = state.render_surfaces();
                     }
                     (None, Some(_)) => {
-                        if let Some(surface) = state.surface.as_ref() {
-                            if surface.focus_reason == Some(FocusReason::MouseEnter) {
-                                state.notifications.deselect();
-                            }
+                        if state
+                            .surfaces
+                            .iter()
+                            .any(|surface| surface.focus_reason == Some(FocusReason::MouseEnter))
+                        {
+                            state.notifications.deselect();
                         }
                         state.update_surface_size();
                         state
@@ -166,13 +456,7 @@ impl Dispatch<wl_pointer::WlPointer, ()> for Moxnotify {
                             .mode
                             .store(keymaps::Mode::Normal, Ordering::Relaxed);
 
-                        if let Some(surface) = state.surface.as_mut() {
-                            _ = surface.render(
-                                &state.wgpu_state.device,
-                                &state.wgpu_state.queue,
-                                &state.notifications,
-                            );
-                        }
+                        _ = state.render_surfaces();
                     }
                     _ => {}
                 }
@@ -181,90 +465,173 @@ impl Dispatch<wl_pointer::WlPointer, ()> for Moxnotify {
                 button,
                 state: WEnum::Value(value),
                 ..
-            } => {
-                if button != LEFT_MOUSE_CLICK {
-                    return;
-                }
+            } => match value {
+                wl_pointer::ButtonState::Pressed => {
+                    let Some(pointer) = state.seats.pointer_mut(seat) else {
+                        return;
+                    };
+                    pointer.change_state(PointerState::Pressed);
+                    if button != LEFT_MOUSE_CLICK {
+                        return;
+                    }
 
-                match value {
-                    wl_pointer::ButtonState::Pressed => {
-                        state.seat.pointer.change_state(PointerState::Pressed);
+                    let start = (pointer.x, pointer.y);
+                    let id = state
+                        .notifications
+                        .get_by_coordinates(start.0, start.1)
+                        .map(|n| n.id());
+                    if let Some(pointer) = state.seats.pointer_mut(seat) {
+                        pointer.drag = id.map(|id| Drag {
+                            id: Some(id),
+                            start,
+                            moved: false,
+                            dragging: false,
+                        });
                     }
-                    wl_pointer::ButtonState::Released => {
-                        state.seat.pointer.change_state(PointerState::Default);
+                }
+                wl_pointer::ButtonState::Released => {
+                    let Some(pointer) = state.seats.pointer_mut(seat) else {
+                        return;
+                    };
+                    pointer.change_state(PointerState::Default);
 
-                        let (x, y) = (state.seat.pointer.x, state.seat.pointer.y);
-                        state.notifications.click(x, y);
+                    let (x, y) = (pointer.x, pointer.y);
+                    let drag = match button {
+                        LEFT_MOUSE_CLICK => pointer.drag.take(),
+                        _ => None,
+                    };
+                    match drag.filter(|drag| drag.dragging) {
+                        Some(drag) => {
+                            if let Some(id) = drag.id {
+                                state.notifications.drop_dragged(id);
+                                state.list_moved(seat);
+                            }
+                        }
+                        None => state.handle_click(seat, button, x, y),
                     }
-                    _ => unreachable!(),
                 }
-            }
-            wl_pointer::Event::Leave { .. } => {
-                if let Some(surface) = state.surface.as_mut() {
-                    if surface.focus_reason == Some(FocusReason::MouseEnter) {
-                        state.seat.pointer.change_state(PointerState::Default);
+                _ => unreachable!(),
+            },
+            wl_pointer::Event::Leave { surface, .. } => {
+                state.notifications.resume_all_timers();
+                if state.notifications.unhover_anchors() {
+                    _ = state.render_surfaces();
+                }
+                if let Some(pointer) = state.seats.pointer_mut(seat) {
+                    pointer.shape = None;
+                    if let Some(id) = pointer
+                        .drag
+                        .take()
+                        .filter(|drag| drag.dragging)
+                        .and_then(|drag| drag.id)
+                    {
+                        state.notifications.cancel_drag(id);
+                        _ = state.render_surfaces();
+                    }
+                }
+
+                if let Some(left) = state.surfaces.iter_mut().find(|s| s.wl_surface == surface) {
+                    if left.focus_reason == Some(FocusReason::MouseEnter) {
+                        left.unfocus();
+                        if let Some(pointer) = state.seats.pointer_mut(seat) {
+                            pointer.change_state(PointerState::Default);
+                        }
                         state.notifications.deselect();
-                        surface.unfocus();
-                        _ = surface.render(
-                            &state.wgpu_state.device,
-                            &state.wgpu_state.queue,
-                            &state.notifications,
-                        );
+                        _ = state.render_surfaces();
                     }
                 }
             }
             wl_pointer::Event::Enter {
                 serial,
+                surface,
                 surface_x,
                 surface_y,
-                ..
             } => {
-                state.seat.pointer.serial = serial;
+                state.seats.active = Some(seat);
+                state.notifications.pause_all_timers();
 
-                if let Some(surface) = state.surface.as_mut() {
-                    surface.focus(FocusReason::MouseEnter)
+                if let Some(entered) = state.surfaces.iter_mut().find(|s| s.wl_surface == surface) {
+                    entered.focus(FocusReason::MouseEnter)
                 }
 
-                state.seat.pointer.x = surface_x;
-                state.seat.pointer.y = surface_y;
+                let Some(pointer) = state.seats.pointer_mut(seat) else {
+                    return;
+                };
+                pointer.serial = serial;
+                pointer.shape = None;
+                pointer.x = surface_x;
+                pointer.y = surface_y;
 
-                state.seat.pointer.change_state(PointerState::Default);
+                // The pointer can enter right on top of a button
+                match state.notifications.hover(surface_x, surface_y) {
+                    true => pointer.change_state(PointerState::Hover),
+                    false => pointer.change_state(PointerState::Default),
+                }
+            }
+            wl_pointer::Event::AxisSource {
+                axis_source: WEnum::Value(source),
+            } => {
+                state.stop_kinetic_scroll(seat);
+                if let Some(pointer) = state.seats.pointer_mut(seat) {
+                    pointer.finger = source == wl_pointer::AxisSource::Finger;
+                }
+            }
+            wl_pointer::Event::AxisDiscrete {
+                axis: WEnum::Value(wl_pointer::Axis::VerticalScroll),
+                discrete,
+            } => {
+                if let Some(pointer) = state.seats.pointer_mut(seat) {
+                    pointer.discrete += discrete * 120;
+                }
+            }
+            wl_pointer::Event::AxisValue120 {
+                axis: WEnum::Value(wl_pointer::Axis::VerticalScroll),
+                value120,
+            } => {
+                if let Some(pointer) = state.seats.pointer_mut(seat) {
+                    pointer.discrete += value120;
+                }
             }
             wl_pointer::Event::Axis {
-                time: _,
-                axis: WEnum::Value(axis),
+                time,
+                axis: WEnum::Value(wl_pointer::Axis::VerticalScroll),
                 value,
             } => {
-                if axis == wl_pointer::Axis::VerticalScroll {
-                    state.seat.pointer.scroll_accumulator += value;
-
-                    if state.seat.pointer.scroll_accumulator.abs()
-                        >= state.config.general.scroll_sensitivity
-                    {
-                        if state.seat.pointer.scroll_accumulator.is_sign_positive() {
-                            state.notifications.next();
-                            state.update_surface_size();
-                            if let Some(surface) = state.surface.as_mut() {
-                                _ = surface.render(
-                                    &state.wgpu_state.device,
-                                    &state.wgpu_state.queue,
-                                    &state.notifications,
-                                );
-                            }
-                        } else {
-                            state.notifications.prev();
-                            state.update_surface_size();
-                            if let Some(surface) = state.surface.as_mut() {
-                                _ = surface.render(
-                                    &state.wgpu_state.device,
-                                    &state.wgpu_state.queue,
-                                    &state.notifications,
-                                );
-                            }
-                        }
+                let Some(pointer) = state.seats.pointer_mut(seat) else {
+                    return;
+                };
+                let delta = match pointer.discrete {
+                    0 => value,
+                    discrete => discrete as f64 / 120. * state.config.general.scroll_sensitivity,
+                };
+                pointer.discrete = 0;
 
-                        state.seat.pointer.scroll_accumulator = 0.0;
+                if pointer.finger {
+                    let elapsed = time.wrapping_sub(pointer.last_axis);
+                    if (1..100).contains(&elapsed) {
+                        pointer.velocity = (pointer.velocity + value / elapsed as f64) / 2.;
+                    } else {
+                        pointer.velocity = 0.;
                     }
+                    pointer.last_axis = time;
+                }
+
+                state.scroll(seat, delta);
+            }
+            wl_pointer::Event::AxisStop {
+                time,
+                axis: WEnum::Value(wl_pointer::Axis::VerticalScroll),
+            } => {
+                // Fingers resting on the touchpad before lifting shouldn't fling the list
+                if state.seats.pointer_mut(seat).is_some_and(|pointer| {
+                    pointer.finger && time.wrapping_sub(pointer.last_axis) < 100
+                }) {
+                    state.start_kinetic_scroll(seat);
+                }
+            }
+            wl_pointer::Event::Frame => {
+                if let Some(pointer) = state.seats.pointer_mut(seat) {
+                    pointer.discrete = 0;
                 }
             }
             _ => {}
@@ -0,0 +1,159 @@
+use crate::{components::notification::NotificationId, manager::Reason, Moxnotify};
+use wayland_client::{
+    protocol::{wl_seat, wl_touch},
+    Connection, Dispatch, Proxy, QueueHandle,
+};
+
+/// Distance in pixels a finger can travel and still count as tapping.
+const TAP_SLOP: f64 = 10.;
+/// Share of the width of the notifications a swipe has to cover to dismiss one.
+const SWIPE_DISMISS: f64 = 0.4;
+
+/// The finger that touched down first, others are ignored until it lifts.
+struct TouchPoint {
+    id: i32,
+    start: (f64, f64),
+    position: (f64, f64),
+    /// Notification the finger came down on.
+    notification: Option<NotificationId>,
+    /// Set once the finger travels further than a tap.
+    moved: bool,
+    /// Whether the finger moved sideways first, dragging the notification along.
+    swiping: bool,
+}
+
+pub struct Touch {
+    wl_touch: wl_touch::WlTouch,
+    point: Option<TouchPoint>,
+}
+
+impl Touch {
+    pub fn new(qh: &QueueHandle<Moxnotify>, wl_seat: &wl_seat::WlSeat, seat: u32) -> Self {
+        Self {
+            wl_touch: wl_seat.get_touch(qh, seat),
+            point: None,
+        }
+    }
+
+    pub fn release(self) {
+        if self.wl_touch.version() >= 3 {
+            self.wl_touch.release();
+        }
+    }
+}
+
+impl Moxnotify {
+    /// Handles the tracked finger lifting: a tap clicks what's under it, a swipe far enough
+    /// dismisses the notification and a shorter one lets it snap back.
+    fn touch_up(&mut self, point: TouchPoint) {
+        self.notifications.resume_all_timers();
+
+        let distance = point.position.0 - point.start.0;
+        match point.notification {
+            Some(id) if point.swiping => {
+                if distance.abs() >= self.notifications.width() as f64 * SWIPE_DISMISS {
+                    log::debug!("Notification {id} swiped away");
+                    self.dismiss_by_id(id, Some(Reason::DismissedByUser));
+                } else {
+                    self.notifications.snap_back(id);
+                    _ = self.render_surfaces();
+                }
+            }
+            _ if !point.moved => {
+                let (x, y) = point.position;
+                if self.notifications.click(x, y) && self.notifications.needs_redraw() {
+                    // Tapping a counter pages through the list
+                    self.update_surface_size();
+                    _ = self.render_surfaces();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<wl_touch::WlTouch, u32> for Moxnotify {
+    fn event(
+        state: &mut Self,
+        _: &wl_touch::WlTouch,
+        event: <wl_touch::WlTouch as wayland_client::Proxy>::Event,
+        seat: &u32,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let seat = *seat;
+        match event {
+            wl_touch::Event::Down { id, x, y, .. } => {
+                let notification = state.notifications.get_by_coordinates(x, y).map(|n| n.id());
+
+                let Some(touch) = state.seats.touch_mut(seat) else {
+                    return;
+                };
+                // Secondary fingers don't take over the gesture of the first one
+                if touch.point.is_some() {
+                    return;
+                }
+
+                touch.point = Some(TouchPoint {
+                    id,
+                    start: (x, y),
+                    position: (x, y),
+                    notification,
+                    moved: false,
+                    swiping: false,
+                });
+                state.seats.active = Some(seat);
+                state.notifications.pause_all_timers();
+            }
+            wl_touch::Event::Motion { id, x, y, .. } => {
+                let Some(point) = state
+                    .seats
+                    .touch_mut(seat)
+                    .and_then(|touch| touch.point.as_mut())
+                    .filter(|point| point.id == id)
+                else {
+                    return;
+                };
+                point.position = (x, y);
+
+                let (dx, dy) = (x - point.start.0, y - point.start.1);
+                if !point.moved && dx.hypot(dy) > TAP_SLOP {
+                    point.moved = true;
+                    point.swiping = dx.abs() > dy.abs();
+                }
+
+                if let (true, Some(notification)) = (point.swiping, point.notification) {
+                    state.notifications.swipe(notification, dx as f32);
+                    _ = state.render_surfaces();
+                }
+            }
+            wl_touch::Event::Up { id, .. } => {
+                let Some(touch) = state.seats.touch_mut(seat) else {
+                    return;
+                };
+                if touch.point.as_ref().is_some_and(|point| point.id == id) {
+                    if let Some(point) = touch.point.take() {
+                        state.touch_up(point);
+                    }
+                }
+            }
+            wl_touch::Event::Cancel => {
+                // The compositor took the touch over for a gesture of its own
+                let Some(point) = state
+                    .seats
+                    .touch_mut(seat)
+                    .and_then(|touch| touch.point.take())
+                else {
+                    return;
+                };
+
+                state.notifications.resume_all_timers();
+                if let (true, Some(notification)) = (point.swiping, point.notification) {
+                    state.notifications.snap_back(notification);
+                    _ = state.render_surfaces();
+                }
+            }
+            _ => {}
+        }
+    }
+}
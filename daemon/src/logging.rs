@@ -0,0 +1,84 @@
+use anyhow::Context;
+use clap::ValueEnum;
+use std::{fs::File, path::Path, sync::Arc};
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::{
+    filter::Targets, fmt::writer::BoxMakeWriter, layer::SubscriberExt, util::SubscriberInitExt,
+    Layer,
+};
+
+#[derive(ValueEnum, Clone, Copy, Default)]
+pub enum LogFormat {
+    #[default]
+    /// Lines meant to be read, spans prefixed to them
+    Text,
+    /// One JSON object per line, with the spans it was logged in
+    Json,
+}
+
+/// Level logged at, info raised by every `-v` up to trace and lowered by every `-q` down
+/// to error.
+pub fn level(verbose: u8, quiet: u8) -> LevelFilter {
+    const LEVELS: [LevelFilter; 5] = [
+        LevelFilter::ERROR,
+        LevelFilter::WARN,
+        LevelFilter::INFO,
+        LevelFilter::DEBUG,
+        LevelFilter::TRACE,
+    ];
+
+    let index = (2 + verbose as usize)
+        .min(LEVELS.len() - 1)
+        .saturating_sub(quiet as usize);
+    LEVELS[index]
+}
+
+/// Installs the global subscriber, records of the `log` crate included. Lines are appended to
+/// `file` if there's one, printed to stderr otherwise.
+pub fn init(level: LevelFilter, format: LogFormat, file: Option<&Path>) -> anyhow::Result<()> {
+    let writer = match file {
+        Some(path) => {
+            let file = File::options()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+            BoxMakeWriter::new(Arc::new(file))
+        }
+        None => BoxMakeWriter::new(std::io::stderr),
+    };
+
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(file.is_none());
+    let layer = match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed(),
+    };
+
+    tracing_subscriber::registry()
+        .with(Targets::new().with_target("daemon", level))
+        .with(layer)
+        .try_init()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::level;
+    use tracing::level_filters::LevelFilter;
+
+    #[test]
+    fn test_level() {
+        assert_eq!(level(0, 0), LevelFilter::INFO);
+        assert_eq!(level(1, 0), LevelFilter::DEBUG);
+        assert_eq!(level(5, 0), LevelFilter::TRACE);
+        assert_eq!(level(0, 5), LevelFilter::ERROR);
+        assert_eq!(level(4, 1), LevelFilter::DEBUG);
+    }
+}
@@ -1,24 +1,32 @@
 mod audio;
 pub mod components;
 mod config;
+mod daemonize;
 mod dbus;
 mod input;
+mod logging;
 mod manager;
 mod rendering;
 pub mod utils;
 
-use audio::Audio;
-use calloop::EventLoop;
+use audio::{Audio, Playback, Sound};
+use calloop::{
+    futures::Scheduler,
+    signals::{Signal, Signals},
+    timer::{TimeoutAction, Timer},
+    EventLoop,
+};
 use calloop_wayland_source::WaylandSource;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use components::notification::NotificationId;
-use config::Config;
-use dbus::xdg::NotificationData;
-use env_logger::Builder;
+use config::{Anchor, Config};
+use dbus::{
+    moxnotify::{ListedNotification, Status},
+    xdg::NotificationData,
+};
 use glyphon::FontSystem;
-use input::Seat;
-use log::LevelFilter;
-use manager::{NotificationManager, Reason};
+use input::Seats;
+use manager::{ActionError, HistoryPage, NotificationManager, Reason};
 use rendering::{
     surface::{FocusReason, Surface},
     wgpu_state,
@@ -26,55 +34,114 @@ use rendering::{
 use rusqlite::params;
 use serde::{Deserialize, Serialize};
 use std::{
-    cell::RefCell,
-    path::Path,
+    cell::{Cell, RefCell},
+    path::{Path, PathBuf},
     rc::Rc,
     sync::{atomic::Ordering, Arc},
+    time::Duration,
 };
-use tokio::sync::broadcast;
-use utils::image_data::ImageData;
+use tokio::sync::{broadcast, oneshot};
+use utils::image_data::{ImageData, ImageFrame};
 use wayland_client::{
     delegate_noop,
     globals::{registry_queue_init, GlobalList, GlobalListContents},
-    protocol::{wl_compositor, wl_output, wl_registry},
-    Connection, Dispatch, Proxy, QueueHandle,
+    protocol::{wl_compositor, wl_output, wl_registry, wl_seat, wl_surface},
+    Connection, Dispatch, Proxy, QueueHandle, WEnum,
+};
+use wayland_protocols::{
+    wp::{
+        fractional_scale::v1::client::wp_fractional_scale_manager_v1,
+        viewporter::client::wp_viewporter,
+    },
+    xdg::activation::v1::client::{xdg_activation_token_v1, xdg_activation_v1},
 };
-use wayland_protocols::xdg::activation::v1::client::{xdg_activation_token_v1, xdg_activation_v1};
 use wayland_protocols_wlr::layer_shell::v1::client::zwlr_layer_shell_v1;
 use zbus::zvariant::Type;
 
 use crate::config::keymaps;
 
+/// How long closing notifications may take on exit before the daemon gives up on D-Bus.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
 #[derive(Debug)]
 pub struct Output {
     id: u32,
     name: Option<Box<str>>,
     scale: f32,
+    transform: wl_output::Transform,
+    /// Size of the current mode in physical pixels.
+    size: (i32, i32),
+    /// Whether properties changed since the last `done` event.
+    changed: bool,
     wl_output: wl_output::WlOutput,
 }
 
 impl Output {
+    /// Width and height of the mode, swapped when the output is rotated sideways.
+    fn transformed_size(&self) -> (i32, i32) {
+        match self.transform {
+            wl_output::Transform::_90
+            | wl_output::Transform::_270
+            | wl_output::Transform::Flipped90
+            | wl_output::Transform::Flipped270 => (self.size.1, self.size.0),
+            _ => self.size,
+        }
+    }
+
+    /// Height in surface coordinates, None until the output announced its mode. Fractional
+    /// scales are rounded up by the compositor, so it may come out a bit short.
+    fn logical_height(&self) -> Option<f32> {
+        let (_, height) = self.transformed_size();
+        (height > 0).then(|| height as f32 / self.scale)
+    }
+
+    /// Width in surface coordinates, None until the output announced its mode.
+    fn logical_width(&self) -> Option<f32> {
+        let (width, _) = self.transformed_size();
+        (width > 0).then(|| width as f32 / self.scale)
+    }
+
     fn new(wl_output: wl_output::WlOutput, id: NotificationId) -> Self {
         Self {
             id,
             name: None,
             scale: 1.0,
+            transform: wl_output::Transform::Normal,
+            size: (0, 0),
+            changed: false,
             wl_output,
         }
     }
 }
 
-#[derive(Default, PartialEq, Clone, Copy, Type, Serialize)]
+#[derive(Default, PartialEq, Clone, Copy, Debug, Type, Serialize)]
 pub enum History {
     #[default]
     Hidden,
     Shown,
 }
 
+/// Reasons notifications are currently held back. They're tracked apart so that lifting a
+/// manual inhibit doesn't end one kept up by a screencast or the do not disturb schedule.
+#[derive(Default, PartialEq, Clone, Copy, Debug, Type, Serialize)]
+pub struct InhibitState {
+    pub manual: bool,
+    pub screencast: bool,
+    pub schedule: bool,
+}
+
+impl InhibitState {
+    pub fn inhibited(&self) -> bool {
+        self.manual || self.screencast || self.schedule
+    }
+}
+
 pub struct Moxnotify {
     layer_shell: zwlr_layer_shell_v1::ZwlrLayerShellV1,
-    seat: Seat,
-    surface: Option<Surface>,
+    fractional_scale_manager: Option<wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1>,
+    viewporter: Option<wp_viewporter::WpViewporter>,
+    seats: Seats,
+    surfaces: Vec<Surface>,
     outputs: Vec<Output>,
     wgpu_state: wgpu_state::WgpuState,
     notifications: NotificationManager,
@@ -82,119 +149,286 @@ pub struct Moxnotify {
     qh: QueueHandle<Self>,
     globals: GlobalList,
     loop_handle: calloop::LoopHandle<'static, Self>,
+    loop_signal: calloop::LoopSignal,
+    /// Set once the daemon was asked to exit, while closing notifications is under way.
+    exiting: bool,
     emit_sender: broadcast::Sender<EmitEvent>,
     compositor: wl_compositor::WlCompositor,
-    audio: Audio,
+    audio: Option<Audio>,
     db: rusqlite::Connection,
     history: History,
+    history_page: HistoryPage,
     font_system: Rc<RefCell<FontSystem>>,
+    dnd_registration_token: Option<calloop::RegistrationToken>,
+    timestamp_registration_token: Option<calloop::RegistrationToken>,
+    /// Timer moving the surfaces down to a lower layer, see `LAYER_DEBOUNCE`.
+    layer_registration_token: Option<calloop::RegistrationToken>,
 }
 
 impl Moxnotify {
+    #[allow(clippy::too_many_arguments)]
     async fn new<T>(
         conn: &Connection,
         qh: QueueHandle<Moxnotify>,
         globals: GlobalList,
         loop_handle: calloop::LoopHandle<'static, Self>,
+        loop_signal: calloop::LoopSignal,
         emit_sender: broadcast::Sender<EmitEvent>,
         event_sender: calloop::channel::Sender<Event>,
         config_path: Option<T>,
+        critical_layer: Option<config::Layer>,
+        standalone: bool,
     ) -> anyhow::Result<Self>
     where
         T: AsRef<Path>,
     {
         let layer_shell = globals.bind(&qh, 1..=5, ())?;
         let compositor = globals.bind::<wl_compositor::WlCompositor, _, _>(&qh, 1..=6, ())?;
-        let seat = Seat::new(&qh, &globals)?;
-
-        let config = Arc::new(Config::load(config_path)?);
-
-        let wgpu_state = wgpu_state::WgpuState::new(conn).await?;
-
-        let db = rusqlite::Connection::open(&config.general.history.path)?;
-        db.execute(
-            "CREATE TABLE IF NOT EXISTS notifications (
-            rowid INTEGER PRIMARY KEY AUTOINCREMENT,
-            id INTEGER,
-            app_name TEXT,
-            app_icon TEXT,
-            summary TEXT,
-            body TEXT,
-            timeout INTEGER,
-            actions TEXT,
-            hints JSON
-        );",
-            (),
-        )?;
+        let fractional_scale_manager = globals.bind(&qh, 1..=1, ()).ok();
+        let viewporter = globals.bind(&qh, 1..=1, ()).ok();
+        let seats = Seats::new(&qh, &globals)?;
+
+        let mut config = Config::load(config_path)?;
+        if let Some(layer) = critical_layer {
+            config.general.urgency_layer.critical = Some(layer);
+        }
+        let config = Arc::new(config);
+
+        // Only used to pick an adapter that can present to surfaces like it
+        let probe = compositor.create_surface(&qh, ());
+        let wgpu_state = wgpu_state::WgpuState::new(conn, &config.general, &probe).await;
+        probe.destroy();
+        let wgpu_state = wgpu_state?;
+
+        // A standalone instance only shows the test notification, it mustn't restore or clear
+        // what the daemon serving notifications has stored
+        let db = match standalone {
+            true => rusqlite::Connection::open_in_memory()?,
+            false => rusqlite::Connection::open(&config.general.history.path)?,
+        };
+        dbus::xdg::create_history_table(&db)?;
+        dbus::xdg::create_waiting_table(&db)?;
+        dbus::xdg::create_snoozed_table(&db)?;
+        dbus::xdg::create_state_table(&db)?;
 
         let font_system = Rc::new(RefCell::new(FontSystem::new()));
 
+        let mut notifications = NotificationManager::new(
+            Arc::clone(&config),
+            loop_handle.clone(),
+            event_sender.clone(),
+            Rc::clone(&font_system),
+        );
+        match config.general.persist_inhibit {
+            true => {
+                let waiting = dbus::xdg::load_waiting(&db)?;
+                // Databases from before the inhibit was stored on its own only hold what it
+                // held back
+                if dbus::xdg::load_inhibited(&db)? || !waiting.is_empty() {
+                    log::info!(
+                        "Restoring inhibit with {} waiting notifications",
+                        waiting.len()
+                    );
+                    notifications.restore_waiting(waiting);
+                }
+            }
+            false => {
+                dbus::xdg::clear_waiting(&db)?;
+                dbus::xdg::set_inhibited(&db, false)?;
+            }
+        }
+
         Ok(Self {
             history: History::Hidden,
+            history_page: HistoryPage::default(),
             db,
-            audio: Audio::new(),
+            audio: Audio::new(config.general.sound_theme.as_deref(), event_sender.clone())
+                .inspect_err(|e| log::warn!("Sound disabled: {e}"))
+                .ok(),
             globals,
             qh,
-            notifications: NotificationManager::new(
-                Arc::clone(&config),
-                loop_handle.clone(),
-                event_sender.clone(),
-                Rc::clone(&font_system),
-            ),
+            notifications,
             font_system,
             config,
             wgpu_state,
             layer_shell,
-            seat,
-            surface: None,
+            fractional_scale_manager,
+            viewporter,
+            seats,
+            surfaces: Vec::new(),
             outputs: Vec::new(),
             loop_handle,
+            loop_signal,
+            exiting: false,
             emit_sender,
             compositor,
+            dnd_registration_token: None,
+            timestamp_registration_token: None,
+            layer_registration_token: None,
         })
     }
 
+    /// Tells senders their notifications are gone with `Reason::Unknown`, then exits once the
+    /// D-Bus server sent that out, or after `SHUTDOWN_TIMEOUT` if it doesn't answer.
+    fn shutdown(&mut self) {
+        if std::mem::replace(&mut self.exiting, true) {
+            return;
+        }
+
+        let ids = self.notifications.lost_on_exit();
+        log::info!("Shutting down, closing {} notifications", ids.len());
+        ids.into_iter().for_each(|id| {
+            _ = self.emit_sender.send(EmitEvent::NotificationClosed {
+                id,
+                reason: Reason::Unknown,
+            });
+        });
+        _ = self.emit_sender.send(EmitEvent::Shutdown);
+
+        let loop_signal = self.loop_signal.clone();
+        _ = self.loop_handle.insert_source(
+            Timer::from_duration(SHUTDOWN_TIMEOUT),
+            move |_, _, _| {
+                log::warn!("D-Bus server didn't confirm closing notifications, exiting");
+                loop_signal.stop();
+                TimeoutAction::Drop
+            },
+        );
+    }
+
+    /// Tears the daemon down once the event loop is done. Surfaces go before the wgpu device
+    /// they were created with.
+    fn close(self) {
+        let Moxnotify {
+            surfaces,
+            wgpu_state,
+            db,
+            ..
+        } = self;
+
+        drop(surfaces);
+        drop(wgpu_state);
+        if let Err((_, e)) = db.close() {
+            log::warn!("Failed to close the history database: {e}");
+        }
+    }
+
+    /// Shows `data`, or holds it back while inhibited, and plays its sound. With `store` it's
+    /// stored in the history afterwards, redacted if it's true.
+    fn deliver(&mut self, data: NotificationData, store: Option<bool>) -> anyhow::Result<()> {
+        let urgency_sound = self
+            .config
+            .general
+            .default_sound_file
+            .get(&data.hints.urgency);
+        let default_sound = urgency_sound.map(|sound| Arc::clone(&sound.path));
+        let looped = urgency_sound
+            .filter(|sound| sound.looped)
+            .map(|sound| Duration::from_secs(sound.max_duration));
+
+        let sound = match (
+            data.hints.sound_file.as_ref().map(Arc::clone),
+            data.hints.sound_name.as_ref().map(Arc::clone),
+        ) {
+            (Some(sound_file), _) => Some(Sound::File(sound_file)),
+            (None, Some(sound_name)) => Some(Sound::Name(sound_name)),
+            (None, None) => default_sound.as_ref().map(Arc::clone).map(Sound::File),
+        };
+
+        let suppress_sound = data.hints.suppress_sound;
+
+        let id = data.id;
+        let mut stored = store.map(|redact| (data.clone(), redact));
+        self.notifications.add(data)?;
+        // Not being able to store it only keeps it out of the history
+        if let Some((data, redact)) = stored.as_mut() {
+            match data.persist(&self.db, *redact) {
+                Ok(()) => {
+                    if let Some(rowid) = data.rowid {
+                        self.notifications.set_row(id, rowid);
+                    }
+                }
+                Err(e) => log::error!("Failed to store notification in the history: {e}"),
+            }
+        }
+        // Only the inhibit outlives restarts, the lock is read again on startup
+        if self.config.general.persist_inhibit && self.notifications.inhibited() {
+            let history = &self.config.general.history;
+            if let Some(data) = self
+                .notifications
+                .held(id)
+                .filter(|data| !history.excludes(&data.app_name))
+            {
+                data.hold(&self.db, history.redacts(&data.app_name))?;
+            }
+        }
+
+        if self.notifications.inhibited() || self.notifications.held(id).is_some() || suppress_sound
+        {
+            log::debug!("Sound suppressed for notification");
+        } else if let Some(sound) = sound {
+            log::debug!("Playing notification sound");
+            if let Some(audio) = self.audio.as_ref() {
+                audio.play(id, sound, default_sound, looped);
+            }
+        }
+
+        Ok(())
+    }
+
     fn handle_app_event(&mut self, event: Event) -> anyhow::Result<()> {
         match event {
-            Event::Dismiss { all, id } => {
-                if all {
+            Event::Dismiss { all, id, app } => {
+                if let Some(app) = app {
+                    log::info!("Dismissing notifications from {app}");
+                    let count = self.dismiss_app(&app);
+                    _ = self.emit_sender.send(EmitEvent::Dismissed(count as u32));
+                } else if all {
                     log::info!("Dismissing all notifications");
                     self.dismiss_range(.., Some(Reason::DismissedByUser));
                 } else if id == 0 {
-                    if let Some(notification) = self.notifications.notifications().first() {
-                        log::info!("Dismissing first notification (id={})", notification.id());
-                        self.dismiss_by_id(notification.id(), Some(Reason::DismissedByUser));
+                    let first = self.notifications.displayed().next().map(|n| n.id());
+                    if let Some(id) = first {
+                        log::info!("Dismissing first notification (id={id})");
+                        self.dismiss_by_id(id, Some(Reason::DismissedByUser));
                     } else {
                         log::debug!("No notifications to dismiss");
                     }
+                } else if self.cancel_snooze(id) {
+                    log::info!("Dismissing snoozed notification with id={id}");
+                    _ = self.emit_sender.send(EmitEvent::NotificationClosed {
+                        id,
+                        reason: Reason::DismissedByUser,
+                    });
                 } else {
                     log::info!("Dismissing notification with id={id}");
                     self.dismiss_by_id(id, Some(Reason::DismissedByUser));
                 }
             }
+            Event::Snooze { id, minutes } => self.snooze(id, minutes)?,
             Event::InvokeAction { id, key } => {
-                if let Some(surface) = self.surface.as_ref() {
-                    let token = surface.token.as_ref().map(Arc::clone);
-                    _ = self.emit_sender.send(crate::EmitEvent::ActionInvoked {
-                        id,
-                        key,
-                        token: token.unwrap_or_default(),
-                    });
+                if let Err(e) = self.notifications.find_action(id, &key) {
+                    log::warn!("Failed to invoke action: {e}");
+                    _ = self.emit_sender.send(EmitEvent::ActionResult(Err(e)));
+                    return Ok(());
                 }
 
-                if !self
-                    .notifications
-                    .notifications()
-                    .iter()
-                    .find(|notification| notification.id() == id)
-                    .map(|n| n.data.hints.resident)
-                    .unwrap_or_default()
-                {
-                    self.dismiss_by_id(id, None);
-                }
+                // Actions invoked over D-Bus may come while no surface is around to hand out a token
+                let token = self
+                    .active_surface()
+                    .and_then(|surface| surface.token.as_ref().map(Arc::clone));
+                _ = self.emit_sender.send(crate::EmitEvent::ActionInvoked {
+                    id,
+                    key,
+                    token: token.unwrap_or_default(),
+                });
+                _ = self.emit_sender.send(EmitEvent::ActionResult(Ok(())));
+
+                self.close_interacted(id);
             }
             Event::InvokeAnchor(uri) => {
-                if let Some(surface) = self.surface.as_ref() {
+                self.notifications.visit_link(&uri);
+                if let Some(surface) = self.active_surface() {
                     let token = surface.token.as_ref().map(Arc::clone);
                     if self
                         .emit_sender
@@ -213,87 +447,130 @@ impl Moxnotify {
                     }
                 }
             }
-            Event::Notify(data) => {
+            Event::OpenLink { id, reply } => {
+                let opened = match self.notifications.first_link(id) {
+                    Some((id, uri)) => {
+                        log::info!("Opening {uri} from notification with id={id}");
+                        self.notifications.visit_link(&uri);
+                        let token = self
+                            .active_surface()
+                            .and_then(|surface| surface.token.as_ref().map(Arc::clone));
+                        _ = self.emit_sender.send(EmitEvent::Open { uri, token });
+
+                        if !self.notifications.resident(id) {
+                            self.dismiss_by_id(id, Some(Reason::DismissedByUser));
+                        }
+                        true
+                    }
+                    None => {
+                        log::info!("No link to open");
+                        false
+                    }
+                };
+
+                if let Some(reply) = reply {
+                    _ = reply.send(opened);
+                }
+            }
+            Event::ToggleExpand(id) => {
+                if !self.notifications.toggle_expand(id) {
+                    return Ok(());
+                }
+            }
+            Event::TogglePin(id) => match self.notifications.toggle_pin(id) {
+                Some(true) => log::info!("Pinned notification with id={id}"),
+                Some(false) => log::info!("Unpinned notification with id={id}"),
+                None => {
+                    log::debug!("No notification with id={id} to pin");
+                    return Ok(());
+                }
+            },
+            Event::Notify(mut data) => {
+                // Everything logged until the notification is stored and shown carries its id
+                let span = tracing::info_span!("notify", id = data.id).entered();
                 log::info!(
                     "Receiving notification from {}: '{}'",
                     data.app_name,
                     data.summary
                 );
 
-                let path = match (
-                    data.hints.sound_file.as_ref().map(Arc::clone),
-                    data.hints.sound_name.as_ref().map(Arc::clone),
-                ) {
-                    (Some(sound_file), None) => Some(sound_file),
-                    (None, Some(sound_name)) => freedesktop_sound::lookup(&sound_name)
-                        .with_cache()
-                        .find()
-                        .map(|s| s.into()),
-                    (None, None) => match data.hints.urgency {
-                        Urgency::Low => self
-                            .config
-                            .general
-                            .default_sound_file
-                            .urgency_low
-                            .as_ref()
-                            .map(Arc::clone),
-                        Urgency::Normal => self
-                            .config
-                            .general
-                            .default_sound_file
-                            .urgency_normal
-                            .as_ref()
-                            .map(Arc::clone),
-                        Urgency::Critical => self
-                            .config
-                            .general
-                            .default_sound_file
-                            .urgency_critical
-                            .as_ref()
-                            .map(Arc::clone),
-                    },
-                    (Some(sound_file), Some(_)) => Some(sound_file),
-                };
+                self.config.apply_rules(&mut data);
+                // Replacing a snoozed notification supersedes it rather than having it come back
+                if self.cancel_snooze(data.id) {
+                    log::info!("Replacing snoozed notification with id={}", data.id);
+                }
+                // Live notifications are all gone while the history is shown
+                if self.history == History::Hidden {
+                    data.rowid = self.notifications.replaced_row(&data);
+                }
+                let history = &self.config.general.history;
+                let mut store =
+                    (!history.excludes(&data.app_name)).then(|| history.redacts(&data.app_name));
 
-                let suppress_sound = data.hints.suppress_sound;
+                // With the history shown the notification is listed as the entry it's stored as,
+                // whose rowid can't clash with the entries already listed, so it's stored first
+                if self.history == History::Shown {
+                    if let Some(redact) = store.take() {
+                        if let Err(e) = data.persist(&self.db, redact) {
+                            log::error!("Failed to store notification in the history: {e}");
+                        }
+                    }
+                    if let Some(rowid) = data.rowid {
+                        data.sent_id = Some(data.id);
+                        data.id = rowid as u32;
+                    }
+                }
+                span.record("id", data.id);
+                _ = self
+                    .emit_sender
+                    .send(EmitEvent::NotificationReceived((&*data).into()));
 
-                let id = match self.history {
-                    History::Shown => self.db.last_insert_rowid() as u32,
-                    History::Hidden => data.id,
+                self.deliver(*data, store)?;
+            }
+            Event::CloseNotification { id, reply } => {
+                // Apps close notifications by the id they sent them with, which history entries
+                // aren't listed by. Only the ones sent this session are reachable that way
+                let shown = match self.history {
+                    History::Shown => self.notifications.sent_as(id).map(|n| n.id()),
+                    History::Hidden => self.notifications.contains(id).then_some(id),
+                };
+                let known = if self.cancel_snooze(id) || self.notifications.withdraw(id) {
+                    log::info!("Closing notification with id={id} before it was shown");
+                    if let Err(e) = dbus::xdg::unhold(&self.db, id) {
+                        log::warn!("Failed to forget waiting notification: {e}");
+                    }
+                    _ = self.emit_sender.send(EmitEvent::NotificationClosed {
+                        id,
+                        reason: Reason::CloseNotificationCall,
+                    });
+                    true
+                } else if let Some(shown) = shown {
+                    log::info!("Closing notification with id={id}");
+                    match self.history {
+                        // Taken off the screen, the entry stays in the history
+                        History::Shown => {
+                            self.notifications.dismiss(shown);
+                            _ = self.emit_sender.send(EmitEvent::NotificationClosed {
+                                id,
+                                reason: Reason::CloseNotificationCall,
+                            });
+                        }
+                        History::Hidden => {
+                            self.dismiss_by_id(shown, Some(Reason::CloseNotificationCall))
+                        }
+                    }
+                    true
+                } else {
+                    log::debug!("No notification with id={id} to close");
+                    false
                 };
 
-                self.notifications.add(NotificationData { id, ..*data })?;
-
-                if self.notifications.inhibited() || suppress_sound {
-                    log::debug!("Sound suppressed for notification");
-                } else if let Some(path) = path {
-                    log::debug!("Playing notification sound");
-                    self.audio.play(path)?;
+                if let Some(reply) = reply {
+                    _ = reply.send(known);
                 }
-
-                if let Some(notification) = self.notifications.notifications().last() {
-                    self.db.execute(
-                        "INSERT INTO notifications (id, app_name, app_icon, timeout, summary, body, actions, hints)
-                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-                        params![
-                            notification.data.id,
-                            notification.data.app_name,
-                            notification.data.app_icon,
-                            notification.data.timeout,
-                            notification.data.summary,
-                            notification.data.body,
-                            serde_json::to_string(&notification.data.actions)?,
-                            serde_json::to_string(&notification.data.hints)?
-                        ],
-                )?;
-                }
-            }
-            Event::CloseNotification(id) => {
-                log::info!("Closing notification with id={id}");
-                self.dismiss_by_id(id, Some(Reason::CloseNotificationCall))
             }
             Event::FocusSurface => {
-                if let Some(surface) = self.surface.as_mut() {
+                if let Some(surface) = self.active_surface_mut() {
                     if surface.focus_reason.is_none() {
                         log::info!("Focusing notification surface");
                         surface.focus(FocusReason::Ctl);
@@ -321,87 +598,56 @@ impl Moxnotify {
                     }
                 }
             }
-            Event::List => {
+            Event::List { redact } => {
                 log::info!("Listing all active notifications");
+                let history = &self.config.general.history;
                 let list = self
                     .notifications
                     .notifications()
                     .iter()
-                    .map(|notification| serde_json::to_string(&notification.data).unwrap())
+                    .map(ListedNotification::from)
+                    .map(|mut listed| {
+                        if redact && history.redacts(&listed.app_name) {
+                            listed.summary = dbus::xdg::REDACTED.into();
+                            listed.body.clear();
+                        }
+                        listed
+                    })
                     .collect::<Vec<_>>();
                 _ = self.emit_sender.send(EmitEvent::List(list));
-
-                return Ok(());
             }
             Event::Mute => {
-                if !self.audio.muted() {
+                let Some(audio) = self.audio.as_mut() else {
+                    log::debug!("Audio unavailable");
+                    return Ok(());
+                };
+
+                if !audio.muted() {
                     log::info!("Muting notification sounds");
                     _ = self.emit_sender.send(EmitEvent::MuteStateChanged(true));
-                    self.audio.mute();
+                    audio.mute();
                 } else {
                     log::debug!("Audio already muted");
                 }
-
-                return Ok(());
             }
             Event::Unmute => {
-                if self.audio.muted() {
+                let Some(audio) = self.audio.as_mut() else {
+                    log::debug!("Audio unavailable");
+                    return Ok(());
+                };
+
+                if audio.muted() {
                     log::info!("Unmuting notification sounds");
-                    self.audio.unmute();
+                    audio.unmute();
                     _ = self
                         .emit_sender
-                        .send(EmitEvent::MuteStateChanged(self.audio.muted()));
+                        .send(EmitEvent::MuteStateChanged(audio.muted()));
                 } else {
                     log::debug!("Audio already unmuted");
                 }
-
-                return Ok(());
-            }
-            Event::ShowHistory => {
-                if self.history == History::Hidden {
-                    self.db.execute(
-                        "DELETE FROM notifications WHERE rowid IN (
-                            SELECT rowid FROM notifications 
-                            ORDER BY rowid ASC 
-                            LIMIT MAX(0, (SELECT COUNT(*) FROM notifications) - ?)
-                        )",
-                        params![self.config.general.history.size],
-                    )?;
-
-                    log::info!("Showing notification history");
-                    self.history = History::Shown;
-                    _ = self
-                        .emit_sender
-                        .send(EmitEvent::HistoryStateChanged(self.history));
-                    self.dismiss_range(.., Some(Reason::Expired));
-                    let mut stmt = self.db.prepare("SELECT rowid, app_name, app_icon, summary, body, actions, hints FROM notifications ORDER BY rowid DESC")?;
-                    let rows = stmt.query_map([], |row| {
-                        Ok(NotificationData {
-                            id: row.get(0)?,
-                            app_name: row.get(1)?,
-                            app_icon: row.get::<_, Option<Box<str>>>(2)?,
-                            summary: row.get::<_, Box<str>>(3)?,
-                            body: row.get::<_, Box<str>>(4)?,
-                            timeout: 0,
-                            actions: {
-                                let json: Box<str> = row.get(5)?;
-                                serde_json::from_str(&json).unwrap()
-                            },
-                            hints: {
-                                let json: Box<str> = row.get(6)?;
-                                serde_json::from_str(&json).unwrap()
-                            },
-                        })
-                    })?;
-                    let notifications = rows.collect::<Result<Vec<_>, _>>()?;
-                    log::info!("Loaded {} historical notifications", notifications.len());
-                    self.notifications.add_many(notifications)?;
-                    drop(stmt);
-                    log::debug!("History view completed");
-                } else {
-                    log::debug!("History already shown");
-                }
             }
+            Event::ShowHistory => self.show_history(None)?,
+            Event::SearchHistory(query) => self.show_history(Some(query))?,
             Event::HideHistory => {
                 if self.history == History::Shown {
                     self.db.execute(
@@ -415,9 +661,23 @@ impl Moxnotify {
 
                     log::info!("Hiding notification history");
                     self.history = History::Hidden;
+                    self.history_page = HistoryPage::default();
+                    self.notifications.history = self.history;
                     _ = self
                         .emit_sender
                         .send(EmitEvent::HistoryStateChanged(self.history));
+                    // Transient notifications skip the database, so the ones that came in
+                    // while the history was shown are still waited on by their senders
+                    self.notifications
+                        .notifications()
+                        .iter()
+                        .filter(|notification| notification.data.hints.transient)
+                        .for_each(|notification| {
+                            _ = self.emit_sender.send(EmitEvent::NotificationClosed {
+                                id: notification.id(),
+                                reason: Reason::DismissedByUser,
+                            });
+                        });
                     self.dismiss_range(.., None);
                     log::debug!("History view dismissed");
                 } else {
@@ -425,93 +685,193 @@ impl Moxnotify {
                 }
             }
             Event::Inhibit => {
-                if !self.notifications.inhibited() {
+                if !self.notifications.inhibit_state().manual {
                     log::info!("Inhibiting notifications");
                     self.notifications.inhibit();
+                    self.persist_inhibit();
                     _ = self.emit_sender.send(EmitEvent::InhibitStateChanged(
-                        self.notifications.inhibited(),
+                        self.notifications.inhibit_state(),
                     ));
                 } else {
                     log::debug!("Notifications already inhibited");
                 }
             }
             Event::Uninhibit => {
-                if self.notifications.inhibited() {
+                if self.notifications.inhibit_state().manual {
                     log::info!("Uninhibiting notifications");
 
-                    let count = self.notifications.waiting();
-                    log::debug!("Processing {count} waiting notifications");
-
-                    let mut stmt = self.db.prepare("SELECT id, app_name, app_icon, summary, body, timeout, actions, hints FROM notifications ORDER BY rowid DESC LIMIT ?1")?;
-                    let rows = stmt.query_map([count], |row| {
-                        Ok(NotificationData {
-                            id: row.get(0)?,
-                            app_name: row.get(1)?,
-                            app_icon: row.get::<_, Option<Box<str>>>(2)?,
-                            summary: row.get::<_, Box<str>>(3)?,
-                            body: row.get::<_, Box<str>>(4)?,
-                            timeout: row.get(5)?,
-                            actions: {
-                                let json: Box<str> = row.get(6)?;
-                                serde_json::from_str(&json).unwrap()
-                            },
-                            hints: {
-                                let json: Box<str> = row.get(7)?;
-                                serde_json::from_str(&json).unwrap()
-                            },
-                        })
-                    })?;
-
-                    self.notifications.uninhibit();
+                    let waiting = self.notifications.uninhibit();
+                    self.persist_inhibit();
                     _ = self.emit_sender.send(EmitEvent::InhibitStateChanged(
-                        self.notifications.inhibited(),
+                        self.notifications.inhibit_state(),
                     ));
-
-                    rows.into_iter()
-                        .try_for_each(|notification| self.notifications.add(notification?))?;
-                    drop(stmt);
+                    self.replay_waiting(waiting)?;
                 } else {
                     log::debug!("Notifications already uninhibited");
                 }
             }
+            Event::Screencast(active) => {
+                if self.notifications.inhibit_state().screencast == active {
+                    return Ok(());
+                }
+
+                match active {
+                    true => log::info!("Screencast started, inhibiting notifications"),
+                    false => log::info!("Screencast ended"),
+                }
+
+                let waiting = self.notifications.set_auto_inhibit(active);
+                _ = self.emit_sender.send(EmitEvent::InhibitStateChanged(
+                    self.notifications.inhibit_state(),
+                ));
+                self.replay_waiting(waiting)?;
+            }
+            Event::DndSchedule(active) => {
+                if self.notifications.inhibit_state().schedule == active {
+                    return Ok(());
+                }
+
+                match active {
+                    true => log::info!("Entering scheduled do not disturb window"),
+                    false => log::info!("Leaving scheduled do not disturb window"),
+                }
+
+                let waiting = self.notifications.set_scheduled_inhibit(active);
+                _ = self.emit_sender.send(EmitEvent::InhibitStateChanged(
+                    self.notifications.inhibit_state(),
+                ));
+                self.replay_waiting(waiting)?;
+            }
+            Event::Locked(locked) => {
+                if self.notifications.locked() == locked {
+                    return Ok(());
+                }
+
+                match locked {
+                    true => log::info!("Session locked, holding back notifications"),
+                    false => log::info!("Session unlocked"),
+                }
+
+                let waiting = self.notifications.set_locked(locked);
+                self.replay_waiting(waiting)?;
+            }
             Event::GetMuted => {
                 log::debug!("Getting audio mute state");
-                _ = self.emit_sender.send(EmitEvent::Muted(self.audio.muted()));
-
-                return Ok(());
+                _ = self.emit_sender.send(EmitEvent::Muted(
+                    self.audio.as_ref().is_none_or(Audio::muted),
+                ));
             }
             Event::GetInhibited => {
                 log::debug!("Getting inhibit state");
                 _ = self
                     .emit_sender
-                    .send(EmitEvent::Inhibited(self.notifications.inhibited()));
-
-                return Ok(());
+                    .send(EmitEvent::Inhibited(self.notifications.inhibit_state()));
             }
             Event::GetHistory => {
                 log::debug!("Getting history state");
                 _ = self.emit_sender.send(EmitEvent::HistoryState(self.history));
-
+            }
+            Event::GetStatus => {
+                log::debug!("Getting daemon status");
+                _ = self.emit_sender.send(EmitEvent::Status(Status {
+                    muted: self.audio.as_ref().is_none_or(Audio::muted),
+                    inhibited: self.notifications.inhibit_state(),
+                    history: self.history,
+                    waiting: self.notifications.waiting(),
+                    active_count: self.notifications.count(),
+                    renderer: self.wgpu_state.adapter_name(),
+                }));
+            }
+            Event::SoundLoaded { id, playback } => {
+                // The notification may have been closed while its sound was decoded
+                if !self.notifications.contains(id) {
+                    log::debug!("Notification id={id} is gone, not playing its sound");
+                } else if let Some(audio) = self.audio.as_mut() {
+                    audio.start(id, *playback);
+                }
+            }
+            Event::Quit => {
+                self.shutdown();
                 return Ok(());
             }
+            Event::Screenshot(path) => {
+                let result = self.screenshot(&path).map_err(|e| {
+                    log::error!("Screenshot failed: {e:#}");
+                    Arc::from(format!("{e:#}"))
+                });
+                if result.is_ok() {
+                    log::info!("Screenshot written to {}", path.display());
+                }
+                _ = self.emit_sender.send(EmitEvent::Screenshot(result));
+            }
+            Event::SetOutput(name) => {
+                let result = self.set_output(&name).map_err(|e| {
+                    log::warn!("{e}");
+                    Arc::from(e.to_string())
+                });
+                _ = self.emit_sender.send(EmitEvent::OutputSet(result));
+            }
+            Event::SetAnchor(anchor) => self.set_anchor(anchor),
+            Event::SetCompact(compact) => _ = self.notifications.set_compact(compact),
+            Event::ToggleCompact => {
+                let compact = !self.notifications.compact();
+                self.notifications.set_compact(compact);
+            }
+            Event::GetCompact => {
+                log::debug!("Getting compact layout state");
+                _ = self
+                    .emit_sender
+                    .send(EmitEvent::Compact(self.notifications.compact()));
+            }
+            Event::Exit => {
+                log::info!("Notifications closed, exiting");
+                self.loop_signal.stop();
+                return Ok(());
+            }
+            Event::IconLoaded {
+                id,
+                path,
+                icon,
+                frames,
+            } => {
+                if !self
+                    .notifications
+                    .set_icon(id, &path, icon.map(|icon| *icon), frames)
+                {
+                    return Ok(());
+                }
+            }
+            Event::MediaProgress { id, value } => {
+                if !self.notifications.set_value(id, value) {
+                    return Ok(());
+                }
+            }
+            Event::ImageLoaded { id, src, image } => {
+                if !self
+                    .notifications
+                    .set_body_image(id, &src, image.map(|image| *image))
+                {
+                    return Ok(());
+                }
+            }
+            Event::ActionIconLoaded { id, action, icon } => {
+                if !self.notifications.set_action_icon(id, &action, *icon) {
+                    return Ok(());
+                }
+            }
             Event::Waiting => {
                 log::debug!("Getting waiting notification count");
                 _ = self
                     .emit_sender
                     .send(EmitEvent::Waiting(self.notifications.waiting()));
-
-                return Ok(());
             }
         };
 
-        self.update_surface_size();
-        if let Some(surface) = self.surface.as_mut() {
-            surface.render(
-                &self.wgpu_state.device,
-                &self.wgpu_state.queue,
-                &self.notifications,
-            )?;
+        if self.notifications.needs_redraw() {
+            self.update_surface_size();
+            self.render_surfaces()?;
         }
+
         Ok(())
     }
 }
@@ -523,7 +883,9 @@ pub enum Image {
     Data(ImageData),
 }
 
-#[derive(PartialEq, Eq, Serialize, Deserialize, Default, Debug, Clone, Copy)]
+#[derive(
+    PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Default, Debug, Clone, Copy, ValueEnum,
+)]
 pub enum Urgency {
     Low,
     #[default]
@@ -552,6 +914,10 @@ pub enum Hint {
 #[derive(Clone)]
 pub enum EmitEvent {
     Waiting(u32),
+    CountChanged(u32),
+    Dismissed(u32),
+    /// Reply to `Event::InvokeAction`, telling whether the notification and action exist.
+    ActionResult(Result<(), ActionError>),
     ActionInvoked {
         id: NotificationId,
         key: Arc<str>,
@@ -565,34 +931,123 @@ pub enum EmitEvent {
         uri: Arc<str>,
         token: Option<Arc<str>>,
     },
-    List(Vec<String>),
+    NotificationReplied {
+        id: NotificationId,
+        text: Arc<str>,
+    },
+    List(Vec<ListedNotification>),
     MuteStateChanged(bool),
     HistoryStateChanged(History),
-    InhibitStateChanged(bool),
+    InhibitStateChanged(InhibitState),
     Muted(bool),
     HistoryState(History),
-    Inhibited(bool),
+    Inhibited(InhibitState),
+    Status(Status),
+    /// Reply to `Event::Screenshot`, with the error if it couldn't be taken.
+    Screenshot(Result<(), Arc<str>>),
+    /// Reply to `Event::SetOutput`, with the error if there's no such output.
+    OutputSet(Result<(), Arc<str>>),
+    Compact(bool),
+    /// Notifications with a `desktop-entry` hint, sent whenever the ones shown change while
+    /// `general.mpris_enrichment` is set.
+    MediaNotifications(Vec<(NotificationId, Arc<str>)>),
+    /// Notification as it arrived, after rules were applied to it.
+    NotificationReceived(ListedNotification),
+    /// Sent after the last `NotificationClosed` before exiting, answered with `Event::Exit`
+    /// once the signals before it are out.
+    Shutdown,
 }
 
 #[derive(Debug)]
 pub enum Event {
     Waiting,
-    Dismiss { all: bool, id: NotificationId },
-    InvokeAction { id: NotificationId, key: Arc<str> },
+    Dismiss {
+        all: bool,
+        id: NotificationId,
+        app: Option<Box<str>>,
+    },
+    InvokeAction {
+        id: NotificationId,
+        key: Arc<str>,
+    },
     InvokeAnchor(Arc<str>),
+    /// Opens the first link of notification `id`, see [`NotificationManager::first_link`]. The
+    /// reply tells whether there was one.
+    OpenLink {
+        id: Option<NotificationId>,
+        reply: Option<oneshot::Sender<bool>>,
+    },
+    ToggleExpand(NotificationId),
+    TogglePin(NotificationId),
     Notify(Box<NotificationData>),
-    CloseNotification(u32),
-    List,
+    /// `reply` is told whether a notification with `id` existed.
+    CloseNotification {
+        id: NotificationId,
+        reply: Option<oneshot::Sender<bool>>,
+    },
+    Snooze {
+        id: NotificationId,
+        minutes: u32,
+    },
+    /// `redact` hides summaries and bodies of apps in `history.exclude_body_apps`.
+    List {
+        redact: bool,
+    },
     FocusSurface,
     Mute,
     Unmute,
     GetMuted,
     ShowHistory,
+    SearchHistory(Box<str>),
     HideHistory,
     GetHistory,
+    GetStatus,
     Inhibit,
     Uninhibit,
     GetInhibited,
+    Screencast(bool),
+    DndSchedule(bool),
+    Locked(bool),
+    IconLoaded {
+        id: NotificationId,
+        path: Box<Path>,
+        icon: Option<Box<ImageData>>,
+        /// Every frame of the image if it's animated, empty otherwise.
+        frames: Arc<[ImageFrame]>,
+    },
+    ImageLoaded {
+        id: NotificationId,
+        src: Arc<str>,
+        image: Option<Box<ImageData>>,
+    },
+    /// Icon of action `action` of notification `id`, for the action-icons hint.
+    ActionIconLoaded {
+        id: NotificationId,
+        action: Arc<str>,
+        icon: Box<ImageData>,
+    },
+    SoundLoaded {
+        id: NotificationId,
+        playback: Box<Playback>,
+    },
+    /// Playback position of the media player notification `id` came from, in percent.
+    MediaProgress {
+        id: NotificationId,
+        value: i32,
+    },
+    /// Closes the notifications and exits, same as SIGTERM.
+    Quit,
+    /// Writes what's shown to a PNG, answered with `EmitEvent::Screenshot`.
+    Screenshot(PathBuf),
+    /// Moves notifications to another output, answered with `EmitEvent::OutputSet`.
+    SetOutput(Box<str>),
+    SetAnchor(Anchor),
+    /// Switches notifications to the layout showing only their summary, or back.
+    SetCompact(bool),
+    ToggleCompact,
+    /// Answered with `EmitEvent::Compact`.
+    GetCompact,
+    Exit,
 }
 
 impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for Moxnotify {
@@ -615,10 +1070,22 @@ impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for Moxnotify {
 
                     let output = Output::new(output, name);
                     state.outputs.push(output);
+                    state.update_surface_size();
+                } else if interface.as_str() == "wl_seat" {
+                    state.seats.add(registry, qh, name, version);
                 }
             }
             wl_registry::Event::GlobalRemove { name } => {
-                state.outputs.retain(|output| output.id != name);
+                if state.outputs.iter().any(|output| output.id == name) {
+                    log::info!("Output removed, moving its notifications elsewhere");
+                    state.outputs.retain(|output| output.id != name);
+                    state
+                        .surfaces
+                        .retain(|surface| surface.output != Some(name));
+                    state.update_surface_size();
+                } else {
+                    state.seats.remove(name, &state.loop_handle);
+                }
             }
             _ => unreachable!(),
         }
@@ -643,24 +1110,64 @@ impl Dispatch<wl_output::WlOutput, ()> for Moxnotify {
         };
 
         match event {
-            wl_output::Event::Scale { factor } => output.scale = factor as f32,
-            wl_output::Event::Name { name } => output.name = Some(name.into()),
+            wl_output::Event::Scale { factor } => {
+                output.scale = factor as f32;
+                output.changed = true;
+            }
+            wl_output::Event::Name { name } => {
+                output.name = Some(name.into());
+                output.changed = true;
+            }
+            wl_output::Event::Geometry {
+                transform: WEnum::Value(transform),
+                ..
+            } => {
+                output.transform = transform;
+                output.changed = true;
+            }
+            wl_output::Event::Mode {
+                flags: WEnum::Value(flags),
+                width,
+                height,
+                ..
+            } if flags.contains(wl_output::Mode::Current) => {
+                output.size = (width, height);
+                output.changed = true;
+            }
+            // Properties arrive in a batch terminated by done, surfaces are only touched once
+            wl_output::Event::Done if output.changed => {
+                output.changed = false;
+                log::debug!(
+                    "Output {} changed: {}x{}, scale {}, {:?}",
+                    output.name.as_deref().unwrap_or("unknown"),
+                    output.size.0,
+                    output.size.1,
+                    output.scale,
+                    output.transform
+                );
+                let id = output.id;
+                state.output_changed(id);
+            }
             _ => {}
         }
     }
 }
 
-impl Dispatch<xdg_activation_token_v1::XdgActivationTokenV1, ()> for Moxnotify {
+impl Dispatch<xdg_activation_token_v1::XdgActivationTokenV1, wl_surface::WlSurface> for Moxnotify {
     fn event(
         state: &mut Self,
         _: &xdg_activation_token_v1::XdgActivationTokenV1,
         event: <xdg_activation_token_v1::XdgActivationTokenV1 as Proxy>::Event,
-        _: &(),
+        wl_surface: &wl_surface::WlSurface,
         _: &Connection,
         _: &QueueHandle<Self>,
     ) {
         if let xdg_activation_token_v1::Event::Done { token } = event {
-            if let Some(surface) = state.surface.as_mut() {
+            if let Some(surface) = state
+                .surfaces
+                .iter_mut()
+                .find(|surface| surface.wl_surface == *wl_surface)
+            {
                 surface.token = Some(token.into());
             }
         }
@@ -682,36 +1189,98 @@ struct Cli {
 
     #[arg(short, long, value_name = "FILE", help = "Path to the config file")]
     config: Option<Box<Path>>,
-}
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
-
-    let mut log_level = LevelFilter::Info;
+    #[arg(
+        long,
+        value_name = "SUMMARY",
+        help = "Show a single notification without D-Bus and exit once it's gone"
+    )]
+    test_notification: Option<Box<str>>,
+
+    #[arg(
+        long,
+        requires = "test_notification",
+        help = "Body of the test notification"
+    )]
+    body: Option<Box<str>>,
+
+    #[arg(
+        long,
+        requires = "test_notification",
+        default_value = "normal",
+        help = "Urgency of the test notification"
+    )]
+    urgency: Urgency,
+
+    #[arg(
+        long,
+        conflicts_with = "test_notification",
+        help = "Run in the background once notifications are served"
+    )]
+    fork: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "test_notification",
+        help = "Write the pid to $XDG_RUNTIME_DIR/moxnotify.pid"
+    )]
+    pidfile: bool,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Append logs to a file instead of stderr"
+    )]
+    log_file: Option<Box<Path>>,
+
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        default_value = "text",
+        help = "Format of logs"
+    )]
+    log_format: logging::LogFormat,
+
+    #[arg(
+        long,
+        value_name = "LAYER",
+        help = "Layer critical notifications go on, overriding general.urgency_layer. Falls back to $MOXNOTIFY_CRITICAL_LAYER"
+    )]
+    critical_layer: Option<config::Layer>,
+}
 
-    (0..cli.verbose).for_each(|_| {
-        log_level = match log_level {
-            LevelFilter::Error => LevelFilter::Warn,
-            LevelFilter::Warn => LevelFilter::Info,
-            LevelFilter::Info => LevelFilter::Debug,
-            LevelFilter::Debug => LevelFilter::Trace,
-            _ => log_level,
-        };
-    });
+/// Layer given by `--critical-layer`, or else by `MOXNOTIFY_CRITICAL_LAYER`.
+fn critical_layer(layer: Option<config::Layer>) -> Option<config::Layer> {
+    layer.or_else(|| {
+        let layer = std::env::var("MOXNOTIFY_CRITICAL_LAYER").ok()?;
+        <config::Layer as ValueEnum>::from_str(&layer, true)
+            .inspect_err(|e| log::warn!("Ignoring MOXNOTIFY_CRITICAL_LAYER: {e}"))
+            .ok()
+    })
+}
 
-    (0..cli.quiet).for_each(|_| {
-        log_level = match log_level {
-            LevelFilter::Warn => LevelFilter::Error,
-            LevelFilter::Info => LevelFilter::Warn,
-            LevelFilter::Debug => LevelFilter::Info,
-            LevelFilter::Trace => LevelFilter::Debug,
-            _ => log_level,
-        };
-    });
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
 
-    Builder::new().filter(Some("daemon"), log_level).init();
+    logging::init(
+        logging::level(cli.verbose, cli.quiet),
+        cli.log_format,
+        cli.log_file.as_deref(),
+    )?;
+
+    // The runtime spawns threads, which don't survive a fork
+    let ready = match cli.fork {
+        true => Some(daemonize::fork()?),
+        false => None,
+    };
+
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(run(cli, ready))
+}
 
+async fn run(cli: Cli, ready: Option<daemonize::Ready>) -> anyhow::Result<()> {
     let conn = Connection::connect_to_env().expect("Failed to connect to Wayland");
     let (globals, event_queue) = registry_queue_init(&conn)?;
     let qh = event_queue.handle();
@@ -719,14 +1288,18 @@ async fn main() -> anyhow::Result<()> {
     let (emit_sender, emit_receiver) = broadcast::channel(std::mem::size_of::<EmitEvent>());
     let (event_sender, event_receiver) = calloop::channel::channel();
     let mut event_loop = EventLoop::try_new()?;
+    let standalone = cli.test_notification.is_some();
     let mut moxnotify = Moxnotify::new(
         &conn,
         qh,
         globals,
         event_loop.handle(),
+        event_loop.get_signal(),
         emit_sender.clone(),
         event_sender.clone(),
         cli.config,
+        critical_layer(cli.critical_layer),
+        standalone,
     )
     .await?;
 
@@ -745,34 +1318,70 @@ async fn main() -> anyhow::Result<()> {
                 );
                 let output = Output::new(wl_output, global.name);
                 moxnotify.outputs.push(output);
+            } else if global.interface == wl_seat::WlSeat::interface().name {
+                moxnotify.seats.add(
+                    moxnotify.globals.registry(),
+                    &moxnotify.qh,
+                    global.name,
+                    global.version,
+                );
             }
         });
     });
 
-    let (executor, scheduler) = calloop::futures::executor()?;
-
-    {
-        let event_sender = event_sender.clone();
-        scheduler.schedule(async move {
-            if let Err(e) = dbus::xdg::serve(event_sender, emit_receiver).await {
-                log::error!("{e}");
-            }
-        })?;
+    moxnotify.apply_dnd_schedule();
+    if !standalone {
+        moxnotify.restore_snoozed()?;
     }
 
-    let emit_receiver = emit_sender.subscribe();
-    scheduler.schedule(async move {
-        if let Err(e) = dbus::moxnotify::serve(event_sender, emit_receiver).await {
-            log::error!("{e}");
+    let (executor, scheduler) = calloop::futures::executor()?;
+    let pidfile = Rc::new(Cell::new(None));
+    match cli.test_notification {
+        Some(summary) => {
+            let data = NotificationData {
+                id: moxnotify.notifications.ids.next(),
+                app_name: "moxnotify".into(),
+                summary,
+                body: cli.body.unwrap_or_default(),
+                timeout: -1,
+                hints: dbus::xdg::NotificationHints {
+                    urgency: cli.urgency,
+                    // Kept out of the history, it's only there to try the config out
+                    transient: true,
+                    ..Default::default()
+                },
+                timestamp: dbus::xdg::timestamp(),
+                ..Default::default()
+            };
+            moxnotify.handle_app_event(Event::Notify(Box::new(data)))?;
+            if moxnotify.notifications.notifications().is_empty() {
+                log::warn!("Test notification was held back, exiting");
+            }
         }
-    })?;
-
-    let emit_receiver = emit_sender.subscribe();
-    scheduler.schedule(async move {
-        if let Err(e) = dbus::portal::serve(emit_receiver).await {
-            log::error!("{e}");
+        None => {
+            let pidfile = Rc::clone(&pidfile);
+            let serving = move || {
+                if cli.pidfile {
+                    match daemonize::Pidfile::create() {
+                        Ok(file) => pidfile.set(Some(file)),
+                        Err(e) => log::warn!("{e}"),
+                    }
+                }
+                if let Some(ready) = ready {
+                    ready.notify();
+                }
+            };
+
+            serve_dbus(
+                &moxnotify,
+                &scheduler,
+                event_sender,
+                emit_sender,
+                emit_receiver,
+                serving,
+            )?
         }
-    })?;
+    }
 
     event_loop
         .handle()
@@ -790,7 +1399,138 @@ async fn main() -> anyhow::Result<()> {
         })
         .map_err(|e| anyhow::anyhow!("Failed to insert source: {}", e))?;
 
-    event_loop.run(None, &mut moxnotify, |_| {})?;
+    event_loop
+        .handle()
+        .insert_source(
+            Signals::new(&[Signal::SIGTERM, Signal::SIGINT])?,
+            |_, _, moxnotify| moxnotify.shutdown(),
+        )
+        .map_err(|e| anyhow::anyhow!("Failed to insert source: {}", e))?;
+
+    // Without D-Bus nothing else arrives, so the daemon is done once the notification is gone
+    let signal = event_loop.get_signal();
+    event_loop.run(None, &mut moxnotify, |moxnotify| {
+        if standalone && moxnotify.notifications.notifications().is_empty() {
+            signal.stop();
+        }
+    })?;
+
+    moxnotify.close();
+    drop(pidfile);
+
+    Ok(())
+}
+
+/// Serves the D-Bus interfaces notifications arrive through, and watches the session for
+/// screencasts and locks. `serving` is called once org.freedesktop.Notifications is owned.
+fn serve_dbus<F>(
+    moxnotify: &Moxnotify,
+    scheduler: &Scheduler<()>,
+    event_sender: calloop::channel::Sender<Event>,
+    emit_sender: broadcast::Sender<EmitEvent>,
+    emit_receiver: broadcast::Receiver<EmitEvent>,
+    serving: F,
+) -> anyhow::Result<()>
+where
+    F: FnOnce() + 'static,
+{
+    let ids = moxnotify.notifications.ids.clone();
+
+    {
+        let event_sender = event_sender.clone();
+        let capabilities =
+            dbus::xdg::Capabilities::new(&moxnotify.config, moxnotify.audio.is_some());
+        let ids = ids.clone();
+        let notice = Some((event_sender.clone(), ids.clone()));
+        let serving = Rc::new(Cell::new(Some(serving)));
+        scheduler.schedule(async move {
+            let serve = || {
+                let serve = dbus::xdg::serve(
+                    event_sender.clone(),
+                    emit_receiver.resubscribe(),
+                    capabilities.clone(),
+                    ids.clone(),
+                );
+                let serving = Rc::clone(&serving);
+                async move {
+                    let conn = serve.await?;
+                    if let Some(serving) = serving.take() {
+                        serving();
+                    }
+                    Ok::<_, dbus::Error>(conn)
+                }
+            };
+            // Nothing arrives without the name, another daemon owning it from the start is fatal
+            if let Err(e) = dbus::supervise("org.freedesktop.Notifications", notice, serve).await {
+                log::error!("{e}");
+                std::process::exit(1);
+            }
+        })?;
+    }
+
+    {
+        let event_sender = event_sender.clone();
+        let emit_sender = emit_sender.clone();
+        scheduler.schedule(async move {
+            let serve = || {
+                dbus::portal_notification::serve(
+                    event_sender.clone(),
+                    emit_sender.subscribe(),
+                    ids.clone(),
+                )
+            };
+            let name = "org.freedesktop.impl.portal.desktop.moxnotify";
+            if let Err(e) = dbus::supervise(name, None, serve).await {
+                log::error!("Notification portal backend unavailable: {e}");
+            }
+        })?;
+    }
+
+    if moxnotify.config.general.inhibit_on_screencast {
+        let event_sender = event_sender.clone();
+        scheduler.schedule(async move {
+            if let Err(e) = dbus::screencast::watch(event_sender).await {
+                log::warn!("Screencast detection unavailable: {e}");
+            }
+        })?;
+    }
+
+    if moxnotify.config.general.lockscreen != config::Lockscreen::Show {
+        let event_sender = event_sender.clone();
+        scheduler.schedule(async move {
+            if let Err(e) = dbus::session_lock::watch(event_sender).await {
+                log::warn!("Session lock detection unavailable: {e}");
+            }
+        })?;
+    }
+
+    if moxnotify.config.general.mpris_enrichment {
+        let event_sender = event_sender.clone();
+        let emit_receiver = emit_sender.subscribe();
+        scheduler.schedule(async move {
+            if let Err(e) = dbus::mpris::watch(event_sender, emit_receiver).await {
+                log::warn!("Media player progress unavailable: {e}");
+            }
+        })?;
+    }
+
+    {
+        let emit_sender = emit_sender.clone();
+        scheduler.schedule(async move {
+            let serve = || dbus::moxnotify::serve(event_sender.clone(), emit_sender.subscribe());
+            if let Err(e) = dbus::supervise("pl.mox.Notify", None, serve).await {
+                log::error!("{e}");
+            }
+        })?;
+    }
+
+    scheduler.schedule(async move {
+        // The portal is only called, its name is never owned so just the connection is watched
+        let serve = || dbus::portal::serve(emit_sender.subscribe());
+        if let Err(e) = dbus::supervise("org.freedesktop.portal.Desktop", None, serve).await {
+            log::error!("{e}");
+        }
+    })?;
 
     Ok(())
 }
@@ -0,0 +1,88 @@
+use crate::{
+    components::{notification::Notification, Bounds},
+    config::Config,
+    rendering::text_renderer::Text,
+    utils::buffers,
+    Urgency,
+};
+use glyphon::{FontSystem, TextArea};
+
+#[derive(Default)]
+pub struct Group {
+    /// Collapsed notifications hidden behind the group's representative, oldest first.
+    pub members: Vec<Notification>,
+    pub expanded: bool,
+    badge: Option<Text>,
+}
+
+impl Group {
+    pub fn update_badge(&mut self, config: &Config, font_system: &mut FontSystem) {
+        self.badge = (!self.members.is_empty()).then(|| {
+            let style = &config.styles.next;
+            Text::new(
+                &style.font,
+                font_system,
+                style.format.replace("{}", &self.members.len().to_string()),
+            )
+        });
+    }
+
+    pub fn badge_data(
+        &self,
+        config: &Config,
+        bounds: Bounds,
+        scale: f32,
+    ) -> Option<(buffers::Instance, TextArea<'_>)> {
+        let badge = self.badge.as_ref()?;
+        let style = &config.styles.next;
+        let text_bounds = badge.get_bounds();
+
+        let width = text_bounds.width
+            + style.padding.left
+            + style.padding.right
+            + style.border.size.left
+            + style.border.size.right;
+        let height = text_bounds.height
+            + style.padding.top
+            + style.padding.bottom
+            + style.border.size.top
+            + style.border.size.bottom;
+
+        let x = bounds.x + bounds.width - width;
+        let y = bounds.y;
+
+        let instance = buffers::Instance {
+            rect_pos: [x, y],
+            rect_size: [
+                width - style.border.size.left - style.border.size.right,
+                height - style.border.size.top - style.border.size.bottom,
+            ],
+            rect_color: style.background.to_linear(&Urgency::Low),
+            border_radius: style.border.radius.into(),
+            border_size: style.border.size.into(),
+            border_color: style.border.color.to_linear(&Urgency::Low),
+            scale,
+            depth: 0.7,
+        };
+
+        let left = x + style.border.size.left + style.padding.left;
+        let top = y + style.border.size.top + style.padding.top;
+
+        let text_area = TextArea {
+            buffer: &badge.buffer,
+            left,
+            top,
+            scale,
+            bounds: glyphon::TextBounds {
+                left: left as i32,
+                top: top as i32,
+                right: (left + text_bounds.width) as i32,
+                bottom: (top + text_bounds.height) as i32,
+            },
+            default_color: style.font.color.into_glyphon(&Urgency::Low),
+            custom_glyphs: &[],
+        };
+
+        Some((instance, text_area))
+    }
+}
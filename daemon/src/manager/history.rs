@@ -0,0 +1,98 @@
+use super::Reason;
+use crate::{dbus::xdg, EmitEvent, History, Moxnotify};
+use rusqlite::params;
+
+/// Part of the history on screen while it's shown.
+#[derive(Default)]
+pub struct HistoryPage {
+    /// Number of newer entries skipped to get to the page.
+    pub offset: usize,
+    /// Only entries whose summary or body contain it are listed.
+    pub query: Option<Box<str>>,
+}
+
+impl Moxnotify {
+    /// Shows the newest page of the history, limited to entries matching `query` when given.
+    pub fn show_history(&mut self, query: Option<Box<str>>) -> anyhow::Result<()> {
+        match self.history {
+            History::Hidden => {
+                self.db.execute(
+                    "DELETE FROM notifications WHERE rowid IN (
+                        SELECT rowid FROM notifications
+                        ORDER BY rowid ASC
+                        LIMIT MAX(0, (SELECT COUNT(*) FROM notifications) - ?)
+                    )",
+                    params![self.config.general.history.size],
+                )?;
+
+                log::info!("Showing notification history");
+                self.history = History::Shown;
+                self.notifications.history = self.history;
+                _ = self
+                    .emit_sender
+                    .send(EmitEvent::HistoryStateChanged(self.history));
+                self.dismiss_range(.., Some(Reason::Expired));
+            }
+            History::Shown if self.history_page.query == query => {
+                log::debug!("History already shown");
+                return Ok(());
+            }
+            History::Shown => {}
+        }
+
+        if let Some(query) = query.as_deref() {
+            log::info!("Searching notification history for '{query}'");
+        }
+        self.history_page = HistoryPage { offset: 0, query };
+        self.load_history_page()
+    }
+
+    /// Moves to the page of older entries, or of newer ones when `forward` is false. Returns
+    /// false when there's no page in that direction.
+    pub fn turn_history_page(&mut self, forward: bool) -> anyhow::Result<bool> {
+        if self.history == History::Hidden {
+            return Ok(false);
+        }
+
+        let page_size = self.config.general.history.page_size.max(1);
+        let offset = self.history_page.offset;
+        let offset = match forward {
+            true => {
+                let len = xdg::count_history(&self.db, self.history_page.query.as_deref())?;
+                (offset + page_size < len).then_some(offset + page_size)
+            }
+            false => (offset > 0).then(|| offset.saturating_sub(page_size)),
+        };
+
+        let Some(offset) = offset else {
+            log::debug!("No more history pages");
+            return Ok(false);
+        };
+
+        self.history_page.offset = offset;
+        self.load_history_page()?;
+
+        Ok(true)
+    }
+
+    /// Replaces the entries on screen with the current page.
+    fn load_history_page(&mut self) -> anyhow::Result<()> {
+        let HistoryPage { offset, ref query } = self.history_page;
+        let notifications = xdg::load_history(
+            &self.db,
+            query.as_deref(),
+            self.config.general.history.page_size.max(1),
+            offset,
+        )?;
+        log::info!(
+            "Loaded {} historical notifications from offset {offset}",
+            notifications.len()
+        );
+
+        // Entries taken off screen stay in the database
+        self.dismiss_range(.., None);
+        self.notifications.add_many(notifications)?;
+
+        Ok(())
+    }
+}
@@ -1,32 +1,48 @@
+mod group;
+mod history;
+mod schedule;
+mod snooze;
 mod view;
 
 use crate::{
     components::{
         button::ButtonType,
         notification::{Notification, NotificationId},
-        text::Text,
+        text::{input::TextInput, shape_cache::ShapeCache, Text},
         Component, Data,
     },
-    config::{keymaps, Config, Queue},
+    config::{
+        keymaps, Config, General, InhibitPass, Layer, LimitPolicy, Lockscreen, Queue,
+        QueueOverflow, Sort, SurfacePlacement,
+    },
+    dbus::xdg::{self, IdGenerator, NotificationHints},
     rendering::texture_renderer::TextureArea,
-    utils::buffers,
-    EmitEvent, History, Moxnotify, NotificationData,
+    utils::{
+        buffers,
+        image_data::{ImageData, ImageFrame},
+    },
+    EmitEvent, History, Image, InhibitState, Moxnotify, NotificationData, Urgency,
 };
 use atomic_float::AtomicF32;
 use calloop::LoopHandle;
 use glyphon::{FontSystem, TextArea};
+use group::Group;
+pub use history::HistoryPage;
 use rayon::prelude::*;
 use rusqlite::params;
 use std::{
     cell::RefCell,
+    collections::HashMap,
     fmt,
+    path::Path,
     rc::Rc,
     sync::{
         atomic::{AtomicBool, AtomicU32, Ordering},
-        Arc,
+        Arc, Mutex,
     },
+    time::{Duration, Instant, SystemTime},
 };
-use view::NotificationView;
+use view::{Counter, NotificationView};
 
 #[derive(Clone)]
 pub struct UiState {
@@ -34,6 +50,10 @@ pub struct UiState {
     pub mode: Arc<keymaps::AtomicMode>,
     pub selected: Arc<AtomicBool>,
     pub selected_id: Arc<AtomicU32>,
+    /// Keys typed so far in hint mode, only hints starting with them are shown.
+    pub typed_hint: Arc<Mutex<String>>,
+    /// Whether notifications only show their summary, `general.compact` decides how it starts.
+    pub compact: Arc<AtomicBool>,
 }
 
 impl Default for UiState {
@@ -43,20 +63,52 @@ impl Default for UiState {
             scale: Arc::new(AtomicF32::new(1.0)),
             selected: Arc::new(AtomicBool::new(false)),
             selected_id: Arc::new(AtomicU32::new(0)),
+            typed_hint: Arc::new(Mutex::new(String::new())),
+            compact: Arc::new(AtomicBool::new(false)),
         }
     }
 }
 
 pub struct NotificationManager {
     notifications: Vec<Notification>,
-    waiting: u32,
+    /// Notifications held back while inhibited or locked, in arrival order. Kept in memory
+    /// since transient ones never reach the history database.
+    waiting: Vec<NotificationData>,
+    /// Config as it was loaded, `config` is it with percentages resolved against the output.
+    source: Arc<Config>,
     config: Arc<Config>,
+    /// Logical width of the output notifications are shown on, None until it's known.
+    output_width: Option<f32>,
     loop_handle: LoopHandle<'static, Moxnotify>,
     pub font_system: Rc<RefCell<FontSystem>>,
+    /// Lines of the bodies shaped so far, reused by replacements and history shown again.
+    shape_cache: Rc<ShapeCache>,
     pub notification_view: NotificationView,
     sender: calloop::channel::Sender<crate::Event>,
-    inhibited: bool,
+    inhibit: InhibitState,
+    /// Whether the session is locked, tracked apart from the inhibit as it isn't one users
+    /// control.
+    locked: bool,
+    /// Id of the notification counting the ones held back while locked.
+    lock_summary: Option<NotificationId>,
+    queued: Vec<NotificationData>,
+    /// Notifications that haven't been in view yet with `queue = "fifo"`, oldest first.
+    pending: Vec<NotificationId>,
+    timers_paused: bool,
+    /// Set whenever something visible changes, cleared once the surfaces are drawn.
+    needs_redraw: bool,
+    groups: HashMap<Arc<str>, Group>,
+    pub history: History,
     pub ui_state: UiState,
+    /// Shared with the D-Bus servers so that new ids skip the ones of notifications still around.
+    pub ids: IdGenerator,
+    /// Timers re-delivering snoozed notifications, keyed by notification id.
+    snoozed: HashMap<NotificationId, calloop::RegistrationToken>,
+    /// Order notifications were last dragged into. The ones listed keep their places among
+    /// each other, the sort still decides where the others go.
+    order: Vec<NotificationId>,
+    /// Notification the pointer is dragging, drawn over the others.
+    dragged: Option<NotificationId>,
 }
 
 impl NotificationManager {
@@ -67,41 +119,146 @@ impl NotificationManager {
         font_system: Rc<RefCell<FontSystem>>,
     ) -> Self {
         let ui_state = UiState::default();
+        ui_state
+            .compact
+            .store(config.general.compact.enabled, Ordering::Relaxed);
 
         Self {
             sender,
-            inhibited: false,
-            waiting: 0,
+            inhibit: InhibitState::default(),
+            locked: false,
+            lock_summary: None,
+            queued: Vec::new(),
+            pending: Vec::new(),
+            timers_paused: false,
+            needs_redraw: false,
+            groups: HashMap::new(),
+            history: History::Hidden,
+            waiting: Vec::new(),
             notification_view: NotificationView::new(
                 Arc::clone(&config),
                 ui_state.clone(),
                 Rc::clone(&font_system),
             ),
             font_system,
+            shape_cache: Rc::new(ShapeCache::default()),
             loop_handle,
             notifications: Vec::new(),
+            source: Arc::clone(&config),
             config,
+            output_width: None,
             ui_state,
+            ids: IdGenerator::default(),
+            snoozed: HashMap::new(),
+            order: Vec::new(),
+            dragged: None,
         }
     }
 
     pub fn inhibit(&mut self) {
-        self.inhibited = true;
+        self.inhibit.manual = true;
+    }
+
+    /// Lifts the manual inhibit and returns the notifications that were held back, unless a
+    /// screencast still keeps them inhibited.
+    pub fn uninhibit(&mut self) -> Vec<NotificationData> {
+        self.inhibit.manual = false;
+        self.release_waiting()
+    }
+
+    /// Inhibits notifications for as long as a screencast is active, returns the ones held back
+    /// once nothing inhibits them anymore.
+    pub fn set_auto_inhibit(&mut self, active: bool) -> Vec<NotificationData> {
+        self.inhibit.screencast = active;
+        self.release_waiting()
+    }
+
+    /// Inhibits notifications for as long as a do not disturb window is active, returns the ones
+    /// held back once nothing inhibits them anymore.
+    pub fn set_scheduled_inhibit(&mut self, active: bool) -> Vec<NotificationData> {
+        self.inhibit.schedule = active;
+        self.release_waiting()
+    }
+
+    /// Holds notifications back for as long as the session is locked, returns the ones held back
+    /// once nothing holds them anymore.
+    pub fn set_locked(&mut self, locked: bool) -> Vec<NotificationData> {
+        self.locked = locked;
+        if !locked {
+            if let Some(id) = self.lock_summary.take() {
+                self.dismiss(id);
+            }
+        }
+
+        self.release_waiting()
+    }
+
+    pub fn locked(&self) -> bool {
+        self.locked
+    }
+
+    fn release_waiting(&mut self) -> Vec<NotificationData> {
+        match self.inhibited() || self.locked {
+            true => Vec::new(),
+            false => std::mem::take(&mut self.waiting),
+        }
     }
 
-    pub fn uninhibit(&mut self) {
-        self.waiting = 0;
-        self.inhibited = false;
+    pub fn inhibited(&self) -> bool {
+        self.inhibit.inhibited()
     }
 
-    pub fn inhibited(&mut self) -> bool {
-        self.inhibited
+    pub fn inhibit_state(&self) -> InhibitState {
+        self.inhibit
     }
 
+    /// Notifications in the order they arrived in, see [`Self::displayed`] for the order they're
+    /// shown in.
     pub fn notifications(&self) -> &[Notification] {
         &self.notifications
     }
 
+    /// Notifications from the top of the list down.
+    pub fn displayed(&self) -> impl DoubleEndedIterator<Item = &Notification> + '_ {
+        self.display_order()
+            .into_iter()
+            .map(move |index| &self.notifications[index])
+    }
+
+    /// Where the surface is placed, by the lowest urgency among the notifications that aren't
+    /// OSDs, or as an OSD when there are only those.
+    pub fn placement(&self) -> SurfacePlacement {
+        if !self.notifications.is_empty() && self.notifications.iter().all(Notification::osd) {
+            return SurfacePlacement::Osd;
+        }
+
+        SurfacePlacement::Urgency(
+            self.notifications
+                .iter()
+                .filter(|notification| !notification.osd())
+                .map(|notification| *notification.urgency())
+                .min()
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Layer the surface goes on for the notifications on screen.
+    pub fn layer(&self) -> Layer {
+        self.config.general.layer(
+            self.notifications
+                .iter()
+                .map(|notification| *notification.urgency()),
+        )
+    }
+
+    pub fn needs_redraw(&self) -> bool {
+        self.needs_redraw
+    }
+
+    pub fn clear_redraw(&mut self) {
+        self.needs_redraw = false;
+    }
+
     pub fn data(
         &self,
     ) -> (
@@ -113,12 +270,21 @@ impl NotificationManager {
         let mut text_areas = Vec::new();
         let mut textures = Vec::new();
 
-        let all_data: Vec<Data> = self
-            .notifications
+        let mut visible = self.visible();
+        // The notification being dragged passes over the others
+        if let Some(position) = self.dragged.and_then(|id| {
+            visible
+                .iter()
+                .position(|index| self.notifications[*index].id() == id)
+        }) {
+            let index = visible.remove(position);
+            visible.push(index);
+        }
+        let all_data: Vec<Data> = visible
             .iter()
-            .enumerate()
-            .filter(|(i, _)| self.notification_view.visible.contains(i))
-            .flat_map(|(_, notification)| notification.get_data(notification.urgency()))
+            .filter_map(|i| self.notifications.get(*i))
+            .flat_map(|notification| notification.get_data(notification.urgency()))
+            .filter_map(|data| self.notification_view.clip(data))
             .collect();
 
         for data_item in all_data {
@@ -129,6 +295,31 @@ impl NotificationManager {
             }
         }
 
+        visible
+            .iter()
+            .filter_map(|i| self.notifications.get(*i))
+            .filter_map(|notification| {
+                let (instance, text_area) =
+                    self.groups.get(&notification.data.app_name)?.badge_data(
+                        &self.config,
+                        notification.get_render_bounds(),
+                        self.ui_state.scale.load(Ordering::Relaxed),
+                    )?;
+                let (mut instance, mut text_area) =
+                    (Data::Instance(instance), Data::TextArea(text_area));
+                let frame = notification.drawn_frame();
+                frame.apply(&mut instance);
+                frame.apply(&mut text_area);
+                Some([instance, text_area])
+            })
+            .flatten()
+            .filter_map(|data| self.notification_view.clip(data))
+            .for_each(|data| match data {
+                Data::Instance(instance) => instances.push(instance),
+                Data::TextArea(text_area) => text_areas.push(text_area),
+                Data::Texture(texture) => textures.push(texture),
+            });
+
         let total_width = self
             .notifications
             .iter()
@@ -151,12 +342,56 @@ impl NotificationManager {
         (instances, text_areas, textures)
     }
 
+    /// Indices of `notifications` in the order they're shown in, see [`Sort`]. The list itself
+    /// stays in arrival order so that replacing and expiring notifications isn't affected.
+    fn display_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.notifications.len()).collect();
+        let key = |index: &usize| {
+            let notification = &self.notifications[*index];
+            let pinned = self.config.general.sort == Sort::Urgency
+                && *notification.urgency() == Urgency::Critical;
+            (!pinned, notification.received_at)
+        };
+
+        // Stable, so notifications received at the same time keep their arrival order
+        match self.config.general.sort {
+            Sort::NewestFirst => order.sort_by(|a, b| key(b).cmp(&key(a))),
+            Sort::OldestFirst | Sort::Urgency => order.sort_by_key(key),
+        }
+
+        if !self.order.is_empty() {
+            let rank = |index: &usize| {
+                let id = self.notifications[*index].id();
+                self.order.iter().position(|dragged| *dragged == id)
+            };
+            let slots: Vec<usize> = (0..order.len())
+                .filter(|position| rank(&order[*position]).is_some())
+                .collect();
+            let mut dragged: Vec<usize> = slots.iter().map(|position| order[*position]).collect();
+            dragged.sort_by_key(rank);
+            slots
+                .into_iter()
+                .zip(dragged)
+                .for_each(|(position, index)| order[position] = index);
+        }
+
+        order
+    }
+
+    /// Indices of the notifications in view, in display order.
+    fn visible(&self) -> Vec<usize> {
+        self.display_order()
+            .get(self.notification_view.visible.clone())
+            .map(<[usize]>::to_vec)
+            .unwrap_or_default()
+    }
+
+    /// Notification under `x`, `y`, ones on their way out can't be clicked anymore.
     pub fn get_by_coordinates(&self, x: f64, y: f64) -> Option<&Notification> {
-        self.notification_view
-            .visible
-            .clone()
+        self.visible()
+            .into_iter()
             .filter_map(|index| {
-                if let Some(notification) = self.notifications.get(index) {
+                if let Some(notification) = self.notifications.get(index).filter(|n| !n.exiting()) {
                     let extents = notification.get_render_bounds();
                     let x_within_bounds =
                         x >= extents.x as f64 && x <= (extents.x + extents.width) as f64;
@@ -173,64 +408,139 @@ impl NotificationManager {
             .next()
     }
 
-    pub fn click(&mut self, x: f64, y: f64) -> bool {
-        self.notification_view.visible.clone().any(|index| {
+    /// Clicks the button or counter under the pointer, counters page through the list.
+    pub fn click_button(&mut self, x: f64, y: f64) -> bool {
+        if let Some(counter) = self.notification_view.counter_at(x, y) {
+            self.page(counter);
+            return true;
+        }
+
+        self.visible().into_iter().any(|index| {
             self.notifications
                 .get_mut(index)
+                .filter(|notification| !notification.exiting())
                 .map(|notification| notification.buttons.click(x, y))
                 .unwrap_or_default()
         })
     }
 
-    pub fn hover(&mut self, x: f64, y: f64) -> bool {
-        self.notification_view.visible.clone().any(|index| {
-            self.notifications
-                .get_mut(index)
-                .map(|notification| notification.buttons.hover(x, y))
-                .unwrap_or_default()
-        })
+    pub fn click(&mut self, x: f64, y: f64) -> bool {
+        // Buttons and anchors take precedence over the notification body
+        if self.click_button(x, y) {
+            return true;
+        }
+
+        let Some(notification) = self.get_by_coordinates(x, y) else {
+            return false;
+        };
+
+        match notification
+            .data
+            .actions
+            .iter()
+            .find(|(key, _)| &**key == "default")
+        {
+            Some((key, _)) => {
+                _ = self.sender.send(crate::Event::InvokeAction {
+                    id: notification.id(),
+                    key: Arc::clone(key),
+                });
+                true
+            }
+            None => false,
+        }
     }
 
-    pub fn height(&self) -> f32 {
-        let height = self
-            .notification_view
-            .prev
-            .as_ref()
-            .map_or(0., |n| n.get_bounds().height);
-        self.notification_view
-            .visible
-            .clone()
-            .fold(height, |acc, i| {
-                if let Some(notification) = self.notifications.get(i) {
-                    let extents = notification.get_bounds();
-                    return acc + extents.height;
+    /// Updates the hover state of buttons and counters, returns whether any of them is under
+    /// the pointer.
+    pub fn hover(&mut self, x: f64, y: f64) -> bool {
+        let counter = self.notification_view.counter_at(x, y);
+        if counter != self.notification_view.hovered {
+            self.notification_view.hovered = counter;
+            self.needs_redraw = true;
+        }
+
+        self.visible()
+            .into_iter()
+            .fold(counter.is_some(), |hovered, index| {
+                let Some(notification) = self.notifications.get_mut(index) else {
+                    return hovered;
                 };
 
-                acc
+                let over_button = notification.buttons.hover(x, y);
+                if notification.hover_anchor(&mut self.font_system.borrow_mut()) {
+                    self.needs_redraw = true;
+                }
+                over_button || hovered
             })
-            + self
-                .notification_view
-                .next
-                .as_ref()
-                .map_or(0., |n| n.get_bounds().height)
     }
 
-    pub fn width(&self) -> f32 {
-        let (min_x, max_x) =
-            self.notifications
-                .iter()
-                .fold((f32::MAX, f32::MIN), |(min_x, max_x), notification| {
-                    let extents = notification.get_bounds();
-                    let left = extents.x + notification.data.hints.x as f32;
-                    let right = extents.x + extents.width + notification.data.hints.x as f32;
-                    (min_x.min(left), max_x.max(right))
-                });
+    /// Colors no link as hovered after the pointer left the surface, returns whether one was.
+    pub fn unhover_anchors(&mut self) -> bool {
+        let font_system = &mut self.font_system.borrow_mut();
+        let unhovered = self
+            .notifications
+            .iter_mut()
+            .fold(false, |unhovered, notification| {
+                notification.unhover_anchor(font_system) || unhovered
+            });
+        self.needs_redraw |= unhovered;
 
-        if min_x == f32::MAX || max_x == f32::MIN {
-            0.0
-        } else {
-            max_x - min_x
+        unhovered
+    }
+
+    /// Colors links to `href` as visited in every notification showing one.
+    pub fn visit_link(&mut self, href: &str) {
+        let font_system = &mut self.font_system.borrow_mut();
+        self.notifications.iter_mut().for_each(|notification| {
+            if notification.visit_link(font_system, href) {
+                self.needs_redraw = true;
+            }
+        });
+    }
+
+    pub fn height(&self) -> f32 {
+        self.notification_view.height()
+    }
+
+    /// Heights of the notifications in display order.
+    fn heights(&self) -> Vec<f32> {
+        self.display_order()
+            .into_iter()
+            .map(|index| self.notifications[index].get_bounds().height)
+            .collect()
+    }
+
+    /// Scrolls the list by `delta` pixels, returns false when it's already at the edge.
+    pub fn scroll(&mut self, delta: f32) -> bool {
+        let heights = self.heights();
+        if !self.notification_view.scroll(delta, &heights) {
+            return false;
+        }
+
+        self.relayout();
+        true
+    }
+
+    /// Scrolls a whole viewport towards `counter`, returns false when it's already at the edge.
+    pub fn page(&mut self, counter: Counter) -> bool {
+        let heights = self.heights();
+        if !self.notification_view.page(counter, &heights) {
+            return false;
         }
+
+        self.relayout();
+        true
+    }
+
+    /// Width of the union of all notifications, wherever their `x` hint moved them.
+    pub fn width(&self) -> f32 {
+        self.notifications
+            .iter()
+            .map(|notification| {
+                notification.x + notification.offset().0 + notification.get_bounds().width
+            })
+            .fold(0., f32::max)
     }
 
     pub fn selected_id(&self) -> Option<NotificationId> {
@@ -240,6 +550,13 @@ impl NotificationManager {
         }
     }
 
+    pub fn selected_notification(&self) -> Option<&Notification> {
+        let id = self.selected_id();
+        self.notifications
+            .iter()
+            .find(|notification| Some(notification.id()) == id)
+    }
+
     pub fn selected_notification_mut(&mut self) -> Option<&mut Notification> {
         let id = self.selected_id();
         self.notifications
@@ -247,17 +564,67 @@ impl NotificationManager {
             .find(|notification| Some(notification.id()) == id)
     }
 
+    /// Adds `c` to the hint typed so far and clicks the button once its whole combination is
+    /// typed. A key no remaining hint continues with starts over.
+    pub fn type_hint(&mut self, c: char) {
+        let mut typed = std::mem::take(&mut *self.ui_state.typed_hint.lock().unwrap());
+        typed.push(c);
+
+        let font_system = Rc::clone(&self.font_system);
+        if let Some(notification) = self.selected_notification_mut() {
+            let buttons = &mut notification.buttons;
+            let mut font_system = font_system.borrow_mut();
+            if !buttons.highlight_hints(&typed, &mut font_system) {
+                log::debug!("No hint starts with {typed}");
+                typed.clear();
+                buttons.highlight_hints(&typed, &mut font_system);
+            } else if buttons.hint(&typed) {
+                typed.clear();
+                buttons.highlight_hints(&typed, &mut font_system);
+            }
+            // The typed prefix isn't part of what the cached instances are keyed by
+            notification.invalidate();
+        }
+
+        *self.ui_state.typed_hint.lock().unwrap() = typed;
+        self.needs_redraw = true;
+    }
+
+    /// Forgets the typed hint so all hints of the selected notification are shown again.
+    pub fn reset_hint(&mut self) {
+        self.ui_state.typed_hint.lock().unwrap().clear();
+
+        let font_system = Rc::clone(&self.font_system);
+        if let Some(notification) = self.selected_notification_mut() {
+            notification
+                .buttons
+                .highlight_hints("", &mut font_system.borrow_mut());
+            notification.invalidate();
+        }
+        self.needs_redraw = true;
+    }
+
     pub fn select(&mut self, id: NotificationId) {
         self.deselect();
+        self.needs_redraw = true;
 
-        if let Some(notification) = self.notifications.iter_mut().find(|n| n.id() == id) {
-            notification.hover();
-            log::info!("Selected notification id: {id}");
+        let Some(notification) = self.notifications.iter_mut().find(|n| n.id() == id) else {
+            return;
+        };
+        notification.hover();
+        log::info!("Selected notification id: {id}");
 
-            self.ui_state.selected_id.store(id, Ordering::Relaxed);
-            self.ui_state.selected.store(true, Ordering::Relaxed);
+        self.ui_state.selected_id.store(id, Ordering::Relaxed);
+        self.ui_state.selected.store(true, Ordering::Relaxed);
 
-            notification.stop_timer(&self.loop_handle);
+        // The selected notification is shown in full while the others stay compact
+        if self.apply_compact() {
+            self.relayout();
+        }
+
+        if let Some(notification) = self.notifications.iter_mut().find(|n| n.id() == id) {
+            // Paused rather than stopped so that the countdown stays where it was
+            notification.pause_timer(&self.loop_handle);
 
             let dismiss_button = notification
                 .buttons
@@ -277,60 +644,28 @@ impl NotificationManager {
                 None,
             );
 
-            notification.summary.set_size(
-                &mut self.font_system.borrow_mut(),
-                Some(
-                    notification.get_style().width
-                        - notification.icons.get_bounds().width
-                        - dismiss_button,
-                ),
-                None,
-            );
+            notification.fit_summary(&mut self.font_system.borrow_mut());
         }
     }
 
     pub fn next(&mut self) {
-        let next_notification_index = {
+        let order = self.display_order();
+        let next_position = {
             let id = self.ui_state.selected_id.load(Ordering::Relaxed);
-            self.notifications
+            let start = order
                 .iter()
-                .position(|n| n.id() == id)
-                .map_or(0, |index| {
-                    if index + 1 < self.notifications.len() {
-                        index + 1
-                    } else {
-                        0
-                    }
-                })
+                .position(|index| self.notifications[*index].id() == id)
+                .map_or(0, |position| position + 1);
+            self.selectable_from(&order, |offset| start + offset)
         };
 
-        if let Some(notification) = self.notifications.get(next_notification_index) {
-            self.select(notification.id());
-            self.notification_view.next(
-                self.height(),
-                next_notification_index,
-                self.notifications.len(),
-            );
+        if let Some(position) = next_position {
+            self.select(self.notifications[order[position]].id());
+            let heights = self.heights();
+            self.notification_view.scroll_to(position, &heights);
         }
 
-        self.notification_view.visible.clone().fold(
-            self.notification_view
-                .prev
-                .as_ref()
-                .map(|p| p.get_bounds().height)
-                .unwrap_or(0.),
-            |acc, i| {
-                if let Some(notification) = self.notifications.get_mut(i) {
-                    notification.set_position(notification.x, acc);
-                    acc + notification.get_bounds().height
-                } else {
-                    acc
-                }
-            },
-        );
-
-        self.notification_view
-            .update_notification_count(self.height(), self.notifications.len());
+        self.relayout();
     }
 
     pub fn prev(&mut self) {
@@ -338,47 +673,64 @@ impl NotificationManager {
             return;
         }
 
-        let notification_index = {
+        let order = self.display_order();
+        let prev_position = {
             let id = self.ui_state.selected_id.load(Ordering::Relaxed);
-            self.notifications.iter().position(|n| n.id() == id).map_or(
-                self.notifications.len().saturating_sub(1),
-                |index| {
-                    if index > 0 {
-                        index - 1
-                    } else {
-                        self.notifications.len().saturating_sub(1)
-                    }
-                },
-            )
+            let len = order.len();
+            let start = order
+                .iter()
+                .position(|index| self.notifications[*index].id() == id)
+                .map_or(len.saturating_sub(1), |position| {
+                    position + len.saturating_sub(1)
+                });
+            self.selectable_from(&order, |offset| start + len - offset)
         };
 
-        if let Some(notification) = self.notifications.get(notification_index) {
-            self.select(notification.id());
-            self.notification_view.prev(
-                self.height(),
-                notification_index,
-                self.notifications.len(),
-            );
+        if let Some(position) = prev_position {
+            self.select(self.notifications[order[position]].id());
+            let heights = self.heights();
+            self.notification_view.scroll_to(position, &heights);
         }
 
-        self.notification_view.visible.clone().fold(
-            self.notification_view
-                .prev
-                .as_ref()
-                .map(|p| p.get_bounds().height)
-                .unwrap_or(0.),
-            |acc, i| {
-                if let Some(notification) = self.notifications.get_mut(i) {
-                    notification.set_position(notification.x, acc);
-                    acc + notification.get_bounds().height
-                } else {
-                    acc
-                }
-            },
-        );
+        self.relayout();
+    }
+
+    /// Scrolls the selected notification back into view, notifications sorted before it push
+    /// it down as they arrive.
+    fn reveal_selected(&mut self) {
+        let Some(id) = self.selected_id() else {
+            return;
+        };
+
+        let position = self.displayed().position(|n| n.id() == id);
+        if let Some(position) = position {
+            let heights = self.heights();
+            self.notification_view.scroll_to(position, &heights);
+            self.relayout();
+        }
+    }
+
+    /// Moves the selection `n` notifications down, wrapping around like `next`.
+    pub fn next_n(&mut self, n: u32) {
+        (0..n).for_each(|_| self.next());
+    }
 
-        self.notification_view
-            .update_notification_count(self.height(), self.notifications.len());
+    /// Moves the selection `n` notifications up, wrapping around like `prev`.
+    pub fn prev_n(&mut self, n: u32) {
+        (0..n).for_each(|_| self.prev());
+    }
+
+    /// Position in `order` of the first notification that can be selected when stepping
+    /// through the list with `step`, which maps an offset to a position that wraps around. OSD
+    /// notifications are skipped.
+    fn selectable_from<F>(&self, order: &[usize], step: F) -> Option<usize>
+    where
+        F: Fn(usize) -> usize,
+    {
+        let len = order.len();
+        (0..len)
+            .map(|offset| step(offset) % len)
+            .find(|position| !self.notifications[order[*position]].osd())
     }
 
     pub fn deselect(&mut self) {
@@ -387,12 +739,18 @@ impl NotificationManager {
         }
 
         self.ui_state.selected.store(false, Ordering::Relaxed);
+        self.needs_redraw = true;
 
         let old_id = self.ui_state.selected_id.load(Ordering::Relaxed);
+        if self.apply_compact() {
+            self.relayout();
+        }
         if let Some(index) = self.notifications.iter().position(|n| n.id() == old_id) {
             if let Some(notification) = self.notifications.get_mut(index) {
                 notification.unhover();
+                notification.buttons.unfocus();
                 match self.config.general.queue {
+                    _ if self.timers_paused => {}
                     Queue::FIFO if index == 0 => notification.start_timer(&self.loop_handle),
                     Queue::Unordered => notification.start_timer(&self.loop_handle),
                     _ => {}
@@ -401,491 +759,3175 @@ impl NotificationManager {
         }
     }
 
-    pub fn waiting(&self) -> u32 {
-        self.waiting
+    pub fn pause_all_timers(&mut self) {
+        if self.timers_paused {
+            return;
+        }
+
+        log::debug!("Pausing expiration timers");
+        self.timers_paused = true;
+        self.notifications
+            .iter_mut()
+            .for_each(|notification| notification.pause_timer(&self.loop_handle));
     }
 
-    pub fn add_many(&mut self, data: Vec<NotificationData>) -> anyhow::Result<()> {
-        let new_notifications: Vec<Notification> = data
-            .into_par_iter()
-            .map_init(
-                FontSystem::new, // Initialize font system once per thread
-                |font_system, data| {
-                    Notification::new(
-                        Arc::clone(&self.config),
-                        font_system,
-                        data,
-                        self.ui_state.clone(),
-                        None,
-                    )
-                },
-            )
-            .collect();
+    pub fn resume_all_timers(&mut self) {
+        if !self.timers_paused {
+            return;
+        }
 
-        self.notifications.extend(new_notifications);
+        log::debug!("Resuming expiration timers");
+        self.timers_paused = false;
+        let selected_id = self.selected_id();
+        self.notifications
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, notification)| Some(notification.id()) != selected_id)
+            .for_each(|(i, notification)| match self.config.general.queue {
+                Queue::FIFO if i == 0 => notification.start_timer(&self.loop_handle),
+                Queue::Unordered => notification.start_timer(&self.loop_handle),
+                _ => {}
+            });
+    }
 
-        let mut y = self
-            .notifications
-            .last()
-            .map(|notification| notification.y)
-            .unwrap_or_default();
-        self.notifications.iter_mut().for_each(|notification| {
-            notification.set_position(0.0, y);
+    pub fn waiting(&self) -> u32 {
+        self.waiting.len() as u32
+    }
 
-            let height = notification.get_bounds().height;
-            y += height;
-        });
+    /// Whether notification `id` is shown, collapsed into a group, held back or queued. Ones
+    /// already on their way out don't count.
+    pub fn contains(&self, id: NotificationId) -> bool {
+        self.notifications
+            .iter()
+            .filter(|n| !n.exiting())
+            .chain(self.groups.values().flat_map(|group| &group.members))
+            .any(|n| n.id() == id)
+            || self
+                .queued
+                .iter()
+                .chain(&self.waiting)
+                .any(|data| data.id == id)
+    }
 
-        if self.notification_view.visible.end < self.notifications.len() {
-            self.notification_view
-                .update_notification_count(self.height(), self.notifications.len());
-        }
+    /// History row of the notification `data` replaces, by id or synchronous tag, so that the
+    /// replacement is stored in its place. None if there's no such notification from the app.
+    pub fn replaced_row(&self, data: &NotificationData) -> Option<i64> {
+        self.notifications
+            .iter()
+            .chain(self.groups.values().flat_map(|group| &group.members))
+            .map(|n| &n.data)
+            .chain(self.queued.iter().chain(&self.waiting))
+            .find(|other| {
+                other.app_name == data.app_name
+                    && (other.id == data.id
+                        || data
+                            .hints
+                            .synchronous
+                            .as_ref()
+                            .is_some_and(|tag| other.hints.synchronous.as_ref() == Some(tag)))
+            })
+            .and_then(|other| other.rowid)
+    }
 
-        let x_offset = self
-            .notifications
+    /// History entry on screen that was sent as notification `id` this session.
+    pub fn sent_as(&self, id: u32) -> Option<&Notification> {
+        self.notifications
             .iter()
-            .map(|n| n.data.hints.x)
-            .min()
-            .unwrap_or_default()
-            .abs() as f32;
+            .find(|n| !n.exiting() && n.data.sent_id == Some(id))
+    }
 
+    /// Row of the history entry listed as `id`, None when no entry on screen is.
+    pub fn history_row(&self, id: NotificationId) -> Option<i64> {
         self.notifications
-            .iter_mut()
-            .for_each(|n| n.set_position(x_offset, n.y));
-        Ok(())
+            .iter()
+            .find(|n| !n.exiting() && n.id() == id)
+            .and_then(|n| n.data.rowid)
     }
 
-    pub fn add(&mut self, data: NotificationData) -> anyhow::Result<()> {
-        if self.inhibited {
-            self.waiting += 1;
-            return Ok(());
+    /// Drops notification `id` if it's held back or queued, returns whether it was.
+    pub fn withdraw(&mut self, id: NotificationId) -> bool {
+        let len = self.waiting.len() + self.queued.len();
+        self.waiting.retain(|data| data.id != id);
+        self.queued.retain(|data| data.id != id);
+
+        if self.waiting.len() + self.queued.len() == len {
+            return false;
         }
 
-        let id = data.id;
-        let (y, existing_index) =
-            if let Some(index) = self.notifications.iter().position(|n| n.id() == id) {
-                let y = self.notifications[index].get_bounds().y;
-                (y, Some(index))
-            } else {
-                (self.height(), None)
-            };
+        self.count_changed();
+        true
+    }
 
-        let mut notification = Notification::new(
-            Arc::clone(&self.config),
-            &mut self.font_system.borrow_mut(),
-            data,
-            self.ui_state.clone(),
-            Some(self.sender.clone()),
-        );
-        notification.set_position(0.0, y);
+    /// Notes the history row notification `id` was stored as after it was added, so that its
+    /// replacements update that entry.
+    pub fn set_row(&mut self, id: NotificationId, rowid: i64) {
+        if let Some(data) = self
+            .notifications
+            .iter_mut()
+            .filter(|n| !n.exiting())
+            .chain(
+                self.groups
+                    .values_mut()
+                    .flat_map(|group| group.members.iter_mut()),
+            )
+            .map(|n| &mut n.data)
+            .chain(self.queued.iter_mut().chain(self.waiting.iter_mut()))
+            .find(|data| data.id == id)
+        {
+            data.rowid = Some(rowid);
+        }
+    }
 
-        match self.config.general.queue {
-            Queue::FIFO if self.notifications.is_empty() => {
-                notification.start_timer(&self.loop_handle)
-            }
+    /// The notification with `id` if it's held back by inhibit.
+    pub fn held(&self, id: NotificationId) -> Option<&NotificationData> {
+        self.waiting.iter().find(|data| data.id == id)
+    }
 
-            Queue::Unordered => notification.start_timer(&self.loop_handle),
-            _ => {}
-        }
+    /// Inhibits again with the notifications that were waiting before a restart, their ids
+    /// aren't handed out to new ones.
+    pub fn restore_waiting(&mut self, waiting: Vec<NotificationData>) {
+        self.inhibit.manual = true;
+        self.waiting = waiting;
+        self.ids.set_in_use(self.active_ids());
+    }
 
-        match existing_index {
+    /// Whether notification `id` asked to stay on screen after one of its actions is invoked.
+    /// First link in the body of notification `id`, or of the selected one when None. Without
+    /// a selection the newest notification is used.
+    pub fn first_link(&self, id: Option<NotificationId>) -> Option<(NotificationId, Arc<str>)> {
+        let notification = match id.or(self.selected_id()) {
+            Some(id) => self.notifications.iter().find(|n| n.id() == id)?,
+            None => self.notifications.iter().max_by_key(|n| n.received_at)?,
+        };
+
+        let anchor = notification.body.anchors.first()?;
+
+        Some((notification.id(), Arc::clone(&anchor.href)))
+    }
+
+    pub fn resident(&self, id: NotificationId) -> bool {
+        self.notifications
+            .iter()
+            .find(|notification| notification.id() == id)
+            .is_some_and(|notification| notification.data.hints.resident)
+    }
+
+    /// Checks that notification `id` is shown or collapsed into a group and offers action `key`.
+    pub fn find_action(&self, id: NotificationId, key: &str) -> Result<(), ActionError> {
+        let notification = self
+            .notifications
+            .iter()
+            .filter(|n| !n.exiting())
+            .chain(self.groups.values().flat_map(|group| group.members.iter()))
+            .find(|n| n.id() == id)
+            .ok_or(ActionError::UnknownNotification(id))?;
+
+        match notification
+            .data
+            .actions
+            .iter()
+            .any(|(action, _)| &**action == key)
+        {
+            true => Ok(()),
+            false => Err(ActionError::UnknownAction(id, key.into())),
+        }
+    }
+
+    /// Number of active notifications, counting collapsed group members but not ones that are
+    /// waiting, queued, exiting or shown as history.
+    pub fn count(&self) -> u32 {
+        if self.history == History::Shown {
+            return 0;
+        }
+
+        let displayed = self.notifications.iter().filter(|n| !n.exiting()).count();
+        let grouped: usize = self.groups.values().map(|group| group.members.len()).sum();
+
+        (displayed + grouped) as u32
+    }
+
+    /// Ids of notifications sent by `app_name`, compared case-insensitively, including ones
+    /// collapsed into its group.
+    pub fn app_ids(&self, app_name: &str) -> Vec<NotificationId> {
+        self.notifications
+            .iter()
+            .filter(|n| !n.exiting())
+            .chain(self.groups.values().flat_map(|group| group.members.iter()))
+            .filter(|n| n.data.app_name.eq_ignore_ascii_case(app_name))
+            .map(Notification::id)
+            .collect()
+    }
+
+    /// Dismisses every notification sent by `app_name`, including ones collapsed into its group,
+    /// and returns their ids. If the selected one is among them, the selection moves to the
+    /// next notification left, or is cleared when there's none.
+    pub fn dismiss_by_app(&mut self, app_name: &str) -> Vec<NotificationId> {
+        let ids = self.app_ids(app_name);
+        if ids.is_empty() {
+            return ids;
+        }
+
+        let next = self
+            .selected_id()
+            .filter(|id| ids.contains(id))
+            .map(|selected| {
+                let remaining: Vec<(NotificationId, bool)> = self
+                    .displayed()
+                    .filter(|n| !n.exiting())
+                    .map(|n| (n.id(), ids.contains(&n.id())))
+                    .collect();
+                let position = remaining
+                    .iter()
+                    .position(|(id, _)| *id == selected)
+                    .unwrap_or_default();
+
+                remaining[position..]
+                    .iter()
+                    .chain(remaining[..position].iter().rev())
+                    .find(|(_, dismissed)| !dismissed)
+                    .map(|(id, _)| *id)
+            });
+
+        // Collapsed members go first so that none of them is promoted in place of the shown one
+        ids.iter().rev().for_each(|id| self.dismiss(*id));
+
+        match next {
+            Some(Some(id)) => self.select(id),
+            Some(None) => self.deselect(),
+            None => {}
+        }
+
+        ids
+    }
+
+    /// Ids of every notification the daemon still holds, shown, grouped, held back or snoozed.
+    fn active_ids(&self) -> impl Iterator<Item = NotificationId> + '_ {
+        self.notifications
+            .iter()
+            .chain(self.groups.values().flat_map(|group| &group.members))
+            .map(|notification| notification.id())
+            .chain(self.queued.iter().chain(&self.waiting).map(|data| data.id))
+            .chain(self.snoozed.keys().copied())
+    }
+
+    /// Ids of notifications that are gone once the daemon exits. Held back ones outlive it
+    /// along with a persisted inhibit, snoozed ones always do.
+    pub fn lost_on_exit(&self) -> Vec<NotificationId> {
+        let persisted = self.config.general.persist_inhibit && self.inhibited();
+        self.notifications
+            .iter()
+            // Entries of the history were closed when it was shown
+            .filter(|notification| {
+                self.history == History::Hidden || notification.data.hints.transient
+            })
+            .chain(self.groups.values().flat_map(|group| &group.members))
+            .map(|notification| notification.id())
+            .chain(self.queued.iter().map(|data| data.id))
+            .chain(
+                self.waiting
+                    .iter()
+                    .filter(|_| !persisted)
+                    .map(|data| data.id),
+            )
+            .collect()
+    }
+
+    fn count_changed(&self) {
+        _ = self.loop_handle.insert_idle(|moxnotify| {
+            let notifications = &moxnotify.notifications;
+            notifications.ids.set_in_use(notifications.active_ids());
+            _ = moxnotify
+                .emit_sender
+                .send(EmitEvent::CountChanged(notifications.count()));
+
+            if moxnotify.config.general.mpris_enrichment {
+                let media = match notifications.history {
+                    History::Shown => Vec::new(),
+                    History::Hidden => notifications
+                        .notifications
+                        .iter()
+                        .filter_map(|n| {
+                            let desktop_entry = n.data.hints.desktop_entry.as_deref()?;
+                            Some((n.id(), Arc::from(desktop_entry)))
+                        })
+                        .collect(),
+                };
+                _ = moxnotify
+                    .emit_sender
+                    .send(EmitEvent::MediaNotifications(media));
+            }
+        });
+    }
+
+    pub fn add_many(&mut self, data: Vec<NotificationData>) -> anyhow::Result<()> {
+        let history = self.history;
+        let new_notifications: Vec<Notification> = data
+            .into_iter()
+            .map(|data| (data, self.sender.clone()))
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map_init(
+                FontSystem::new, // Initialize font system once per thread
+                |font_system, (data, sender)| {
+                    Notification::new(
+                        Arc::clone(&self.config),
+                        font_system,
+                        data,
+                        self.ui_state.clone(),
+                        Some(sender),
+                        history,
+                        Some(Rc::clone(&self.shape_cache)),
+                    )
+                },
+            )
+            .collect();
+
+        self.notifications.extend(new_notifications);
+        self.relayout();
+        self.reveal_selected();
+        self.count_changed();
+
+        Ok(())
+    }
+
+    /// Whether `data` has to wait until notifications are neither inhibited nor locked.
+    fn held_back(&self, data: &NotificationData) -> bool {
+        let passes = |passes: &[InhibitPass]| {
+            passes
+                .iter()
+                .any(|pass| pass.matches(&data.app_name, data.hints.urgency))
+        };
+
+        (self.inhibited() && !passes(&self.config.general.inhibit_pass_urgency))
+            || (self.locked
+                && self.lock_summary != Some(data.id)
+                && !passes(&self.config.general.lockscreen_pass_urgency))
+    }
+
+    /// Shows how many notifications are held back while locked, in place of the notifications
+    /// themselves.
+    fn update_lock_summary(&mut self) -> anyhow::Result<()> {
+        if !self.locked
+            || self.inhibited()
+            || self.waiting.is_empty()
+            || self.config.general.lockscreen != Lockscreen::SummaryCount
+        {
+            return Ok(());
+        }
+
+        let id = *self.lock_summary.get_or_insert_with(|| self.ids.next());
+        let summary: Box<str> = match self.waiting.len() {
+            1 => "1 new notification".into(),
+            count => format!("{count} new notifications").into(),
+        };
+
+        self.add(NotificationData {
+            id,
+            app_name: "moxnotify".into(),
+            summary,
+            timeout: 0,
+            hints: NotificationHints {
+                transient: true,
+                ..Default::default()
+            },
+            timestamp: xdg::timestamp(),
+            ..Default::default()
+        })
+    }
+
+    #[tracing::instrument(skip_all, fields(id = data.id))]
+    pub fn add(&mut self, data: NotificationData) -> anyhow::Result<()> {
+        if self.held_back(&data) {
+            match self
+                .waiting
+                .iter_mut()
+                .find(|waiting| waiting.id == data.id)
+            {
+                Some(waiting) => *waiting = data,
+                None => self.waiting.push(data),
+            }
+            return self.update_lock_summary();
+        }
+
+        let id = data.id;
+        let replaces = self.replaced_index(&data).is_some();
+        if self.history == History::Hidden && !replaces {
+            if let Some(limit) = self.config.general.app_limits.get(&*data.app_name) {
+                let from_app: Vec<NotificationId> = self
+                    .notifications
+                    .iter()
+                    .filter(|n| n.data.app_name == data.app_name && !n.exiting())
+                    .map(|n| n.id())
+                    .collect();
+
+                if from_app.len() >= limit.max {
+                    match limit.policy {
+                        LimitPolicy::Queue => {
+                            log::debug!(
+                                "Limit for {} reached, queueing notification, id: {id}",
+                                data.app_name
+                            );
+                            self.queued.push(data);
+                            return Ok(());
+                        }
+                        LimitPolicy::Replace => from_app
+                            .iter()
+                            .take(from_app.len() + 1 - limit.max)
+                            .for_each(|id| self.expire(*id)),
+                    }
+                }
+            }
+        }
+
+        let group_index = if self.config.general.group_by_app
+            && self.history == History::Hidden
+            && !replaces
+            && !self
+                .groups
+                .get(&data.app_name)
+                .is_some_and(|group| group.expanded)
+        {
+            self.collapse_into_group(&data.app_name)
+        } else {
+            None
+        };
+
+        let (y, existing_index) = if let Some(index) = self.replaced_index(&data) {
+            let y = self.notifications[index].get_bounds().y;
+            (y, Some(index))
+        } else {
+            (self.height(), None)
+        };
+
+        let mut notification = Notification::new(
+            Arc::clone(&self.config),
+            &mut self.font_system.borrow_mut(),
+            data,
+            self.ui_state.clone(),
+            Some(self.sender.clone()),
+            self.history,
+            Some(Rc::clone(&self.shape_cache)),
+        );
+        // Updates of a pinned notification stay pinned
+        if existing_index.is_some_and(|index| self.notifications[index].pinned()) {
+            notification.set_pinned(true, &self.loop_handle);
+        }
+        notification.set_position(0.0, y);
+
+        match self.config.general.queue {
+            _ if self.timers_paused => {}
+            Queue::FIFO if self.notifications.is_empty() || existing_index == Some(0) => {
+                notification.start_timer(&self.loop_handle)
+            }
+
+            Queue::Unordered => notification.start_timer(&self.loop_handle),
+            _ => {}
+        }
+
+        let added = existing_index.is_none().then(|| notification.id());
+        match existing_index {
             Some(index) => {
-                if let Some(notification) = self.notifications.get(index) {
+                if let Some(notification) = self.notifications.get_mut(index) {
+                    notification.stop_timer(&self.loop_handle);
+                    notification.stop_icon_animation(&self.loop_handle);
+                }
+
+                self.notifications[index] = notification;
+            }
+            None => {
+                if self.history == History::Hidden {
+                    notification.animate_in();
+                }
+
+                match group_index {
+                    Some(index) => {
+                        self.notifications.insert(index, notification);
+                        self.relayout();
+                    }
+                    None => self.notifications.push(notification),
+                }
+            }
+        }
+
+        // Maintain selection if replaced, selecting resizes the body so it goes before the
+        // relayout that moves everything below a replacement of a different height
+        if let Some(id) = self.selected_id() {
+            self.select(id);
+        }
+
+        if self.config.general.queue == Queue::FIFO && self.history == History::Hidden {
+            self.pending.extend(added);
+        }
+
+        self.relayout();
+        self.reveal_selected();
+        self.limit_pending();
+        self.count_changed();
+
+        Ok(())
+    }
+
+    /// Drops notifications waiting to be shown beyond `general.queue_limit` the way
+    /// `general.queue_overflow` says, telling their senders they expired.
+    fn limit_pending(&mut self) {
+        let Some(limit) = self.config.general.queue_limit else {
+            return;
+        };
+
+        while let (Some(&oldest), Some(&newest)) = (self.pending.first(), self.pending.last()) {
+            if self.pending.len() <= limit {
+                break;
+            }
+
+            let id = match self.config.general.queue_overflow {
+                QueueOverflow::DropOldest => oldest,
+                QueueOverflow::DropNewest => newest,
+                QueueOverflow::Coalesce => {
+                    let app_name = |id: NotificationId| {
+                        self.notifications
+                            .iter()
+                            .find(|n| n.id() == id)
+                            .map(|n| Arc::clone(&n.data.app_name))
+                    };
+                    let app = app_name(newest);
+                    self.pending
+                        .iter()
+                        .rev()
+                        .skip(1)
+                        .copied()
+                        .find(|id| app.is_some() && app_name(*id) == app)
+                        .unwrap_or(oldest)
+                }
+            };
+
+            self.pending.retain(|pending| *pending != id);
+            self.expire(id);
+        }
+    }
+
+    /// Index of the notification that `data` takes the place of, either because it reuses its id
+    /// or because both carry the same synchronous tag.
+    fn replaced_index(&self, data: &NotificationData) -> Option<usize> {
+        self.notifications.iter().position(|n| {
+            n.id() == data.id
+                || data
+                    .hints
+                    .synchronous
+                    .as_ref()
+                    .is_some_and(|tag| n.data.hints.synchronous.as_ref() == Some(tag))
+        })
+    }
+
+    /// Starts the exit animation of a displayed notification, it's removed once that finishes.
+    /// Without animations the notification is removed right away.
+    #[tracing::instrument(skip(self))]
+    pub fn dismiss(&mut self, id: NotificationId) {
+        self.count_changed();
+        self.needs_redraw = true;
+        // It can't be dragged any further while on its way out
+        if self.dragged == Some(id) {
+            self.dragged = None;
+        }
+
+        if self.history == History::Hidden && self.config.general.animation.duration_ms > 0 {
+            if let Some(notification) = self.notifications.iter_mut().find(|n| n.id() == id) {
+                if !notification.exiting() {
                     notification.stop_timer(&self.loop_handle);
+                    notification.animate_out();
                 }
+                return;
+            }
+        }
+
+        self.remove(id);
+    }
+
+    /// Sets the logical height of the output notifications are shown on, which decides how
+    /// many are visible with `max_visible = "auto"` and how far `y` hints can move them.
+    pub fn set_output_height(&mut self, height: Option<f32>) {
+        if self.notification_view.output_height == height {
+            return;
+        }
+
+        self.notification_view.output_height = height;
+        self.relayout();
+    }
+
+    /// Sets the logical width of the output notifications are shown on, sizes given in percent
+    /// are resolved against it. Returns whether that changed the config.
+    pub fn set_output_width(&mut self, width: Option<f32>) -> bool {
+        if self.output_width == width {
+            return false;
+        }
+
+        self.output_width = width;
+        let Some(config) = width.and_then(|width| self.source.resolved(width)) else {
+            // `x` hints are clamped to the output all the same
+            self.relayout();
+            return false;
+        };
+
+        self.config = Arc::new(config);
+        self.notification_view.set_config(Arc::clone(&self.config));
+        self.shape_cache.clear();
+        self.rebuild(|_| true);
+
+        self.relayout();
+        true
+    }
+
+    /// Builds the notifications `filter` picks again, grouped ones included, with the current
+    /// config and layout.
+    fn rebuild<F>(&mut self, filter: F)
+    where
+        F: Fn(&Notification) -> bool,
+    {
+        let mut font_system = self.font_system.borrow_mut();
+        self.notifications
+            .iter_mut()
+            .chain(
+                self.groups
+                    .values_mut()
+                    .flat_map(|group| group.members.iter_mut()),
+            )
+            .filter(|notification| filter(notification))
+            .for_each(|notification| {
+                notification.reconfigure(
+                    Arc::clone(&self.config),
+                    &mut font_system,
+                    Some(self.sender.clone()),
+                    self.history,
+                    &self.loop_handle,
+                )
+            });
+    }
+
+    pub fn compact(&self) -> bool {
+        self.ui_state.compact.load(Ordering::Relaxed)
+    }
+
+    /// Switches notifications to the layout showing only their summary or back to the full
+    /// one, returns false when they're in it already.
+    pub fn set_compact(&mut self, compact: bool) -> bool {
+        if self.ui_state.compact.swap(compact, Ordering::Relaxed) == compact {
+            return false;
+        }
+
+        log::info!("Compact layout {}", if compact { "on" } else { "off" });
+        self.apply_compact();
+        self.relayout();
+        true
+    }
+
+    /// Switches the notifications in place to the layout the compact mode calls for, the
+    /// selected one is shown in full with `compact.expand_selected`. Returns whether any of
+    /// them changed.
+    fn apply_compact(&mut self) -> bool {
+        let compact = self.compact();
+        let selected = self.selected_id();
+        let expand_selected = self.config.general.compact.expand_selected;
+
+        let mut font_system = self.font_system.borrow_mut();
+        let mut changed = false;
+        self.notifications
+            .iter_mut()
+            .chain(
+                self.groups
+                    .values_mut()
+                    .flat_map(|group| group.members.iter_mut()),
+            )
+            .for_each(|notification| {
+                let full = expand_selected && selected == Some(notification.id());
+                changed |= notification.set_compact(compact && !full, &mut font_system);
+            });
+
+        changed
+    }
+
+    /// Changes the general config for the rest of the session, as loaded and as resolved against
+    /// the output. Notifications aren't built again, it's meant for what only surfaces read.
+    pub fn update_general<F>(&mut self, update: F) -> Arc<Config>
+    where
+        F: Fn(&mut General),
+    {
+        let mut source = (*self.source).clone();
+        update(&mut source.general);
+        self.source = Arc::new(source);
+
+        let mut config = (*self.config).clone();
+        update(&mut config.general);
+        self.config = Arc::new(config);
+        self.notification_view.set_config(Arc::clone(&self.config));
+
+        Arc::clone(&self.config)
+    }
+
+    /// Config with the percentages resolved against the output.
+    pub fn config(&self) -> Arc<Config> {
+        Arc::clone(&self.config)
+    }
+
+    pub fn animating(&self) -> bool {
+        self.notifications.iter().any(Notification::animating)
+    }
+
+    /// Shifts notification `id` sideways by `distance` while it's being swiped.
+    pub fn swipe(&mut self, id: NotificationId, distance: f32) {
+        if let Some(notification) = self.notifications.iter_mut().find(|n| n.id() == id) {
+            notification.swipe(distance);
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Puts notification `id` back in place after a swipe too short to dismiss it.
+    pub fn snap_back(&mut self, id: NotificationId) {
+        if let Some(notification) = self.notifications.iter_mut().find(|n| n.id() == id) {
+            notification.snap_back();
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Moves notification `id` `distance` pixels up or down while the pointer drags it,
+    /// returns false when it's gone.
+    pub fn drag(&mut self, id: NotificationId, distance: f32) -> bool {
+        let Some(notification) = self
+            .notifications
+            .iter_mut()
+            .find(|n| n.id() == id && !n.exiting())
+        else {
+            self.dragged = None;
+            return false;
+        };
+
+        notification.drag(distance);
+        self.dragged = Some(id);
+        self.needs_redraw = true;
+        true
+    }
+
+    /// Puts notification `id` back where the drag started.
+    pub fn cancel_drag(&mut self, id: NotificationId) {
+        self.dragged = None;
+        self.snap_back(id);
+    }
+
+    /// Drops notification `id` where it was dragged to, the ones it passed move over to make
+    /// room for it.
+    pub fn drop_dragged(&mut self, id: NotificationId) {
+        self.dragged = None;
+        let order = self.display_order();
+        let Some(index) = order.iter().copied().find(|index| {
+            self.notifications[*index].id() == id && !self.notifications[*index].exiting()
+        }) else {
+            return;
+        };
+
+        let center = |notification: &Notification| {
+            notification.y + notification.frame().offset + notification.get_bounds().height / 2.
+        };
+        let dropped_at = center(&self.notifications[index]);
+        let mut ids: Vec<NotificationId> = order
+            .iter()
+            .filter(|other| **other != index)
+            .map(|other| self.notifications[*other].id())
+            .collect();
+        let position = order
+            .iter()
+            .filter(|other| **other != index && center(&self.notifications[**other]) < dropped_at)
+            .count();
+        ids.insert(position, id);
+        self.order = ids;
+
+        self.notifications[index].snap_back();
+        let previous: HashMap<NotificationId, f32> =
+            self.notifications.iter().map(|n| (n.id(), n.y)).collect();
+
+        self.relayout();
+
+        self.notifications.iter_mut().for_each(|notification| {
+            if let Some(y) = previous.get(&notification.id()) {
+                notification.animate_move(*y);
+            }
+        });
+    }
+
+    /// Whether a notification on screen shows a countdown that's still running.
+    pub fn counting_down(&self) -> bool {
+        self.visible()
+            .into_iter()
+            .filter_map(|i| self.notifications.get(i))
+            .any(Notification::counting_down)
+    }
+
+    /// Advances animations, removing notifications whose exit animation is over. Returns true
+    /// if any were removed and the surface has to be resized.
+    pub fn tick(&mut self) -> bool {
+        self.tick_at(Instant::now())
+    }
+
+    /// Same as `tick`, with animations advanced to `now`.
+    pub fn tick_at(&mut self, now: Instant) -> bool {
+        // The frame an animation settles on still has to be drawn
+        self.needs_redraw |= self.animating() || self.counting_down();
+
+        for index in self.visible() {
+            self.notifications[index].animate_icon(&self.loop_handle);
+        }
+
+        let finished: Vec<NotificationId> = self
+            .notifications
+            .iter_mut()
+            .filter_map(|notification| notification.settle(now).then(|| notification.id()))
+            .collect();
+
+        finished.iter().for_each(|id| self.remove(*id));
+
+        !finished.is_empty()
+    }
+
+    fn remove(&mut self, id: NotificationId) {
+        self.needs_redraw = true;
+        if self.dragged == Some(id) {
+            self.dragged = None;
+        }
+
+        // Its id may be handed out again once it's gone
+        if self.lock_summary == Some(id) {
+            self.lock_summary = None;
+        }
+
+        if let Some(mut notification) = self.take_group_member(id) {
+            notification.stop_timer(&self.loop_handle);
+            notification.stop_icon_animation(&self.loop_handle);
+            return;
+        }
+
+        if let Some(i) = self.notifications.iter().position(|n| n.id() == id) {
+            if let Some(notification) = self.notifications.get_mut(i) {
+                notification.stop_timer(&self.loop_handle);
+                notification.stop_icon_animation(&self.loop_handle);
+                let app_name = Arc::clone(&notification.data.app_name);
+
+                // The one shown below takes over the selection
+                let order = self.display_order();
+                let below = order
+                    .iter()
+                    .position(|index| *index == i)
+                    .and_then(|position| order.get(position + 1))
+                    .map(|index| self.notifications[*index].id());
+
+                if let Some(below) = below {
+                    if self.selected_id() == Some(id) {
+                        self.select(below);
+                    }
+                    self.notifications.remove(i);
+                } else {
+                    self.notifications.remove(i);
+                    self.prev();
+                }
+
+                self.promote_group_member(&app_name, i);
+            }
+        }
+
+        if self.config.general.queue == Queue::FIFO && !self.timers_paused {
+            if let Some(notification) = self.notifications.first_mut().filter(|n| !n.hovered()) {
+                notification.start_timer(&self.loop_handle);
+            }
+        }
+
+        let previous: HashMap<NotificationId, f32> =
+            self.notifications.iter().map(|n| (n.id(), n.y)).collect();
+
+        self.relayout();
+
+        self.notifications.iter_mut().for_each(|notification| {
+            if let Some(y) = previous.get(&notification.id()) {
+                notification.animate_move(*y);
+            }
+        });
+
+        self.release_queued();
+    }
+
+    /// Swaps the placeholder of notification `id` for its decoded image, returns false when
+    /// the notification is gone or has since been replaced with a different image.
+    pub fn set_icon(
+        &mut self,
+        id: NotificationId,
+        path: &Path,
+        icon: Option<ImageData>,
+        frames: Arc<[ImageFrame]>,
+    ) -> bool {
+        let Some(notification) = self
+            .notifications
+            .iter_mut()
+            .chain(
+                self.groups
+                    .values_mut()
+                    .flat_map(|group| group.members.iter_mut()),
+            )
+            .find(|n| {
+                n.id() == id
+                    && matches!(&n.data.hints.image, Some(Image::File(file)) if **file == *path)
+            })
+        else {
+            return false;
+        };
+
+        notification.icons.set_icon(icon, frames);
+        self.relayout();
+
+        true
+    }
+
+    /// Moves the animated image of notification `id` on to its next frame and returns how long
+    /// that's shown for. None once the notification is gone or out of view, the timer calling
+    /// this is dropped then and started again by `tick` when it's back.
+    pub fn next_frame(&mut self, id: NotificationId) -> Option<Duration> {
+        let visible = self
+            .visible()
+            .into_iter()
+            .any(|index| self.notifications[index].id() == id);
+        let notification = self
+            .notifications
+            .iter_mut()
+            .chain(
+                self.groups
+                    .values_mut()
+                    .flat_map(|group| group.members.iter_mut()),
+            )
+            .find(|n| n.id() == id)?;
+
+        let delay = visible.then(|| notification.next_frame()).flatten();
+        if delay.is_none() {
+            notification.frame_token = None;
+        }
+
+        delay
+    }
+
+    /// Updates the progress bar of notification `id` in place, without restarting its timeout.
+    /// Returns false when the notification is gone or already shows `value`.
+    pub fn set_value(&mut self, id: NotificationId, value: i32) -> bool {
+        let Some(notification) = self
+            .notifications
+            .iter_mut()
+            .chain(
+                self.groups
+                    .values_mut()
+                    .flat_map(|group| group.members.iter_mut()),
+            )
+            .find(|n| n.id() == id)
+        else {
+            return false;
+        };
+
+        if notification.data.hints.value == Some(value) {
+            return false;
+        }
+
+        notification.set_value(value);
+        self.relayout();
+
+        true
+    }
+
+    /// Shows the decoded `<img>` source `src` in the body of notification `id`, returns false
+    /// when the notification is gone or no longer shows that image.
+    pub fn set_body_image(
+        &mut self,
+        id: NotificationId,
+        src: &str,
+        image: Option<ImageData>,
+    ) -> bool {
+        let Some(notification) = self
+            .notifications
+            .iter_mut()
+            .chain(
+                self.groups
+                    .values_mut()
+                    .flat_map(|group| group.members.iter_mut()),
+            )
+            .find(|n| n.id() == id)
+        else {
+            return false;
+        };
+
+        if !notification.set_body_image(&mut self.font_system.borrow_mut(), src, image) {
+            return false;
+        }
+        self.relayout();
+
+        true
+    }
+
+    /// Shows the icon of action `action` on its buttons, returns false when notification `id`
+    /// or the action is gone.
+    pub fn set_action_icon(&mut self, id: NotificationId, action: &str, icon: ImageData) -> bool {
+        let Some(notification) = self
+            .notifications
+            .iter_mut()
+            .chain(
+                self.groups
+                    .values_mut()
+                    .flat_map(|group| group.members.iter_mut()),
+            )
+            .find(|n| n.id() == id)
+        else {
+            return false;
+        };
+
+        if !notification.set_action_icon(action, icon) {
+            return false;
+        }
+        self.relayout();
+
+        true
+    }
+
+    /// Brings the relative timestamps of displayed notifications up to date, returns whether
+    /// any of them changed.
+    pub fn refresh_timestamps(&mut self) -> bool {
+        let now = SystemTime::now();
+        let mut font_system = self.font_system.borrow_mut();
+        let changed = self
+            .notifications
+            .iter_mut()
+            .fold(false, |changed, notification| {
+                notification.refresh_timestamp(&mut font_system, now) || changed
+            });
+        drop(font_system);
+
+        if changed {
+            self.relayout();
+        }
+
+        changed
+    }
+
+    /// Positions notifications one below another, shifted up by the scroll offset.
+    fn relayout(&mut self) {
+        self.needs_redraw = true;
+
+        let left = self.place_offsets();
+        let heights = self.heights();
+        self.notification_view.update(&heights);
+
+        let order = self.display_order();
+        if !self.pending.is_empty() {
+            // Ones that made it into view or are gone aren't waiting anymore
+            let hidden: Vec<NotificationId> = order
+                .iter()
+                .enumerate()
+                .filter(|(position, _)| !self.notification_view.visible.contains(position))
+                .map(|(_, index)| self.notifications[*index].id())
+                .collect();
+            self.pending.retain(|id| hidden.contains(id));
+        }
+        self.notification_view.set_pending(self.pending.len());
+
+        let top = self.notification_view.top() - self.notification_view.offset;
+        order
+            .into_iter()
+            .zip(heights)
+            .fold(top, |y, (index, height)| {
+                self.notifications[index].set_position(left, y);
+                y + height
+            });
+    }
+
+    /// Clamps the offsets position hints ask for so that notifications stay on the output,
+    /// and makes room above the viewport and below it for the `y` ones. Returns how far
+    /// notifications have to be moved right for ones with a negative `x` to fit.
+    fn place_offsets(&mut self) -> f32 {
+        let margin = &self.config.general.margin;
+        let (widest, tallest) = self
+            .notifications
+            .iter()
+            .map(|notification| notification.get_bounds())
+            .fold((0., 0.), |(width, height): (f32, f32), bounds| {
+                (width.max(bounds.width), height.max(bounds.height))
+            });
+        let room_x = self.output_width.map_or(f32::INFINITY, |width| {
+            width - margin.left.resolve(0.) - margin.right.resolve(0.) - widest
+        });
+        let room_y = self
+            .notification_view
+            .output_height
+            .map_or(f32::INFINITY, |height| {
+                height - margin.top.resolve(0.) - margin.bottom.resolve(0.) - tallest
+            });
+
+        let (mut xs, mut ys): (Vec<f32>, Vec<f32>) = self
+            .notifications
+            .iter()
+            .map(Notification::requested_offset)
+            .unzip();
+        let (left, _) = clamp_offsets(&mut xs, room_x);
+        let (above, below) = clamp_offsets(&mut ys, room_y);
+
+        self.notifications
+            .iter_mut()
+            .zip(xs.into_iter().zip(ys))
+            .for_each(|(notification, (x, y))| notification.set_offset(x, y));
+        self.notification_view.padding = (above, below);
+
+        left
+    }
+
+    /// Moves the currently displayed notification of `app_name` into its group and returns
+    /// the index it occupied, so that the newest notification can take its place.
+    fn collapse_into_group(&mut self, app_name: &Arc<str>) -> Option<usize> {
+        let index = self
+            .notifications
+            .iter()
+            .position(|n| n.data.app_name == *app_name)?;
+        let notification = self.notifications.remove(index);
+
+        let group = self.groups.entry(Arc::clone(app_name)).or_default();
+        group.members.push(notification);
+        group.update_badge(&self.config, &mut self.font_system.borrow_mut());
+
+        Some(index)
+    }
+
+    fn promote_group_member(&mut self, app_name: &Arc<str>, index: usize) {
+        let Some(group) = self.groups.get_mut(app_name) else {
+            return;
+        };
+
+        if let Some(member) = group.members.pop() {
+            group.update_badge(&self.config, &mut self.font_system.borrow_mut());
+            self.notifications
+                .insert(index.min(self.notifications.len()), member);
+        } else if !self
+            .notifications
+            .iter()
+            .any(|n| n.data.app_name == *app_name)
+        {
+            self.groups.remove(app_name);
+        }
+    }
+
+    fn take_group_member(&mut self, id: NotificationId) -> Option<Notification> {
+        let group = self
+            .groups
+            .values_mut()
+            .find(|group| group.members.iter().any(|n| n.id() == id))?;
+        let index = group.members.iter().position(|n| n.id() == id)?;
+        let notification = group.members.remove(index);
+        group.update_badge(&self.config, &mut self.font_system.borrow_mut());
+
+        Some(notification)
+    }
+
+    pub fn is_grouped(&self, id: NotificationId) -> bool {
+        self.groups
+            .values()
+            .any(|group| group.members.iter().any(|n| n.id() == id))
+    }
+
+    /// Removes every collapsed member of the group represented by `id` and returns their ids.
+    pub fn dismiss_group(&mut self, id: NotificationId) -> Vec<NotificationId> {
+        let Some(app_name) = self
+            .notifications
+            .iter()
+            .find(|n| n.id() == id)
+            .map(|n| Arc::clone(&n.data.app_name))
+        else {
+            return Vec::new();
+        };
+
+        let Some(group) = self.groups.get_mut(&app_name) else {
+            return Vec::new();
+        };
+
+        let members = std::mem::take(&mut group.members);
+        group.update_badge(&self.config, &mut self.font_system.borrow_mut());
+
+        members
+            .into_iter()
+            .map(|mut notification| {
+                notification.stop_timer(&self.loop_handle);
+                notification.id()
+            })
+            .collect()
+    }
+
+    /// Opens the reply input on the selected notification, keeping it on screen until the reply
+    /// is sent or cancelled.
+    pub fn start_reply(&mut self) -> bool {
+        let Some(id) = self.selected_id() else {
+            return false;
+        };
+
+        let Some(notification) = self.notifications.iter_mut().find(|n| n.id() == id) else {
+            return false;
+        };
+
+        if !notification.start_reply(&mut self.font_system.borrow_mut()) {
+            return false;
+        }
+
+        notification.pause_timer(&self.loop_handle);
+        self.relayout();
+
+        true
+    }
+
+    /// Expands the body of a notification cut off at `max_lines` or collapses it back, moving
+    /// the notifications below it along.
+    pub fn toggle_expand(&mut self, id: NotificationId) -> bool {
+        let Some(notification) = self.notifications.iter_mut().find(|n| n.id() == id) else {
+            return false;
+        };
+
+        if !notification.toggle_expand(&mut self.font_system.borrow_mut()) {
+            return false;
+        }
+
+        self.relayout();
+
+        true
+    }
+
+    /// Pins notification `id` so that it stays until dismissed, or unpins it and lets it expire
+    /// again. Returns whether it's pinned now, None when there's no such notification.
+    pub fn toggle_pin(&mut self, id: NotificationId) -> Option<bool> {
+        let index = self.notifications.iter().position(|n| n.id() == id)?;
+        let selected = self.selected_id() == Some(id);
+        let notification = &mut self.notifications[index];
+        let pinned = !notification.pinned();
+        notification.set_pinned(pinned, &self.loop_handle);
+
+        if !pinned && !selected && !notification.hovered() {
+            match self.config.general.queue {
+                _ if self.timers_paused => {}
+                Queue::FIFO if index == 0 => notification.start_timer(&self.loop_handle),
+                Queue::Unordered => notification.start_timer(&self.loop_handle),
+                _ => {}
+            }
+        }
+        self.needs_redraw = true;
+
+        Some(pinned)
+    }
+
+    pub fn edit_reply<F>(&mut self, edit: F)
+    where
+        F: FnOnce(&mut TextInput, &mut FontSystem),
+    {
+        if let Some(reply) = self
+            .notifications
+            .iter_mut()
+            .find_map(|notification| notification.reply.as_mut())
+        {
+            edit(reply, &mut self.font_system.borrow_mut());
+            self.relayout();
+        }
+    }
+
+    pub fn take_reply(&mut self) -> Option<(NotificationId, TextInput)> {
+        let (id, reply) = self.notifications.iter_mut().find_map(|notification| {
+            notification
+                .reply
+                .take()
+                .map(|reply| (notification.id(), reply))
+        })?;
+        self.relayout();
+
+        Some((id, reply))
+    }
+
+    pub fn toggle_group(&mut self) {
+        let Some(id) = self.selected_id() else {
+            return;
+        };
+
+        let Some(index) = self.notifications.iter().position(|n| n.id() == id) else {
+            return;
+        };
+
+        let app_name = Arc::clone(&self.notifications[index].data.app_name);
+        let Some(group) = self.groups.get_mut(&app_name) else {
+            return;
+        };
+
+        if group.expanded {
+            log::debug!("Collapsing notifications from {app_name}");
+            group.expanded = false;
+
+            let newest = self
+                .notifications
+                .iter()
+                .filter(|n| n.data.app_name == app_name)
+                .map(|n| n.id())
+                .max();
+
+            let (mut members, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut self.notifications)
+                .into_iter()
+                .partition(|n| n.data.app_name == app_name && Some(n.id()) != newest);
+            members.sort_by_key(|n| n.id());
+
+            self.notifications = rest;
+            group.members = members;
+            group.update_badge(&self.config, &mut self.font_system.borrow_mut());
+
+            if let Some(newest) = newest {
+                self.select(newest);
+            }
+        } else {
+            if group.members.is_empty() {
+                return;
+            }
+
+            log::debug!("Expanding notifications from {app_name}");
+            group.expanded = true;
+
+            let members = std::mem::take(&mut group.members);
+            group.update_badge(&self.config, &mut self.font_system.borrow_mut());
+            self.notifications
+                .splice(index + 1..index + 1, members.into_iter().rev());
+        }
+
+        self.relayout();
+    }
+
+    fn expire(&mut self, id: NotificationId) {
+        log::debug!("Expiring notification to make room for new ones, id: {id}");
+        self.dismiss(id);
+        _ = self.loop_handle.insert_idle(move |moxnotify| {
+            _ = moxnotify.emit_sender.send(EmitEvent::NotificationClosed {
+                id,
+                reason: Reason::Expired,
+            });
+        });
+    }
+
+    fn release_queued(&mut self) {
+        if self.history == History::Shown {
+            return;
+        }
+
+        while let Some(index) = self.queued.iter().position(|data| {
+            self.config
+                .general
+                .app_limits
+                .get(&*data.app_name)
+                .is_none_or(|limit| {
+                    self.notifications
+                        .iter()
+                        .filter(|n| n.data.app_name == data.app_name)
+                        .count()
+                        < limit.max
+                })
+        }) {
+            let data = self.queued.remove(index);
+            if let Err(e) = self.add(data) {
+                log::error!("Failed to add queued notification: {e}");
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Reason {
+    Expired = 1,
+    DismissedByUser = 2,
+    CloseNotificationCall = 3,
+    Unknown = 4,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Reason::Expired => "Expired",
+            Reason::DismissedByUser => "DismissedByUser",
+            Reason::CloseNotificationCall => "CloseNotificationCall",
+            Reason::Unknown => "Unknown",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// Clamps `offsets` into a span of at most `room` pixels that includes 0, negative ones are
+/// kept first. Returns how far the offsets reach below and above 0.
+fn clamp_offsets(offsets: &mut [f32], room: f32) -> (f32, f32) {
+    let room = room.max(0.);
+    let low = offsets.iter().copied().fold(0., f32::min).max(-room);
+    offsets
+        .iter_mut()
+        .for_each(|offset| *offset = offset.clamp(low, low + room));
+
+    (-low, offsets.iter().copied().fold(0., f32::max))
+}
+
+/// Why an action couldn't be invoked on a notification.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ActionError {
+    UnknownNotification(NotificationId),
+    UnknownAction(NotificationId, Arc<str>),
+}
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ActionError::UnknownNotification(id) => write!(f, "No notification with id {id}"),
+            ActionError::UnknownAction(id, key) => {
+                write!(f, "Notification {id} has no action \"{key}\"")
+            }
+        }
+    }
+}
+
+impl Moxnotify {
+    pub fn dismiss_range<T>(&mut self, range: T, reason: Option<Reason>)
+    where
+        T: std::slice::SliceIndex<[Notification], Output = [Notification]>,
+    {
+        // Pinned notifications are only ever dismissed on purpose
+        let ids: Vec<_> = self.notifications.notifications()[range]
+            .iter()
+            .filter(|notification| {
+                !(notification.pinned() && matches!(reason, Some(Reason::Expired)))
+            })
+            .map(|notification| notification.id())
+            .collect();
+        let grouped: Vec<_> = ids
+            .iter()
+            .flat_map(|id| self.notifications.dismiss_group(*id))
+            .collect();
+
+        if let Some(audio) = self.audio.as_mut() {
+            ids.iter()
+                .chain(grouped.iter())
+                .for_each(|id| audio.stop(*id));
+        }
+
+        if let Some(reason) = reason {
+            ids.iter().chain(grouped.iter()).for_each(|id| {
+                _ = self
+                    .emit_sender
+                    .send(EmitEvent::NotificationClosed { id: *id, reason });
+            });
+        }
+
+        if ids.len() == self.notifications.notifications.len()
+            && self.config.general.animation.duration_ms == 0
+        {
+            self.notifications.groups.clear();
+            self.notifications.notifications.clear();
+            self.notifications.count_changed();
+            self.notifications.relayout();
+            self.notifications.release_queued();
+            return;
+        }
+
+        ids.iter().for_each(|id| self.notifications.dismiss(*id));
+    }
+
+    /// Stores the manual inhibit with `general.persist_inhibit`, so that it outlives restarts
+    /// even with nothing held back.
+    pub fn persist_inhibit(&self) {
+        if !self.config.general.persist_inhibit {
+            return;
+        }
+
+        let inhibited = self.notifications.inhibit_state().manual;
+        if let Err(e) = xdg::set_inhibited(&self.db, inhibited) {
+            log::warn!("Failed to store inhibit: {e}");
+        }
+    }
+
+    /// Shows notifications that were held back while inhibited or locked. Ones let through by
+    /// `inhibit_pass_urgency` or `lockscreen_pass_urgency` are already on screen and aren't part
+    /// of `waiting`.
+    pub fn replay_waiting(&mut self, waiting: Vec<NotificationData>) -> anyhow::Result<()> {
+        log::debug!("Processing {} waiting notifications", waiting.len());
+        if !waiting.is_empty() {
+            xdg::clear_waiting(&self.db)?;
+        }
+
+        waiting
+            .into_iter()
+            .try_for_each(|data| self.notifications.add(data))
+    }
+
+    /// Dismisses every notification sent by `app_name` and returns how many were closed. With
+    /// history shown the matching rows are deleted from the database as well.
+    pub fn dismiss_app(&mut self, app_name: &str) -> usize {
+        let ids = self.notifications.app_ids(app_name);
+
+        match self.history {
+            History::Shown => {
+                let deleted = self
+                    .db
+                    .execute(
+                        "DELETE FROM notifications WHERE app_name = ?1 COLLATE NOCASE",
+                        params![app_name],
+                    )
+                    .unwrap_or_else(|e| {
+                        log::error!("Failed to delete history of {app_name}: {e}");
+                        0
+                    });
+                ids.iter().for_each(|id| self.notifications.dismiss(*id));
+
+                deleted
+            }
+            History::Hidden => {
+                let selected = self.notifications.selected_id();
+                let ids = self.notifications.dismiss_by_app(app_name);
+                if selected.is_some_and(|id| ids.contains(&id)) {
+                    self.notifications
+                        .ui_state
+                        .mode
+                        .store(keymaps::Mode::Normal, Ordering::Relaxed);
+                }
+
+                ids.iter().for_each(|id| {
+                    if let Some(audio) = self.audio.as_mut() {
+                        audio.stop(*id);
+                    }
+                    _ = self.emit_sender.send(EmitEvent::NotificationClosed {
+                        id: *id,
+                        reason: Reason::DismissedByUser,
+                    });
+                });
+
+                self.update_surface_size();
+                if let Err(e) = self.render_surfaces() {
+                    log::error!("Render error: {e}");
+                }
+
+                // Nothing is left for the keys typed so far or the one held down to act on
+                if self.notifications.notifications().is_empty() {
+                    self.seats
+                        .keyboards_mut()
+                        .for_each(|keyboard| keyboard.reset());
+                }
+
+                ids.len()
+            }
+        }
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub fn dismiss_by_id(&mut self, id: u32, reason: Option<Reason>) {
+        match self.history {
+            History::Shown => {
+                let Some(rowid) = self.notifications.history_row(id) else {
+                    log::debug!("No history entry with id={id}");
+                    return;
+                };
+                if let Err(e) = xdg::forget(&self.db, rowid) {
+                    log::error!("Failed to delete history entry {rowid}: {e}");
+                }
+                self.notifications.dismiss(id);
+            }
+            History::Hidden => {
+                if let Some(audio) = self.audio.as_mut() {
+                    audio.stop(id);
+                }
+
+                if let Some(index) = self
+                    .notifications
+                    .notifications
+                    .iter()
+                    .position(|n| n.id() == id)
+                {
+                    if self.notifications.selected_id() == Some(id) {
+                        self.notifications
+                            .ui_state
+                            .mode
+                            .store(keymaps::Mode::Normal, Ordering::Relaxed);
+                    }
+
+                    if let Some(reason @ Reason::DismissedByUser) = reason {
+                        self.notifications
+                            .dismiss_group(id)
+                            .into_iter()
+                            .for_each(|id| {
+                                _ = self
+                                    .emit_sender
+                                    .send(EmitEvent::NotificationClosed { id, reason });
+                            });
+                    }
+
+                    self.notifications.dismiss(id);
+                    if let Some(reason) = reason {
+                        _ = self
+                            .emit_sender
+                            .send(EmitEvent::NotificationClosed { id, reason });
+                    }
+                    if self.notifications.selected_id() == Some(id) {
+                        let new_index = if index >= self.notifications.notifications.len() {
+                            self.notifications.notifications.len().saturating_sub(1)
+                        } else {
+                            index
+                        };
+
+                        if let Some(notification) = self.notifications.notifications.get(new_index)
+                        {
+                            self.notifications.select(notification.id());
+                        }
+                    }
+                } else if self.notifications.is_grouped(id) {
+                    self.notifications.dismiss(id);
+                    if let Some(reason) = reason {
+                        _ = self
+                            .emit_sender
+                            .send(EmitEvent::NotificationClosed { id, reason });
+                    }
+                }
+            }
+        }
+
+        self.update_surface_size();
+        if let Err(e) = self.render_surfaces() {
+            log::error!("Render error: {e}");
+        }
+
+        if self.notifications.notifications().is_empty() {
+            self.seats
+                .keyboards_mut()
+                .for_each(|keyboard| keyboard.repeat.key = None);
+        }
+    }
+
+    /// Closes notification `id` after an action was invoked on it or it was replied to, unless
+    /// it's resident. Clients are told after ActionInvoked or NotificationReplied, and members of
+    /// its group stay, unlike when it's dismissed. Returns whether it was closed.
+    pub fn close_interacted(&mut self, id: NotificationId) -> bool {
+        if self.notifications.resident(id) {
+            return false;
+        }
+
+        self.dismiss_by_id(id, None);
+        if self.history == History::Hidden {
+            _ = self.emit_sender.send(EmitEvent::NotificationClosed {
+                id,
+                reason: Reason::DismissedByUser,
+            });
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+    use calloop::EventLoop;
+    use glyphon::FontSystem;
+
+    use super::{ActionError, Counter, NotificationManager};
+    use crate::{
+        components::{button::ButtonType, notification::Notification, Component},
+        config::{
+            AppLimit, Config, InhibitPass, LimitPolicy, Lockscreen, MaxVisible,
+            NotificationStyleEntry, Queue, QueueOverflow, RenderMode, Size, Sort, SurfacePlacement,
+        },
+        dbus::xdg::{self, NotificationData, NotificationHints},
+        rendering::animation::Frame,
+        History, InhibitState, Urgency,
+    };
+
+    fn manager(
+        event_loop: &EventLoop<'static, crate::Moxnotify>,
+        config: Arc<Config>,
+    ) -> NotificationManager {
+        NotificationManager::new(
+            config,
+            event_loop.handle(),
+            calloop::channel::channel().0,
+            Rc::new(RefCell::new(FontSystem::new())),
+        )
+    }
+
+    #[test]
+    fn test_add() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let font_system = Rc::new(RefCell::new(FontSystem::new()));
+        let mut manager = NotificationManager::new(
+            Arc::clone(&config),
+            event_loop.handle(),
+            calloop::channel::channel().0,
+            font_system,
+        );
+
+        let data = NotificationData::default();
+        manager.add(data).unwrap();
+
+        assert_eq!(manager.notifications().len(), 1);
+    }
+
+    #[test]
+    fn test_add_with_duplicate_id() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let font_system = Rc::new(RefCell::new(FontSystem::new()));
+        let mut manager = NotificationManager::new(
+            Arc::clone(&config),
+            event_loop.handle(),
+            calloop::channel::channel().0,
+            font_system,
+        );
+
+        let data = NotificationData {
+            id: 42,
+            ..Default::default()
+        };
+
+        manager.add(data.clone()).unwrap();
+
+        manager.add(data).unwrap();
+
+        assert_eq!(manager.notifications().len(), 1);
+    }
+
+    #[test]
+    fn test_replace_selected_with_taller() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+
+        for id in 1..=3 {
+            let data = NotificationData {
+                id,
+                summary: "Summary".into(),
+                body: "Body".into(),
+                ..Default::default()
+            };
+            manager.add(data).unwrap();
+        }
+        manager.select(2);
+
+        let bounds = |manager: &NotificationManager, id| {
+            manager
+                .notifications()
+                .iter()
+                .find(|n| n.id() == id)
+                .unwrap()
+                .get_render_bounds()
+        };
+        let height = |manager: &NotificationManager, id| {
+            manager
+                .notifications()
+                .iter()
+                .find(|n| n.id() == id)
+                .unwrap()
+                .get_bounds()
+                .height
+        };
+        let old_height = height(&manager, 2);
+        let old_y = bounds(&manager, 3).y;
+
+        let data = NotificationData {
+            id: 2,
+            summary: "Summary".into(),
+            body: "A much longer body that wraps over a few lines, "
+                .repeat(8)
+                .into(),
+            ..Default::default()
+        };
+        manager.add(data).unwrap();
+
+        assert_eq!(manager.selected_id(), Some(2));
+        let grown = height(&manager, 2) - old_height;
+        assert!(grown > 0.);
+        assert!((bounds(&manager, 3).y - (old_y + grown)).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_synchronous_tag_replaces() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+
+        let tagged = |id, tag: &str| {
+            let mut data = NotificationData {
+                id,
+                summary: format!("Volume {id}").into(),
+                ..Default::default()
+            };
+            data.hints.synchronous = Some(tag.into());
+            data
+        };
+
+        manager.add(tagged(1, "volume")).unwrap();
+        manager
+            .add(NotificationData {
+                id: 2,
+                ..Default::default()
+            })
+            .unwrap();
+        manager.add(tagged(3, "volume")).unwrap();
+
+        let ids: Vec<_> = manager.notifications().iter().map(|n| n.id()).collect();
+        assert_eq!(ids, vec![3, 2]);
+
+        manager.add(tagged(4, "brightness")).unwrap();
+        manager.add(tagged(5, "volume")).unwrap();
+
+        let ids: Vec<_> = manager.notifications().iter().map(|n| n.id()).collect();
+        assert_eq!(ids, vec![5, 2, 4]);
+    }
+
+    #[test]
+    fn test_app_limits() {
+        let mut config = Config::default();
+        config.general.app_limits.insert(
+            "chatty".into(),
+            AppLimit {
+                max: 2,
+                policy: LimitPolicy::Replace,
+            },
+        );
+        config.general.app_limits.insert(
+            "queued".into(),
+            AppLimit {
+                max: 1,
+                policy: LimitPolicy::Queue,
+            },
+        );
+        let config = Arc::new(config);
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+
+        for id in 1..=3 {
+            let data = NotificationData {
+                id,
+                app_name: "chatty".into(),
+                ..Default::default()
+            };
+            manager.add(data).unwrap();
+        }
+
+        let ids: Vec<_> = manager.notifications().iter().map(|n| n.id()).collect();
+        assert_eq!(ids, vec![2, 3]);
+
+        let data = NotificationData {
+            id: 3,
+            app_name: "chatty".into(),
+            ..Default::default()
+        };
+        manager.add(data).unwrap();
+        assert_eq!(manager.notifications().len(), 2);
+
+        for id in 4..=5 {
+            let data = NotificationData {
+                id,
+                app_name: "queued".into(),
+                ..Default::default()
+            };
+            manager.add(data).unwrap();
+        }
+        assert_eq!(manager.notifications().len(), 3);
+
+        manager.dismiss(4);
+        assert!(manager.notifications().iter().any(|n| n.id() == 5));
+    }
+
+    #[test]
+    fn test_pause_and_resume_timers() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+
+        let data = NotificationData {
+            id: 1,
+            timeout: 5000,
+            ..Default::default()
+        };
+        manager.add(data).unwrap();
+        assert!(manager.notifications()[0].registration_token.is_some());
+
+        manager.pause_all_timers();
+        assert!(manager.notifications()[0].registration_token.is_none());
+
+        let data = NotificationData {
+            id: 2,
+            timeout: 5000,
+            ..Default::default()
+        };
+        manager.add(data).unwrap();
+        assert!(manager.notifications()[1].registration_token.is_none());
+
+        manager.resume_all_timers();
+        assert!(manager
+            .notifications()
+            .iter()
+            .all(|n| n.registration_token.is_some()));
+    }
+
+    #[test]
+    fn test_pin() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+
+        let data = NotificationData {
+            id: 1,
+            timeout: 5000,
+            ..Default::default()
+        };
+        manager.add(data.clone()).unwrap();
+        assert!(manager.notifications()[0].registration_token.is_some());
+
+        assert_eq!(manager.toggle_pin(1), Some(true));
+        assert!(manager.notifications()[0].registration_token.is_none());
+        assert!(manager.notifications()[0].timeout().is_none());
+
+        // Replacing it keeps it pinned
+        manager.add(data).unwrap();
+        assert!(manager.notifications()[0].pinned());
+        assert!(manager.notifications()[0].registration_token.is_none());
+
+        assert_eq!(manager.toggle_pin(1), Some(false));
+        assert!(manager.notifications()[0].registration_token.is_some());
+        assert_eq!(manager.toggle_pin(2), None);
+    }
+
+    #[test]
+    fn test_queue_overflow() {
+        let burst = |overflow| {
+            let mut config = Config::default();
+            config.general.queue = Queue::FIFO;
+            config.general.queue_limit = Some(10);
+            config.general.queue_overflow = overflow;
+            config.general.animation.duration_ms = 0;
+            let event_loop = EventLoop::try_new().unwrap();
+            let mut manager = manager(&event_loop, Arc::new(config));
+
+            for id in 1..=100 {
+                let app_name = if id % 2 == 0 { "even" } else { "odd" };
+                manager
+                    .add(NotificationData {
+                        id,
+                        app_name: app_name.into(),
+                        timeout: 5000,
+                        ..Default::default()
+                    })
+                    .unwrap();
+            }
+
+            // The first five are in view, ten wait below it
+            assert_eq!(manager.notifications().len(), 15);
+            assert!(manager.notifications()[0].registration_token.is_some());
+            manager.pending.clone()
+        };
+
+        assert_eq!(
+            burst(QueueOverflow::DropOldest),
+            (91..=100).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            burst(QueueOverflow::DropNewest),
+            (6..=15).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            burst(QueueOverflow::Coalesce),
+            (6..=13).chain([99, 100]).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_set_value() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+
+        let data = NotificationData {
+            id: 1,
+            timeout: 5000,
+            ..Default::default()
+        };
+        manager.add(data).unwrap();
+        let height = manager.notifications()[0].get_render_bounds().height;
+
+        assert!(manager.set_value(1, 40));
+        assert_eq!(manager.notifications()[0].data.hints.value, Some(40));
+        assert!(manager.notifications()[0].get_render_bounds().height > height);
+        assert!(manager.notifications()[0].registration_token.is_some());
+
+        assert!(!manager.set_value(1, 40));
+        assert!(!manager.set_value(2, 40));
+    }
+
+    #[test]
+    fn test_replaced_row() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+
+        let stored = |id, app_name: &str, tag: Option<&str>, rowid| NotificationData {
+            id,
+            app_name: app_name.into(),
+            hints: NotificationHints {
+                synchronous: tag.map(Into::into),
+                ..Default::default()
+            },
+            rowid,
+            ..Default::default()
+        };
+        manager.add(stored(1, "rsync", None, Some(5))).unwrap();
+        manager
+            .add(stored(2, "volume", Some("volume"), Some(6)))
+            .unwrap();
+
+        assert_eq!(
+            manager.replaced_row(&stored(1, "rsync", None, None)),
+            Some(5)
+        );
+        assert_eq!(manager.replaced_row(&stored(1, "mail", None, None)), None);
+        assert_eq!(
+            manager.replaced_row(&stored(3, "volume", Some("volume"), None)),
+            Some(6)
+        );
+        assert_eq!(manager.replaced_row(&stored(3, "volume", None, None)), None);
+    }
+
+    #[test]
+    fn test_set_row() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+
+        let data = |id| NotificationData {
+            id,
+            app_name: "rsync".into(),
+            ..Default::default()
+        };
+        // Stored only once it's shown
+        manager.add(data(1)).unwrap();
+        assert_eq!(manager.replaced_row(&data(1)), None);
+
+        manager.set_row(1, 5);
+        assert_eq!(manager.replaced_row(&data(1)), Some(5));
+
+        // Held back ones are stored the same way
+        manager.inhibit();
+        manager.add(data(2)).unwrap();
+        manager.set_row(2, 6);
+        assert_eq!(manager.held(2).and_then(|data| data.rowid), Some(6));
+    }
+
+    #[test]
+    fn test_history_entries_stay() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+        manager.history = History::Shown;
+
+        let data = NotificationData {
+            id: 1,
+            timeout: 5000,
+            rowid: Some(1),
+            ..Default::default()
+        };
+        manager.add(data).unwrap();
+
+        let entry = &manager.notifications()[0];
+        assert_eq!(entry.data.timeout, 5000);
+        assert!(entry.timeout().is_none());
+        assert!(entry.registration_token.is_none());
+    }
+
+    #[test]
+    fn test_history_row() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+        manager.history = History::Shown;
+
+        let db = rusqlite::Connection::open_in_memory().unwrap();
+        xdg::create_history_table(&db).unwrap();
+        // Sent as 2 and 9, stored in rows 1 and 2
+        for id in [2, 9] {
+            let mut data = NotificationData {
+                id,
+                ..Default::default()
+            };
+            data.persist(&db, false).unwrap();
+        }
+        manager
+            .add_many(xdg::load_history(&db, None, 10, 0).unwrap())
+            .unwrap();
+
+        // Entries are only found by the id they're listed as
+        assert_eq!(manager.history_row(2), Some(2));
+        assert_eq!(manager.history_row(1), Some(1));
+        assert_eq!(manager.history_row(9), None);
+
+        let rowid = manager.history_row(2).unwrap();
+        xdg::forget(&db, rowid).unwrap();
+        manager.dismiss(2);
+        assert_eq!(manager.history_row(2), None);
+        assert_eq!(xdg::load_history(&db, None, 10, 0).unwrap().len(), 1);
+
+        // Apps can't reach entries from the database by the id they sent them with
+        assert!(manager.sent_as(2).is_none());
+
+        // Only by the one of a notification sent this session
+        let mut data = NotificationData {
+            id: 2,
+            ..Default::default()
+        };
+        data.persist(&db, false).unwrap();
+        data.sent_id = Some(data.id);
+        data.id = data.rowid.unwrap() as u32;
+        manager.add(data).unwrap();
+        assert_eq!(manager.sent_as(2).map(|n| n.id()), Some(3));
+        assert_eq!(manager.history_row(2), None);
+    }
+
+    #[test]
+    fn test_countdown() {
+        let mut config = Config::default();
+        config.styles.default.countdown.height = Size::Value(3.);
+        config.styles.hover.countdown.height = Size::Value(3.);
+        let config = Arc::new(config);
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+
+        let data = NotificationData {
+            id: 1,
+            timeout: 5000,
+            ..Default::default()
+        };
+        manager.add(data).unwrap();
+        assert!(manager.counting_down());
+
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        manager.select(1);
+        assert!(!manager.counting_down());
+
+        let remaining = manager.notifications()[0].remaining_timeout().unwrap();
+        assert!(remaining < std::time::Duration::from_millis(5000));
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        assert_eq!(
+            manager.notifications()[0].remaining_timeout(),
+            Some(remaining)
+        );
+
+        manager.deselect();
+        assert!(manager.counting_down());
+    }
+
+    #[test]
+    fn test_group_by_app() {
+        let mut config = Config::default();
+        config.general.group_by_app = true;
+        let config = Arc::new(config);
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+
+        for id in 1..=3 {
+            let data = NotificationData {
+                id,
+                app_name: "chatty".into(),
+                ..Default::default()
+            };
+            manager.add(data).unwrap();
+        }
+
+        let data = NotificationData {
+            id: 4,
+            app_name: "other".into(),
+            ..Default::default()
+        };
+        manager.add(data).unwrap();
+
+        let ids: Vec<_> = manager.notifications().iter().map(|n| n.id()).collect();
+        assert_eq!(ids, vec![3, 4]);
+        assert!(manager.is_grouped(1));
+        assert!(manager.is_grouped(2));
+
+        manager.select(3);
+        manager.toggle_group();
+        assert_eq!(manager.notifications().len(), 4);
+
+        manager.toggle_group();
+        assert_eq!(manager.notifications().len(), 2);
+
+        manager.dismiss(3);
+        let ids: Vec<_> = manager.notifications().iter().map(|n| n.id()).collect();
+        assert_eq!(ids, vec![2, 4]);
+
+        assert_eq!(manager.dismiss_group(2), vec![1]);
+        assert!(!manager.is_grouped(1));
+    }
+
+    #[test]
+    fn test_add_many() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let font_system = Rc::new(RefCell::new(FontSystem::new()));
+        let mut manager = NotificationManager::new(
+            Arc::clone(&config),
+            event_loop.handle(),
+            calloop::channel::channel().0,
+            font_system,
+        );
+
+        let mut notifications = Vec::new();
+        for i in 1..=5 {
+            let data = NotificationData {
+                id: i,
+                ..Default::default()
+            };
+            notifications.push(data);
+        }
+
+        manager.add_many(notifications).unwrap();
+        assert_eq!(manager.notifications().len(), 5);
+    }
+
+    #[test]
+    fn test_dismiss() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let font_system = Rc::new(RefCell::new(FontSystem::new()));
+        let mut manager = NotificationManager::new(
+            Arc::clone(&config),
+            event_loop.handle(),
+            calloop::channel::channel().0,
+            font_system,
+        );
+
+        let data = NotificationData {
+            id: 123,
+            ..Default::default()
+        };
+        manager.add(data).unwrap();
+
+        assert_eq!(manager.notifications().len(), 1);
+
+        manager.dismiss(123);
+        assert_eq!(manager.notifications().len(), 0);
+    }
+
+    #[test]
+    fn test_dismiss_waits_for_exit_animation() {
+        let mut config = Config::default();
+        config.general.animation.duration_ms = 50;
+        let config = Arc::new(config);
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+
+        for id in 1..=2 {
+            let data = NotificationData {
+                id,
+                ..Default::default()
+            };
+            manager.add(data).unwrap();
+        }
+        assert!(manager.animating());
+
+        manager.dismiss(1);
+        let dismissed = std::time::Instant::now();
+        assert_eq!(manager.notifications().len(), 2);
+        assert!(manager.notifications()[0].exiting());
+        assert!(!manager.tick_at(dismissed));
+
+        assert!(manager.tick_at(dismissed + std::time::Duration::from_millis(50)));
+        assert_eq!(manager.notifications().len(), 1);
+        assert_eq!(manager.notifications()[0].id(), 2);
+        assert_eq!(manager.notifications()[0].get_bounds().y, 0.);
+        assert!(manager.animating());
+    }
+
+    #[test]
+    fn test_compact() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+
+        for id in 1..=2 {
+            let data = NotificationData {
+                id,
+                summary: "Summary".into(),
+                body: "First line\nSecond line\nThird line".into(),
+                actions: vec![(Arc::from("open"), Arc::from("Open"))].into(),
+                ..Default::default()
+            };
+            manager.add(data).unwrap();
+        }
+        let full = manager.notifications()[0].get_bounds().height;
+        let second_y = manager.notifications()[1].get_bounds().y;
+
+        assert!(manager.set_compact(true));
+        assert!(!manager.set_compact(true));
+        let notification = &manager.notifications()[0];
+        assert!(notification.compact());
+        assert!(notification.get_bounds().height < full);
+        assert!(notification
+            .buttons
+            .buttons()
+            .iter()
+            .all(|button| button.button_type() == ButtonType::Dismiss));
+        assert!(manager.notifications()[1].get_bounds().y < second_y);
+
+        // Only the selected one is shown in full
+        manager.select(2);
+        assert!(manager.notifications()[0].compact());
+        assert!(!manager.notifications()[1].compact());
+        manager.select(1);
+        assert!(!manager.notifications()[0].compact());
+        assert!(manager.notifications()[1].compact());
+        manager.deselect();
+        assert!(manager.notifications()[0].compact());
+
+        assert!(manager.set_compact(false));
+        assert!(!manager.notifications()[0].compact());
+        assert_eq!(manager.notifications()[0].get_bounds().height, full);
+    }
+
+    #[test]
+    fn test_swipe() {
+        let mut config = Config::default();
+        config.general.animation.duration_ms = 50;
+        let config = Arc::new(config);
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+
+        let data = NotificationData {
+            id: 1,
+            ..Default::default()
+        };
+        manager.add(data).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(60));
+        manager.tick();
+
+        manager.swipe(1, 80.);
+        assert_eq!(manager.notifications()[0].frame().shift, 80.);
+
+        // Too short a swipe eases back into place
+        manager.snap_back(1);
+        assert!(manager.animating());
+        std::thread::sleep(std::time::Duration::from_millis(60));
+        assert!(!manager.tick());
+        assert_eq!(manager.notifications()[0].frame(), Frame::REST);
+
+        // Dismissing slides it off the side it was swiped to
+        manager.swipe(1, -80.);
+        manager.dismiss(1);
+        let notification = &manager.notifications()[0];
+        assert!(notification.exiting());
+        assert!(notification.frame().shift <= -80.);
+
+        std::thread::sleep(std::time::Duration::from_millis(60));
+        let width = notification.get_bounds().width;
+        assert!((notification.frame().shift + width).abs() < 0.01);
+        assert!(manager.tick());
+        assert!(manager.notifications().is_empty());
+    }
+
+    #[test]
+    fn test_select_and_deselect() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let font_system = Rc::new(RefCell::new(FontSystem::new()));
+        let mut manager = NotificationManager::new(
+            Arc::clone(&config),
+            event_loop.handle(),
+            calloop::channel::channel().0,
+            font_system,
+        );
+
+        let data = NotificationData {
+            id: 1,
+            ..Default::default()
+        };
+        manager.add(data).unwrap();
+
+        assert_eq!(manager.selected_id(), None);
+
+        manager.select(1);
+        assert_eq!(manager.selected_id(), Some(1));
+
+        let notification = manager.selected_notification_mut().unwrap();
+        assert!(notification.hovered());
+
+        manager.deselect();
+        assert_eq!(manager.selected_id(), None);
+    }
+
+    #[test]
+    fn test_needs_redraw() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+
+        let data = NotificationData {
+            id: 1,
+            ..Default::default()
+        };
+        manager.add(data).unwrap();
+        assert!(manager.needs_redraw());
+
+        manager.clear_redraw();
+        manager.inhibit();
+        let data = NotificationData {
+            id: 2,
+            ..Default::default()
+        };
+        manager.add(data).unwrap();
+        assert!(!manager.needs_redraw());
+
+        manager.select(1);
+        assert!(manager.needs_redraw());
+
+        manager.clear_redraw();
+        manager.deselect();
+        assert!(manager.needs_redraw());
+
+        manager.clear_redraw();
+        manager.deselect();
+        assert!(!manager.needs_redraw());
+    }
+
+    #[test]
+    fn test_next_and_prev() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let font_system = Rc::new(RefCell::new(FontSystem::new()));
+        let mut manager = NotificationManager::new(
+            Arc::clone(&config),
+            event_loop.handle(),
+            calloop::channel::channel().0,
+            font_system,
+        );
+
+        for i in 1..=10 {
+            let data = NotificationData {
+                id: i,
+                ..Default::default()
+            };
+            manager.add(data).unwrap();
+        }
+
+        manager.next();
+        assert_eq!(manager.selected_id(), Some(1));
+
+        manager.next();
+        assert_eq!(manager.selected_id(), Some(2));
+
+        manager.next();
+        assert_eq!(manager.selected_id(), Some(3));
+
+        manager.next();
+        assert_eq!(manager.selected_id(), Some(4));
+
+        manager.next();
+        assert_eq!(manager.selected_id(), Some(5));
+
+        manager.next();
+        assert_eq!(manager.selected_id(), Some(6));
+
+        manager.prev();
+        assert_eq!(manager.selected_id(), Some(5));
+
+        manager.prev();
+        assert_eq!(manager.selected_id(), Some(4));
+
+        manager.prev();
+        assert_eq!(manager.selected_id(), Some(3));
+
+        manager.prev();
+        assert_eq!(manager.selected_id(), Some(2));
+
+        manager.prev();
+        assert_eq!(manager.selected_id(), Some(1));
+    }
+
+    #[test]
+    fn test_next_and_prev_n() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+
+        for i in 1..=10 {
+            let data = NotificationData {
+                id: i,
+                ..Default::default()
+            };
+            manager.add(data).unwrap();
+        }
+
+        manager.next();
+        assert_eq!(manager.selected_id(), Some(1));
+
+        manager.next_n(3);
+        assert_eq!(manager.selected_id(), Some(4));
+
+        manager.prev_n(2);
+        assert_eq!(manager.selected_id(), Some(2));
+    }
+
+    #[test]
+    fn test_osd_not_selectable() {
+        let mut config = Config::default();
+        let mut volume = NotificationStyleEntry {
+            app: "volume".into(),
+            default: config.styles.default.clone(),
+            hover: config.styles.hover.clone(),
+            ..Default::default()
+        };
+        volume.default.render_mode = RenderMode::Osd;
+        config.styles.notification.push(volume);
+
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::new(config));
+
+        let osd = |id, value| {
+            let mut data = NotificationData {
+                id,
+                app_name: "volume".into(),
+                summary: "Volume".into(),
+                ..Default::default()
+            };
+            data.hints.value = Some(value);
+            data.hints.synchronous = Some("volume".into());
+            data
+        };
+
+        manager
+            .add(NotificationData {
+                id: 1,
+                ..Default::default()
+            })
+            .unwrap();
+        manager.add(osd(2, 40)).unwrap();
+        manager
+            .add(NotificationData {
+                id: 3,
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert!(manager.notifications()[1].osd());
+        assert!(manager.notifications()[1].buttons.buttons().is_empty());
+
+        manager.next();
+        assert_eq!(manager.selected_id(), Some(1));
+        manager.next();
+        assert_eq!(manager.selected_id(), Some(3));
+        manager.next();
+        assert_eq!(manager.selected_id(), Some(1));
+        manager.prev();
+        assert_eq!(manager.selected_id(), Some(3));
+        manager.prev();
+        assert_eq!(manager.selected_id(), Some(1));
+
+        manager.add(osd(4, 60)).unwrap();
+        let ids: Vec<_> = manager.notifications().iter().map(|n| n.id()).collect();
+        assert_eq!(ids, vec![1, 4, 3]);
+        assert!(manager.notifications()[1].osd());
+    }
+
+    #[test]
+    fn test_osd_placement() {
+        let mut config = Config::default();
+        let mut volume = NotificationStyleEntry {
+            app: "volume".into(),
+            default: config.styles.default.clone(),
+            hover: config.styles.hover.clone(),
+            ..Default::default()
+        };
+        volume.default.render_mode = RenderMode::Osd;
+        config.styles.notification.push(volume);
+
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::new(config));
+
+        let mut volume = NotificationData {
+            id: 1,
+            app_name: "volume".into(),
+            ..Default::default()
+        };
+        volume.hints.value = Some(40);
+        manager.add(volume).unwrap();
+        assert_eq!(manager.placement(), SurfacePlacement::Osd);
+
+        // Other notifications place the surface while they're shown, the OSD goes along
+        let mut critical = NotificationData {
+            id: 2,
+            ..Default::default()
+        };
+        critical.hints.urgency = Urgency::Critical;
+        manager.add(critical).unwrap();
+        assert_eq!(
+            manager.placement(),
+            SurfacePlacement::Urgency(Urgency::Critical)
+        );
+
+        manager.dismiss(2);
+        assert_eq!(manager.placement(), SurfacePlacement::Osd);
+    }
+
+    #[test]
+    fn test_scroll() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+
+        for i in 1..=10 {
+            let data = NotificationData {
+                id: i,
+                ..Default::default()
+            };
+            manager.add(data).unwrap();
+        }
+
+        let height = manager.notifications[0].get_bounds().height;
+        assert_eq!(manager.notification_view.visible, 0..5);
+        assert!(manager.notification_view.prev.is_none());
+        assert!(manager.notification_view.next.is_some());
+
+        assert!(manager.scroll(height / 2.));
+        assert_eq!(manager.notification_view.visible, 0..6);
+
+        assert!(manager.scroll(f32::MAX));
+        assert!((manager.notification_view.offset - height * 5.).abs() < 0.01);
+        assert_eq!(manager.notification_view.visible, 5..10);
+        assert!(manager.notification_view.prev.is_some());
+        assert!(manager.notification_view.next.is_none());
+        assert!(!manager.scroll(1.));
+
+        // Selecting the first notification brings it back into view
+        manager.next();
+        assert_eq!(manager.selected_id(), Some(1));
+        assert_eq!(manager.notification_view.offset, 0.);
+        assert_eq!(manager.notifications[0].y, manager.notification_view.top());
+
+        (0..6).for_each(|_| manager.next());
+        assert_eq!(manager.selected_id(), Some(7));
+        assert_eq!(manager.notification_view.visible, 2..7);
+    }
+
+    #[test]
+    fn test_max_visible_auto() {
+        let mut config = Config::default();
+        config.general.max_visible = MaxVisible::Auto;
+        let config = Arc::new(config);
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+
+        for i in 1..=10 {
+            let data = NotificationData {
+                id: i,
+                ..Default::default()
+            };
+            manager.add(data).unwrap();
+        }
+
+        // Until the output is known as many are shown as by default
+        assert_eq!(manager.notification_view.visible, 0..5);
+
+        let height = manager.notifications[0].get_bounds().height;
+        manager.set_output_height(Some(height * 20.));
+        assert_eq!(manager.notification_view.visible, 0..10);
+        assert!(manager.notification_view.next.is_none());
+
+        // The counters take some of the room
+        manager.set_output_height(Some(height * 7.5));
+        let visible = manager.notification_view.visible.clone();
+        assert!(visible.start == 0 && (1..7).contains(&visible.end));
+        assert!(manager.notification_view.height() <= height * 7.5);
+        assert!(manager.notification_view.next.is_some());
+
+        manager.set_output_height(Some(1.));
+        assert_eq!(manager.notification_view.visible, 0..1);
+    }
+
+    #[test]
+    fn test_counter_click() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+
+        for i in 1..=10 {
+            let data = NotificationData {
+                id: i,
+                ..Default::default()
+            };
+            manager.add(data).unwrap();
+        }
+
+        let center = |counter: &Option<Notification>| {
+            let bounds = counter.as_ref().unwrap().get_render_bounds();
+            (
+                (bounds.x + bounds.width / 2.) as f64,
+                (bounds.y + bounds.height / 2.) as f64,
+            )
+        };
+
+        let (x, y) = center(&manager.notification_view.next);
+        assert!(manager.hover(x, y));
+        assert_eq!(manager.notification_view.hovered, Some(Counter::Next));
+
+        assert!(manager.click_button(x, y));
+        assert_eq!(manager.notification_view.visible, 5..10);
+        assert!(manager.notification_view.next.is_none());
+        assert_eq!(manager.notification_view.hovered, None);
+
+        let (x, y) = center(&manager.notification_view.prev);
+        assert!(manager.click_button(x, y));
+        assert_eq!(manager.notification_view.visible, 0..5);
+        assert!(manager.notification_view.prev.is_none());
+    }
+
+    #[test]
+    fn test_inhibit() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let font_system = Rc::new(RefCell::new(FontSystem::new()));
+        let mut manager = NotificationManager::new(
+            Arc::clone(&config),
+            event_loop.handle(),
+            calloop::channel::channel().0,
+            font_system,
+        );
+
+        let data = NotificationData {
+            id: 0,
+            ..Default::default()
+        };
+        manager.add(data).unwrap();
 
-                let replaced_height_differs = self.notifications[index].get_bounds().height
-                    != notification.get_bounds().height;
+        assert_eq!(manager.notifications().len(), 1);
 
-                self.notifications[index] = notification;
+        manager.inhibit();
 
-                if replaced_height_differs {
-                    self.notification_view.visible.clone().fold(
-                        self.notification_view
-                            .prev
-                            .as_ref()
-                            .map(|p| p.get_bounds().height)
-                            .unwrap_or(0.),
-                        |acc, i| {
-                            if let Some(notification) = self.notifications.get_mut(i) {
-                                notification.set_position(notification.x, acc);
-                                acc + notification.get_bounds().height
-                            } else {
-                                acc
-                            }
-                        },
-                    );
-                }
-            }
-            None => self.notifications.push(notification),
-        }
+        let data = NotificationData {
+            id: 1,
+            ..Default::default()
+        };
+        manager.add(data).unwrap();
 
-        // Maintain selection if replaced
-        if let Some(id) = self.selected_id() {
-            self.select(id);
-        }
+        assert!(manager.inhibited());
+        assert_eq!(manager.notifications().len(), 1);
+        assert_eq!(manager.waiting(), 1);
 
-        if self.notification_view.visible.end < self.notifications.len() {
-            self.notification_view
-                .update_notification_count(self.height(), self.notifications.len());
-        }
+        manager.uninhibit();
 
-        let x_offset = self
-            .notifications
-            .iter()
-            .map(|n| n.data.hints.x)
-            .min()
-            .unwrap_or_default()
-            .abs() as f32;
+        assert!(!manager.inhibited());
+        assert_eq!(manager.notifications().len(), 1);
+        assert_eq!(manager.waiting(), 0);
+    }
 
-        self.notifications
-            .iter_mut()
-            .for_each(|n| n.set_position(x_offset, n.y));
+    #[test]
+    fn test_restore_waiting() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
 
-        Ok(())
+        let waiting = [1, 2]
+            .map(|id| NotificationData {
+                id,
+                ..Default::default()
+            })
+            .into();
+        manager.restore_waiting(waiting);
+
+        assert!(manager.inhibited());
+        assert_eq!(manager.waiting(), 2);
+        // Ids of the restored notifications aren't handed out again
+        assert_eq!(manager.ids.next(), 3);
     }
 
-    pub fn dismiss(&mut self, id: NotificationId) {
-        if let Some(i) = self.notifications.iter().position(|n| n.id() == id) {
-            if let Some(notification) = self.notifications.get(i) {
-                notification.stop_timer(&self.loop_handle);
+    #[test]
+    fn test_auto_inhibit() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
 
-                if let Some(next_notification) = self.notifications.get(i + 1) {
-                    if self.selected_id() == Some(notification.id()) {
-                        self.select(next_notification.id());
-                    }
-                    self.notifications.remove(i);
-                } else {
-                    self.notifications.remove(i);
-                    self.prev();
-                }
-            }
-        }
+        assert!(manager.set_auto_inhibit(true).is_empty());
+        manager.inhibit();
 
-        self.notification_view
-            .update_notification_count(self.height(), self.notifications.len());
+        let data = NotificationData {
+            id: 1,
+            ..Default::default()
+        };
+        manager.add(data).unwrap();
 
-        if let Queue::FIFO = self.config.general.queue {
-            if let Some(notification) = self.notifications.first_mut().filter(|n| !n.hovered()) {
-                notification.start_timer(&self.loop_handle);
+        // Lifting the manual inhibit leaves the screencast one in place
+        assert!(manager.uninhibit().is_empty());
+        assert!(manager.inhibited());
+        assert_eq!(
+            manager.inhibit_state(),
+            InhibitState {
+                manual: false,
+                screencast: true,
+                schedule: false,
             }
-        }
-
-        self.notification_view.visible.clone().fold(
-            self.notification_view
-                .prev
-                .as_ref()
-                .map(|p| p.get_bounds().height)
-                .unwrap_or(0.),
-            |acc, i| {
-                if let Some(notification) = self.notifications.get_mut(i) {
-                    notification.set_position(notification.x, acc);
-                    acc + notification.get_bounds().height
-                } else {
-                    acc
-                }
-            },
         );
-    }
-}
-
-#[derive(Clone, Copy)]
-pub enum Reason {
-    Expired = 1,
-    DismissedByUser = 2,
-    CloseNotificationCall = 3,
-    Unkown = 4,
-}
-
-impl fmt::Display for Reason {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let s = match self {
-            Reason::Expired => "Expired",
-            Reason::DismissedByUser => "DismissedByUser",
-            Reason::CloseNotificationCall => "CloseNotificationCall",
-            Reason::Unkown => "Unknown",
-        };
-        write!(f, "{s}")
-    }
-}
+        assert_eq!(manager.waiting(), 1);
 
-impl Moxnotify {
-    pub fn dismiss_range<T>(&mut self, range: T, reason: Option<Reason>)
-    where
-        T: std::slice::SliceIndex<[Notification], Output = [Notification]>,
-    {
-        let ids: Vec<_> = self.notifications.notifications()[range]
+        let waiting: Vec<u32> = manager
+            .set_auto_inhibit(false)
             .iter()
-            .map(|notification| notification.id())
+            .map(|data| data.id)
             .collect();
-
-        if let Some(reason) = reason {
-            ids.iter().for_each(|id| {
-                _ = self
-                    .emit_sender
-                    .send(EmitEvent::NotificationClosed { id: *id, reason });
-            });
-        }
-
-        if ids.len() == self.notifications.notifications.len() {
-            self.notifications.notifications.clear();
-            self.notifications
-                .notification_view
-                .update_notification_count(0., 0);
-            return;
-        }
-
-        ids.iter().for_each(|id| self.notifications.dismiss(*id));
+        assert_eq!(waiting, vec![1]);
+        assert!(!manager.inhibited());
+        assert_eq!(manager.waiting(), 0);
     }
 
-    pub fn dismiss_by_id(&mut self, id: u32, reason: Option<Reason>) {
-        match self.history {
-            History::Shown => {
-                _ = self
-                    .db
-                    .execute("DELETE FROM notifications WHERE rowid = ?1", params![id]);
-                self.notifications.dismiss(id);
-            }
-            History::Hidden => {
-                if let Some(index) = self
-                    .notifications
-                    .notifications
-                    .iter()
-                    .position(|n| n.id() == id)
-                {
-                    if self.notifications.selected_id() == Some(id) {
-                        self.notifications
-                            .ui_state
-                            .mode
-                            .store(keymaps::Mode::Normal, Ordering::Relaxed);
-                    }
+    #[test]
+    fn test_scheduled_inhibit() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
 
-                    self.notifications.dismiss(id);
-                    if let Some(reason) = reason {
-                        _ = self
-                            .emit_sender
-                            .send(EmitEvent::NotificationClosed { id, reason });
-                    }
-                    if self.notifications.selected_id() == Some(id) {
-                        let new_index = if index >= self.notifications.notifications.len() {
-                            self.notifications.notifications.len().saturating_sub(1)
-                        } else {
-                            index
-                        };
+        manager.inhibit();
+        assert!(manager.set_scheduled_inhibit(true).is_empty());
 
-                        if let Some(notification) = self.notifications.notifications.get(new_index)
-                        {
-                            self.notifications.select(notification.id());
-                        }
-                    }
-                }
-            }
-        }
+        let data = NotificationData {
+            id: 1,
+            ..Default::default()
+        };
+        manager.add(data).unwrap();
 
-        self.update_surface_size();
-        if let Some(surface) = self.surface.as_mut() {
-            if let Err(e) = surface.render(
-                &self.wgpu_state.device,
-                &self.wgpu_state.queue,
-                &self.notifications,
-            ) {
-                log::error!("Render error: {e}");
+        // The window ending leaves the manual inhibit in place
+        assert!(manager.set_scheduled_inhibit(false).is_empty());
+        assert_eq!(
+            manager.inhibit_state(),
+            InhibitState {
+                manual: true,
+                screencast: false,
+                schedule: false,
             }
-        }
+        );
+        assert_eq!(manager.waiting(), 1);
 
-        if self.notifications.notifications().is_empty() {
-            self.seat.keyboard.repeat.key = None;
-        }
+        let waiting: Vec<u32> = manager.uninhibit().iter().map(|data| data.id).collect();
+        assert_eq!(waiting, vec![1]);
+        assert!(!manager.inhibited());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::{cell::RefCell, rc::Rc, sync::Arc};
+    #[test]
+    fn test_lock() {
+        let mut config = Config::default();
+        config.general.lockscreen = Lockscreen::SummaryCount;
+        config.general.lockscreen_pass_urgency = vec![InhibitPass::Urgency(Urgency::Critical)];
+        let config = Arc::new(config);
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
 
-    use calloop::EventLoop;
-    use glyphon::FontSystem;
+        assert!(manager.set_locked(true).is_empty());
 
-    use super::NotificationManager;
-    use crate::{components::Component, config::Config, dbus::xdg::NotificationData};
+        (10..12).for_each(|id| {
+            let data = NotificationData {
+                id,
+                ..Default::default()
+            };
+            manager.add(data).unwrap();
+        });
 
-    #[test]
-    fn test_add() {
-        let config = Arc::new(Config::default());
-        let event_loop = EventLoop::try_new().unwrap();
-        let font_system = Rc::new(RefCell::new(FontSystem::new()));
-        let mut manager = NotificationManager::new(
-            Arc::clone(&config),
-            event_loop.handle(),
-            calloop::channel::channel().0,
-            font_system,
+        // A single notification stands in for the ones held back
+        assert_eq!(manager.waiting(), 2);
+        assert_eq!(manager.notifications().len(), 1);
+        assert_eq!(
+            &*manager.notifications()[0].data.summary,
+            "2 new notifications"
         );
 
-        let data = NotificationData::default();
+        let mut data = NotificationData {
+            id: 12,
+            ..Default::default()
+        };
+        data.hints.urgency = Urgency::Critical;
         manager.add(data).unwrap();
+        assert_eq!(manager.waiting(), 2);
+        assert_eq!(manager.notifications().len(), 2);
 
-        assert_eq!(manager.notifications().len(), 1);
+        let waiting: Vec<u32> = manager
+            .set_locked(false)
+            .iter()
+            .map(|data| data.id)
+            .collect();
+        assert_eq!(waiting, vec![10, 11]);
+        assert!(!manager.locked());
+        assert!(manager
+            .notifications()
+            .iter()
+            .all(|n| n.id() == 12 || n.exiting()));
     }
 
     #[test]
-    fn test_add_with_duplicate_id() {
-        let config = Arc::new(Config::default());
+    fn test_inhibit_pass_urgency() {
+        let mut config = Config::default();
+        config.general.inhibit_pass_urgency = vec![
+            InhibitPass::Urgency(Urgency::Critical),
+            InhibitPass::App("alarm".into()),
+        ];
+        let config = Arc::new(config);
         let event_loop = EventLoop::try_new().unwrap();
-        let font_system = Rc::new(RefCell::new(FontSystem::new()));
-        let mut manager = NotificationManager::new(
-            Arc::clone(&config),
-            event_loop.handle(),
-            calloop::channel::channel().0,
-            font_system,
-        );
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+
+        manager.inhibit();
 
         let data = NotificationData {
-            id: 42,
+            id: 1,
             ..Default::default()
         };
+        manager.add(data).unwrap();
 
-        manager.add(data.clone()).unwrap();
+        let mut data = NotificationData {
+            id: 2,
+            ..Default::default()
+        };
+        data.hints.urgency = Urgency::Critical;
+        manager.add(data).unwrap();
 
+        let data = NotificationData {
+            id: 3,
+            app_name: "alarm".into(),
+            ..Default::default()
+        };
         manager.add(data).unwrap();
 
-        assert_eq!(manager.notifications().len(), 1);
+        let ids: Vec<u32> = manager.notifications().iter().map(|n| n.id()).collect();
+        assert_eq!(ids, vec![2, 3]);
+        assert_eq!(manager.waiting(), 1);
+
+        let waiting: Vec<u32> = manager.uninhibit().iter().map(|data| data.id).collect();
+        assert_eq!(waiting, vec![1]);
+        assert_eq!(manager.waiting(), 0);
+        assert_eq!(manager.notifications().len(), 2);
     }
 
     #[test]
-    fn test_add_many() {
+    fn test_count() {
         let config = Arc::new(Config::default());
         let event_loop = EventLoop::try_new().unwrap();
-        let font_system = Rc::new(RefCell::new(FontSystem::new()));
-        let mut manager = NotificationManager::new(
-            Arc::clone(&config),
-            event_loop.handle(),
-            calloop::channel::channel().0,
-            font_system,
-        );
+        let mut manager = manager(&event_loop, Arc::clone(&config));
 
-        let mut notifications = Vec::new();
-        for i in 1..=5 {
+        for id in 1..=2 {
             let data = NotificationData {
-                id: i,
+                id,
                 ..Default::default()
             };
-            notifications.push(data);
+            manager.add(data).unwrap();
         }
+        assert_eq!(manager.count(), 2);
+
+        manager.inhibit();
+        let data = NotificationData {
+            id: 3,
+            ..Default::default()
+        };
+        manager.add(data).unwrap();
+        assert_eq!(manager.count(), 2);
+
+        manager.dismiss(1);
+        assert_eq!(manager.count(), 1);
+
+        manager.history = History::Shown;
+        assert_eq!(manager.count(), 0);
+    }
+
+    #[test]
+    fn test_lost_on_exit() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+
+        let data = NotificationData {
+            id: 1,
+            ..Default::default()
+        };
+        manager.add(data).unwrap();
 
-        manager.add_many(notifications).unwrap();
-        assert_eq!(manager.notifications().len(), 5);
+        manager.inhibit();
+        let data = NotificationData {
+            id: 2,
+            ..Default::default()
+        };
+        manager.add(data).unwrap();
+        assert_eq!(manager.lost_on_exit(), vec![1, 2]);
+
+        manager.history = History::Shown;
+        assert_eq!(manager.lost_on_exit(), vec![2]);
     }
 
     #[test]
-    fn test_dismiss() {
+    fn test_resident() {
         let config = Arc::new(Config::default());
         let event_loop = EventLoop::try_new().unwrap();
-        let font_system = Rc::new(RefCell::new(FontSystem::new()));
-        let mut manager = NotificationManager::new(
-            Arc::clone(&config),
-            event_loop.handle(),
-            calloop::channel::channel().0,
-            font_system,
-        );
+        let mut manager = manager(&event_loop, Arc::clone(&config));
 
-        let data = NotificationData {
-            id: 123,
+        let mut data = NotificationData {
+            id: 1,
             ..Default::default()
         };
+        data.hints.resident = true;
         manager.add(data).unwrap();
 
-        assert_eq!(manager.notifications().len(), 1);
+        let data = NotificationData {
+            id: 2,
+            ..Default::default()
+        };
+        manager.add(data).unwrap();
 
-        manager.dismiss(123);
-        assert_eq!(manager.notifications().len(), 0);
+        assert!(manager.resident(1));
+        assert!(!manager.resident(2));
+        assert!(!manager.resident(3));
     }
 
     #[test]
-    fn test_select_and_deselect() {
+    fn test_first_link() {
         let config = Arc::new(Config::default());
         let event_loop = EventLoop::try_new().unwrap();
-        let font_system = Rc::new(RefCell::new(FontSystem::new()));
-        let mut manager = NotificationManager::new(
-            Arc::clone(&config),
-            event_loop.handle(),
-            calloop::channel::channel().0,
-            font_system,
-        );
+        let mut manager = manager(&event_loop, Arc::clone(&config));
 
         let data = NotificationData {
             id: 1,
+            body: "See https://example.com and https://example.org".into(),
             ..Default::default()
         };
         manager.add(data).unwrap();
 
-        assert_eq!(manager.selected_id(), None);
+        let data = NotificationData {
+            id: 2,
+            body: "No links here".into(),
+            ..Default::default()
+        };
+        manager.add(data).unwrap();
 
-        manager.select(1);
-        assert_eq!(manager.selected_id(), Some(1));
+        assert_eq!(
+            manager.first_link(Some(1)),
+            Some((1, "https://example.com".into()))
+        );
+        assert_eq!(manager.first_link(Some(3)), None);
 
-        let notification = manager.selected_notification_mut().unwrap();
-        assert!(notification.hovered());
+        // The newest notification is used when none is selected
+        assert_eq!(manager.first_link(None), None);
 
-        manager.deselect();
-        assert_eq!(manager.selected_id(), None);
+        manager.select(1);
+        assert_eq!(
+            manager.first_link(None),
+            Some((1, "https://example.com".into()))
+        );
     }
 
     #[test]
-    fn test_next_and_prev() {
+    fn test_find_action() {
         let config = Arc::new(Config::default());
         let event_loop = EventLoop::try_new().unwrap();
-        let font_system = Rc::new(RefCell::new(FontSystem::new()));
-        let mut manager = NotificationManager::new(
-            Arc::clone(&config),
-            event_loop.handle(),
-            calloop::channel::channel().0,
-            font_system,
-        );
+        let mut manager = manager(&event_loop, Arc::clone(&config));
 
-        for i in 1..=10 {
-            let data = NotificationData {
-                id: i,
-                ..Default::default()
-            };
-            manager.add(data).unwrap();
-        }
+        let data = NotificationData {
+            id: 1,
+            actions: vec![(Arc::from("open"), Arc::from("Open"))].into(),
+            ..Default::default()
+        };
+        manager.add(data).unwrap();
 
-        manager.next();
-        assert_eq!(manager.selected_id(), Some(1));
+        assert_eq!(manager.find_action(1, "open"), Ok(()));
+        assert_eq!(
+            manager.find_action(1, "reply"),
+            Err(ActionError::UnknownAction(1, "reply".into()))
+        );
+        assert_eq!(
+            manager.find_action(2, "open"),
+            Err(ActionError::UnknownNotification(2))
+        );
+    }
 
-        manager.next();
-        assert_eq!(manager.selected_id(), Some(2));
+    #[test]
+    fn test_action_keeps_resident() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+
+        [(1, true), (2, false)]
+            .into_iter()
+            .for_each(|(id, resident)| {
+                let mut data = NotificationData {
+                    id,
+                    actions: vec![(Arc::from("default"), Arc::from("Open"))].into(),
+                    ..Default::default()
+                };
+                data.hints.resident = resident;
+                manager.add(data).unwrap();
+            });
 
-        manager.next();
-        assert_eq!(manager.selected_id(), Some(3));
+        // What Event::InvokeAction goes through before closing the notification
+        [1, 2].into_iter().for_each(|id| {
+            assert_eq!(manager.find_action(id, "default"), Ok(()));
+            if !manager.resident(id) {
+                manager.dismiss(id);
+            }
+        });
 
-        manager.next();
-        assert_eq!(manager.selected_id(), Some(4));
+        let ids: Vec<u32> = manager.notifications().iter().map(|n| n.id()).collect();
+        assert_eq!(ids, vec![1]);
+        assert_eq!(manager.find_action(1, "default"), Ok(()));
+    }
 
-        manager.next();
-        assert_eq!(manager.selected_id(), Some(5));
+    #[test]
+    fn test_app_ids() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+
+        [(1, "Discord"), (2, "firefox"), (3, "discord")]
+            .into_iter()
+            .for_each(|(id, app_name)| {
+                let data = NotificationData {
+                    id,
+                    app_name: app_name.into(),
+                    ..Default::default()
+                };
+                manager.add(data).unwrap();
+            });
 
-        manager.next();
-        assert_eq!(manager.selected_id(), Some(6));
+        assert_eq!(manager.app_ids("DISCORD"), vec![1, 3]);
+        assert_eq!(manager.app_ids("firefox"), vec![2]);
+        assert!(manager.app_ids("slack").is_empty());
+    }
 
-        manager.prev();
-        assert_eq!(manager.selected_id(), Some(5));
+    #[test]
+    fn test_dismiss_by_app() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+
+        [(1, "Discord"), (2, "firefox"), (3, "Discord"), (4, "slack")]
+            .into_iter()
+            .for_each(|(id, app_name)| {
+                let data = NotificationData {
+                    id,
+                    app_name: app_name.into(),
+                    ..Default::default()
+                };
+                manager.add(data).unwrap();
+            });
 
-        manager.prev();
+        manager.select(3);
+        assert_eq!(manager.dismiss_by_app("Discord"), vec![1, 3]);
+        let ids: Vec<_> = manager.notifications().iter().map(|n| n.id()).collect();
+        assert_eq!(ids, vec![2, 4]);
         assert_eq!(manager.selected_id(), Some(4));
 
-        manager.prev();
-        assert_eq!(manager.selected_id(), Some(3));
-
-        manager.prev();
-        assert_eq!(manager.selected_id(), Some(2));
+        // Dismissing another app keeps the selection where it is
+        assert_eq!(manager.dismiss_by_app("firefox"), vec![2]);
+        assert_eq!(manager.selected_id(), Some(4));
 
-        manager.prev();
-        assert_eq!(manager.selected_id(), Some(1));
+        assert_eq!(manager.dismiss_by_app("slack"), vec![4]);
+        assert!(manager.notifications().is_empty());
+        assert_eq!(manager.selected_id(), None);
+        assert!(manager.dismiss_by_app("slack").is_empty());
     }
 
     #[test]
-    fn test_inhibit() {
+    fn test_data() {
         let config = Arc::new(Config::default());
         let event_loop = EventLoop::try_new().unwrap();
         let font_system = Rc::new(RefCell::new(FontSystem::new()));
@@ -897,56 +3939,147 @@ mod tests {
         );
 
         let data = NotificationData {
-            id: 0,
+            id: 123,
             ..Default::default()
         };
         manager.add(data).unwrap();
 
-        assert_eq!(manager.notifications().len(), 1);
+        let data = manager.data();
+        // Body, summary, notification and dismiss button
+        assert_eq!(data.0.len(), 4);
+        // Body and summary
+        assert_eq!(data.1.len(), 2);
+        assert_eq!(data.2.len(), 0);
+    }
 
-        manager.inhibit();
+    fn add_fifty(manager: &mut NotificationManager) {
+        for id in 1..=50 {
+            let data = NotificationData {
+                id,
+                summary: format!("Summary {id}").into(),
+                body: format!("Body of <b>notification</b> {id}").into(),
+                ..Default::default()
+            };
+            manager.add(data).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_cached_instances() {
+        let mut config = Config::default();
+        config.general.max_visible = MaxVisible::Count(50);
+        let config = Arc::new(config);
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
+        add_fifty(&mut manager);
+
+        let instances = |manager: &NotificationManager| {
+            bytemuck::cast_slice::<_, u8>(&manager.data().0).to_vec()
+        };
+
+        let rebuilt = instances(&manager);
+        assert_eq!(instances(&manager), rebuilt);
+
+        // What's drawn from the cache is what would have been drawn without it
+        manager.select(2);
+        manager.hover(0., 0.);
+        let cached = instances(&manager);
+        manager
+            .notifications()
+            .iter()
+            .for_each(Notification::invalidate);
+        assert_eq!(instances(&manager), cached);
+
+        manager.dismiss(1);
+        let cached = instances(&manager);
+        manager
+            .notifications()
+            .iter()
+            .for_each(Notification::invalidate);
+        assert_eq!(instances(&manager), cached);
+    }
+
+    #[test]
+    fn test_unhover_anchors() {
+        let config = Arc::new(Config::default());
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::clone(&config));
 
         let data = NotificationData {
             id: 1,
+            summary: "Link".into(),
+            body: "<a href=\"https://example.com\">example</a>".into(),
             ..Default::default()
         };
         manager.add(data).unwrap();
 
-        assert!(manager.inhibited());
-        assert_eq!(manager.notifications().len(), 1);
-        assert_eq!(manager.waiting(), 1);
+        let bounds = manager.notifications()[0]
+            .buttons
+            .buttons()
+            .iter()
+            .find(|button| button.button_type() == ButtonType::Anchor)
+            .unwrap()
+            .get_render_bounds();
+        let (x, y) = (
+            (bounds.x + bounds.width / 2.) as f64,
+            (bounds.y + bounds.height / 2.) as f64,
+        );
+        assert!(manager.hover(x, y));
 
-        manager.uninhibit();
+        // Leaving the surface doesn't leave the link colored as hovered
+        manager.clear_redraw();
+        assert!(manager.unhover_anchors());
+        assert!(manager.needs_redraw());
+        assert!(!manager.unhover_anchors());
+    }
 
-        assert!(!manager.inhibited());
-        assert_eq!(manager.notifications().len(), 1);
-        assert_eq!(manager.waiting(), 0);
+    #[test]
+    fn test_exiting_not_dragged() {
+        let mut config = Config::default();
+        config.general.animation.duration_ms = 200;
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::new(config));
+
+        for id in 1..=2 {
+            let data = NotificationData {
+                id,
+                ..Default::default()
+            };
+            manager.add(data).unwrap();
+        }
+
+        assert!(manager.drag(1, 10.));
+        assert_eq!(manager.dragged, Some(1));
+
+        manager.dismiss(1);
+        assert!(manager.notifications()[0].exiting());
+        assert_eq!(manager.dragged, None);
+        assert!(!manager.drag(1, 20.));
     }
 
     #[test]
-    fn test_data() {
-        let config = Arc::new(Config::default());
+    fn test_exiting_not_clickable() {
+        let mut config = Config::default();
+        config.general.animation.duration_ms = 200;
         let event_loop = EventLoop::try_new().unwrap();
-        let font_system = Rc::new(RefCell::new(FontSystem::new()));
-        let mut manager = NotificationManager::new(
-            Arc::clone(&config),
-            event_loop.handle(),
-            calloop::channel::channel().0,
-            font_system,
-        );
+        let mut manager = manager(&event_loop, Arc::new(config));
 
         let data = NotificationData {
-            id: 123,
+            id: 1,
             ..Default::default()
         };
         manager.add(data).unwrap();
+        let bounds = manager.notifications()[0].get_render_bounds();
+        let (x, y) = (
+            (bounds.x + bounds.width / 2.) as f64,
+            (bounds.y + bounds.height / 2.) as f64,
+        );
+        assert!(manager.get_by_coordinates(x, y).is_some());
 
-        let data = manager.data();
-        // Body, summary, notification and dismiss button
-        assert_eq!(data.0.len(), 4);
-        // Body and summary
-        assert_eq!(data.1.len(), 2);
-        assert_eq!(data.2.len(), 0);
+        manager.dismiss(1);
+        assert!(manager.notifications()[0].exiting());
+        assert!(manager.get_by_coordinates(x, y).is_none());
+        assert!(!manager.click(x, y));
     }
 
     #[test]
@@ -1061,4 +4194,218 @@ mod tests {
             .get_by_coordinates(right + epsilon, bottom + epsilon)
             .is_none());
     }
+
+    fn offset_data(id: u32, x: i32, y: Option<i32>) -> NotificationData {
+        NotificationData {
+            id,
+            hints: NotificationHints {
+                x,
+                y,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_negative_x_offset() {
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::new(Config::default()));
+        manager.add(offset_data(1, -30, None)).unwrap();
+        manager.add(offset_data(2, 0, None)).unwrap();
+
+        // Everything moves right for the notification moved left to fit
+        let width = manager.notifications[1].get_bounds().width;
+        assert_eq!(manager.width(), width + 30.);
+
+        let moved = manager.notifications[0].get_render_bounds();
+        let plain = manager.notifications[1].get_render_bounds();
+        assert_eq!(plain.x - moved.x, 30.);
+
+        let hit = |x: f32, y: f32| {
+            manager
+                .get_by_coordinates(x as f64, y as f64)
+                .map(Notification::id)
+        };
+        assert_eq!(hit(moved.x + 1., moved.y + 1.), Some(1));
+        assert_eq!(hit(plain.x + 1., plain.y + 1.), Some(2));
+        assert_eq!(hit(plain.x - 1., plain.y + 1.), None);
+    }
+
+    #[test]
+    fn test_positive_y_offset() {
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::new(Config::default()));
+        manager.add(offset_data(1, 0, Some(40))).unwrap();
+        manager.add(offset_data(2, 0, None)).unwrap();
+
+        let heights: f32 = manager.heights().iter().sum();
+        assert_eq!(manager.height(), heights + 40.);
+
+        let moved = &manager.notifications[0];
+        let plain = &manager.notifications[1];
+        let margin = plain.get_render_bounds().y - plain.get_bounds().y;
+        assert_eq!(
+            moved.get_render_bounds().y - moved.get_bounds().y,
+            margin + 40.
+        );
+
+        // The top of its slot is left empty
+        let (x, y) = (
+            moved.get_render_bounds().x + 1.,
+            moved.get_bounds().y + margin + 1.,
+        );
+        assert!(manager.get_by_coordinates(x as f64, y as f64).is_none());
+        assert_eq!(
+            manager
+                .get_by_coordinates(x as f64, (y + 40.) as f64)
+                .map(Notification::id),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_mixed_offsets() {
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = manager(&event_loop, Arc::new(Config::default()));
+        manager.add(offset_data(1, -20, Some(-10))).unwrap();
+        manager.add(offset_data(2, 50, Some(30))).unwrap();
+
+        let width = manager.notifications[0].get_bounds().width;
+        let heights: f32 = manager.heights().iter().sum();
+        assert_eq!(manager.width(), width + 70.);
+        assert_eq!(manager.height(), heights + 40.);
+        assert_eq!(manager.notifications[0].offset(), (-20., -10.));
+        assert_eq!(
+            manager.notifications[1].get_render_bounds().x
+                - manager.notifications[0].get_render_bounds().x,
+            70.
+        );
+
+        // Only 40 pixels to spare on the output, the offset to the right gives way
+        let margin = &manager.config.general.margin;
+        let margins = margin.left.resolve(0.) + margin.right.resolve(0.);
+        manager.set_output_width(Some(width + margins + 40.));
+        assert_eq!(manager.notifications[1].offset(), (20., 30.));
+        assert_eq!(manager.width(), width + 40.);
+    }
+
+    fn sorted_manager(
+        event_loop: &EventLoop<'static, crate::Moxnotify>,
+        sort: Sort,
+    ) -> NotificationManager {
+        let mut config = Config::default();
+        config.general.sort = sort;
+        manager(event_loop, Arc::new(config))
+    }
+
+    fn sent_at(id: u32, timestamp: u64, urgency: Urgency) -> NotificationData {
+        NotificationData {
+            id,
+            timestamp,
+            hints: NotificationHints {
+                urgency,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn shown(manager: &NotificationManager) -> Vec<u32> {
+        let mut notifications: Vec<&Notification> = manager.notifications().iter().collect();
+        notifications.sort_by(|a, b| a.get_bounds().y.total_cmp(&b.get_bounds().y));
+        notifications.iter().map(|n| n.id()).collect()
+    }
+
+    #[test]
+    fn test_drag_reorder() {
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = sorted_manager(&event_loop, Sort::OldestFirst);
+        for id in 1..=3 {
+            manager
+                .add(sent_at(id, id as u64 * 1000, Urgency::Normal))
+                .unwrap();
+        }
+        assert_eq!(shown(&manager), vec![1, 2, 3]);
+
+        // Dropped past the middle of the last one
+        let height = manager.notifications()[0].get_bounds().height;
+        assert!(manager.drag(1, height * 2.5));
+        assert_eq!(manager.notifications()[0].frame().offset, height * 2.5);
+        manager.drop_dragged(1);
+        assert_eq!(shown(&manager), vec![2, 3, 1]);
+        assert_eq!(manager.notifications()[0].frame(), Frame::REST);
+
+        // New notifications still go where the sort puts them
+        manager.add(sent_at(4, 4000, Urgency::Normal)).unwrap();
+        assert_eq!(shown(&manager), vec![2, 3, 1, 4]);
+
+        manager.drag(4, -height * 3.);
+        manager.cancel_drag(4);
+        assert_eq!(shown(&manager), vec![2, 3, 1, 4]);
+        assert_eq!(manager.notifications()[3].frame(), Frame::REST);
+
+        // Gone mid-drag
+        assert!(!manager.drag(5, height));
+    }
+
+    #[test]
+    fn test_sort_newest_first() {
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = sorted_manager(&event_loop, Sort::NewestFirst);
+        manager.add(sent_at(1, 1000, Urgency::Normal)).unwrap();
+        manager.add(sent_at(2, 3000, Urgency::Normal)).unwrap();
+        manager.add(sent_at(3, 2000, Urgency::Normal)).unwrap();
+
+        // Positions follow the sort, the list itself stays in arrival order
+        assert_eq!(shown(&manager), vec![2, 3, 1]);
+        let ids: Vec<u32> = manager.notifications().iter().map(|n| n.id()).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        let displayed: Vec<u32> = manager.displayed().map(|n| n.id()).collect();
+        assert_eq!(displayed, vec![2, 3, 1]);
+
+        // Traversal goes down the screen
+        manager.next();
+        assert_eq!(manager.selected_id(), Some(2));
+        manager.next();
+        assert_eq!(manager.selected_id(), Some(3));
+        manager.prev();
+        assert_eq!(manager.selected_id(), Some(2));
+        manager.prev();
+        assert_eq!(manager.selected_id(), Some(1));
+    }
+
+    #[test]
+    fn test_sort_by_urgency() {
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = sorted_manager(&event_loop, Sort::Urgency);
+        manager.add(sent_at(1, 1000, Urgency::Low)).unwrap();
+        manager.add(sent_at(2, 2000, Urgency::Critical)).unwrap();
+        manager.add(sent_at(3, 3000, Urgency::Normal)).unwrap();
+        manager.add(sent_at(4, 4000, Urgency::Critical)).unwrap();
+
+        assert_eq!(shown(&manager), vec![2, 4, 1, 3]);
+    }
+
+    #[test]
+    fn test_selection_survives_resort() {
+        let event_loop = EventLoop::try_new().unwrap();
+        let mut manager = sorted_manager(&event_loop, Sort::NewestFirst);
+        (1..=3).for_each(|id| {
+            manager
+                .add(sent_at(id, 1000 * u64::from(id), Urgency::Normal))
+                .unwrap()
+        });
+
+        manager.next();
+        manager.next();
+        assert_eq!(manager.selected_id(), Some(2));
+
+        // Arriving on top moves the selected notification down without changing the selection
+        manager.add(sent_at(4, 4000, Urgency::Normal)).unwrap();
+        assert_eq!(manager.selected_id(), Some(2));
+        assert_eq!(shown(&manager), vec![4, 3, 2, 1]);
+        manager.next();
+        assert_eq!(manager.selected_id(), Some(1));
+    }
 }
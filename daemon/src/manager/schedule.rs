@@ -0,0 +1,132 @@
+use crate::{config::DndWindow, Event, Moxnotify};
+use calloop::timer::{TimeoutAction, Timer};
+use chrono::{Days, Local, NaiveDateTime};
+
+pub fn is_active(schedule: &[DndWindow], time: NaiveDateTime) -> bool {
+    schedule.iter().any(|window| window.contains(time))
+}
+
+/// Returns the next point in time at which the schedule flips between active and inactive.
+/// Boundaries of overlapping windows that don't change the overall state are skipped.
+pub fn next_transition(schedule: &[DndWindow], now: NaiveDateTime) -> Option<NaiveDateTime> {
+    let active = is_active(schedule, now);
+
+    let mut boundaries: Vec<NaiveDateTime> = (0..=7)
+        .filter_map(|offset| now.date().checked_add_days(Days::new(offset)))
+        .flat_map(|date| {
+            schedule
+                .iter()
+                .flat_map(move |window| [date.and_time(window.start), date.and_time(window.end)])
+        })
+        .filter(|boundary| *boundary > now)
+        .collect();
+    boundaries.sort();
+
+    boundaries
+        .into_iter()
+        .find(|boundary| is_active(schedule, *boundary) != active)
+}
+
+impl Moxnotify {
+    /// Sets the schedule's own inhibit reason when a window starts or ends, leaving a manual
+    /// inhibit alone. At startup nothing changes unless the current time falls into a window.
+    pub fn apply_dnd_schedule(&mut self) {
+        if self.config.general.dnd_schedule.is_empty() {
+            return;
+        }
+
+        let active = is_active(
+            &self.config.general.dnd_schedule,
+            Local::now().naive_local(),
+        );
+        if self.notifications.inhibit_state().schedule != active {
+            if let Err(e) = self.handle_app_event(Event::DndSchedule(active)) {
+                log::error!("Failed to apply do not disturb schedule: {e}");
+            }
+        }
+
+        self.schedule_dnd();
+    }
+
+    fn schedule_dnd(&mut self) {
+        if let Some(token) = self.dnd_registration_token.take() {
+            self.loop_handle.remove(token);
+        }
+
+        let now = Local::now().naive_local();
+        let Some(next) = next_transition(&self.config.general.dnd_schedule, now) else {
+            return;
+        };
+
+        let Ok(duration) = (next - now).to_std() else {
+            return;
+        };
+
+        log::debug!("Next do not disturb transition at {next}");
+
+        let timer = Timer::from_duration(duration);
+        self.dnd_registration_token = self
+            .loop_handle
+            .insert_source(timer, |_, _, moxnotify| {
+                moxnotify.dnd_registration_token = None;
+                moxnotify.apply_dnd_schedule();
+                TimeoutAction::Drop
+            })
+            .ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, NaiveTime, Weekday};
+
+    fn window(days: Vec<Weekday>, start: (u32, u32), end: (u32, u32)) -> DndWindow {
+        DndWindow {
+            days,
+            start: NaiveTime::from_hms_opt(start.0, start.1, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(end.0, end.1, 0).unwrap(),
+        }
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        // 2025-01-06 is a Monday
+        NaiveDate::from_ymd_opt(2025, 1, 6 + day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_window_crossing_midnight() {
+        let schedule = [window(vec![Weekday::Mon], (22, 0), (7, 0))];
+
+        assert!(!is_active(&schedule, at(0, 21, 59)));
+        assert!(is_active(&schedule, at(0, 22, 0)));
+        assert!(is_active(&schedule, at(1, 6, 59)));
+        assert!(!is_active(&schedule, at(1, 7, 0)));
+        assert!(!is_active(&schedule, at(1, 22, 30)));
+    }
+
+    #[test]
+    fn test_next_transition() {
+        let schedule = [window(vec![Weekday::Mon], (22, 0), (7, 0))];
+
+        assert_eq!(next_transition(&schedule, at(0, 12, 0)), Some(at(0, 22, 0)));
+        assert_eq!(next_transition(&schedule, at(0, 23, 0)), Some(at(1, 7, 0)));
+        assert_eq!(next_transition(&schedule, at(1, 8, 0)), Some(at(7, 22, 0)));
+        assert_eq!(next_transition(&[], at(0, 12, 0)), None);
+    }
+
+    #[test]
+    fn test_overlapping_windows() {
+        let schedule = [
+            window(vec![Weekday::Mon], (20, 0), (23, 0)),
+            window(vec![Weekday::Mon], (22, 0), (2, 0)),
+        ];
+
+        assert!(is_active(&schedule, at(0, 22, 30)));
+        assert!(is_active(&schedule, at(0, 23, 30)));
+        assert_eq!(next_transition(&schedule, at(0, 21, 0)), Some(at(1, 2, 0)));
+    }
+}
@@ -0,0 +1,132 @@
+use crate::{
+    components::notification::NotificationId, dbus::xdg, History, Moxnotify, NotificationData,
+};
+use calloop::timer::{TimeoutAction, Timer};
+use std::time::Duration;
+
+impl Moxnotify {
+    /// Takes notification `id` off screen for `minutes` without closing it, it's delivered again
+    /// once the time is up.
+    pub fn snooze(&mut self, id: NotificationId, minutes: u32) -> anyhow::Result<()> {
+        // Entries listed in the history aren't live notifications
+        if self.history == History::Shown {
+            log::debug!("Not snoozing history entry {id}");
+            return Ok(());
+        }
+
+        let Some(data) = self
+            .notifications
+            .notifications()
+            .iter()
+            .find(|notification| notification.id() == id && !notification.exiting())
+            .map(|notification| notification.data.clone())
+        else {
+            log::debug!("No notification with id={id} to snooze");
+            return Ok(());
+        };
+
+        let due = xdg::timestamp() + u64::from(minutes) * 60;
+        // Apps kept out of the history are only snoozed until the daemon exits
+        let history = &self.config.general.history;
+        if !history.excludes(&data.app_name) {
+            data.snooze(&self.db, due, history.redacts(&data.app_name))?;
+        }
+        log::info!("Snoozing notification with id={id} for {minutes} minutes");
+
+        // Scheduled first so the id stays reserved while it's off screen
+        self.schedule_snoozed(data, due);
+        self.dismiss_by_id(id, None);
+
+        Ok(())
+    }
+
+    /// Cancels the re-delivery of notification `id`, returns whether it was snoozed.
+    pub fn cancel_snooze(&mut self, id: NotificationId) -> bool {
+        let Some(token) = self.notifications.snoozed.remove(&id) else {
+            return false;
+        };
+
+        self.loop_handle.remove(token);
+        if let Err(e) = xdg::unsnooze(&self.db, id) {
+            log::warn!("Failed to forget snoozed notification: {e}");
+        }
+
+        true
+    }
+
+    /// Schedules the notifications that were snoozed before a restart, overdue ones are
+    /// delivered right away. Their ids aren't handed out to new notifications meanwhile.
+    pub fn restore_snoozed(&mut self) -> anyhow::Result<()> {
+        let snoozed = xdg::load_snoozed(&self.db)?;
+        if !snoozed.is_empty() {
+            log::info!("Restoring {} snoozed notifications", snoozed.len());
+        }
+
+        snoozed.into_iter().for_each(|(data, due)| {
+            // A replacement held back while it was snoozed took its place
+            if self.notifications.contains(data.id) {
+                log::debug!("Snoozed notification with id={} was replaced", data.id);
+                if let Err(e) = xdg::unsnooze(&self.db, data.id) {
+                    log::warn!("Failed to forget snoozed notification: {e}");
+                }
+                return;
+            }
+            self.schedule_snoozed(data, due);
+        });
+
+        let notifications = &self.notifications;
+        notifications.ids.set_in_use(notifications.active_ids());
+
+        Ok(())
+    }
+
+    fn schedule_snoozed(&mut self, data: NotificationData, due: u64) {
+        let id = data.id;
+        let timer = Timer::from_duration(Duration::from_secs(due.saturating_sub(xdg::timestamp())));
+
+        let mut data = Some(data);
+        let token = self
+            .loop_handle
+            .insert_source(timer, move |_, _, moxnotify| {
+                if let Some(data) = data.take() {
+                    moxnotify.wake(data);
+                }
+                TimeoutAction::Drop
+            });
+
+        match token {
+            Ok(token) => {
+                if let Some(previous) = self.notifications.snoozed.insert(id, token) {
+                    self.loop_handle.remove(previous);
+                }
+            }
+            Err(e) => log::error!("Failed to schedule snoozed notification: {e}"),
+        }
+    }
+
+    fn wake(&mut self, data: NotificationData) {
+        let id = data.id;
+        log::info!("Snooze of notification with id={id} is over");
+
+        self.notifications.snoozed.remove(&id);
+        if let Err(e) = xdg::unsnooze(&self.db, id) {
+            log::warn!("Failed to forget snoozed notification: {e}");
+        }
+
+        let data = NotificationData {
+            timestamp: xdg::timestamp(),
+            ..data
+        };
+        if let Err(e) = self.deliver(data) {
+            log::error!("Failed to deliver snoozed notification: {e}");
+            return;
+        }
+
+        if self.notifications.needs_redraw() {
+            self.update_surface_size();
+            if let Err(e) = self.render_surfaces() {
+                log::error!("Render error: {e}");
+            }
+        }
+    }
+}
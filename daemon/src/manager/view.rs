@@ -1,9 +1,9 @@
 use super::UiState;
 use crate::{
-    components::{notification::Notification, text::Text, Component},
-    config::Config,
+    components::{notification::Notification, text::Text, Component, Data},
+    config::{Config, MaxVisible},
     utils::buffers,
-    NotificationData,
+    History, NotificationData,
 };
 use glyphon::{FontSystem, TextArea};
 use std::{
@@ -13,10 +13,36 @@ use std::{
     sync::{atomic::Ordering, Arc},
 };
 
+/// Notifications shown with `max_visible = "auto"` before the output's size is known.
+const FALLBACK_VISIBLE: usize = 5;
+
+/// One of the counters of notifications scrolled out of view.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Counter {
+    Prev,
+    Next,
+}
+
 pub struct NotificationView {
     pub visible: Range<usize>,
+    /// Distance in pixels the list is scrolled past the top of its first notification.
+    pub offset: f32,
+    /// Height of the area notifications are shown in, counters excluded.
+    pub viewport: f32,
+    /// Whether the notifications don't fit in the viewport and have to be clipped.
+    overflowing: bool,
+    /// Logical height of the output the notifications are on, None until it's known.
+    pub output_height: Option<f32>,
+    /// Room kept above and below the viewport for notifications their `y` hint moves there.
+    pub padding: (f32, f32),
     pub prev: Option<Notification>,
     pub next: Option<Notification>,
+    /// Counter the pointer is over.
+    pub hovered: Option<Counter>,
+    /// Notifications below the view that haven't been in it yet.
+    pending: usize,
+    /// Number of notifications the counters were last updated for.
+    count: usize,
     font_system: Rc<RefCell<FontSystem>>,
     config: Arc<Config>,
     ui_state: UiState,
@@ -29,173 +55,344 @@ impl NotificationView {
         font_system: Rc<RefCell<FontSystem>>,
     ) -> Self {
         Self {
-            visible: 0..config.general.max_visible,
+            visible: 0..0,
+            offset: 0.,
+            viewport: 0.,
+            overflowing: false,
+            output_height: None,
+            padding: (0., 0.),
             config,
             font_system,
             prev: None,
             next: None,
+            hovered: None,
+            pending: 0,
+            count: 0,
             ui_state,
         }
     }
 
-    pub fn prev(&mut self, total_height: f32, index: usize, notification_count: usize) {
-        if index + 1 == notification_count {
-            self.visible = (notification_count
-                .max(self.config.general.max_visible)
-                .saturating_sub(self.config.general.max_visible))
-                ..notification_count.max(self.config.general.max_visible);
-        } else {
-            let first_visible = self.visible.start;
-            if index < first_visible {
-                let start = index;
-                let end = index + self.config.general.max_visible;
-                self.visible = start..end;
-            }
+    /// Switches to `config`, the counters are built again with it on the next update.
+    pub fn set_config(&mut self, config: Arc<Config>) {
+        self.config = config;
+        self.prev = None;
+        self.next = None;
+    }
+
+    /// Y coordinate the viewport starts at, below the counter of notifications above and the
+    /// padding.
+    pub fn top(&self) -> f32 {
+        self.prev.as_ref().map_or(0., |p| p.get_bounds().height) + self.padding.0
+    }
+
+    /// Y coordinate the viewport and the padding below it end at.
+    fn bottom(&self) -> f32 {
+        self.top() + self.viewport + self.padding.1
+    }
+
+    pub fn height(&self) -> f32 {
+        self.bottom() + self.next.as_ref().map_or(0., |n| n.get_bounds().height)
+    }
+
+    /// Moves the scroll offset by `delta` pixels, returns false if it's already at the edge.
+    pub fn scroll(&mut self, delta: f32, heights: &[f32]) -> bool {
+        let offset = self.offset;
+        self.offset += delta;
+        self.update(heights);
+
+        self.offset != offset
+    }
+
+    /// Scrolls just enough for the notification at `index` to be fully visible, or for its top
+    /// to be when it's taller than the viewport.
+    pub fn scroll_to(&mut self, index: usize, heights: &[f32]) {
+        let viewport = self.viewport_height(heights);
+        let top: f32 = heights.iter().take(index).sum();
+        let bottom = top + heights.get(index).copied().unwrap_or_default();
+
+        if bottom > self.offset + viewport {
+            self.offset = bottom - viewport;
         }
-        self.update_notification_count(total_height, notification_count);
-    }
-
-    pub fn next(&mut self, total_height: f32, index: usize, notification_count: usize) {
-        if index == 0 {
-            self.visible = 0..self.config.general.max_visible;
-        } else {
-            let last_visible = self.visible.end.saturating_sub(1);
-            if index > last_visible {
-                let start = index + 1 - self.config.general.max_visible;
-                let end = index + 1;
-                self.visible = start..end;
-            }
+        if top < self.offset {
+            self.offset = top;
+        }
+
+        self.update(heights);
+    }
+
+    /// Counter at the given surface coordinates, if it's shown.
+    pub fn counter_at(&self, x: f64, y: f64) -> Option<Counter> {
+        [(Counter::Prev, &self.prev), (Counter::Next, &self.next)]
+            .into_iter()
+            .find_map(|(counter, notification)| {
+                let extents = notification.as_ref()?.get_render_bounds();
+                (x >= extents.x as f64
+                    && y >= extents.y as f64
+                    && x <= (extents.x + extents.width) as f64
+                    && y <= (extents.y + extents.height) as f64)
+                    .then_some(counter)
+            })
+    }
+
+    /// Scrolls a whole viewport towards `counter`, returns false when there's nothing more in
+    /// that direction.
+    pub fn page(&mut self, counter: Counter, heights: &[f32]) -> bool {
+        let delta = match counter {
+            Counter::Prev => -self.viewport,
+            Counter::Next => self.viewport,
+        };
+
+        self.scroll(delta, heights)
+    }
+
+    /// The viewport fits the first `max_visible` notifications, the rest is reached by
+    /// scrolling.
+    fn viewport_height(&self, heights: &[f32]) -> f32 {
+        let max_visible = match self.config.general.max_visible {
+            MaxVisible::Count(count) => count,
+            MaxVisible::Auto => self.fitting(heights),
+        };
+
+        heights.iter().take(max_visible).sum()
+    }
+
+    /// Number of leading notifications that fit on the output together with the margins and,
+    /// when not all of them do, the counters. At least one is always shown.
+    fn fitting(&self, heights: &[f32]) -> usize {
+        let Some(output_height) = self.output_height else {
+            return FALLBACK_VISIBLE;
+        };
+
+        let margin = &self.config.general.margin;
+        let room = output_height
+            - margin.top.resolve(0.)
+            - margin.bottom.resolve(0.)
+            - self.padding.0
+            - self.padding.1;
+        let fits = |room: f32| {
+            heights
+                .iter()
+                .scan(0., |bottom, height| {
+                    *bottom += height;
+                    Some(*bottom)
+                })
+                .take_while(|bottom| *bottom <= room)
+                .count()
+        };
+
+        let count = fits(room);
+        if count == heights.len() {
+            return count;
+        }
+
+        // Scrolled to the middle both counters are shown
+        fits(room - 2. * self.counter_height()).max(1)
+    }
+
+    fn counter_height(&self) -> f32 {
+        match self.next.as_ref().or(self.prev.as_ref()) {
+            Some(counter) => counter.get_bounds().height,
+            None => self.new_counter().get_bounds().height,
+        }
+    }
+
+    /// Clamps the scroll offset to the content, then recomputes which notifications are in view
+    /// and the counters of the ones that aren't. `heights` are the heights of all notifications
+    /// in display order.
+    pub fn update(&mut self, heights: &[f32]) {
+        let content: f32 = heights.iter().sum();
+        self.viewport = self.viewport_height(heights);
+        self.offset = self.offset.clamp(0., (content - self.viewport).max(0.));
+        self.overflowing = content > self.viewport;
+
+        let (start, end, _) = heights.iter().enumerate().fold(
+            (heights.len(), 0, 0.),
+            |(start, end, y), (i, height)| {
+                // Slivers under half a pixel don't count as visible
+                if y + height - self.offset > 0.5 && self.offset + self.viewport - y > 0.5 {
+                    (start.min(i), i + 1, y + height)
+                } else {
+                    (start, end, y + height)
+                }
+            },
+        );
+        self.visible = if start < end { start..end } else { 0..0 };
+
+        self.update_notification_count(heights.len());
+    }
+
+    /// Sets how many of the notifications below the view haven't been in it yet, the next
+    /// counter shows them apart from the ones scrolled past.
+    pub fn set_pending(&mut self, pending: usize) {
+        if self.pending != pending {
+            self.pending = pending;
+            self.update_notification_count(self.count);
         }
-        self.update_notification_count(total_height, notification_count);
     }
 
-    pub fn update_notification_count(&mut self, mut total_height: f32, notification_count: usize) {
-        if self.visible.start > 0 {
+    fn update_notification_count(&mut self, notification_count: usize) {
+        self.count = notification_count;
+        let above = self.visible.start;
+        let below = notification_count.saturating_sub(self.visible.end);
+        let pending = self.pending.min(below);
+
+        self.prev = (above > 0).then(|| {
             let summary = self
                 .config
                 .styles
-                .next
-                .format
-                .replace("{}", &self.visible.start.to_string());
-            if let Some(notification) = &mut self.prev {
-                let mut font_system = self.font_system.borrow_mut();
-                notification.summary.set_text(&mut font_system, &summary);
-                notification.set_position(0., 0.);
-            } else {
-                self.prev = Some(Notification::new(
-                    Arc::clone(&self.config),
-                    &mut self.font_system.borrow_mut(),
-                    NotificationData {
-                        summary: summary.into(),
-                        ..Default::default()
-                    },
-                    self.ui_state.clone(),
-                    None,
-                ));
-
-                total_height += self
-                    .prev
-                    .as_ref()
-                    .map(|p| p.get_bounds().height)
-                    .unwrap_or_default();
-            }
-        } else {
-            total_height -= self
                 .prev
-                .as_ref()
-                .map(|p| p.get_bounds().height)
-                .unwrap_or_default();
-            self.prev = None;
-        };
+                .format
+                .replace("{}", &above.to_string());
+            let mut prev = self.prev.take().unwrap_or_else(|| self.new_counter());
+            prev.summary
+                .set_text(&mut self.font_system.borrow_mut(), &summary);
+            prev.set_position(0., 0.);
+            prev
+        });
 
-        if notification_count > self.visible.end {
-            let summary = self.config.styles.prev.format.replace(
-                "{}",
-                &notification_count
-                    .saturating_sub(self.visible.end)
-                    .to_string(),
-            );
-            if let Some(notification) = &mut self.next {
-                let mut font_system = self.font_system.borrow_mut();
-                notification.summary.set_text(&mut font_system, &summary);
-                notification.set_position(
-                    notification.x,
-                    total_height - notification.get_bounds().height,
-                );
-            } else {
-                let mut next = Notification::new(
-                    Arc::clone(&self.config),
-                    &mut self.font_system.borrow_mut(),
-                    NotificationData {
-                        summary: summary.into(),
-                        ..Default::default()
-                    },
-                    self.ui_state.clone(),
-                    None,
-                );
-                next.set_position(next.x, total_height);
-                self.next = Some(next);
-            }
-        } else {
-            self.next = None;
+        let top = self.bottom();
+        self.next = (below > 0).then(|| {
+            let style = &self.config.styles.next;
+            let summary = [
+                (below - pending, &style.format),
+                (pending, &style.pending_format),
+            ]
+            .into_iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, format)| format.replace("{}", &count.to_string()))
+            .collect::<Vec<_>>()
+            .join(" ");
+            let mut next = self.next.take().unwrap_or_else(|| self.new_counter());
+            next.summary
+                .set_text(&mut self.font_system.borrow_mut(), &summary);
+            next.set_position(next.x, top);
+            next
+        });
+
+        if self
+            .hovered
+            .is_some_and(|counter| self.counter(counter).is_none())
+        {
+            self.hovered = None;
         }
     }
 
-    pub fn prev_data(&self, total_width: f32) -> Option<(buffers::Instance, TextArea<'_>)> {
-        if let Some(prev) = self.prev.as_ref() {
-            let extents = prev.get_render_bounds();
-            let style = &self.config.styles.prev;
-            let instance = buffers::Instance {
-                rect_pos: [extents.x, extents.y],
-                rect_size: [
-                    total_width - style.border.size.left - style.border.size.right,
-                    extents.height - style.border.size.top - style.border.size.bottom,
-                ],
-                rect_color: style.background.to_linear(&crate::Urgency::Low),
-                border_radius: style.border.radius.into(),
-                border_size: style.border.size.into(),
-                border_color: style.border.color.to_linear(&crate::Urgency::Low),
-                scale: self.ui_state.scale.load(Ordering::Relaxed),
-                depth: 0.9,
-            };
-
-            return Some((
-                instance,
-                prev.summary
-                    .get_text_areas(&crate::Urgency::Low)
-                    .swap_remove(0),
-            ));
+    fn counter(&self, counter: Counter) -> Option<&Notification> {
+        match counter {
+            Counter::Prev => self.prev.as_ref(),
+            Counter::Next => self.next.as_ref(),
         }
+    }
 
-        None
+    fn new_counter(&self) -> Notification {
+        Notification::new(
+            Arc::clone(&self.config),
+            &mut self.font_system.borrow_mut(),
+            NotificationData::default(),
+            self.ui_state.clone(),
+            None,
+            History::Hidden,
+            None,
+        )
     }
 
-    pub fn next_data(&self, total_width: f32) -> Option<(buffers::Instance, TextArea<'_>)> {
-        if let Some(next) = self.next.as_ref() {
-            let extents = next.get_render_bounds();
-            let style = &self.config.styles.prev;
-            let instance = buffers::Instance {
-                rect_pos: [extents.x, extents.y],
-                rect_size: [
-                    total_width - style.border.size.left - style.border.size.right,
-                    extents.height - style.border.size.top - style.border.size.bottom,
-                ],
-                rect_color: style.background.to_linear(&crate::Urgency::Low),
-                border_radius: style.border.radius.into(),
-                border_size: style.border.size.into(),
-                border_color: style.border.color.to_linear(&crate::Urgency::Low),
-                scale: self.ui_state.scale.load(Ordering::Relaxed),
-                depth: 0.9,
-            };
-
-            return Some((
-                instance,
-                next.summary
-                    .get_text_areas(&crate::Urgency::Low)
-                    .swap_remove(0),
-            ));
+    /// Cuts off the parts of a notification that were scrolled out of the viewport, returns
+    /// None when nothing is left.
+    pub fn clip<'a>(&self, mut data: Data<'a>) -> Option<Data<'a>> {
+        if !self.overflowing {
+            return Some(data);
+        }
+
+        // Notifications moved into the padding are only cut off past it
+        let top = self.top() - self.padding.0;
+        let bottom = self.bottom();
+
+        match &mut data {
+            Data::Instance(instance) => {
+                let y = instance.rect_pos[1];
+                let height =
+                    instance.rect_size[1] + instance.border_size[2] + instance.border_size[3];
+                let (clipped_top, clipped_bottom) = (y.max(top), (y + height).min(bottom));
+                if clipped_bottom <= clipped_top {
+                    return None;
+                }
+
+                if y < top {
+                    instance.border_size[2] = 0.;
+                }
+                if y + height > bottom {
+                    instance.border_size[3] = 0.;
+                }
+                instance.rect_pos[1] = clipped_top;
+                instance.rect_size[1] = (clipped_bottom
+                    - clipped_top
+                    - instance.border_size[2]
+                    - instance.border_size[3])
+                    .max(0.);
+            }
+            Data::TextArea(text_area) => {
+                text_area.bounds.top = text_area.bounds.top.max(top as i32);
+                text_area.bounds.bottom = text_area.bounds.bottom.min(bottom as i32);
+                if text_area.bounds.bottom <= text_area.bounds.top {
+                    return None;
+                }
+            }
+            Data::Texture(texture) => {
+                texture.bounds.top = texture.bounds.top.max(top as u32);
+                texture.bounds.bottom = texture.bounds.bottom.min(bottom as u32);
+                if texture.bounds.bottom <= texture.bounds.top {
+                    return None;
+                }
+            }
         }
 
-        None
+        Some(data)
+    }
+
+    pub fn prev_data(&self, total_width: f32) -> Option<(buffers::Instance, TextArea<'_>)> {
+        self.counter_data(Counter::Prev, total_width)
+    }
+
+    pub fn next_data(&self, total_width: f32) -> Option<(buffers::Instance, TextArea<'_>)> {
+        self.counter_data(Counter::Next, total_width)
+    }
+
+    fn counter_data(
+        &self,
+        counter: Counter,
+        total_width: f32,
+    ) -> Option<(buffers::Instance, TextArea<'_>)> {
+        let notification = self.counter(counter)?;
+        let extents = notification.get_render_bounds();
+        let style = match counter {
+            Counter::Prev => &self.config.styles.prev,
+            Counter::Next => &self.config.styles.next,
+        };
+        let (border, background) = match self.hovered == Some(counter) {
+            true => (&style.hover.border, &style.hover.background),
+            false => (&style.border, &style.background),
+        };
+
+        let instance = buffers::Instance {
+            rect_pos: [extents.x, extents.y],
+            rect_size: [
+                total_width - border.size.left - border.size.right,
+                extents.height - border.size.top - border.size.bottom,
+            ],
+            rect_color: background.to_linear(&crate::Urgency::Low),
+            border_radius: border.radius.into(),
+            border_size: border.size.into(),
+            border_color: border.color.to_linear(&crate::Urgency::Low),
+            scale: self.ui_state.scale.load(Ordering::Relaxed),
+            depth: 0.9,
+        };
+
+        Some((
+            instance,
+            notification
+                .summary
+                .get_text_areas(&crate::Urgency::Low)
+                .swap_remove(0),
+        ))
     }
 }
@@ -1,8 +1,159 @@
-//pub struct AnimationManager {}
-//
-//struct Animation {
-//timer: f32,
-//current_frame: u32,
-//frames: u32,
-//frame_duration: f32,
-//}
+use crate::{components::Data, config};
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Kind {
+    Enter,
+    Exit,
+    Move,
+}
+
+/// Vertical offset from the laid out position and opacity multiplier of a notification.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Frame {
+    pub offset: f32,
+    /// Horizontal offset, notifications are shifted sideways while swiped away.
+    pub shift: f32,
+    pub opacity: f32,
+}
+
+impl Frame {
+    pub const REST: Frame = Frame {
+        offset: 0.,
+        shift: 0.,
+        opacity: 1.,
+    };
+
+    pub fn apply(&self, data: &mut Data) {
+        match data {
+            Data::Instance(instance) => {
+                instance.rect_pos[0] += self.shift;
+                instance.rect_pos[1] += self.offset;
+                instance.rect_color[3] *= self.opacity;
+                instance.border_color[3] *= self.opacity;
+            }
+            Data::TextArea(text_area) => {
+                text_area.left += self.shift;
+                text_area.top += self.offset;
+                text_area.bounds.left += self.shift as i32;
+                text_area.bounds.right += self.shift as i32;
+                text_area.bounds.top += self.offset as i32;
+                text_area.bounds.bottom += self.offset as i32;
+
+                let color = text_area.default_color;
+                text_area.default_color = glyphon::Color::rgba(
+                    color.r(),
+                    color.g(),
+                    color.b(),
+                    (color.a() as f32 * self.opacity) as u8,
+                );
+            }
+            Data::Texture(texture) => {
+                texture.left += self.shift;
+                texture.top += self.offset;
+                texture.bounds.left = (texture.bounds.left as f32 + self.shift).max(0.) as u32;
+                texture.bounds.right = (texture.bounds.right as f32 + self.shift).max(0.) as u32;
+                texture.bounds.top = (texture.bounds.top as f32 + self.offset).max(0.) as u32;
+                texture.bounds.bottom = (texture.bounds.bottom as f32 + self.offset).max(0.) as u32;
+                texture.opacity *= self.opacity;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Animation {
+    pub kind: Kind,
+    start: Instant,
+    duration: Duration,
+    easing: config::Easing,
+    from: Frame,
+    to: Frame,
+}
+
+impl Animation {
+    pub fn new(config: &config::Animation, kind: Kind, from: Frame, to: Frame) -> Self {
+        Self {
+            kind,
+            start: Instant::now(),
+            duration: Duration::from_millis(config.duration_ms),
+            easing: config.easing,
+            from,
+            to,
+        }
+    }
+
+    fn progress(&self, now: Instant) -> f32 {
+        if self.duration.is_zero() {
+            return 1.;
+        }
+
+        let elapsed = now.saturating_duration_since(self.start);
+        self.easing
+            .apply(elapsed.as_secs_f32() / self.duration.as_secs_f32())
+    }
+
+    pub fn frame(&self, now: Instant) -> Frame {
+        let t = self.progress(now);
+        Frame {
+            offset: self.from.offset + (self.to.offset - self.from.offset) * t,
+            shift: self.from.shift + (self.to.shift - self.from.shift) * t,
+            opacity: self.from.opacity + (self.to.opacity - self.from.opacity) * t,
+        }
+    }
+
+    pub fn finished(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.start) >= self.duration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Animation, Frame, Kind};
+    use crate::config::{self, Easing};
+    use std::time::Duration;
+
+    #[test]
+    fn test_frame_interpolation() {
+        let config = config::Animation {
+            duration_ms: 100,
+            easing: Easing::Linear,
+        };
+        let animation = Animation::new(
+            &config,
+            Kind::Exit,
+            Frame::REST,
+            Frame {
+                offset: -20.,
+                shift: 0.,
+                opacity: 0.,
+            },
+        );
+
+        assert_eq!(animation.frame(animation.start), Frame::REST);
+
+        let halfway = animation.frame(animation.start + Duration::from_millis(50));
+        assert!((halfway.offset + 10.).abs() < f32::EPSILON);
+        assert!((halfway.opacity - 0.5).abs() < f32::EPSILON);
+
+        let end = animation.start + Duration::from_millis(150);
+        assert!(animation.finished(end));
+        assert_eq!(animation.frame(end).opacity, 0.);
+    }
+
+    #[test]
+    fn test_easing_bounds() {
+        [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+        ]
+        .into_iter()
+        .for_each(|easing| {
+            assert_eq!(easing.apply(0.), 0.);
+            assert_eq!(easing.apply(1.), 1.);
+            assert_eq!(easing.apply(2.), 1.);
+        });
+    }
+}
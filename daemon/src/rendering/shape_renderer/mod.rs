@@ -9,6 +9,7 @@ pub struct ShapeRenderer {
     index_buffer: buffers::IndexBuffer,
     instance_buffer: buffers::InstanceBuffer<buffers::Instance>,
     projection_uniform: buffers::Projection,
+    prepared_instances: usize,
 }
 
 impl ShapeRenderer {
@@ -95,6 +96,7 @@ impl ShapeRenderer {
             vertex_buffer,
             index_buffer,
             projection_uniform,
+            prepared_instances: 0,
         }
     }
 
@@ -114,13 +116,15 @@ impl ShapeRenderer {
         queue: &wgpu::Queue,
         instances: &[buffers::Instance],
     ) {
+        self.prepared_instances = instances.len();
+
         if instances.is_empty() {
             return;
         }
 
         let needed_buffer_size = std::mem::size_of_val(instances);
 
-        if self.instance_buffer.size() < needed_buffer_size as u32 {
+        if self.instance_buffer.capacity() < needed_buffer_size as u64 {
             self.instance_buffer =
                 buffers::InstanceBuffer::with_size(device, needed_buffer_size as u64);
         }
@@ -129,6 +133,10 @@ impl ShapeRenderer {
     }
 
     pub fn render(&self, render_pass: &mut wgpu::RenderPass<'_>) {
+        if self.prepared_instances == 0 {
+            return;
+        }
+
         render_pass.set_pipeline(&self.render_pipeline);
         render_pass.set_bind_group(0, &self.projection_uniform.bind_group, &[]);
         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
@@ -137,7 +145,139 @@ impl ShapeRenderer {
         render_pass.draw_indexed(
             0..self.index_buffer.size(),
             0,
-            0..self.instance_buffer.size(),
+            0..self.prepared_instances as u32,
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ShapeRenderer;
+    use crate::{components::Layer, utils::buffers};
+
+    const SIZE: u32 = 4;
+    const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+    /// Rows of a texture copy are padded to this many bytes.
+    const ROW: u32 = 256;
+
+    fn quad(rect_color: [f32; 4], layer: Layer) -> buffers::Instance {
+        buffers::Instance {
+            rect_pos: [0., 0.],
+            rect_size: [SIZE as f32; 2],
+            rect_color,
+            border_radius: [0.; 4],
+            border_size: [0.; 4],
+            border_color: [0.; 4],
+            scale: 1.,
+            depth: layer.depth(),
+        }
+    }
+
+    /// Draws `instances` in order onto an offscreen target and returns its centre pixel, `None`
+    /// when there's no adapter to render with.
+    fn render(instances: &[buffers::Instance]) -> Option<[u8; 4]> {
+        futures_lite::future::block_on(async {
+            let instance = wgpu::Instance::default();
+            let adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions::default())
+                .await
+                .ok()?;
+            let (device, queue) = adapter.request_device(&Default::default()).await.ok()?;
+
+            let target = device.create_texture(&wgpu::TextureDescriptor {
+                label: None,
+                size: wgpu::Extent3d {
+                    width: SIZE,
+                    height: SIZE,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            });
+            let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+            let depth_buffer = buffers::DepthBuffer::new(&device, SIZE, SIZE);
+
+            let mut renderer = ShapeRenderer::new(&device, FORMAT);
+            renderer.resize(&queue, SIZE as f32, SIZE as f32);
+            renderer.prepare(&device, &queue, instances);
+
+            let mut encoder = device.create_command_encoder(&Default::default());
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: depth_buffer.view(),
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            renderer.render(&mut render_pass);
+            drop(render_pass);
+
+            let readback = device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: u64::from(ROW * SIZE),
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            });
+            encoder.copy_texture_to_buffer(
+                target.as_image_copy(),
+                wgpu::TexelCopyBufferInfo {
+                    buffer: &readback,
+                    layout: wgpu::TexelCopyBufferLayout {
+                        offset: 0,
+                        bytes_per_row: Some(ROW),
+                        rows_per_image: None,
+                    },
+                },
+                target.size(),
+            );
+            queue.submit(Some(encoder.finish()));
+
+            let slice = readback.slice(..);
+            slice.map_async(wgpu::MapMode::Read, |_| {});
+            device.poll(wgpu::PollType::Wait).ok()?;
+
+            let offset = (SIZE / 2 * ROW + SIZE / 2 * 4) as usize;
+            let pixel = slice.get_mapped_range()[offset..offset + 4].try_into().ok();
+            pixel
+        })
+    }
+
+    #[test]
+    fn test_layer_depths() {
+        assert!(Layer::Background.depth() > Layer::HintText.depth());
+        assert!(Layer::HintText.depth() > 0.);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn test_layer_order() {
+        let red = [1., 0., 0., 1.];
+        let blue = [0., 0., 1., 1.];
+
+        // A hint drawn before a background it overlaps stays in front of it
+        let pixel = render(&[quad(red, Layer::Hint), quad(blue, Layer::Background)]);
+        assert_eq!(pixel, Some([255, 0, 0, 255]));
+
+        // Transparent parts of a shape don't hide what's drawn behind them afterwards
+        let pixel = render(&[quad([0.; 4], Layer::Focus), quad(blue, Layer::Element)]);
+        assert_eq!(pixel, Some([0, 0, 255, 255]));
+    }
+}
@@ -100,8 +100,14 @@ fn fs_main(in: VertexOutput) -> FragmentOutput {
     let inner_color = in.rect_color * inner_alpha;
     let border_color = in.border_color * border_alpha;
 
+    let color = inner_color + border_color;
+    // Fully transparent parts would still write depth and hide whatever is drawn behind later
+    if color.a <= 0.0 {
+        discard;
+    }
+
     var out: FragmentOutput;
-    out.color = inner_color + border_color;
+    out.color = color;
     out.depth = in.clip_position.z / in.clip_position.w;
     return out;
 }
@@ -1,25 +1,48 @@
 pub mod wgpu_surface;
 
 use crate::{
-    config::{self, Anchor, Config},
+    config::{self, Anchor, Config, ExclusiveZone, SurfacePlacement},
     manager::NotificationManager,
+    rendering::texture_renderer::TextureArea,
     utils::buffers,
     wgpu_state, Moxnotify, Output,
 };
-use glyphon::FontSystem;
+use anyhow::Context;
+use calloop::timer::{TimeoutAction, Timer};
+use glyphon::{FontSystem, TextArea};
 use std::{
     cell::RefCell,
     fmt,
+    hash::{DefaultHasher, Hash, Hasher},
+    mem::ManuallyDrop,
+    path::Path,
     rc::Rc,
     sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+use wayland_client::{
+    delegate_noop,
+    protocol::{wl_callback, wl_surface},
+    Connection, Dispatch, Proxy, QueueHandle,
+};
+use wayland_protocols::{
+    wp::{
+        fractional_scale::v1::client::{wp_fractional_scale_manager_v1, wp_fractional_scale_v1},
+        viewporter::client::{wp_viewport, wp_viewporter},
+    },
+    xdg::foreign::zv2::client::zxdg_exporter_v2,
 };
-use wayland_client::{delegate_noop, protocol::wl_surface, Connection, Dispatch, QueueHandle};
-use wayland_protocols::xdg::foreign::zv2::client::zxdg_exporter_v2;
 use wayland_protocols_wlr::layer_shell::v1::client::{
     zwlr_layer_shell_v1,
     zwlr_layer_surface_v1::{self, KeyboardInteractivity},
 };
 
+/// How often relative timestamps are brought up to date while notifications are shown.
+const TIMESTAMP_REFRESH: Duration = Duration::from_secs(30);
+/// How long surfaces stay on a higher layer than needed before they're moved down, so that
+/// critical notifications coming and going don't replace them every time.
+const LAYER_DEBOUNCE: Duration = Duration::from_secs(2);
+
 #[derive(PartialEq, Debug)]
 pub enum FocusReason {
     Ctl,
@@ -37,34 +60,63 @@ impl fmt::Display for FocusReason {
 }
 
 pub struct Surface {
-    pub wgpu_surface: wgpu_surface::WgpuSurface,
+    /// Dropped by hand so the swapchain goes away before the wl_surface it presents to.
+    pub wgpu_surface: ManuallyDrop<wgpu_surface::WgpuSurface>,
+    /// Waited on before teardown, frames still in flight hold on to the swapchain.
+    device: wgpu::Device,
     pub wl_surface: wl_surface::WlSurface,
     pub layer_surface: zwlr_layer_surface_v1::ZwlrLayerSurfaceV1,
+    fractional_scale: Option<wp_fractional_scale_v1::WpFractionalScaleV1>,
+    viewport: Option<wp_viewport::WpViewport>,
     pub scale: f32,
+    /// Size of the surface in surface-local coordinates, as last configured by the compositor.
+    logical_size: (u32, u32),
+    /// Size last asked of the compositor, what dimensions it leaves up to the surface are.
+    requested_size: (u32, u32),
     configured: bool,
     pub token: Option<Arc<str>>,
     pub focus_reason: Option<FocusReason>,
+    /// Registry name of the output the surface was placed on, if one was requested.
+    pub output: Option<u32>,
+    /// Placement the surface is anchored with.
+    placement: SurfacePlacement,
+    /// Layer the surface was created on, it can't move to another one.
+    layer: config::Layer,
+    /// Whether the surface sits along an edge, so that it can reserve space there.
+    reserves: bool,
+    /// Exclusive zone last sent to the compositor.
+    exclusive_zone: i32,
     font_system: Rc<RefCell<FontSystem>>,
+    qh: QueueHandle<Moxnotify>,
+    /// Whether a frame callback is outstanding, redraws wait for it instead of drawing right away.
+    frame_pending: bool,
+    /// Whether something changed while a frame was pending and has to be drawn on the next one.
+    damaged: bool,
+    /// Hash of what was last presented, identical frames aren't submitted again.
+    last_frame: Option<u64>,
 }
 
 impl Surface {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         wgpu_state: &wgpu_state::WgpuState,
         wl_surface: wl_surface::WlSurface,
         layer_shell: &zwlr_layer_shell_v1::ZwlrLayerShellV1,
+        fractional_scale_manager: Option<
+            &wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1,
+        >,
+        viewporter: Option<&wp_viewporter::WpViewporter>,
         qh: &QueueHandle<Moxnotify>,
-        outputs: &[Output],
+        output: Option<&Output>,
         config: &Config,
+        placement: SurfacePlacement,
+        layer: config::Layer,
         font_system: Rc<RefCell<FontSystem>>,
     ) -> anyhow::Result<Self> {
-        let output = outputs
-            .iter()
-            .find(|output| output.name.as_ref() == config.general.output.as_ref());
-
         let layer_surface = layer_shell.get_layer_surface(
             &wl_surface,
             output.map(|o| &o.wl_output),
-            match config.general.layer {
+            match layer {
                 config::Layer::Top => zwlr_layer_shell_v1::Layer::Top,
                 config::Layer::Background => zwlr_layer_shell_v1::Layer::Background,
                 config::Layer::Bottom => zwlr_layer_shell_v1::Layer::Bottom,
@@ -77,39 +129,21 @@ impl Surface {
 
         let scale = output.map(|o| o.scale).unwrap_or(1.0);
 
-        layer_surface.set_keyboard_interactivity(KeyboardInteractivity::None);
-        layer_surface
-            .set_anchor(zwlr_layer_surface_v1::Anchor::Right | zwlr_layer_surface_v1::Anchor::Top);
-        layer_surface.set_anchor(match config.general.anchor {
-            Anchor::TopRight => {
-                zwlr_layer_surface_v1::Anchor::Top | zwlr_layer_surface_v1::Anchor::Right
-            }
-            Anchor::TopCenter => zwlr_layer_surface_v1::Anchor::Top,
-            Anchor::TopLeft => {
-                zwlr_layer_surface_v1::Anchor::Top | zwlr_layer_surface_v1::Anchor::Left
-            }
-            Anchor::BottomRight => {
-                zwlr_layer_surface_v1::Anchor::Bottom | zwlr_layer_surface_v1::Anchor::Right
-            }
-            Anchor::BottomCenter => zwlr_layer_surface_v1::Anchor::Bottom,
-            Anchor::BottomLeft => {
-                zwlr_layer_surface_v1::Anchor::Bottom | zwlr_layer_surface_v1::Anchor::Left
+        // Fractional scaling needs both protocols, the buffer is sized in physical pixels
+        // and the viewport maps it back onto the logical surface size.
+        let (fractional_scale, viewport) = match (fractional_scale_manager, viewporter) {
+            (Some(manager), Some(viewporter)) => (
+                Some(manager.get_fractional_scale(&wl_surface, qh, ())),
+                Some(viewporter.get_viewport(&wl_surface, qh, ())),
+            ),
+            _ => {
+                wl_surface.set_buffer_scale(scale.ceil() as i32);
+                (None, None)
             }
-            Anchor::CenterRight => zwlr_layer_surface_v1::Anchor::Right,
-            Anchor::Center => {
-                zwlr_layer_surface_v1::Anchor::Top
-                    | zwlr_layer_surface_v1::Anchor::Bottom
-                    | zwlr_layer_surface_v1::Anchor::Left
-                    | zwlr_layer_surface_v1::Anchor::Right
-            }
-            Anchor::CenterLeft => zwlr_layer_surface_v1::Anchor::Left,
-        });
-        layer_surface.set_margin(
-            config.general.margin.top.resolve(0.) as i32,
-            config.general.margin.right.resolve(0.) as i32,
-            config.general.margin.bottom.resolve(0.) as i32,
-            config.general.margin.left.resolve(0.) as i32,
-        );
+        };
+
+        layer_surface.set_keyboard_interactivity(KeyboardInteractivity::None);
+        let reserves = place(&layer_surface, config, placement);
         layer_surface.set_exclusive_zone(-1);
 
         log::debug!("New surface created");
@@ -119,13 +153,67 @@ impl Surface {
             token: None,
             configured: false,
             scale,
-            wgpu_surface: wgpu_surface::WgpuSurface::new(wgpu_state, &wl_surface, config)?,
+            logical_size: (0, 0),
+            requested_size: (0, 0),
+            output: output.map(|o| o.id),
+            placement,
+            layer,
+            reserves,
+            exclusive_zone: -1,
+            fractional_scale,
+            viewport,
+            wgpu_surface: ManuallyDrop::new(wgpu_surface::WgpuSurface::new(
+                wgpu_state,
+                &wl_surface,
+                config,
+            )?),
+            device: wgpu_state.device.clone(),
             wl_surface,
             layer_surface,
             font_system,
+            qh: qh.clone(),
+            frame_pending: false,
+            damaged: false,
+            last_frame: None,
         })
     }
 
+    /// Moves the surface to where `placement` puts it, the compositor picks it up with the
+    /// next commit.
+    pub fn set_placement(&mut self, config: &Config, placement: SurfacePlacement) {
+        if self.placement == placement {
+            return;
+        }
+
+        self.reserves = place(&self.layer_surface, config, placement);
+        self.placement = placement;
+    }
+
+    /// Places the surface again after its margins changed along with the width of the output.
+    pub fn replace(&mut self, config: &Config) {
+        self.reserves = place(&self.layer_surface, config, self.placement);
+    }
+
+    /// Reserves space for notifications taking up `width` by `height` as configured, it's only
+    /// sent when it changed so that the compositor doesn't rearrange windows on every frame.
+    pub fn set_exclusive_zone(&mut self, config: &Config, width: u32, height: u32) {
+        let (anchor, _) = config.general.placement(self.placement);
+        let zone = match config.general.exclusive_zone {
+            _ if !self.reserves => -1,
+            ExclusiveZone::None => -1,
+            ExclusiveZone::Auto => match anchor {
+                Anchor::CenterLeft | Anchor::CenterRight => width as i32,
+                _ => height as i32,
+            },
+            ExclusiveZone::Value(zone) => zone as i32,
+        };
+
+        if zone != self.exclusive_zone {
+            self.layer_surface.set_exclusive_zone(zone);
+            self.exclusive_zone = zone;
+        }
+    }
+
     pub fn render(
         &mut self,
         device: &wgpu::Device,
@@ -136,22 +224,85 @@ impl Surface {
             return Ok(());
         }
 
+        // Notification data is shared between surfaces, so it has to be generated at the scale
+        // of the surface that's currently being drawn.
+        notifications
+            .ui_state
+            .scale
+            .store(self.scale, Ordering::Relaxed);
+
+        let (instances, text_data, textures) = notifications.data();
+        self.damaged = false;
+
+        // Nothing to draw, the surface is about to be torn down
+        if instances.is_empty() && text_data.is_empty() && textures.is_empty() {
+            return Ok(());
+        }
+
+        let hash = self.frame_hash(&instances, &text_data, &textures);
+        if self.last_frame == Some(hash) {
+            return Ok(());
+        }
+
         log::debug!("render()");
 
-        let surface_texture = self
-            .wgpu_surface
-            .surface
-            .get_current_texture()
-            .expect("failed to acquire next swapchain texture");
+        let surface_texture = match self.wgpu_surface.surface.get_current_texture() {
+            Ok(surface_texture) => surface_texture,
+            Err(wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Lost) => {
+                log::debug!("Swapchain out of date, skipping frame");
+                self.wgpu_surface
+                    .surface
+                    .configure(device, &self.wgpu_surface.config);
+                self.damaged = true;
+                return Ok(());
+            }
+            Err(wgpu::SurfaceError::Timeout) => {
+                self.damaged = true;
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        };
         let texture_view = surface_texture
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
 
         let mut encoder = device.create_command_encoder(&Default::default());
+        self.draw(
+            device,
+            queue,
+            &mut encoder,
+            &texture_view,
+            (instances, text_data, textures),
+        )?;
+
+        queue.submit(Some(encoder.finish()));
+
+        // Frame requests are committed together with the buffer on present, anything changing
+        // before the compositor is ready for another frame gets drawn once it is.
+        if !self.frame_pending {
+            self.wl_surface.frame(&self.qh, self.wl_surface.clone());
+            self.frame_pending = true;
+        }
+
+        surface_texture.present();
+        self.last_frame = Some(hash);
+
+        Ok(())
+    }
+
+    /// Records drawing `data` onto `view`, which has to be the size and format of the surface.
+    fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        (instances, text_data, textures): (Vec<buffers::Instance>, Vec<TextArea>, Vec<TextureArea>),
+    ) -> anyhow::Result<()> {
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Render pass"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &texture_view,
+                view,
                 resolve_target: None,
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
@@ -170,8 +321,6 @@ impl Surface {
             occlusion_query_set: None,
         });
 
-        let (instances, text_data, textures) = notifications.data();
-
         self.wgpu_surface
             .shape_renderer
             .prepare(device, queue, &instances);
@@ -189,22 +338,167 @@ impl Surface {
         self.wgpu_surface.texture_renderer.render(&mut render_pass);
         self.wgpu_surface.text_ctx.render(&mut render_pass)?;
 
-        drop(render_pass); // Drop renderpass and release mutable borrow on encoder
+        Ok(())
+    }
+
+    /// Draws what the surface shows onto an offscreen texture and reads it back as RGBA rows,
+    /// along with its width and height in physical pixels. Nothing is presented.
+    pub fn screenshot(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        notifications: &NotificationManager,
+    ) -> anyhow::Result<(Vec<u8>, u32, u32)> {
+        let wgpu::SurfaceConfiguration {
+            format,
+            width,
+            height,
+            ..
+        } = self.wgpu_surface.config;
+        let bgra = match format {
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            format => anyhow::bail!("Can't read back surfaces in {format:?}"),
+        };
+        if !self.configured {
+            anyhow::bail!("Surface isn't configured yet");
+        }
+
+        notifications
+            .ui_state
+            .scale
+            .store(self.scale, Ordering::Relaxed);
 
+        let target = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Screenshot"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder = device.create_command_encoder(&Default::default());
+        self.draw(device, queue, &mut encoder, &view, notifications.data())?;
+
+        let row = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Screenshot readback"),
+            size: u64::from(row * height),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            target.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &readback,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(row),
+                    rows_per_image: None,
+                },
+            },
+            target.size(),
+        );
         queue.submit(Some(encoder.finish()));
-        surface_texture.present();
 
-        Ok(())
+        let slice = readback.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| _ = sender.send(result));
+        device.poll(wgpu::PollType::Wait)?;
+        receiver.recv()??;
+
+        let pixels = unpad(&slice.get_mapped_range(), width, row, bgra);
+        Ok((pixels, width, height))
+    }
+
+    fn frame_hash(
+        &self,
+        instances: &[buffers::Instance],
+        text_areas: &[TextArea],
+        textures: &[TextureArea],
+    ) -> u64 {
+        let mut hasher = DefaultHasher::new();
+
+        self.scale.to_bits().hash(&mut hasher);
+        self.wgpu_surface.config.width.hash(&mut hasher);
+        self.wgpu_surface.config.height.hash(&mut hasher);
+
+        bytemuck::cast_slice::<_, u8>(instances).hash(&mut hasher);
+
+        text_areas.iter().for_each(|area| {
+            [area.left, area.top, area.scale]
+                .map(f32::to_bits)
+                .hash(&mut hasher);
+            let bounds = &area.bounds;
+            [bounds.left, bounds.top, bounds.right, bounds.bottom].hash(&mut hasher);
+            area.default_color.hash(&mut hasher);
+            area.buffer.layout_runs().for_each(|run| {
+                run.line_y.to_bits().hash(&mut hasher);
+                run.glyphs.iter().for_each(|glyph| {
+                    glyph.glyph_id.hash(&mut hasher);
+                    [glyph.x, glyph.y, glyph.w, glyph.font_size]
+                        .map(f32::to_bits)
+                        .hash(&mut hasher);
+                    glyph.color_opt.hash(&mut hasher);
+                    glyph.metadata.hash(&mut hasher);
+                });
+            });
+        });
+
+        textures.iter().for_each(|texture| {
+            [
+                texture.left,
+                texture.top,
+                texture.width,
+                texture.height,
+                texture.scale,
+                texture.depth,
+                texture.opacity,
+            ]
+            .map(f32::to_bits)
+            .hash(&mut hasher);
+            texture.radius.map(f32::to_bits).hash(&mut hasher);
+            texture.border_size.map(f32::to_bits).hash(&mut hasher);
+            let bounds = &texture.bounds;
+            [bounds.left, bounds.top, bounds.right, bounds.bottom].hash(&mut hasher);
+            texture.data.hash(&mut hasher);
+        });
+
+        hasher.finish()
     }
 
     pub fn resize(&mut self, queue: &wgpu::Queue, device: &wgpu::Device, width: u32, height: u32) {
-        if width == self.wgpu_surface.config.height
-            || height == self.wgpu_surface.config.width
-            || width == 0
-            || height == 0
-        {
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        self.logical_size = (width, height);
+        if let Some(viewport) = self.viewport.as_ref() {
+            viewport.set_destination(width as i32, height as i32);
+        }
+
+        let (width, height) = if self.viewport.is_some() {
+            (
+                (width as f32 * self.scale).round() as u32,
+                (height as f32 * self.scale).round() as u32,
+            )
+        } else {
+            let scale = self.scale.ceil() as u32;
+            (width * scale, height * scale)
+        };
+
+        if width == self.wgpu_surface.config.width && height == self.wgpu_surface.config.height {
             return;
         }
+
         self.wgpu_surface.depth_buffer = buffers::DepthBuffer::new(device, width, height);
         self.wgpu_surface.config.width = width;
         self.wgpu_surface.config.height = height;
@@ -223,6 +517,35 @@ impl Surface {
             .resize(queue, width as f32, height as f32);
     }
 
+    /// Picks up the new scale of the output the surface is on and configures the wgpu surface
+    /// again, a rotated or resized output can leave the swapchain stale.
+    fn output_changed(&mut self, queue: &wgpu::Queue, device: &wgpu::Device, output: &Output) {
+        // With fractional scaling the compositor sends the preferred scale itself
+        if self.fractional_scale.is_none() {
+            self.wl_surface.set_buffer_scale(output.scale.ceil() as i32);
+            self.set_scale(queue, device, output.scale);
+        }
+
+        if self.wgpu_surface.config.width > 0 && self.wgpu_surface.config.height > 0 {
+            self.wgpu_surface
+                .surface
+                .configure(device, &self.wgpu_surface.config);
+        }
+        self.last_frame = None;
+    }
+
+    pub fn set_scale(&mut self, queue: &wgpu::Queue, device: &wgpu::Device, scale: f32) {
+        if self.scale == scale {
+            return;
+        }
+
+        log::debug!("Surface scale changed from {} to {scale}", self.scale);
+
+        self.scale = scale;
+        let (width, height) = self.logical_size;
+        self.resize(queue, device, width, height);
+    }
+
     pub fn focus(&mut self, focus_reason: FocusReason) {
         if self.focus_reason.is_some() {
             return;
@@ -252,8 +575,121 @@ impl Surface {
     }
 }
 
+/// Anchors the layer surface where `placement` puts it, see [`config::General::placement`].
+/// Returns whether it's placed along an edge it can reserve space on.
+fn place(
+    layer_surface: &zwlr_layer_surface_v1::ZwlrLayerSurfaceV1,
+    config: &Config,
+    placement: SurfacePlacement,
+) -> bool {
+    let (anchor, margin) = config.general.placement(placement);
+    layer_surface.set_anchor(match anchor {
+        Anchor::TopRight => {
+            zwlr_layer_surface_v1::Anchor::Top | zwlr_layer_surface_v1::Anchor::Right
+        }
+        Anchor::TopCenter => zwlr_layer_surface_v1::Anchor::Top,
+        Anchor::TopLeft => zwlr_layer_surface_v1::Anchor::Top | zwlr_layer_surface_v1::Anchor::Left,
+        Anchor::BottomRight => {
+            zwlr_layer_surface_v1::Anchor::Bottom | zwlr_layer_surface_v1::Anchor::Right
+        }
+        Anchor::BottomCenter => zwlr_layer_surface_v1::Anchor::Bottom,
+        Anchor::BottomLeft => {
+            zwlr_layer_surface_v1::Anchor::Bottom | zwlr_layer_surface_v1::Anchor::Left
+        }
+        Anchor::CenterRight => zwlr_layer_surface_v1::Anchor::Right,
+        Anchor::Center => {
+            zwlr_layer_surface_v1::Anchor::Top
+                | zwlr_layer_surface_v1::Anchor::Bottom
+                | zwlr_layer_surface_v1::Anchor::Left
+                | zwlr_layer_surface_v1::Anchor::Right
+        }
+        Anchor::CenterLeft => zwlr_layer_surface_v1::Anchor::Left,
+    });
+    layer_surface.set_margin(
+        margin.top.resolve(0.) as i32,
+        margin.right.resolve(0.) as i32,
+        margin.bottom.resolve(0.) as i32,
+        margin.left.resolve(0.) as i32,
+    );
+
+    if config.general.exclusive_zone == ExclusiveZone::None {
+        return false;
+    }
+
+    // Corners touch two edges, since version 5 the one notifications stack from can be picked
+    let corners = layer_surface.version() >= 5;
+    let edge = match anchor {
+        Anchor::TopCenter | Anchor::BottomCenter | Anchor::CenterLeft | Anchor::CenterRight => {
+            Some(zwlr_layer_surface_v1::Anchor::empty())
+        }
+        Anchor::TopLeft | Anchor::TopRight if corners => Some(zwlr_layer_surface_v1::Anchor::Top),
+        Anchor::BottomLeft | Anchor::BottomRight if corners => {
+            Some(zwlr_layer_surface_v1::Anchor::Bottom)
+        }
+        _ => None,
+    };
+
+    if corners {
+        layer_surface.set_exclusive_edge(edge.unwrap_or(zwlr_layer_surface_v1::Anchor::empty()));
+    }
+    if edge.is_none() {
+        log::warn!(
+            "Exclusive zone has no effect with anchor {anchor:?}, notifications have to sit along a single edge"
+        );
+    }
+
+    edge.is_some()
+}
+
+/// Strips the padding off rows `row` bytes apart and swaps BGRA pixels to RGBA.
+fn unpad(data: &[u8], width: u32, row: u32, bgra: bool) -> Vec<u8> {
+    let mut pixels: Vec<u8> = data
+        .chunks(row as usize)
+        .flat_map(|row| &row[..width as usize * 4])
+        .copied()
+        .collect();
+    if bgra {
+        pixels
+            .chunks_exact_mut(4)
+            .for_each(|pixel| pixel.swap(0, 2));
+    }
+
+    pixels
+}
+
+/// Drops `swapchain` once `wait` returns, frames still in flight hold on to it. Errors from
+/// `wait` are only logged, the swapchain has to go before the wl_surface it presents to anyway.
+///
+/// # Safety
+///
+/// `swapchain` must not be used or dropped again afterwards.
+unsafe fn release_swapchain<S, E: fmt::Display>(
+    swapchain: &mut ManuallyDrop<S>,
+    wait: impl FnOnce() -> Result<(), E>,
+) {
+    if let Err(e) = wait() {
+        log::warn!("Failed to wait for in-flight frames: {e}");
+    }
+    unsafe { ManuallyDrop::drop(swapchain) };
+}
+
 impl Drop for Surface {
     fn drop(&mut self) {
+        let device = &self.device;
+        // SAFETY: the field is dropped only here and never used afterwards
+        unsafe {
+            release_swapchain(&mut self.wgpu_surface, || {
+                device.poll(wgpu::PollType::Wait).map(|_| ())
+            })
+        };
+
+        if let Some(fractional_scale) = self.fractional_scale.as_ref() {
+            fractional_scale.destroy();
+        }
+        if let Some(viewport) = self.viewport.as_ref() {
+            viewport.destroy();
+        }
+        // The compositor gives the exclusive zone back along with the layer surface
         self.layer_surface.destroy();
         self.wl_surface.destroy();
         log::debug!("Surface destroyed");
@@ -263,7 +699,7 @@ impl Drop for Surface {
 impl Dispatch<zwlr_layer_surface_v1::ZwlrLayerSurfaceV1, ()> for Moxnotify {
     fn event(
         state: &mut Self,
-        _: &zwlr_layer_surface_v1::ZwlrLayerSurfaceV1,
+        layer_surface: &zwlr_layer_surface_v1::ZwlrLayerSurfaceV1,
         event: <zwlr_layer_surface_v1::ZwlrLayerSurfaceV1 as wayland_client::Proxy>::Event,
         _: &(),
         _: &Connection,
@@ -275,22 +711,35 @@ impl Dispatch<zwlr_layer_surface_v1::ZwlrLayerSurfaceV1, ()> for Moxnotify {
             height,
         } = event
         {
-            if let Some(surface) = state.surface.as_ref() {
-                let token = state.seat.xdg_activation.get_activation_token(qh, ());
-                token.set_serial(serial, &state.seat.wl_seat);
-                token.set_surface(&surface.wl_surface);
-                token.commit();
-            }
+            if let Some(surface) = state
+                .surfaces
+                .iter_mut()
+                .find(|surface| surface.layer_surface == *layer_surface)
+            {
+                let output_size = output_size(&state.outputs, surface.output);
+                if let Some(wl_seat) = state.seats.active() {
+                    let token = state
+                        .seats
+                        .xdg_activation
+                        .get_activation_token(qh, surface.wl_surface.clone());
+                    token.set_serial(serial, wl_seat);
+                    token.set_surface(&surface.wl_surface);
+                    token.commit();
+                }
 
-            if let Some(surface) = state.surface.as_mut() {
-                surface.resize(
-                    &state.wgpu_state.queue,
-                    &state.wgpu_state.device,
-                    width,
-                    height,
-                );
+                match configured_size((width, height), surface.requested_size, output_size) {
+                    Some((width, height)) => surface.resize(
+                        &state.wgpu_state.queue,
+                        &state.wgpu_state.device,
+                        width,
+                        height,
+                    ),
+                    None => log::warn!("Ignoring configure to {width}x{height}"),
+                }
                 surface.layer_surface.ack_configure(serial);
                 surface.configured = true;
+                // The configure has to be answered with a commit even if nothing changed
+                surface.last_frame = None;
                 _ = surface.render(
                     &state.wgpu_state.device,
                     &state.wgpu_state.queue,
@@ -302,52 +751,518 @@ impl Dispatch<zwlr_layer_surface_v1::ZwlrLayerSurfaceV1, ()> for Moxnotify {
     }
 }
 
+impl Dispatch<wp_fractional_scale_v1::WpFractionalScaleV1, ()> for Moxnotify {
+    fn event(
+        state: &mut Self,
+        fractional_scale: &wp_fractional_scale_v1::WpFractionalScaleV1,
+        event: <wp_fractional_scale_v1::WpFractionalScaleV1 as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let wp_fractional_scale_v1::Event::PreferredScale { scale } = event else {
+            return;
+        };
+
+        // The preferred scale is sent as a numerator over a denominator of 120
+        let scale = scale as f32 / 120.0;
+
+        if let Some(surface) = state
+            .surfaces
+            .iter_mut()
+            .find(|surface| surface.fractional_scale.as_ref() == Some(fractional_scale))
+        {
+            surface.set_scale(&state.wgpu_state.queue, &state.wgpu_state.device, scale);
+            _ = surface.render(
+                &state.wgpu_state.device,
+                &state.wgpu_state.queue,
+                &state.notifications,
+            );
+        }
+    }
+}
+
+impl Dispatch<wl_callback::WlCallback, wl_surface::WlSurface> for Moxnotify {
+    fn event(
+        state: &mut Self,
+        _: &wl_callback::WlCallback,
+        event: <wl_callback::WlCallback as wayland_client::Proxy>::Event,
+        wl_surface: &wl_surface::WlSurface,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let wl_callback::Event::Done { .. } = event else {
+            return;
+        };
+
+        let Some(surface) = state
+            .surfaces
+            .iter_mut()
+            .find(|surface| surface.wl_surface == *wl_surface)
+        else {
+            return;
+        };
+        surface.frame_pending = false;
+
+        if state.notifications.tick() {
+            state.update_surface_size();
+            if let Err(e) = state.render_surfaces() {
+                log::error!("Render error: {e}");
+            }
+
+            if state.notifications.notifications().is_empty() {
+                state
+                    .seats
+                    .keyboards_mut()
+                    .for_each(|keyboard| keyboard.repeat.key = None);
+            }
+        } else if state.notifications.needs_redraw() {
+            if let Err(e) = state.render_surfaces() {
+                log::error!("Render error: {e}");
+            }
+        } else if surface.damaged {
+            if let Err(e) = surface.render(
+                &state.wgpu_state.device,
+                &state.wgpu_state.queue,
+                &state.notifications,
+            ) {
+                log::error!("Render error: {e}");
+            }
+        }
+    }
+}
+
 delegate_noop!(Moxnotify: zxdg_exporter_v2::ZxdgExporterV2);
+delegate_noop!(Moxnotify: wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1);
+delegate_noop!(Moxnotify: wp_viewporter::WpViewporter);
+delegate_noop!(Moxnotify: wp_viewport::WpViewport);
 delegate_noop!(Moxnotify: ignore wl_surface::WlSurface);
 
+/// Either every output or just the configured one, None standing for whichever output the
+/// compositor picks when that one isn't there.
+fn target_outputs<'a>(config: &Config, outputs: &'a [Output]) -> Vec<Option<&'a Output>> {
+    match config.general.output.as_deref() {
+        Some("all") => outputs.iter().map(Some).collect(),
+        name => vec![outputs.iter().find(|output| output.name.as_deref() == name)],
+    }
+}
+
+/// Logical size of output `id`, None when it's unknown or hasn't announced its mode yet.
+fn output_size(outputs: &[Output], id: Option<u32>) -> Option<(u32, u32)> {
+    let output = outputs.iter().find(|output| Some(output.id) == id)?;
+    Some((
+        output.logical_width()? as u32,
+        output.logical_height()? as u32,
+    ))
+}
+
+/// Shrinks `size` to fit on an output of `output_size`, compositors ignore surfaces larger
+/// than their output.
+fn fit_output((width, height): (u32, u32), output_size: Option<(u32, u32)>) -> (u32, u32) {
+    match output_size {
+        Some((max_width, max_height)) => (width.min(max_width), height.min(max_height)),
+        None => (width, height),
+    }
+}
+
+/// Size a configure event has the surface drawn at. Dimensions the compositor sends as 0 are
+/// left for the surface to pick, so they're the size it `requested`. Returns None when there's
+/// still nothing to draw.
+fn configured_size(
+    (width, height): (u32, u32),
+    (requested_width, requested_height): (u32, u32),
+    output_size: Option<(u32, u32)>,
+) -> Option<(u32, u32)> {
+    let width = if width == 0 { requested_width } else { width };
+    let height = if height == 0 {
+        requested_height
+    } else {
+        height
+    };
+    let (width, height) = fit_output((width, height), output_size);
+
+    (width > 0 && height > 0).then_some((width, height))
+}
+
 impl Moxnotify {
     pub fn update_surface_size(&mut self) {
+        // The shortest output notifications are on decides how many fit
+        let output_height = target_outputs(&self.config, &self.outputs)
+            .into_iter()
+            .filter_map(|output| output?.logical_height())
+            .min_by(f32::total_cmp);
+        self.notifications.set_output_height(output_height);
+
+        // Sizes given in percent are of the narrowest one
+        let output_width = target_outputs(&self.config, &self.outputs)
+            .into_iter()
+            .filter_map(|output| output?.logical_width())
+            .min_by(f32::total_cmp);
+        if self.notifications.set_output_width(output_width) {
+            self.config = self.notifications.config();
+            self.surfaces
+                .iter_mut()
+                .for_each(|surface| surface.replace(&self.config));
+        }
+
         let total_height = self.notifications.height();
         let total_width = self.notifications.width();
 
-        if self.surface.is_none() {
+        if total_width == 0. || total_height == 0. {
+            self.surfaces.clear();
+            self.seats
+                .keyboards_mut()
+                .for_each(|keyboard| keyboard.key_combination.clear());
+            if let Some(token) = self.timestamp_registration_token.take() {
+                self.loop_handle.remove(token);
+            }
+            if let Some(token) = self.layer_registration_token.take() {
+                self.loop_handle.remove(token);
+            }
+            return;
+        }
+
+        if self.timestamp_registration_token.is_none() {
+            let timer = Timer::from_duration(TIMESTAMP_REFRESH);
+            self.timestamp_registration_token = self
+                .loop_handle
+                .insert_source(timer, |_, _, moxnotify| {
+                    if moxnotify.notifications.refresh_timestamps() {
+                        moxnotify.update_surface_size();
+                        if let Err(e) = moxnotify.render_surfaces() {
+                            log::error!("Render error: {e}");
+                        }
+                    }
+                    TimeoutAction::ToDuration(TIMESTAMP_REFRESH)
+                })
+                .ok();
+        }
+
+        let layer = self.follow_layer();
+        let targets = target_outputs(&self.config, &self.outputs);
+
+        // A fallback surface goes away once the configured output shows up, and the other way
+        // around when it disappears
+        self.surfaces.retain(|surface| {
+            targets
+                .iter()
+                .any(|output| output.map(|o| o.id) == surface.output)
+        });
+
+        let placement = self.notifications.placement();
+        for output in targets {
+            let id = output.map(|o| o.id);
+            if self.surfaces.iter().any(|surface| surface.output == id) {
+                continue;
+            }
+
             let wl_surface = self.compositor.create_surface(&self.qh, ());
-            self.surface = Surface::new(
+            match Surface::new(
                 &self.wgpu_state,
                 wl_surface,
                 &self.layer_shell,
+                self.fractional_scale_manager.as_ref(),
+                self.viewporter.as_ref(),
                 &self.qh,
-                &self.outputs,
+                output,
                 &self.config,
+                placement,
+                layer,
                 Rc::clone(&self.font_system),
-            )
-            .ok();
+            ) {
+                Ok(surface) => self.surfaces.push(surface),
+                Err(e) => log::error!("Failed to create surface: {e}"),
+            }
+        }
 
-            let scale = self
-                .surface
-                .as_ref()
-                .map(|surface| surface.scale)
-                .unwrap_or(1.0);
+        self.surfaces.iter_mut().for_each(|surface| {
+            let (width, height) = fit_output(
+                (total_width as u32, total_height as u32),
+                output_size(&self.outputs, surface.output),
+            );
+            surface.set_placement(&self.config, placement);
+            surface.layer_surface.set_size(width, height);
+            surface.requested_size = (width, height);
+            surface.set_exclusive_zone(&self.config, width, height);
+            surface.wl_surface.commit();
+        });
+    }
 
-            self.notifications
-                .ui_state
-                .scale
-                .store(scale, Ordering::Relaxed);
+    /// Replaces the surfaces by ones on the layer the notifications on screen go on, right away
+    /// when it's higher and after `LAYER_DEBOUNCE` when it's lower. Returns the layer new
+    /// surfaces are created on.
+    fn follow_layer(&mut self) -> config::Layer {
+        let layer = self.notifications.layer();
+        let current = self.surfaces.first().map(|surface| surface.layer);
+        if current.is_none_or(|current| layer >= current) {
+            if let Some(token) = self.layer_registration_token.take() {
+                self.loop_handle.remove(token);
+            }
+            if current.is_some_and(|current| layer > current) {
+                log::info!("Raising notifications to the {layer:?} layer");
+                self.surfaces.clear();
+            }
+            return layer;
         }
 
-        if total_width == 0. || total_height == 0. {
-            if let Some(surface) = self.surface.take() {
-                drop(surface);
+        if self.layer_registration_token.is_none() {
+            let timer = Timer::from_duration(LAYER_DEBOUNCE);
+            self.layer_registration_token = self
+                .loop_handle
+                .insert_source(timer, |_, _, moxnotify| {
+                    moxnotify.layer_registration_token = None;
+                    let layer = moxnotify.notifications.layer();
+                    if moxnotify
+                        .surfaces
+                        .first()
+                        .is_some_and(|surface| surface.layer > layer)
+                    {
+                        log::info!("Lowering notifications to the {layer:?} layer");
+                        moxnotify.recreate_surfaces();
+                    }
+                    TimeoutAction::Drop
+                })
+                .ok();
+        }
+
+        current.unwrap_or(layer)
+    }
+
+    /// Refreshes surfaces on output `id` after its mode, scale or transform changed, it may also
+    /// be the configured output that just got its name.
+    pub fn output_changed(&mut self, id: u32) {
+        if let Some(output) = self.outputs.iter().find(|output| output.id == id) {
+            self.surfaces
+                .iter_mut()
+                .filter(|surface| surface.output == Some(id))
+                .for_each(|surface| {
+                    surface.output_changed(&self.wgpu_state.queue, &self.wgpu_state.device, output)
+                });
+        }
+
+        self.update_surface_size();
+        if let Err(e) = self.render_surfaces() {
+            log::error!("Render error: {e}");
+        }
+    }
+
+    /// Moves notifications to output `name`, or onto every output with `all`, for the rest of
+    /// the session. Fails listing the outputs there are if none is called that.
+    pub fn set_output(&mut self, name: &str) -> anyhow::Result<()> {
+        let names: Vec<&str> = self
+            .outputs
+            .iter()
+            .filter_map(|output| output.name.as_deref())
+            .collect();
+        if name != "all" && !names.contains(&name) {
+            anyhow::bail!("No output named {name}, there's {}", names.join(", "));
+        }
+
+        log::info!("Moving notifications to output {name}");
+        self.config = self
+            .notifications
+            .update_general(|general| general.output = Some(name.into()));
+        self.recreate_surfaces();
+
+        Ok(())
+    }
+
+    /// Anchors notifications to `anchor` whatever their urgency, for the rest of the session.
+    pub fn set_anchor(&mut self, anchor: Anchor) {
+        log::info!("Anchoring notifications to {anchor:?}");
+        self.config = self.notifications.update_general(|general| {
+            general.anchor = anchor;
+            let placement = &mut general.urgency_placement;
+            [
+                &mut placement.urgency_low,
+                &mut placement.urgency_normal,
+                &mut placement.urgency_critical,
+            ]
+            .into_iter()
+            .flatten()
+            .for_each(|placement| placement.anchor = None);
+        });
+        self.recreate_surfaces();
+    }
+
+    /// Replaces the surfaces with ones placed as the config says now. The old ones wait for
+    /// their frames in flight as they're dropped, notifications stay as they are.
+    fn recreate_surfaces(&mut self) {
+        self.surfaces.clear();
+        self.update_surface_size();
+        if let Err(e) = self.render_surfaces() {
+            log::error!("Render error: {e}");
+        }
+    }
+
+    /// Draws every surface that isn't waiting on a frame callback, the rest are marked as
+    /// damaged and drawn from their callback.
+    pub fn render_surfaces(&mut self) -> anyhow::Result<()> {
+        self.notifications.clear_redraw();
+        self.surfaces.iter_mut().try_for_each(|surface| {
+            if surface.frame_pending {
+                surface.damaged = true;
+                return Ok(());
             }
-            self.seat.keyboard.key_combination.clear();
-            return;
+
+            surface.render(
+                &self.wgpu_state.device,
+                &self.wgpu_state.queue,
+                &self.notifications,
+            )
+        })
+    }
+
+    /// Writes what the active surface shows to `path` as a PNG.
+    pub fn screenshot(&mut self, path: &Path) -> anyhow::Result<()> {
+        let index = self
+            .surfaces
+            .iter()
+            .position(|surface| surface.focus_reason.is_some())
+            .unwrap_or_default();
+        let surface = self
+            .surfaces
+            .get_mut(index)
+            .context("No notifications are shown")?;
+
+        let (pixels, width, height) = surface.screenshot(
+            &self.wgpu_state.device,
+            &self.wgpu_state.queue,
+            &self.notifications,
+        )?;
+        image::save_buffer_with_format(
+            path,
+            &pixels,
+            width,
+            height,
+            image::ExtendedColorType::Rgba8,
+            image::ImageFormat::Png,
+        )
+        .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Surface that currently has keyboard or pointer focus, or the first one if none does.
+    pub fn active_surface(&self) -> Option<&Surface> {
+        self.surfaces
+            .iter()
+            .find(|surface| surface.focus_reason.is_some())
+            .or_else(|| self.surfaces.first())
+    }
+
+    pub fn active_surface_mut(&mut self) -> Option<&mut Surface> {
+        let index = self
+            .surfaces
+            .iter()
+            .position(|surface| surface.focus_reason.is_some())
+            .unwrap_or_default();
+        self.surfaces.get_mut(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{configured_size, fit_output, release_swapchain, unpad};
+    use std::{cell::RefCell, mem::ManuallyDrop, rc::Rc};
+
+    type Log = Rc<RefCell<Vec<&'static str>>>;
+
+    struct Logged(&'static str, Log);
+
+    impl Drop for Logged {
+        fn drop(&mut self) {
+            self.1.borrow_mut().push(self.0);
         }
+    }
 
-        if let Some(surface) = self.surface.as_ref() {
-            surface
-                .layer_surface
-                .set_size(total_width as u32, total_height as u32);
-            surface.wl_surface.commit();
+    /// Torn down like `Surface`, with the swapchain released before its other fields drop.
+    struct Torn {
+        swapchain: ManuallyDrop<Logged>,
+        _wl_surface: Logged,
+        wait: Result<(), &'static str>,
+        log: Log,
+    }
+
+    impl Drop for Torn {
+        fn drop(&mut self) {
+            let (log, wait) = (Rc::clone(&self.log), self.wait);
+            unsafe {
+                release_swapchain(&mut self.swapchain, || {
+                    log.borrow_mut().push("wait");
+                    wait
+                })
+            };
         }
     }
+
+    fn teardown(wait: Result<(), &'static str>) -> Vec<&'static str> {
+        let log = Log::default();
+        drop(Torn {
+            swapchain: ManuallyDrop::new(Logged("swapchain", Rc::clone(&log))),
+            _wl_surface: Logged("wl_surface", Rc::clone(&log)),
+            wait,
+            log: Rc::clone(&log),
+        });
+
+        log.take()
+    }
+
+    #[test]
+    fn test_teardown_order() {
+        assert_eq!(teardown(Ok(())), ["wait", "swapchain", "wl_surface"]);
+        // A failed wait still lets go of the swapchain before the wl_surface
+        assert_eq!(teardown(Err("lost")), ["wait", "swapchain", "wl_surface"]);
+    }
+
+    #[test]
+    fn test_configured_size() {
+        let output = Some((1920, 1080));
+        assert_eq!(
+            configured_size((300, 200), (300, 200), output),
+            Some((300, 200))
+        );
+        // Square sizes are as good as any other
+        assert_eq!(
+            configured_size((250, 250), (300, 200), output),
+            Some((250, 250))
+        );
+        assert_eq!(
+            configured_size((0, 0), (300, 200), output),
+            Some((300, 200))
+        );
+        assert_eq!(
+            configured_size((0, 150), (300, 0), output),
+            Some((300, 150))
+        );
+        assert_eq!(
+            configured_size((100_000, 100_000), (300, 200), output),
+            Some((1920, 1080))
+        );
+        assert_eq!(
+            configured_size((100_000, 200), (300, 200), None),
+            Some((100_000, 200))
+        );
+        assert_eq!(configured_size((0, 0), (0, 0), output), None);
+    }
+
+    #[test]
+    fn test_fit_output() {
+        assert_eq!(fit_output((300, 4000), Some((1920, 1080))), (300, 1080));
+        assert_eq!(fit_output((300, 4000), None), (300, 4000));
+    }
+
+    #[test]
+    fn test_unpad() {
+        // Two BGRA pixels per row, padded to 12 bytes
+        let data = [
+            [1, 2, 3, 4, 5, 6, 7, 8, 0, 0, 0, 0],
+            [9, 10, 11, 12, 13, 14, 15, 16, 0, 0, 0, 0],
+        ]
+        .concat();
+
+        assert_eq!(
+            unpad(&data, 2, 12, true),
+            [3, 2, 1, 4, 7, 6, 5, 8, 11, 10, 9, 12, 15, 14, 13, 16]
+        );
+        assert_eq!(unpad(&data, 1, 12, false), [1, 2, 3, 4, 9, 10, 11, 12]);
+    }
 }
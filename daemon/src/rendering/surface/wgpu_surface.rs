@@ -39,24 +39,32 @@ impl WgpuSurface {
         };
 
         let surface_caps = wgpu_surface.get_capabilities(&wgpu_state.adapter);
+        // Adapters that can't present to Wayland surfaces report no capabilities at all
         let surface_format = surface_caps
             .formats
             .iter()
             .find(|f| f.is_srgb())
-            .unwrap_or(&surface_caps.formats[0]);
+            .or(surface_caps.formats.first())
+            .context("Adapter can't present to the surface")?;
 
         let alpha_mode = surface_caps
             .alpha_modes
             .iter()
             .find(|a| **a == wgpu::CompositeAlphaMode::PreMultiplied)
-            .unwrap_or(&surface_caps.alpha_modes[0]);
+            .or(surface_caps.alpha_modes.first())
+            .context("Adapter can't present to the surface")?;
+
+        let present_mode = *surface_caps
+            .present_modes
+            .first()
+            .context("Adapter can't present to the surface")?;
 
         let surface_config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: *surface_format,
             width: 1,
             height: 1,
-            present_mode: surface_caps.present_modes[0],
+            present_mode,
             alpha_mode: *alpha_mode,
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
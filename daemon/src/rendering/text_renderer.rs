@@ -1,4 +1,7 @@
-use crate::{components::Bounds, config::Font};
+use crate::{
+    components::{Bounds, Layer},
+    config::Font,
+};
 use glyphon::{
     Attrs, Buffer, Cache, FontSystem, Shaping, SwashCache, TextArea, TextAtlas, TextRenderer,
     Viewport, Weight,
@@ -25,11 +28,18 @@ pub struct Text {
 
 impl Text {
     pub fn new<T>(font: &Font, font_system: &mut FontSystem, body: T) -> Self
+    where
+        T: AsRef<str>,
+    {
+        Self::with_layer(font, font_system, body, Layer::Text)
+    }
+
+    pub fn with_layer<T>(font: &Font, font_system: &mut FontSystem, body: T, layer: Layer) -> Self
     where
         T: AsRef<str>,
     {
         let attrs = Attrs::new()
-            .metadata(0.6_f32.to_bits() as usize)
+            .metadata(layer.metadata())
             .family(glyphon::Family::Name(&font.family))
             .weight(Weight::BOLD);
         let mut buffer = create_buffer(font, font_system, None);
@@ -108,6 +118,19 @@ impl TextContext {
             return Ok(());
         }
 
+        // Text areas are laid out in logical pixels, the viewport is in physical ones
+        let text = text.into_iter().map(|mut area| {
+            area.left *= area.scale;
+            area.top *= area.scale;
+            area.bounds = glyphon::TextBounds {
+                left: (area.bounds.left as f32 * area.scale) as i32,
+                top: (area.bounds.top as f32 * area.scale) as i32,
+                right: (area.bounds.right as f32 * area.scale).ceil() as i32,
+                bottom: (area.bounds.bottom as f32 * area.scale).ceil() as i32,
+            };
+            area
+        });
+
         self.renderer.prepare_with_depth(
             device,
             queue,
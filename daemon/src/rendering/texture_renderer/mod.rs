@@ -2,18 +2,29 @@ use crate::utils::{
     buffers::{self, Buffer, DataDescription},
     math::{Mat4, Matrix},
 };
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
 
 pub struct TextureRenderer {
     render_pipeline: wgpu::RenderPipeline,
     texture: wgpu::Texture,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
     bind_group: wgpu::BindGroup,
     vertex_buffer: buffers::VertexBuffer,
     index_buffer: buffers::IndexBuffer,
     projection_uniform: buffers::Projection,
     instance_buffer: buffers::InstanceBuffer<buffers::TextureInstance>,
     height: f32,
-    max_icon_size: u32,
+    /// Width and height of every layer of the texture, grown when a wider or taller texture
+    /// comes along.
+    texture_size: (u32, u32),
     prepared_instances: usize,
+    /// Hash of what each layer of the texture holds, layers are only written when it changes,
+    /// so that an animated image only uploads its new frame.
+    layers: Vec<u64>,
 }
 
 #[derive(Clone)]
@@ -24,10 +35,14 @@ pub struct TextureArea<'a> {
     pub scale: f32,
     pub radius: [f32; 4],
     pub data: &'a [u8],
+    /// Width and height of `data` in pixels, anything past the largest texture the device
+    /// supports is cut off.
+    pub data_size: (u32, u32),
     pub width: f32,
     pub height: f32,
     pub border_size: [f32; 4],
     pub depth: f32,
+    pub opacity: f32,
 }
 
 #[derive(Clone)]
@@ -42,7 +57,7 @@ impl TextureRenderer {
     pub fn new(
         device: &wgpu::Device,
         texture_format: wgpu::TextureFormat,
-        max_icon_size: u32,
+        max_texture_size: u32,
     ) -> Self {
         let projection_uniform = buffers::Projection::new(device, 0.0, 0.0, 0.0, 0.0);
 
@@ -69,30 +84,6 @@ impl TextureRenderer {
                 label: Some("texture_bind_group_layout"),
             });
 
-        let texture_size = wgpu::Extent3d {
-            width: max_icon_size,
-            height: max_icon_size,
-            depth_or_array_layers: 256,
-        };
-
-        let texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("texture_renderer_texture"),
-            size: texture_size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            view_formats: &[],
-        });
-
-        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor {
-            dimension: Some(wgpu::TextureViewDimension::D2Array),
-            base_array_layer: 0,
-            array_layer_count: Some(256),
-            ..Default::default()
-        });
-
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             label: Some("texture_renderer_sampler"),
             address_mode_u: wgpu::AddressMode::ClampToEdge,
@@ -104,20 +95,9 @@ impl TextureRenderer {
             ..Default::default()
         });
 
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&texture_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
-                },
-            ],
-            label: Some("texture_bind_group"),
-        });
+        let texture_size = (max_texture_size, max_texture_size);
+        let (texture, bind_group) =
+            create_texture(device, &texture_bind_group_layout, &sampler, texture_size);
 
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -191,11 +171,14 @@ impl TextureRenderer {
 
         Self {
             prepared_instances: 0,
-            max_icon_size,
+            layers: Vec::new(),
+            texture_size,
             instance_buffer,
             projection_uniform,
             render_pipeline,
             texture,
+            bind_group_layout: texture_bind_group_layout,
+            sampler,
             index_buffer,
             vertex_buffer,
             bind_group,
@@ -229,25 +212,64 @@ impl TextureRenderer {
             return;
         }
 
+        let max_dimension = device.limits().max_texture_dimension_2d;
+        let needed = textures
+            .iter()
+            .fold(self.texture_size, |(width, height), texture| {
+                (
+                    width.max(texture.data_size.0.min(max_dimension)),
+                    height.max(texture.data_size.1.min(max_dimension)),
+                )
+            });
+        if needed != self.texture_size {
+            (self.texture, self.bind_group) =
+                create_texture(device, &self.bind_group_layout, &self.sampler, needed);
+            self.texture_size = needed;
+            self.layers.clear();
+        }
+
         let mut instances = Vec::new();
 
         textures.iter().enumerate().for_each(|(i, texture)| {
+            let copy_size = (
+                texture.data_size.0.min(self.texture_size.0),
+                texture.data_size.1.min(self.texture_size.1),
+            );
             instances.push(buffers::TextureInstance {
                 scale: texture.scale,
-                pos: [texture.left, self.height - texture.top - texture.height],
-                size: [texture.width, texture.height],
+                pos: [
+                    texture.left * texture.scale,
+                    self.height - (texture.top + texture.height) * texture.scale,
+                ],
+                size: [
+                    texture.width * copy_size.0 as f32 / texture.data_size.0.max(1) as f32,
+                    texture.height,
+                ],
                 radius: texture.radius,
                 border_width: texture.border_size,
                 container_rect: [
-                    texture.bounds.left as f32,
-                    self.height - texture.bounds.top as f32 - texture.height,
-                    texture.bounds.right as f32,
-                    self.height - texture.bounds.bottom as f32,
+                    texture.bounds.left as f32 * texture.scale,
+                    self.height - (texture.bounds.top as f32 + texture.height) * texture.scale,
+                    texture.bounds.right as f32 * texture.scale,
+                    self.height - texture.bounds.bottom as f32 * texture.scale,
                 ],
                 depth: texture.depth,
+                opacity: texture.opacity,
+                tex_size: [
+                    copy_size.0 as f32 / self.texture_size.0 as f32,
+                    copy_size.1 as f32 / self.texture_size.1 as f32,
+                ],
             });
 
-            let bytes_per_row = (4 * self.max_icon_size).div_ceil(256) * 256;
+            let mut hasher = DefaultHasher::new();
+            texture.data_size.hash(&mut hasher);
+            texture.data.hash(&mut hasher);
+            let hash = hasher.finish();
+            match self.layers.get_mut(i) {
+                Some(layer) if *layer == hash => return,
+                Some(layer) => *layer = hash,
+                None => self.layers.push(hash),
+            }
 
             queue.write_texture(
                 wgpu::TexelCopyTextureInfo {
@@ -263,12 +285,12 @@ impl TextureRenderer {
                 texture.data,
                 wgpu::TexelCopyBufferLayout {
                     offset: 0,
-                    bytes_per_row: Some(bytes_per_row),
+                    bytes_per_row: Some(4 * texture.data_size.0),
                     rows_per_image: None,
                 },
                 wgpu::Extent3d {
-                    width: self.max_icon_size,
-                    height: self.max_icon_size,
+                    width: copy_size.0,
+                    height: copy_size.1,
                     depth_or_array_layers: 1,
                 },
             );
@@ -277,7 +299,7 @@ impl TextureRenderer {
         let instance_buffer_size =
             std::mem::size_of::<buffers::TextureInstance>() * instances.len();
 
-        if self.instance_buffer.size() < instance_buffer_size as u32 {
+        if self.instance_buffer.capacity() < instance_buffer_size as u64 {
             self.instance_buffer =
                 buffers::InstanceBuffer::with_size(device, instance_buffer_size as u64);
         }
@@ -306,3 +328,50 @@ impl TextureRenderer {
         );
     }
 }
+
+/// Creates the texture array every texture is drawn from, 256 layers of `size`.
+fn create_texture(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    sampler: &wgpu::Sampler,
+    size: (u32, u32),
+) -> (wgpu::Texture, wgpu::BindGroup) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("texture_renderer_texture"),
+        size: wgpu::Extent3d {
+            width: size.0,
+            height: size.1,
+            depth_or_array_layers: 256,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+
+    let texture_view = texture.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(wgpu::TextureViewDimension::D2Array),
+        base_array_layer: 0,
+        array_layer_count: Some(256),
+        ..Default::default()
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&texture_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+        label: Some("texture_bind_group"),
+    });
+
+    (texture, bind_group)
+}
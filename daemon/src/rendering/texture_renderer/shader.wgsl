@@ -16,6 +16,8 @@ struct InstanceInput {
     @location(6) border_width: vec4<f32>,
     @location(7) scale: f32,
     @location(8) depth: f32,
+    @location(9) opacity: f32,
+    @location(10) tex_size: vec2<f32>,
 };
 
 struct VertexOutput {
@@ -28,6 +30,8 @@ struct VertexOutput {
     @location(5) surface_position: vec2<f32>,
     @location(6) border_width: vec4<f32>,
     @location(7) depth: f32,
+    @location(8) opacity: f32,
+    @location(9) tex_size: vec2<f32>,
 };
 
 @vertex
@@ -59,6 +63,8 @@ fn vs_main(
     out.surface_position = position;
     out.border_width = instance.border_width * instance.scale;
     out.depth = instance.depth;
+    out.opacity = instance.opacity;
+    out.tex_size = instance.tex_size;
 
     return out;
 }
@@ -86,7 +92,7 @@ fn fs_main(in: VertexOutput) -> FragmentOutput {
     let tex_color = textureSample(
         t_diffuse,
         s_diffuse,
-        vec2<f32>(in.tex_coords.x, 1.0 - in.tex_coords.y),
+        vec2<f32>(in.tex_coords.x, 1.0 - in.tex_coords.y) * in.tex_size,
         i32(in.layer)
     );
 
@@ -103,7 +109,10 @@ fn fs_main(in: VertexOutput) -> FragmentOutput {
     let color = mix(tex_color, vec4<f32>(0., 0., 0., 0.), border_alpha);
     let alpha = outer;
 
-    let final_alpha = color.a * alpha;
+    let final_alpha = color.a * alpha * in.opacity;
+    if final_alpha <= 0.0 {
+        discard;
+    }
 
     var out: FragmentOutput;
     out.color = vec4<f32>(color.rgb * final_alpha, final_alpha);
@@ -1,6 +1,10 @@
-use raw_window_handle::{RawDisplayHandle, WaylandDisplayHandle};
+use crate::config::{General, PowerPreference, Renderer};
+use anyhow::Context;
+use raw_window_handle::{
+    RawDisplayHandle, RawWindowHandle, WaylandDisplayHandle, WaylandWindowHandle,
+};
 use std::ptr::NonNull;
-use wayland_client::Connection;
+use wayland_client::{protocol::wl_surface, Connection, Proxy};
 
 pub struct WgpuState {
     pub instance: wgpu::Instance,
@@ -10,33 +14,126 @@ pub struct WgpuState {
     pub raw_display_handle: RawDisplayHandle,
 }
 
-impl WgpuState {
-    pub async fn new(conn: &Connection) -> anyhow::Result<Self> {
-        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::VULKAN,
-            ..Default::default()
-        });
+/// Backends tried in order, along with whether only an adapter running on the CPU will do.
+fn attempts(renderer: Renderer) -> Vec<(wgpu::Backends, bool)> {
+    let software = (wgpu::Backends::VULKAN | wgpu::Backends::GL, true);
+    match renderer {
+        Renderer::Auto => vec![
+            (wgpu::Backends::VULKAN, false),
+            (wgpu::Backends::GL, false),
+            software,
+        ],
+        Renderer::Vulkan => vec![(wgpu::Backends::VULKAN, false)],
+        Renderer::Gl => vec![(wgpu::Backends::GL, false)],
+        Renderer::Software => vec![software],
+    }
+}
 
+impl WgpuState {
+    /// Picks the first adapter of the configured renderer that can present to `probe`, a
+    /// surface like the ones notifications are drawn on.
+    pub async fn new(
+        conn: &Connection,
+        config: &General,
+        probe: &wl_surface::WlSurface,
+    ) -> anyhow::Result<Self> {
         let raw_display_handle = RawDisplayHandle::Wayland(WaylandDisplayHandle::new(
             NonNull::new(conn.backend().display_ptr() as *mut _).unwrap(),
         ));
+        let raw_window_handle = RawWindowHandle::Wayland(WaylandWindowHandle::new(
+            NonNull::new(probe.id().as_ptr() as *mut _).context("Surface id is a null ptr")?,
+        ));
+
+        let power_preference = match config.power_preference {
+            PowerPreference::LowPower => wgpu::PowerPreference::LowPower,
+            PowerPreference::HighPerformance => wgpu::PowerPreference::HighPerformance,
+        };
+
+        for (backends, software) in attempts(config.renderer) {
+            let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+                backends,
+                ..Default::default()
+            });
+
+            let surface = unsafe {
+                instance.create_surface_unsafe(wgpu::SurfaceTargetUnsafe::RawHandle {
+                    raw_display_handle,
+                    raw_window_handle,
+                })
+            };
+            let surface = match surface {
+                Ok(surface) => surface,
+                Err(e) => {
+                    log::warn!("No surface for {backends:?}: {e}");
+                    continue;
+                }
+            };
+
+            let adapter = match instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference,
+                    force_fallback_adapter: software,
+                    compatible_surface: Some(&surface),
+                })
+                .await
+            {
+                Ok(adapter) => adapter,
+                Err(e) => {
+                    log::warn!("No adapter for {backends:?} (software: {software}): {e}");
+                    continue;
+                }
+            };
+
+            let info = adapter.get_info();
+            let (device, queue) = match adapter.request_device(&Default::default()).await {
+                Ok(device) => device,
+                Err(e) => {
+                    log::warn!("Failed to open {}: {e}", info.name);
+                    continue;
+                }
+            };
+
+            log::info!(
+                "Rendering with {} ({}, {:?})",
+                info.name,
+                info.backend.to_str(),
+                info.device_type
+            );
+
+            return Ok(Self {
+                device,
+                queue,
+                instance,
+                adapter,
+                raw_display_handle,
+            });
+        }
+
+        Err(anyhow::anyhow!(
+            "No usable graphics adapter for renderer {:?}",
+            config.renderer
+        ))
+    }
+
+    /// Name and backend of the adapter notifications are drawn with.
+    pub fn adapter_name(&self) -> String {
+        let info = self.adapter.get_info();
+        format!("{} ({})", info.name, info.backend.to_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::attempts;
+    use crate::config::Renderer;
 
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions::default())
-            .await
-            .expect("Failed to find suitable adapter");
-
-        let (device, queue) = adapter
-            .request_device(&Default::default())
-            .await
-            .expect("Failed to request device");
-
-        Ok(Self {
-            device,
-            queue,
-            instance,
-            adapter,
-            raw_display_handle,
-        })
+    #[test]
+    fn test_auto_falls_back_to_software() {
+        let auto = attempts(Renderer::Auto);
+        assert_eq!(auto.first(), Some(&(wgpu::Backends::VULKAN, false)));
+        assert_eq!(auto.last().map(|(_, software)| *software), Some(true));
+        assert!(attempts(Renderer::Gl)
+            .iter()
+            .all(|(backends, _)| *backends == wgpu::Backends::GL));
     }
 }
@@ -1,4 +1,5 @@
 use super::math::{Mat4, Matrix};
+use std::ops::Range;
 use wgpu::{util::DeviceExt, Texture, TextureView};
 
 pub trait DataDescription {
@@ -96,6 +97,10 @@ pub struct TextureInstance {
     pub border_width: [f32; 4],
     pub scale: f32,
     pub depth: f32,
+    pub opacity: f32,
+    /// Part of the texture layer the image takes up, images smaller than the layer sit in its
+    /// top left corner.
+    pub tex_size: [f32; 2],
 }
 
 impl DataDescription for TextureInstance {
@@ -107,6 +112,8 @@ impl DataDescription for TextureInstance {
         6 => Float32x4,
         7 => Float32,
         8 => Float32,
+        9 => Float32,
+        10 => Float32x2,
     ];
     const STEP_MODE: wgpu::VertexStepMode = wgpu::VertexStepMode::Instance;
 }
@@ -140,9 +147,37 @@ impl DataDescription for Instance {
 
 pub struct InstanceBuffer<T> {
     buffer: wgpu::Buffer,
+    /// What the buffer holds, so that writes only upload what changed.
     instances: Box<[T]>,
 }
 
+/// Runs of `new` that differ from what's at the same index in `old`.
+fn changed_ranges<T: bytemuck::Pod>(old: &[T], new: &[T]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for (i, instance) in new.iter().enumerate() {
+        let unchanged = old
+            .get(i)
+            .is_some_and(|old| bytemuck::bytes_of(old) == bytemuck::bytes_of(instance));
+        if unchanged {
+            continue;
+        }
+
+        match ranges.last_mut() {
+            Some(range) if range.end == i => range.end += 1,
+            _ => ranges.push(i..i + 1),
+        }
+    }
+
+    ranges
+}
+
+impl<T> InstanceBuffer<T> {
+    /// Size of the underlying buffer in bytes.
+    pub fn capacity(&self) -> u64 {
+        self.buffer.size()
+    }
+}
+
 impl<T> Buffer for InstanceBuffer<T>
 where
     T: bytemuck::Pod,
@@ -150,6 +185,11 @@ where
     type DataType = T;
 
     fn new(device: &wgpu::Device, data: &[Self::DataType]) -> Self {
+        // Slicing an empty buffer panics, so there's always room for at least one instance
+        if data.is_empty() {
+            return Self::with_size(device, std::mem::size_of::<T>() as u64);
+        }
+
         Self {
             buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("InstanceBuffer"),
@@ -188,7 +228,10 @@ where
     }
 
     fn write(&mut self, queue: &wgpu::Queue, data: &[Self::DataType]) {
-        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(data));
+        for range in changed_ranges(&self.instances, data) {
+            let offset = (range.start * std::mem::size_of::<T>()) as wgpu::BufferAddress;
+            queue.write_buffer(&self.buffer, offset, bytemuck::cast_slice(&data[range]));
+        }
 
         self.instances = data.into();
     }
@@ -337,3 +380,20 @@ impl Projection {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::changed_ranges;
+
+    #[test]
+    fn test_changed_ranges() {
+        let old = [1u32, 2, 3, 4, 5];
+
+        assert!(changed_ranges(&old, &old).is_empty());
+        assert_eq!(changed_ranges(&old, &[1, 0, 0, 4, 0]), vec![1..3, 4..5]);
+        // Instances past the old ones are always written
+        assert_eq!(changed_ranges(&old[..2], &[1, 2, 3]), vec![2..3]);
+        assert_eq!(changed_ranges(&[], &[1, 2]), vec![0..2]);
+        assert!(changed_ranges(&old, &old[..3]).is_empty());
+    }
+}
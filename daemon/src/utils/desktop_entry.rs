@@ -0,0 +1,243 @@
+use crate::dbus::xdg::NotificationData;
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, LazyLock, Mutex},
+};
+
+/// Desktop entries read so far by id, None for ids no desktop file was found for.
+static DESKTOP_ENTRIES: LazyLock<Mutex<HashMap<Box<str>, Option<Arc<DesktopEntry>>>>> =
+    LazyLock::new(Mutex::default);
+
+/// What's used of a `.desktop` file, `name` in the language of the session if it's translated.
+#[derive(Debug, Default, PartialEq)]
+pub struct DesktopEntry {
+    pub name: Option<Box<str>>,
+    pub icon: Option<Box<str>>,
+}
+
+/// `applications` directories of the XDG data directories, in the order they're searched.
+fn application_dirs() -> Vec<PathBuf> {
+    let data_home = std::env::var("XDG_DATA_HOME")
+        .ok()
+        .filter(|data_home| !data_home.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var("HOME")
+                .ok()
+                .map(|home| PathBuf::from(home).join(".local/share"))
+        });
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|data_dirs| !data_dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+
+    data_home
+        .into_iter()
+        .chain(
+            data_dirs
+                .split(':')
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from),
+        )
+        .map(|dir| dir.join("applications"))
+        .collect()
+}
+
+/// Locales `Name[...]` keys are matched against for `locale`, such as `sr_RS.UTF-8@latin`,
+/// most specific first.
+fn locale_keys(locale: &str) -> Vec<String> {
+    let (locale, modifier) = match locale.split_once('@') {
+        Some((locale, modifier)) => (locale, Some(modifier)),
+        None => (locale, None),
+    };
+    let locale = locale.split('.').next().unwrap_or_default();
+    let (lang, country) = match locale.split_once('_') {
+        Some((lang, country)) => (lang, Some(country)),
+        None => (locale, None),
+    };
+    if lang.is_empty() || lang == "C" || lang == "POSIX" {
+        return Vec::new();
+    }
+
+    let mut keys = Vec::new();
+    if let (Some(country), Some(modifier)) = (country, modifier) {
+        keys.push(format!("{lang}_{country}@{modifier}"));
+    }
+    if let Some(country) = country {
+        keys.push(format!("{lang}_{country}"));
+    }
+    if let Some(modifier) = modifier {
+        keys.push(format!("{lang}@{modifier}"));
+    }
+    keys.push(lang.to_string());
+
+    keys
+}
+
+fn session_locale() -> String {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .find_map(|var| std::env::var(var).ok().filter(|locale| !locale.is_empty()))
+        .unwrap_or_default()
+}
+
+/// Reads `Name` and `Icon` of the `[Desktop Entry]` group, preferring the translation of
+/// `Name` that comes first in `locales`.
+fn parse(contents: &str, locales: &[String]) -> DesktopEntry {
+    let mut entry = DesktopEntry::default();
+    // How far down `locales` the name found so far is, untranslated ones come last
+    let mut name_rank = usize::MAX;
+    let mut in_entry = false;
+
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_entry || line.starts_with('#') {
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+        if value.is_empty() {
+            continue;
+        }
+
+        let rank = match key.split_once('[') {
+            None if key == "Icon" => {
+                entry.icon = Some(value.into());
+                continue;
+            }
+            None if key == "Name" => locales.len(),
+            Some(("Name", locale)) => {
+                let locale = locale.trim_end_matches(']');
+                match locales.iter().position(|key| key == locale) {
+                    Some(rank) => rank,
+                    None => continue,
+                }
+            }
+            _ => continue,
+        };
+
+        if rank < name_rank {
+            name_rank = rank;
+            entry.name = Some(value.into());
+        }
+    }
+
+    entry
+}
+
+fn read(id: &str) -> Option<DesktopEntry> {
+    // Apps tend to send their name capitalized while desktop files are lowercase
+    let mut files = vec![format!("{id}.desktop")];
+    if id.chars().any(char::is_uppercase) {
+        files.push(format!("{}.desktop", id.to_lowercase()));
+    }
+
+    let dirs = application_dirs();
+    let contents = files.iter().find_map(|file| {
+        dirs.iter()
+            .find_map(|dir| std::fs::read_to_string(dir.join(file)).ok())
+    })?;
+
+    Some(parse(&contents, &locale_keys(&session_locale())))
+}
+
+/// Desktop entry with the id `id`, the `.desktop` suffix being optional. Files are read on a
+/// blocking thread the first time an id is asked for, what was found is kept from then on.
+pub async fn lookup(id: &str) -> Option<Arc<DesktopEntry>> {
+    let id = id.trim_end_matches(".desktop");
+    if id.is_empty() || id.contains('/') {
+        return None;
+    }
+
+    if let Some(entry) = DESKTOP_ENTRIES.lock().unwrap().get(id) {
+        return entry.clone();
+    }
+
+    let id: Box<str> = id.into();
+    let entry = tokio::task::spawn_blocking({
+        let id = id.clone();
+        move || read(&id).map(Arc::new)
+    })
+    .await
+    .ok()
+    .flatten();
+    DESKTOP_ENTRIES.lock().unwrap().insert(id, entry.clone());
+
+    entry
+}
+
+/// Fills in the name of the app the notification came from as its desktop file calls it, and
+/// its icon if the notification came without one. The `desktop-entry` hint names the file,
+/// the app name does when there's no hint. Nothing changes when there's no such file.
+pub async fn enrich(data: &mut NotificationData) {
+    let id = data
+        .hints
+        .desktop_entry
+        .as_deref()
+        .unwrap_or(&*data.app_name);
+    let Some(entry) = lookup(id).await else {
+        return;
+    };
+
+    data.display_name = entry.name.clone();
+    if data.app_icon.is_none() {
+        data.app_icon = entry.icon.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{locale_keys, parse, DesktopEntry};
+
+    #[test]
+    fn test_locale_keys() {
+        assert_eq!(
+            locale_keys("sr_RS.UTF-8@latin"),
+            ["sr_RS@latin", "sr_RS", "sr@latin", "sr"]
+        );
+        assert_eq!(locale_keys("de_DE.UTF-8"), ["de_DE", "de"]);
+        assert!(locale_keys("C.UTF-8").is_empty());
+        assert!(locale_keys("").is_empty());
+    }
+
+    #[test]
+    fn test_parse() {
+        let contents = r#"
+[Desktop Entry]
+Type=Application
+Name=Telegram
+Name[de]=Telegramm
+Name[pl_PL]=Telegram PL
+Icon=org.telegram.desktop
+# Name=Commented
+
+[Desktop Action quit]
+Name=Quit Telegram
+Icon=application-exit
+"#;
+
+        assert_eq!(
+            parse(contents, &locale_keys("pl_PL.UTF-8")),
+            DesktopEntry {
+                name: Some("Telegram PL".into()),
+                icon: Some("org.telegram.desktop".into()),
+            }
+        );
+        assert_eq!(
+            parse(contents, &locale_keys("de_AT")).name.as_deref(),
+            Some("Telegramm")
+        );
+        assert_eq!(
+            parse(contents, &locale_keys("fr_FR")).name.as_deref(),
+            Some("Telegram")
+        );
+        assert_eq!(parse("Name=Orphan", &[]), DesktopEntry::default());
+    }
+}
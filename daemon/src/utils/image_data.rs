@@ -1,9 +1,31 @@
 use fast_image_resize::{self as fr, ResizeOptions};
-use image::DynamicImage;
+use image::{
+    codecs::{gif::GifDecoder, png::PngDecoder},
+    AnimationDecoder, DynamicImage, ImageDecoder, ImageFormat, ImageReader,
+};
 use serde::{Deserialize, Serialize};
-use std::str::FromStr;
+use std::{path::Path, str::FromStr, time::Duration};
 use zbus::zvariant::{Signature, Structure};
 
+/// Frames shown for less than this are shown for [`DEFAULT_FRAME_DELAY`] instead, as browsers
+/// do. Plenty of GIFs leave the delay at 0 and rely on that.
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(20);
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+
+/// Most frames decoded of an animated image, later ones are left out.
+const MAX_FRAMES: usize = 256;
+
+/// Most bytes the shrunk frames of an animated image take up together, frames past it are left
+/// out.
+const MAX_FRAMES_SIZE: usize = 32 * 1024 * 1024;
+
+/// Frame of an animated image, shown for `delay` before the next one.
+#[derive(Debug, Clone)]
+pub struct ImageFrame {
+    pub image: ImageData,
+    pub delay: Duration,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ImageData {
     width: u32,
@@ -16,26 +38,135 @@ pub struct ImageData {
 }
 
 impl ImageData {
-    pub fn to_rgba(self) -> Self {
-        if self.has_alpha {
-            self
-        } else {
-            let mut data = self.data;
-            let mut new_data = Vec::with_capacity(data.len() / self.channels as usize * 4);
-
-            data.chunks_exact_mut(self.channels as usize)
-                .for_each(|chunk| {
-                    new_data.extend_from_slice(chunk);
-                    new_data.push(0xFF);
-                });
-
-            Self {
-                has_alpha: true,
-                data: new_data,
-                channels: 4,
-                rowstride: self.width as i32 * 4,
-                ..self
+    /// Decodes an image file, applying its EXIF orientation and shrinking it to fit within
+    /// `max_size` so that large photos don't end up as full size textures.
+    pub fn open<T>(path: T, max_size: u32) -> anyhow::Result<Self>
+    where
+        T: AsRef<Path>,
+    {
+        let mut image = Self::decode(path)?;
+        if image.width() > max_size || image.height() > max_size {
+            image = image.thumbnail(max_size, max_size);
+        }
+
+        Self::try_from(image)
+    }
+
+    /// Decodes an image file like [`Self::open`], but only shrinks it down to `max_height`,
+    /// however wide it is.
+    pub fn open_max_height<T>(path: T, max_height: u32) -> anyhow::Result<Self>
+    where
+        T: AsRef<Path>,
+    {
+        let mut image = Self::decode(path)?;
+        if image.height() > max_height {
+            image = image.thumbnail(u32::MAX, max_height);
+        }
+
+        Self::try_from(image)
+    }
+
+    fn decode<T>(path: T) -> anyhow::Result<DynamicImage>
+    where
+        T: AsRef<Path>,
+    {
+        let mut decoder = ImageReader::open(path)?
+            .with_guessed_format()?
+            .into_decoder()?;
+        let orientation = decoder.orientation()?;
+
+        let mut image = DynamicImage::from_decoder(decoder)?;
+        image.apply_orientation(orientation);
+
+        Ok(image)
+    }
+
+    /// Decodes the frames of an animated GIF or PNG one at a time, each shrunk the same way as
+    /// by [`Self::open`], up to [`MAX_FRAMES`] or [`MAX_FRAMES_SIZE`]. Images that aren't
+    /// animated have no frames, [`Self::open`] reads them.
+    pub fn open_frames<T>(path: T, max_size: u32) -> anyhow::Result<Vec<ImageFrame>>
+    where
+        T: AsRef<Path>,
+    {
+        let reader = ImageReader::open(path)?.with_guessed_format()?;
+        let frames = match reader.format() {
+            Some(ImageFormat::Gif) => GifDecoder::new(reader.into_inner())?.into_frames(),
+            Some(ImageFormat::Png) => {
+                let decoder = PngDecoder::new(reader.into_inner())?;
+                if !decoder.is_apng()? {
+                    return Ok(Vec::new());
+                }
+                decoder.apng()?.into_frames()
+            }
+            _ => return Ok(Vec::new()),
+        };
+
+        let mut decoded = Vec::new();
+        let mut size = 0;
+        for frame in frames {
+            if decoded.len() == MAX_FRAMES {
+                log::debug!("Animated image has over {MAX_FRAMES} frames, leaving the rest out");
+                break;
+            }
+
+            let frame = frame?;
+            let delay = match Duration::from(frame.delay()) {
+                delay if delay < MIN_FRAME_DELAY => DEFAULT_FRAME_DELAY,
+                delay => delay,
+            };
+
+            let mut image = DynamicImage::ImageRgba8(frame.into_buffer());
+            if image.width() > max_size || image.height() > max_size {
+                image = image.thumbnail(max_size, max_size);
+            }
+            let image = Self::try_from(image)?;
+
+            size += image.data.len();
+            if size > MAX_FRAMES_SIZE {
+                log::debug!("Animated image is over {MAX_FRAMES_SIZE} bytes, leaving the rest out");
+                break;
             }
+            decoded.push(ImageFrame { image, delay });
+        }
+
+        if decoded.len() < 2 {
+            return Ok(Vec::new());
+        }
+
+        Ok(decoded)
+    }
+
+    /// Packs the pixels as tightly laid out RGBA, dropping the padding at the end of rows and
+    /// making RGB data opaque. Alpha is kept straight, textures are blended that way.
+    pub fn to_rgba(self) -> Self {
+        let channels = self.channels as usize;
+        let row_len = self.width as usize * channels;
+        if self.has_alpha && self.rowstride as usize == row_len {
+            return self;
+        }
+
+        let mut data = Vec::with_capacity(self.width as usize * self.height as usize * 4);
+        self.data
+            .chunks(self.rowstride as usize)
+            .take(self.height as usize)
+            .for_each(|row| {
+                row[..row_len.min(row.len())]
+                    .chunks_exact(channels)
+                    .for_each(|pixel| match self.has_alpha {
+                        true => data.extend_from_slice(pixel),
+                        false => {
+                            data.extend_from_slice(&pixel[..3]);
+                            data.push(0xFF);
+                        }
+                    });
+            });
+
+        Self {
+            has_alpha: true,
+            data,
+            channels: 4,
+            rowstride: self.width as i32 * 4,
+            ..self
         }
     }
 
@@ -56,6 +187,7 @@ impl ImageData {
         Self {
             width: dst.width(),
             height: dst.height(),
+            rowstride: dst.width() as i32 * 4,
             data: dst.into_vec(),
             ..self
         }
@@ -152,27 +284,28 @@ impl<'a> TryFrom<Structure<'a>> for ImageData {
         }
 
         if bits_per_sample != 8 {
-            return Err(zbus::Error::Failure(
-                "Invalid ImageData: bits_per_sample is not 8".to_string(),
-            ));
+            return Err(zbus::Error::Failure(format!(
+                "Invalid ImageData: {bits_per_sample} bits per sample aren't supported, only 8 are"
+            )));
         }
 
-        if has_alpha && channels != 4 {
-            return Err(zbus::Error::Failure(
-                "Invalid ImageData: has_alpha is true but channels is not 4".to_string(),
-            ));
+        if channels != if has_alpha { 4 } else { 3 } {
+            return Err(zbus::Error::Failure(format!(
+                "Invalid ImageData: {channels} channels with has_alpha {has_alpha}"
+            )));
         }
 
-        if (width * height * channels) as usize != data.len() {
+        let row_len = width as usize * channels as usize;
+        if i64::from(rowstride) < row_len as i64 {
             return Err(zbus::Error::Failure(
-                "Invalid ImageData: data length does not match width * height * channels"
-                    .to_string(),
+                "Invalid ImageData: rowstride is shorter than width * channels".to_string(),
             ));
         }
 
-        if data.len() != (rowstride * height) as usize {
+        // The last row doesn't have to be padded up to the rowstride
+        if data.len() < rowstride as usize * (height as usize - 1) + row_len {
             return Err(zbus::Error::Failure(
-                "Invalid ImageData: data length does not match rowstride * height".to_string(),
+                "Invalid ImageData: data is shorter than rowstride * height".to_string(),
             ));
         }
 
@@ -233,6 +366,165 @@ mod tests {
         assert_eq!(converted.data[3], 128);
     }
 
+    #[test]
+    fn open_downscales_large_images() {
+        let path = std::env::temp_dir().join("moxnotify-open-downscales.png");
+        RgbaImage::new(64, 32).save(&path).unwrap();
+
+        let image_data = ImageData::open(&path, 16).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(image_data.size(), (16, 8));
+    }
+
+    #[test]
+    fn open_max_height_keeps_wide_images_wide() {
+        let path = std::env::temp_dir().join("moxnotify-open-max-height.png");
+        RgbaImage::new(256, 32).save(&path).unwrap();
+
+        let image_data = ImageData::open_max_height(&path, 16).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(image_data.size(), (128, 16));
+    }
+
+    #[test]
+    fn open_frames_of_animated_gif() {
+        use image::{codecs::gif::GifEncoder, Delay, Frame};
+
+        let path = std::env::temp_dir().join("moxnotify-open-frames.gif");
+        let frames = [0, 50].map(|delay| {
+            Frame::from_parts(
+                RgbaImage::new(64, 32),
+                0,
+                0,
+                Delay::from_saturating_duration(Duration::from_millis(delay)),
+            )
+        });
+        GifEncoder::new(std::fs::File::create(&path).unwrap())
+            .encode_frames(frames)
+            .unwrap();
+
+        let frames = ImageData::open_frames(&path, 16).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].image.size(), (16, 8));
+        assert_eq!(frames[0].delay, DEFAULT_FRAME_DELAY);
+        assert_eq!(frames[1].delay, Duration::from_millis(50));
+    }
+
+    #[test]
+    fn open_frames_stops_at_max_frames() {
+        use image::{codecs::gif::GifEncoder, Delay, Frame};
+
+        let path = std::env::temp_dir().join("moxnotify-open-max-frames.gif");
+        let frames = (0..MAX_FRAMES + 10).map(|_| {
+            Frame::from_parts(
+                RgbaImage::new(2, 2),
+                0,
+                0,
+                Delay::from_saturating_duration(Duration::from_millis(50)),
+            )
+        });
+        GifEncoder::new(std::fs::File::create(&path).unwrap())
+            .encode_frames(frames)
+            .unwrap();
+
+        let frames = ImageData::open_frames(&path, 16).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(frames.len(), MAX_FRAMES);
+    }
+
+    #[test]
+    fn open_frames_of_still_image() {
+        let path = std::env::temp_dir().join("moxnotify-open-frames.png");
+        RgbaImage::new(4, 4).save(&path).unwrap();
+
+        let frames = ImageData::open_frames(&path, 16).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(frames.is_empty());
+    }
+
+    fn structure(
+        width: i32,
+        height: i32,
+        rowstride: i32,
+        has_alpha: bool,
+        channels: i32,
+        data: Vec<u8>,
+    ) -> Structure<'static> {
+        Structure::from((width, height, rowstride, has_alpha, 8, channels, data))
+    }
+
+    #[test]
+    fn drops_rowstride_padding() {
+        // 3x2 RGBA with 4 bytes of padding after each row, the last one left unpadded
+        let mut data = Vec::new();
+        for y in 0..2u8 {
+            for x in 0..3u8 {
+                data.extend_from_slice(&[x, y, 0, 255]);
+            }
+            if y == 0 {
+                data.extend_from_slice(&[9; 4]);
+            }
+        }
+
+        let image_data = ImageData::try_from(structure(3, 2, 16, true, 4, data))
+            .unwrap()
+            .to_rgba();
+
+        assert_eq!(image_data.rowstride, 12);
+        assert_eq!(
+            image_data.data,
+            [
+                0, 0, 0, 255, 1, 0, 0, 255, 2, 0, 0, 255, //
+                0, 1, 0, 255, 1, 1, 0, 255, 2, 1, 0, 255,
+            ]
+        );
+    }
+
+    #[test]
+    fn expands_padded_rgb() {
+        // Odd width, rows of 3 * 3 bytes padded to 12
+        let data = vec![
+            10, 20, 30, 40, 50, 60, 70, 80, 90, 0, 0, 0, //
+            11, 21, 31, 41, 51, 61, 71, 81, 91, 0, 0, 0,
+        ];
+
+        let image_data = ImageData::try_from(structure(3, 2, 12, false, 3, data))
+            .unwrap()
+            .to_rgba();
+
+        assert!(image_data.has_alpha);
+        assert_eq!(image_data.channels, 4);
+        assert_eq!(
+            image_data.data,
+            [
+                10, 20, 30, 255, 40, 50, 60, 255, 70, 80, 90, 255, //
+                11, 21, 31, 255, 41, 51, 61, 255, 71, 81, 91, 255,
+            ]
+        );
+        assert_eq!(image_data.resize(2).rowstride, 8);
+    }
+
+    #[test]
+    fn rejects_unsupported_image_data() {
+        let sixteen_bit = Structure::from((1, 1, 8, true, 16, 4, vec![0u8; 8]));
+        assert!(ImageData::try_from(sixteen_bit).is_err());
+
+        let alpha_with_three_channels = structure(1, 1, 3, true, 3, vec![0; 3]);
+        assert!(ImageData::try_from(alpha_with_three_channels).is_err());
+
+        let short_rowstride = structure(2, 1, 4, true, 4, vec![0; 8]);
+        assert!(ImageData::try_from(short_rowstride).is_err());
+
+        let truncated = structure(2, 2, 8, true, 4, vec![0; 12]);
+        assert!(ImageData::try_from(truncated).is_err());
+    }
+
     #[test]
     fn converts_from_dynamic_image() {
         let img = RgbaImage::new(32, 32);
@@ -1,3 +1,4 @@
 pub mod buffers;
+pub mod desktop_entry;
 pub mod image_data;
 pub mod math;
@@ -40,15 +40,12 @@
           with pkgs;
           mkShell.override { stdenv = pkgs.clang12Stdenv; } rec {
             buildInputs = [
-              (rust-bin.selectLatestNightlyWith (
-                toolchain:
-                toolchain.default.override {
-                  extensions = [
-                    "rust-src"
-                    "rustfmt"
-                  ];
-                }
-              ))
+              (rust-bin.stable.latest.default.override {
+                extensions = [
+                  "rust-src"
+                  "rustfmt"
+                ];
+              })
               rust-analyzer-unwrapped
               nixd
               pkg-config
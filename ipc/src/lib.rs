@@ -0,0 +1,320 @@
+//! Typed client for the D-Bus API of the moxnotify daemon.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), moxnotify_ipc::Error> {
+//! let client = moxnotify_ipc::MoxnotifyClient::connect().await?;
+//! for notification in client.list().await? {
+//!     println!("{}", notification.format("{id} {summary}"));
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod proxy;
+mod types;
+
+use futures_lite::{Stream, StreamExt};
+use std::fmt;
+
+pub use proxy::{NotificationsProxy, NotifyProxy};
+pub use types::{Action, History, InhibitState, Notification, Status};
+
+#[derive(Debug)]
+pub enum Error {
+    Dbus(zbus::Error),
+    /// Another notification server owns the bus name, it's named along with its vendor.
+    UnknownServer(String, String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Dbus(e) => write!(f, "{e}"),
+            Error::UnknownServer(name, vendor) => {
+                write!(f, "Unknown notification server {name} by {vendor}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Dbus(e) => Some(e),
+            Error::UnknownServer(..) => None,
+        }
+    }
+}
+
+impl From<zbus::Error> for Error {
+    fn from(e: zbus::Error) -> Self {
+        Error::Dbus(e)
+    }
+}
+
+impl From<zbus::fdo::Error> for Error {
+    fn from(e: zbus::fdo::Error) -> Self {
+        Error::Dbus(e.into())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Changes the daemon announces without being asked.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum EmitEvent {
+    MuteStateChanged(bool),
+    HistoryStateChanged(History),
+    InhibitChanged(bool),
+    NotificationCountChanged(u32),
+    NotificationReceived(Notification),
+    /// `reason` is one of the reasons of the notification specification, 1 for expired ones,
+    /// 2 for ones dismissed by the user, 3 for ones closed by `CloseNotification` and 4 otherwise.
+    NotificationClosed {
+        id: u32,
+        reason: u32,
+    },
+    ActionInvoked {
+        id: u32,
+        key: String,
+    },
+}
+
+pub struct MoxnotifyClient {
+    notifications: NotificationsProxy<'static>,
+    notify: NotifyProxy<'static>,
+}
+
+impl MoxnotifyClient {
+    /// Connects to the daemon on the session bus, fails when the notification server running
+    /// isn't moxnotify.
+    pub async fn connect() -> Result<Self> {
+        Self::with_connection(&zbus::Connection::session().await?).await
+    }
+
+    pub async fn with_connection(conn: &zbus::Connection) -> Result<Self> {
+        let notifications = NotificationsProxy::new(conn).await?;
+        let (name, vendor, ..) = notifications.get_server_information().await?;
+        if *name != *"moxnotify" && *vendor != *"mox" {
+            return Err(Error::UnknownServer(name.into(), vendor.into()));
+        }
+
+        Ok(Self {
+            notifications,
+            notify: NotifyProxy::new(conn).await?,
+        })
+    }
+
+    /// The proxy the client wraps, for whatever it doesn't cover.
+    pub fn proxy(&self) -> &NotifyProxy<'static> {
+        &self.notify
+    }
+
+    pub async fn focus(&self) -> Result<()> {
+        Ok(self.notify.focus().await?)
+    }
+
+    /// Stops the daemon after it closed its notifications.
+    pub async fn quit(&self) -> Result<()> {
+        Ok(self.notify.quit().await?)
+    }
+
+    /// Has the daemon write what it shows to `path`, which has to be absolute, as a PNG.
+    pub async fn screenshot(&self, path: &str) -> Result<()> {
+        Ok(self.notify.screenshot(path).await?)
+    }
+
+    pub async fn list(&self) -> Result<Vec<Notification>> {
+        Ok(self.notify.list().await?)
+    }
+
+    /// Lists notifications with the summary and body of apps whose content the history doesn't
+    /// keep replaced by a placeholder.
+    pub async fn list_redacted(&self) -> Result<Vec<Notification>> {
+        Ok(self.notify.list_redacted().await?)
+    }
+
+    pub async fn count(&self) -> Result<u32> {
+        Ok(self.notify.notification_count().await?)
+    }
+
+    /// Number of notifications held back while inhibited.
+    pub async fn waiting(&self) -> Result<u32> {
+        Ok(self.notify.waiting().await?)
+    }
+
+    pub async fn status(&self) -> Result<Status> {
+        Ok(self.notify.status().await?)
+    }
+
+    /// Moves notifications to the output called `name`, or onto every output with `all`. Fails
+    /// listing the outputs there are if none is called that.
+    pub async fn set_output(&self, name: &str) -> Result<()> {
+        Ok(self.notify.set_output(name).await?)
+    }
+
+    /// Anchors notifications to `anchor`, such as `bottom-right`.
+    pub async fn set_anchor(&self, anchor: &str) -> Result<()> {
+        Ok(self.notify.set_anchor(anchor).await?)
+    }
+
+    /// Shows only the summary of notifications, or everything again.
+    pub async fn set_compact(&self, compact: bool) -> Result<()> {
+        Ok(self.notify.set_compact(compact).await?)
+    }
+
+    pub async fn compact(&self) -> Result<bool> {
+        Ok(self.notify.compact().await?)
+    }
+
+    pub async fn dismiss(&self, id: u32) -> Result<()> {
+        Ok(self.notify.dismiss(false, id).await?)
+    }
+
+    pub async fn dismiss_all(&self) -> Result<()> {
+        Ok(self.notify.dismiss(true, 0).await?)
+    }
+
+    /// Dismisses every notification of `app`, returns how many there were.
+    pub async fn dismiss_app(&self, app: &str) -> Result<u32> {
+        Ok(self.notify.dismiss_app(app).await?)
+    }
+
+    pub async fn invoke_action(&self, id: u32, key: &str) -> Result<()> {
+        Ok(self.notify.invoke_action(id, key).await?)
+    }
+
+    pub async fn snooze(&self, id: u32, minutes: u32) -> Result<()> {
+        Ok(self.notify.snooze(id, minutes).await?)
+    }
+
+    /// Pins notification `id` so that it doesn't expire, or unpins it if it's pinned.
+    pub async fn pin(&self, id: u32) -> Result<()> {
+        Ok(self.notify.pin(id).await?)
+    }
+
+    /// Opens the first link in the body of notification `id`, returns false if it has none.
+    pub async fn open_link(&self, id: u32) -> Result<bool> {
+        Ok(self.notify.open_link(id).await?)
+    }
+
+    pub async fn mute(&self) -> Result<()> {
+        Ok(self.notify.mute().await?)
+    }
+
+    pub async fn unmute(&self) -> Result<()> {
+        Ok(self.notify.unmute().await?)
+    }
+
+    pub async fn muted(&self) -> Result<bool> {
+        Ok(self.notify.muted().await?)
+    }
+
+    pub async fn show_history(&self) -> Result<()> {
+        Ok(self.notify.show_history().await?)
+    }
+
+    /// Shows the history limited to entries whose summary or body contain `query`.
+    pub async fn search_history(&self, query: &str) -> Result<()> {
+        Ok(self.notify.search_history(query).await?)
+    }
+
+    pub async fn hide_history(&self) -> Result<()> {
+        Ok(self.notify.hide_history().await?)
+    }
+
+    pub async fn history(&self) -> Result<History> {
+        Ok(self.notify.history().await?)
+    }
+
+    pub async fn inhibit(&self) -> Result<()> {
+        Ok(self.notify.inhibit().await?)
+    }
+
+    pub async fn uninhibit(&self) -> Result<()> {
+        Ok(self.notify.uninhibit().await?)
+    }
+
+    pub async fn inhibit_state(&self) -> Result<InhibitState> {
+        Ok(self.notify.inhibit_state().await?)
+    }
+
+    /// Subscribes to the changes the daemon announces, the stream ends with the connection.
+    pub async fn subscribe_events(&self) -> Result<impl Stream<Item = EmitEvent> + Unpin> {
+        let muted = self
+            .notify
+            .receive_mute_state_changed()
+            .await?
+            .filter_map(|signal| {
+                let args = signal.args().ok()?;
+                Some(EmitEvent::MuteStateChanged(*args.muted()))
+            });
+
+        let history = self
+            .notify
+            .receive_history_state_changed()
+            .await?
+            .filter_map(|signal| {
+                let args = signal.args().ok()?;
+                Some(EmitEvent::HistoryStateChanged(*args.history()))
+            });
+
+        let inhibited = self
+            .notify
+            .receive_inhibit_changed()
+            .await?
+            .filter_map(|signal| {
+                let args = signal.args().ok()?;
+                Some(EmitEvent::InhibitChanged(*args.inhibited()))
+            });
+
+        let count = self
+            .notify
+            .receive_notification_count_changed()
+            .await
+            .then(|change| async move { change.get().await.ok() })
+            .filter_map(|count| count.map(EmitEvent::NotificationCountChanged));
+
+        let received = self
+            .notify
+            .receive_notification_received()
+            .await?
+            .filter_map(|signal| {
+                let args = signal.args().ok()?;
+                Some(EmitEvent::NotificationReceived(args.notification().clone()))
+            });
+
+        let closed = self
+            .notifications
+            .receive_notification_closed()
+            .await?
+            .filter_map(|signal| {
+                let args = signal.args().ok()?;
+                Some(EmitEvent::NotificationClosed {
+                    id: *args.id(),
+                    reason: *args.reason(),
+                })
+            });
+
+        let action = self
+            .notifications
+            .receive_action_invoked()
+            .await?
+            .filter_map(|signal| {
+                let args = signal.args().ok()?;
+                Some(EmitEvent::ActionInvoked {
+                    id: *args.id(),
+                    key: args.action_key().to_string(),
+                })
+            });
+
+        Ok(muted
+            .or(history)
+            .or(inhibited)
+            .or(count)
+            .or(received)
+            .or(closed)
+            .or(action)
+            .boxed())
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{History, InhibitState, Notification, Status};
+use std::collections::HashMap;
+use zbus::zvariant::Value;
+
+#[zbus::proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+pub trait Notifications {
+    #[allow(clippy::type_complexity)]
+    async fn get_server_information(
+        &self,
+    ) -> zbus::fdo::Result<(Box<str>, Box<str>, Box<str>, Box<str>)>;
+
+    #[allow(clippy::too_many_arguments)]
+    async fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::fdo::Result<u32>;
+
+    #[zbus(signal)]
+    fn action_invoked(&self, id: u32, action_key: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn notification_closed(&self, id: u32, reason: u32) -> zbus::Result<()>;
+}
+
+#[zbus::proxy(
+    interface = "pl.mox.Notify",
+    default_service = "pl.mox.Notify",
+    default_path = "/pl/mox/Notify"
+)]
+pub trait Notify {
+    async fn focus(&self) -> zbus::Result<()>;
+
+    async fn quit(&self) -> zbus::Result<()>;
+
+    async fn screenshot(&self, path: &str) -> zbus::Result<()>;
+
+    async fn list(&self) -> zbus::Result<Vec<Notification>>;
+
+    async fn list_redacted(&self) -> zbus::Result<Vec<Notification>>;
+
+    async fn set_output(&self, name: &str) -> zbus::Result<()>;
+
+    async fn set_anchor(&self, anchor: &str) -> zbus::Result<()>;
+
+    async fn set_compact(&self, compact: bool) -> zbus::Result<()>;
+
+    async fn compact(&self) -> zbus::Result<bool>;
+
+    async fn dismiss(&self, all: bool, id: u32) -> zbus::Result<()>;
+
+    async fn dismiss_app(&self, app: &str) -> zbus::Result<u32>;
+
+    async fn invoke_action(&self, id: u32, key: &str) -> zbus::Result<()>;
+
+    async fn snooze(&self, id: u32, minutes: u32) -> zbus::Result<()>;
+
+    async fn pin(&self, id: u32) -> zbus::Result<()>;
+
+    async fn open_link(&self, id: u32) -> zbus::Result<bool>;
+
+    async fn mute(&self) -> zbus::Result<()>;
+
+    async fn unmute(&self) -> zbus::Result<()>;
+
+    async fn muted(&self) -> zbus::Result<bool>;
+
+    #[zbus(signal)]
+    fn mute_state_changed(&self, muted: bool) -> zbus::Result<()>;
+
+    async fn show_history(&self) -> zbus::Result<()>;
+
+    async fn search_history(&self, query: &str) -> zbus::Result<()>;
+
+    async fn hide_history(&self) -> zbus::Result<()>;
+
+    async fn history(&self) -> zbus::Result<History>;
+
+    #[zbus(signal)]
+    fn history_state_changed(&self, history: History) -> zbus::Result<()>;
+
+    async fn inhibit(&self) -> zbus::Result<()>;
+
+    async fn uninhibit(&self) -> zbus::Result<()>;
+
+    async fn inhibited(&self) -> zbus::Result<bool>;
+
+    async fn inhibit_state(&self) -> zbus::Result<InhibitState>;
+
+    #[zbus(signal)]
+    fn inhibit_changed(&self, inhibited: bool) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn notification_received(&self, notification: Notification) -> zbus::Result<()>;
+
+    async fn waiting(&self) -> zbus::Result<u32>;
+
+    async fn status(&self) -> zbus::Result<Status>;
+
+    #[zbus(property)]
+    fn notification_count(&self) -> zbus::Result<u32>;
+}
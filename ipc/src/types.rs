@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+use zbus::zvariant::Type;
+
+/// Whether the daemon shows the notification history instead of live notifications.
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy, Type, Serialize, Deserialize)]
+pub enum History {
+    #[default]
+    Hidden,
+    Shown,
+}
+
+impl History {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            History::Hidden => "hidden",
+            History::Shown => "shown",
+        }
+    }
+}
+
+/// Reasons notifications are held back, a manual inhibit and ones kept up by a screencast or the
+/// do not disturb schedule are tracked apart.
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy, Type, Serialize, Deserialize)]
+pub struct InhibitState {
+    pub manual: bool,
+    pub screencast: bool,
+    pub schedule: bool,
+}
+
+impl InhibitState {
+    pub fn inhibited(&self) -> bool {
+        self.manual || self.screencast || self.schedule
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Type, Serialize, Deserialize)]
+pub struct Action {
+    pub key: String,
+    pub label: String,
+}
+
+/// Notification as returned by `List`.
+#[derive(Debug, PartialEq, Eq, Clone, Type, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: u32,
+    pub app_name: String,
+    pub summary: String,
+    pub body: String,
+    /// One of `low`, `normal` or `critical`.
+    pub urgency: String,
+    /// Seconds since the Unix epoch at which the notification arrived.
+    pub timestamp: u64,
+    pub actions: Vec<Action>,
+    pub has_icon: bool,
+}
+
+impl Notification {
+    /// Fills `{field}` placeholders in `template` with the fields of the notification, unknown
+    /// ones are kept as they are.
+    pub fn format(&self, template: &str) -> String {
+        let mut out = String::with_capacity(template.len());
+        let mut rest = template;
+
+        while let Some(start) = rest.find('{') {
+            out.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            let Some(end) = rest.find('}') else {
+                break;
+            };
+
+            let field = &rest[1..end];
+            match field {
+                "id" => out.push_str(&self.id.to_string()),
+                "app_name" => out.push_str(&self.app_name),
+                "summary" => out.push_str(&self.summary),
+                "body" => out.push_str(&self.body),
+                "urgency" => out.push_str(&self.urgency),
+                "timestamp" => out.push_str(&self.timestamp.to_string()),
+                "actions" => out.push_str(
+                    &self
+                        .actions
+                        .iter()
+                        .map(|action| action.key.as_str())
+                        .collect::<Vec<_>>()
+                        .join(","),
+                ),
+                "has_icon" => out.push_str(&self.has_icon.to_string()),
+                _ => out.push_str(&rest[..=end]),
+            }
+            rest = &rest[end + 1..];
+        }
+
+        out.push_str(rest);
+        out
+    }
+}
+
+/// Everything status bars show, gathered at once so the fields agree with each other.
+#[derive(Debug, PartialEq, Eq, Clone, Type, Serialize, Deserialize)]
+pub struct Status {
+    pub muted: bool,
+    pub inhibited: InhibitState,
+    pub history: History,
+    pub waiting: u32,
+    /// Same as the `NotificationCount` property.
+    pub active_count: u32,
+    /// Graphics adapter notifications are drawn with, and its backend.
+    pub renderer: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, History, InhibitState, Notification, Status};
+    use zbus::zvariant::Type;
+
+    #[test]
+    fn test_format() {
+        let notification = Notification {
+            id: 3,
+            app_name: "mail".into(),
+            summary: "New mail".into(),
+            body: "Hello".into(),
+            urgency: "critical".into(),
+            timestamp: 1700000000,
+            actions: vec![
+                Action {
+                    key: "default".into(),
+                    label: "Open".into(),
+                },
+                Action {
+                    key: "reply".into(),
+                    label: "Reply".into(),
+                },
+            ],
+            has_icon: false,
+        };
+
+        assert_eq!(notification.format("{id}\t{app_name}"), "3\tmail");
+        assert_eq!(
+            notification.format("[{urgency}] {summary}: {actions} {unknown} {"),
+            "[critical] New mail: default,reply {unknown} {"
+        );
+        assert_eq!(notification.format("{timestamp}"), "1700000000");
+    }
+
+    /// The signatures are what the daemon puts on the bus, changing them breaks every client.
+    #[test]
+    fn test_wire_schema() {
+        assert_eq!(History::SIGNATURE.to_string(), "u");
+        assert_eq!(InhibitState::SIGNATURE.to_string(), "(bb)");
+        assert_eq!(Notification::SIGNATURE.to_string(), "(ussssta(ss)b)");
+        assert_eq!(Status::SIGNATURE.to_string(), "(b(bb)uuus)");
+    }
+}
@@ -30,6 +30,7 @@ rustPlatform.buildRustPackage rec {
       lib.any (p: lib.hasPrefix p relPath) [
         "daemon"
         "ctl"
+        "ipc"
         "contrib"
         "pl.mox.notify.service.in"
         "Cargo.toml"
@@ -71,6 +72,8 @@ rustPlatform.buildRustPackage rec {
     substitute $src/pl.mox.notify.service.in $out/share/dbus-1/services/pl.mox.notify.service --replace-fail '@bindir@' "$out/bin"
     chmod 0644 $out/share/dbus-1/services/pl.mox.notify.service
 
+    install -Dm644 $src/contrib/xdg-desktop-portal/moxnotify.portal $out/share/xdg-desktop-portal/portals/moxnotify.portal
+
     patchelf --set-rpath "${lib.makeLibraryPath buildInputs}" $out/bin/moxnotify
   '';
 